@@ -1,6 +1,5 @@
 
 use polymarket_arb_hft::*;
-use tokio;
 
 #[tokio::main]
 async fn main() {
@@ -14,16 +13,16 @@ async fn main() {
 
     println!("🔑 Credenziali configurate:");
     println!("   API Key: {}...{}", 
-        api_key.as_ref().unwrap()[..8].to_string(),
-        api_key.as_ref().unwrap()[api_key.as_ref().unwrap().len()-4..].to_string()
+        &api_key.as_ref().unwrap()[..8],
+        &api_key.as_ref().unwrap()[api_key.as_ref().unwrap().len()-4..]
     );
     println!("   Secret: {}...{}", 
-        secret.as_ref().unwrap()[..8].to_string(),
-        secret.as_ref().unwrap()[secret.as_ref().unwrap().len()-4..].to_string()
+        &secret.as_ref().unwrap()[..8],
+        &secret.as_ref().unwrap()[secret.as_ref().unwrap().len()-4..]
     );
     println!("   Passphrase: {}...{}", 
-        passphrase.as_ref().unwrap()[..8].to_string(),
-        passphrase.as_ref().unwrap()[passphrase.as_ref().unwrap().len()-4..].to_string()
+        &passphrase.as_ref().unwrap()[..8],
+        &passphrase.as_ref().unwrap()[passphrase.as_ref().unwrap().len()-4..]
     );
 
     // Configurazione bot con dati reali
@@ -42,17 +41,49 @@ async fn main() {
         polymarket_api_key: api_key,
         polymarket_secret: secret,
         polymarket_passphrase: passphrase,
+        polymarket_private_key: None,
+        storage: StorageConfig::default(),
+        step_history: Default::default(),
+        news_feed: None,
+        market_filter: MarketFilter::default(),
+        stat_arb: None,
+        live_reconciliation: None,
+        accounts: Vec::new(),
+        trading_costs: Default::default(),
+        refresh_schedule: Default::default(),
+        leg_sizing: Default::default(),
+        event_export: None,
+        opportunity_webhook: None,
+        cross_venue: None,
+        impact: Default::default(),
+        opportunity_dedup: Default::default(),
+        adaptive_threshold: None,
+        pair_stat_arb: None,
+        onboarding: None,
+        faults: None,
+        relations: Vec::new(),
+        triangles: Vec::new(),
+        execution_queue: None,
+        opportunity_history: None,
+        order_journal: None,
+        graph_limits: None,
+        adverse_selection: None,
+        sizing_tiers: None,
+        maker_arb: None,
+        strategy_risk: Vec::new(),
     };
 
     println!("\n📡 Inizializzando bot con configurazione reale...");
-    let mut bot = HftArbitrageBot::new(config);
+    let bot = HftArbitrageBot::new(config);
 
     // Verifica che il client API sia stato inizializzato con le credenziali
     if let Some(ref api_client) = bot.polymarket_api {
+        let api_config = api_client.config();
         println!("✅ Client API inizializzato");
         println!("📡 Configurazione API:");
-        println!("   Gamma URL: https://gamma-api.polymarket.com");
-        println!("   WebSocket: wss://ws-subscriptions-clob.polymarket.com");
+        println!("   Gamma URL: {}", api_config.gamma_api_url);
+        println!("   WebSocket: {}", api_config.websocket_url);
+        println!("   CLOB L2 auth: {}", api_client.clob().is_authenticated());
     } else {
         println!("⚠️  Client API non inizializzato (use_real_data = false)");
     }
@@ -1,6 +1,5 @@
 // Test autenticazione con credenziali API reali
 use polymarket_arb_hft::{PolymarketApiClient, PolymarketApiConfig, MarketData};
-use tokio;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -13,10 +12,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         clob_api_url: "https://clob.polymarket.com".to_string(),
         websocket_url: "wss://ws-subscriptions-clob.polymarket.com".to_string(),
         api_key: Some("019c2d5e-6b63-70d5-a637-b320e266fee5".to_string()),
+        wallet_address: None,
+        ..PolymarketApiConfig::default()
     };
 
     println!("✅ Configurazione completata con credenziali API");
-    println!("   API Key: {}", "019c2d5e...fee5");
+    println!("   API Key: 019c2d5e...fee5");
 
     // Crea il client API con tutti i 4 parametri richiesti
     let api_client = PolymarketApiClient::new(
@@ -24,7 +25,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         Some("019c2d5e-6b63-70d5-a637-b320e266fee5".to_string()),
         Some("nLSuNgtPSuuGkG8nhqdgdefrUXwi8I7vRxKEaNjWePo=".to_string()),
         Some("8f03eb97b93c26a9006a1bb4748ac7b6376c712888a72bb13cfa711d110b8d45".to_string())
-    );
+    )?;
     println!("✅ Client API creato");
 
     // Test autenticazione recuperando mercati
@@ -32,8 +33,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 🔄 Test autenticazione recupero mercati...");
     let markets: Vec<MarketData> = api_client.get_markets().await?;
 
+    println!("✅ Recuperati {} mercati", markets.len());
+
+    // Test autenticazione L2 su endpoint privato CLOB
+    println!("
+🔄 Test autenticazione L2 CLOB...");
+    let api_keys = api_client.clob().get_api_keys().await?;
     println!("✅ Autenticazione RIUSCITA!");
-    println!("   Recuperati {} mercati", markets.len());
+    println!("   API keys: {}", api_keys);
 
     if !markets.is_empty() {
         println!("
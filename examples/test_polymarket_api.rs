@@ -4,13 +4,11 @@
 //! with the HFT arbitrage bot.
 
 use polymarket_arb_hft::*;
-use tokio;
-use std::time::Duration;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("🚀 Testing Polymarket API Integration for HFT Bot");
-    println!("=".repeat(50));
+    println!("{}", "=".repeat(50));
 
     // Test 1: Initialize API Client
     println!("
@@ -19,7 +17,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("   Gamma API: {}", api_config.gamma_api_url);
     println!("   WebSocket: {}", api_config.websocket_url);
 
-    let api_client = PolymarketApiClient::new(api_config);
+    let api_client = PolymarketApiClient::new(api_config, None, None, None)?;
 
     // Test 2: Connect to API
     println!("
@@ -47,7 +45,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 println!("   YES Price: {:.4} | NO Price: {:.4}", 
                     market.yes_price, market.no_price);
                 println!("   Liquidity: ${:.2} | Volume: ${:.2}", 
-                    market.liquidity, market.volume);
+                    market.yes_liquidity + market.no_liquidity, market.volume_24h);
             }
         }
         Err(e) => {
@@ -59,10 +57,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Test 4: Initialize Bot with Real Data
     println!("
 🤖 Test 4: Initializing HFT Bot with Real Data");
-    let mut bot_config = BotConfig::default();
-    bot_config.use_real_data = true; // Enable real data
-    bot_config.initial_capital = 1000.0;
-    bot_config.min_profit_threshold = 0.005; // 0.5% threshold (optimized)
+    let bot_config = BotConfig {
+        use_real_data: true, // Enable real data
+        initial_capital: 1000.0,
+        min_profit_threshold: 0.005, // 0.5% threshold (optimized)
+        ..BotConfig::default()
+    };
 
     println!("   Config: Capital=${:.2}, MinProfit={:.2}%", 
         bot_config.initial_capital, bot_config.min_profit_threshold * 100.0);
@@ -71,8 +71,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let mut bot = HftArbitrageBot::new(bot_config);
 
     // Initialize API connections
-    if let Err(e) = bot.initialize_api().await {
-        println!("   ⚠️  API initialization warning: {}", e);
+    bot.attach_live_feed().await;
+    if let Some(api) = &bot.polymarket_api {
+        if let Err(e) = api.initialize().await {
+            println!("   ⚠️  API initialization warning: {}", e);
+        }
     }
 
     // Test 5: Run Single Step with Real Data
@@ -86,14 +89,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             println!("   Profit: ${:.4}", result.profit);
             println!("   Current Capital: ${:.2}", result.capital);
             println!("   Win Rate: {:.2}%", result.win_rate * 100.0);
+            println!("   Avg Edge: {:.2}%", result.avg_edge * 100.0);
+            println!("   Expectancy: ${:.4}", result.expectancy);
         }
         Err(e) => {
             println!("   ❌ Step execution failed: {}", e);
         }
     }
 
-    println!("
-" + "=".repeat(50));
+    println!("\n{}", "=".repeat(50));
     println!("✅ Polymarket API Integration Test Completed!");
 
     Ok(())
@@ -0,0 +1,71 @@
+/*
+ * C ABI of the polymarket_arb_hft detection engine.
+ *
+ * Build the shared library with `cargo build --release --no-default-features --features ffi`
+ * and link against libpolymarket_arb_hft. All buffers are owned by the caller.
+ */
+
+#ifndef POLYMARKET_ARB_H
+#define POLYMARKET_ARB_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define PM_OK 0
+#define PM_NULL_POINTER (-1)
+#define PM_BUFFER_TOO_SMALL (-2)   /* out_len holds the required length */
+#define PM_INVALID_ARGUMENT (-3)
+
+/* Top-of-book quote of one binary market */
+typedef struct PmQuote {
+    double yes_price;
+    double no_price;
+    double yes_liquidity;
+    double no_liquidity;
+    double volume_24h;
+} PmQuote;
+
+/* Trading costs deducted from the gross margin */
+typedef struct PmCosts {
+    double taker_fee_rate;
+    double gas_per_trade;
+    double relayer_per_order;
+    double reference_size;
+} PmCosts;
+
+/* Opportunity found on quotes[quote_index] */
+typedef struct PmOpportunity {
+    size_t quote_index;
+    double profit;        /* net margin per YES+NO pair */
+    double roi_pct;
+    double confidence;
+    double sum_price;
+    double liquidity;
+} PmOpportunity;
+
+/* Risk metrics of a PnL series */
+typedef struct PmRiskMetrics {
+    double var_95;
+    double sharpe_ratio;
+    double current_drawdown;  /* fraction below the peak */
+    double max_drawdown_pct;
+    double final_capital;
+} PmRiskMetrics;
+
+/* YES/NO arbitrage on `len` quotes; `costs` may be NULL for the default costs. */
+int32_t pm_detect_opportunities(const PmQuote *quotes, size_t len, const PmCosts *costs,
+                                double min_profit, double min_liquidity,
+                                PmOpportunity *out, size_t out_capacity, size_t *out_len);
+
+/* VaR, Sharpe and drawdown of `len` trade PnLs starting from `initial_capital`. */
+int32_t pm_risk_metrics(const double *pnl, size_t len, double initial_capital, PmRiskMetrics *out);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* POLYMARKET_ARB_H */
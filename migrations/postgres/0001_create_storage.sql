@@ -0,0 +1,22 @@
+-- Trades and per-step snapshots of every run.
+-- Full records live in `data` (JSON); the other columns exist for filtering and ordering.
+CREATE TABLE IF NOT EXISTS trades (
+    run_id TEXT NOT NULL,
+    trade_id TEXT NOT NULL,
+    market_id TEXT NOT NULL,
+    entry_ms BIGINT NOT NULL,
+    data TEXT NOT NULL,
+    PRIMARY KEY (run_id, trade_id)
+);
+
+CREATE TABLE IF NOT EXISTS snapshots (
+    run_id TEXT NOT NULL,
+    step BIGINT NOT NULL,
+    timestamp_ms BIGINT NOT NULL,
+    data TEXT NOT NULL,
+    PRIMARY KEY (run_id, step)
+);
+
+CREATE INDEX IF NOT EXISTS trades_market_entry_idx ON trades (market_id, entry_ms);
+CREATE INDEX IF NOT EXISTS trades_entry_idx ON trades (entry_ms);
+CREATE INDEX IF NOT EXISTS snapshots_timestamp_idx ON snapshots (timestamp_ms);
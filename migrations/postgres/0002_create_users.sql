@@ -0,0 +1,5 @@
+-- Dashboard accounts; `data` holds the full record including the password hash.
+CREATE TABLE IF NOT EXISTS users (
+    username TEXT PRIMARY KEY,
+    data TEXT NOT NULL
+);
@@ -0,0 +1,11 @@
+-- Per-market price snapshots recorded by a run, replayed offline for strategy research.
+CREATE TABLE IF NOT EXISTS prices (
+    id BIGSERIAL PRIMARY KEY,
+    run_id TEXT NOT NULL,
+    market_id TEXT NOT NULL,
+    timestamp_ms BIGINT NOT NULL,
+    data TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS prices_market_timestamp_idx ON prices (market_id, timestamp_ms);
+CREATE INDEX IF NOT EXISTS prices_timestamp_idx ON prices (timestamp_ms);
@@ -0,0 +1,406 @@
+//! Dashboard accounts module
+//!
+//! Implements:
+//! 1. Roles (admin, trader, viewer) and the permissions each one grants
+//! 2. Salted PBKDF2-SHA256 password hashes
+//! 3. Accounts persisted through the storage backend, with bearer-token sessions
+//! 4. First admin gated on a one-time bootstrap secret; unique usernames and serialized account changes
+//! 5. Login lockout per username and source after repeated failures, with unknown usernames costing a full hash check
+
+use crate::storage::SharedStorage;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use fxhash::FxHashMap;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// PBKDF2 rounds for new password hashes (few in unit tests, which run unoptimized)
+pub const PASSWORD_ROUNDS: u32 = if cfg!(test) { 1_000 } else { 100_000 };
+
+/// Session lifetime
+pub const SESSION_TTL_HOURS: i64 = 12;
+
+/// Failed logins allowed per username and source before that source is locked out
+pub const MAX_LOGIN_FAILURES: u32 = 5;
+
+/// Lockout after too many failed logins, counted from the last one
+pub const LOGIN_LOCKOUT_SECS: i64 = 300;
+
+/// Environment variable holding the one-time secret that creates the first admin
+pub const BOOTSTRAP_TOKEN_ENV: &str = "DASHBOARD_BOOTSTRAP_TOKEN";
+
+/// Dashboard role
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Admin,
+    Trader,
+    Viewer,
+}
+
+/// Operation gated by role
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    View,        // Lettura di stato, trade, mercati
+    Trade,       // Trade manuali
+    Control,     // Avvio/arresto del bot
+    Configure,   // Watchlist e parametri
+    Clear,       // Azzeramento di trade e ledger
+    ManageUsers,
+}
+
+impl Role {
+    pub fn allows(&self, permission: Permission) -> bool {
+        match self {
+            Role::Admin => true,
+            Role::Trader => matches!(permission, Permission::View | Permission::Trade | Permission::Control | Permission::Configure),
+            Role::Viewer => permission == Permission::View,
+        }
+    }
+}
+
+impl std::str::FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "admin" => Ok(Role::Admin),
+            "trader" => Ok(Role::Trader),
+            "viewer" => Ok(Role::Viewer),
+            _ => Err(format!("Unknown role {}", s)),
+        }
+    }
+}
+
+/// Stored account
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserAccount {
+    pub username: String,
+    pub role: Role,
+    pub password_hash: String, // pbkdf2-sha256$<round>$<salt>$<hash>
+    pub created_at: DateTime<Utc>,
+}
+
+/// Account as shown by the API (no hash)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserInfo {
+    pub username: String,
+    pub role: Role,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<&UserAccount> for UserInfo {
+    fn from(account: &UserAccount) -> Self {
+        Self {
+            username: account.username.clone(),
+            role: account.role,
+            created_at: account.created_at,
+        }
+    }
+}
+
+/// Hash a password with a fresh random salt
+pub fn hash_password(password: &str) -> String {
+    let mut salt = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut salt);
+    let mut hash = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(password.as_bytes(), &salt, PASSWORD_ROUNDS, &mut hash);
+    format!("pbkdf2-sha256${}${}${}", PASSWORD_ROUNDS, STANDARD.encode(salt), STANDARD.encode(hash))
+}
+
+/// Check a password against a stored hash (constant-time comparison)
+pub fn verify_password(password: &str, stored: &str) -> bool {
+    let parts: Vec<&str> = stored.split('$').collect();
+    let [scheme, rounds, salt, expected] = parts.as_slice() else { return false };
+    if *scheme != "pbkdf2-sha256" {
+        return false;
+    }
+    let (Ok(rounds), Ok(salt), Ok(expected)) = (rounds.parse::<u32>(), STANDARD.decode(salt), STANDARD.decode(expected)) else {
+        return false;
+    };
+
+    let mut hash = vec![0u8; expected.len()];
+    pbkdf2::pbkdf2_hmac::<Sha256>(password.as_bytes(), &salt, rounds, &mut hash);
+    hash.iter().zip(&expected).fold(0u8, |diff, (a, b)| diff | (a ^ b)) == 0 && !expected.is_empty()
+}
+
+/// Logged-in user
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Session {
+    pub token: String,
+    pub username: String,
+    pub role: Role,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Why a request was refused
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthError {
+    Unauthenticated,
+    Forbidden,
+    RateLimited,     // Troppi login falliti per lo stesso utente
+    Invalid(String), // Richiesta rifiutata: dati non validi o vincolo violato
+    Storage(String),
+}
+
+/// Failed logins of one username from one source
+#[derive(Debug, Clone, Copy)]
+struct LoginFailures {
+    count: u32,
+    last: DateTime<Utc>,
+}
+
+/// Hash checked for unknown usernames, so a login takes as long whether or not the user exists
+fn dummy_hash() -> &'static str {
+    static DUMMY_HASH: OnceLock<String> = OnceLock::new();
+    DUMMY_HASH.get_or_init(|| hash_password("unknown user"))
+}
+
+/// Constant-time comparison of two secrets
+fn secrets_match(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Account registry backed by the storage, with in-memory sessions
+///
+/// Account changes are serialized, so check-then-write sequences (duplicate usernames, the last
+/// admin, the first admin) can't interleave.
+#[derive(Clone)]
+pub struct Accounts {
+    storage: SharedStorage,
+    sessions: Arc<Mutex<FxHashMap<String, Session>>>,
+    has_users: Arc<Mutex<Option<bool>>>, // Cache di `enabled`, None finché non letta dallo storage
+    bootstrap_token: Arc<Mutex<Option<String>>>, // Secret monouso per creare il primo admin
+    failures: Arc<Mutex<FxHashMap<(String, String), LoginFailures>>>, // Chiave (username, indirizzo di origine)
+    changes: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl Accounts {
+    pub fn new(storage: SharedStorage) -> Self {
+        Self {
+            storage,
+            sessions: Arc::new(Mutex::new(FxHashMap::default())),
+            has_users: Arc::new(Mutex::new(None)),
+            bootstrap_token: Arc::new(Mutex::new(None)),
+            failures: Arc::new(Mutex::new(FxHashMap::default())),
+            changes: Arc::new(tokio::sync::Mutex::new(())),
+        }
+    }
+
+    /// One-time secret required to create the first admin; without it the first admin can't be created
+    pub fn with_bootstrap_token(self, token: Option<String>) -> Self {
+        *self.bootstrap_token.lock().unwrap() = token.filter(|t| !t.is_empty());
+        self
+    }
+
+    /// Whether any account exists (without accounts the dashboard stays open)
+    pub async fn enabled(&self) -> Result<bool, String> {
+        if let Some(has_users) = *self.has_users.lock().unwrap() {
+            return Ok(has_users);
+        }
+        let has_users = !self.storage.list_users().await?.is_empty();
+        *self.has_users.lock().unwrap() = Some(has_users);
+        Ok(has_users)
+    }
+
+    pub async fn list(&self) -> Result<Vec<UserInfo>, String> {
+        Ok(self.storage.list_users().await?.iter().map(UserInfo::from).collect())
+    }
+
+    /// Create an account; an existing username is refused
+    pub async fn create(&self, username: &str, password: &str, role: Role) -> Result<UserInfo, AuthError> {
+        let _changes = self.changes.lock().await;
+        self.create_locked(username, password, role).await
+    }
+
+    /// Create the first admin with the bootstrap token, consumed on success
+    pub async fn bootstrap(&self, token: Option<&str>, username: &str, password: &str) -> Result<UserInfo, AuthError> {
+        let _changes = self.changes.lock().await;
+        if self.enabled().await.map_err(AuthError::Storage)? {
+            return Err(AuthError::Invalid("Accounts already exist: log in as an admin".to_string()));
+        }
+        let expected = self.bootstrap_token.lock().unwrap().clone()
+            .ok_or_else(|| AuthError::Invalid(format!("First admin disabled: {} is not set", BOOTSTRAP_TOKEN_ENV)))?;
+        if !token.is_some_and(|token| secrets_match(token, &expected)) {
+            return Err(AuthError::Forbidden);
+        }
+        let user = self.create_locked(username, password, Role::Admin).await?;
+        *self.bootstrap_token.lock().unwrap() = None;
+        Ok(user)
+    }
+
+    async fn create_locked(&self, username: &str, password: &str, role: Role) -> Result<UserInfo, AuthError> {
+        let username = username.trim();
+        if username.is_empty() {
+            return Err(AuthError::Invalid("username is required".to_string()));
+        }
+        if password.len() < 8 {
+            return Err(AuthError::Invalid("password must be at least 8 characters".to_string()));
+        }
+        if self.storage.load_user(username).await.map_err(AuthError::Storage)?.is_some() {
+            return Err(AuthError::Invalid(format!("User {} already exists", username)));
+        }
+
+        // L'hash è volutamente lento: fuori dal runtime async
+        let password = password.to_string();
+        let password_hash = tokio::task::spawn_blocking(move || hash_password(&password))
+            .await
+            .map_err(|e| AuthError::Storage(format!("Password hashing failed: {}", e)))?;
+
+        let account = UserAccount {
+            username: username.to_string(),
+            role,
+            password_hash,
+            created_at: Utc::now(),
+        };
+        self.storage.save_user(&account).await.map_err(AuthError::Storage)?;
+        *self.has_users.lock().unwrap() = Some(true);
+        Ok(UserInfo::from(&account))
+    }
+
+    /// Delete an account and end its sessions; returns whether it existed
+    ///
+    /// The last admin is never deleted: nobody could manage users any more.
+    pub async fn delete(&self, username: &str) -> Result<bool, AuthError> {
+        let _changes = self.changes.lock().await;
+        let users = self.list().await.map_err(AuthError::Storage)?;
+        let admins = users.iter().filter(|u| u.role == Role::Admin).count();
+        if admins == 1 && users.iter().any(|u| u.username == username && u.role == Role::Admin) {
+            return Err(AuthError::Invalid("Cannot delete the last admin".to_string()));
+        }
+
+        let removed = self.storage.delete_user(username).await.map_err(AuthError::Storage)?;
+        self.sessions.lock().unwrap().retain(|_, s| s.username != username);
+        *self.has_users.lock().unwrap() = Some(users.len() > usize::from(removed));
+        Ok(removed)
+    }
+
+    /// Open a session for valid credentials
+    ///
+    /// After `MAX_LOGIN_FAILURES` failures from `source` (the client address) a username is locked
+    /// out for that source only, for `LOGIN_LOCKOUT_SECS` from the last one: a third party cannot
+    /// lock the account out for everyone else.
+    pub async fn login(&self, username: &str, password: &str, source: &str) -> Result<Session, AuthError> {
+        let now = Utc::now();
+        let key = (username.to_string(), source.to_string());
+        {
+            let mut failures = self.failures.lock().unwrap();
+            failures.retain(|_, f| now - f.last < chrono::Duration::seconds(LOGIN_LOCKOUT_SECS));
+            if failures.get(&key).is_some_and(|f| f.count >= MAX_LOGIN_FAILURES) {
+                return Err(AuthError::RateLimited);
+            }
+        }
+
+        let account = self.storage.load_user(username).await.map_err(AuthError::Storage)?;
+        // Utente sconosciuto: stesso costo di verifica di uno esistente
+        let hash = account.as_ref().map_or_else(|| dummy_hash().to_string(), |a| a.password_hash.clone());
+        let password = password.to_string();
+        let valid = tokio::task::spawn_blocking(move || verify_password(&password, &hash))
+            .await
+            .map_err(|e| AuthError::Storage(format!("Password check failed: {}", e)))?;
+        let Some(account) = account.filter(|_| valid) else {
+            let mut failures = self.failures.lock().unwrap();
+            let entry = failures.entry(key).or_insert(LoginFailures { count: 0, last: now });
+            entry.count += 1;
+            entry.last = now;
+            return Err(AuthError::Unauthenticated);
+        };
+        self.failures.lock().unwrap().remove(&key);
+
+        let session = Session {
+            token: uuid::Uuid::new_v4().simple().to_string(),
+            username: account.username,
+            role: account.role,
+            expires_at: now + chrono::Duration::hours(SESSION_TTL_HOURS),
+        };
+        self.sessions.lock().unwrap().insert(session.token.clone(), session.clone());
+        Ok(session)
+    }
+
+    pub fn logout(&self, token: &str) -> bool {
+        self.sessions.lock().unwrap().remove(token).is_some()
+    }
+
+    /// Live session for a token (expired sessions are dropped)
+    pub fn session(&self, token: &str) -> Option<Session> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let now = Utc::now();
+        sessions.retain(|_, s| s.expires_at > now);
+        sessions.get(token).cloned()
+    }
+
+    /// Check a bearer token against a permission; Ok(None) when accounts are not enabled
+    pub async fn authorize(&self, token: Option<&str>, permission: Permission) -> Result<Option<Session>, AuthError> {
+        if !self.enabled().await.map_err(AuthError::Storage)? {
+            return Ok(None);
+        }
+        let session = token
+            .and_then(|token| self.session(token))
+            .ok_or(AuthError::Unauthenticated)?;
+        if !session.role.allows(permission) {
+            return Err(AuthError::Forbidden);
+        }
+        Ok(Some(session))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::FlatFileStorage;
+
+    #[tokio::test]
+    async fn test_roles_and_sessions() {
+        let dir = std::env::temp_dir().join(format!("accounts_{}", uuid::Uuid::new_v4()));
+        let accounts = Accounts::new(Arc::new(FlatFileStorage::new(&dir))).with_bootstrap_token(Some("first-admin".to_string()));
+
+        // Senza account la dashboard resta aperta; il primo admin richiede il secret monouso
+        assert_eq!(accounts.authorize(None, Permission::Clear).await, Ok(None));
+        assert_eq!(accounts.bootstrap(Some("guess"), "mallory", "correct horse").await.unwrap_err(), AuthError::Forbidden);
+        assert_eq!(accounts.bootstrap(None, "mallory", "correct horse").await.unwrap_err(), AuthError::Forbidden);
+        accounts.bootstrap(Some("first-admin"), "alice", "correct horse").await.unwrap();
+        assert!(matches!(accounts.bootstrap(Some("first-admin"), "mallory", "correct horse").await, Err(AuthError::Invalid(_))));
+
+        accounts.create("bob", "battery staple", Role::Viewer).await.unwrap();
+        assert!(matches!(accounts.create("carol", "short", Role::Trader).await, Err(AuthError::Invalid(_))));
+        // Username esistente: rifiutato invece di sostituire l'account
+        assert!(matches!(accounts.create("alice", "new password", Role::Viewer).await, Err(AuthError::Invalid(_))));
+
+        assert_eq!(accounts.login("bob", "wrong password", "127.0.0.1").await.unwrap_err(), AuthError::Unauthenticated);
+        assert_eq!(accounts.login("nobody", "wrong password", "127.0.0.1").await.unwrap_err(), AuthError::Unauthenticated);
+        let bob = accounts.login("bob", "battery staple", "127.0.0.1").await.unwrap();
+        assert_eq!(accounts.authorize(None, Permission::View).await.unwrap_err(), AuthError::Unauthenticated);
+        assert!(accounts.authorize(Some(&bob.token), Permission::View).await.is_ok());
+        assert_eq!(accounts.authorize(Some(&bob.token), Permission::Control).await.unwrap_err(), AuthError::Forbidden);
+
+        let alice = accounts.login("alice", "correct horse", "127.0.0.1").await.unwrap();
+        assert!(accounts.authorize(Some(&alice.token), Permission::ManageUsers).await.is_ok());
+        assert!(matches!(accounts.delete("alice").await, Err(AuthError::Invalid(_))));
+
+        assert!(accounts.delete("bob").await.unwrap());
+        assert!(accounts.session(&bob.token).is_none());
+        assert_eq!(accounts.list().await.unwrap().len(), 1);
+
+        // Troppi tentativi falliti: bloccato anche con la password giusta, ma solo da quell'origine
+        for _ in 0..MAX_LOGIN_FAILURES {
+            assert_eq!(accounts.login("alice", "wrong password", "203.0.113.9").await.unwrap_err(), AuthError::Unauthenticated);
+        }
+        assert_eq!(accounts.login("alice", "correct horse", "203.0.113.9").await.unwrap_err(), AuthError::RateLimited);
+        assert!(accounts.login("alice", "correct horse", "127.0.0.1").await.is_ok());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_password_hash_roundtrip() {
+        let hash = hash_password("hunter22");
+        assert!(verify_password("hunter22", &hash));
+        assert!(!verify_password("hunter23", &hash));
+        assert!(!verify_password("hunter22", "plaintext"));
+        assert_ne!(hash, hash_password("hunter22"));
+        assert!(Role::Trader.allows(Permission::Control) && !Role::Trader.allows(Permission::Clear));
+    }
+}
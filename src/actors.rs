@@ -0,0 +1,531 @@
+//! Actor-based orchestrator
+//!
+//! Implements:
+//! 1. Market, Strategy, Risk and Execution actors, each owning a disjoint slice of the bot
+//! 2. Message passing over bounded mailboxes (backpressure on the tick intake)
+//! 3. Per-actor supervision: a panicking handler is isolated and counted, the actor
+//!    stops after `max_restarts` panics and its health is reported
+
+use crate::analytics::TradeMetricsAggregator;
+use crate::arbitrage::ArbitrageDetector;
+use crate::clock::SharedClock;
+use crate::detector::{Detector, MarketView};
+use crate::execution::TradeExecutor;
+use crate::market::{MarketManager, PriceSnapshot, Watchlist};
+use crate::optimization::StatisticalArbOptimizer;
+use crate::orderbook::OrderBookStore;
+use crate::polymarket_api::ConnectionState;
+use crate::relations::{MarketTriangle, RelationBook};
+use crate::risk::RiskManager;
+#[cfg(feature = "rl")]
+use crate::rl::QLearningOptimizer;
+use crate::types::*;
+use crate::{HftArbitrageBot, StepResult};
+use futures_util::future::BoxFuture;
+use futures_util::FutureExt;
+use fxhash::FxHashMap;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use tokio::sync::{mpsc, oneshot, watch};
+use tokio::task::JoinHandle;
+
+/// Actor system configuration
+#[derive(Debug, Clone)]
+pub struct ActorConfig {
+    pub mailbox_capacity: usize, // Capacità delle mailbox bounded
+    pub max_restarts: u32,       // Panic tollerati per attore prima di fermarlo
+}
+
+impl Default for ActorConfig {
+    fn default() -> Self {
+        Self {
+            mailbox_capacity: 64,
+            max_restarts: 3,
+        }
+    }
+}
+
+/// Health of a supervised actor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActorHealth {
+    Running,
+    Restarted(u32), // Numero di panic assorbiti finora
+    Stopped,
+    Failed,
+}
+
+type Reply = oneshot::Sender<StepResult>;
+
+enum MarketMsg {
+    Tick { step: u64, reply: Reply },
+}
+
+enum StrategyMsg {
+    Scan {
+        step: u64,
+        markets: Vec<MarketData>,
+        events: Vec<EventData>,
+        watchlist: Watchlist,
+        books: OrderBookStore, // Solo i book dei mercati da scansionare
+        price_history: FxHashMap<String, Vec<PriceSnapshot>>, // Solo gli storici dei mercati da scansionare
+        reply: Reply,
+    },
+}
+
+enum RiskMsg {
+    Review {
+        step: u64,
+        opportunities: usize,
+        candidate: Option<ArbitrageOpportunity>,
+        reply: Reply,
+    },
+    Fill {
+        step: u64,
+        opportunities: usize,
+        trade: Option<TradeExecution>,
+        reply: Reply,
+    },
+}
+
+enum ExecutionMsg {
+    Execute {
+        step: u64,
+        opportunities: usize,
+        opportunity: ArbitrageOpportunity,
+        capital: f64,
+        max_position: f64, // Limite della strategia
+        reply: Reply,
+    },
+}
+
+/// An actor handles one message at a time with exclusive access to its state
+trait Actor: Send + 'static {
+    type Msg: Send + 'static;
+    const NAME: &'static str;
+
+    fn handle(&mut self, msg: Self::Msg) -> BoxFuture<'_, ()>;
+}
+
+/// Bounded intake plus an optional unbounded side channel drained first
+struct Mailbox<M> {
+    intake: mpsc::Receiver<M>,
+    priority: Option<mpsc::UnboundedReceiver<M>>,
+}
+
+impl<M> Mailbox<M> {
+    fn bounded(intake: mpsc::Receiver<M>) -> Self {
+        Self { intake, priority: None }
+    }
+
+    /// Next message; None once the intake is closed and the side channel is drained
+    async fn recv(&mut self) -> Option<M> {
+        let Some(priority) = self.priority.as_mut() else {
+            return self.intake.recv().await;
+        };
+        if let Ok(msg) = priority.try_recv() {
+            return Some(msg);
+        }
+
+        tokio::select! {
+            biased;
+            Some(msg) = priority.recv() => Some(msg),
+            msg = self.intake.recv() => match msg {
+                Some(msg) => Some(msg),
+                None => priority.try_recv().ok(),
+            },
+        }
+    }
+}
+
+/// Run an actor under supervision, publishing its health
+fn spawn_supervised<A: Actor>(
+    mut actor: A,
+    mut mailbox: Mailbox<A::Msg>,
+    max_restarts: u32,
+) -> (JoinHandle<()>, watch::Receiver<ActorHealth>) {
+    let (health_tx, health_rx) = watch::channel(ActorHealth::Running);
+
+    let handle = tokio::spawn(async move {
+        let mut restarts = 0;
+        while let Some(msg) = mailbox.recv().await {
+            // Il panic resta confinato al messaggio: lo stato dell'attore sopravvive
+            if AssertUnwindSafe(actor.handle(msg)).catch_unwind().await.is_err() {
+                restarts += 1;
+                eprintln!("⚠️  Actor {} panicked ({} of {})", A::NAME, restarts, max_restarts);
+                if restarts > max_restarts {
+                    let _ = health_tx.send(ActorHealth::Failed);
+                    return;
+                }
+                let _ = health_tx.send(ActorHealth::Restarted(restarts));
+            }
+        }
+        let _ = health_tx.send(ActorHealth::Stopped);
+    });
+
+    (handle, health_rx)
+}
+
+/// Owns market data: price updates, scan selection, feed state
+struct MarketActor {
+    market_manager: MarketManager,
+    feed_state: Option<watch::Receiver<ConnectionState>>,
+    strategy_tx: mpsc::Sender<StrategyMsg>,
+    risk_tx: mpsc::Sender<RiskMsg>,
+}
+
+impl Actor for MarketActor {
+    type Msg = MarketMsg;
+    const NAME: &'static str = "market";
+
+    fn handle(&mut self, msg: MarketMsg) -> BoxFuture<'_, ()> {
+        async move {
+            let MarketMsg::Tick { step, reply } = msg;
+
+            let updated = self.market_manager.update_prices().await;
+            self.market_manager.run_onboarding();
+            let feed_ready = self.feed_state
+                .as_ref()
+                .is_none_or(|state| *state.borrow() == ConnectionState::Connected);
+
+            if let Err(e) = updated {
+                eprintln!("Step error: {}", e);
+            }
+            if !feed_ready {
+                let _ = self.risk_tx.send(RiskMsg::Review { step, opportunities: 0, candidate: None, reply }).await;
+                return;
+            }
+
+            let markets = self.market_manager.markets_to_scan(step);
+            let books = self.market_manager.order_books.subset(
+                markets.iter().filter_map(|m| m.tokens.as_ref()).flat_map(|t| [t.yes_token_id.as_str(), t.no_token_id.as_str()]),
+            );
+            let price_history = markets.iter()
+                .filter_map(|m| self.market_manager.price_history.get(&m.id).map(|h| (m.id.clone(), h.clone())))
+                .collect();
+            let _ = self.strategy_tx.send(StrategyMsg::Scan {
+                step,
+                markets,
+                events: self.market_manager.tradeable_events(),
+                watchlist: self.market_manager.watchlist.clone(),
+                books,
+                price_history,
+                reply,
+            }).await;
+        }
+        .boxed()
+    }
+}
+
+/// Owns detection and optimization
+struct StrategyActor {
+    arb_detector: Arc<RwLock<ArbitrageDetector>>, // Riprezzamento e dedup, condiviso con il detector YES/NO in detectors
+    detectors: Vec<Box<dyn Detector>>, // Tutti i detector del bot, built-in e registrati
+    relations: RelationBook,
+    triangles: Vec<MarketTriangle>,
+    optimizer: StatisticalArbOptimizer,
+    clock: SharedClock, // Scadenza del cooldown delle opportunità già riportate
+    risk_tx: mpsc::Sender<RiskMsg>,
+}
+
+impl Actor for StrategyActor {
+    type Msg = StrategyMsg;
+    const NAME: &'static str = "strategy";
+
+    fn handle(&mut self, msg: StrategyMsg) -> BoxFuture<'_, ()> {
+        async move {
+            let StrategyMsg::Scan { step, markets, events, watchlist, books, price_history, reply } = msg;
+
+            // Stessa scansione di HftArbitrageBot::detect_opportunities
+            let view = MarketView {
+                markets: &markets,
+                events: &events,
+                watchlist: &watchlist,
+                order_books: &books,
+                price_history: &price_history,
+                relations: self.relations.list(),
+                triangles: &self.triangles,
+                rewards: &self.optimizer.rewards,
+                now: self.clock.now(),
+            };
+            let mut opportunities = Vec::new();
+            for detector in &self.detectors {
+                let found = detector.scan(&view);
+                if detector.on_tick_grid() {
+                    opportunities.extend(found);
+                } else {
+                    opportunities.extend(self.arb_detector.read().unwrap().enforce_order_constraints(found));
+                }
+            }
+            let opportunities = self.arb_detector.write().unwrap().dedup(opportunities, self.clock.now());
+
+            // Il capitale è del RiskActor: l'optimizer qui ordina soltanto
+            let optimized = self.optimizer.optimize_arbitrage_pairs(&opportunities, 0.0).await;
+            let projected = self.optimizer.bregman_projection(&optimized).await;
+            // Solo il candidato inviato all'esecuzione entra in cooldown
+            if let Some(candidate) = projected.first() {
+                self.arb_detector.write().unwrap().mark_reported(candidate, self.clock.now());
+            }
+
+            let _ = self.risk_tx.send(RiskMsg::Review {
+                step,
+                opportunities: opportunities.len(),
+                candidate: projected.into_iter().next(),
+                reply,
+            }).await;
+        }
+        .boxed()
+    }
+}
+
+/// Owns capital and risk limits; produces every step result
+struct RiskActor {
+    risk_manager: RiskManager,
+    capital: f64,
+    trade_metrics: TradeMetricsAggregator,
+    execution_tx: mpsc::Sender<ExecutionMsg>,
+}
+
+impl RiskActor {
+    fn result(&self, step: u64, opportunities: usize, trades: u32, profit: f64) -> StepResult {
+        let metrics = self.trade_metrics.rolling();
+        StepResult {
+            step,
+            opportunities,
+            trades,
+            profit,
+            capital: self.capital,
+            win_rate: metrics.win_rate,
+            avg_edge: metrics.avg_edge,
+            expectancy: metrics.expectancy,
+        }
+    }
+}
+
+impl Actor for RiskActor {
+    type Msg = RiskMsg;
+    const NAME: &'static str = "risk";
+
+    fn handle(&mut self, msg: RiskMsg) -> BoxFuture<'_, ()> {
+        async move {
+            match msg {
+                RiskMsg::Review { step, opportunities, candidate, reply } => {
+                    let multiplier = candidate.as_ref().map_or(0.0, |o| self.risk_manager.resolution_multiplier(o));
+                    let allowed = |o: &ArbitrageOpportunity| multiplier > 0.0 && self.risk_manager.can_trade_strategy(o.arb_type, self.capital);
+                    let Some(opportunity) = candidate.filter(allowed) else {
+                        let _ = reply.send(self.result(step, opportunities, 0, 0.0));
+                        return;
+                    };
+                    let capital = self.risk_manager.tradable_capital(self.capital) * multiplier;
+                    let max_position = self.risk_manager.max_position_for(opportunity.arb_type);
+                    let _ = self.execution_tx.send(ExecutionMsg::Execute { step, opportunities, opportunity, capital, max_position, reply }).await;
+                }
+                RiskMsg::Fill { step, opportunities, trade, reply } => {
+                    let profit = trade.as_ref().map(|t| t.profit).unwrap_or(0.0);
+                    self.capital += profit;
+                    match &trade {
+                        Some(t) => self.risk_manager.update_strategy(t.arb_type, profit, self.capital),
+                        None => self.risk_manager.update(profit, self.capital),
+                    }
+                    if let Some(ref t) = trade {
+                        self.trade_metrics.record_trade(t);
+                    }
+
+                    let trades = if trade.is_some() { 1 } else { 0 };
+                    let _ = reply.send(self.result(step, opportunities, trades, profit));
+                }
+            }
+        }
+        .boxed()
+    }
+}
+
+/// Owns order execution and the learning agent
+struct ExecutionActor {
+    executor: TradeExecutor,
+    #[cfg(feature = "rl")]
+    rl_agent: QLearningOptimizer,
+    // Unbounded: un ciclo Risk <-> Execution con due code bounded può andare in deadlock;
+    // i fill in volo sono comunque limitati dalla mailbox bounded dei tick
+    fill_tx: mpsc::UnboundedSender<RiskMsg>,
+}
+
+impl Actor for ExecutionActor {
+    type Msg = ExecutionMsg;
+    const NAME: &'static str = "execution";
+
+    fn handle(&mut self, msg: ExecutionMsg) -> BoxFuture<'_, ()> {
+        async move {
+            let ExecutionMsg::Execute { step, opportunities, opportunity, capital, max_position, reply } = msg;
+
+            let trade = self.executor.execute_arbitrage_within(&opportunity, capital, max_position).await;
+            #[cfg(feature = "rl")]
+            if let Some(ref t) = trade {
+                self.rl_agent.learn_from_trade(&opportunity, t);
+            }
+
+            let _ = self.fill_tx.send(RiskMsg::Fill {
+                step,
+                opportunities,
+                trade,
+                reply,
+            });
+        }
+        .boxed()
+    }
+}
+
+/// Running actor system built from a bot
+pub struct ActorSystem {
+    market_tx: mpsc::Sender<MarketMsg>,
+    health: Vec<(&'static str, watch::Receiver<ActorHealth>)>,
+    tasks: Vec<JoinHandle<()>>,
+    step: AtomicU64,
+}
+
+impl ActorSystem {
+    /// Split the bot into actors and start them
+    ///
+    /// Fails for bots the pipeline cannot run as configured: stat-arb positions (single-token
+    /// and pair spreads), the adverse-selection discount and the opportunity webhook have no actor.
+    pub fn spawn(bot: HftArbitrageBot, config: ActorConfig) -> Result<Self, String> {
+        let unsupported: Vec<&str> = [
+            ("stat-arb positions", bot.stat_arb.is_some()),
+            ("adverse selection", bot.adverse_selection.is_some()),
+            ("opportunity webhook", bot.opportunity_webhook.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(name, configured)| configured.then_some(name))
+        .collect();
+        if !unsupported.is_empty() {
+            return Err(format!("Actor system does not support: {}", unsupported.join(", ")));
+        }
+
+        let capacity = config.mailbox_capacity.max(1);
+        let (market_tx, market_rx) = mpsc::channel(capacity);
+        let (strategy_tx, strategy_rx) = mpsc::channel(capacity);
+        let (risk_tx, risk_rx) = mpsc::channel(capacity);
+        let (execution_tx, execution_rx) = mpsc::channel(capacity);
+        let (fill_tx, fill_rx) = mpsc::unbounded_channel();
+
+        let market = MarketActor {
+            market_manager: bot.market_manager,
+            feed_state: bot.feed_state,
+            strategy_tx,
+            risk_tx: risk_tx.clone(),
+        };
+        let strategy = StrategyActor {
+            arb_detector: bot.arb_detector,
+            detectors: bot.detectors,
+            relations: bot.relations,
+            triangles: bot.triangles,
+            optimizer: bot.optimizer,
+            clock: bot.clock.clone(),
+            risk_tx,
+        };
+        let risk = RiskActor {
+            risk_manager: bot.risk_manager,
+            capital: bot.capital,
+            trade_metrics: bot.trade_metrics,
+            execution_tx,
+        };
+        let execution = ExecutionActor {
+            executor: bot.executor,
+            #[cfg(feature = "rl")]
+            rl_agent: bot.rl_agent,
+            fill_tx,
+        };
+
+        let mut tasks = Vec::new();
+        let mut health = Vec::new();
+        let mut register = |name, (task, rx)| {
+            tasks.push(task);
+            health.push((name, rx));
+        };
+        register(MarketActor::NAME, spawn_supervised(market, Mailbox::bounded(market_rx), config.max_restarts));
+        register(StrategyActor::NAME, spawn_supervised(strategy, Mailbox::bounded(strategy_rx), config.max_restarts));
+        register(RiskActor::NAME, spawn_supervised(
+            risk,
+            Mailbox { intake: risk_rx, priority: Some(fill_rx) },
+            config.max_restarts,
+        ));
+        register(ExecutionActor::NAME, spawn_supervised(execution, Mailbox::bounded(execution_rx), config.max_restarts));
+
+        Ok(Self {
+            market_tx,
+            health,
+            tasks,
+            step: AtomicU64::new(bot.current_step),
+        })
+    }
+
+    /// Run one trading step through the pipeline
+    pub async fn step(&self) -> Result<StepResult, String> {
+        let step = self.step.fetch_add(1, Ordering::SeqCst) + 1;
+        let (reply, result) = oneshot::channel();
+
+        self.market_tx
+            .send(MarketMsg::Tick { step, reply })
+            .await
+            .map_err(|_| "Market actor is not running".to_string())?;
+
+        result.await.map_err(|_| format!("Step {} was dropped by a failed actor", step))
+    }
+
+    /// Run several steps, skipping failed ones
+    pub async fn run(&self, num_steps: u64) -> Vec<StepResult> {
+        let mut results = Vec::new();
+        for _ in 0..num_steps {
+            match self.step().await {
+                Ok(result) => results.push(result),
+                Err(e) => eprintln!("Step error: {}", e),
+            }
+        }
+        results
+    }
+
+    /// Current health of every actor
+    pub fn health(&self) -> Vec<(&'static str, ActorHealth)> {
+        self.health.iter().map(|(name, rx)| (*name, *rx.borrow())).collect()
+    }
+
+    /// Close the intake and wait for every actor to drain and stop
+    pub async fn shutdown(self) {
+        drop(self.market_tx);
+        for task in self.tasks {
+            let _ = task.await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_actor_pipeline_runs_steps() {
+        let mut bot = HftArbitrageBot::new(BotConfig::default());
+        bot.market_manager.fetch_markets().await.unwrap();
+        let initial_capital = bot.capital;
+
+        let system = ActorSystem::spawn(bot, ActorConfig { mailbox_capacity: 4, max_restarts: 1 }).unwrap();
+        let results = system.run(5).await;
+
+        assert_eq!(results.len(), 5);
+        assert_eq!(results.iter().map(|r| r.step).collect::<Vec<_>>(), vec![1, 2, 3, 4, 5]);
+        let profit: f64 = results.iter().map(|r| r.profit).sum();
+        assert!((results[4].capital - (initial_capital + profit)).abs() < 1e-6);
+        assert!(system.health().iter().all(|(_, h)| *h == ActorHealth::Running));
+
+        system.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_spawn_refuses_configs_without_an_actor() {
+        let config = BotConfig { stat_arb: Some(crate::stat_arb::StatArbConfig::default()), ..BotConfig::default() };
+        let bot = HftArbitrageBot::new(config);
+
+        let err = ActorSystem::spawn(bot, ActorConfig::default()).err().unwrap();
+        assert!(err.contains("stat-arb"));
+    }
+}
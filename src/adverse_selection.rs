@@ -0,0 +1,232 @@
+//! Adverse selection module
+//!
+//! Implements:
+//! 1. Quote activity per market from its price history: update frequency and mean move per update
+//! 2. Fill latency per market: moving average of detection-to-fill times, with a global fallback
+//! 3. Expected adverse move of each leg over the fill latency (random walk of the quotes)
+//! 4. Opportunity profit and ROI discounted by that move, so thin edges on fast-moving markets
+//!    rank lower and those it consumes entirely are dropped
+
+use crate::market::PriceSnapshot;
+use crate::types::{ArbType, ArbitrageOpportunity, TradeExecution};
+use chrono::{DateTime, Duration, Utc};
+use fxhash::FxHashMap;
+use serde::{Deserialize, Serialize};
+
+/// Windows and weights of the adverse selection discount
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AdverseSelectionConfig {
+    pub window_secs: i64,        // Storico prezzi su cui misurare frequenza e ampiezza degli aggiornamenti
+    pub default_latency_ms: f64, // Latenza di fill prima che ne sia misurata una
+    pub latency_alpha: f64,      // Peso dell'ultimo fill nella media mobile della latenza
+    pub multiplier: f64,         // Scala dello sconto (1 = mossa attesa per intero)
+}
+
+impl Default for AdverseSelectionConfig {
+    fn default() -> Self {
+        Self { window_secs: 300, default_latency_ms: 250.0, latency_alpha: 0.2, multiplier: 1.0 }
+    }
+}
+
+/// How often and how far a market's quotes move
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct QuoteActivity {
+    pub updates: usize,
+    pub updates_per_sec: f64,
+    pub mean_abs_move: f64, // Variazione media di YES e NO per aggiornamento
+}
+
+impl QuoteActivity {
+    /// Activity over the snapshots of the last `window` before `now`; None with fewer than two
+    pub fn measure(history: &[PriceSnapshot], now: DateTime<Utc>, window: Duration) -> Option<Self> {
+        let since = now - window;
+        let recent: Vec<&PriceSnapshot> = history.iter().filter(|s| s.timestamp >= since && s.timestamp <= now).collect();
+        if recent.len() < 2 {
+            return None;
+        }
+        let updates = recent.len() - 1;
+        let total_move: f64 = recent.windows(2)
+            .map(|w| ((w[1].yes_price - w[0].yes_price).abs() + (w[1].no_price - w[0].no_price).abs()) / 2.0)
+            .sum();
+        // Frequenza sull'intervallo dal primo aggiornamento fino a ora: un mercato fermo rallenta
+        let span_secs = ((now - recent[0].timestamp).num_milliseconds() as f64 / 1000.0).max(1.0);
+        Some(Self {
+            updates,
+            updates_per_sec: updates as f64 / span_secs,
+            mean_abs_move: total_move / updates as f64,
+        })
+    }
+
+    /// Expected absolute quote move within `latency_ms`
+    ///
+    /// Below one expected update the move is the chance of an update times its size; above,
+    /// the quotes random-walk and the move grows with the square root of the updates.
+    pub fn expected_move(&self, latency_ms: f64) -> f64 {
+        let expected_updates = self.updates_per_sec * latency_ms.max(0.0) / 1000.0;
+        let steps = if expected_updates < 1.0 { expected_updates } else { expected_updates.sqrt() };
+        self.mean_abs_move * steps
+    }
+}
+
+/// Fill latencies and the adverse selection discount derived from them
+#[derive(Debug, Clone, Default)]
+pub struct AdverseSelectionModel {
+    pub config: AdverseSelectionConfig,
+    fill_latency_ms: FxHashMap<String, f64>, // Media mobile per mercato
+    overall_latency_ms: Option<f64>,         // Media mobile su tutti i fill
+}
+
+impl AdverseSelectionModel {
+    pub fn new(config: AdverseSelectionConfig) -> Self {
+        Self { config, ..Self::default() }
+    }
+
+    /// Record the detection-to-fill time of a trade on each of its markets
+    pub fn record_fill(&mut self, trade: &TradeExecution, latency_ms: f64) {
+        let alpha = self.config.latency_alpha.clamp(0.0, 1.0);
+        let blend = |previous: Option<f64>| previous.map_or(latency_ms, |p| p * (1.0 - alpha) + latency_ms * alpha);
+
+        let mut markets: Vec<&str> = trade.legs.iter().map(|l| l.market_id.as_str()).collect();
+        markets.push(trade.market_id.as_str());
+        markets.sort_unstable();
+        markets.dedup();
+        for market_id in markets {
+            let latency = blend(self.fill_latency_ms.get(market_id).copied());
+            self.fill_latency_ms.insert(market_id.to_string(), latency);
+        }
+        self.overall_latency_ms = Some(blend(self.overall_latency_ms));
+    }
+
+    /// Expected fill latency on a market: its own average, else the overall one, else the default
+    pub fn fill_latency_ms(&self, market_id: &str) -> f64 {
+        self.fill_latency_ms.get(market_id)
+            .copied()
+            .or(self.overall_latency_ms)
+            .unwrap_or(self.config.default_latency_ms)
+    }
+
+    /// Expected loss per unit of the opportunity while its legs are being filled
+    pub fn discount(&self, opportunity: &ArbitrageOpportunity, histories: &FxHashMap<String, Vec<PriceSnapshot>>, now: DateTime<Utc>) -> f64 {
+        let window = Duration::seconds(self.config.window_secs.max(1));
+        let leg_move = |node: &str| {
+            // I nodi del grafo portano l'esito dopo l'ultimo '-'
+            let (market_id, history) = histories.get_key_value(node)
+                .or_else(|| node.rsplit_once('-').and_then(|(id, _)| histories.get_key_value(id)))?;
+            let activity = QuoteActivity::measure(history, now, window)?;
+            Some(activity.expected_move(self.fill_latency_ms(market_id)))
+        };
+
+        let total: f64 = match (&opportunity.legs, &opportunity.path) {
+            (Some(legs), _) => {
+                // Nelle coppie statistiche la quantità è il peso di copertura della gamba
+                let weighted = opportunity.arb_type == ArbType::StatisticalArb;
+                legs.iter()
+                    .map(|l| leg_move(&l.market_id).unwrap_or(0.0) * if weighted { l.quantity.abs() } else { 1.0 })
+                    .sum()
+            }
+            (None, Some(path)) => path.iter().map(|node| leg_move(node).unwrap_or(0.0)).sum(),
+            // YES e NO dello stesso mercato
+            (None, None) => 2.0 * leg_move(&opportunity.market_id).unwrap_or(0.0),
+        };
+        total * self.config.multiplier.max(0.0)
+    }
+
+    /// Opportunities with their profit and ROI discounted, and those left without edge
+    pub fn adjust(
+        &self,
+        opportunities: Vec<ArbitrageOpportunity>,
+        histories: &FxHashMap<String, Vec<PriceSnapshot>>,
+        now: DateTime<Utc>,
+    ) -> (Vec<ArbitrageOpportunity>, Vec<ArbitrageOpportunity>) {
+        let mut kept = Vec::new();
+        let mut consumed = Vec::new();
+        for mut opportunity in opportunities {
+            let discount = self.discount(&opportunity, histories, now);
+            let profit = opportunity.profit - discount;
+            if profit <= 0.0 {
+                consumed.push(opportunity);
+                continue;
+            }
+            if opportunity.profit > 0.0 {
+                opportunity.roi_pct *= profit / opportunity.profit;
+            }
+            opportunity.profit = profit;
+            kept.push(opportunity);
+        }
+        (kept, consumed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn opportunity(market_id: &str, profit: f64) -> ArbitrageOpportunity {
+        ArbitrageOpportunity {
+            market_id: market_id.to_string(),
+            question: String::new(),
+            arb_type: ArbType::YesNoSimple,
+            profit,
+            roi_pct: profit * 100.0,
+            confidence: 0.9,
+            yes_price: 0.48,
+            no_price: 0.49,
+            sum_price: 0.97,
+            liquidity: 1000.0,
+            timestamp: Utc::now(),
+            legs: None,
+            path: None,
+            maker: None,
+        }
+    }
+
+    #[test]
+    fn test_flickering_market_edge_is_discounted_below_a_quiet_one() {
+        let now = Utc::now();
+        let snapshot = |ms_ago: i64, yes_price: f64| PriceSnapshot {
+            timestamp: now - Duration::milliseconds(ms_ago),
+            yes_price,
+            no_price: 1.0 - yes_price,
+            volume: 0.0,
+        };
+        // Mercato veloce: un aggiornamento da 2 centesimi ogni 100 ms; lento: uno al minuto
+        let fast: Vec<PriceSnapshot> = (0..20).map(|i| snapshot(2_000 - i * 100, if i % 2 == 0 { 0.50 } else { 0.52 })).collect();
+        let slow = vec![snapshot(120_000, 0.50), snapshot(60_000, 0.52)];
+        let histories: FxHashMap<String, Vec<PriceSnapshot>> =
+            [("fast".to_string(), fast), ("slow".to_string(), slow)].into_iter().collect();
+
+        let mut model = AdverseSelectionModel::new(AdverseSelectionConfig::default());
+        let activity = QuoteActivity::measure(&histories["fast"], now, Duration::seconds(300)).unwrap();
+        assert!((activity.updates_per_sec - 9.5).abs() < 1e-9);
+        assert!((activity.mean_abs_move - 0.02).abs() < 1e-9);
+
+        // Con 400 ms di latenza misurata il margine di 3 centesimi sul mercato veloce si annulla
+        let trade = TradeExecution {
+            trade_id: "t1".to_string(),
+            market_id: "fast".to_string(),
+            arb_type: ArbType::YesNoSimple,
+            legs: Vec::new(),
+            total_investment: 97.0,
+            expected_return: 100.0,
+            actual_return: 100.0,
+            profit: 3.0,
+            roi_pct: 3.0,
+            entry_time: now,
+            exit_time: now,
+            execution_time_ms: 400,
+            slippage_pct: 0.0,
+            gas_cost: 0.0,
+            fees: 0.0,
+        };
+        model.record_fill(&trade, 400.0);
+        assert_eq!(model.fill_latency_ms("fast"), 400.0);
+        assert_eq!(model.fill_latency_ms("never_traded"), 400.0);
+
+        let (kept, consumed) = model.adjust(vec![opportunity("fast", 0.03), opportunity("slow", 0.03)], &histories, now);
+        assert_eq!(consumed[0].market_id, "fast");
+        assert_eq!(kept[0].market_id, "slow");
+        assert!(kept[0].profit < 0.03 && kept[0].profit > 0.029);
+        assert!((kept[0].roi_pct - kept[0].profit * 100.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,620 @@
+//! Position analytics module
+//!
+//! Implements:
+//! 1. Carry analysis: value of holding to resolution vs unwinding now
+//! 2. Hold/unwind decisions for arbitrage pairs and single legs
+//! 3. Capital efficiency: deployed capital, turnover, return on deployed capital
+//! 4. Expiry ladder: open positions by time to resolution with the action each one needs
+//! 5. Partial liquidation planner: which positions to trim when capital binds
+//! 6. Trade metrics: win rate, average edge captured and expectancy, overall or over a rolling window
+
+use crate::paper::PaperPosition;
+use crate::types::*;
+use chrono::{DateTime, Utc};
+use fxhash::FxHashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Hold-or-unwind verdict
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HoldDecision {
+    HoldToResolution,
+    Unwind,
+}
+
+/// Result of a carry analysis
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CarryAnalysis {
+    pub hold_value: f64,           // Valore atteso alla risoluzione, scontato al tasso hurdle
+    pub unwind_value: f64,         // Incasso netto chiudendo ora
+    pub carry: f64,                // hold_value - unwind_value
+    pub annualized_carry_pct: f64, // Rendimento annualizzato (non scontato) del tenere la posizione
+    pub days_to_resolution: f64,
+    pub decision: HoldDecision,
+}
+
+/// Carry analyzer with opportunity cost of capital and exit costs
+#[derive(Debug, Clone)]
+pub struct CarryAnalyzer {
+    pub annual_hurdle_rate: f64, // Costo opportunità del capitale (es. 0.05 = 5%/anno)
+    pub exit_fee_pct: f64,       // Costo di uscita sul notional venduto
+}
+
+impl Default for CarryAnalyzer {
+    fn default() -> Self {
+        Self {
+            annual_hurdle_rate: 0.05,
+            exit_fee_pct: 0.002,
+        }
+    }
+}
+
+impl CarryAnalyzer {
+    pub fn new(annual_hurdle_rate: f64, exit_fee_pct: f64) -> Self {
+        Self { annual_hurdle_rate, exit_fee_pct }
+    }
+
+    /// Single outcome leg: expected payout is `fair_probability` per share
+    pub fn analyze_leg(&self, quantity: f64, fair_probability: f64, exit_bid: f64, days_to_resolution: f64) -> CarryAnalysis {
+        let expected_payout = fair_probability.clamp(0.0, 1.0) * quantity;
+        let unwind_value = exit_bid * quantity * (1.0 - self.exit_fee_pct);
+        self.compare(expected_payout, unwind_value, days_to_resolution)
+    }
+
+    /// YES+NO pair: pays exactly 1.0 per pair at resolution regardless of outcome
+    pub fn analyze_pair(&self, pairs: f64, yes_bid: f64, no_bid: f64, days_to_resolution: f64) -> CarryAnalysis {
+        let unwind_value = (yes_bid + no_bid) * pairs * (1.0 - self.exit_fee_pct);
+        self.compare(pairs, unwind_value, days_to_resolution)
+    }
+
+    /// Analyze an open paper position, using the current bid as fair value if none is given
+    pub fn analyze_position(
+        &self,
+        position: &PaperPosition,
+        exit_bid: f64,
+        fair_probability: Option<f64>,
+        days_to_resolution: f64,
+    ) -> CarryAnalysis {
+        self.analyze_leg(position.quantity, fair_probability.unwrap_or(exit_bid), exit_bid, days_to_resolution)
+    }
+
+    /// Fair probability of an outcome from a two-sided quote
+    pub fn implied_probability(market: &MarketData, token_type: TokenType) -> f64 {
+        let sum = market.yes_price + market.no_price;
+        if sum <= 0.0 {
+            return 0.5;
+        }
+        match token_type {
+            TokenType::Yes => market.yes_price / sum,
+            TokenType::No => market.no_price / sum,
+        }
+    }
+
+    fn compare(&self, payout_at_resolution: f64, unwind_value: f64, days_to_resolution: f64) -> CarryAnalysis {
+        let days = days_to_resolution.max(0.0);
+        let years = days / 365.0;
+        let discount = (1.0 + self.annual_hurdle_rate).powf(years);
+        let hold_value = payout_at_resolution / discount;
+
+        let annualized_carry_pct = if unwind_value > 0.0 && years > 0.0 {
+            ((payout_at_resolution / unwind_value).powf(1.0 / years) - 1.0) * 100.0
+        } else {
+            0.0
+        };
+
+        let carry = hold_value - unwind_value;
+        CarryAnalysis {
+            hold_value,
+            unwind_value,
+            carry,
+            annualized_carry_pct,
+            days_to_resolution: days,
+            decision: if carry >= 0.0 { HoldDecision::HoldToResolution } else { HoldDecision::Unwind },
+        }
+    }
+}
+
+/// Capital efficiency summary
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CapitalEfficiencyMetrics {
+    pub average_deployed: f64,
+    pub peak_deployed: f64,
+    pub utilization_pct: f64,        // Capitale medio impiegato / capitale base
+    pub turnover: f64,               // Notional scambiato / capitale base
+    pub return_on_deployed_pct: f64, // PnL realizzato / capitale medio impiegato
+    pub roi_pct: f64,                // PnL realizzato / capitale base
+}
+
+/// Running tracker of how much capital is actually at work
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CapitalEfficiency {
+    pub samples: u64,
+    pub deployed_sum: f64,
+    pub peak_deployed: f64,
+    pub traded_notional: f64,
+    pub realized_pnl: f64,
+}
+
+impl CapitalEfficiency {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record capital tied up at one sampling point (one step or tick)
+    pub fn sample_deployed(&mut self, deployed: f64) {
+        let deployed = deployed.max(0.0);
+        self.samples += 1;
+        self.deployed_sum += deployed;
+        self.peak_deployed = self.peak_deployed.max(deployed);
+    }
+
+    /// Record a fill: its notional counts towards turnover
+    pub fn record_trade(&mut self, notional: f64, realized_pnl: f64) {
+        self.traded_notional += notional.abs();
+        self.realized_pnl += realized_pnl;
+    }
+
+    pub fn average_deployed(&self) -> f64 {
+        if self.samples == 0 {
+            return 0.0;
+        }
+        self.deployed_sum / self.samples as f64
+    }
+
+    /// Metrics relative to `base_capital` (usually the initial capital)
+    pub fn metrics(&self, base_capital: f64) -> CapitalEfficiencyMetrics {
+        let average_deployed = self.average_deployed();
+        let ratio = |num: f64, den: f64| if den > 0.0 { num / den } else { 0.0 };
+
+        CapitalEfficiencyMetrics {
+            average_deployed,
+            peak_deployed: self.peak_deployed,
+            utilization_pct: ratio(average_deployed, base_capital) * 100.0,
+            turnover: ratio(self.traded_notional, base_capital),
+            return_on_deployed_pct: ratio(self.realized_pnl, average_deployed) * 100.0,
+            roi_pct: ratio(self.realized_pnl, base_capital) * 100.0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// Win rate, edge captured and expectancy of a set of trades
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct TradeMetrics {
+    pub trades: usize,
+    pub win_rate: f64,   // Quota di trade in profitto (0-1)
+    pub avg_edge: f64,   // Profitto medio per dollaro investito
+    pub expectancy: f64, // Profitto atteso per trade (USD): win_rate * vincita media - loss_rate * perdita media
+}
+
+impl TradeMetrics {
+    /// Metrics of `(profit, notional)` outcomes; trades without notional count towards the edge as 0
+    pub fn from_outcomes(outcomes: impl IntoIterator<Item = (f64, f64)>) -> Self {
+        let (mut trades, mut wins, mut profit, mut edge) = (0usize, 0usize, 0.0, 0.0);
+        for (trade_profit, notional) in outcomes {
+            trades += 1;
+            wins += usize::from(trade_profit > 0.0);
+            profit += trade_profit;
+            if notional.abs() > 0.0 {
+                edge += trade_profit / notional.abs();
+            }
+        }
+        if trades == 0 {
+            return Self::default();
+        }
+        let n = trades as f64;
+        Self { trades, win_rate: wins as f64 / n, avg_edge: edge / n, expectancy: profit / n }
+    }
+
+    /// Metrics of executed arbitrage trades
+    pub fn from_trades<'a>(trades: impl IntoIterator<Item = &'a TradeExecution>) -> Self {
+        Self::from_outcomes(trades.into_iter().map(|t| (t.profit, t.total_investment)))
+    }
+}
+
+/// Trades in the rolling window of the per-step metrics
+pub const TRADE_METRICS_WINDOW: usize = 100;
+
+/// Trade metrics over the last `window` trades
+#[derive(Debug, Clone)]
+pub struct TradeMetricsAggregator {
+    window: usize,
+    outcomes: VecDeque<(f64, f64)>, // (profit, notional) dei trade più recenti
+}
+
+impl TradeMetricsAggregator {
+    pub fn new(window: usize) -> Self {
+        Self { window: window.max(1), outcomes: VecDeque::new() }
+    }
+
+    /// Record a closed trade, dropping the oldest once the window is full
+    pub fn record(&mut self, profit: f64, notional: f64) {
+        if self.outcomes.len() == self.window {
+            self.outcomes.pop_front();
+        }
+        self.outcomes.push_back((profit, notional));
+    }
+
+    pub fn record_trade(&mut self, trade: &TradeExecution) {
+        self.record(trade.profit, trade.total_investment);
+    }
+
+    /// Metrics of the trades in the window
+    pub fn rolling(&self) -> TradeMetrics {
+        TradeMetrics::from_outcomes(self.outcomes.iter().copied())
+    }
+
+    pub fn reset(&mut self) {
+        self.outcomes.clear();
+    }
+}
+
+/// Time-to-resolution bucket of the expiry ladder
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExpiryBucket {
+    Expired, // Data di risoluzione passata, in attesa del payout
+    Within1d,
+    Within7d,
+    Within30d,
+    Beyond30d,
+    Unknown, // Mercato senza data di risoluzione nota
+}
+
+impl ExpiryBucket {
+    pub fn for_days(days_to_resolution: Option<f64>) -> Self {
+        match days_to_resolution {
+            None => ExpiryBucket::Unknown,
+            Some(days) if days <= 0.0 => ExpiryBucket::Expired,
+            Some(days) if days <= 1.0 => ExpiryBucket::Within1d,
+            Some(days) if days <= 7.0 => ExpiryBucket::Within7d,
+            Some(days) if days <= 30.0 => ExpiryBucket::Within30d,
+            Some(_) => ExpiryBucket::Beyond30d,
+        }
+    }
+}
+
+/// What the operator should do with a position
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LadderAction {
+    Hold,
+    Unwind, // Il carry fino alla risoluzione non ripaga il capitale immobilizzato
+    Redeem, // Mercato scaduto: riscattare le share vincenti
+}
+
+/// One open position on the ladder
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LadderPosition {
+    pub market_id: String,
+    pub question: String,
+    pub token_type: TokenType,
+    pub quantity: f64,
+    pub avg_price: f64,
+    pub mark: f64, // Prezzo corrente, o prezzo medio se il mercato non è quotato
+    pub end_date: Option<DateTime<Utc>>,
+    pub days_to_resolution: Option<f64>,
+    pub carry: Option<f64>,
+    pub action: LadderAction,
+}
+
+/// Positions sharing a time-to-resolution bucket
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LadderRung {
+    pub bucket: ExpiryBucket,
+    pub cost_basis: f64,
+    pub market_value: f64,
+    pub to_unwind: usize,
+    pub to_redeem: usize,
+    pub positions: Vec<LadderPosition>,
+}
+
+/// Open positions by time to resolution, nearest first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExpiryLadder {
+    pub generated_at: DateTime<Utc>,
+    pub rungs: Vec<LadderRung>,
+}
+
+/// Build the expiry ladder; `mark` gives the current price of an outcome, `end_dates` the resolution date per market
+pub fn expiry_ladder(
+    positions: &[PaperPosition],
+    mark: impl Fn(&str, TokenType) -> Option<f64>,
+    end_dates: &FxHashMap<String, DateTime<Utc>>,
+    analyzer: &CarryAnalyzer,
+    now: DateTime<Utc>,
+) -> ExpiryLadder {
+    let mut rungs: FxHashMap<ExpiryBucket, Vec<LadderPosition>> = FxHashMap::default();
+    for position in positions {
+        let end_date = end_dates.get(&position.market_id).copied();
+        let days = end_date.map(|end| (end - now).num_seconds() as f64 / 86400.0);
+        let price = mark(&position.market_id, position.token_type).unwrap_or(position.avg_price);
+
+        let bucket = ExpiryBucket::for_days(days);
+        let carry = days
+            .filter(|d| *d > 0.0)
+            .map(|d| analyzer.analyze_position(position, price, None, d));
+        let action = match (&bucket, &carry) {
+            (ExpiryBucket::Expired, _) => LadderAction::Redeem,
+            (_, Some(analysis)) if analysis.decision == HoldDecision::Unwind => LadderAction::Unwind,
+            _ => LadderAction::Hold,
+        };
+
+        rungs.entry(bucket).or_default().push(LadderPosition {
+            market_id: position.market_id.clone(),
+            question: position.question.clone(),
+            token_type: position.token_type,
+            quantity: position.quantity,
+            avg_price: position.avg_price,
+            mark: price,
+            end_date,
+            days_to_resolution: days,
+            carry: carry.map(|c| c.carry),
+            action,
+        });
+    }
+
+    let mut rungs: Vec<LadderRung> = rungs
+        .into_iter()
+        .map(|(bucket, mut positions)| {
+            positions.sort_by(|a, b| a.end_date.cmp(&b.end_date).then_with(|| a.market_id.cmp(&b.market_id)));
+            LadderRung {
+                bucket,
+                cost_basis: positions.iter().map(|p| p.quantity * p.avg_price).sum(),
+                market_value: positions.iter().map(|p| p.quantity * p.mark).sum(),
+                to_unwind: positions.iter().filter(|p| p.action == LadderAction::Unwind).count(),
+                to_redeem: positions.iter().filter(|p| p.action == LadderAction::Redeem).count(),
+                positions,
+            }
+        })
+        .collect();
+    rungs.sort_by_key(|r| r.bucket);
+
+    ExpiryLadder { generated_at: now, rungs }
+}
+
+/// One position trim of a liquidation plan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LiquidationTrim {
+    pub market_id: String,
+    pub token_type: TokenType,
+    pub quantity: f64,        // Share da vendere
+    pub price: f64,           // Bid di uscita
+    pub freed_capital: f64,   // Incasso netto dei costi di uscita
+    pub exit_cost: f64,
+    pub forgone_edge: f64,    // Carry atteso a cui si rinuncia vendendo
+    pub edge_per_dollar: f64, // Carry per dollaro liberato: si taglia prima il più basso
+    pub full_exit: bool,
+}
+
+/// Trims that free the requested capital at the lowest forgone edge
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LiquidationPlan {
+    pub capital_needed: f64,
+    pub capital_freed: f64,
+    pub shortfall: f64, // Capitale che nemmeno liquidando tutto si riesce a liberare
+    pub exit_costs: f64,
+    pub forgone_edge: f64,
+    pub trims: Vec<LiquidationTrim>,
+}
+
+impl LiquidationPlan {
+    pub fn is_sufficient(&self) -> bool {
+        self.shortfall <= 1e-9
+    }
+}
+
+/// Plan trims freeing `capital_needed`, cutting first the positions with the lowest expected edge per freed dollar
+///
+/// `fair` gives the fair probability of an outcome (the mark is used when unknown); positions in `protected` markets are never trimmed.
+#[allow(clippy::too_many_arguments)]
+pub fn plan_liquidation(
+    positions: &[PaperPosition],
+    capital_needed: f64,
+    mark: impl Fn(&str, TokenType) -> Option<f64>,
+    fair: impl Fn(&str, TokenType) -> Option<f64>,
+    end_dates: &FxHashMap<String, DateTime<Utc>>,
+    protected: &[&str],
+    analyzer: &CarryAnalyzer,
+    now: DateTime<Utc>,
+) -> LiquidationPlan {
+    // Per ogni posizione: (posizione, bid, incasso netto per share, carry per share)
+    let mut candidates: Vec<(&PaperPosition, f64, f64, f64)> = positions
+        .iter()
+        .filter(|p| p.quantity > 0.0 && !protected.contains(&p.market_id.as_str()))
+        .filter_map(|p| {
+            let bid = mark(&p.market_id, p.token_type)?;
+            // Senza data di risoluzione il carry non è scontato
+            let days = end_dates
+                .get(&p.market_id)
+                .map(|end| (*end - now).num_seconds() as f64 / 86400.0)
+                .unwrap_or(0.0);
+            let probability = fair(&p.market_id, p.token_type).unwrap_or(bid);
+            let per_share = analyzer.analyze_leg(1.0, probability, bid, days);
+            (per_share.unwind_value > 0.0).then_some((p, bid, per_share.unwind_value, per_share.carry))
+        })
+        .collect();
+    candidates.sort_by(|a, b| {
+        (a.3 / a.2)
+            .total_cmp(&(b.3 / b.2))
+            .then_with(|| a.0.market_id.cmp(&b.0.market_id))
+    });
+
+    let mut remaining = capital_needed.max(0.0);
+    let mut trims = Vec::new();
+    for (position, bid, freed_per_share, carry_per_share) in candidates {
+        if remaining <= 1e-9 {
+            break;
+        }
+        let quantity = position.quantity.min(remaining / freed_per_share);
+        let freed_capital = quantity * freed_per_share;
+        remaining -= freed_capital;
+        trims.push(LiquidationTrim {
+            market_id: position.market_id.clone(),
+            token_type: position.token_type,
+            quantity,
+            price: bid,
+            freed_capital,
+            exit_cost: quantity * bid - freed_capital,
+            forgone_edge: quantity * carry_per_share,
+            edge_per_dollar: carry_per_share / freed_per_share,
+            full_exit: quantity >= position.quantity - 1e-9,
+        });
+    }
+
+    LiquidationPlan {
+        capital_needed,
+        capital_freed: trims.iter().map(|t| t.freed_capital).sum(),
+        shortfall: remaining.max(0.0),
+        exit_costs: trims.iter().map(|t| t.exit_cost).sum(),
+        forgone_edge: trims.iter().map(|t| t.forgone_edge).sum(),
+        trims,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_rolling_trade_metrics() {
+        let mut metrics = TradeMetricsAggregator::new(3);
+        assert_eq!(metrics.rolling(), TradeMetrics::default());
+
+        // Il primo trade (perdita) esce dalla finestra
+        for (profit, notional) in [(-5.0, 100.0), (2.0, 100.0), (4.0, 200.0), (-1.0, 50.0)] {
+            metrics.record(profit, notional);
+        }
+        let rolling = metrics.rolling();
+        assert_eq!(rolling.trades, 3);
+        assert!((rolling.win_rate - 2.0 / 3.0).abs() < 1e-9);
+        assert!((rolling.avg_edge - (0.02 + 0.02 - 0.02) / 3.0).abs() < 1e-9);
+        assert!((rolling.expectancy - 5.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_pair_carry_decision() {
+        let analyzer = CarryAnalyzer::new(0.05, 0.0);
+
+        // Coppia quotata 0.97 a 30 giorni: ~3% in un mese batte il 5% annuo
+        let hold = analyzer.analyze_pair(100.0, 0.48, 0.49, 30.0);
+        assert_eq!(hold.decision, HoldDecision::HoldToResolution);
+        assert!(hold.annualized_carry_pct > 5.0);
+
+        // Coppia rivendibile a 0.995 con un anno davanti: meglio liberare capitale
+        let unwind = analyzer.analyze_pair(100.0, 0.50, 0.495, 365.0);
+        assert_eq!(unwind.decision, HoldDecision::Unwind);
+        assert!(unwind.carry < 0.0);
+    }
+
+    #[test]
+    fn test_leg_carry_uses_fair_probability() {
+        let analyzer = CarryAnalyzer::default();
+        let rich = analyzer.analyze_leg(10.0, 0.80, 0.60, 10.0);
+        assert_eq!(rich.decision, HoldDecision::HoldToResolution);
+
+        let cheap = analyzer.analyze_leg(10.0, 0.40, 0.60, 10.0);
+        assert_eq!(cheap.decision, HoldDecision::Unwind);
+    }
+
+    #[test]
+    fn test_expiry_ladder() {
+        let now = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let position = |market_id: &str, avg_price: f64| PaperPosition {
+            market_id: market_id.to_string(),
+            question: String::new(),
+            token_type: TokenType::Yes,
+            quantity: 100.0,
+            avg_price,
+            opened_at: now,
+            source: Default::default(),
+            stop_loss: None,
+            take_profit: None,
+        };
+        let positions = vec![position("expired", 0.9), position("soon", 0.5), position("far", 0.5), position("undated", 0.5)];
+        let end_dates: FxHashMap<String, DateTime<Utc>> = [
+            ("expired", now - Duration::hours(2)),
+            ("soon", now + Duration::hours(12)),
+            ("far", now + Duration::days(365)),
+        ]
+        .into_iter()
+        .map(|(id, end)| (id.to_string(), end))
+        .collect();
+        let mark = |market_id: &str, _: TokenType| (market_id != "undated").then_some(0.6);
+
+        let ladder = expiry_ladder(&positions, mark, &end_dates, &CarryAnalyzer::default(), now);
+        let buckets: Vec<ExpiryBucket> = ladder.rungs.iter().map(|r| r.bucket).collect();
+        assert_eq!(buckets, vec![ExpiryBucket::Expired, ExpiryBucket::Within1d, ExpiryBucket::Beyond30d, ExpiryBucket::Unknown]);
+
+        let action = |i: usize| ladder.rungs[i].positions[0].action;
+        assert_eq!(action(0), LadderAction::Redeem);
+        assert_eq!(action(1), LadderAction::Hold);
+        // Un anno di capitale immobilizzato al 5% vale più della fee di uscita
+        assert_eq!(action(2), LadderAction::Unwind);
+        assert_eq!(action(3), LadderAction::Hold);
+        assert_eq!(ladder.rungs[0].to_redeem, 1);
+        assert!((ladder.rungs[1].market_value - 60.0).abs() < 1e-9);
+        assert!((ladder.rungs[3].market_value - 50.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_liquidation_plan_trims_lowest_edge_first() {
+        let now = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let position = |market_id: &str| PaperPosition {
+            market_id: market_id.to_string(),
+            question: String::new(),
+            token_type: TokenType::Yes,
+            quantity: 100.0,
+            avg_price: 0.5,
+            opened_at: now,
+            source: Default::default(),
+            stop_loss: None,
+            take_profit: None,
+        };
+        let positions = vec![position("rich"), position("flat"), position("target")];
+        let mark = |_: &str, _: TokenType| Some(0.5);
+        // "rich" vale 0.7 alla risoluzione, "flat" è prezzato correttamente
+        let fair = |market_id: &str, _: TokenType| (market_id == "rich").then_some(0.7);
+        let analyzer = CarryAnalyzer::new(0.05, 0.01);
+
+        let plan = plan_liquidation(&positions, 70.0, mark, fair, &FxHashMap::default(), &["target"], &analyzer, now);
+        assert!(plan.is_sufficient());
+        assert_eq!(plan.trims.iter().map(|t| t.market_id.as_str()).collect::<Vec<_>>(), vec!["flat", "rich"]);
+        assert!(plan.trims[0].full_exit && !plan.trims[1].full_exit);
+        assert!((plan.capital_freed - 70.0).abs() < 1e-9);
+        // Uscita: 1% del notional venduto
+        assert!((plan.exit_costs - 70.0 / 0.99 * 0.01).abs() < 1e-9);
+        assert!(plan.trims[0].forgone_edge > 0.0 && plan.trims[1].edge_per_dollar > plan.trims[0].edge_per_dollar);
+
+        // Le posizioni non bastano: resta uno shortfall
+        let plan = plan_liquidation(&positions, 500.0, mark, fair, &FxHashMap::default(), &["target"], &analyzer, now);
+        assert!(!plan.is_sufficient());
+        assert!((plan.shortfall - (500.0 - 2.0 * 49.5)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_capital_efficiency_metrics() {
+        let mut efficiency = CapitalEfficiency::new();
+        // Capitale impiegato solo in metà dei campioni
+        for deployed in [1000.0, 0.0, 1000.0, 0.0] {
+            efficiency.sample_deployed(deployed);
+        }
+        efficiency.record_trade(1000.0, 10.0);
+        efficiency.record_trade(-1000.0, 10.0);
+
+        let metrics = efficiency.metrics(10_000.0);
+        assert!((metrics.average_deployed - 500.0).abs() < 1e-9);
+        assert!((metrics.utilization_pct - 5.0).abs() < 1e-9);
+        assert!((metrics.turnover - 0.2).abs() < 1e-9);
+        assert!((metrics.return_on_deployed_pct - 4.0).abs() < 1e-9);
+        assert!((metrics.roi_pct - 0.2).abs() < 1e-9);
+
+        // Nessun campione: niente divisioni per zero
+        let empty = CapitalEfficiency::new().metrics(0.0);
+        assert_eq!(empty.return_on_deployed_pct, 0.0);
+    }
+}
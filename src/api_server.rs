@@ -2,65 +2,84 @@
 //! API Server per Dashboard HFT Polymarket
 //! Fornisce endpoint REST e WebSocket per gestione bot e paper trading
 
-use actix_web::{web, App, HttpServer, HttpResponse, Responder, Result, Error};
+use actix_web::{web, App, HttpRequest, HttpServer, HttpResponse, Responder, Result};
 use actix_cors::Cors;
-use actix_files::{Files, NamedFile};
-use actix_ws::{Message, ProtocolError};
+use actix_files::NamedFile;
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 use std::sync::{Arc, Mutex};
 use std::collections::HashMap;
-use rand::seq::{IteratorRandom, SliceRandom};
+use futures_util::StreamExt;
+use tokio::sync::broadcast::{self, error::RecvError};
+use fxhash::FxHashMap;
+use rand::seq::IteratorRandom;
+use crate::accounts::{Accounts, AuthError, Permission, Role, Session, UserInfo, BOOTSTRAP_TOKEN_ENV};
+use crate::audit::{AuditLog, AuditRecord, ORDER_AUDIT_PATH};
+use crate::analytics::{
+    expiry_ladder, plan_liquidation, CapitalEfficiencyMetrics, CarryAnalyzer, ExpiryLadder, LiquidationPlan, TradeMetrics,
+    TradeMetricsAggregator, TRADE_METRICS_WINDOW,
+};
+use crate::arbitrage::ArbitrageDetector;
+use crate::backtest::{what_if, BacktestConfig, WhatIfReport, WhatIfRequest};
+use crate::demo::DemoProgress;
+use crate::clustering::{cluster_trades, ClusterReport, TradeAlert, TradeAnomalyMonitor, TradeSample};
+use crate::market::{DataSource, PinnedMarket, PriceSnapshot, Watchlist};
+pub use crate::paper::{BotState, MarketInfo, SimulatedTrade};
+use crate::paper::{ExitPolicy, PaperBroker, PaperOrder, PaperPosition, PaperRiskLimits, TradeSource};
+use crate::polymarket_api::{GammaApiClient, PolymarketApiConfig};
+use crate::relations::{MarketRelation, RelationBook, RELATIONS_PATH};
+use crate::resolution::{PositionTracker, ResolutionMonitor};
+use crate::signals::{verify_signature, ExternalSignal, SignalBook, SIGNAL_SECRET_ENV, SIGNATURE_HEADER};
+use crate::snapshot::{PortfolioSnapshot, SnapshotDiff, SNAPSHOT_SECRET_ENV};
+use crate::onboarding::{MarketOnboarding, OnboardingConfig, OnboardingRecord, OnboardingStatus, ONBOARDING_PATH};
+use crate::storage::{open_storage, validate_run_id, FlatFileStorage, SharedStorage, StepHistoryConfig, StepQuery, StorageConfig, MAX_STEP_QUERY_LIMIT, STORAGE_DIR};
+use crate::symbols::{SymbolMapping, SymbolRegistry, SYMBOL_REGISTRY_PATH};
+use crate::telemetry::DashboardMetrics;
+use crate::types::{self, BotConfig, Direction, EventData, MarketData, TokenType};
+use crate::venues::{VenueComparison, VenueQuote, VenueSpreadSample, VenueSpreadSummary, POLYMARKET_VENUE};
+use crate::clock::Stopwatch;
+use crate::{HftArbitrageBot, OPPORTUNITY_STREAM_CAPACITY};
 
 
+/// Directory da cui servire il frontend al posto degli asset incorporati (sviluppo)
+pub const FRONTEND_DIR_ENV: &str = "DASHBOARD_FRONTEND_DIR";
 
+/// Asset del dashboard incorporati nel binario in fase di build
+#[derive(rust_embed::Embed)]
+#[folder = "frontend/"]
+struct DashboardAssets;
 
+/// File di persistenza della watchlist
+pub const WATCHLIST_PATH: &str = "./data/watchlist.json";
 
+/// Snapshot per mercato conservati nella finestra registrata (usata da /api/whatif)
+pub const RECORDED_WINDOW_LEN: usize = 500;
 
+/// Finestra recente di prezzi osservati dal bot, per mercato
+pub type RecordedWindow = FxHashMap<String, Vec<PriceSnapshot>>;
 
-
-
-
-/// Stato globale del bot per dashboard
+/// Evento con i suoi mercati, per il raggruppamento in dashboard
 #[derive(Clone, Serialize, Deserialize)]
-pub struct BotState {
-    pub running: bool,
-    pub balance: f64,
-    pub initial_balance: f64,
-    pub total_pnl: f64,
-    pub win_rate: f64,
-    pub total_trades: usize,
-    pub profitable_trades: usize,
-    pub last_update: DateTime<Utc>,
-}
-
-/// Trade simulato con dati reali per backtesting
-#[derive(Clone, Serialize, Deserialize, Debug)]
-pub struct SimulatedTrade {
+pub struct EventInfo {
     pub id: String,
-    pub market_id: String,
-    pub question: String,
-    pub action: String, // "BUY_YES", "BUY_NO", "SELL_YES", "SELL_NO"
-    pub price: f64,
-    pub quantity: f64,
-    pub amount: f64,
-    pub timestamp: DateTime<Utc>,
-    pub status: String, // "PENDING", "FILLED", "CANCELLED"
-    pub pnl: f64,
-    pub arbitrage_profit: f64, // Profitto di arbitraggio simulato
+    pub title: String,
+    pub neg_risk: bool,
+    pub tags: Vec<String>,
+    pub end_date: Option<DateTime<Utc>>,
+    pub market_ids: Vec<String>,
 }
 
-/// Informazioni mercato reale
-#[derive(Clone, Serialize, Deserialize)]
-pub struct MarketInfo {
-    pub id: String,
-    pub question: String,
-    pub yes_price: f64,
-    pub no_price: f64,
-    pub yes_liquidity: f64,
-    pub no_liquidity: f64,
-    pub volume_24h: f64,
-    pub timestamp: DateTime<Utc>,
+impl From<&EventData> for EventInfo {
+    fn from(event: &EventData) -> Self {
+        EventInfo {
+            id: event.id.clone(),
+            title: event.title.clone(),
+            neg_risk: event.neg_risk,
+            tags: event.tags.clone(),
+            end_date: event.end_date,
+            market_ids: event.markets.iter().map(|m| m.id.clone()).collect(),
+        }
+    }
 }
 
 /// Dati live per WebSocket
@@ -88,11 +107,42 @@ pub struct AppState {
     pub bot_state: Arc<Mutex<BotState>>,
     pub trades: Arc<Mutex<Vec<SimulatedTrade>>>,
     pub markets: Arc<Mutex<Vec<MarketInfo>>>,
+    pub events: Arc<Mutex<Vec<EventInfo>>>, // Eventi Gamma che raggruppano i mercati
     pub clients: Arc<Mutex<HashMap<String, bool>>>, // WebSocket clients
+    pub watchlist: Arc<Mutex<Watchlist>>, // Mercati pinnati
+    pub broker: Arc<Mutex<PaperBroker>>, // Ledger condiviso bot + trade manuali
+    pub recorded_window: Arc<Mutex<RecordedWindow>>, // Prezzi visti dal bot negli ultimi tick
+    pub audit_log: AuditLog, // Audit trail degli ordini inviati al CLOB
+    pub accounts: Accounts, // Utenti della dashboard e sessioni attive
+    pub signals: SignalBook, // Segnali esterni ricevuti via webhook, letti dalle strategie
+    pub signal_secret: Option<String>, // Secret HMAC del webhook; senza, /api/signals è disabilitato
+    pub venue_comparison: Arc<Mutex<VenueComparison>>, // Spread dello stesso evento tra venue
+    pub data_source: Arc<Mutex<Option<DataSource>>>, // Sorgente del feed mercati; None finché non viene scelta
+    pub trade_monitor: Arc<Mutex<TradeAnomalyMonitor>>, // Alert sui cluster di trade anomali
+    pub metrics: DashboardMetrics, // Metriche Prometheus etichettate per strategia, categoria e venue
+    pub storage: SharedStorage, // Backend di persistenza di account, trade e snapshot degli step
+    pub feed_step_history: StepHistoryConfig, // Step del feed mercati salvati nello storage; spento salvo configurazione
+    pub symbols: Arc<Mutex<SymbolRegistry>>, // Identificativi per venue dei mercati, condivisi con i componenti multi-venue
+    pub onboarding: Arc<Mutex<MarketOnboarding>>, // Stato di onboarding dei mercati scoperti dal feed
+    pub relations: Arc<Mutex<RelationBook>>, // Relazioni logiche tra mercati definite dall'utente
+    pub snapshot_secret: Option<String>, // Secret HMAC degli snapshot di portafoglio; senza, non sono firmati
+    pub demo: Arc<Mutex<Option<DemoProgress>>>, // Passo corrente dello scenario demo, se avviato con `demo`
+    pub opportunities: broadcast::Sender<types::ArbitrageOpportunity>, // Opportunità rilevate dai bot del server, per i client in streaming
+}
+
+impl Default for AppState {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl AppState {
     pub fn new() -> Self {
+        Self::with_storage(Arc::new(FlatFileStorage::new(STORAGE_DIR)))
+    }
+
+    /// Stato con gli account salvati nel backend indicato
+    pub fn with_storage(storage: SharedStorage) -> Self {
         let initial_balance = 10000.0;
         AppState {
             bot_state: Arc::new(Mutex::new(BotState {
@@ -107,11 +157,61 @@ impl AppState {
             })),
             trades: Arc::new(Mutex::new(Vec::new())),
             markets: Arc::new(Mutex::new(Vec::new())),
+            events: Arc::new(Mutex::new(Vec::new())),
             clients: Arc::new(Mutex::new(HashMap::new())),
+            watchlist: Arc::new(Mutex::new(Watchlist::load(WATCHLIST_PATH).unwrap_or_else(|e| {
+                eprintln!("⚠️  Watchlist non caricata: {}", e);
+                Watchlist::default()
+            }))),
+            broker: Arc::new(Mutex::new(PaperBroker::new(PaperRiskLimits::default()))),
+            recorded_window: Arc::new(Mutex::new(RecordedWindow::default())),
+            audit_log: AuditLog::new(ORDER_AUDIT_PATH),
+            accounts: Accounts::new(storage.clone())
+                .with_bootstrap_token(std::env::var(BOOTSTRAP_TOKEN_ENV).ok()),
+            signals: SignalBook::new(),
+            signal_secret: std::env::var(SIGNAL_SECRET_ENV).ok().filter(|s| !s.is_empty()),
+            venue_comparison: Arc::new(Mutex::new(VenueComparison::new())),
+            data_source: Arc::new(Mutex::new(None)),
+            trade_monitor: Arc::new(Mutex::new(TradeAnomalyMonitor::new())),
+            metrics: DashboardMetrics::new(),
+            symbols: Arc::new(Mutex::new(SymbolRegistry::load(SYMBOL_REGISTRY_PATH).unwrap_or_else(|e| {
+                eprintln!("⚠️  Registro dei simboli non caricato: {}", e);
+                SymbolRegistry::default()
+            }))),
+            onboarding: Arc::new(Mutex::new(MarketOnboarding::load(ONBOARDING_PATH).unwrap_or_else(|e| {
+                eprintln!("⚠️  Stato di onboarding non caricato: {}", e);
+                MarketOnboarding::default()
+            }))),
+            relations: Arc::new(Mutex::new(RelationBook::load(RELATIONS_PATH).unwrap_or_else(|e| {
+                eprintln!("⚠️  Relazioni tra mercati non caricate: {}", e);
+                RelationBook::default()
+            }))),
+            snapshot_secret: std::env::var(SNAPSHOT_SECRET_ENV).ok().filter(|s| !s.is_empty()),
+            demo: Arc::new(Mutex::new(None)),
+            opportunities: broadcast::channel(OPPORTUNITY_STREAM_CAPACITY).0,
+            storage,
+            feed_step_history: StepHistoryConfig::feed_from_env().unwrap_or_else(|e| {
+                eprintln!("⚠️  Storico degli step del feed disattivato: {}", e);
+                StepHistoryConfig::dashboard_feed()
+            }),
         }
     }
 }
 
+/// Performance del paper trading, incluse le metriche di efficienza del capitale
+#[derive(Clone, Serialize)]
+pub struct PerformanceReport {
+    pub initial_balance: f64,
+    pub balance: f64,
+    pub total_pnl: f64,
+    pub total_roi: f64,
+    pub win_rate: f64,
+    pub total_trades: usize,
+    pub deployed_capital: f64, // Capitale attualmente impiegato in posizioni aperte
+    pub capital_efficiency: CapitalEfficiencyMetrics,
+    pub trade_metrics: TradeMetrics, // Win rate (0-1), edge ed expectancy sugli ultimi trade chiusi
+}
+
 /// Request payload per avviare/fermare bot
 #[derive(Deserialize)]
 pub struct BotControlRequest {
@@ -120,6 +220,73 @@ pub struct BotControlRequest {
     pub trade_frequency: Option<u64>, // Secondi tra trade
 }
 
+/// Request payload per pinnare un mercato
+#[derive(Deserialize)]
+pub struct PinMarketRequest {
+    pub market_id: String,
+    pub min_profit_override: Option<f64>, // Soglia rilassata opzionale
+}
+
+/// Request payload per trade manuale di paper trading
+#[derive(Deserialize)]
+pub struct ManualTradeRequest {
+    pub market_id: String,
+    pub side: String,   // "YES" o "NO"
+    pub action: String, // "open" o "close"
+    pub size: f64,      // Numero di share
+    pub limit_price: Option<f64>,
+    #[serde(default)]
+    pub liquidate: bool, // Se il balance non basta, riduce le posizioni a minor edge invece di rifiutare
+}
+
+/// Query per l'anteprima del piano di liquidazione
+#[derive(Deserialize)]
+pub struct LiquidationQuery {
+    pub amount: f64,               // Capitale da liberare, in USDC
+    pub market_id: Option<String>, // Mercato del nuovo trade, escluso dai tagli
+}
+
+/// Request payload per i livelli di uscita di una posizione (None rimuove il livello)
+#[derive(Deserialize)]
+pub struct ExitLevelsRequest {
+    pub market_id: String,
+    pub side: String, // "YES" o "NO"
+    pub stop_loss: Option<f64>,
+    pub take_profit: Option<f64>,
+}
+
+/// Request payload per la policy di uscita di default di una strategia
+#[derive(Deserialize)]
+pub struct ExitPolicyRequest {
+    pub source: TradeSource,
+    #[serde(flatten)]
+    pub policy: ExitPolicy,
+}
+
+/// Request payload per il confronto di due snapshot di portafoglio
+#[derive(Deserialize)]
+pub struct SnapshotDiffRequest {
+    pub from: PortfolioSnapshot,
+    pub to: PortfolioSnapshot,
+}
+
+/// Request payload per il login
+#[derive(Deserialize)]
+pub struct LoginRequest {
+    pub username: String,
+    pub password: String,
+}
+
+/// Request payload per creare un utente
+#[derive(Deserialize)]
+pub struct CreateUserRequest {
+    pub username: String,
+    pub password: String,
+    pub role: Role,
+    #[serde(default)]
+    pub bootstrap_token: Option<String>, // Secret monouso, richiesto solo per il primo admin
+}
+
 /// Response payload
 #[derive(Serialize)]
 pub struct ApiResponse<T> {
@@ -146,8 +313,40 @@ impl<T> ApiResponse<T> {
     }
 }
 
+/// Token dall'header `Authorization: Bearer <token>`
+fn bearer_token(req: &HttpRequest) -> Option<&str> {
+    req.headers()
+        .get(actix_web::http::header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+}
+
+/// Risposta HTTP per un rifiuto di autenticazione o di una modifica agli account
+fn auth_error_response(error: AuthError) -> HttpResponse {
+    match error {
+        AuthError::Unauthenticated => HttpResponse::Unauthorized().json(ApiResponse::<()>::error("Login required".to_string())),
+        AuthError::Forbidden => HttpResponse::Forbidden().json(ApiResponse::<()>::error("Operation not allowed".to_string())),
+        AuthError::RateLimited => HttpResponse::TooManyRequests().json(ApiResponse::<()>::error("Too many failed logins, retry later".to_string())),
+        AuthError::Invalid(e) => HttpResponse::BadRequest().json(ApiResponse::<()>::error(e)),
+        AuthError::Storage(e) => HttpResponse::InternalServerError().json(ApiResponse::<()>::error(e)),
+    }
+}
+
+/// Verifica il permesso del chiamante; senza account configurati la dashboard resta aperta
+async fn require(data: &AppState, req: &HttpRequest, permission: Permission) -> Result<Option<Session>, HttpResponse> {
+    data.accounts
+        .authorize(bearer_token(req), permission)
+        .await
+        .map_err(auth_error_response)
+}
+
 /// GET /api/status - Get bot status
-pub async fn get_bot_status(data: web::Data<AppState>) -> impl Responder {
+pub async fn get_bot_status(data: web::Data<AppState>, http: HttpRequest) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::View).await {
+        return response;
+    }
     let bot_state = data.bot_state.lock().unwrap();
     HttpResponse::Ok().json(ApiResponse::success(bot_state.clone()))
 }
@@ -155,8 +354,12 @@ pub async fn get_bot_status(data: web::Data<AppState>) -> impl Responder {
 /// POST /api/control - Control bot (start/stop)
 pub async fn control_bot(
     data: web::Data<AppState>,
+    http: HttpRequest,
     req: web::Json<BotControlRequest>
 ) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::Control).await {
+        return response;
+    }
     let mut bot_state = data.bot_state.lock().unwrap();
 
     match req.action.as_str() {
@@ -178,6 +381,13 @@ pub async fn control_bot(
                 data.bot_state.clone(),
                 data.trades.clone(),
                 data.markets.clone(),
+                data.watchlist.clone(),
+                data.onboarding.clone(),
+                data.broker.clone(),
+                data.recorded_window.clone(),
+                data.venue_comparison.clone(),
+                data.trade_monitor.clone(),
+                data.metrics.clone(),
                 req.trade_frequency.unwrap_or(30) // Default 30 secondi
             ));
 
@@ -191,25 +401,289 @@ pub async fn control_bot(
     }
 }
 
+/// Request payload per cambiare la sorgente dei mercati
+#[derive(Deserialize)]
+pub struct DataSourceRequest {
+    pub source: DataSource,
+}
+
+/// GET /api/data-source - Current market data source (null until one is chosen)
+pub async fn get_data_source(data: web::Data<AppState>, http: HttpRequest) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::View).await {
+        return response;
+    }
+    let source = *data.data_source.lock().unwrap();
+    HttpResponse::Ok().json(ApiResponse::success(source))
+}
+
+/// POST /api/data-source - Switch between simulated and live Polymarket data without a restart
+pub async fn set_data_source(
+    data: web::Data<AppState>,
+    http: HttpRequest,
+    req: web::Json<DataSourceRequest>
+) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::Configure).await {
+        return response;
+    }
+    if req.source == DataSource::Scripted {
+        return HttpResponse::BadRequest().json(ApiResponse::<()>::error("The scripted source is only driven by the demo command".to_string()));
+    }
+    let previous = data.data_source.lock().unwrap().replace(req.source);
+
+    // Il primo cambio avvia il feed mercati; i successivi vengono letti dal feed stesso
+    if previous.is_none() {
+        tokio::spawn(run_market_feed(
+            data.data_source.clone(),
+            data.markets.clone(),
+            data.events.clone(),
+            data.symbols.clone(),
+            data.onboarding.clone(),
+            data.watchlist.clone(),
+            data.signals.clone(),
+            data.opportunities.clone(),
+            data.storage.clone(),
+            data.feed_step_history.clone(),
+        ));
+    }
+    HttpResponse::Ok().json(ApiResponse::success(req.source))
+}
+
+/// GET /api/demo - Current step of the demo scenario (null outside the demo command)
+pub async fn get_demo(data: web::Data<AppState>, http: HttpRequest) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::View).await {
+        return response;
+    }
+    let demo = data.demo.lock().unwrap().clone();
+    HttpResponse::Ok().json(ApiResponse::success(demo))
+}
+
+/// GET /api/opportunities/stream - WebSocket pushing every detected opportunity as JSON
+pub async fn stream_opportunities(data: web::Data<AppState>, http: HttpRequest, body: web::Payload) -> Result<HttpResponse> {
+    if let Err(response) = require(&data, &http, Permission::View).await {
+        return Ok(response);
+    }
+    let (response, mut session, mut messages) = actix_ws::handle(&http, body)?;
+    let mut opportunities = data.opportunities.subscribe();
+    let client_id = uuid::Uuid::new_v4().to_string();
+    data.clients.lock().unwrap().insert(client_id.clone(), true);
+    let clients = data.clients.clone();
+
+    actix_web::rt::spawn(async move {
+        loop {
+            tokio::select! {
+                received = opportunities.recv() => match received {
+                    Ok(opportunity) => {
+                        let Ok(json) = serde_json::to_string(&opportunity) else { continue };
+                        if session.text(json).await.is_err() {
+                            break;
+                        }
+                    }
+                    // Client lento: le opportunità più vecchie sono perse, si riprende dalle recenti
+                    Err(RecvError::Lagged(skipped)) => eprintln!("⚠️  Stream opportunità: client {} ne ha perse {}", client_id, skipped),
+                    Err(RecvError::Closed) => break,
+                },
+                message = messages.next() => match message {
+                    Some(Ok(actix_ws::Message::Ping(bytes))) => {
+                        if session.pong(&bytes).await.is_err() {
+                            break;
+                        }
+                    }
+                    Some(Ok(actix_ws::Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => {}
+                },
+            }
+        }
+        clients.lock().unwrap().remove(&client_id);
+        let _ = session.close(None).await;
+    });
+    Ok(response)
+}
+
 /// GET /api/trades - Get all trades
-pub async fn get_trades(data: web::Data<AppState>) -> impl Responder {
+pub async fn get_trades(data: web::Data<AppState>, http: HttpRequest) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::View).await {
+        return response;
+    }
     let trades = data.trades.lock().unwrap();
     HttpResponse::Ok().json(ApiResponse::success(trades.clone()))
 }
 
 /// GET /api/markets - Get market data
-pub async fn get_markets(data: web::Data<AppState>) -> impl Responder {
+pub async fn get_markets(data: web::Data<AppState>, http: HttpRequest) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::View).await {
+        return response;
+    }
     let markets = data.markets.lock().unwrap();
     HttpResponse::Ok().json(ApiResponse::success(markets.clone()))
 }
 
+/// GET /api/events - Events with the ids of their markets
+pub async fn get_events(data: web::Data<AppState>, http: HttpRequest) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::View).await {
+        return response;
+    }
+    let events = data.events.lock().unwrap();
+    HttpResponse::Ok().json(ApiResponse::success(events.clone()))
+}
+
+/// GET /api/performance - Get PnL and capital efficiency metrics
+pub async fn get_performance(data: web::Data<AppState>, http: HttpRequest) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::View).await {
+        return response;
+    }
+    let trade_metrics = closed_trade_metrics(&data.trades.lock().unwrap());
+    let bot_state = data.bot_state.lock().unwrap();
+    let broker = data.broker.lock().unwrap();
+
+    let total_roi = if bot_state.initial_balance > 0.0 {
+        (bot_state.total_pnl / bot_state.initial_balance) * 100.0
+    } else {
+        0.0
+    };
+
+    HttpResponse::Ok().json(ApiResponse::success(PerformanceReport {
+        initial_balance: bot_state.initial_balance,
+        balance: bot_state.balance,
+        total_pnl: bot_state.total_pnl,
+        total_roi,
+        win_rate: bot_state.win_rate,
+        total_trades: bot_state.total_trades,
+        deployed_capital: broker.deployed_capital(),
+        capital_efficiency: broker.efficiency.metrics(bot_state.initial_balance),
+        trade_metrics,
+    }))
+}
+
+/// Trade metrics of the filled paper trades that realized PnL, over the rolling window
+fn closed_trade_metrics(trades: &[SimulatedTrade]) -> TradeMetrics {
+    let mut metrics = TradeMetricsAggregator::new(TRADE_METRICS_WINDOW);
+    for trade in trades.iter().filter(|t| t.status == "FILLED" && t.pnl != 0.0) {
+        metrics.record(trade.pnl, trade.amount);
+    }
+    metrics.rolling()
+}
+
+/// GET /api/analytics/clusters - Trade clusters by detector, category and size, with anomalous ones flagged
+pub async fn get_trade_clusters(data: web::Data<AppState>, http: HttpRequest) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::View).await {
+        return response;
+    }
+    let report = analyze_trades(&data.trades, &data.markets, &data.trade_monitor);
+    HttpResponse::Ok().json(ApiResponse::success(report))
+}
+
+/// GET /api/alerts - Alerts raised on anomalous trade clusters, newest first
+pub async fn get_alerts(data: web::Data<AppState>, http: HttpRequest) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::View).await {
+        return response;
+    }
+    let alerts: Vec<TradeAlert> = data.trade_monitor.lock().unwrap().alerts.iter().rev().cloned().collect();
+    HttpResponse::Ok().json(ApiResponse::success(alerts))
+}
+
+/// GET /api/risk/drawdowns - Drawdown episodes of the realized PnL with recovery statistics
+pub async fn get_drawdowns(data: web::Data<AppState>, http: HttpRequest) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::View).await {
+        return response;
+    }
+    let report = data.broker.lock().unwrap().drawdowns.report(5);
+    HttpResponse::Ok().json(ApiResponse::success(report))
+}
+
+/// GET /metrics - Prometheus exposition of the paper ledger, labeled by strategy, category and venue
+pub async fn get_metrics(data: web::Data<AppState>, http: HttpRequest) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::View).await {
+        return response;
+    }
+    let balance = data.bot_state.lock().unwrap().balance;
+    let positions = data.broker.lock().unwrap().open_positions();
+    let markets = data.markets.lock().unwrap().clone();
+    data.metrics.refresh(&positions, &markets, balance);
+
+    match data.metrics.encode() {
+        Ok(text) => HttpResponse::Ok().content_type("text/plain; version=0.0.4").body(text),
+        Err(e) => HttpResponse::InternalServerError().json(ApiResponse::<()>::error(e)),
+    }
+}
+
+/// GET /api/metrics/grafana-dashboard - Grafana dashboard JSON for the registered metrics (provisioning or import)
+pub async fn get_grafana_dashboard(data: web::Data<AppState>, http: HttpRequest) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::View).await {
+        return response;
+    }
+    HttpResponse::Ok().json(data.metrics.grafana_dashboard())
+}
+
+/// GET /api/steps?from=&to=&run_id=&limit= - Persisted step series (opportunities, trades, profit, capital, latency)
+///
+/// At most `MAX_STEP_QUERY_LIMIT` steps per request, also when no limit is given.
+pub async fn get_steps(data: web::Data<AppState>, http: HttpRequest, query: web::Query<StepQuery>) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::View).await {
+        return response;
+    }
+    let mut query = query.into_inner();
+    query.limit = Some(query.limit.unwrap_or(MAX_STEP_QUERY_LIMIT).min(MAX_STEP_QUERY_LIMIT));
+    if let Some(Err(e)) = query.run_id.as_deref().map(validate_run_id) {
+        return HttpResponse::BadRequest().json(ApiResponse::<()>::error(e));
+    }
+    match data.storage.query_steps(&query).await {
+        Ok(steps) => HttpResponse::Ok().json(ApiResponse::success(steps)),
+        Err(e) => HttpResponse::InternalServerError().json(ApiResponse::<()>::error(e)),
+    }
+}
+
+/// GET /api/whatif/window - Recorded window, for what-if runs in the browser (wasm build)
+pub async fn get_recorded_window(data: web::Data<AppState>, http: HttpRequest) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::View).await {
+        return response;
+    }
+    let window = data.recorded_window.lock().unwrap().clone();
+    HttpResponse::Ok().json(ApiResponse::success(window))
+}
+
+/// POST /api/whatif - Re-run the recorded window under a modified configuration
+pub async fn run_what_if(
+    data: web::Data<AppState>,
+    http: HttpRequest,
+    req: web::Json<WhatIfRequest>
+) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::View).await {
+        return response;
+    }
+    if req.trade_fraction.is_some_and(|f| !(0.0..=1.0).contains(&f)) {
+        return HttpResponse::BadRequest().json(ApiResponse::<()>::error("trade_fraction must be between 0 and 1".to_string()));
+    }
+
+    let window = data.recorded_window.lock().unwrap().clone();
+    if window.is_empty() {
+        return HttpResponse::BadRequest().json(ApiResponse::<()>::error("No recorded market window yet".to_string()));
+    }
+
+    let baseline = BacktestConfig {
+        initial_capital: data.bot_state.lock().unwrap().initial_balance,
+        ..BacktestConfig::default()
+    };
+    let scenario = req.apply(&baseline);
+
+    // La finestra registrata non contiene risoluzioni: le posizioni restano valutate al mark
+    let report = web::block(move || what_if(&window, &[], baseline, scenario)).await;
+    match report {
+        Ok(report) => HttpResponse::Ok().json(ApiResponse::<WhatIfReport>::success(report)),
+        Err(e) => HttpResponse::InternalServerError().json(ApiResponse::<()>::error(e.to_string())),
+    }
+}
+
 /// POST /api/trades/clear - Clear all trades
-pub async fn clear_trades(data: web::Data<AppState>) -> impl Responder {
+pub async fn clear_trades(data: web::Data<AppState>, http: HttpRequest) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::Clear).await {
+        return response;
+    }
     let mut trades = data.trades.lock().unwrap();
     trades.clear();
 
     // Reset bot state
     let mut bot_state = data.bot_state.lock().unwrap();
+    data.broker.lock().unwrap().reset();
     bot_state.total_trades = 0;
     bot_state.profitable_trades = 0;
     bot_state.total_pnl = 0.0;
@@ -219,11 +693,784 @@ pub async fn clear_trades(data: web::Data<AppState>) -> impl Responder {
     HttpResponse::Ok().json(ApiResponse::success("Trades cleared successfully"))
 }
 
+/// POST /api/trade - Manually open/close a paper position
+pub async fn manual_trade(
+    data: web::Data<AppState>,
+    http: HttpRequest,
+    req: web::Json<ManualTradeRequest>
+) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::Trade).await {
+        return response;
+    }
+    let token_type = match req.side.to_uppercase().as_str() {
+        "YES" => TokenType::Yes,
+        "NO" => TokenType::No,
+        _ => return HttpResponse::BadRequest().json(ApiResponse::<()>::error("side must be YES or NO".to_string())),
+    };
+    let direction = match req.action.as_str() {
+        "open" => Direction::Buy,
+        "close" => Direction::Sell,
+        _ => return HttpResponse::BadRequest().json(ApiResponse::<()>::error("action must be open or close".to_string())),
+    };
+
+    let market = {
+        let markets = data.markets.lock().unwrap();
+        markets.iter().find(|m| m.id == req.market_id).cloned()
+    };
+    let Some(market) = market else {
+        return HttpResponse::NotFound().json(ApiResponse::<()>::error(format!("Unknown market {}", req.market_id)));
+    };
+
+    let order = PaperOrder {
+        market_id: req.market_id.clone(),
+        token_type,
+        direction,
+        quantity: req.size,
+        limit_price: req.limit_price,
+        source: TradeSource::Manual,
+    };
+
+    let markets = data.markets.lock().unwrap().clone();
+    let window = data.recorded_window.lock().unwrap().clone();
+    let end_dates = market_end_dates(&data);
+    let (trims, result) = {
+        let mut bot_state = data.bot_state.lock().unwrap();
+        let mut broker = data.broker.lock().unwrap();
+
+        // Capitale insufficiente: libera cassa vendendo le posizioni a minor edge per dollaro
+        let mut trims = Vec::new();
+        if req.liquidate && direction == Direction::Buy {
+            let price = match token_type {
+                TokenType::Yes => market.yes_price,
+                TokenType::No => market.no_price,
+            };
+            let shortfall = match broker.capital_shortfall(&bot_state, &order, price) {
+                Ok(shortfall) => shortfall,
+                Err(e) => return HttpResponse::BadRequest().json(ApiResponse::<()>::error(e)),
+            };
+            if shortfall > 0.0 {
+                let plan = liquidation_plan(&broker.open_positions(), shortfall, &markets, &window, &end_dates, &[&req.market_id]);
+                if !plan.is_sufficient() {
+                    return HttpResponse::BadRequest().json(ApiResponse::<()>::error(format!(
+                        "Cannot free {:.2}: open positions cover only {:.2}",
+                        shortfall, plan.capital_freed
+                    )));
+                }
+                trims = broker.liquidate(&mut bot_state, &plan, &markets);
+            }
+        }
+        (trims, broker.execute(&mut bot_state, &order, &market))
+    };
+
+    for trim in trims {
+        push_trade(&data.trades, &data.metrics, &data.markets, trim);
+    }
+    match result {
+        Ok(trade) => {
+            push_trade(&data.trades, &data.metrics, &data.markets, trade.clone());
+            HttpResponse::Ok().json(ApiResponse::success(trade))
+        }
+        Err(e) => HttpResponse::BadRequest().json(ApiResponse::<()>::error(e)),
+    }
+}
+
+/// Date di risoluzione per mercato, ereditate dall'evento Gamma
+fn market_end_dates(data: &AppState) -> FxHashMap<String, DateTime<Utc>> {
+    data.events
+        .lock()
+        .unwrap()
+        .iter()
+        .filter_map(|event| event.end_date.map(|end| (event, end)))
+        .flat_map(|(event, end)| event.market_ids.iter().map(move |id| (id.clone(), end)))
+        .collect()
+}
+
+/// Piano di liquidazione ai prezzi correnti
+///
+/// Il fair value è la probabilità media dei prezzi registrati nella finestra: una quotazione lontana dal
+/// consenso recente porta edge verso di esso. Senza storico il fair value è il mark (edge nullo).
+fn liquidation_plan(
+    positions: &[PaperPosition],
+    capital_needed: f64,
+    markets: &[MarketInfo],
+    window: &RecordedWindow,
+    end_dates: &FxHashMap<String, DateTime<Utc>>,
+    protected: &[&str],
+) -> LiquidationPlan {
+    let quote = |market_id: &str| markets.iter().find(|m| m.id == market_id);
+    let mark = |market_id: &str, token_type: TokenType| {
+        quote(market_id).map(|m| match token_type {
+            TokenType::Yes => m.yes_price,
+            TokenType::No => m.no_price,
+        })
+    };
+    let fair = |market_id: &str, token_type: TokenType| {
+        let probabilities: Vec<f64> = window.get(market_id)?
+            .iter()
+            .filter(|s| s.yes_price + s.no_price > 0.0)
+            .map(|s| s.yes_price / (s.yes_price + s.no_price))
+            .collect();
+        if probabilities.len() < 2 {
+            return None;
+        }
+        let yes = probabilities.iter().sum::<f64>() / probabilities.len() as f64;
+        Some(match token_type {
+            TokenType::Yes => yes,
+            TokenType::No => 1.0 - yes,
+        })
+    };
+    plan_liquidation(positions, capital_needed, mark, fair, end_dates, protected, &CarryAnalyzer::default(), Utc::now())
+}
+
+/// GET /api/positions/liquidation-plan - Preview which positions would be trimmed to free capital
+pub async fn get_liquidation_plan(
+    data: web::Data<AppState>,
+    http: HttpRequest,
+    query: web::Query<LiquidationQuery>
+) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::View).await {
+        return response;
+    }
+    if query.amount <= 0.0 {
+        return HttpResponse::BadRequest().json(ApiResponse::<()>::error("amount must be positive".to_string()));
+    }
+    let positions = data.broker.lock().unwrap().open_positions();
+    let markets = data.markets.lock().unwrap().clone();
+    let protected: Vec<&str> = query.market_id.as_deref().into_iter().collect();
+    let window = data.recorded_window.lock().unwrap().clone();
+    let plan = liquidation_plan(&positions, query.amount, &markets, &window, &market_end_dates(&data), &protected);
+    HttpResponse::Ok().json(ApiResponse::<LiquidationPlan>::success(plan))
+}
+
+/// GET /api/positions - Get open paper positions
+pub async fn get_positions(data: web::Data<AppState>, http: HttpRequest) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::View).await {
+        return response;
+    }
+    let broker = data.broker.lock().unwrap();
+    HttpResponse::Ok().json(ApiResponse::<Vec<PaperPosition>>::success(broker.open_positions()))
+}
+
+/// GET /api/positions/expiry-ladder - Open positions by time to resolution with hold/unwind/redeem actions
+pub async fn get_expiry_ladder(data: web::Data<AppState>, http: HttpRequest) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::View).await {
+        return response;
+    }
+    let positions = data.broker.lock().unwrap().open_positions();
+    let markets = data.markets.lock().unwrap().clone();
+    let end_dates = market_end_dates(&data);
+
+    let mark = |market_id: &str, token_type: TokenType| {
+        markets.iter().find(|m| m.id == market_id).map(|m| match token_type {
+            TokenType::Yes => m.yes_price,
+            TokenType::No => m.no_price,
+        })
+    };
+    let ladder = expiry_ladder(&positions, mark, &end_dates, &CarryAnalyzer::default(), Utc::now());
+    HttpResponse::Ok().json(ApiResponse::<ExpiryLadder>::success(ladder))
+}
+
+/// POST /api/positions/exits - Set stop-loss and take-profit of an open position
+pub async fn set_exit_levels(
+    data: web::Data<AppState>,
+    http: HttpRequest,
+    req: web::Json<ExitLevelsRequest>
+) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::Trade).await {
+        return response;
+    }
+    let token_type = match req.side.to_uppercase().as_str() {
+        "YES" => TokenType::Yes,
+        "NO" => TokenType::No,
+        _ => return HttpResponse::BadRequest().json(ApiResponse::<()>::error("side must be YES or NO".to_string())),
+    };
+    if [req.stop_loss, req.take_profit].iter().flatten().any(|level| *level <= 0.0 || *level >= 1.0) {
+        return HttpResponse::BadRequest().json(ApiResponse::<()>::error("exit levels must be between 0 and 1".to_string()));
+    }
+
+    let result = data.broker.lock().unwrap().set_exit_levels(&req.market_id, token_type, req.stop_loss, req.take_profit);
+    match result {
+        Ok(position) => HttpResponse::Ok().json(ApiResponse::success(position)),
+        Err(e) => HttpResponse::BadRequest().json(ApiResponse::<()>::error(e)),
+    }
+}
+
+/// GET /api/exit-policies - Get the default exit levels per strategy
+pub async fn get_exit_policies(data: web::Data<AppState>, http: HttpRequest) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::View).await {
+        return response;
+    }
+    let broker = data.broker.lock().unwrap();
+    HttpResponse::Ok().json(ApiResponse::success(broker.exit_policies.clone()))
+}
+
+/// POST /api/exit-policies - Set the default exit levels of a strategy for new positions
+pub async fn set_exit_policy(
+    data: web::Data<AppState>,
+    http: HttpRequest,
+    req: web::Json<ExitPolicyRequest>
+) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::Configure).await {
+        return response;
+    }
+    if [req.policy.stop_loss_pct, req.policy.take_profit_pct].iter().flatten().any(|pct| *pct <= 0.0) {
+        return HttpResponse::BadRequest().json(ApiResponse::<()>::error("exit percentages must be positive".to_string()));
+    }
+    if req.policy.stop_loss_pct.is_some_and(|pct| pct >= 1.0) {
+        return HttpResponse::BadRequest().json(ApiResponse::<()>::error("stop_loss_pct must be below 1".to_string()));
+    }
+
+    data.broker.lock().unwrap().set_exit_policy(req.source, req.policy);
+    HttpResponse::Ok().json(ApiResponse::success(req.policy))
+}
+
+/// GET /api/audit/{trade_id} - Get the order audit trail of a trade
+pub async fn get_order_audit(
+    data: web::Data<AppState>,
+    http: HttpRequest,
+    path: web::Path<String>
+) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::View).await {
+        return response;
+    }
+    let trade_id = path.into_inner();
+    match data.audit_log.records_for(&trade_id) {
+        Ok(records) if records.is_empty() => HttpResponse::NotFound().json(ApiResponse::<()>::error(format!("No audit records for trade {}", trade_id))),
+        Ok(records) => HttpResponse::Ok().json(ApiResponse::<Vec<AuditRecord>>::success(records)),
+        Err(e) => HttpResponse::InternalServerError().json(ApiResponse::<()>::error(e)),
+    }
+}
+
+/// Salva un trade mantenendo solo gli ultimi 100 in memoria e lo conta nelle metriche Prometheus
+fn push_trade(trades: &Arc<Mutex<Vec<SimulatedTrade>>>, metrics: &DashboardMetrics, markets: &Arc<Mutex<Vec<MarketInfo>>>, trade: SimulatedTrade) {
+    // Contatori incrementati al fill: lo storico qui sotto è troncato, le metriche no
+    metrics.record_trade(&trade, &markets.lock().unwrap());
+    let mut trades_guard = trades.lock().unwrap();
+    trades_guard.push(trade);
+
+    if trades_guard.len() > 100 {
+        trades_guard.remove(0);
+    }
+}
+
+/// Clustering dei trade recenti; i nuovi cluster anomali generano un alert
+fn analyze_trades(
+    trades: &Arc<Mutex<Vec<SimulatedTrade>>>,
+    markets: &Arc<Mutex<Vec<MarketInfo>>>,
+    monitor: &Arc<Mutex<TradeAnomalyMonitor>>
+) -> ClusterReport {
+    let categories: HashMap<String, Option<String>> = markets
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|m| (m.id.clone(), m.category.clone()))
+        .collect();
+    let samples: Vec<TradeSample> = trades
+        .lock()
+        .unwrap()
+        .iter()
+        .filter_map(|t| TradeSample::from_simulated(t, categories.get(&t.market_id).and_then(|c| c.as_deref())))
+        .collect();
+
+    let report = cluster_trades(&samples);
+    for alert in monitor.lock().unwrap().check(&report, Utc::now()) {
+        eprintln!("🚨 Anomalia trade: {}", alert.message);
+    }
+    report
+}
+
+/// Registra i prezzi del tick nella finestra, mantenendo gli ultimi RECORDED_WINDOW_LEN per mercato
+fn record_window(window: &Arc<Mutex<RecordedWindow>>, markets: &[MarketInfo]) {
+    let mut window_guard = window.lock().unwrap();
+    for market in markets {
+        let history = window_guard.entry(market.id.clone()).or_default();
+        history.push(PriceSnapshot {
+            timestamp: market.timestamp,
+            yes_price: market.yes_price,
+            no_price: market.no_price,
+            volume: market.volume_24h,
+        });
+        if history.len() > RECORDED_WINDOW_LEN {
+            history.remove(0);
+        }
+    }
+}
+
+/// GET /api/watchlist - Get pinned markets
+pub async fn get_watchlist(data: web::Data<AppState>, http: HttpRequest) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::View).await {
+        return response;
+    }
+    let watchlist = data.watchlist.lock().unwrap();
+    HttpResponse::Ok().json(ApiResponse::<Vec<PinnedMarket>>::success(watchlist.list()))
+}
+
+/// POST /api/watchlist - Pin a market
+pub async fn pin_market(
+    data: web::Data<AppState>,
+    http: HttpRequest,
+    req: web::Json<PinMarketRequest>
+) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::Configure).await {
+        return response;
+    }
+    if req.market_id.is_empty() {
+        return HttpResponse::BadRequest().json(ApiResponse::<()>::error("market_id is required".to_string()));
+    }
+
+    let mut watchlist = data.watchlist.lock().unwrap();
+    match watchlist.pin(&req.market_id, req.min_profit_override) {
+        Ok(()) => HttpResponse::Ok().json(ApiResponse::success(watchlist.list())),
+        Err(e) => HttpResponse::InternalServerError().json(ApiResponse::<()>::error(e)),
+    }
+}
+
+/// DELETE /api/watchlist/{market_id} - Unpin a market
+pub async fn unpin_market(
+    data: web::Data<AppState>,
+    http: HttpRequest,
+    path: web::Path<String>
+) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::Configure).await {
+        return response;
+    }
+    let market_id = path.into_inner();
+    let mut watchlist = data.watchlist.lock().unwrap();
+    match watchlist.unpin(&market_id) {
+        Ok(true) => HttpResponse::Ok().json(ApiResponse::success(watchlist.list())),
+        Ok(false) => HttpResponse::NotFound().json(ApiResponse::<()>::error(format!("Market {} is not pinned", market_id))),
+        Err(e) => HttpResponse::InternalServerError().json(ApiResponse::<()>::error(e)),
+    }
+}
+
+/// GET /api/relations - User-defined relations between markets
+pub async fn get_relations(data: web::Data<AppState>, http: HttpRequest) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::View).await {
+        return response;
+    }
+    let relations = data.relations.lock().unwrap();
+    HttpResponse::Ok().json(ApiResponse::<Vec<MarketRelation>>::success(relations.list().to_vec()))
+}
+
+/// POST /api/relations - Add or replace a relation (A implies B, A and B mutually exclusive)
+pub async fn add_relation(
+    data: web::Data<AppState>,
+    http: HttpRequest,
+    req: web::Json<MarketRelation>
+) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::Configure).await {
+        return response;
+    }
+    let mut relations = data.relations.lock().unwrap();
+    match relations.add(req.into_inner()) {
+        Ok(()) => HttpResponse::Ok().json(ApiResponse::success(relations.list().to_vec())),
+        Err(e) => HttpResponse::BadRequest().json(ApiResponse::<()>::error(e)),
+    }
+}
+
+/// DELETE /api/relations/{a}/{b} - Remove the relations between two markets
+pub async fn remove_relation(
+    data: web::Data<AppState>,
+    http: HttpRequest,
+    path: web::Path<(String, String)>
+) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::Configure).await {
+        return response;
+    }
+    let (a, b) = path.into_inner();
+    let mut relations = data.relations.lock().unwrap();
+    match relations.remove(&a, &b) {
+        Ok(true) => HttpResponse::Ok().json(ApiResponse::success(relations.list().to_vec())),
+        Ok(false) => HttpResponse::NotFound().json(ApiResponse::<()>::error(format!("No relation between {} and {}", a, b))),
+        Err(e) => HttpResponse::InternalServerError().json(ApiResponse::<()>::error(e)),
+    }
+}
+
+/// GET /api/relations/violations - Relations whose current prices break their probability bound, net of costs
+pub async fn get_relation_violations(data: web::Data<AppState>, http: HttpRequest) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::View).await {
+        return response;
+    }
+    let markets: Vec<MarketData> = data.markets.lock().unwrap().iter().map(MarketData::from).collect();
+    let detector = ArbitrageDetector::new(0.0, 0.0);
+    let violations = detector.scan_relations(&markets, data.relations.lock().unwrap().list());
+    HttpResponse::Ok().json(ApiResponse::<Vec<types::ArbitrageOpportunity>>::success(violations))
+}
+
+/// GET /api/snapshot - Positions, open orders, capital and risk state, signed when a secret is configured
+pub async fn get_snapshot(data: web::Data<AppState>, http: HttpRequest) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::View).await {
+        return response;
+    }
+    let snapshot = {
+        let state = data.bot_state.lock().unwrap();
+        let broker = data.broker.lock().unwrap();
+        PortfolioSnapshot::capture(&state, &broker, Utc::now())
+    };
+    let snapshot = match &data.snapshot_secret {
+        Some(secret) => match snapshot.sign(secret) {
+            Ok(signed) => signed,
+            Err(e) => return HttpResponse::InternalServerError().json(ApiResponse::<()>::error(e)),
+        },
+        None => snapshot,
+    };
+    HttpResponse::Ok().json(ApiResponse::success(snapshot))
+}
+
+/// POST /api/snapshot/diff - Changes between two snapshots; with a secret configured both must carry a valid signature
+pub async fn diff_snapshots(
+    data: web::Data<AppState>,
+    http: HttpRequest,
+    req: web::Json<SnapshotDiffRequest>
+) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::View).await {
+        return response;
+    }
+    for (label, snapshot) in [("from", &req.from), ("to", &req.to)] {
+        if let Err(e) = snapshot.check_version() {
+            return HttpResponse::BadRequest().json(ApiResponse::<()>::error(format!("Unsupported '{}' snapshot: {}", label, e)));
+        }
+    }
+    if let Some(secret) = &data.snapshot_secret {
+        for (label, snapshot) in [("from", &req.from), ("to", &req.to)] {
+            if !snapshot.verify(secret) {
+                return HttpResponse::Unauthorized().json(ApiResponse::<()>::error(format!("Invalid signature on '{}' snapshot", label)));
+            }
+        }
+    }
+    HttpResponse::Ok().json(ApiResponse::<SnapshotDiff>::success(req.from.diff(&req.to)))
+}
+
+/// POST /api/auth/login - Open a session
+pub async fn login(
+    data: web::Data<AppState>,
+    http: HttpRequest,
+    req: web::Json<LoginRequest>
+) -> impl Responder {
+    // I fallimenti contano per (username, indirizzo del peer): chi sbaglia blocca solo se stesso
+    let source = http.peer_addr().map(|addr| addr.ip().to_string()).unwrap_or_default();
+    match data.accounts.login(&req.username, &req.password, &source).await {
+        Ok(session) => HttpResponse::Ok().json(ApiResponse::success(session)),
+        Err(AuthError::Unauthenticated) => HttpResponse::Unauthorized().json(ApiResponse::<()>::error("Invalid username or password".to_string())),
+        Err(e) => auth_error_response(e),
+    }
+}
+
+/// POST /api/auth/logout - Close the caller's session
+pub async fn logout(data: web::Data<AppState>, http: HttpRequest) -> impl Responder {
+    match bearer_token(&http) {
+        Some(token) if data.accounts.logout(token) => HttpResponse::Ok().json(ApiResponse::success("Logged out")),
+        _ => HttpResponse::Unauthorized().json(ApiResponse::<()>::error("No active session".to_string())),
+    }
+}
+
+/// GET /api/auth/me - Current session
+pub async fn current_user(data: web::Data<AppState>, http: HttpRequest) -> impl Responder {
+    match bearer_token(&http).and_then(|token| data.accounts.session(token)) {
+        Some(session) => HttpResponse::Ok().json(ApiResponse::success(session)),
+        None => HttpResponse::Unauthorized().json(ApiResponse::<()>::error("No active session".to_string())),
+    }
+}
+
+/// GET /api/users - List accounts (admin)
+pub async fn list_users(data: web::Data<AppState>, http: HttpRequest) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::ManageUsers).await {
+        return response;
+    }
+    match data.accounts.list().await {
+        Ok(users) => HttpResponse::Ok().json(ApiResponse::<Vec<UserInfo>>::success(users)),
+        Err(e) => HttpResponse::InternalServerError().json(ApiResponse::<()>::error(e)),
+    }
+}
+
+/// POST /api/users - Create an account (admin; the very first account is an admin created with the bootstrap token)
+pub async fn create_user(
+    data: web::Data<AppState>,
+    http: HttpRequest,
+    req: web::Json<CreateUserRequest>
+) -> impl Responder {
+    let created = match require(&data, &http, Permission::ManageUsers).await {
+        Err(response) => return response,
+        // Nessun account ancora: bootstrap del primo amministratore
+        Ok(None) if req.role != Role::Admin => {
+            return HttpResponse::BadRequest().json(ApiResponse::<()>::error("The first account must be an admin".to_string()));
+        }
+        Ok(None) => data.accounts.bootstrap(req.bootstrap_token.as_deref(), &req.username, &req.password).await,
+        Ok(Some(_)) => data.accounts.create(&req.username, &req.password, req.role).await,
+    };
+
+    match created {
+        Ok(user) => HttpResponse::Ok().json(ApiResponse::success(user)),
+        Err(e) => auth_error_response(e),
+    }
+}
+
+/// DELETE /api/users/{username} - Delete an account (admin)
+pub async fn delete_user(
+    data: web::Data<AppState>,
+    http: HttpRequest,
+    path: web::Path<String>
+) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::ManageUsers).await {
+        return response;
+    }
+    let username = path.into_inner();
+
+    // L'ultimo amministratore non si può eliminare: rifiutato da `delete`
+    match data.accounts.delete(&username).await {
+        Ok(true) => HttpResponse::Ok().json(ApiResponse::success(format!("User {} deleted", username))),
+        Ok(false) => HttpResponse::NotFound().json(ApiResponse::<()>::error(format!("Unknown user {}", username))),
+        Err(e) => auth_error_response(e),
+    }
+}
+
+/// Payload del webhook: un segnale o un batch
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum SignalPayload {
+    One(ExternalSignal),
+    Many(Vec<ExternalSignal>),
+}
+
+/// POST /api/signals - Ingest external signals (body signed with HMAC-SHA256)
+pub async fn ingest_signals(
+    data: web::Data<AppState>,
+    http: HttpRequest,
+    body: web::Bytes
+) -> impl Responder {
+    let Some(secret) = &data.signal_secret else {
+        return HttpResponse::ServiceUnavailable().json(ApiResponse::<()>::error(format!("Signal webhook disabled: {} is not set", SIGNAL_SECRET_ENV)));
+    };
+    let signature = http.headers().get(SIGNATURE_HEADER).and_then(|v| v.to_str().ok()).unwrap_or_default();
+    if !verify_signature(secret, &body, signature) {
+        return HttpResponse::Unauthorized().json(ApiResponse::<()>::error("Invalid signal signature".to_string()));
+    }
+
+    let signals = match serde_json::from_slice::<SignalPayload>(&body) {
+        Ok(SignalPayload::One(signal)) => vec![signal],
+        Ok(SignalPayload::Many(signals)) => signals,
+        Err(e) => return HttpResponse::BadRequest().json(ApiResponse::<()>::error(format!("Invalid signal payload: {}", e))),
+    };
+
+    // Il batch è accettato solo se tutti i segnali sono validi
+    let now = Utc::now();
+    if let Some(e) = signals.iter().find_map(|s| s.validate(now).err()) {
+        return HttpResponse::BadRequest().json(ApiResponse::<()>::error(e));
+    }
+    let accepted = signals.len();
+    for signal in signals {
+        if let Err(e) = data.signals.ingest(signal, now) {
+            return HttpResponse::BadRequest().json(ApiResponse::<()>::error(e));
+        }
+    }
+    HttpResponse::Ok().json(ApiResponse::success(accepted))
+}
+
+/// GET /api/signals - Live external signals
+pub async fn get_signals(data: web::Data<AppState>, http: HttpRequest) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::View).await {
+        return response;
+    }
+    HttpResponse::Ok().json(ApiResponse::<Vec<ExternalSignal>>::success(data.signals.active(Utc::now())))
+}
+
+/// Request payload per collegare un mercato di una venue a un evento
+#[derive(Deserialize)]
+pub struct VenueLinkRequest {
+    pub venue: String,
+    pub market_id: String,
+    pub event_key: String,
+}
+
+/// GET /api/venues/spreads - Cross-venue spread statistics per linked event
+pub async fn get_venue_spreads(data: web::Data<AppState>, http: HttpRequest) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::View).await {
+        return response;
+    }
+    let comparison = data.venue_comparison.lock().unwrap();
+    HttpResponse::Ok().json(ApiResponse::<Vec<VenueSpreadSummary>>::success(comparison.summary()))
+}
+
+/// GET /api/venues/spreads/{event_key} - Spread history of one event
+pub async fn get_venue_spread_history(
+    data: web::Data<AppState>,
+    http: HttpRequest,
+    path: web::Path<String>
+) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::View).await {
+        return response;
+    }
+    let comparison = data.venue_comparison.lock().unwrap();
+    HttpResponse::Ok().json(ApiResponse::<Vec<VenueSpreadSample>>::success(comparison.history(&path.into_inner())))
+}
+
+/// POST /api/venues/links - Link a venue market to an event key
+pub async fn link_venue_market(
+    data: web::Data<AppState>,
+    http: HttpRequest,
+    req: web::Json<VenueLinkRequest>
+) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::Configure).await {
+        return response;
+    }
+    data.venue_comparison.lock().unwrap().link(&req.venue, &req.market_id, &req.event_key);
+    HttpResponse::Ok().json(ApiResponse::success(format!("{}:{} linked to {}", req.venue, req.market_id, req.event_key)))
+}
+
+/// POST /api/venues/quotes - Quotes from venues without an adapter yet
+pub async fn ingest_venue_quotes(
+    data: web::Data<AppState>,
+    http: HttpRequest,
+    req: web::Json<Vec<VenueQuote>>
+) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::Configure).await {
+        return response;
+    }
+    let mut comparison = data.venue_comparison.lock().unwrap();
+    let samples = req.into_inner().into_iter().filter_map(|quote| comparison.observe(quote)).count();
+    HttpResponse::Ok().json(ApiResponse::success(samples))
+}
+
+/// Query di /api/onboarding
+#[derive(Deserialize)]
+pub struct OnboardingQuery {
+    pub status: Option<OnboardingStatus>,
+}
+
+/// Request payload per rifiutare un mercato in onboarding
+#[derive(Deserialize, Default)]
+pub struct OnboardingRejectRequest {
+    pub reason: Option<String>,
+}
+
+/// GET /api/onboarding?status= - Onboarding records of discovered markets, optionally by state
+pub async fn get_onboarding(data: web::Data<AppState>, http: HttpRequest, query: web::Query<OnboardingQuery>) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::View).await {
+        return response;
+    }
+    let onboarding = data.onboarding.lock().unwrap();
+    HttpResponse::Ok().json(ApiResponse::<Vec<OnboardingRecord>>::success(onboarding.list(query.status)))
+}
+
+/// GET /api/onboarding/config - Eligibility checks and approval mode
+pub async fn get_onboarding_config(data: web::Data<AppState>, http: HttpRequest) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::View).await {
+        return response;
+    }
+    let config = data.onboarding.lock().unwrap().config.clone();
+    HttpResponse::Ok().json(ApiResponse::success(config))
+}
+
+/// PUT /api/onboarding/config - Replace the eligibility checks (including manual-approval mode)
+pub async fn set_onboarding_config(
+    data: web::Data<AppState>,
+    http: HttpRequest,
+    req: web::Json<OnboardingConfig>
+) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::Configure).await {
+        return response;
+    }
+    let mut onboarding = data.onboarding.lock().unwrap();
+    match onboarding.set_config(req.into_inner()) {
+        Ok(()) => HttpResponse::Ok().json(ApiResponse::success(onboarding.config.clone())),
+        Err(e) => HttpResponse::InternalServerError().json(ApiResponse::<()>::error(e)),
+    }
+}
+
+/// POST /api/onboarding/{market_id}/approve - Make a discovered market tradeable
+pub async fn approve_market(data: web::Data<AppState>, http: HttpRequest, path: web::Path<String>) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::Configure).await {
+        return response;
+    }
+    match data.onboarding.lock().unwrap().approve(&path.into_inner()) {
+        Ok(record) => HttpResponse::Ok().json(ApiResponse::success(record)),
+        Err(e) => HttpResponse::NotFound().json(ApiResponse::<()>::error(e)),
+    }
+}
+
+/// POST /api/onboarding/{market_id}/reject - Keep a discovered market out of trading
+pub async fn reject_market(
+    data: web::Data<AppState>,
+    http: HttpRequest,
+    path: web::Path<String>,
+    req: Option<web::Json<OnboardingRejectRequest>>
+) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::Configure).await {
+        return response;
+    }
+    let reason = req.and_then(|r| r.into_inner().reason);
+    match data.onboarding.lock().unwrap().reject(&path.into_inner(), reason) {
+        Ok(record) => HttpResponse::Ok().json(ApiResponse::success(record)),
+        Err(e) => HttpResponse::NotFound().json(ApiResponse::<()>::error(e)),
+    }
+}
+
+/// Query di /api/symbols
+#[derive(Deserialize)]
+pub struct SymbolQuery {
+    pub market_id: Option<String>,
+}
+
+/// Query di /api/symbols/resolve
+#[derive(Deserialize)]
+pub struct SymbolResolveQuery {
+    pub venue: String,
+    pub symbol: String,
+}
+
+/// GET /api/symbols?market_id= - Venue identifiers, of one market or all
+pub async fn get_symbols(data: web::Data<AppState>, http: HttpRequest, query: web::Query<SymbolQuery>) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::View).await {
+        return response;
+    }
+    let symbols = data.symbols.lock().unwrap();
+    let mappings = match &query.market_id {
+        Some(market_id) => symbols.mappings_for(market_id),
+        None => symbols.list(),
+    };
+    HttpResponse::Ok().json(ApiResponse::<Vec<SymbolMapping>>::success(mappings))
+}
+
+/// GET /api/symbols/resolve?venue=&symbol= - Internal market and outcome of a venue identifier
+pub async fn resolve_symbol(data: web::Data<AppState>, http: HttpRequest, query: web::Query<SymbolResolveQuery>) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::View).await {
+        return response;
+    }
+    match data.symbols.lock().unwrap().resolve(&query.venue, &query.symbol) {
+        Some(mapping) => HttpResponse::Ok().json(ApiResponse::success(mapping.clone())),
+        None => HttpResponse::NotFound().json(ApiResponse::<()>::error(format!("{} symbol {} not mapped", query.venue, query.symbol))),
+    }
+}
+
+/// POST /api/symbols - Map a venue identifier to an internal market or outcome
+pub async fn register_symbol(
+    data: web::Data<AppState>,
+    http: HttpRequest,
+    req: web::Json<SymbolMapping>
+) -> impl Responder {
+    if let Err(response) = require(&data, &http, Permission::Configure).await {
+        return response;
+    }
+    let mut symbols = data.symbols.lock().unwrap();
+    match symbols.register(req.into_inner()) {
+        Ok(()) => HttpResponse::Ok().json(ApiResponse::success(symbols.len())),
+        Err(e) => HttpResponse::BadRequest().json(ApiResponse::<()>::error(e)),
+    }
+}
+
 /// Simula trading con dati reali dai mercati Polymarket
+#[allow(clippy::too_many_arguments)]
 async fn simulate_trading(
     bot_state: Arc<Mutex<BotState>>,
     trades: Arc<Mutex<Vec<SimulatedTrade>>>,
     markets: Arc<Mutex<Vec<MarketInfo>>>,
+    watchlist: Arc<Mutex<Watchlist>>,
+    onboarding: Arc<Mutex<MarketOnboarding>>,
+    broker: Arc<Mutex<PaperBroker>>,
+    recorded_window: Arc<Mutex<RecordedWindow>>,
+    venue_comparison: Arc<Mutex<VenueComparison>>,
+    trade_monitor: Arc<Mutex<TradeAnomalyMonitor>>,
+    metrics: DashboardMetrics,
     frequency: u64
 ) {
     use std::time::Duration;
@@ -250,9 +1497,52 @@ async fn simulate_trading(
             }
             markets_guard.clone()
         };
+        record_window(&recorded_window, &available_markets);
+
+        // Stop-loss e take-profit delle posizioni aperte contro i prezzi del tick
+        let exits = {
+            let mut state = bot_state.lock().unwrap();
+            broker.lock().unwrap().check_exits(&mut state, &available_markets)
+        };
+        for trade in exits {
+            push_trade(&trades, &metrics, &markets, trade);
+        }
+        {
+            let mut comparison = venue_comparison.lock().unwrap();
+            for market in &available_markets {
+                comparison.observe(VenueQuote {
+                    venue: POLYMARKET_VENUE.to_string(),
+                    market_id: market.id.clone(),
+                    yes_price: market.yes_price,
+                    timestamp: market.timestamp,
+                    no_price: Some(market.no_price),
+                    liquidity: Some(market.yes_liquidity.min(market.no_liquidity)),
+                });
+            }
+        }
+
+        // Si negoziano solo i mercati che hanno completato l'onboarding
+        let tradeable: Vec<&MarketInfo> = {
+            let onboarding_guard = onboarding.lock().unwrap();
+            available_markets.iter().filter(|m| onboarding_guard.is_tradeable(&m.id)).collect()
+        };
 
-        // Seleziona mercato random per trade simulato
-        if let Some(market) = available_markets.iter().choose(&mut rand::thread_rng()) {
+        // I mercati pinnati vengono scansionati a ogni tick, gli altri a tick alterni
+        let pinned: Vec<&MarketInfo> = {
+            let watchlist_guard = watchlist.lock().unwrap();
+            tradeable.iter().copied().filter(|m| watchlist_guard.is_pinned(&m.id)).collect()
+        };
+        let candidate = if !pinned.is_empty() && rand::thread_rng().gen_bool(0.5) {
+            pinned.into_iter().choose(&mut rand::thread_rng())
+        } else {
+            tradeable.into_iter().choose(&mut rand::thread_rng())
+        };
+
+        // Capitale impiegato in questo tick (round trip del bot), per le metriche di efficienza
+        let mut in_flight = 0.0;
+
+        // Seleziona mercato per trade simulato
+        if let Some(market) = candidate {
             let mut rng = rand::thread_rng();
 
             // Simula decisione trading basata su dati reali
@@ -296,41 +1586,150 @@ async fn simulate_trading(
                 status: "FILLED".to_string(),
                 pnl,
                 arbitrage_profit,
+                source: TradeSource::Bot,
             };
 
-            // Aggiorna stato bot
-            {
+            // Registra il trade nello stesso ledger dei trade manuali (stessi controlli di rischio)
+            let booked = {
                 let mut state = bot_state.lock().unwrap();
-                state.balance += pnl + arbitrage_profit;
-                state.total_pnl += pnl + arbitrage_profit;
-                state.total_trades += 1;
-                state.profitable_trades += if pnl + arbitrage_profit > 0.0 { 1 } else { 0 };
-                state.win_rate = (state.profitable_trades as f64 / state.total_trades as f64) * 100.0;
-                state.last_update = Utc::now();
+                let mut broker_guard = broker.lock().unwrap();
+                broker_guard.book_round_trip(&mut state, &trade)
+            };
+
+            match booked {
+                Ok(()) => {
+                    in_flight = trade.amount;
+                    push_trade(&trades, &metrics, &markets, trade);
+                    analyze_trades(&trades, &markets, &trade_monitor);
+                }
+                Err(e) => eprintln!("⚠️  Trade bot rifiutato dai controlli di rischio: {}", e),
             }
+        }
 
-            // Salva trade
-            {
-                let mut trades_guard = trades.lock().unwrap();
-                trades_guard.push(trade);
+        broker.lock().unwrap().sample_deployed(in_flight);
+    }
+}
 
-                // Mantieni solo ultimi 100 trade in memoria
-                if trades_guard.len() > 100 {
-                    trades_guard.remove(0);
+/// Feed mercati della dashboard: segue la sorgente scelta via /api/data-source e pubblica i prezzi in `markets`
+#[allow(clippy::too_many_arguments)]
+async fn run_market_feed(
+    data_source: Arc<Mutex<Option<DataSource>>>,
+    markets: Arc<Mutex<Vec<MarketInfo>>>,
+    events: Arc<Mutex<Vec<EventInfo>>>,
+    symbols: Arc<Mutex<SymbolRegistry>>,
+    onboarding: Arc<Mutex<MarketOnboarding>>,
+    watchlist: Arc<Mutex<Watchlist>>,
+    signals: SignalBook,
+    opportunities: broadcast::Sender<types::ArbitrageOpportunity>,
+    storage: SharedStorage,
+    step_history: StepHistoryConfig,
+) {
+    let mut bot = HftArbitrageBot::new(BotConfig { step_history, ..BotConfig::default() });
+    bot.arb_detector.write().unwrap().signals = signals; // I segnali ricevuti da /api/signals pesano sul rilevamento
+    bot.storage = Some(storage); // Step del feed salvati nello stesso backend letto da /api/steps
+    bot.stream_opportunities_to(opportunities.clone());
+    if let Err(e) = bot.market_manager.fetch_markets().await {
+        eprintln!("⚠️  Mercati simulati non generati: {}", e);
+    }
+    let mut interval = tokio::time::interval(std::time::Duration::from_millis(bot.market_manager.config.update_interval_ms));
+
+    loop {
+        interval.tick().await;
+
+        let Some(wanted) = *data_source.lock().unwrap() else { continue };
+        if wanted != bot.market_manager.data_source {
+            if let Err(e) = bot.set_data_source(wanted).await {
+                // Resta sulla sorgente attuale e lo riporta alla dashboard
+                eprintln!("⚠️  Cambio sorgente dati a {:?} fallito: {}", wanted, e);
+                *data_source.lock().unwrap() = Some(bot.market_manager.data_source);
+            } else if let Err(e) = symbols.lock().unwrap().register_markets(bot.market_manager.get_all_markets()) {
+                eprintln!("⚠️  Registro dei simboli non aggiornato: {}", e);
+            }
+        }
+
+        // Pin e soglie della dashboard: scansione e sottoscrizioni seguono la stessa watchlist
+        let pinned = watchlist.lock().unwrap().clone();
+        let resubscribe = pinned.pinned_ids() != bot.market_manager.watchlist.pinned_ids();
+        bot.market_manager.watchlist = pinned;
+        if resubscribe {
+            bot.sync_subscriptions().await;
+        }
+
+        bot.check_staleness().await;
+        bot.resync_order_books().await;
+        bot.refresh_rest_prices().await;
+        if let Err(e) = bot.market_manager.update_prices().await {
+            eprintln!("⚠️  Aggiornamento prezzi fallito: {}", e);
+        }
+        let manager = &bot.market_manager;
+        let discovered = manager
+            .get_all_markets()
+            .into_iter()
+            .map(|m| (m, manager.price_history.get(&m.id).map_or(0, Vec::len)));
+        if let Err(e) = onboarding.lock().unwrap().onboard(discovered) {
+            eprintln!("⚠️  Stato di onboarding non salvato: {}", e);
+        }
+
+        // Rilevamento con client in ascolto sullo stream o sugli step da salvare: le opportunità non sono eseguite
+        let streaming = opportunities.receiver_count() > 0;
+        if streaming || bot.config.step_history.is_due(bot.current_step + 1) {
+            let timer = Stopwatch::start(&bot.clock);
+            let detected = bot.detect_opportunities();
+            if streaming {
+                let now = bot.clock.now();
+                let mut arb_detector = bot.arb_detector.write().unwrap();
+                for opportunity in &detected {
+                    arb_detector.mark_reported(opportunity, now); // Pubblicata sullo stream: in cooldown
                 }
             }
+            let latency_ms = timer.elapsed_ms();
+            bot.persist_detection_step(detected.len(), latency_ms).await;
+        } else {
+            bot.current_step += 1; // Tick senza rilevamento: conta per l'intervallo dello storico
         }
+
+        *markets.lock().unwrap() = bot.market_manager.get_all_markets().into_iter().map(MarketInfo::from).collect();
+        *events.lock().unwrap() = bot.market_manager.current_events().iter().map(EventInfo::from).collect();
+    }
+}
+
+/// Avvia il monitor delle risoluzioni sulle posizioni del broker e il tracker che le liquida a 0/1
+fn spawn_resolution_tracking(state: &AppState) {
+    let (tx, rx) = tokio::sync::mpsc::channel(64);
+    match GammaApiClient::new(PolymarketApiConfig::default().with_env_overrides()) {
+        Ok(gamma) => {
+            tokio::spawn(ResolutionMonitor::new(tx).run(Arc::new(gamma), state.broker.clone()));
+        }
+        Err(e) => eprintln!("⚠️  Resolution monitor disabled: {}", e),
     }
+
+    let (trades, metrics, markets) = (state.trades.clone(), state.metrics.clone(), state.markets.clone());
+    let tracker = PositionTracker::new(state.broker.clone(), state.bot_state.clone());
+    tokio::spawn(tracker.run(rx, move |settlements| {
+        for trade in settlements {
+            push_trade(&trades, &metrics, &markets, trade);
+        }
+    }));
 }
 
-/// Avvia il server API
+/// Avvia il server API sul backend di storage scelto da `STORAGE_CONFIG_ENV`
 pub async fn start_api_server(port: u16) -> std::io::Result<()> {
-    env_logger::init();
+    let config = StorageConfig::from_env().map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+    let storage = open_storage(&config).await.map_err(std::io::Error::other)?;
+    serve(port, web::Data::new(AppState::with_storage(storage))).await
+}
 
-    let app_state = web::Data::new(AppState::new());
+/// Serve the dashboard API on `port` over an existing state (shared with the demo driver)
+pub async fn serve(port: u16, app_state: web::Data<AppState>) -> std::io::Result<()> {
+    env_logger::init();
+    spawn_resolution_tracking(&app_state);
 
+    let frontend_dir = std::env::var(FRONTEND_DIR_ENV).ok().map(std::path::PathBuf::from);
     println!("🚀 Avvio server API dashboard su http://0.0.0.0:{}", port);
-    println!("📁 Frontend servito su /frontend");
+    match &frontend_dir {
+        Some(dir) => println!("📁 Frontend servito su /frontend da {}", dir.display()),
+        None => println!("📁 Frontend (incorporato) servito su /frontend"),
+    }
 
     HttpServer::new(move || {
         let cors = Cors::permissive();
@@ -338,12 +1737,64 @@ pub async fn start_api_server(port: u16) -> std::io::Result<()> {
         App::new()
             .wrap(cors)
             .app_data(app_state.clone())
+            .app_data(web::Data::new(FrontendSource(frontend_dir.clone())))
             .route("/api/status", web::get().to(get_bot_status))
             .route("/api/control", web::post().to(control_bot))
             .route("/api/trades", web::get().to(get_trades))
             .route("/api/markets", web::get().to(get_markets))
+            .route("/api/events", web::get().to(get_events))
+            .route("/api/data-source", web::get().to(get_data_source))
+            .route("/api/data-source", web::post().to(set_data_source))
+            .route("/api/demo", web::get().to(get_demo))
+            .route("/api/opportunities/stream", web::get().to(stream_opportunities))
             .route("/api/trades/clear", web::post().to(clear_trades))
-            .service(Files::new("/frontend", "./frontend"))
+            .route("/api/trade", web::post().to(manual_trade))
+            .route("/api/positions", web::get().to(get_positions))
+            .route("/api/positions/exits", web::post().to(set_exit_levels))
+            .route("/api/positions/expiry-ladder", web::get().to(get_expiry_ladder))
+            .route("/api/positions/liquidation-plan", web::get().to(get_liquidation_plan))
+            .route("/api/exit-policies", web::get().to(get_exit_policies))
+            .route("/api/exit-policies", web::post().to(set_exit_policy))
+            .route("/api/performance", web::get().to(get_performance))
+            .route("/api/analytics/clusters", web::get().to(get_trade_clusters))
+            .route("/api/alerts", web::get().to(get_alerts))
+            .route("/api/risk/drawdowns", web::get().to(get_drawdowns))
+            .route("/api/snapshot", web::get().to(get_snapshot))
+            .route("/api/snapshot/diff", web::post().to(diff_snapshots))
+            .route("/api/steps", web::get().to(get_steps))
+            .route("/metrics", web::get().to(get_metrics))
+            .route("/api/metrics/grafana-dashboard", web::get().to(get_grafana_dashboard))
+            .route("/api/whatif", web::post().to(run_what_if))
+            .route("/api/whatif/window", web::get().to(get_recorded_window))
+            .route("/api/audit/{trade_id}", web::get().to(get_order_audit))
+            .route("/api/watchlist", web::get().to(get_watchlist))
+            .route("/api/watchlist", web::post().to(pin_market))
+            .route("/api/watchlist/{market_id}", web::delete().to(unpin_market))
+            .route("/api/auth/login", web::post().to(login))
+            .route("/api/auth/logout", web::post().to(logout))
+            .route("/api/auth/me", web::get().to(current_user))
+            .route("/api/users", web::get().to(list_users))
+            .route("/api/users", web::post().to(create_user))
+            .route("/api/users/{username}", web::delete().to(delete_user))
+            .route("/api/signals", web::get().to(get_signals))
+            .route("/api/signals", web::post().to(ingest_signals))
+            .route("/api/venues/spreads", web::get().to(get_venue_spreads))
+            .route("/api/venues/spreads/{event_key}", web::get().to(get_venue_spread_history))
+            .route("/api/venues/links", web::post().to(link_venue_market))
+            .route("/api/venues/quotes", web::post().to(ingest_venue_quotes))
+            .route("/api/relations", web::get().to(get_relations))
+            .route("/api/relations", web::post().to(add_relation))
+            .route("/api/relations/violations", web::get().to(get_relation_violations))
+            .route("/api/relations/{a}/{b}", web::delete().to(remove_relation))
+            .route("/api/onboarding", web::get().to(get_onboarding))
+            .route("/api/onboarding/config", web::get().to(get_onboarding_config))
+            .route("/api/onboarding/config", web::put().to(set_onboarding_config))
+            .route("/api/onboarding/{market_id}/approve", web::post().to(approve_market))
+            .route("/api/onboarding/{market_id}/reject", web::post().to(reject_market))
+            .route("/api/symbols", web::get().to(get_symbols))
+            .route("/api/symbols", web::post().to(register_symbol))
+            .route("/api/symbols/resolve", web::get().to(resolve_symbol))
+            .route("/frontend/{path:.*}", web::get().to(serve_frontend_asset))
             .route("/", web::get().to(serve_frontend))
     })
     .bind(("0.0.0.0", port))?
@@ -351,10 +1802,37 @@ pub async fn start_api_server(port: u16) -> std::io::Result<()> {
     .await
 }
 
+/// Directory del frontend in sviluppo; None = asset incorporati
+struct FrontendSource(Option<std::path::PathBuf>);
+
+/// Asset del frontend: dalla directory di sviluppo se configurata, altrimenti dal binario
+fn frontend_response(source: &FrontendSource, path: &str, http: &HttpRequest) -> HttpResponse {
+    let path = if path.is_empty() { "index.html" } else { path };
+    // Solo segmenti normali: niente "..", radici assolute o prefissi di drive fuori dalla directory
+    if !std::path::Path::new(path).components().all(|c| matches!(c, std::path::Component::Normal(_))) {
+        return HttpResponse::NotFound().finish();
+    }
+
+    match &source.0 {
+        Some(dir) => match NamedFile::open(dir.join(path)) {
+            Ok(file) => file.into_response(http),
+            Err(_) => HttpResponse::NotFound().finish(),
+        },
+        None => match DashboardAssets::get(path) {
+            Some(file) => HttpResponse::Ok().content_type(file.metadata.mimetype()).body(file.data.into_owned()),
+            None => HttpResponse::NotFound().finish(),
+        },
+    }
+}
+
 /// Serve il frontend
-async fn serve_frontend() -> Result<NamedFile, Error> {
-    use actix_files::NamedFile;
-    Ok(NamedFile::open("./frontend/index.html")?)
+async fn serve_frontend(source: web::Data<FrontendSource>, http: HttpRequest) -> HttpResponse {
+    frontend_response(&source, "index.html", &http)
+}
+
+/// Serve un asset del frontend sotto /frontend
+async fn serve_frontend_asset(source: web::Data<FrontendSource>, http: HttpRequest, path: web::Path<String>) -> HttpResponse {
+    frontend_response(&source, &path, &http)
 }
 
 // Add this to use choose method
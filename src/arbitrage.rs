@@ -1,196 +1,1439 @@
 //! Arbitrage detection module
 //!
 //! Implements:
-//! 1. YES/NO arbitrage: YES_price + NO_price < 1
-//! 2. Graph-based arbitrage detection
-//! 3. Modified Moore-Bellman-Ford (MMBF) algorithm
+//! 1. YES/NO arbitrage: YES_price + NO_price < 1 (skipped when the CLOB spread eats the edge),
+//!    sized by walking both ask ladders when the local books are available
+//! 2. Graph-based arbitrage detection, on a price graph kept between scans behind a lock
+//! 3. Modified Moore-Bellman-Ford (MMBF) algorithm: one SPFA pass per subgraph from a virtual source,
+//!    re-relaxing only the subgraphs touched by price changes
+//! 4. Negative-risk events: sum of YES prices < 1, or sum of NO prices < N - 1 (until the event's end date)
+//! 5. Confidence adjusted by external signals pushed through the webhook
+//! 6. Net edge: taker fees, gas/settlement and relayer costs deducted from profit and ROI
+//! 7. Duplicate markets (same question, same end date): YES on one + NO on the other < 1
+//! 8. Mint-and-sell: YES + NO bids > 1, a set minted for 1 USDC of collateral and both tokens sold
+//! 9. Deduplication: an opportunity fingerprint (market, type, rounded edge) reported once per TTL
+//! 10. YES/NO minimum profit per market category, when the adaptive threshold controller is enabled
+//! 11. Logical relations between markets (A implies B, A and B mutually exclusive) priced against
+//!     their implied probability bounds
+//! 12. Executability: leg prices rounded to the market's tick size and the minimum order size enforced,
+//!     dropping opportunities whose edge does not survive
+//! 13. Opportunity history: every candidate logged with the filter that dropped it, when enabled
+//! 14. Graph size limits: illiquid and stale markets pruned, and the graph capped to its most
+//!     liquid markets, before MMBF runs
+//! 15. Per-leg quantities: legs without book depth sized on a max-participation share of each
+//!     side's liquidity, so every opportunity carries a feasible trade
 
 use crate::types::*;
-use fxhash::FxHashMap;
-use std::collections::HashSet;
+use crate::market::Watchlist;
+use crate::opportunity_log::{FilterReason, OpportunityLog, OpportunityLogConfig, OpportunityQuery, OpportunityRecord};
+use crate::orderbook::{LocalOrderBook, OrderBookStore, WsOrderLevel};
+use crate::relations::{MarketRelation, MarketTriangle};
+use crate::signals::SignalBook;
+use crate::threshold::{AdaptiveThresholdConfig, ThresholdController};
+use chrono::{DateTime, Duration, Utc};
+use fxhash::{FxHashMap, FxHashSet};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::sync::{Mutex, MutexGuard};
+
+/// Minimum question similarity for two markets to count as duplicates
+pub const DUPLICATE_QUESTION_SIMILARITY: f64 = 0.85;
+
+/// Maximum distance between the end dates of duplicate markets
+pub const DUPLICATE_END_DATE_TOLERANCE_HOURS: i64 = 24;
+
+/// Words that negate a question ("won't" splits into "won" and "t")
+const NEGATION_WORDS: &[&str] = &["not", "no", "never", "t", "without"];
+
+/// Words of a question in order, lowercased, without punctuation
+fn question_words(question: &str) -> Vec<String> {
+    question
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Similarity of two word sequences: twice their longest common subsequence over the total words
+///
+/// Order matters, so "A beat B" and "B beat A" are not duplicates, and questions with a different
+/// number of negations never are.
+fn word_similarity(a: &[String], b: &[String]) -> f64 {
+    let negations = |words: &[String]| words.iter().filter(|w| NEGATION_WORDS.contains(&w.as_str())).count();
+    if a.is_empty() || b.is_empty() || negations(a) != negations(b) {
+        return 0.0;
+    }
+    // LCS a riga singola: previous[j] = LCS di a[..i] e b[..j]
+    let mut previous = vec![0usize; b.len() + 1];
+    for word in a {
+        let mut current = vec![0usize; b.len() + 1];
+        for (j, other) in b.iter().enumerate() {
+            current[j + 1] = if word == other { previous[j] + 1 } else { previous[j + 1].max(current[j]) };
+        }
+        previous = current;
+    }
+    2.0 * previous[b.len()] as f64 / (a.len() + b.len()) as f64
+}
+
+/// Similarity of two questions by their ordered words (1.0 = same words in the same order)
+pub fn question_similarity(a: &str, b: &str) -> f64 {
+    word_similarity(&question_words(a), &question_words(b))
+}
+
+/// Execution costs deducted from the gross edge of an opportunity
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TradingCosts {
+    pub taker_fee_rate: f64,     // Fee taker per share, applicata a min(p, 1 - p) come sul CLOB
+    pub gas_per_trade: f64,      // USDC di gas/settlement per trade
+    pub relayer_per_order: f64,  // USDC per ordine inviato tramite relayer
+    pub reference_size: f64,     // Share per leg su cui ripartire i costi fissi se la profondità non è nota
+}
+
+impl Default for TradingCosts {
+    fn default() -> Self {
+        Self {
+            taker_fee_rate: 0.0,
+            gas_per_trade: 0.02,
+            relayer_per_order: 0.0,
+            reference_size: 100.0,
+        }
+    }
+}
+
+impl TradingCosts {
+    /// Taker fee paid per share bought at `price`
+    pub fn fee_per_share(&self, price: f64) -> f64 {
+        self.taker_fee_rate * price.min(1.0 - price).max(0.0)
+    }
+
+    /// Cost per basket of buying one share at each of `prices`, fixed costs spread over `shares` baskets
+    pub fn basket_cost(&self, prices: &[f64], shares: f64) -> f64 {
+        let fees: f64 = prices.iter().map(|&p| self.fee_per_share(p)).sum();
+        let fixed = self.gas_per_trade + self.relayer_per_order * prices.len() as f64;
+        fees + if shares > 0.0 { fixed / shares } else { fixed }
+    }
+}
+
+/// Share of each side's displayed liquidity a detected leg may take
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LegSizingConfig {
+    pub max_participation: f64, // In (0, 1]: oltre, l'ordine muoverebbe il prezzo quotato
+}
+
+impl Default for LegSizingConfig {
+    fn default() -> Self {
+        Self { max_participation: 0.1 }
+    }
+}
+
+/// Cooldown of opportunities re-detected on persistent quotes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OpportunityDedupConfig {
+    pub ttl_secs: i64,  // Finestra in cui la stessa opportunità non è riportata di nuovo; 0 = disattivata
+    pub edge_step: f64, // Arrotondamento del margine nell'impronta: variazioni minori sono la stessa quotazione
+}
+
+impl Default for OpportunityDedupConfig {
+    fn default() -> Self {
+        Self { ttl_secs: 60, edge_step: 0.001 }
+    }
+}
+
+/// Identity of a mispricing: market, arbitrage type and edge rounded to the dedup step
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OpportunityFingerprint {
+    pub market_id: String,
+    pub arb_type: ArbType,
+    pub edge_steps: i64,
+}
+
+impl OpportunityFingerprint {
+    pub fn of(opportunity: &ArbitrageOpportunity, edge_step: f64) -> Self {
+        let edge_steps = if edge_step > 0.0 { (opportunity.profit / edge_step).round() as i64 } else { 0 };
+        Self { market_id: opportunity.market_id.clone(), arb_type: opportunity.arb_type, edge_steps }
+    }
+}
+
+/// Fingerprints reported within the TTL, with the time they were reported
+#[derive(Debug, Clone, Default)]
+pub struct OpportunityCache {
+    pub config: OpportunityDedupConfig,
+    reported: FxHashMap<OpportunityFingerprint, DateTime<Utc>>,
+}
+
+impl OpportunityCache {
+    pub fn new(config: OpportunityDedupConfig) -> Self {
+        Self { config, reported: FxHashMap::default() }
+    }
+
+    /// Whether the opportunity was reported within the TTL
+    pub fn is_duplicate(&self, opportunity: &ArbitrageOpportunity, now: DateTime<Utc>) -> bool {
+        if self.config.ttl_secs <= 0 {
+            return false;
+        }
+        let fingerprint = OpportunityFingerprint::of(opportunity, self.config.edge_step);
+        let ttl = Duration::seconds(self.config.ttl_secs);
+        self.reported.get(&fingerprint).is_some_and(|&at| now - at < ttl)
+    }
+
+    /// Start the cooldown of an opportunity that was executed or published
+    pub fn record(&mut self, opportunity: &ArbitrageOpportunity, now: DateTime<Utc>) {
+        if self.config.ttl_secs > 0 {
+            self.reported.insert(OpportunityFingerprint::of(opportunity, self.config.edge_step), now);
+        }
+    }
+
+    /// Whether an opportunity is new (or its cooldown expired), recording it as reported if so
+    pub fn admit(&mut self, opportunity: &ArbitrageOpportunity, now: DateTime<Utc>) -> bool {
+        if self.is_duplicate(opportunity, now) {
+            return false;
+        }
+        self.record(opportunity, now);
+        true
+    }
+
+    /// Drop fingerprints whose cooldown expired
+    pub fn expire(&mut self, now: DateTime<Utc>) {
+        let ttl = Duration::seconds(self.config.ttl_secs);
+        self.reported.retain(|_, &mut at| now - at < ttl);
+    }
+
+    /// Opportunities not reported within the TTL; expired fingerprints are dropped
+    ///
+    /// Filtering starts no cooldown: an opportunity skipped downstream (optimizer, risk, queue)
+    /// is considered again next scan, until `record` marks it executed or published.
+    pub fn filter(&mut self, opportunities: Vec<ArbitrageOpportunity>, now: DateTime<Utc>) -> Vec<ArbitrageOpportunity> {
+        self.expire(now);
+        opportunities.into_iter().filter(|o| !self.is_duplicate(o, now)).collect()
+    }
+
+    /// Fingerprints currently cooling down
+    pub fn len(&self) -> usize {
+        self.reported.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.reported.is_empty()
+    }
+}
+
+/// YES+NO pairs executable on both ladders of one side, with blended fill prices
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DepthFill {
+    pub quantity: f64,  // Coppie YES+NO eseguibili
+    pub yes_price: f64, // Prezzo medio di riempimento YES
+    pub no_price: f64,  // Prezzo medio di riempimento NO
+}
+
+impl DepthFill {
+    /// Notional of every pair at the fill prices
+    pub fn cost(&self) -> f64 {
+        self.quantity * (self.yes_price + self.no_price)
+    }
+}
+
+/// Walk the YES and NO asks (best first) while each further pair still earns `min_profit` after taker fees
+pub fn walk_yes_no_books(yes_asks: &[WsOrderLevel], no_asks: &[WsOrderLevel], min_profit: f64, costs: &TradingCosts) -> Option<DepthFill> {
+    walk_pair_ladders(yes_asks, no_asks, min_profit, |yes, no| {
+        1.0 - yes - no - costs.fee_per_share(yes) - costs.fee_per_share(no)
+    })
+}
+
+/// Walk the YES and NO bids (best first) while each further minted pair still sells for `min_profit` over 1 after taker fees
+pub fn walk_yes_no_bids(yes_bids: &[WsOrderLevel], no_bids: &[WsOrderLevel], min_profit: f64, costs: &TradingCosts) -> Option<DepthFill> {
+    walk_pair_ladders(yes_bids, no_bids, min_profit, |yes, no| {
+        yes + no - 1.0 - costs.fee_per_share(yes) - costs.fee_per_share(no)
+    })
+}
+
+/// Take pairs level by level while `edge` of the current YES and NO prices stays above `min_profit`
+fn walk_pair_ladders(yes_levels: &[WsOrderLevel], no_levels: &[WsOrderLevel], min_profit: f64, edge: impl Fn(f64, f64) -> f64) -> Option<DepthFill> {
+    let (mut i, mut j) = (0, 0);
+    let (mut yes_left, mut no_left) = (yes_levels.first()?.size, no_levels.first()?.size);
+    let (mut quantity, mut yes_cost, mut no_cost) = (0.0, 0.0, 0.0);
+
+    while i < yes_levels.len() && j < no_levels.len() {
+        let (yes, no) = (&yes_levels[i], &no_levels[j]);
+        // Il margine marginale decresce livello dopo livello: ci si ferma al primo sotto soglia
+        if edge(yes.price, no.price) < min_profit {
+            break;
+        }
+        let pairs = yes_left.min(no_left);
+        quantity += pairs;
+        yes_cost += pairs * yes.price;
+        no_cost += pairs * no.price;
+        yes_left -= pairs;
+        no_left -= pairs;
+        if yes_left <= 1e-9 {
+            i += 1;
+            yes_left = yes_levels.get(i).map_or(0.0, |l| l.size);
+        }
+        if no_left <= 1e-9 {
+            j += 1;
+            no_left = no_levels.get(j).map_or(0.0, |l| l.size);
+        }
+    }
+
+    (quantity > 0.0).then(|| DepthFill { quantity, yes_price: yes_cost / quantity, no_price: no_cost / quantity })
+}
 
 /// Arbitrage detector for YES/NO arbitrage
 pub struct ArbitrageDetector {
     pub min_profit: f64,
     pub min_liquidity: f64,
+    pub signals: SignalBook, // Segnali esterni condivisi con /api/signals
+    pub costs: TradingCosts, // Costi dedotti dal margine lordo
+    pub dedup: OpportunityCache, // Opportunità già riportate, in cooldown fino alla scadenza del TTL
+    pub thresholds: Option<ThresholdController>, // Soglia adattiva per categoria, se configurata
+    pub order_constraints: FxHashMap<String, OrderConstraints>, // Tick e size minima per mercato (default CLOB se ignoti)
+    pub history: Option<Mutex<OpportunityLog>>, // Storico dei candidati con il filtro che li ha scartati, se abilitato
+    pub leg_sizing: LegSizingConfig, // Quota massima della liquidità di ciascun lato presa dai leg
 }
 
 impl ArbitrageDetector {
     pub fn new(min_profit: f64, min_liquidity: f64) -> Self {
         Self { 
-            min_profit: 0.005,  // Ridotto da 1% a 0.5% per aumentare frequenza trade
-            min_liquidity 
+            min_profit,
+            min_liquidity,
+            signals: SignalBook::new(),
+            costs: TradingCosts::default(),
+            dedup: OpportunityCache::new(OpportunityDedupConfig::default()),
+            thresholds: None,
+            order_constraints: FxHashMap::default(),
+            history: None,
+            leg_sizing: LegSizingConfig::default(),
+        }
+    }
+
+    /// Detector adapting the YES/NO minimum profit per category, starting from `min_profit`
+    pub fn with_adaptive_threshold(mut self, config: AdaptiveThresholdConfig) -> Self {
+        self.thresholds = Some(ThresholdController::new(config, self.min_profit));
+        self
+    }
+
+    /// YES/NO minimum profit of a market: its category's adaptive threshold, or the fixed one
+    pub fn min_profit_for(&self, market: &MarketData) -> f64 {
+        self.thresholds.as_ref().map_or(self.min_profit, |t| t.threshold_for(market.category.as_deref()))
+    }
+
+    /// Detector deducting the given execution costs
+    pub fn with_costs(mut self, costs: TradingCosts) -> Self {
+        self.costs = costs;
+        self
+    }
+
+    /// Detector sizing legs on the given share of each side's liquidity
+    pub fn with_leg_sizing(mut self, config: LegSizingConfig) -> Self {
+        self.leg_sizing = config;
+        self
+    }
+
+    /// Detector with the given re-report cooldown
+    pub fn with_dedup(mut self, config: OpportunityDedupConfig) -> Self {
+        self.dedup = OpportunityCache::new(config);
+        self
+    }
+
+    /// Detector logging every candidate, kept or dropped, to an opportunity history
+    pub fn with_history(mut self, config: OpportunityLogConfig) -> Self {
+        self.history = Some(Mutex::new(OpportunityLog::new(config)));
+        self
+    }
+
+    /// Logged candidates matching a query (empty without history)
+    pub fn opportunity_history(&self, query: &OpportunityQuery) -> Vec<OpportunityRecord> {
+        self.history.as_ref().and_then(|h| h.lock().ok()).map_or_else(Vec::new, |log| log.query(query))
+    }
+
+    /// Log a candidate's filter verdict (no-op without history)
+    fn log_candidate(&self, record: impl FnOnce() -> OpportunityRecord) {
+        if let Some(mut log) = self.history.as_ref().and_then(|h| h.lock().ok()) {
+            log.record(record());
+        }
+    }
+
+    /// Log a dropped candidate; always None, for early returns
+    fn drop_candidate(&self, record: impl FnOnce() -> OpportunityRecord) -> Option<ArbitrageOpportunity> {
+        self.log_candidate(record);
+        None
+    }
+
+    /// Register the CLOB constraints of a market
+    pub fn set_order_constraints(&mut self, market_id: &str, constraints: OrderConstraints) {
+        self.order_constraints.insert(market_id.to_string(), constraints);
+    }
+
+    /// Constraints of a market, defaulting to the standard CLOB tick and minimum size
+    pub fn constraints_for(&self, market_id: &str) -> OrderConstraints {
+        self.order_constraints.get(market_id).copied().unwrap_or_default()
+    }
+
+    /// Opportunity re-priced on the markets' tick grid, or None when it cannot be executed
+    ///
+    /// Top-of-book leg prices are rounded against the trade (buys up, sells down) and the lost
+    /// edge is deducted from profit and ROI; book-walked prices are averages of levels already on
+    /// the grid and are kept. The opportunity is dropped when the remaining profit is below
+    /// `min_profit`, when a book-sized leg rounds below the minimum order size, or when the
+    /// available liquidity cannot fund the smallest order every leg accepts.
+    pub fn executable(&self, opportunity: ArbitrageOpportunity, min_profit: f64) -> Option<ArbitrageOpportunity> {
+        let dropped = self.history.is_some().then(|| OpportunityRecord::of(&opportunity, min_profit, FilterReason::Execution));
+        let executable = self.reprice_on_grid(opportunity, min_profit);
+        if let (None, Some(record)) = (&executable, dropped) {
+            self.log_candidate(|| record);
+        }
+        executable
+    }
+
+    /// Opportunity re-priced on the tick grid by `executable`, without logging
+    fn reprice_on_grid(&self, mut opportunity: ArbitrageOpportunity, min_profit: f64) -> Option<ArbitrageOpportunity> {
+        let Some(legs) = opportunity.legs.as_mut() else { return Some(opportunity) };
+        // Nei panieri ogni leg vale una share per unità; nelle coppie statistiche la quantità è il peso di copertura
+        let weighted = opportunity.arb_type == ArbType::StatisticalArb;
+
+        let (mut erosion, mut unit_cost, mut min_units) = (0.0, 0.0, 0.0_f64);
+        for leg in legs.iter_mut() {
+            let constraints = self.constraints_for(&leg.market_id);
+            let units = if weighted { leg.quantity } else { 1.0 };
+            if weighted || leg.quantity <= 0.0 {
+                let rounded = match leg.direction {
+                    Direction::Buy => constraints.round_price(leg.price, Direction::Sell),
+                    Direction::Sell => constraints.round_price(leg.price, Direction::Buy),
+                };
+                erosion += units * match leg.direction {
+                    Direction::Buy => rounded - leg.price,
+                    Direction::Sell => leg.price - rounded,
+                };
+                leg.price = rounded;
+            } else {
+                leg.quantity = constraints.round_size(leg.quantity)?;
+            }
+            unit_cost += leg.price * units;
+            if units > 0.0 {
+                min_units = min_units.max(constraints.min_size / units);
+            }
+        }
+        if opportunity.liquidity < min_units * unit_cost {
+            return None;
+        }
+
+        let profit = opportunity.profit - erosion;
+        if profit < min_profit || profit <= 0.0 {
+            return None;
+        }
+        if opportunity.profit > 0.0 {
+            opportunity.roi_pct *= profit / opportunity.profit;
         }
+        opportunity.profit = profit;
+        if !weighted {
+            opportunity.sum_price = unit_cost;
+            if matches!(opportunity.arb_type, ArbType::YesNoSimple | ArbType::YesNoSell | ArbType::CrossMarket) {
+                for leg in legs.iter() {
+                    match leg.token_type {
+                        TokenType::Yes => opportunity.yes_price = leg.price,
+                        TokenType::No => opportunity.no_price = leg.price,
+                    }
+                }
+            }
+        }
+        Some(opportunity)
+    }
+
+    /// Fill the unsized legs with the largest quantity every side can supply
+    ///
+    /// Each leg may take `max_participation` of its side's displayed liquidity, in shares at the
+    /// leg's price, and all legs of the pair or basket share the quantity of the shallowest side.
+    /// Legs sized from the books are kept. The opportunity is dropped when the allocated size is
+    /// below a leg's minimum order size.
+    pub fn allocate_legs(&self, mut opportunity: ArbitrageOpportunity, markets: &[&MarketData]) -> Option<ArbitrageOpportunity> {
+        let Some(legs) = opportunity.legs.as_ref() else { return Some(opportunity) };
+        if opportunity.arb_type == ArbType::StatisticalArb || legs.iter().all(|l| l.quantity > 0.0) {
+            return Some(opportunity);
+        }
+        let participation = self.leg_sizing.max_participation.clamp(0.0, 1.0);
+        let capacity = |leg: &ArbitrageLeg| {
+            let Some(market) = markets.iter().find(|m| m.id == leg.market_id) else { return 0.0 };
+            let side = match leg.token_type {
+                TokenType::Yes => market.yes_liquidity,
+                TokenType::No => market.no_liquidity,
+            };
+            if leg.price > 0.0 { side * participation / leg.price } else { 0.0 }
+        };
+        let units = legs.iter().map(capacity).fold(f64::INFINITY, f64::min);
+        let Some(quantities) = legs.iter().map(|l| self.constraints_for(&l.market_id).round_size(units)).collect::<Option<Vec<f64>>>() else {
+            return self.drop_candidate(|| OpportunityRecord::of(&opportunity, self.min_profit, FilterReason::Execution));
+        };
+
+        for (leg, quantity) in opportunity.legs.iter_mut().flatten().zip(quantities) {
+            leg.quantity = quantity;
+        }
+        Some(opportunity)
+    }
+
+    /// Keep the opportunities of other detectors that remain executable on the tick grid with a positive edge
+    pub fn enforce_order_constraints(&self, opportunities: Vec<ArbitrageOpportunity>) -> Vec<ArbitrageOpportunity> {
+        opportunities.into_iter().filter_map(|o| self.executable(o, 0.0)).collect()
+    }
+
+    /// Drop opportunities already reported within the TTL, so persistent quotes are counted and traded once
+    ///
+    /// The cooldown starts only with `mark_reported`, once an opportunity is executed or published.
+    /// With history, the verdict on every opportunity is logged: accepted or duplicate.
+    pub fn dedup(&mut self, opportunities: Vec<ArbitrageOpportunity>, now: DateTime<Utc>) -> Vec<ArbitrageOpportunity> {
+        if self.history.is_none() {
+            return self.dedup.filter(opportunities, now);
+        }
+        self.dedup.expire(now);
+        let mut kept = Vec::with_capacity(opportunities.len());
+        for opportunity in opportunities {
+            let admitted = !self.dedup.is_duplicate(&opportunity, now);
+            let reason = if admitted { FilterReason::Accepted } else { FilterReason::Duplicate };
+            self.log_candidate(|| OpportunityRecord::of(&opportunity, self.min_profit, reason));
+            if admitted {
+                kept.push(opportunity);
+            }
+        }
+        kept
+    }
+
+    /// Start the dedup cooldown of an executed or published opportunity
+    pub fn mark_reported(&mut self, opportunity: &ArbitrageOpportunity, now: DateTime<Utc>) {
+        self.dedup.record(opportunity, now);
     }
 
     /// Detect YES/NO arbitrage opportunity
-    pub fn detect_yes_no_arbitrage(&self, market: &MarketData) -> Option<ArbitrageOpportunity> {
-        let sum = market.yes_price + market.no_price;
+    ///
+    /// With the YES and NO books, prices are the blended fills of the executable size
+    /// (carried in the legs' quantity) instead of the top of book.
+    pub fn detect_yes_no_arbitrage(&self, market: &MarketData, books: Option<(&LocalOrderBook, &LocalOrderBook)>) -> Option<ArbitrageOpportunity> {
+        self.detect_yes_no_arbitrage_with_threshold(market, books, self.min_profit_for(market))
+    }
+
+    /// Detect YES/NO arbitrage opportunity with an explicit minimum profit
+    pub fn detect_yes_no_arbitrage_with_threshold(
+        &self,
+        market: &MarketData,
+        books: Option<(&LocalOrderBook, &LocalOrderBook)>,
+        min_profit: f64,
+    ) -> Option<ArbitrageOpportunity> {
+        let depth = match books {
+            Some((yes_book, no_book)) => Some(walk_yes_no_books(&yes_book.asks(), &no_book.asks(), min_profit, &self.costs)?),
+            None => None,
+        };
+        let (yes_price, no_price) = depth.map_or((market.yes_price, market.no_price), |d| (d.yes_price, d.no_price));
+        let sum = yes_price + no_price;
         
         // Arbitrage condition: YES + NO < 1
         if sum >= 1.0 { 
             return None; 
         }
 
-        let arb_profit = 1.0 - sum;
+        // Margine netto: fee taker e costi fissi ripartiti sulla size eseguibile
+        let pairs = depth.map_or(self.costs.reference_size, |d| d.quantity);
+        let arb_profit = 1.0 - sum - self.costs.basket_cost(&[yes_price, no_price], pairs);
+        let total_liquidity = market.yes_liquidity + market.no_liquidity;
+        let dropped = |reason| move || {
+            OpportunityRecord::candidate(&market.id, ArbType::YesNoSimple, market.timestamp, arb_profit, total_liquidity, min_profit, reason)
+        };
         
         // Check minimum profit threshold
-        if arb_profit < self.min_profit { 
-            return None; 
+        if arb_profit < min_profit { 
+            return self.drop_candidate(dropped(FilterReason::MinProfit));
+        }
+
+        // Uno spread pari o superiore al margine lo annulla in esecuzione (già nei prezzi se dal book)
+        if depth.is_none() && market.spread.is_some_and(|spread| spread >= arb_profit) {
+            return self.drop_candidate(dropped(FilterReason::Spread));
         }
 
         // Check liquidity
-        let total_liquidity = market.yes_liquidity + market.no_liquidity;
         if total_liquidity < self.min_liquidity { 
-            return None; 
+            return self.drop_candidate(dropped(FilterReason::Liquidity));
         }
 
-        // Calculate confidence score
-        let liquidity_score = (total_liquidity / 10000.0).min(1.0);
-        let profit_score = (arb_profit / 0.05).min(1.0);
-        let volume_score = (market.volume_24h / 50000.0).min(1.0);
-        let confidence = liquidity_score * 0.3 + profit_score * 0.5 + volume_score * 0.2;
+        let confidence = self.confidence(market, total_liquidity, arb_profit);
 
-        Some(ArbitrageOpportunity {
+        self.executable(ArbitrageOpportunity {
             market_id: market.id.clone(),
             question: market.question.clone(),
             arb_type: ArbType::YesNoSimple,
             profit: arb_profit,
             roi_pct: arb_profit * 100.0,
             confidence,
-            yes_price: market.yes_price,
-            no_price: market.no_price,
+            yes_price,
+            no_price,
             sum_price: sum,
-            liquidity: total_liquidity,
+            liquidity: depth.map_or(total_liquidity, |d| d.cost()), // Con il book: nozionale eseguibile
             timestamp: market.timestamp,
             legs: Some(vec![
                 ArbitrageLeg {
                     market_id: market.id.clone(),
                     token_type: TokenType::Yes,
                     direction: Direction::Buy,
-                    price: market.yes_price,
-                    quantity: 0.0,
+                    price: yes_price,
+                    quantity: depth.map_or(0.0, |d| d.quantity),
+                    token_id: market.tokens.as_ref().map(|t| t.yes_token_id.clone()),
+                    venue: None,
                 },
                 ArbitrageLeg {
                     market_id: market.id.clone(),
                     token_type: TokenType::No,
                     direction: Direction::Buy,
-                    price: market.no_price,
-                    quantity: 0.0,
+                    price: no_price,
+                    quantity: depth.map_or(0.0, |d| d.quantity),
+                    token_id: market.tokens.as_ref().map(|t| t.no_token_id.clone()),
+                    venue: None,
                 },
             ]),
             path: None,
-        })
+            maker: None,
+        }, min_profit).and_then(|o| self.allocate_legs(o, &[market]))
     }
 
-    /// Scan all markets for arbitrage opportunities
-    pub fn scan_markets(&self, markets: &[MarketData]) -> Vec<ArbitrageOpportunity> {
+    /// Detect mint-and-sell arbitrage: YES + NO bids above 1
+    ///
+    /// One USDC of collateral mints a YES+NO set, and both tokens are sold. The collateral is
+    /// locked from the mint until both sells fill, so `liquidity` is the collateral the executable
+    /// size needs. With the books, prices are the blended fills down both bid ladders.
+    pub fn detect_yes_no_sell_arbitrage(&self, market: &MarketData, books: Option<(&LocalOrderBook, &LocalOrderBook)>) -> Option<ArbitrageOpportunity> {
+        self.detect_yes_no_sell_arbitrage_with_threshold(market, books, self.min_profit_for(market))
+    }
+
+    /// Detect mint-and-sell arbitrage with an explicit minimum profit
+    pub fn detect_yes_no_sell_arbitrage_with_threshold(
+        &self,
+        market: &MarketData,
+        books: Option<(&LocalOrderBook, &LocalOrderBook)>,
+        min_profit: f64,
+    ) -> Option<ArbitrageOpportunity> {
+        let depth = match books {
+            Some((yes_book, no_book)) => Some(walk_yes_no_bids(&yes_book.bids(), &no_book.bids(), min_profit, &self.costs)?),
+            None => None,
+        };
+        let (yes_price, no_price) = depth.map_or((market.yes_price, market.no_price), |d| (d.yes_price, d.no_price));
+        let sum = yes_price + no_price;
+        if sum <= 1.0 {
+            return None;
+        }
+
+        // Fee taker sulle due vendite; il gas fisso copre anche lo split del collaterale
+        let pairs = depth.map_or(self.costs.reference_size, |d| d.quantity);
+        let arb_profit = sum - 1.0 - self.costs.basket_cost(&[yes_price, no_price], pairs);
+        let total_liquidity = market.yes_liquidity + market.no_liquidity;
+        let dropped = |reason| move || {
+            OpportunityRecord::candidate(&market.id, ArbType::YesNoSell, market.timestamp, arb_profit, total_liquidity, min_profit, reason)
+        };
+        if arb_profit < min_profit {
+            return self.drop_candidate(dropped(FilterReason::MinProfit));
+        }
+        if depth.is_none() && market.spread.is_some_and(|spread| spread >= arb_profit) {
+            return self.drop_candidate(dropped(FilterReason::Spread));
+        }
+        if total_liquidity < self.min_liquidity {
+            return self.drop_candidate(dropped(FilterReason::Liquidity));
+        }
+        let confidence = self.confidence(market, total_liquidity, arb_profit);
+
+        let quantity = depth.map_or(0.0, |d| d.quantity);
+        let leg = |token_type: TokenType, price: f64, token_id: Option<String>| ArbitrageLeg {
+            market_id: market.id.clone(),
+            token_type,
+            direction: Direction::Sell,
+            price,
+            quantity,
+            token_id,
+            venue: None,
+        };
+        self.executable(ArbitrageOpportunity {
+            market_id: market.id.clone(),
+            question: market.question.clone(),
+            arb_type: ArbType::YesNoSell,
+            profit: arb_profit,
+            roi_pct: arb_profit * 100.0, // Per coppia il capitale impegnato è 1 USDC di collaterale
+            confidence,
+            yes_price,
+            no_price,
+            sum_price: sum,
+            liquidity: depth.map_or(total_liquidity, |d| d.quantity), // Con il book: collaterale da impegnare
+            timestamp: market.timestamp,
+            legs: Some(vec![
+                leg(TokenType::Yes, yes_price, market.tokens.as_ref().map(|t| t.yes_token_id.clone())),
+                leg(TokenType::No, no_price, market.tokens.as_ref().map(|t| t.no_token_id.clone())),
+            ]),
+            path: None,
+            maker: None,
+        }, min_profit).and_then(|o| self.allocate_legs(o, &[market]))
+    }
+
+    /// Confidence of a YES/NO opportunity from liquidity, edge and volume, adjusted by external signals
+    fn confidence(&self, market: &MarketData, total_liquidity: f64, arb_profit: f64) -> f64 {
+        let liquidity_score = (total_liquidity / 10000.0).min(1.0);
+        let profit_score = (arb_profit / 0.05).min(1.0);
+        let volume_score = (market.volume_24h / 50000.0).min(1.0);
+        let confidence = liquidity_score * 0.3 + profit_score * 0.5 + volume_score * 0.2;
+        self.signals.adjust_confidence(&market.id, confidence, market.timestamp)
+    }
+
+    /// Detect arbitrage across all outcomes of a negRisk event
+    ///
+    /// Exactly one outcome resolves YES, so a full YES basket pays 1 and a full NO basket pays N - 1.
+    pub fn detect_neg_risk_arbitrage(&self, event: &EventData) -> Option<ArbitrageOpportunity> {
+        let n = event.markets.len();
+        if !event.neg_risk || n < 2 {
+            return None;
+        }
+
+        // Un evento oltre la data di fine è in risoluzione: i prezzi non sono più negoziabili
+        if event.has_ended(event.timestamp) {
+            return None;
+        }
+
+        let yes_cost = event.yes_price_sum();
+        let no_cost = event.no_price_sum();
+        let basket_cost = |token_type: TokenType| {
+            let prices: Vec<f64> = event.markets
+                .iter()
+                .map(|m| match token_type {
+                    TokenType::Yes => m.yes_price,
+                    TokenType::No => m.no_price,
+                })
+                .collect();
+            self.costs.basket_cost(&prices, self.costs.reference_size)
+        };
+        let yes_profit = 1.0 - yes_cost - basket_cost(TokenType::Yes);
+        let no_profit = (n as f64 - 1.0) - no_cost - basket_cost(TokenType::No);
+
+        // Rendimento per dollaro investito, per confrontare i due panieri
+        let yes_roi = if yes_cost > 0.0 { yes_profit / yes_cost } else { 0.0 };
+        let no_roi = if no_cost > 0.0 { no_profit / no_cost } else { 0.0 };
+        let (token_type, cost, profit) = if yes_roi >= no_roi {
+            (TokenType::Yes, yes_cost, yes_profit)
+        } else {
+            (TokenType::No, no_cost, no_profit)
+        };
+
+        // Il paniere è limitato dal leg meno liquido
+        let liquidity = event.markets
+            .iter()
+            .map(|m| match token_type {
+                TokenType::Yes => m.yes_liquidity,
+                TokenType::No => m.no_liquidity,
+            })
+            .fold(f64::INFINITY, f64::min) * n as f64;
+        let dropped = |reason| move || {
+            OpportunityRecord::candidate(&event.id, ArbType::YesNoMulti, event.timestamp, profit, liquidity, self.min_profit, reason)
+        };
+        if profit < self.min_profit {
+            return self.drop_candidate(dropped(FilterReason::MinProfit));
+        }
+        if liquidity < self.min_liquidity {
+            return self.drop_candidate(dropped(FilterReason::Liquidity));
+        }
+
+        let roi = profit / cost;
+        let legs = event.markets
+            .iter()
+            .map(|m| ArbitrageLeg {
+                market_id: m.id.clone(),
+                token_type,
+                direction: Direction::Buy,
+                price: match token_type {
+                    TokenType::Yes => m.yes_price,
+                    TokenType::No => m.no_price,
+                },
+                quantity: 0.0,
+                token_id: m.tokens.as_ref().map(|t| t.token_id(token_type).to_string()),
+                venue: None,
+            })
+            .collect();
+
+        self.executable(ArbitrageOpportunity {
+            market_id: event.id.clone(),
+            question: event.title.clone(),
+            arb_type: ArbType::YesNoMulti,
+            profit,
+            roi_pct: roi * 100.0,
+            confidence: self.signals.adjust_confidence(&event.id, (roi / 0.05).min(1.0), event.timestamp),
+            yes_price: yes_cost,
+            no_price: no_cost,
+            sum_price: cost,
+            liquidity,
+            timestamp: event.timestamp,
+            legs: Some(legs),
+            path: Some(event.markets.iter().map(|m| m.id.clone()).collect()),
+            maker: None,
+        }, self.min_profit).and_then(|o| self.allocate_legs(o, &event.markets.iter().collect::<Vec<_>>()))
+    }
+
+    /// Scan negRisk events for basket arbitrage
+    pub fn scan_events(&self, events: &[EventData]) -> Vec<ArbitrageOpportunity> {
+        events.iter()
+            .filter_map(|event| self.detect_neg_risk_arbitrage(event))
+            .collect()
+    }
+
+    /// Detect equivalence arbitrage between two markets asking the same question
+    ///
+    /// Both resolve the same way, so YES on one plus NO on the other pays exactly 1.
+    pub fn detect_cross_market_arbitrage(&self, a: &MarketData, b: &MarketData) -> Option<ArbitrageOpportunity> {
+        let (end_a, end_b) = (a.end_date?, b.end_date?);
+        if a.id == b.id || (end_a - end_b).num_hours().abs() > DUPLICATE_END_DATE_TOLERANCE_HOURS {
+            return None;
+        }
+        if question_similarity(&a.question, &b.question) < DUPLICATE_QUESTION_SIMILARITY {
+            return None;
+        }
+        self.cross_market_opportunity(a, b)
+    }
+
+    /// Cheaper of the two YES/NO combinations of a duplicate pair, if profitable
+    fn cross_market_opportunity(&self, a: &MarketData, b: &MarketData) -> Option<ArbitrageOpportunity> {
+        // YES sul mercato più economico, NO sull'altro
+        let (yes_market, no_market) = if a.yes_price + b.no_price <= b.yes_price + a.no_price { (a, b) } else { (b, a) };
+        let (yes_price, no_price) = (yes_market.yes_price, no_market.no_price);
+        let sum = yes_price + no_price;
+        if sum >= 1.0 {
+            return None;
+        }
+
+        let profit = 1.0 - sum - self.costs.basket_cost(&[yes_price, no_price], self.costs.reference_size);
+        // La coppia è limitata dal leg meno liquido
+        let liquidity = yes_market.yes_liquidity.min(no_market.no_liquidity) * 2.0;
+        let id = format!("{}+{}", yes_market.id, no_market.id);
+        let timestamp = yes_market.timestamp.min(no_market.timestamp);
+        let dropped = |reason| {
+            let id = &id;
+            move || OpportunityRecord::candidate(id, ArbType::CrossMarket, timestamp, profit, liquidity, self.min_profit, reason)
+        };
+        if profit < self.min_profit {
+            return self.drop_candidate(dropped(FilterReason::MinProfit));
+        }
+        if liquidity < self.min_liquidity {
+            return self.drop_candidate(dropped(FilterReason::Liquidity));
+        }
+
+        let roi = profit / sum;
+        self.executable(ArbitrageOpportunity {
+            market_id: id.clone(),
+            question: yes_market.question.clone(),
+            arb_type: ArbType::CrossMarket,
+            profit,
+            roi_pct: roi * 100.0,
+            confidence: self.signals.adjust_confidence(&id, (roi / 0.05).min(1.0), timestamp),
+            yes_price,
+            no_price,
+            sum_price: sum,
+            liquidity,
+            timestamp,
+            legs: Some(vec![
+                ArbitrageLeg {
+                    market_id: yes_market.id.clone(),
+                    token_type: TokenType::Yes,
+                    direction: Direction::Buy,
+                    price: yes_price,
+                    quantity: 0.0,
+                    token_id: yes_market.tokens.as_ref().map(|t| t.yes_token_id.clone()),
+                    venue: None,
+                },
+                ArbitrageLeg {
+                    market_id: no_market.id.clone(),
+                    token_type: TokenType::No,
+                    direction: Direction::Buy,
+                    price: no_price,
+                    quantity: 0.0,
+                    token_id: no_market.tokens.as_ref().map(|t| t.no_token_id.clone()),
+                    venue: None,
+                },
+            ]),
+            path: Some(vec![yes_market.id.clone(), no_market.id.clone()]),
+            maker: None,
+        }, self.min_profit).and_then(|o| self.allocate_legs(o, &[yes_market, no_market]))
+    }
+
+    /// Scan markets for duplicate pairs priced apart
+    ///
+    /// Markets are sorted by end date so only pairs within the date tolerance are compared.
+    pub fn scan_duplicate_markets(&self, markets: &[MarketData]) -> Vec<ArbitrageOpportunity> {
+        let mut dated: Vec<(&MarketData, Vec<String>)> = markets
+            .iter()
+            .filter(|m| m.end_date.is_some())
+            .map(|m| (m, question_words(&m.question)))
+            .collect();
+        dated.sort_by_key(|(m, _)| m.end_date);
+
+        let mut opportunities = Vec::new();
+        for (i, (a, words_a)) in dated.iter().enumerate() {
+            for (b, words_b) in &dated[i + 1..] {
+                let (Some(end_a), Some(end_b)) = (a.end_date, b.end_date) else { continue };
+                if (end_b - end_a).num_hours() > DUPLICATE_END_DATE_TOLERANCE_HOURS {
+                    break;
+                }
+                if a.id == b.id || word_similarity(words_a, words_b) < DUPLICATE_QUESTION_SIMILARITY {
+                    continue;
+                }
+                opportunities.extend(self.cross_market_opportunity(a, b));
+            }
+        }
+        opportunities
+    }
+
+    /// Detect a violated relation bound between markets `a` and `b`
+    ///
+    /// A => B bounds P(A) <= P(B) and exclusion bounds P(A) + P(B) <= 1. When the prices break the
+    /// bound, the relation's basket (NO on A + YES on B, or NO on both) costs less than the 1 it pays
+    /// in every outcome the relation allows.
+    pub fn detect_relation_arbitrage(&self, relation: &MarketRelation, a: &MarketData, b: &MarketData) -> Option<ArbitrageOpportunity> {
+        if a.id == b.id {
+            return None;
+        }
+        let (token_a, token_b) = relation.kind.basket();
+        let quote = |market: &MarketData, token_type: TokenType| match token_type {
+            TokenType::Yes => (market.yes_price, market.yes_liquidity),
+            TokenType::No => (market.no_price, market.no_liquidity),
+        };
+        let ((price_a, liquidity_a), (price_b, liquidity_b)) = (quote(a, token_a), quote(b, token_b));
+        let cost = price_a + price_b;
+        if cost >= 1.0 {
+            return None;
+        }
+
+        let profit = 1.0 - cost - self.costs.basket_cost(&[price_a, price_b], self.costs.reference_size);
+        let liquidity = liquidity_a.min(liquidity_b) * 2.0;
+        let id = relation.id();
+        let timestamp = a.timestamp.min(b.timestamp);
+        let dropped = |reason| {
+            let id = &id;
+            move || OpportunityRecord::candidate(id, ArbType::Conditional, timestamp, profit, liquidity, self.min_profit, reason)
+        };
+        if profit < self.min_profit {
+            return self.drop_candidate(dropped(FilterReason::MinProfit));
+        }
+        if liquidity < self.min_liquidity {
+            return self.drop_candidate(dropped(FilterReason::Liquidity));
+        }
+
+        let roi = profit / cost;
+        let leg = |market: &MarketData, token_type: TokenType, price: f64| ArbitrageLeg {
+            market_id: market.id.clone(),
+            token_type,
+            direction: Direction::Buy,
+            price,
+            quantity: 0.0,
+            token_id: market.tokens.as_ref().map(|t| t.token_id(token_type).to_string()),
+            venue: None,
+        };
+        self.executable(ArbitrageOpportunity {
+            market_id: id.clone(),
+            question: format!("{} {} {}", a.question, relation.kind.symbol(), b.question),
+            arb_type: ArbType::Conditional,
+            profit,
+            roi_pct: roi * 100.0,
+            confidence: self.signals.adjust_confidence(&id, (roi / 0.05).min(1.0), timestamp),
+            yes_price: a.yes_price, // Probabilità implicite dei due mercati
+            no_price: b.yes_price,
+            sum_price: cost,
+            liquidity,
+            timestamp,
+            legs: Some(vec![leg(a, token_a, price_a), leg(b, token_b, price_b)]),
+            path: Some(vec![a.id.clone(), b.id.clone()]),
+            maker: None,
+        }, self.min_profit).and_then(|o| self.allocate_legs(o, &[a, b]))
+    }
+
+    /// Scan user-defined relations whose markets are both among `markets`
+    pub fn scan_relations(&self, markets: &[MarketData], relations: &[MarketRelation]) -> Vec<ArbitrageOpportunity> {
+        let by_id: FxHashMap<&str, &MarketData> = markets.iter().map(|m| (m.id.as_str(), m)).collect();
+        relations
+            .iter()
+            .filter_map(|r| self.detect_relation_arbitrage(r, by_id.get(r.a.as_str())?, by_id.get(r.b.as_str())?))
+            .collect()
+    }
+
+    /// Detect a joint market priced below what its two components imply
+    ///
+    /// With `joint` = `a` and `b`, the prices must satisfy P(joint) >= P(a) + P(b) - 1. Below that
+    /// bound, YES on the joint market plus NO on both components costs less than 1 and pays at
+    /// least 1 whichever way the three markets resolve.
+    pub fn detect_triangular_arbitrage(
+        &self,
+        triangle: &MarketTriangle,
+        joint: &MarketData,
+        a: &MarketData,
+        b: &MarketData,
+    ) -> Option<ArbitrageOpportunity> {
+        if joint.id == a.id || joint.id == b.id || a.id == b.id {
+            return None;
+        }
+        let basket = [(joint, TokenType::Yes), (a, TokenType::No), (b, TokenType::No)];
+        let quote = |market: &MarketData, token_type: TokenType| match token_type {
+            TokenType::Yes => (market.yes_price, market.yes_liquidity),
+            TokenType::No => (market.no_price, market.no_liquidity),
+        };
+        let prices: Vec<f64> = basket.iter().map(|&(m, t)| quote(m, t).0).collect();
+        let cost: f64 = prices.iter().sum();
+        if cost >= 1.0 {
+            return None;
+        }
+
+        let profit = 1.0 - cost - self.costs.basket_cost(&prices, self.costs.reference_size);
+        let liquidity = basket.iter().map(|&(m, t)| quote(m, t).1).fold(f64::INFINITY, f64::min) * 3.0;
+        let id = triangle.id();
+        let timestamp = joint.timestamp.min(a.timestamp).min(b.timestamp);
+        let dropped = |reason| {
+            let id = &id;
+            move || OpportunityRecord::candidate(id, ArbType::GraphArbitrage, timestamp, profit, liquidity, self.min_profit, reason)
+        };
+        if profit < self.min_profit {
+            return self.drop_candidate(dropped(FilterReason::MinProfit));
+        }
+        if liquidity < self.min_liquidity {
+            return self.drop_candidate(dropped(FilterReason::Liquidity));
+        }
+
+        let roi = profit / cost;
+        let legs = basket.iter().zip(&prices)
+            .map(|(&(market, token_type), &price)| ArbitrageLeg {
+                market_id: market.id.clone(),
+                token_type,
+                direction: Direction::Buy,
+                price,
+                quantity: 0.0,
+                token_id: market.tokens.as_ref().map(|t| t.token_id(token_type).to_string()),
+                venue: None,
+            })
+            .collect();
+        self.executable(ArbitrageOpportunity {
+            market_id: id.clone(),
+            question: format!("{} = {} & {}", joint.question, a.question, b.question),
+            arb_type: ArbType::GraphArbitrage,
+            profit,
+            roi_pct: roi * 100.0,
+            confidence: self.signals.adjust_confidence(&id, (roi / 0.05).min(1.0), timestamp),
+            yes_price: joint.yes_price, // Prezzo congiunto quotato e limite inferiore implicito
+            no_price: a.yes_price + b.yes_price - 1.0,
+            sum_price: cost,
+            liquidity,
+            timestamp,
+            legs: Some(legs),
+            path: Some(vec![joint.id.clone(), a.id.clone(), b.id.clone()]),
+            maker: None,
+        }, self.min_profit).and_then(|o| self.allocate_legs(o, &[joint, a, b]))
+    }
+
+    /// Scan user-defined triangles whose three markets are all among `markets`
+    ///
+    /// Each triangle is checked against both bounds: the lower one as a three-leg basket, the
+    /// upper one P(joint) <= min(P(a), P(b)) through the implications from the joint market to
+    /// each component.
+    pub fn scan_triangles(&self, markets: &[MarketData], triangles: &[MarketTriangle]) -> Vec<ArbitrageOpportunity> {
+        let by_id: FxHashMap<&str, &MarketData> = markets.iter().map(|m| (m.id.as_str(), m)).collect();
+        triangles
+            .iter()
+            .filter_map(|t| {
+                let [joint, a, b] = t.markets().map(|id| by_id.get(id).copied());
+                Some((t, joint?, a?, b?))
+            })
+            .flat_map(|(t, joint, a, b)| {
+                let [to_a, to_b] = t.implied_relations();
+                [
+                    self.detect_triangular_arbitrage(t, joint, a, b),
+                    self.detect_relation_arbitrage(&to_a, joint, a),
+                    self.detect_relation_arbitrage(&to_b, joint, b),
+                ]
+            })
+            .flatten()
+            .collect()
+    }
+
+    /// Scan all markets for buy-side and mint-and-sell opportunities, sized on the local books where held
+    pub fn scan_markets(&self, markets: &[MarketData], books: &OrderBookStore) -> Vec<ArbitrageOpportunity> {
         markets.iter()
-            .filter_map(|market| self.detect_yes_no_arbitrage(market))
+            .flat_map(|market| {
+                let books = Self::books_for(market, books);
+                self.detect_yes_no_arbitrage(market, books)
+                    .into_iter()
+                    .chain(self.detect_yes_no_sell_arbitrage(market, books))
+            })
             .collect()
     }
+
+    /// Scan markets applying relaxed thresholds for pinned markets
+    pub fn scan_markets_with_watchlist(&self, markets: &[MarketData], watchlist: &Watchlist, books: &OrderBookStore) -> Vec<ArbitrageOpportunity> {
+        markets.iter()
+            .flat_map(|market| {
+                let min_profit = watchlist.min_profit_for(&market.id, self.min_profit_for(market));
+                let books = Self::books_for(market, books);
+                self.detect_yes_no_arbitrage_with_threshold(market, books, min_profit)
+                    .into_iter()
+                    .chain(self.detect_yes_no_sell_arbitrage_with_threshold(market, books, min_profit))
+            })
+            .collect()
+    }
+
+    fn books_for<'a>(market: &MarketData, books: &'a OrderBookStore) -> Option<(&'a LocalOrderBook, &'a LocalOrderBook)> {
+        books.token_books(market.tokens.as_ref()?)
+    }
 }
 
-/// Graph-based arbitrage detector using Modified Moore-Bellman-Ford
+/// Size limits of the price graph, keeping each MMBF scan within its latency budget
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GraphLimitsConfig {
+    pub max_markets: usize,  // Mercati nel grafo (2 nodi ciascuno); oltre, esclusi i meno liquidi
+    pub min_liquidity: f64,  // Liquidità minima del lato più sottile
+    pub max_age_secs: i64,   // Quotazioni più vecchie della più recente del batch di oltre tanto sono escluse
+}
+
+impl Default for GraphLimitsConfig {
+    fn default() -> Self {
+        Self { max_markets: 500, min_liquidity: 100.0, max_age_secs: 300 }
+    }
+}
+
+/// Markets kept out of the price graph by its limits, cumulative across updates
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct GraphPruneStats {
+    pub illiquid: u64,
+    pub stale: u64,
+    pub capped: u64,            // Esclusi per il limite di dimensione
+    pub pruned_nodes: u64,
+    pub last_pruned_nodes: usize, // Nodi esclusi dall'ultimo aggiornamento
+    pub max_nodes: usize,         // Dimensione massima raggiunta dal grafo
+}
+
+/// Graph-based arbitrage detector over an incremental `PriceGraph`
+///
+/// The graph sits behind a lock, so scans through `Detector::scan` update it from a
+/// shared reference while the bot still reads its prune stats.
 pub struct GraphArbitrageDetector {
-    pub markets: FxHashMap<String, MarketData>,
+    graph: Mutex<PriceGraph>,
+}
+
+impl Default for GraphArbitrageDetector {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl GraphArbitrageDetector {
     pub fn new() -> Self {
-        Self { markets: FxHashMap::default() }
+        Self { graph: Mutex::new(PriceGraph::new()) }
+    }
+
+    /// Prune and cap the markets entering the graph
+    pub fn with_limits(self, limits: GraphLimitsConfig) -> Self {
+        Self { graph: Mutex::new(self.graph.into_inner().unwrap().with_limits(limits)) }
+    }
+
+    /// Exclusive access to the price graph
+    pub fn graph(&self) -> MutexGuard<'_, PriceGraph> {
+        self.graph.lock().unwrap()
+    }
+
+    /// Markets and nodes kept out by the graph limits
+    pub fn prune_stats(&self) -> GraphPruneStats {
+        self.graph().prune_stats()
+    }
+
+    /// Apply the latest prices of a batch of markets and detect the cycles of the updated graph
+    pub fn scan_markets(&self, markets: &[MarketData]) -> Vec<ArbitrageOpportunity> {
+        let mut graph = self.graph();
+        graph.update_markets(markets);
+        graph.detect_arbitrage_cycles()
+    }
+}
+
+/// Price graph searched with Modified Moore-Bellman-Ford
+///
+/// The graph is kept between scans: market updates replace that market's
+/// edges and mark their endpoints dirty, and a scan re-relaxes only the
+/// connected subgraphs containing a dirty node. Cycles of untouched subgraphs
+/// are served from the previous scan.
+pub struct PriceGraph {
+    markets: FxHashMap<String, MarketData>,
+    /// Edges by source node, weighted by negative log price
+    graph: FxHashMap<String, FxHashMap<String, f64>>,
+    /// Source nodes of the edges entering each node
+    incoming: FxHashMap<String, FxHashSet<String>>,
+    /// Nodes whose edges changed since the last scan
+    dirty: FxHashSet<String>,
+    /// Negative cycles found in the last relaxation of their subgraph, keyed by first node
+    cycles: FxHashMap<String, Vec<Vec<String>>>,
+    /// Nodes re-relaxed by the last scan
+    last_relaxed: usize,
+    /// Size limits applied on update; None keeps every market
+    limits: Option<GraphLimitsConfig>,
+    prune_stats: GraphPruneStats,
+}
+
+impl Default for PriceGraph {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PriceGraph {
+    pub fn new() -> Self {
+        Self {
+            markets: FxHashMap::default(),
+            graph: FxHashMap::default(),
+            incoming: FxHashMap::default(),
+            dirty: FxHashSet::default(),
+            cycles: FxHashMap::default(),
+            last_relaxed: 0,
+            limits: None,
+            prune_stats: GraphPruneStats::default(),
+        }
     }
 
+    /// Prune and cap the markets entering the graph
+    pub fn with_limits(mut self, limits: GraphLimitsConfig) -> Self {
+        self.limits = Some(limits);
+        self
+    }
+
+    /// Insert or update a market, replacing its edges if its prices changed
     pub fn add_market(&mut self, market: MarketData) {
+        let unchanged = self.markets.get(&market.id)
+            .is_some_and(|m| m.yes_price == market.yes_price && m.no_price == market.no_price);
+        if !unchanged {
+            for (from, to, weight) in Self::_market_edges(&market) {
+                self._set_edge(from, to, weight);
+            }
+        }
         self.markets.insert(market.id.clone(), market);
     }
 
-    /// Detect arbitrage cycles using MMBF algorithm
-    pub fn detect_arbitrage_cycles(&self) -> Vec<ArbitrageOpportunity> {
-        let mut opportunities = Vec::new();
-        let graph = self._build_price_graph();
-        let cycles = self._mmbf_algorithm(&graph);
-        
-        for cycle in cycles {
-            if let Some(opp) = self._cycle_to_opportunity(&cycle) {
-                opportunities.push(opp);
+    /// Apply the latest prices of a batch of markets, within the graph limits if set
+    ///
+    /// Staleness is measured against the newest quote of the batch, so replayed feeds
+    /// are pruned the same way as live ones.
+    pub fn update_markets(&mut self, markets: &[MarketData]) {
+        let Some(limits) = self.limits.clone() else {
+            for market in markets {
+                self.add_market(market.clone());
             }
+            return;
+        };
+        let depth = |m: &MarketData| m.yes_liquidity.min(m.no_liquidity);
+        let newest = markets.iter().map(|m| m.timestamp).max();
+        let is_stale = |m: &MarketData| newest.is_some_and(|t| (t - m.timestamp).num_seconds() > limits.max_age_secs);
+        let (mut illiquid, mut stale) = (0, 0);
+
+        for market in markets {
+            if depth(market) < limits.min_liquidity {
+                illiquid += 1;
+            } else if is_stale(market) {
+                stale += 1;
+            } else {
+                self.add_market(market.clone());
+                continue;
+            }
+            self.remove_market(&market.id);
         }
-        opportunities
+
+        // Mercati di batch precedenti diventati stale
+        let expired: Vec<String> = self.markets.values().filter(|m| is_stale(m)).map(|m| m.id.clone()).collect();
+        stale += expired.len();
+        for market_id in &expired {
+            self.remove_market(market_id);
+        }
+
+        // Oltre il limite restano i mercati più liquidi
+        let excess = self.markets.len().saturating_sub(limits.max_markets);
+        if excess > 0 {
+            let mut by_depth: Vec<(f64, String)> = self.markets.values().map(|m| (depth(m), m.id.clone())).collect();
+            by_depth.sort_by(|a, b| a.0.total_cmp(&b.0).then_with(|| a.1.cmp(&b.1)));
+            for (_, market_id) in by_depth.into_iter().take(excess) {
+                self.remove_market(&market_id);
+            }
+        }
+
+        let pruned = illiquid + stale + excess;
+        let stats = &mut self.prune_stats;
+        stats.illiquid += illiquid as u64;
+        stats.stale += stale as u64;
+        stats.capped += excess as u64;
+        stats.pruned_nodes += pruned as u64 * 2;
+        stats.last_pruned_nodes = pruned * 2;
+        stats.max_nodes = stats.max_nodes.max(self.graph.len());
     }
 
-    /// Build price graph for arbitrage detection
-    fn _build_price_graph(&self) -> FxHashMap<String, FxHashMap<String, f64>> {
-        let mut graph: FxHashMap<String, FxHashMap<String, f64>> = FxHashMap::default();
-        
-        for (market_id, market) in &self.markets {
-            // Use negative log prices for shortest path conversion
-            let yes_weight = -market.yes_price.ln();
-            let no_weight = -market.no_price.ln();
-            
-            // Create bidirectional edges
-            graph.entry(format!("{}-YES", market_id))
-                .or_insert_with(FxHashMap::default)
-                .insert(format!("{}-NO", market_id), yes_weight);
-            
-            graph.entry(format!("{}-NO", market_id))
-                .or_insert_with(FxHashMap::default)
-                .insert(format!("{}-YES", market_id), no_weight);
+    /// Markets and nodes kept out by the graph limits
+    pub fn prune_stats(&self) -> GraphPruneStats {
+        self.prune_stats
+    }
+
+    /// Drop a market and its nodes from the graph
+    pub fn remove_market(&mut self, market_id: &str) -> Option<MarketData> {
+        let market = self.markets.remove(market_id)?;
+        for node in [format!("{}-YES", market_id), format!("{}-NO", market_id)] {
+            self._remove_node(&node);
         }
-        graph
+        Some(market)
     }
 
-    /// Modified Moore-Bellman-Ford algorithm for cycle detection
-    fn _mmbf_algorithm(&self, graph: &FxHashMap<String, FxHashMap<String, f64>>) -> Vec<Vec<String>> {
-        let mut cycles = Vec::new();
-        let mut dist: FxHashMap<String, f64> = FxHashMap::default();
-        let mut pred: FxHashMap<String, Option<String>> = FxHashMap::default();
-        
-        // Initialize distances
-        for node in graph.keys() {
-            dist.insert(node.clone(), f64::MAX);
-            pred.insert(node.clone(), None);
+    pub fn market_count(&self) -> usize {
+        self.markets.len()
+    }
+
+    /// Nodes waiting to be re-relaxed by the next scan
+    pub fn dirty_nodes(&self) -> usize {
+        self.dirty.len()
+    }
+
+    /// Nodes re-relaxed by the last scan
+    pub fn last_relaxed(&self) -> usize {
+        self.last_relaxed
+    }
+
+    /// Detect arbitrage cycles using MMBF algorithm
+    pub fn detect_arbitrage_cycles(&mut self) -> Vec<ArbitrageOpportunity> {
+        self._relax_dirty_subgraphs();
+        self.cycles
+            .values()
+            .flatten()
+            .filter_map(|cycle| self._cycle_to_opportunity(cycle))
+            .collect()
+    }
+
+    /// Edges of a market between its YES and NO nodes
+    fn _market_edges(market: &MarketData) -> [(String, String, f64); 2] {
+        // Use negative log prices for shortest path conversion
+        let yes = format!("{}-YES", market.id);
+        let no = format!("{}-NO", market.id);
+        [
+            (yes.clone(), no.clone(), -market.yes_price.ln()),
+            (no, yes, -market.no_price.ln()),
+        ]
+    }
+
+    /// Insert or reweight an edge, marking both endpoints dirty when it changes
+    fn _set_edge(&mut self, from: String, to: String, weight: f64) {
+        let neighbors = self.graph.entry(from.clone()).or_default();
+        if neighbors.get(&to) == Some(&weight) {
+            return;
         }
-        
-        // Run MMBF from each node
-        for start in graph.keys() {
-            dist.insert(start.clone(), 0.0);
-            
-            // Relax edges V-1 times
-            for _ in 0..graph.len() {
-                for (u, neighbors) in graph.iter() {
-                    for (v, weight) in neighbors.iter() {
-                        let du = *dist.get(u).unwrap_or(&f64::MAX);
-                        let dv = *dist.get(v).unwrap_or(&f64::MAX);
-                        
-                        if du + weight < dv {
-                            dist.insert(v.clone(), du + weight);
-                            pred.insert(v.clone(), Some(u.clone()));
-                        }
-                    }
+        neighbors.insert(to.clone(), weight);
+        self.graph.entry(to.clone()).or_default();
+        self.incoming.entry(to.clone()).or_default().insert(from.clone());
+        self.dirty.insert(from);
+        self.dirty.insert(to);
+    }
+
+    /// Remove a node with its edges, marking its former neighbors dirty
+    fn _remove_node(&mut self, node: &str) {
+        let outgoing = self.graph.remove(node).unwrap_or_default();
+        let incoming = self.incoming.remove(node).unwrap_or_default();
+        for to in outgoing.keys() {
+            if let Some(sources) = self.incoming.get_mut(to) {
+                sources.remove(node);
+            }
+        }
+        for from in &incoming {
+            if let Some(neighbors) = self.graph.get_mut(from) {
+                neighbors.remove(node);
+            }
+        }
+        self.dirty.remove(node);
+        self.cycles.remove(node);
+        // I cicli in cache che passavano dal nodo rimosso vanno ricalcolati
+        self.dirty.extend(outgoing.into_keys().chain(incoming).filter(|n| n != node));
+    }
+
+    /// Re-run cycle detection over each connected subgraph containing a dirty node
+    fn _relax_dirty_subgraphs(&mut self) {
+        self.last_relaxed = 0;
+        let mut seen: FxHashSet<String> = FxHashSet::default();
+        let dirty: Vec<String> = self.dirty.drain().collect();
+        for node in dirty {
+            if seen.contains(&node) || !self.graph.contains_key(&node) {
+                continue;
+            }
+            let component = self._component(&node);
+            for member in &component {
+                self.cycles.remove(member);
+            }
+            for cycle in self._spfa_cycles(&component) {
+                self.cycles.entry(cycle[0].clone()).or_default().push(cycle);
+            }
+            self.last_relaxed += component.len();
+            seen.extend(component);
+        }
+    }
+
+    /// Nodes connected to `start`, ignoring edge direction
+    fn _component(&self, start: &str) -> Vec<String> {
+        let mut component = vec![start.to_string()];
+        let mut visited: FxHashSet<&str> = FxHashSet::default();
+        visited.insert(start);
+        let mut i = 0;
+        while i < component.len() {
+            let node = component[i].clone();
+            let outgoing = self.graph.get(&node).into_iter().flat_map(|n| n.keys());
+            let incoming = self.incoming.get(&node).into_iter().flatten();
+            for next in outgoing.chain(incoming) {
+                if visited.insert(next.as_str()) {
+                    component.push(next.clone());
                 }
             }
-            
-            // Check for negative cycles (arbitrage)
-            for (u, neighbors) in graph.iter() {
-                for (v, weight) in neighbors.iter() {
-                    let du = *dist.get(u).unwrap_or(&f64::MAX);
-                    let dv = *dist.get(v).unwrap_or(&f64::MAX);
-                    
-                    if du + weight < dv {
-                        // Found negative cycle
-                        if let Some(cycle) = self._extract_cycle(&pred, v) {
+            i += 1;
+        }
+        component
+    }
+
+    /// Negative cycles of a subgraph, by SPFA from a virtual source linked to every node
+    ///
+    /// All nodes start at distance 0 and only nodes whose distance improved are
+    /// re-queued, so a subgraph without negative cycles exits after a few passes.
+    /// A node whose shortest path reaches V edges sits behind a negative cycle;
+    /// the nodes of each cycle found are then frozen so the search can go on.
+    fn _spfa_cycles(&self, nodes: &[String]) -> Vec<Vec<String>> {
+        let mut cycles = Vec::new();
+        let mut dist: FxHashMap<&str, f64> = nodes.iter().map(|n| (n.as_str(), 0.0)).collect();
+        let mut hops: FxHashMap<&str, usize> = nodes.iter().map(|n| (n.as_str(), 0)).collect();
+        let mut pred: FxHashMap<String, Option<String>> = nodes.iter().map(|n| (n.clone(), None)).collect();
+        let mut queue: VecDeque<&str> = nodes.iter().map(String::as_str).collect();
+        let mut queued: FxHashSet<&str> = queue.iter().copied().collect();
+        let mut in_cycle: FxHashSet<String> = FxHashSet::default();
+
+        while let Some(u) = queue.pop_front() {
+            queued.remove(u);
+            if in_cycle.contains(u) {
+                continue;
+            }
+            let Some(neighbors) = self.graph.get(u) else { continue };
+            for (v, weight) in neighbors {
+                let v = v.as_str();
+                if in_cycle.contains(v) {
+                    continue;
+                }
+                let du = dist[u];
+                if du + weight >= dist[v] {
+                    continue;
+                }
+                dist.insert(v, du + weight);
+                pred.insert(v.to_string(), Some(u.to_string()));
+                let h = hops[u] + 1;
+                hops.insert(v, h);
+
+                if h >= nodes.len() {
+                    // Found negative cycle
+                    if let Some(cycle) = self._extract_cycle(&pred, v) {
+                        if !cycle.iter().any(|n| in_cycle.contains(n)) {
+                            in_cycle.extend(cycle.iter().cloned());
                             cycles.push(cycle);
                         }
                     }
+                    if in_cycle.contains(u) {
+                        break;
+                    }
+                } else if queued.insert(v) {
+                    queue.push_back(v);
                 }
             }
-            
-            // Reset for next iteration
-            for node in graph.keys() {
-                dist.insert(node.clone(), f64::MAX);
-                pred.insert(node.clone(), None);
-            }
         }
         cycles
     }
@@ -219,6 +1462,7 @@ impl GraphArbitrageDetector {
         if cycle.len() < 2 { return None; }
 
         let mut profit = 1.0;
+        let mut timestamp: Option<DateTime<Utc>> = None; // Gamba più vecchia del ciclo, come negli altri detector multi-mercato
         for node in cycle {
             if let Some((market_id, token_type)) = self._parse_node(node) {
                 if let Some(market) = self.markets.get(&market_id) {
@@ -227,9 +1471,11 @@ impl GraphArbitrageDetector {
                         TokenType::No => market.no_price,
                     };
                     profit *= price;
+                    timestamp = Some(timestamp.map_or(market.timestamp, |t| t.min(market.timestamp)));
                 }
             }
         }
+        let timestamp = timestamp?;
         
         let arb_profit = 1.0 - profit;
         if arb_profit <= 0.001 { return None; }  // Minimum 0.1% profit
@@ -245,9 +1491,10 @@ impl GraphArbitrageDetector {
             no_price: 0.0,
             sum_price: profit,
             liquidity: 0.0,
-            timestamp: chrono::Utc::now(),
+            timestamp,
             legs: None,
             path: Some(cycle.to_vec()),
+            maker: None,
         })
     }
 
@@ -264,3 +1511,483 @@ impl GraphArbitrageDetector {
         Some((parts[1].to_string(), token_type))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(neg_risk: bool, prices: &[(f64, f64)]) -> EventData {
+        EventData {
+            id: "event_1".to_string(),
+            title: "Who wins?".to_string(),
+            neg_risk,
+            markets: prices
+                .iter()
+                .enumerate()
+                .map(|(i, &(yes_price, no_price))| MarketData {
+                    id: format!("outcome_{}", i),
+                    yes_price,
+                    no_price,
+                    yes_liquidity: 5000.0,
+                    no_liquidity: 5000.0,
+                    ..MarketData::default()
+                })
+                .collect(),
+            timestamp: chrono::Utc::now(),
+            tags: Vec::new(),
+            end_date: None,
+        }
+    }
+
+    #[test]
+    fn test_spread_wider_than_edge_is_skipped() {
+        let detector = ArbitrageDetector::new(0.005, 1000.0);
+        let mut market = MarketData {
+            id: "m1".to_string(),
+            yes_price: 0.48,
+            no_price: 0.50,
+            yes_liquidity: 5000.0,
+            no_liquidity: 5000.0,
+            ..MarketData::default()
+        };
+        assert!(detector.detect_yes_no_arbitrage(&market, None).is_some());
+
+        market.spread = Some(0.01);
+        assert!(detector.detect_yes_no_arbitrage(&market, None).is_some());
+        market.spread = Some(0.03);
+        assert!(detector.detect_yes_no_arbitrage(&market, None).is_none());
+    }
+
+    #[test]
+    fn test_depth_aware_sizing_walks_both_books() {
+        let detector = ArbitrageDetector::new(0.005, 1000.0);
+        let book = |asset_id: &str, asks: &[(f64, f64)]| {
+            LocalOrderBook::from_snapshot(&crate::orderbook::WsBookEvent {
+                asset_id: asset_id.to_string(),
+                market: String::new(),
+                bids: Vec::new(),
+                asks: asks.iter().map(|&(price, size)| WsOrderLevel { price, size }).collect(),
+                timestamp: 0,
+                hash: None,
+            })
+        };
+        let market = MarketData {
+            id: "m1".to_string(),
+            yes_price: 0.45,
+            no_price: 0.50,
+            yes_liquidity: 5000.0,
+            no_liquidity: 5000.0,
+            ..MarketData::default()
+        };
+
+        // 100 coppie a 0.95, 50 a 0.97 (YES 0.47), poi 0.997 sotto soglia
+        let yes = book("yes", &[(0.45, 100.0), (0.47, 200.0)]);
+        let no = book("no", &[(0.50, 150.0), (0.527, 500.0)]);
+        let opp = detector.detect_yes_no_arbitrage(&market, Some((&yes, &no))).unwrap();
+        let legs = opp.legs.unwrap();
+        assert!((legs[0].quantity - 150.0).abs() < 1e-9);
+        assert!((opp.yes_price - (100.0 * 0.45 + 50.0 * 0.47) / 150.0).abs() < 1e-9);
+        assert!((opp.no_price - 0.50).abs() < 1e-9);
+        assert!((opp.liquidity - 150.0 * opp.sum_price).abs() < 1e-9);
+        assert!(opp.profit < 1.0 - 0.95);
+
+        // Il top of book del mercato promette un margine che i book non hanno
+        let thin = book("no", &[(0.552, 150.0)]);
+        assert!(detector.detect_yes_no_arbitrage(&market, Some((&yes, &thin))).is_none());
+    }
+
+    #[test]
+    fn test_mint_and_sell_when_bids_exceed_one() {
+        let detector = ArbitrageDetector::new(0.005, 1000.0);
+        let market = MarketData {
+            id: "m1".to_string(),
+            yes_price: 0.53,
+            no_price: 0.50,
+            yes_liquidity: 5000.0,
+            no_liquidity: 5000.0,
+            ..MarketData::default()
+        };
+        assert!(detector.detect_yes_no_arbitrage(&market, None).is_none());
+        let opp = detector.detect_yes_no_sell_arbitrage(&market, None).unwrap();
+        assert_eq!(opp.arb_type, ArbType::YesNoSell);
+        let net = 0.03 - detector.costs.basket_cost(&[0.53, 0.50], detector.costs.reference_size);
+        assert!((opp.profit - net).abs() < 1e-9);
+        assert!(opp.legs.unwrap().iter().all(|l| l.direction == Direction::Sell));
+
+        // Sui book: 100 coppie a 1.03, poi 1.002 sotto soglia
+        let book = |asset_id: &str, bids: &[(f64, f64)]| {
+            LocalOrderBook::from_snapshot(&crate::orderbook::WsBookEvent {
+                asset_id: asset_id.to_string(),
+                market: String::new(),
+                bids: bids.iter().map(|&(price, size)| WsOrderLevel { price, size }).collect(),
+                asks: Vec::new(),
+                timestamp: 0,
+                hash: None,
+            })
+        };
+        let yes = book("yes", &[(0.53, 100.0), (0.502, 300.0)]);
+        let no = book("no", &[(0.50, 400.0)]);
+        let opp = detector.detect_yes_no_sell_arbitrage(&market, Some((&yes, &no))).unwrap();
+        assert!((opp.liquidity - 100.0).abs() < 1e-9);
+        assert!((opp.legs.unwrap()[0].quantity - 100.0).abs() < 1e-9);
+
+        let scanned = detector.scan_markets(&[market], &OrderBookStore::default());
+        assert_eq!(scanned.len(), 1);
+        assert_eq!(scanned[0].arb_type, ArbType::YesNoSell);
+    }
+
+    #[test]
+    fn test_tick_rounding_and_min_size_drop_unexecutable_edges() {
+        let mut detector = ArbitrageDetector::new(0.005, 0.0).with_costs(TradingCosts { gas_per_trade: 0.0, ..TradingCosts::default() });
+        let market = |yes_price: f64, no_price: f64| MarketData {
+            id: "m1".to_string(),
+            yes_price,
+            no_price,
+            yes_liquidity: 5000.0,
+            no_liquidity: 5000.0,
+            ..MarketData::default()
+        };
+
+        // 0.474 + 0.516 = 0.99 a metà tick: sulla griglia 0.01 gli acquisti costano 0.48 + 0.52
+        assert!(detector.detect_yes_no_arbitrage(&market(0.474, 0.516), None).is_none());
+
+        // Con tick 0.001 il margine resta
+        detector.set_order_constraints("m1", OrderConstraints { tick_size: 0.001, min_size: 5.0 });
+        let opp = detector.detect_yes_no_arbitrage(&market(0.4745, 0.516), None).unwrap();
+        assert!((opp.profit - 0.009).abs() < 1e-9);
+        assert!((opp.sum_price - 0.991).abs() < 1e-9);
+        assert!((opp.roi_pct - 0.9).abs() < 1e-9);
+
+        // Liquidità insufficiente per l'ordine minimo (5 coppie a 0.99)
+        detector.set_order_constraints("m1", OrderConstraints { tick_size: 0.01, min_size: 5.0 });
+        let thin = MarketData { yes_liquidity: 2.0, no_liquidity: 2.0, ..market(0.47, 0.50) };
+        assert!(detector.detect_yes_no_arbitrage(&thin, None).is_none());
+        assert!(detector.detect_yes_no_arbitrage(&market(0.47, 0.50), None).is_some());
+    }
+
+    #[test]
+    fn test_relation_violations_buy_the_covering_basket() {
+        use crate::relations::RelationKind;
+
+        let detector = ArbitrageDetector::new(0.005, 0.0).with_costs(TradingCosts { gas_per_trade: 0.0, ..TradingCosts::default() });
+        let market = |id: &str, yes_price: f64, no_price: f64| MarketData {
+            id: id.to_string(),
+            yes_price,
+            no_price,
+            yes_liquidity: 5000.0,
+            no_liquidity: 5000.0,
+            ..MarketData::default()
+        };
+        let markets = vec![market("a", 0.60, 0.40), market("b", 0.50, 0.50), market("c", 0.30, 0.70)];
+
+        // A => B con P(A) = 0.60 > P(B) = 0.50: NO su A + YES su B costa 0.90 e paga almeno 1
+        let implies = MarketRelation::new("a", "b", RelationKind::Implies);
+        let opp = detector.detect_relation_arbitrage(&implies, &markets[0], &markets[1]).unwrap();
+        assert_eq!(opp.arb_type, ArbType::Conditional);
+        assert_eq!(opp.market_id, "a=>b");
+        assert!((opp.profit - 0.10).abs() < 1e-9);
+        let legs: Vec<(&str, TokenType)> = opp.legs.as_ref().unwrap().iter().map(|l| (l.market_id.as_str(), l.token_type)).collect();
+        assert_eq!(legs, vec![("a", TokenType::No), ("b", TokenType::Yes)]);
+
+        // A | B con P(A) + P(B) = 1.10: NO su entrambi costa 0.90
+        let exclusive = MarketRelation::new("a", "b", RelationKind::MutuallyExclusive);
+        assert!(detector.detect_relation_arbitrage(&exclusive, &markets[0], &markets[1]).is_some());
+
+        // Prezzi coerenti con la relazione: nessuna opportunità
+        let consistent = vec![
+            MarketRelation::new("c", "b", RelationKind::Implies),
+            MarketRelation::new("b", "c", RelationKind::MutuallyExclusive),
+            MarketRelation::new("a", "missing", RelationKind::Implies),
+        ];
+        assert!(detector.scan_relations(&markets, &consistent).is_empty());
+        assert_eq!(detector.scan_relations(&markets, &[implies, exclusive]).len(), 2);
+    }
+
+    #[test]
+    fn test_triangle_buys_joint_priced_below_its_components() {
+        let detector = ArbitrageDetector::new(0.005, 0.0).with_costs(TradingCosts { gas_per_trade: 0.0, ..TradingCosts::default() });
+        let market = |id: &str, yes_price: f64| MarketData {
+            id: id.to_string(),
+            yes_price,
+            no_price: 1.0 - yes_price,
+            yes_liquidity: 5000.0,
+            no_liquidity: 5000.0,
+            ..MarketData::default()
+        };
+        // Primarie 0.90, partito 0.70: l'elezione vale almeno 0.60 ma è quotata 0.50
+        let markets = vec![market("election", 0.50), market("primary", 0.90), market("party", 0.70)];
+        let triangle = MarketTriangle::new("election", "primary", "party");
+
+        let opps = detector.scan_triangles(&markets, std::slice::from_ref(&triangle));
+        assert_eq!(opps.len(), 1);
+        let opp = &opps[0];
+        assert_eq!((opp.arb_type, opp.market_id.as_str()), (ArbType::GraphArbitrage, "election=primary&party"));
+        assert_eq!(opp.path.as_deref().unwrap(), ["election", "primary", "party"]);
+        assert!((opp.sum_price - 0.90).abs() < 1e-9);
+        let legs: Vec<(&str, TokenType)> = opp.legs.as_ref().unwrap().iter().map(|l| (l.market_id.as_str(), l.token_type)).collect();
+        assert_eq!(legs, vec![("election", TokenType::Yes), ("primary", TokenType::No), ("party", TokenType::No)]);
+
+        // Prezzo congiunto sopra il limite, o un mercato mancante: nessuna opportunità
+        let consistent = vec![market("election", 0.62), market("primary", 0.90), market("party", 0.70)];
+        assert!(detector.scan_triangles(&consistent, std::slice::from_ref(&triangle)).is_empty());
+        assert!(detector.scan_triangles(&markets[..2], std::slice::from_ref(&triangle)).is_empty());
+
+        // Congiunto sopra il partito (0.80 > 0.70): NO sull'elezione + YES sul partito
+        let above = vec![market("election", 0.80), market("primary", 0.90), market("party", 0.70)];
+        let opps = detector.scan_triangles(&above, &[triangle]);
+        assert_eq!(opps.len(), 1);
+        assert_eq!((opps[0].arb_type, opps[0].market_id.as_str()), (ArbType::Conditional, "election=>party"));
+        assert!((opps[0].sum_price - 0.90).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_legs_are_sized_on_the_shallowest_side() {
+        let detector = ArbitrageDetector::new(0.02, 1000.0);
+        // Lato NO sottile: 10% di 1000 USDC a 0.50 = 200 share, contro le 2000 del lato YES
+        let market = MarketData {
+            id: "m1".to_string(),
+            yes_price: 0.45,
+            no_price: 0.50,
+            yes_liquidity: 9000.0,
+            no_liquidity: 1000.0,
+            ..MarketData::default()
+        };
+        let opp = detector.detect_yes_no_arbitrage(&market, None).unwrap();
+        let quantities: Vec<f64> = opp.legs.as_ref().unwrap().iter().map(|l| l.quantity).collect();
+        assert_eq!(quantities, vec![200.0, 200.0]);
+
+        // Con una partecipazione minima il lato sottile non regge la size minima d'ordine
+        let cautious = ArbitrageDetector::new(0.02, 1000.0).with_leg_sizing(LegSizingConfig { max_participation: 0.001 });
+        assert!(cautious.detect_yes_no_arbitrage(&market, None).is_none());
+    }
+
+    #[test]
+    fn test_persistent_quote_is_reported_once_per_ttl() {
+        let mut detector = ArbitrageDetector::new(0.005, 1000.0);
+        let mut market = MarketData {
+            id: "m1".to_string(),
+            yes_price: 0.45,
+            no_price: 0.50,
+            yes_liquidity: 5000.0,
+            no_liquidity: 5000.0,
+            ..MarketData::default()
+        };
+        let now = chrono::Utc::now();
+        let scan = |detector: &ArbitrageDetector, market: &MarketData| detector.scan_markets(std::slice::from_ref(market), &OrderBookStore::default());
+
+        // Finché non è eseguita o pubblicata l'opportunità resta candidabile
+        let first = detector.dedup(scan(&detector, &market), now);
+        assert_eq!(first.len(), 1);
+        assert_eq!(detector.dedup(scan(&detector, &market), now + Duration::seconds(10)).len(), 1);
+        detector.mark_reported(&first[0], now);
+        let again = scan(&detector, &market);
+        assert!(detector.dedup(again, now + Duration::seconds(30)).is_empty());
+
+        // Un margine diverso è una nuova quotazione; la stessa torna dopo il TTL
+        market.yes_price = 0.43;
+        let wider = detector.dedup(scan(&detector, &market), now + Duration::seconds(30));
+        assert_eq!(wider.len(), 1);
+        detector.mark_reported(&wider[0], now + Duration::seconds(30));
+        market.yes_price = 0.45;
+        let expired = scan(&detector, &market);
+        assert_eq!(detector.dedup(expired, now + Duration::seconds(61)).len(), 1);
+        assert_eq!(detector.dedup.len(), 1);
+    }
+
+    #[test]
+    fn test_profit_is_net_of_fees_and_gas() {
+        let market = MarketData {
+            id: "m1".to_string(),
+            yes_price: 0.48,
+            no_price: 0.50,
+            yes_liquidity: 5000.0,
+            no_liquidity: 5000.0,
+            ..MarketData::default()
+        };
+        let costs = TradingCosts { taker_fee_rate: 0.002, gas_per_trade: 0.10, relayer_per_order: 0.05, reference_size: 100.0 };
+        let detector = ArbitrageDetector::new(0.005, 1000.0).with_costs(costs);
+
+        // 0.02 lordi - fee (0.002 * 0.48 + 0.002 * 0.50) - (0.10 + 2 * 0.05) / 100
+        let opp = detector.detect_yes_no_arbitrage(&market, None).unwrap();
+        let net = 0.02 - 0.00196 - 0.002;
+        assert!((opp.profit - net).abs() < 1e-9);
+        assert!((opp.roi_pct - net * 100.0).abs() < 1e-9);
+
+        // Fee più alte annullano il margine
+        let expensive = ArbitrageDetector::new(0.005, 1000.0).with_costs(TradingCosts { taker_fee_rate: 0.05, ..TradingCosts::default() });
+        assert!(expensive.detect_yes_no_arbitrage(&market, None).is_none());
+    }
+
+    #[test]
+    fn test_neg_risk_basket_arbitrage() {
+        let detector = ArbitrageDetector::new(0.005, 1000.0);
+
+        // YES: 0.30 + 0.30 + 0.32 = 0.92 < 1
+        let cheap_yes = event(true, &[(0.30, 0.72), (0.30, 0.72), (0.32, 0.70)]);
+        let opp = detector.detect_neg_risk_arbitrage(&cheap_yes).unwrap();
+        assert_eq!(opp.arb_type, ArbType::YesNoMulti);
+        assert!((opp.profit - (0.08 - 0.02 / 100.0)).abs() < 1e-9); // Al netto del gas di default
+        let legs = opp.legs.unwrap();
+        assert_eq!(legs.len(), 3);
+        assert!(legs.iter().all(|l| l.token_type == TokenType::Yes));
+
+        // NO: 0.60 + 0.60 + 0.70 = 1.90 < 2
+        let cheap_no = event(true, &[(0.42, 0.60), (0.42, 0.60), (0.32, 0.70)]);
+        let opp = detector.detect_neg_risk_arbitrage(&cheap_no).unwrap();
+        assert!(opp.legs.unwrap().iter().all(|l| l.token_type == TokenType::No));
+
+        // Prezzi coerenti o evento non negRisk: nessuna opportunità
+        assert!(detector.detect_neg_risk_arbitrage(&event(true, &[(0.50, 0.51), (0.51, 0.50)])).is_none());
+        assert!(detector.detect_neg_risk_arbitrage(&event(false, &[(0.30, 0.72), (0.30, 0.72)])).is_none());
+
+        // Evento oltre la data di fine: non più negoziabile
+        let mut ended = cheap_yes.clone();
+        ended.end_date = Some(ended.timestamp - chrono::Duration::hours(1));
+        assert!(detector.detect_neg_risk_arbitrage(&ended).is_none());
+    }
+
+    #[test]
+    fn test_duplicate_market_equivalence_arbitrage() {
+        let detector = ArbitrageDetector::new(0.005, 1000.0);
+        let end = chrono::DateTime::from_timestamp(1_790_000_000, 0).unwrap();
+        let market = |id: &str, question: &str, yes_price: f64, no_price: f64, hours: i64| MarketData {
+            id: id.to_string(),
+            question: question.to_string(),
+            yes_price,
+            no_price,
+            yes_liquidity: 5000.0,
+            no_liquidity: 5000.0,
+            end_date: Some(end + chrono::Duration::hours(hours)),
+            ..MarketData::default()
+        };
+        let a = market("a", "Will Bitcoin close above $100k on December 31?", 0.40, 0.58, 0);
+        let b = market("b", "Will Bitcoin close above $100k on December 31", 0.55, 0.45, 2);
+        let other = market("c", "Will Ethereum close above $5k on December 31?", 0.30, 0.30, 0);
+
+        // YES su "a" (0.40) + NO su "b" (0.45) = 0.85 < 1
+        let opp = detector.detect_cross_market_arbitrage(&b, &a).unwrap();
+        assert_eq!(opp.arb_type, ArbType::CrossMarket);
+        assert!((opp.profit - (0.15 - 0.02 / 100.0)).abs() < 1e-9);
+        let legs = opp.legs.unwrap();
+        assert_eq!((legs[0].market_id.as_str(), legs[0].token_type), ("a", TokenType::Yes));
+        assert_eq!((legs[1].market_id.as_str(), legs[1].token_type), ("b", TokenType::No));
+
+        // Domanda diversa, data di fine lontana o data ignota: non sono duplicati
+        assert!(detector.detect_cross_market_arbitrage(&a, &other).is_none());
+        let later = market("b", "Will Bitcoin close above $100k on December 31?", 0.55, 0.45, 72);
+        assert!(detector.detect_cross_market_arbitrage(&a, &later).is_none());
+        assert!(detector.detect_cross_market_arbitrage(&a, &MarketData { end_date: None, ..b.clone() }).is_none());
+
+        // Stesse parole in altro ordine o con una negazione: eventi opposti, non duplicati
+        assert_eq!(question_similarity("Will Lakers beat Celtics?", "Will Celtics beat Lakers?"), 0.5);
+        assert_eq!(question_similarity("Will the Fed cut rates in December?", "Will the Fed not cut rates in December?"), 0.0);
+        assert_eq!(question_similarity("Will the Fed cut rates?", "Won't the Fed cut rates?"), 0.0);
+
+        let found = detector.scan_duplicate_markets(&[other, later, b, a]);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].market_id, "a+b");
+    }
+
+    #[test]
+    fn test_graph_relaxes_only_changed_subgraphs() {
+        let market = |id: &str, yes_price: f64| MarketData {
+            id: id.to_string(),
+            yes_price,
+            no_price: 1.0 - yes_price - 0.02,
+            ..MarketData::default()
+        };
+        let mut detector = PriceGraph::new();
+        detector.update_markets(&[market("a", 0.40), market("b", 0.60), market("c", 0.30)]);
+        assert_eq!(detector.dirty_nodes(), 6);
+        assert!(detector.detect_arbitrage_cycles().is_empty());
+        assert_eq!(detector.last_relaxed(), 6);
+
+        // Prezzi invariati: nessun sottografo da rilassare
+        detector.update_markets(&[market("a", 0.40), market("b", 0.60), market("c", 0.30)]);
+        assert_eq!(detector.dirty_nodes(), 0);
+        detector.detect_arbitrage_cycles();
+        assert_eq!(detector.last_relaxed(), 0);
+
+        // Solo il mercato "b" è cambiato
+        detector.add_market(market("b", 0.55));
+        assert_eq!(detector.dirty_nodes(), 2);
+        detector.detect_arbitrage_cycles();
+        assert_eq!(detector.last_relaxed(), 2);
+
+        assert!(detector.remove_market("c").is_some());
+        assert_eq!(detector.market_count(), 2);
+        detector.detect_arbitrage_cycles();
+        assert_eq!(detector.last_relaxed(), 0);
+    }
+
+    #[test]
+    fn test_spfa_finds_negative_cycles_per_subgraph() {
+        let mut detector = PriceGraph::new();
+        // YES * NO > 1: ciclo YES -> NO -> YES di peso negativo
+        for (id, yes_price, no_price) in [("a", 0.40, 0.55), ("b", 1.20, 0.95), ("c", 0.30, 0.60)] {
+            detector.add_market(MarketData { id: id.to_string(), yes_price, no_price, ..MarketData::default() });
+        }
+        detector.detect_arbitrage_cycles();
+        let cycles: Vec<&Vec<String>> = detector.cycles.values().flatten().collect();
+        assert_eq!(cycles.len(), 1);
+        let mut nodes = cycles[0].clone();
+        nodes.sort();
+        assert_eq!(nodes, vec!["b-NO".to_string(), "b-YES".to_string()]);
+
+        detector.add_market(MarketData { id: "b".to_string(), yes_price: 0.45, no_price: 0.50, ..MarketData::default() });
+        detector.detect_arbitrage_cycles();
+        assert!(detector.cycles.values().all(Vec::is_empty));
+    }
+
+    #[test]
+    fn test_graph_opportunity_carries_the_cycle_price_time() {
+        let recorded = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let mut detector = PriceGraph::new();
+        for (id, age_secs) in [("a", 30), ("b", 5)] {
+            let timestamp = recorded - Duration::seconds(age_secs);
+            detector.add_market(MarketData { id: id.to_string(), yes_price: 0.40, no_price: 0.50, timestamp, ..MarketData::default() });
+        }
+
+        // Replay: l'opportunità porta l'ora registrata della gamba più vecchia, non l'orologio di sistema
+        let opportunity = detector._cycle_to_opportunity(&["a-YES".to_string(), "b-NO".to_string()]).unwrap();
+        assert_eq!(opportunity.timestamp, recorded - Duration::seconds(30));
+        assert!(detector._cycle_to_opportunity(&["x-YES".to_string(), "y-NO".to_string()]).is_none());
+    }
+
+    #[test]
+    fn test_graph_limits_prune_illiquid_stale_and_excess_markets() {
+        let now = chrono::Utc::now();
+        let market = |id: &str, liquidity: f64, age_secs: i64| MarketData {
+            id: id.to_string(),
+            yes_price: 0.45,
+            no_price: 0.50,
+            yes_liquidity: liquidity,
+            no_liquidity: liquidity,
+            timestamp: now - Duration::seconds(age_secs),
+            ..MarketData::default()
+        };
+        let mut detector = PriceGraph::new()
+            .with_limits(GraphLimitsConfig { max_markets: 2, min_liquidity: 100.0, max_age_secs: 60 });
+
+        // "thin" sotto la liquidità minima, "old" stale rispetto al batch, "d" il meno liquido oltre il limite
+        detector.update_markets(&[market("a", 5000.0, 0), market("b", 3000.0, 10), market("thin", 50.0, 0), market("old", 5000.0, 120), market("d", 1000.0, 0)]);
+        assert_eq!(detector.market_count(), 2);
+        assert!(detector.markets.contains_key("a") && detector.markets.contains_key("b"));
+        let stats = detector.prune_stats();
+        assert_eq!((stats.illiquid, stats.stale, stats.capped), (1, 1, 1));
+        assert_eq!((stats.pruned_nodes, stats.last_pruned_nodes, stats.max_nodes), (6, 6, 4));
+
+        // Un mercato già nel grafo che non si aggiorna diventa stale e lascia il posto
+        let later = |id: &str, liquidity: f64| MarketData { timestamp: now + Duration::seconds(90), ..market(id, liquidity, 0) };
+        detector.update_markets(&[later("a", 5000.0), later("d", 1000.0)]);
+        assert!(!detector.markets.contains_key("b"));
+        assert_eq!(detector.market_count(), 2);
+        assert_eq!(detector.prune_stats().stale, 2);
+
+        // Senza limiti ogni mercato entra nel grafo
+        let mut unlimited = PriceGraph::new();
+        unlimited.update_markets(&[market("thin", 50.0, 0), market("old", 5000.0, 120)]);
+        assert_eq!(unlimited.market_count(), 2);
+        assert_eq!(unlimited.prune_stats(), GraphPruneStats::default());
+    }
+}
@@ -0,0 +1,257 @@
+//! Order audit trail
+//!
+//! Implements:
+//! 1. Append-only JSON-lines log of outgoing order requests and exchange responses
+//! 2. Redaction of L2 signature metadata before anything touches disk
+//! 3. Lookup of every record belonging to a trade id
+//! 4. Realized fills of the submitted orders: requested against filled price, for impact calibration
+
+use crate::versioning::{read_lines, LineWriter, Schema};
+use chrono::{DateTime, Utc};
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Default audit log location
+pub const ORDER_AUDIT_PATH: &str = "./data/order_audit.jsonl";
+
+/// One order request/response exchange
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub trade_id: String,
+    pub method: String,
+    pub path: String,
+    pub request_body: serde_json::Value,
+    pub headers: BTreeMap<String, String>, // Header L2 già oscurati
+    pub sent_at: DateTime<Utc>,
+    pub received_at: Option<DateTime<Utc>>,
+    pub status: Option<u16>,
+    pub response_body: Option<serde_json::Value>,
+    pub error: Option<String>, // Errore di trasporto, se la risposta non è arrivata
+    #[serde(default)]
+    pub latency_ms: Option<u64>, // Misurata con clock monotono, non da sent_at/received_at
+}
+
+impl AuditRecord {
+    /// Start a record for a request about to be sent
+    pub fn request(trade_id: &str, method: &str, path: &str, body: &str, headers: &HeaderMap) -> Self {
+        Self {
+            trade_id: trade_id.to_string(),
+            method: method.to_string(),
+            path: path.to_string(),
+            request_body: serde_json::from_str(body).unwrap_or(serde_json::Value::String(body.to_string())),
+            headers: redact_headers(headers),
+            sent_at: Utc::now(),
+            received_at: None,
+            status: None,
+            response_body: None,
+            error: None,
+            latency_ms: None,
+        }
+    }
+
+    /// Attach the exchange response
+    pub fn with_response(mut self, status: u16, body: &str, latency: Duration) -> Self {
+        self.received_at = Some(Utc::now());
+        self.latency_ms = Some(latency.as_millis() as u64);
+        self.status = Some(status);
+        self.response_body = Some(serde_json::from_str(body).unwrap_or(serde_json::Value::String(body.to_string())));
+        self
+    }
+
+    /// Attach a transport error
+    pub fn with_error(mut self, error: &str, latency: Duration) -> Self {
+        self.received_at = Some(Utc::now());
+        self.latency_ms = Some(latency.as_millis() as u64);
+        self.error = Some(error.to_string());
+        self
+    }
+}
+
+/// Fill of a submitted order, as recorded in the audit log
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditFill {
+    pub token_id: String,
+    pub notional: f64,  // Dollari scambiati
+    pub slippage: f64,  // Frazione del prezzo richiesto persa nell'esecuzione (negativa se migliorato)
+}
+
+/// Number the API may encode as a string
+fn amount(value: &serde_json::Value) -> Option<f64> {
+    let amount = match value {
+        serde_json::Value::String(s) => s.parse().ok()?,
+        other => other.as_f64()?,
+    };
+    (amount.is_finite() && amount > 0.0).then_some(amount)
+}
+
+impl AuditRecord {
+    /// Fill of a matched order submission
+    ///
+    /// The requested price comes from the signed order's maker/taker amounts, the filled price from
+    /// the amounts the exchange reports as matched: USDC per share, making over taking for buys and
+    /// taking over making for sells.
+    pub fn fill(&self) -> Option<AuditFill> {
+        if self.method != "POST" || self.path != "/order" || !self.status.is_some_and(|s| (200..300).contains(&s)) {
+            return None;
+        }
+        let order = &self.request_body["order"];
+        let response = self.response_body.as_ref()?;
+        let buy = match order["side"].as_str()? {
+            "BUY" => true,
+            "SELL" => false,
+            _ => return None,
+        };
+        let (maker, taker) = (amount(&order["makerAmount"])?, amount(&order["takerAmount"])?);
+        let (making, taking) = (amount(&response["makingAmount"])?, amount(&response["takingAmount"])?);
+
+        let (requested, filled, notional) = if buy {
+            (maker / taker, making / taking, making)
+        } else {
+            (taker / maker, taking / making, taking)
+        };
+        // Acquisto: peggio se pagato di più; vendita: peggio se incassato di meno
+        let slippage = if buy { filled / requested - 1.0 } else { 1.0 - filled / requested };
+        let token_id = match &order["tokenId"] {
+            serde_json::Value::String(s) => s.clone(),
+            other => other.as_u64()?.to_string(),
+        };
+        Some(AuditFill { token_id, notional, slippage })
+    }
+}
+
+/// Mask a secret-bearing value, keeping only a short suffix for correlation
+fn mask(value: &str, visible: usize) -> String {
+    let chars: Vec<char> = value.chars().collect();
+    if chars.len() <= visible * 2 {
+        return "***".to_string();
+    }
+    let tail: String = chars[chars.len() - visible..].iter().collect();
+    format!("***{}", tail)
+}
+
+/// Header map with L2 credentials redacted (passphrase dropped, key and signature masked)
+pub fn redact_headers(headers: &HeaderMap) -> BTreeMap<String, String> {
+    headers
+        .iter()
+        .filter_map(|(name, value)| {
+            let value = value.to_str().unwrap_or_default();
+            let redacted = match name.as_str().to_ascii_uppercase().as_str() {
+                "POLY_PASSPHRASE" => "[REDACTED]".to_string(),
+                "POLY_API_KEY" | "POLY_SIGNATURE" => mask(value, 4),
+                "POLY_ADDRESS" | "POLY_TIMESTAMP" | "CONTENT-TYPE" => value.to_string(),
+                _ => return None,
+            };
+            Some((name.as_str().to_ascii_uppercase(), redacted))
+        })
+        .collect()
+}
+
+/// Schema of the audit log lines
+pub const AUDIT_SCHEMA: Schema = Schema::new("order_audit", &[]);
+
+/// Append-only audit log backed by a JSON-lines file
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    path: PathBuf,
+    write_lock: Arc<Mutex<()>>,
+    writer: LineWriter,
+}
+
+impl AuditLog {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            write_lock: Arc::new(Mutex::new(())),
+            writer: LineWriter::default(),
+        }
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// Append one record (the file is never rewritten)
+    pub fn append(&self, record: &AuditRecord) -> Result<(), String> {
+        let line = serde_json::to_string(record)
+            .map_err(|e| format!("Failed to serialize audit record: {}", e))?;
+
+        let _guard = self.write_lock.lock().unwrap();
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create audit directory: {}", e))?;
+        }
+        self.writer.append(&AUDIT_SCHEMA, &self.path, &[line])
+            .map(|_| ())
+            .map_err(|e| format!("Failed to write audit log: {}", e))
+    }
+
+    /// All records, oldest first (unparseable lines are skipped)
+    pub fn records(&self) -> Result<Vec<AuditRecord>, String> {
+        let records = read_lines(&AUDIT_SCHEMA, &self.path)
+            .map_err(|e| format!("Failed to read audit log: {}", e))?;
+        Ok(records.into_iter().flatten().collect())
+    }
+
+    /// Fills of every matched order submission, oldest first
+    pub fn fills(&self) -> Result<Vec<AuditFill>, String> {
+        Ok(self.records()?.iter().filter_map(AuditRecord::fill).collect())
+    }
+
+    /// Records for one trade id, oldest first
+    pub fn records_for(&self, trade_id: &str) -> Result<Vec<AuditRecord>, String> {
+        Ok(self.records()?.into_iter().filter(|r| r.trade_id == trade_id).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_audit_log_roundtrip_and_redaction() {
+        let path = std::env::temp_dir().join(format!("audit_{}.jsonl", uuid::Uuid::new_v4()));
+        let log = AuditLog::new(&path);
+
+        let mut headers = HeaderMap::new();
+        headers.insert("POLY_API_KEY", HeaderValue::from_static("00000000-aaaa-bbbb-cccc-123456789abc"));
+        headers.insert("POLY_PASSPHRASE", HeaderValue::from_static("super-secret-passphrase"));
+        headers.insert("POLY_SIGNATURE", HeaderValue::from_static("Ubq2OY24O8Fh0v8yR6pyCXhngeW57ax2g0v7NxsHnN0="));
+        headers.insert("POLY_TIMESTAMP", HeaderValue::from_static("1700000000"));
+
+        let first = AuditRecord::request("t-1", "POST", "/order", r#"{"price":0.5}"#, &headers)
+            .with_response(200, r#"{"success":true,"orderID":"0xabc"}"#, Duration::from_millis(42));
+        let second = AuditRecord::request("t-2", "POST", "/order", "{}", &headers).with_error("timeout", Duration::from_secs(5));
+        log.append(&first).unwrap();
+        log.append(&second).unwrap();
+
+        let records = log.records_for("t-1").unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].status, Some(200));
+        assert_eq!(records[0].latency_ms, Some(42));
+        assert_eq!(records[0].request_body["price"], 0.5);
+
+        let raw = std::fs::read_to_string(&path).unwrap();
+        assert!(!raw.contains("super-secret-passphrase"));
+        assert!(!raw.contains("Ubq2OY24O8Fh0v8yR6pyCXhngeW57ax2g0v7NxsHnN0="));
+        assert_eq!(records[0].headers["POLY_API_KEY"], "***9abc");
+        assert_eq!(records[0].headers["POLY_TIMESTAMP"], "1700000000");
+
+        assert_eq!(log.records().unwrap().len(), 2);
+
+        // Acquisto richiesto a 0.50 (50 USDC per 100 share), eseguito a 0.51
+        let order = r#"{"order": {"tokenId": "123", "side": "BUY", "makerAmount": "50000000", "takerAmount": "100000000"}}"#;
+        let filled = AuditRecord::request("t-3", "POST", "/order", order, &headers)
+            .with_response(200, r#"{"success": true, "makingAmount": "51", "takingAmount": "100"}"#, Duration::from_millis(30));
+        log.append(&filled).unwrap();
+        let fills = log.fills().unwrap();
+        assert_eq!(fills.len(), 1);
+        assert_eq!((fills[0].token_id.as_str(), fills[0].notional), ("123", 51.0));
+        assert!((fills[0].slippage - 0.02).abs() < 1e-9);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
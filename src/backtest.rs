@@ -0,0 +1,675 @@
+//! Backtesting module
+//!
+//! Implements:
+//! 1. Replay of historical price snapshots through the YES/NO detector
+//! 2. Paper execution through the shared PaperBroker ledger
+//! 3. Resolution outcomes (historical or probabilistic) with position settlement
+//! 4. Optional carry-based early unwinding of held pairs
+//! 5. What-if comparison of two configurations over the same window
+//! 6. Optional risk manager (daily resets, cooldowns) running on simulated time
+//! 7. Optional fault injection per tick: lost snapshots, API outages and partial fills
+
+use crate::analytics::{CarryAnalyzer, HoldDecision};
+use crate::arbitrage::{ArbitrageDetector, TradingCosts};
+use crate::clock::{Clock, SimulatedClock};
+use crate::faults::{FaultConfig, FaultInjector, FaultStats};
+use crate::market::PriceSnapshot;
+use crate::paper::{BotState, MarketInfo, PaperBroker, PaperOrder, PaperRiskLimits, SimulatedTrade, TradeSource};
+use crate::risk::RiskManager;
+use crate::types::*;
+use chrono::{DateTime, Utc};
+use fxhash::FxHashMap;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+/// How outcomes are assigned when a market reaches its resolution date
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResolutionMode {
+    /// Use `resolved_outcome` from the market schedule; unknown outcomes fall back to a draw
+    Historical,
+    /// Draw YES with probability equal to the last normalized YES mark
+    ImpliedProbability,
+    /// Draw YES with a fixed probability
+    FixedProbability(f64),
+}
+
+/// Static metadata of a market in the backtest window
+#[derive(Debug, Clone)]
+pub struct MarketSchedule {
+    pub market_id: String,
+    pub question: String,
+    pub end_date: Option<DateTime<Utc>>,
+    pub resolved_outcome: Option<TokenType>, // Esito storico, se noto
+}
+
+/// Backtest configuration
+#[derive(Debug, Clone)]
+pub struct BacktestConfig {
+    pub initial_capital: f64,
+    pub min_profit: f64,
+    pub min_liquidity: f64,
+    pub trade_fraction: f64, // Quota del balance investita per opportunità
+    pub resolution_mode: ResolutionMode,
+    pub seed: u64,
+    pub carry_analyzer: Option<CarryAnalyzer>, // Se presente, le coppie vengono chiuse quando il carry è negativo
+    pub costs: TradingCosts, // Fee e costi dedotti dal margine delle opportunità
+    pub faults: Option<FaultConfig>, // Gap del feed, outage e fill parziali simulati per tick
+}
+
+impl Default for BacktestConfig {
+    fn default() -> Self {
+        Self {
+            initial_capital: 10000.0,
+            min_profit: 0.005,
+            min_liquidity: 0.0,
+            trade_fraction: 0.05,
+            resolution_mode: ResolutionMode::Historical,
+            seed: 42,
+            carry_analyzer: None,
+            costs: TradingCosts::default(),
+            faults: None,
+        }
+    }
+}
+
+/// Resolution applied during a backtest
+#[derive(Debug, Clone)]
+pub struct Settlement {
+    pub market_id: String,
+    pub outcome: TokenType,
+    pub resolved_at: DateTime<Utc>,
+    pub drawn: bool, // true se l'esito è stato estratto e non storico
+    pub realized_pnl: f64,
+}
+
+/// Backtest result
+#[derive(Debug, Clone)]
+pub struct BacktestResult {
+    pub initial_capital: f64,
+    pub final_capital: f64, // Cash + mark-to-market delle posizioni aperte
+    pub cash: f64,
+    pub realized_pnl: f64,
+    pub unrealized_value: f64,
+    pub trades: Vec<SimulatedTrade>,
+    pub settlements: Vec<Settlement>,
+    pub open_positions: usize,
+    pub max_drawdown_pct: f64, // Drawdown massimo dell'equity mark-to-market
+    pub peak_exposure: f64,    // Capitale massimo impiegato in posizioni aperte
+    pub faults: FaultStats,    // Fault iniettati durante la finestra
+}
+
+impl BacktestResult {
+    pub fn summary(&self) -> BacktestSummary {
+        BacktestSummary {
+            trades: self.trades.len(),
+            realized_pnl: self.realized_pnl,
+            total_pnl: self.final_capital - self.initial_capital,
+            max_drawdown_pct: self.max_drawdown_pct,
+            peak_exposure: self.peak_exposure,
+            open_positions: self.open_positions,
+        }
+    }
+}
+
+/// Headline numbers of a backtest run
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BacktestSummary {
+    pub trades: usize,
+    pub realized_pnl: f64,
+    pub total_pnl: f64,
+    pub max_drawdown_pct: f64,
+    pub peak_exposure: f64,
+    pub open_positions: usize,
+}
+
+/// Scenario minus baseline
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SummaryDelta {
+    pub trades: i64,
+    pub realized_pnl: f64,
+    pub total_pnl: f64,
+    pub max_drawdown_pct: f64,
+    pub peak_exposure: f64,
+}
+
+/// Baseline vs modified configuration over the same window
+#[derive(Debug, Clone, Serialize)]
+pub struct WhatIfReport {
+    pub baseline: BacktestSummary,
+    pub scenario: BacktestSummary,
+    pub delta: SummaryDelta,
+}
+
+/// Request payload per what-if: parametri da modificare rispetto alla configurazione corrente
+#[derive(Deserialize)]
+pub struct WhatIfRequest {
+    pub min_profit: Option<f64>,
+    pub min_liquidity: Option<f64>,
+    pub trade_fraction: Option<f64>,
+    pub initial_capital: Option<f64>,
+    pub carry_hurdle_rate: Option<f64>, // Se presente abilita l'unwind basato sul carry
+    pub faults: Option<FaultConfig>, // Fault simulati nello scenario, se presenti
+}
+
+impl WhatIfRequest {
+    pub fn apply(&self, base: &BacktestConfig) -> BacktestConfig {
+        BacktestConfig {
+            min_profit: self.min_profit.unwrap_or(base.min_profit),
+            min_liquidity: self.min_liquidity.unwrap_or(base.min_liquidity),
+            trade_fraction: self.trade_fraction.unwrap_or(base.trade_fraction),
+            initial_capital: self.initial_capital.unwrap_or(base.initial_capital),
+            carry_analyzer: match self.carry_hurdle_rate {
+                Some(rate) => Some(CarryAnalyzer { annual_hurdle_rate: rate, ..CarryAnalyzer::default() }),
+                None => base.carry_analyzer.clone(),
+            },
+            faults: self.faults.clone().or_else(|| base.faults.clone()),
+            ..base.clone()
+        }
+    }
+}
+
+/// Replay the same window under two configurations and compare them
+pub fn what_if(
+    price_history: &FxHashMap<String, Vec<PriceSnapshot>>,
+    schedules: &[MarketSchedule],
+    baseline: BacktestConfig,
+    scenario: BacktestConfig,
+) -> WhatIfReport {
+    let baseline = Backtester::new(baseline, schedules.to_vec()).run(price_history).summary();
+    let scenario = Backtester::new(scenario, schedules.to_vec()).run(price_history).summary();
+
+    let delta = SummaryDelta {
+        trades: scenario.trades as i64 - baseline.trades as i64,
+        realized_pnl: scenario.realized_pnl - baseline.realized_pnl,
+        total_pnl: scenario.total_pnl - baseline.total_pnl,
+        max_drawdown_pct: scenario.max_drawdown_pct - baseline.max_drawdown_pct,
+        peak_exposure: scenario.peak_exposure - baseline.peak_exposure,
+    };
+
+    WhatIfReport { baseline, scenario, delta }
+}
+
+/// Event-driven backtester over price snapshots
+pub struct Backtester {
+    pub config: BacktestConfig,
+    pub schedules: FxHashMap<String, MarketSchedule>,
+    pub broker: PaperBroker,
+    pub clock: SimulatedClock, // Avanza con i timestamp degli snapshot
+    pub risk_manager: Option<RiskManager>, // Se presente, blocca nuove entrate come nel live
+    pub faults: Option<FaultInjector>, // Fault simulati, se configurati
+    detector: ArbitrageDetector,
+    rng: StdRng,
+}
+
+impl Backtester {
+    pub fn new(config: BacktestConfig, schedules: Vec<MarketSchedule>) -> Self {
+        let detector = ArbitrageDetector::new(config.min_profit, config.min_liquidity).with_costs(config.costs.clone());
+
+        let clock = SimulatedClock::new(DateTime::<Utc>::UNIX_EPOCH);
+
+        Self {
+            rng: StdRng::seed_from_u64(config.seed),
+            detector,
+            broker: PaperBroker::with_clock(
+                PaperRiskLimits {
+                    max_trade_fraction: 1.0,
+                    max_market_exposure: f64::MAX,
+                },
+                clock.shared(),
+            ),
+            clock,
+            risk_manager: None,
+            faults: config.faults.clone().map(FaultInjector::new),
+            schedules: schedules.into_iter().map(|s| (s.market_id.clone(), s)).collect(),
+            config,
+        }
+    }
+
+    /// Gate entries through a risk manager driven by the backtest's simulated clock
+    pub fn with_risk_manager(mut self, mut risk_manager: RiskManager) -> Self {
+        risk_manager.set_clock(self.clock.shared());
+        self.risk_manager = Some(risk_manager);
+        self
+    }
+
+    /// Feed realized PnL to the risk manager
+    fn record_realized(&mut self, realized_pnl: f64, state: &BotState) {
+        if let Some(risk_manager) = self.risk_manager.as_mut() {
+            risk_manager.update(realized_pnl, state.balance + self.broker.deployed_capital());
+        }
+    }
+
+    /// Replay price history (same layout as `MarketManager::price_history`)
+    pub fn run(&mut self, price_history: &FxHashMap<String, Vec<PriceSnapshot>>) -> BacktestResult {
+        let mut state = BotState {
+            running: true,
+            balance: self.config.initial_capital,
+            initial_balance: self.config.initial_capital,
+            total_pnl: 0.0,
+            win_rate: 0.0,
+            total_trades: 0,
+            profitable_trades: 0,
+            last_update: self.clock.now(),
+        };
+
+        // Timeline unica ordinata per timestamp
+        let mut timeline: Vec<(&String, &PriceSnapshot)> = price_history
+            .iter()
+            .flat_map(|(id, snaps)| snaps.iter().map(move |s| (id, s)))
+            .collect();
+        timeline.sort_by(|a, b| a.1.timestamp.cmp(&b.1.timestamp).then_with(|| a.0.cmp(b.0)));
+
+        let mut marks: FxHashMap<String, PriceSnapshot> = FxHashMap::default();
+        let mut resolved: FxHashMap<String, TokenType> = FxHashMap::default();
+        let mut trades = Vec::new();
+        let mut settlements = Vec::new();
+        let mut peak_equity = self.config.initial_capital;
+        let mut max_drawdown_pct: f64 = 0.0;
+        let mut peak_exposure: f64 = 0.0;
+
+        for (market_id, snapshot) in &timeline {
+            self.resolve_due(snapshot.timestamp, &marks, &mut resolved, &mut state, &mut trades, &mut settlements);
+
+            if resolved.contains_key(*market_id) {
+                continue; // Nessun trading dopo la risoluzione
+            }
+            self.clock.advance_to(snapshot.timestamp);
+
+            // Gap del feed: lo snapshot non arriva; outage: nessun ordine raggiunge l'exchange
+            let faults = self.faults.as_mut().map(FaultInjector::begin_step).unwrap_or_default();
+            if faults.ws_gap {
+                continue;
+            }
+            marks.insert((*market_id).clone(), (*snapshot).clone());
+            if !faults.api_outage {
+                self.on_snapshot(market_id, snapshot, &mut state, &mut trades);
+            }
+
+            let equity = state.balance + self.mark_to_market(&marks);
+            peak_equity = peak_equity.max(equity);
+            if peak_equity > 0.0 {
+                max_drawdown_pct = max_drawdown_pct.max((peak_equity - equity) / peak_equity * 100.0);
+            }
+            peak_exposure = peak_exposure.max(self.broker.deployed_capital());
+        }
+
+        // Mercati la cui data di risoluzione cade entro la fine della finestra
+        if let Some((_, last)) = timeline.last() {
+            self.resolve_due(last.timestamp, &marks, &mut resolved, &mut state, &mut trades, &mut settlements);
+        }
+
+        let unrealized_value = self.mark_to_market(&marks);
+
+        BacktestResult {
+            initial_capital: self.config.initial_capital,
+            final_capital: state.balance + unrealized_value,
+            cash: state.balance,
+            realized_pnl: state.total_pnl,
+            unrealized_value,
+            trades,
+            settlements,
+            open_positions: self.broker.positions.len(),
+            max_drawdown_pct,
+            peak_exposure,
+            faults: self.faults.as_ref().map(|f| f.stats).unwrap_or_default(),
+        }
+    }
+
+    /// Value of open positions at the latest marks
+    fn mark_to_market(&self, marks: &FxHashMap<String, PriceSnapshot>) -> f64 {
+        self.broker.positions_value(|market_id, token| {
+            marks.get(market_id).map(|m| match token {
+                TokenType::Yes => m.yes_price,
+                TokenType::No => m.no_price,
+            })
+        })
+    }
+
+    /// Enter YES/NO arbitrage on a snapshot and hold both legs to resolution
+    fn on_snapshot(&mut self, market_id: &str, snapshot: &PriceSnapshot, state: &mut BotState, trades: &mut Vec<SimulatedTrade>) {
+        let question = self.schedules.get(market_id).map(|s| s.question.clone()).unwrap_or_default();
+        let info = MarketInfo {
+            id: market_id.to_string(),
+            question: question.clone(),
+            yes_price: snapshot.yes_price,
+            no_price: snapshot.no_price,
+            yes_liquidity: snapshot.volume,
+            no_liquidity: snapshot.volume,
+            volume_24h: snapshot.volume,
+            timestamp: snapshot.timestamp,
+            event_id: None,
+            category: None,
+        };
+
+        if self.broker.market_exposure(market_id) > 0.0 {
+            // Già posizionati: valuta se conviene tenere fino alla risoluzione
+            self.review_carry(&info, state, trades);
+            return;
+        }
+
+        let market = MarketData {
+            id: market_id.to_string(),
+            question,
+            yes_price: snapshot.yes_price,
+            no_price: snapshot.no_price,
+            yes_liquidity: snapshot.volume,
+            no_liquidity: snapshot.volume,
+            timestamp: snapshot.timestamp,
+            volume_24h: snapshot.volume,
+            tokens: None,
+            spread: None,
+            event_id: None,
+            category: None,
+            end_date: None,
+        };
+        let Some(opportunity) = self.detector.detect_yes_no_arbitrage(&market, None) else { return };
+
+        if let Some(risk_manager) = self.risk_manager.as_mut() {
+            risk_manager.roll_day();
+            if !risk_manager.can_trade(state.balance) {
+                return;
+            }
+        }
+
+        // Con fill parziali simulati entrambi i leg eseguono solo la stessa quota dell'ordine
+        let fill_ratio = self.faults.as_mut().map_or(1.0, |f| f.fill_ratio());
+        let pairs = state.balance * self.config.trade_fraction / opportunity.sum_price * fill_ratio;
+
+        for token_type in [TokenType::Yes, TokenType::No] {
+            let order = PaperOrder {
+                market_id: market.id.clone(),
+                token_type,
+                direction: Direction::Buy,
+                quantity: pairs,
+                limit_price: None,
+                source: TradeSource::Bot,
+            };
+            match self.broker.execute(state, &order, &info) {
+                Ok(trade) => trades.push(trade),
+                Err(_) => return,
+            }
+        }
+    }
+
+    /// Unwind a held pair when holding to resolution is worth less than selling now
+    fn review_carry(&mut self, info: &MarketInfo, state: &mut BotState, trades: &mut Vec<SimulatedTrade>) {
+        let Some(analyzer) = &self.config.carry_analyzer else { return };
+        let Some(end_date) = self.schedules.get(&info.id).and_then(|s| s.end_date) else { return };
+
+        let held = |token: TokenType| {
+            self.broker.positions.values()
+                .find(|p| p.market_id == info.id && p.token_type == token)
+                .map(|p| p.quantity)
+                .unwrap_or(0.0)
+        };
+        let pairs = held(TokenType::Yes).min(held(TokenType::No));
+        if pairs <= 0.0 {
+            return;
+        }
+
+        let days = (end_date - info.timestamp).num_seconds() as f64 / 86400.0;
+        let analysis = analyzer.analyze_pair(pairs, info.yes_price, info.no_price, days);
+        if analysis.decision != HoldDecision::Unwind {
+            return;
+        }
+
+        for token_type in [TokenType::Yes, TokenType::No] {
+            let order = PaperOrder {
+                market_id: info.id.clone(),
+                token_type,
+                direction: Direction::Sell,
+                quantity: pairs,
+                limit_price: None,
+                source: TradeSource::Bot,
+            };
+            if let Ok(trade) = self.broker.execute(state, &order, info) {
+                self.record_realized(trade.pnl, state);
+                trades.push(trade);
+            }
+        }
+    }
+
+    /// Resolve and settle every market whose end date is at or before `now`
+    fn resolve_due(
+        &mut self,
+        now: DateTime<Utc>,
+        marks: &FxHashMap<String, PriceSnapshot>,
+        resolved: &mut FxHashMap<String, TokenType>,
+        state: &mut BotState,
+        trades: &mut Vec<SimulatedTrade>,
+        settlements: &mut Vec<Settlement>,
+    ) {
+        let mut due: Vec<MarketSchedule> = self.schedules
+            .values()
+            .filter(|s| !resolved.contains_key(&s.market_id))
+            .filter(|s| s.end_date.is_some_and(|end| end <= now))
+            .cloned()
+            .collect();
+        due.sort_by(|a, b| a.end_date.cmp(&b.end_date).then_with(|| a.market_id.cmp(&b.market_id)));
+
+        for schedule in due {
+            let (outcome, drawn) = self.assign_outcome(&schedule, marks.get(&schedule.market_id));
+            resolved.insert(schedule.market_id.clone(), outcome);
+
+            let resolved_at = schedule.end_date.unwrap_or(now);
+            self.clock.advance_to(resolved_at);
+            let redemptions = self.broker.settle(state, &schedule.market_id, outcome);
+            if !redemptions.is_empty() {
+                self.record_realized(redemptions.iter().map(|t| t.pnl).sum(), state);
+            }
+
+            settlements.push(Settlement {
+                market_id: schedule.market_id.clone(),
+                outcome,
+                resolved_at,
+                drawn,
+                realized_pnl: redemptions.iter().map(|t| t.pnl).sum(),
+            });
+            trades.extend(redemptions);
+        }
+    }
+
+    fn assign_outcome(&mut self, schedule: &MarketSchedule, mark: Option<&PriceSnapshot>) -> (TokenType, bool) {
+        let p_yes = match self.config.resolution_mode {
+            ResolutionMode::Historical => {
+                if let Some(outcome) = schedule.resolved_outcome {
+                    return (outcome, false);
+                }
+                Self::implied_yes_probability(mark)
+            }
+            ResolutionMode::ImpliedProbability => Self::implied_yes_probability(mark),
+            ResolutionMode::FixedProbability(p) => p,
+        };
+
+        let outcome = if self.rng.gen_bool(p_yes.clamp(0.0, 1.0)) { TokenType::Yes } else { TokenType::No };
+        (outcome, true)
+    }
+
+    fn implied_yes_probability(mark: Option<&PriceSnapshot>) -> f64 {
+        match mark {
+            Some(m) if m.yes_price + m.no_price > 0.0 => m.yes_price / (m.yes_price + m.no_price),
+            _ => 0.5,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn snapshot(t: DateTime<Utc>, yes: f64, no: f64) -> PriceSnapshot {
+        PriceSnapshot { timestamp: t, yes_price: yes, no_price: no, volume: 10000.0 }
+    }
+
+    #[test]
+    fn test_positions_settled_at_resolution() {
+        let t0 = Utc::now();
+        let mut history = FxHashMap::default();
+        history.insert("m1".to_string(), vec![
+            snapshot(t0, 0.45, 0.50),
+            snapshot(t0 + Duration::hours(1), 0.60, 0.38),
+            snapshot(t0 + Duration::hours(3), 0.99, 0.01),
+        ]);
+        history.insert("m2".to_string(), vec![
+            snapshot(t0, 0.40, 0.55),
+            snapshot(t0 + Duration::hours(3), 0.30, 0.65),
+        ]);
+
+        let schedules = vec![
+            MarketSchedule {
+                market_id: "m1".to_string(),
+                question: "Resolves in window".to_string(),
+                end_date: Some(t0 + Duration::hours(2)),
+                resolved_outcome: Some(TokenType::Yes),
+            },
+            MarketSchedule {
+                market_id: "m2".to_string(),
+                question: "Still open".to_string(),
+                end_date: Some(t0 + Duration::days(30)),
+                resolved_outcome: None,
+            },
+        ];
+
+        let mut backtester = Backtester::new(BacktestConfig::default(), schedules);
+        let result = backtester.run(&history);
+
+        assert_eq!(result.settlements.len(), 1);
+        let settlement = &result.settlements[0];
+        assert_eq!(settlement.market_id, "m1");
+        assert_eq!(settlement.outcome, TokenType::Yes);
+        assert!(!settlement.drawn);
+        // Coppia YES+NO comprata a 0.95, rimborsata a 1.0
+        assert!(settlement.realized_pnl > 0.0);
+
+        // Il clock simulato segue la finestra: fill e rimborsi hanno i timestamp storici
+        assert_eq!(result.trades[0].timestamp, t0);
+        let redemption = result.trades.iter().find(|t| t.action.starts_with("REDEEM_")).unwrap();
+        assert_eq!(redemption.timestamp, t0 + Duration::hours(2));
+
+        // m2 resta aperto e valutato al mark
+        assert_eq!(result.open_positions, 2);
+        assert!((result.final_capital - (result.cash + result.unrealized_value)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_carry_unwinds_rich_pair() {
+        let t0 = Utc::now();
+        let mut history = FxHashMap::default();
+        history.insert("m1".to_string(), vec![
+            snapshot(t0, 0.45, 0.50),
+            snapshot(t0 + Duration::hours(1), 0.52, 0.50), // Coppia rivendibile sopra 1.0
+        ]);
+        let schedules = vec![MarketSchedule {
+            market_id: "m1".to_string(),
+            question: String::new(),
+            end_date: Some(t0 + Duration::days(180)),
+            resolved_outcome: None,
+        }];
+        let config = BacktestConfig { carry_analyzer: Some(CarryAnalyzer::new(0.05, 0.0)), ..BacktestConfig::default() };
+
+        let result = Backtester::new(config, schedules).run(&history);
+        assert_eq!(result.open_positions, 0);
+        assert!(result.realized_pnl > 0.0);
+        assert_eq!(result.trades.len(), 4);
+    }
+
+    #[test]
+    fn test_probabilistic_resolution_is_seeded() {
+        let t0 = Utc::now();
+        let mut history = FxHashMap::default();
+        history.insert("m1".to_string(), vec![snapshot(t0, 0.45, 0.50), snapshot(t0 + Duration::hours(2), 0.5, 0.5)]);
+        let schedules = vec![MarketSchedule {
+            market_id: "m1".to_string(),
+            question: String::new(),
+            end_date: Some(t0 + Duration::hours(1)),
+            resolved_outcome: None,
+        }];
+        let config = BacktestConfig { resolution_mode: ResolutionMode::FixedProbability(1.0), ..BacktestConfig::default() };
+
+        let result = Backtester::new(config, schedules).run(&history);
+        assert_eq!(result.settlements[0].outcome, TokenType::Yes);
+        assert!(result.settlements[0].drawn);
+        assert_eq!(result.open_positions, 0);
+    }
+
+    #[test]
+    fn test_faults_block_entries_and_shrink_fills() {
+        let t0 = Utc::now();
+        let mut history = FxHashMap::default();
+        history.insert("m1".to_string(), vec![snapshot(t0, 0.45, 0.50)]);
+        let schedules = vec![MarketSchedule {
+            market_id: "m1".to_string(),
+            question: String::new(),
+            end_date: None,
+            resolved_outcome: None,
+        }];
+        let run = |faults: FaultConfig| {
+            let config = BacktestConfig { faults: Some(faults), ..BacktestConfig::default() };
+            Backtester::new(config, schedules.clone()).run(&history)
+        };
+
+        // Outage: l'opportunità c'è ma nessun ordine viene eseguito
+        let outage = run(FaultConfig { api_outage_rate: 1.0, ..FaultConfig::default() });
+        assert!(outage.trades.is_empty());
+        assert_eq!(outage.faults.outage_steps, 1);
+
+        // Gap: lo snapshot non arriva, nessun mark
+        let gap = run(FaultConfig { ws_gap_rate: 1.0, ..FaultConfig::default() });
+        assert!(gap.trades.is_empty());
+        assert_eq!(gap.unrealized_value, 0.0);
+
+        // Fill parziali: stessa quota eseguita su entrambi i leg
+        let full = run(FaultConfig::default());
+        let partial = run(FaultConfig { min_fill_ratio: 0.5, max_fill_ratio: 0.5, ..FaultConfig::default() });
+        assert_eq!(partial.trades.len(), 2);
+        assert!((partial.trades[0].quantity - full.trades[0].quantity * 0.5).abs() < 1e-9);
+        assert_eq!(partial.faults.partial_fills, 1);
+    }
+
+    #[test]
+    fn test_what_if_reports_delta() {
+        let t0 = Utc::now();
+        let mut history = FxHashMap::default();
+        history.insert("m1".to_string(), vec![snapshot(t0, 0.45, 0.50)]); // 5% di margine
+        history.insert("m2".to_string(), vec![snapshot(t0, 0.49, 0.50)]); // 1% di margine
+
+        let baseline = BacktestConfig::default();
+        let scenario = BacktestConfig { min_profit: 0.02, ..BacktestConfig::default() };
+        let report = what_if(&history, &[], baseline, scenario);
+
+        assert_eq!(report.baseline.trades, 4);
+        assert_eq!(report.scenario.trades, 2);
+        assert_eq!(report.delta.trades, -2);
+        assert!(report.delta.peak_exposure < 0.0);
+    }
+
+    #[test]
+    fn test_risk_cooldown_runs_on_simulated_time() {
+        let t0 = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let mut history = FxHashMap::default();
+        // Unwind a t0+1m: il leg YES chiude in perdita e fa partire il cooldown di 15 minuti
+        history.insert("unwound".to_string(), vec![
+            snapshot(t0, 0.45, 0.50),
+            snapshot(t0 + Duration::minutes(1), 0.30, 0.72),
+        ]);
+        history.insert("during_cooldown".to_string(), vec![snapshot(t0 + Duration::minutes(5), 0.45, 0.50)]);
+        history.insert("after_cooldown".to_string(), vec![snapshot(t0 + Duration::minutes(40), 0.45, 0.50)]);
+        let schedules = vec![MarketSchedule {
+            market_id: "unwound".to_string(),
+            question: String::new(),
+            end_date: Some(t0 + Duration::days(180)),
+            resolved_outcome: None,
+        }];
+
+        let config = BacktestConfig { carry_analyzer: Some(CarryAnalyzer::new(0.05, 0.0)), ..BacktestConfig::default() };
+        let risk_manager = RiskManager::new(f64::MAX, 1, 1.0, 0.10, 0.20, 10);
+        let result = Backtester::new(config, schedules).with_risk_manager(risk_manager).run(&history);
+
+        let entered = |id: &str| result.trades.iter().any(|t| t.market_id == id && t.action.starts_with("BUY_"));
+        assert!(entered("unwound"));
+        assert!(!entered("during_cooldown"));
+        assert!(entered("after_cooldown"));
+    }
+}
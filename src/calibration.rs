@@ -0,0 +1,189 @@
+//! Probability calibration module
+//!
+//! Implements:
+//! 1. Sampling of the bot's implied fair YES probability of each watched market
+//! 2. Resolution of watched markets with their final outcome
+//! 3. Calibration curve and Brier score over resolved markets
+
+use crate::analytics::CarryAnalyzer;
+use crate::types::{MarketData, TokenType};
+use chrono::{DateTime, Duration, Utc};
+use fxhash::FxHashMap;
+use serde::{Deserialize, Serialize};
+
+/// Minimum spacing between two samples of the same market
+pub const CALIBRATION_SAMPLE_SECS: i64 = 60;
+
+/// Samples kept per unresolved market (oldest are dropped first)
+pub const CALIBRATION_MAX_SAMPLES: usize = 10_000;
+
+/// Fair YES probability at a point in time
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FairPriceSample {
+    pub timestamp: DateTime<Utc>,
+    pub fair_yes: f64,
+}
+
+/// Market resolved while watched, with its samples
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResolvedMarket {
+    pub market_id: String,
+    pub question: String,
+    pub yes_won: bool,
+    pub resolved_at: DateTime<Utc>,
+    pub samples: Vec<FairPriceSample>,
+}
+
+impl ResolvedMarket {
+    /// Mean squared error of the samples against the outcome
+    pub fn brier_score(&self) -> Option<f64> {
+        let outcome = if self.yes_won { 1.0 } else { 0.0 };
+        if self.samples.is_empty() {
+            return None;
+        }
+        Some(self.samples.iter().map(|s| (s.fair_yes - outcome).powi(2)).sum::<f64>() / self.samples.len() as f64)
+    }
+}
+
+/// One bucket of the calibration curve
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalibrationBin {
+    pub lower: f64,
+    pub upper: f64,
+    pub count: usize,
+    pub mean_predicted: f64,
+    pub observed_frequency: f64, // Frazione di campioni il cui mercato ha risolto YES
+}
+
+/// Calibration of the fair-value model over resolved markets
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalibrationReport {
+    pub resolved_markets: usize,
+    pub samples: usize,
+    pub brier_score: Option<f64>, // Media per mercato, così i mercati osservati a lungo non dominano
+    pub curve: Vec<CalibrationBin>, // Solo bucket con campioni
+}
+
+/// Records fair prices of watched markets until they resolve
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CalibrationTracker {
+    pub pending: FxHashMap<String, (String, Vec<FairPriceSample>)>, // market_id -> (domanda, campioni)
+    pub resolved: Vec<ResolvedMarket>,
+}
+
+impl CalibrationTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sample a market's fair YES probability (at most once per `CALIBRATION_SAMPLE_SECS`)
+    pub fn record(&mut self, market: &MarketData, now: DateTime<Utc>) -> bool {
+        if self.resolved.iter().any(|r| r.market_id == market.id) {
+            return false;
+        }
+        let (_, samples) = self.pending
+            .entry(market.id.clone())
+            .or_insert_with(|| (market.question.clone(), Vec::new()));
+        if samples.last().is_some_and(|last| now - last.timestamp < Duration::seconds(CALIBRATION_SAMPLE_SECS)) {
+            return false;
+        }
+
+        samples.push(FairPriceSample {
+            timestamp: now,
+            fair_yes: CarryAnalyzer::implied_probability(market, TokenType::Yes),
+        });
+        if samples.len() > CALIBRATION_MAX_SAMPLES {
+            samples.remove(0);
+        }
+        true
+    }
+
+    /// Markets with samples still waiting for a resolution
+    pub fn pending_markets(&self) -> Vec<String> {
+        self.pending.keys().cloned().collect()
+    }
+
+    /// Close a watched market with its outcome; returns false if it was not watched
+    pub fn resolve(&mut self, market_id: &str, yes_won: bool, resolved_at: DateTime<Utc>) -> bool {
+        let Some((question, samples)) = self.pending.remove(market_id) else { return false };
+        // Solo i campioni precedenti alla risoluzione contano
+        let samples: Vec<FairPriceSample> = samples.into_iter().filter(|s| s.timestamp <= resolved_at).collect();
+        self.resolved.push(ResolvedMarket {
+            market_id: market_id.to_string(),
+            question,
+            yes_won,
+            resolved_at,
+            samples,
+        });
+        true
+    }
+
+    /// Calibration curve with `bins` equal-width buckets and the Brier score
+    pub fn report(&self, bins: usize) -> CalibrationReport {
+        let bins = bins.max(1);
+        let mut sums = vec![(0usize, 0.0, 0.0); bins]; // (campioni, somma previsioni, esiti YES)
+        for market in &self.resolved {
+            let outcome = if market.yes_won { 1.0 } else { 0.0 };
+            for sample in &market.samples {
+                let bin = ((sample.fair_yes.clamp(0.0, 1.0) * bins as f64) as usize).min(bins - 1);
+                sums[bin].0 += 1;
+                sums[bin].1 += sample.fair_yes;
+                sums[bin].2 += outcome;
+            }
+        }
+
+        let scores: Vec<f64> = self.resolved.iter().filter_map(ResolvedMarket::brier_score).collect();
+        CalibrationReport {
+            resolved_markets: self.resolved.len(),
+            samples: sums.iter().map(|(count, _, _)| count).sum(),
+            brier_score: (!scores.is_empty()).then(|| scores.iter().sum::<f64>() / scores.len() as f64),
+            curve: sums
+                .iter()
+                .enumerate()
+                .filter(|(_, (count, _, _))| *count > 0)
+                .map(|(i, &(count, predicted, observed))| CalibrationBin {
+                    lower: i as f64 / bins as f64,
+                    upper: (i + 1) as f64 / bins as f64,
+                    count,
+                    mean_predicted: predicted / count as f64,
+                    observed_frequency: observed / count as f64,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn market(id: &str, yes_price: f64) -> MarketData {
+        MarketData { id: id.to_string(), yes_price, no_price: 1.0 - yes_price, ..MarketData::default() }
+    }
+
+    #[test]
+    fn test_calibration_curve_and_brier() {
+        let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let mut tracker = CalibrationTracker::new();
+
+        assert!(tracker.record(&market("a", 0.8), start));
+        assert!(!tracker.record(&market("a", 0.7), start + Duration::seconds(10))); // Troppo presto
+        assert!(tracker.record(&market("a", 0.9), start + Duration::seconds(60)));
+        assert!(tracker.record(&market("b", 0.2), start));
+        assert!(tracker.record(&market("c", 0.5), start));
+
+        assert!(tracker.resolve("a", true, start + Duration::hours(1)));
+        assert!(tracker.resolve("b", false, start + Duration::hours(1)));
+        assert!(!tracker.resolve("unknown", true, start));
+        assert!(!tracker.record(&market("a", 0.99), start + Duration::hours(2)));
+        assert_eq!(tracker.pending_markets(), vec!["c"]);
+
+        let report = tracker.report(10);
+        assert_eq!((report.resolved_markets, report.samples), (2, 3));
+        // a: (0.04 + 0.01) / 2 = 0.025; b: 0.04 -> media 0.0325
+        assert!((report.brier_score.unwrap() - 0.0325).abs() < 1e-9);
+        assert_eq!(report.curve.len(), 3);
+        assert_eq!(report.curve[0].observed_frequency, 0.0);
+        assert_eq!(report.curve[2].observed_frequency, 1.0);
+    }
+}
@@ -0,0 +1,290 @@
+//! Exchange API capture module
+//!
+//! Implements:
+//! 1. Sampled capture of Gamma/CLOB request-response pairs (error responses are always kept)
+//! 2. Redaction of credentials in headers, query strings and JSON bodies
+//! 3. Retention window with bounded memory, and JSON-lines export for compliance or support tickets
+
+use crate::audit::redact_headers;
+use crate::versioning::Schema;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Environment variable overriding the capture sample rate
+pub const CAPTURE_SAMPLE_RATE_ENV: &str = "POLYMARKET_CAPTURE_SAMPLE_RATE";
+
+/// Response bodies longer than this are truncated in the capture
+const MAX_CAPTURED_BODY: usize = 16 * 1024;
+
+/// JSON fields and query parameters whose values are never captured
+const SECRET_FIELDS: &[&str] = &["secret", "passphrase", "apikey", "api_key", "owner", "signature", "privatekey", "private_key", "key"];
+
+/// Capture settings; a zero sample rate captures only error responses
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptureConfig {
+    pub enabled: bool,
+    pub sample_rate: f64,      // Quota delle richieste riuscite catturate (0.0 - 1.0)
+    pub retention_secs: i64,   // Finestra di conservazione
+    pub max_entries: usize,    // Limite di memoria: oltre, si scartano le più vecchie
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            sample_rate: 0.01,
+            retention_secs: 7 * 24 * 3600,
+            max_entries: 10_000,
+        }
+    }
+}
+
+/// Schema of capture exports
+pub const CAPTURE_SCHEMA: Schema = Schema::new("api_capture", &[]);
+
+/// One captured request-response pair, already redacted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapturedExchange {
+    pub api: String,
+    pub method: String,
+    pub path: String,
+    pub query: BTreeMap<String, String>,
+    pub request_headers: BTreeMap<String, String>,
+    pub request_body: Option<serde_json::Value>,
+    pub status: Option<u16>,
+    pub response_body: Option<serde_json::Value>,
+    pub error: Option<String>, // Errore di trasporto
+    pub sampled: bool,         // false = catturata perché errore
+    pub sent_at: DateTime<Utc>,
+    pub latency_ms: u64,
+}
+
+/// Whether a JSON field or query parameter carries a secret
+fn is_secret(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    SECRET_FIELDS.contains(&name.as_str())
+}
+
+/// JSON value with every secret field replaced
+pub fn redact_json(value: &serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(map) => map
+            .iter()
+            .map(|(k, v)| {
+                let v = if is_secret(k) { serde_json::Value::String("[REDACTED]".to_string()) } else { redact_json(v) };
+                (k.clone(), v)
+            })
+            .collect(),
+        serde_json::Value::Array(items) => items.iter().map(redact_json).collect(),
+        other => other.clone(),
+    }
+}
+
+/// Body as redacted JSON (non-JSON bodies are kept as a truncated string)
+///
+/// The whole body is parsed and redacted before truncation: a JSON body cut first would no longer
+/// parse and would be kept as raw text, secrets included. A redacted body over the size limit is
+/// kept as its truncated serialization.
+fn redact_body(body: &[u8]) -> Option<serde_json::Value> {
+    if body.is_empty() {
+        return None;
+    }
+    match serde_json::from_slice::<serde_json::Value>(body) {
+        Ok(json) => {
+            let redacted = redact_json(&json);
+            let text = redacted.to_string();
+            if text.len() <= MAX_CAPTURED_BODY {
+                return Some(redacted);
+            }
+            let end = (0..=MAX_CAPTURED_BODY).rev().find(|&i| text.is_char_boundary(i)).unwrap_or(0);
+            Some(serde_json::Value::String(text[..end].to_string()))
+        }
+        Err(_) => {
+            let text = String::from_utf8_lossy(&body[..body.len().min(MAX_CAPTURED_BODY)]);
+            Some(serde_json::Value::String(text.into_owned()))
+        }
+    }
+}
+
+/// Request details kept until the response arrives
+pub struct PendingCapture {
+    api: String,
+    method: String,
+    path: String,
+    query: BTreeMap<String, String>,
+    request_headers: BTreeMap<String, String>,
+    request_body: Option<serde_json::Value>,
+    sampled: bool,
+    sent_at: DateTime<Utc>,
+    started: std::time::Instant,
+}
+
+impl PendingCapture {
+    /// Whether the response body must be read (sampled, or an error)
+    pub fn wants_response(&self, status: u16) -> bool {
+        self.sampled || status >= 400
+    }
+}
+
+/// Shared in-memory capture buffer
+#[derive(Debug, Clone, Default)]
+pub struct ApiCapture {
+    config: CaptureConfig,
+    entries: Arc<Mutex<VecDeque<CapturedExchange>>>,
+}
+
+impl ApiCapture {
+    pub fn new(config: CaptureConfig) -> Self {
+        Self { config, entries: Arc::new(Mutex::new(VecDeque::new())) }
+    }
+
+    pub fn config(&self) -> &CaptureConfig {
+        &self.config
+    }
+
+    /// Start capturing a request about to be sent; None when capture is off
+    pub fn begin(&self, api: &str, request: &reqwest::Request) -> Option<PendingCapture> {
+        if !self.config.enabled {
+            return None;
+        }
+        let query = request
+            .url()
+            .query_pairs()
+            .map(|(k, v)| {
+                let v = if is_secret(&k) { "[REDACTED]".to_string() } else { v.into_owned() };
+                (k.into_owned(), v)
+            })
+            .collect();
+        Some(PendingCapture {
+            api: api.to_string(),
+            method: request.method().to_string(),
+            path: request.url().path().to_string(),
+            query,
+            request_headers: redact_headers(request.headers()),
+            request_body: request.body().and_then(|b| b.as_bytes()).and_then(redact_body),
+            sampled: rand::random::<f64>() < self.config.sample_rate,
+            sent_at: Utc::now(),
+            started: std::time::Instant::now(),
+        })
+    }
+
+    /// Complete a capture with the response (or transport error); unsampled successes are dropped
+    pub fn finish(&self, pending: PendingCapture, status: Option<u16>, response_body: Option<&[u8]>, error: Option<String>) {
+        let failed = error.is_some() || status.is_some_and(|s| s >= 400);
+        if !pending.sampled && !failed {
+            return;
+        }
+        self.record(CapturedExchange {
+            api: pending.api,
+            method: pending.method,
+            path: pending.path,
+            query: pending.query,
+            request_headers: pending.request_headers,
+            request_body: pending.request_body,
+            status,
+            response_body: response_body.and_then(redact_body),
+            error,
+            sampled: pending.sampled,
+            sent_at: pending.sent_at,
+            latency_ms: pending.started.elapsed().as_millis() as u64,
+        });
+    }
+
+    /// Store an exchange, pruning entries outside the retention window
+    pub fn record(&self, exchange: CapturedExchange) {
+        let cutoff = Utc::now() - Duration::seconds(self.config.retention_secs);
+        let mut entries = self.entries.lock().unwrap();
+        entries.push_back(exchange);
+        while entries.front().is_some_and(|e| e.sent_at < cutoff) || entries.len() > self.config.max_entries {
+            entries.pop_front();
+        }
+    }
+
+    /// Captured exchanges sent at or after `since`, oldest first
+    pub fn entries(&self, since: Option<DateTime<Utc>>) -> Vec<CapturedExchange> {
+        let cutoff = Utc::now() - Duration::seconds(self.config.retention_secs);
+        let since = since.map_or(cutoff, |s| s.max(cutoff));
+        self.entries.lock().unwrap().iter().filter(|e| e.sent_at >= since).cloned().collect()
+    }
+
+    /// Write the captured exchanges since a time as JSON lines, after a schema header line; returns how many were written
+    pub fn export_jsonl(&self, path: &Path, since: Option<DateTime<Utc>>) -> Result<usize, String> {
+        let entries = self.entries(since);
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create export directory: {}", e))?;
+        }
+        let mut file = std::fs::File::create(path).map_err(|e| format!("Failed to create capture export: {}", e))?;
+        let header = serde_json::to_string(&CAPTURE_SCHEMA.header()).map_err(|e| format!("Failed to serialize capture: {}", e))?;
+        writeln!(file, "{}", header).map_err(|e| format!("Failed to write capture export: {}", e))?;
+        for entry in &entries {
+            let line = serde_json::to_string(entry).map_err(|e| format!("Failed to serialize capture: {}", e))?;
+            writeln!(file, "{}", line).map_err(|e| format!("Failed to write capture export: {}", e))?;
+        }
+        Ok(entries.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capture_redacts_samples_and_exports() {
+        let client = reqwest::Client::new();
+        let request = client
+            .post("https://clob.polymarket.com/order?signature=abc&token_id=1")
+            .header("POLY_PASSPHRASE", "super-secret")
+            .header("POLY_API_KEY", "00000000-aaaa-bbbb-cccc-123456789abc")
+            .body(r#"{"order":{"price":"0.5","signature":"0xdeadbeef"},"owner":"k"}"#)
+            .build()
+            .unwrap();
+
+        // Campionamento a zero: solo gli errori vengono conservati
+        let capture = ApiCapture::new(CaptureConfig { sample_rate: 0.0, ..CaptureConfig::default() });
+        let pending = capture.begin("clob", &request).unwrap();
+        assert!(!pending.wants_response(200));
+        capture.finish(pending, Some(200), Some(b"{}"), None);
+        assert!(capture.entries(None).is_empty());
+
+        let pending = capture.begin("clob", &request).unwrap();
+        assert!(pending.wants_response(401));
+        capture.finish(pending, Some(401), Some(br#"{"error":"Unauthorized","apiKey":"leak"}"#), None);
+
+        let entries = capture.entries(None);
+        assert_eq!(entries.len(), 1);
+        let entry = &entries[0];
+        assert_eq!(entry.path, "/order");
+        assert_eq!(entry.query["signature"], "[REDACTED]");
+        assert_eq!(entry.query["token_id"], "1");
+        assert_eq!(entry.request_body.as_ref().unwrap()["order"]["signature"], "[REDACTED]");
+        assert_eq!(entry.request_body.as_ref().unwrap()["order"]["price"], "0.5");
+        assert_eq!(entry.request_body.as_ref().unwrap()["owner"], "[REDACTED]");
+        assert_eq!(entry.response_body.as_ref().unwrap()["apiKey"], "[REDACTED]");
+        assert_eq!(entry.request_headers["POLY_PASSPHRASE"], "[REDACTED]");
+
+        let path = std::env::temp_dir().join(format!("capture_{}.jsonl", uuid::Uuid::new_v4()));
+        assert_eq!(capture.export_jsonl(&path, None).unwrap(), 1);
+        let raw = std::fs::read_to_string(&path).unwrap();
+        assert!(!raw.contains("super-secret") && !raw.contains("deadbeef") && !raw.contains("leak"));
+        let exported = crate::versioning::read_lines::<CapturedExchange>(&CAPTURE_SCHEMA, &path).unwrap();
+        assert_eq!(exported.len(), 1);
+        std::fs::remove_file(&path).unwrap();
+
+        // Corpo oltre il limite: redatto per intero, poi troncato
+        let large = format!(r#"{{"apiKey":"leak","items":"{}"}}"#, "x".repeat(MAX_CAPTURED_BODY));
+        let pending = capture.begin("clob", &request).unwrap();
+        capture.finish(pending, Some(500), Some(large.as_bytes()), None);
+        let truncated = capture.entries(None).pop().unwrap().response_body.unwrap();
+        assert_eq!(truncated.as_str().unwrap().len(), MAX_CAPTURED_BODY);
+        assert!(!truncated.as_str().unwrap().contains("leak"));
+        capture.entries.lock().unwrap().pop_back();
+
+        // Fuori dalla finestra di conservazione
+        capture.record(CapturedExchange { sent_at: Utc::now() - Duration::days(8), ..entry.clone() });
+        assert_eq!(capture.entries(None).len(), 1);
+    }
+}
@@ -0,0 +1,159 @@
+//! Time source module
+//!
+//! Implements:
+//! 1. `Clock` abstraction with wall-clock and monotonic readings
+//! 2. System clock for live trading
+//! 3. Simulated clock advanced explicitly by tests and backtests
+//! 4. Monotonic stopwatch for latency and execution-time measurement
+//! 5. wasm32 fallback measuring the system clock on wall-clock time
+
+use chrono::{DateTime, Utc};
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+
+/// Source of time: wall-clock for timestamps, monotonic for measuring durations
+pub trait Clock: Send + Sync + Debug {
+    /// Wall-clock time, used only to stamp records
+    fn now(&self) -> DateTime<Utc>;
+    /// Monotonic reading since the clock's origin; never goes backwards
+    fn monotonic(&self) -> Duration;
+}
+
+/// Clock shared between components
+pub type SharedClock = Arc<dyn Clock>;
+
+/// Real system time
+#[derive(Debug, Clone)]
+pub struct SystemClock {
+    #[cfg(not(target_arch = "wasm32"))]
+    origin: Instant,
+    #[cfg(target_arch = "wasm32")]
+    origin: DateTime<Utc>, // Nel browser `Instant` non esiste: si misura sul wall-clock
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        return Self { origin: Instant::now() };
+        #[cfg(target_arch = "wasm32")]
+        return Self { origin: Utc::now() };
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn monotonic(&self) -> Duration {
+        #[cfg(not(target_arch = "wasm32"))]
+        return self.origin.elapsed();
+        #[cfg(target_arch = "wasm32")]
+        return (Utc::now() - self.origin).to_std().unwrap_or_default();
+    }
+}
+
+/// System clock as a shared handle
+pub fn system_clock() -> SharedClock {
+    Arc::new(SystemClock::default())
+}
+
+/// Deterministic clock: time only moves when advanced
+#[derive(Debug, Clone)]
+pub struct SimulatedClock {
+    start: DateTime<Utc>,
+    elapsed: Arc<Mutex<Duration>>,
+}
+
+impl SimulatedClock {
+    pub fn new(start: DateTime<Utc>) -> Self {
+        Self {
+            start,
+            elapsed: Arc::new(Mutex::new(Duration::ZERO)),
+        }
+    }
+
+    /// Move time forward
+    pub fn advance(&self, by: Duration) {
+        *self.elapsed.lock().unwrap() += by;
+    }
+
+    /// Jump to a wall-clock time; earlier times are ignored so the clock stays monotonic
+    pub fn advance_to(&self, time: DateTime<Utc>) {
+        let Ok(target) = (time - self.start).to_std() else { return };
+        let mut elapsed = self.elapsed.lock().unwrap();
+        if target > *elapsed {
+            *elapsed = target;
+        }
+    }
+
+    /// Shared handle to this clock (clones advance together)
+    pub fn shared(&self) -> SharedClock {
+        Arc::new(self.clone())
+    }
+}
+
+impl Clock for SimulatedClock {
+    fn now(&self) -> DateTime<Utc> {
+        let elapsed = *self.elapsed.lock().unwrap();
+        self.start + chrono::Duration::from_std(elapsed).unwrap_or_default()
+    }
+
+    fn monotonic(&self) -> Duration {
+        *self.elapsed.lock().unwrap()
+    }
+}
+
+/// Monotonic stopwatch on a clock
+#[derive(Debug, Clone)]
+pub struct Stopwatch {
+    clock: SharedClock,
+    started: Duration,
+}
+
+impl Stopwatch {
+    pub fn start(clock: &SharedClock) -> Self {
+        Self {
+            clock: clock.clone(),
+            started: clock.monotonic(),
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.clock.monotonic().saturating_sub(self.started)
+    }
+
+    pub fn elapsed_ms(&self) -> u64 {
+        self.elapsed().as_millis() as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_simulated_clock_is_deterministic() {
+        let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let clock = SimulatedClock::new(start);
+        let shared = clock.shared();
+
+        let stopwatch = Stopwatch::start(&shared);
+        assert_eq!(shared.now(), start);
+        assert_eq!(stopwatch.elapsed_ms(), 0);
+
+        clock.advance(Duration::from_millis(250));
+        assert_eq!(stopwatch.elapsed_ms(), 250);
+        assert_eq!(shared.now(), start + chrono::Duration::milliseconds(250));
+
+        // Un salto all'indietro non fa regredire il tempo monotono
+        clock.advance_to(start);
+        assert_eq!(shared.monotonic(), Duration::from_millis(250));
+
+        clock.advance_to(start + chrono::Duration::hours(1));
+        assert_eq!(shared.now(), start + chrono::Duration::hours(1));
+    }
+}
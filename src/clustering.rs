@@ -0,0 +1,349 @@
+//! Trade clustering module
+//!
+//! Implements:
+//! 1. Features of executed bot trades and dashboard trades
+//! 2. Clusters of trades by detector, market category and size
+//! 3. Anomalous clusters: systematic losses, excess slippage or slow execution against the other trades
+//! 4. Alerts raised once per anomalous cluster until it recovers
+
+use crate::paper::SimulatedTrade;
+use crate::types::TradeExecution;
+use chrono::{DateTime, Utc};
+use fxhash::{FxHashMap, FxHashSet};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Smallest cluster that can be flagged
+pub const MIN_CLUSTER_TRADES: usize = 5;
+
+/// Distance of a cluster mean from the other trades, in standard errors, to flag it
+pub const ANOMALY_Z_THRESHOLD: f64 = 2.0;
+
+/// Steps between two clustering passes of the bot
+pub const TRADE_ANALYSIS_INTERVAL_STEPS: u64 = 100;
+
+/// Alerts kept for the dashboard
+pub const TRADE_ALERT_HISTORY: usize = 200;
+
+/// Category of markets without one
+pub const UNCATEGORIZED: &str = "uncategorized";
+
+/// Trade size bucket by notional
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SizeBucket {
+    Small,  // < 100 USDC
+    Medium, // < 1000 USDC
+    Large,
+}
+
+impl SizeBucket {
+    pub fn of(notional: f64) -> Self {
+        match notional.abs() {
+            n if n < 100.0 => SizeBucket::Small,
+            n if n < 1000.0 => SizeBucket::Medium,
+            _ => SizeBucket::Large,
+        }
+    }
+}
+
+/// Features of one executed trade
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TradeSample {
+    pub trade_id: String,
+    pub detector: String, // Tipo di arbitraggio per il bot, origine e azione per la dashboard
+    pub category: String,
+    pub notional: f64,
+    pub pnl: f64,
+    pub slippage_pct: f64,
+    pub execution_time_ms: u64,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl TradeSample {
+    /// Trade of the bot executor, in a market of `category`
+    pub fn from_execution(trade: &TradeExecution, category: Option<&str>) -> Self {
+        Self {
+            trade_id: trade.trade_id.clone(),
+            detector: format!("{:?}", trade.arb_type),
+            category: category.unwrap_or(UNCATEGORIZED).to_string(),
+            notional: trade.total_investment,
+            pnl: trade.profit,
+            slippage_pct: trade.slippage_pct,
+            execution_time_ms: trade.execution_time_ms,
+            timestamp: trade.exit_time,
+        }
+    }
+
+    /// Dashboard trade with a realized result; None while it has no PnL (open manual buys)
+    pub fn from_simulated(trade: &SimulatedTrade, category: Option<&str>) -> Option<Self> {
+        let pnl = trade.pnl + trade.arbitrage_profit;
+        if pnl == 0.0 {
+            return None;
+        }
+        Some(Self {
+            trade_id: trade.id.clone(),
+            detector: format!("{:?}/{}", trade.source, trade.action),
+            category: category.unwrap_or(UNCATEGORIZED).to_string(),
+            notional: trade.amount,
+            pnl,
+            slippage_pct: 0.0,
+            execution_time_ms: 0,
+            timestamp: trade.timestamp,
+        })
+    }
+
+    /// PnL as a percentage of notional
+    pub fn return_pct(&self) -> f64 {
+        if self.notional.abs() > 0.0 { self.pnl / self.notional.abs() * 100.0 } else { 0.0 }
+    }
+
+    pub fn key(&self) -> ClusterKey {
+        ClusterKey {
+            detector: self.detector.clone(),
+            category: self.category.clone(),
+            size: SizeBucket::of(self.notional),
+        }
+    }
+}
+
+/// Features shared by the trades of a cluster
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ClusterKey {
+    pub detector: String,
+    pub category: String,
+    pub size: SizeBucket,
+}
+
+/// Statistics of one cluster
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TradeCluster {
+    pub key: ClusterKey,
+    pub trades: usize,
+    pub total_pnl: f64,
+    pub mean_return_pct: f64,
+    pub loss_rate: f64,
+    pub mean_slippage_pct: f64,
+    pub mean_execution_ms: f64,
+}
+
+/// How a cluster deviates from the other trades
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnomalyKind {
+    SystematicLoss, // Rendimento medio negativo e sotto gli altri trade
+    ExcessSlippage,
+    SlowExecution,
+}
+
+impl AnomalyKind {
+    /// Per-trade value compared between a cluster and the other trades
+    pub fn metric(&self, sample: &TradeSample) -> f64 {
+        match self {
+            AnomalyKind::SystematicLoss => sample.return_pct(),
+            AnomalyKind::ExcessSlippage => sample.slippage_pct,
+            AnomalyKind::SlowExecution => sample.execution_time_ms as f64,
+        }
+    }
+}
+
+/// Cluster flagged as anomalous
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClusterAnomaly {
+    pub key: ClusterKey,
+    pub kind: AnomalyKind,
+    pub trades: usize,
+    pub cluster_mean: f64,
+    pub baseline_mean: f64, // Media degli altri trade
+    pub z_score: f64,
+    pub message: String,
+}
+
+/// Clusters of a set of trades and the anomalous ones
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ClusterReport {
+    pub total_trades: usize,
+    pub clusters: Vec<TradeCluster>, // Dal PnL totale peggiore
+    pub anomalies: Vec<ClusterAnomaly>,
+}
+
+fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() { 0.0 } else { values.iter().sum::<f64>() / values.len() as f64 }
+}
+
+fn std_dev(values: &[f64]) -> f64 {
+    if values.len() < 2 {
+        return 0.0;
+    }
+    let m = mean(values);
+    (values.iter().map(|v| (v - m).powi(2)).sum::<f64>() / (values.len() - 1) as f64).sqrt()
+}
+
+/// Cluster mean against the other trades, in standard errors of the whole population
+fn deviation(cluster: &[f64], rest: &[f64], all: &[f64]) -> Option<(f64, f64, f64)> {
+    let sd = std_dev(all);
+    if rest.is_empty() || sd <= 0.0 {
+        return None;
+    }
+    let (cluster_mean, baseline_mean) = (mean(cluster), mean(rest));
+    Some((cluster_mean, baseline_mean, (cluster_mean - baseline_mean) / (sd / (cluster.len() as f64).sqrt())))
+}
+
+/// Cluster trades and flag clusters that deviate from the rest
+pub fn cluster_trades(samples: &[TradeSample]) -> ClusterReport {
+    let mut groups: FxHashMap<ClusterKey, Vec<&TradeSample>> = FxHashMap::default();
+    for sample in samples {
+        groups.entry(sample.key()).or_default().push(sample);
+    }
+
+    let mut clusters = Vec::new();
+    let mut anomalies = Vec::new();
+    for (key, trades) in &groups {
+        let returns: Vec<f64> = trades.iter().map(|t| t.return_pct()).collect();
+        clusters.push(TradeCluster {
+            key: key.clone(),
+            trades: trades.len(),
+            total_pnl: trades.iter().map(|t| t.pnl).sum(),
+            mean_return_pct: mean(&returns),
+            loss_rate: trades.iter().filter(|t| t.pnl < 0.0).count() as f64 / trades.len() as f64,
+            mean_slippage_pct: mean(&trades.iter().map(|t| t.slippage_pct).collect::<Vec<_>>()),
+            mean_execution_ms: mean(&trades.iter().map(|t| t.execution_time_ms as f64).collect::<Vec<_>>()),
+        });
+
+        if trades.len() < MIN_CLUSTER_TRADES {
+            continue;
+        }
+        for kind in [AnomalyKind::SystematicLoss, AnomalyKind::ExcessSlippage, AnomalyKind::SlowExecution] {
+            let cluster: Vec<f64> = trades.iter().map(|t| kind.metric(t)).collect();
+            let rest: Vec<f64> = samples.iter().filter(|s| &s.key() != key).map(|s| kind.metric(s)).collect();
+            let all: Vec<f64> = samples.iter().map(|s| kind.metric(s)).collect();
+            let Some((cluster_mean, baseline_mean, z_score)) = deviation(&cluster, &rest, &all) else { continue };
+
+            let flagged = match kind {
+                AnomalyKind::SystematicLoss => cluster_mean < 0.0 && z_score <= -ANOMALY_Z_THRESHOLD,
+                AnomalyKind::ExcessSlippage | AnomalyKind::SlowExecution => z_score >= ANOMALY_Z_THRESHOLD,
+            };
+            if flagged {
+                anomalies.push(ClusterAnomaly {
+                    key: key.clone(),
+                    kind,
+                    trades: trades.len(),
+                    cluster_mean,
+                    baseline_mean,
+                    z_score,
+                    message: format!(
+                        "{:?} on {} trades of {} in {} ({:?}): {:.2} vs {:.2} elsewhere (z {:.1})",
+                        kind, trades.len(), key.detector, key.category, key.size, cluster_mean, baseline_mean, z_score
+                    ),
+                });
+            }
+        }
+    }
+
+    clusters.sort_by(|a, b| a.total_pnl.partial_cmp(&b.total_pnl).unwrap().then_with(|| a.key.cmp(&b.key)));
+    anomalies.sort_by(|a, b| b.z_score.abs().partial_cmp(&a.z_score.abs()).unwrap());
+    ClusterReport {
+        total_trades: samples.len(),
+        clusters,
+        anomalies,
+    }
+}
+
+/// Alert on a newly anomalous cluster
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TradeAlert {
+    pub key: ClusterKey,
+    pub kind: AnomalyKind,
+    pub message: String,
+    pub raised_at: DateTime<Utc>,
+}
+
+/// Turns anomalies of successive reports into alerts, once per cluster and kind
+#[derive(Debug, Clone, Default)]
+pub struct TradeAnomalyMonitor {
+    pub alerts: VecDeque<TradeAlert>,
+    active: FxHashSet<(ClusterKey, AnomalyKind)>,
+}
+
+impl TradeAnomalyMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Alerts for anomalies not flagged in the previous report; recovered clusters can alert again
+    pub fn check(&mut self, report: &ClusterReport, now: DateTime<Utc>) -> Vec<TradeAlert> {
+        let current: FxHashSet<(ClusterKey, AnomalyKind)> = report.anomalies
+            .iter()
+            .map(|a| (a.key.clone(), a.kind))
+            .collect();
+
+        let raised: Vec<TradeAlert> = report.anomalies
+            .iter()
+            .filter(|a| !self.active.contains(&(a.key.clone(), a.kind)))
+            .map(|a| TradeAlert {
+                key: a.key.clone(),
+                kind: a.kind,
+                message: a.message.clone(),
+                raised_at: now,
+            })
+            .collect();
+
+        self.active = current;
+        for alert in &raised {
+            self.alerts.push_back(alert.clone());
+            if self.alerts.len() > TRADE_ALERT_HISTORY {
+                self.alerts.pop_front();
+            }
+        }
+        raised
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(detector: &str, category: &str, pnl: f64, execution_time_ms: u64) -> TradeSample {
+        TradeSample {
+            trade_id: uuid::Uuid::new_v4().to_string(),
+            detector: detector.to_string(),
+            category: category.to_string(),
+            notional: 500.0,
+            pnl,
+            slippage_pct: 0.1,
+            execution_time_ms,
+            timestamp: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_losing_cluster_is_flagged_once() {
+        let mut samples = Vec::new();
+        for i in 0..20 {
+            samples.push(sample("YesNoSimple", "Politics", 5.0 + (i % 3) as f64, 40));
+            samples.push(sample("YesNoSimple", "Crypto", 4.0 + (i % 2) as f64, 45));
+        }
+        for i in 0..6 {
+            samples.push(sample("GraphArbitrage", "Sports", -10.0 - i as f64, 400));
+        }
+        samples.push(sample("YesNoMulti", "Sports", -50.0, 40)); // Troppo piccolo per un flag
+
+        let report = cluster_trades(&samples);
+        assert_eq!(report.total_trades, 47);
+        assert_eq!(report.clusters.len(), 4);
+        assert_eq!(report.clusters[0].key.detector, "GraphArbitrage");
+        assert_eq!(report.clusters[0].loss_rate, 1.0);
+
+        let kinds: Vec<AnomalyKind> = report.anomalies.iter().map(|a| a.kind).collect();
+        assert!(kinds.contains(&AnomalyKind::SystematicLoss) && kinds.contains(&AnomalyKind::SlowExecution));
+        assert!(report.anomalies.iter().all(|a| a.key.category == "Sports" && a.key.detector == "GraphArbitrage"));
+
+        let mut monitor = TradeAnomalyMonitor::new();
+        assert_eq!(monitor.check(&report, Utc::now()).len(), 2);
+        assert!(monitor.check(&report, Utc::now()).is_empty());
+        // Rientrato e poi di nuovo anomalo: nuovo alert
+        monitor.check(&ClusterReport::default(), Utc::now());
+        assert_eq!(monitor.check(&report, Utc::now()).len(), 2);
+        assert_eq!(monitor.alerts.len(), 4);
+    }
+}
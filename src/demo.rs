@@ -0,0 +1,441 @@
+//! Demo scenario module
+//!
+//! Implements:
+//! 1. Scripted markets and mispricings, identical on every run: fair prices, an underpriced
+//!    YES+NO pair, an edge below the threshold, an edge eaten by the spread, an overpriced pair
+//! 2. The full bot pipeline stepped through the script on fixed prices (no random walk)
+//! 3. Dashboard state fed with the script's markets, the trades and the explanation of each step,
+//!    while the API server runs as usual
+
+use crate::api_server::{self, AppState};
+use crate::market::DataSource;
+use crate::paper::{MarketInfo, SimulatedTrade, TradeSource};
+use crate::types::{ArbType, BotConfig, MarketData, TradeExecution};
+use crate::HftArbitrageBot;
+use actix_web::web;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Demo run parameters
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DemoConfig {
+    pub port: u16,
+    pub step_interval_ms: u64, // Tempo per leggere ogni passo sulla dashboard
+    pub repeat: bool,          // Ricomincia lo scenario alla fine
+}
+
+impl Default for DemoConfig {
+    fn default() -> Self {
+        Self { port: 8080, step_interval_ms: 5_000, repeat: true }
+    }
+}
+
+impl DemoConfig {
+    /// Parse `--port 8080 --step-ms 5000 --once`
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let mut config = Self::default();
+        let mut iter = args.iter();
+
+        while let Some(flag) = iter.next() {
+            if flag == "--once" {
+                config.repeat = false;
+                continue;
+            }
+            let value = iter.next().ok_or_else(|| format!("Missing value for {}", flag))?;
+            let invalid = || format!("Invalid value for {}: {}", flag, value);
+            match flag.as_str() {
+                "--port" => config.port = value.parse().map_err(|_| invalid())?,
+                "--step-ms" => config.step_interval_ms = value.parse().map_err(|_| invalid())?,
+                _ => return Err(format!("Unknown option {}", flag)),
+            }
+        }
+
+        if config.step_interval_ms == 0 {
+            return Err("step interval must be positive".to_string());
+        }
+        Ok(config)
+    }
+}
+
+/// Prices of one market at a step of the script
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DemoQuote {
+    pub market_id: String,
+    pub yes_price: f64,
+    pub no_price: f64,
+    pub spread: Option<f64>,
+}
+
+/// One step of the script: markets not quoted go back to their fair prices
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DemoStep {
+    pub label: String,
+    pub explanation: String,
+    pub quotes: Vec<DemoQuote>,
+    pub expected_trade: Option<ArbType>, // Trade che il passo deve produrre, None se nessuno
+}
+
+/// Where the demo is, as shown by the dashboard
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DemoProgress {
+    pub round: u32,
+    pub step: usize,
+    pub total_steps: usize,
+    pub label: String,
+    pub explanation: String,
+    pub opportunities: usize,
+    pub trades: Vec<String>, // Descrizione dei trade eseguiti nel passo
+}
+
+/// Scripted markets and the steps that misprice them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DemoScenario {
+    pub markets: Vec<MarketData>, // Prezzi equi (YES + NO = 1)
+    pub steps: Vec<DemoStep>,
+}
+
+impl DemoScenario {
+    /// The built-in script
+    pub fn scripted() -> Self {
+        let market = |id: &str, question: &str, yes_price: f64, category: &str| MarketData {
+            id: id.to_string(),
+            question: question.to_string(),
+            yes_price,
+            no_price: 1.0 - yes_price,
+            yes_liquidity: 2_500.0,
+            no_liquidity: 2_500.0,
+            volume_24h: 50_000.0,
+            category: Some(category.to_string()),
+            ..MarketData::default()
+        };
+        let quote = |market_id: &str, yes_price: f64, no_price: f64, spread: Option<f64>| DemoQuote {
+            market_id: market_id.to_string(),
+            yes_price,
+            no_price,
+            spread,
+        };
+        let step = |label: &str, explanation: &str, quotes: Vec<DemoQuote>, expected_trade: Option<ArbType>| DemoStep {
+            label: label.to_string(),
+            explanation: explanation.to_string(),
+            quotes,
+            expected_trade,
+        };
+
+        Self {
+            markets: vec![
+                market("demo_btc", "Will BTC close above $100k on Dec 31?", 0.62, "Crypto"),
+                market("demo_fed", "Will the Fed cut rates in March?", 0.41, "Economics"),
+                market("demo_rain", "Will it rain in London tomorrow?", 0.70, "Weather"),
+            ],
+            steps: vec![
+                step(
+                    "Fair prices",
+                    "Every market prices YES + NO at exactly 1: there is nothing to capture.",
+                    Vec::new(),
+                    None,
+                ),
+                step(
+                    "YES + NO below 1",
+                    "Fed cut trades at 0.41 YES + 0.54 NO = 0.95. Buying both pays 1 whatever happens: \
+                     the bot buys the pair and locks in ~5 cents per share.",
+                    vec![quote("demo_fed", 0.41, 0.54, None)],
+                    Some(ArbType::YesNoSimple),
+                ),
+                step(
+                    "Edge below the threshold",
+                    "London rain sums to 0.997: the 0.3 cent edge is below the 0.5 cent minimum profit \
+                     once gas is paid, so the bot lets it go.",
+                    vec![quote("demo_rain", 0.70, 0.297, None)],
+                    None,
+                ),
+                step(
+                    "Edge eaten by the spread",
+                    "BTC sums to 0.97, but the bid-ask spread is 4 cents: crossing it costs more than \
+                     the 3 cent edge, so the opportunity is skipped.",
+                    vec![quote("demo_btc", 0.60, 0.37, Some(0.04))],
+                    None,
+                ),
+                step(
+                    "YES + NO above 1",
+                    "BTC bids sum to 0.66 + 0.38 = 1.04. Minting a YES+NO set costs 1 USDC: the bot \
+                     mints and sells both tokens for ~4 cents per set.",
+                    vec![quote("demo_btc", 0.66, 0.38, None)],
+                    Some(ArbType::YesNoSell),
+                ),
+                step(
+                    "Back to fair",
+                    "Arbitrageurs closed the gaps: prices sum to 1 again and the bot waits.",
+                    Vec::new(),
+                    None,
+                ),
+            ],
+        }
+    }
+
+    /// Bot on the script's markets, with prices only moved by the script
+    pub fn bot(&self, initial_capital: f64) -> HftArbitrageBot {
+        let mut bot = HftArbitrageBot::new(BotConfig { initial_capital, ..BotConfig::default() });
+        bot.market_manager.set_data_source(DataSource::Scripted);
+        for market in &self.markets {
+            bot.market_manager.add_market(market.clone());
+        }
+        bot
+    }
+
+    /// Apply the prices of step `index` and run one pipeline step; returns the opportunities and the trades
+    pub async fn play(&self, bot: &mut HftArbitrageBot, index: usize) -> Result<(usize, Vec<TradeExecution>), String> {
+        let step = self.steps.get(index).ok_or_else(|| format!("Demo step {} out of range", index))?;
+        for fair in &self.markets {
+            let mut market = fair.clone();
+            if let Some(quote) = step.quotes.iter().find(|q| q.market_id == market.id) {
+                market.yes_price = quote.yes_price;
+                market.no_price = quote.no_price;
+                market.spread = quote.spread;
+            }
+            market.timestamp = Utc::now();
+            bot.market_manager.add_market(market);
+        }
+
+        let executed_before = bot.executor.executed_trades.len();
+        let result = bot.run_step().await?;
+        Ok((result.opportunities, bot.executor.executed_trades[executed_before..].to_vec()))
+    }
+}
+
+/// Dashboard trade of an executed arbitrage
+fn dashboard_trade(trade: &TradeExecution, question: &str) -> SimulatedTrade {
+    let action = match trade.arb_type {
+        ArbType::YesNoSimple => "BUY_YES_NO".to_string(),
+        ArbType::YesNoSell => "MINT_SELL_YES_NO".to_string(),
+        other => format!("ARB_{:?}", other),
+    };
+    let quantity = trade.legs.iter().map(|l| l.quantity).fold(f64::INFINITY, f64::min);
+    SimulatedTrade {
+        id: trade.trade_id.clone(),
+        market_id: trade.market_id.clone(),
+        question: question.to_string(),
+        action,
+        price: trade.legs.iter().map(|l| l.price).sum(),
+        quantity: if quantity.is_finite() { quantity } else { 0.0 },
+        amount: trade.total_investment,
+        timestamp: trade.exit_time,
+        status: "FILLED".to_string(),
+        pnl: 0.0,
+        arbitrage_profit: trade.profit,
+        source: TradeSource::Bot,
+    }
+}
+
+/// Step through the script forever (or once), publishing every step to the dashboard state
+async fn drive(state: web::Data<AppState>, config: DemoConfig) {
+    let scenario = DemoScenario::scripted();
+    let mut interval = tokio::time::interval(Duration::from_millis(config.step_interval_ms));
+    *state.data_source.lock().unwrap() = Some(DataSource::Scripted);
+    state.bot_state.lock().unwrap().running = true;
+
+    let mut round = 0;
+    loop {
+        round += 1;
+        // Un bot nuovo a ogni giro: la deduplicazione non nasconde le opportunità già viste
+        let initial_capital = state.bot_state.lock().unwrap().balance;
+        let mut bot = scenario.bot(initial_capital);
+        bot.stream_opportunities_to(state.opportunities.clone());
+
+        for (index, step) in scenario.steps.iter().enumerate() {
+            interval.tick().await;
+            let (opportunities, trades) = match scenario.play(&mut bot, index).await {
+                Ok(outcome) => outcome,
+                Err(e) => {
+                    eprintln!("⚠️  Demo step {} failed: {}", index + 1, e);
+                    continue;
+                }
+            };
+            println!("🎬 [{}/{}] {}: {}", index + 1, scenario.steps.len(), step.label, step.explanation);
+
+            let markets: Vec<MarketInfo> = bot.market_manager.get_all_markets().into_iter().map(MarketInfo::from).collect();
+            let mut described = Vec::new();
+            for trade in &trades {
+                let question = bot.market_manager.get_market(&trade.market_id).map_or("", |m| m.question.as_str());
+                let booked = dashboard_trade(trade, question);
+                let result = {
+                    let mut bot_state = state.bot_state.lock().unwrap();
+                    state.broker.lock().unwrap().book_round_trip(&mut bot_state, &booked)
+                };
+                match result {
+                    Ok(()) => {
+                        let description = format!("{} {} for {:.2} USDC, profit {:.2}", booked.action, booked.market_id, booked.amount, booked.arbitrage_profit);
+                        println!("   💰 {}", description);
+                        described.push(description);
+                        state.trades.lock().unwrap().push(booked);
+                    }
+                    Err(e) => eprintln!("⚠️  Demo trade rejected by the paper risk checks: {}", e),
+                }
+            }
+
+            *state.markets.lock().unwrap() = markets;
+            state.bot_state.lock().unwrap().last_update = Utc::now();
+            *state.demo.lock().unwrap() = Some(DemoProgress {
+                round,
+                step: index + 1,
+                total_steps: scenario.steps.len(),
+                label: step.label.clone(),
+                explanation: step.explanation.clone(),
+                opportunities,
+                trades: described,
+            });
+        }
+
+        if !config.repeat {
+            break;
+        }
+    }
+    state.bot_state.lock().unwrap().running = false;
+}
+
+/// Start the dashboard API and play the demo script on it
+pub async fn run_demo(config: DemoConfig) -> std::io::Result<()> {
+    let state = web::Data::new(AppState::new());
+    tokio::spawn(drive(state.clone(), config.clone()));
+    api_server::serve(config.port, state).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::webhook::{OpportunityWebhook, OpportunityWebhookConfig};
+
+    #[tokio::test]
+    async fn test_script_trades_exactly_at_its_mispricings() {
+        let scenario = DemoScenario::scripted();
+        let mut bot = scenario.bot(10_000.0);
+
+        for (index, step) in scenario.steps.iter().enumerate() {
+            let (_, trades) = scenario.play(&mut bot, index).await.unwrap();
+            let kinds: Vec<ArbType> = trades.iter().map(|t| t.arb_type).collect();
+            assert_eq!(kinds, step.expected_trade.into_iter().collect::<Vec<_>>(), "step {}", step.label);
+            for trade in &trades {
+                assert!(trade.profit > 0.0, "step {}: {:?}", step.label, trade);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_subscribers_receive_detected_opportunities() {
+        let scenario = DemoScenario::scripted();
+        let mut bot = scenario.bot(10_000.0);
+        let (mut first, mut second) = (bot.subscribe_opportunities(), bot.subscribe_opportunities());
+
+        scenario.play(&mut bot, 0).await.unwrap();
+        assert!(first.try_recv().is_err());
+        scenario.play(&mut bot, 1).await.unwrap();
+        for receiver in [&mut first, &mut second] {
+            let opportunity = receiver.try_recv().unwrap();
+            assert_eq!((opportunity.market_id.as_str(), opportunity.arb_type), ("demo_fed", ArbType::YesNoSimple));
+            assert!(receiver.try_recv().is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_builtin_detectors_are_registered_and_removable() {
+        let scenario = DemoScenario::scripted();
+        let mut bot = scenario.bot(10_000.0);
+        let names: Vec<&str> = bot.detectors.iter().map(|d| d.name()).collect();
+        assert_eq!(names, vec!["yes_no", "graph"]);
+
+        // Senza il detector YES/NO la coppia sottoprezzata dello step 1 non viene più vista
+        assert!(bot.remove_detector("yes_no"));
+        assert!(!bot.remove_detector("yes_no"));
+        scenario.play(&mut bot, 0).await.unwrap();
+        let (opportunities, trades) = scenario.play(&mut bot, 1).await.unwrap();
+        assert_eq!(opportunities, 0);
+        assert!(trades.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_signal_only_webhook_publishes_instead_of_executing() {
+        use crate::webhook::OpportunitySignal;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Ricevente di prova: legge una richiesta completa e risponde 200 con il body decodificato
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/signals", listener.local_addr().unwrap());
+        let receiver = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 8192];
+            loop {
+                let n = socket.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&request).to_string();
+                let Some((head, body)) = text.split_once("\r\n\r\n") else { continue };
+                let length: usize = head.lines()
+                    .find_map(|l| l.to_ascii_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse().unwrap()))
+                    .unwrap();
+                if body.len() >= length {
+                    socket.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n").await.unwrap();
+                    return serde_json::from_str::<OpportunitySignal>(body).unwrap();
+                }
+            }
+        });
+
+        let scenario = DemoScenario::scripted();
+        let mut bot = scenario.bot(10_000.0);
+        bot.opportunity_webhook = Some(OpportunityWebhook::new(OpportunityWebhookConfig::new(&url)).unwrap());
+
+        scenario.play(&mut bot, 0).await.unwrap();
+        let (_, trades) = scenario.play(&mut bot, 1).await.unwrap();
+        assert!(trades.is_empty());
+        assert_eq!(bot.capital, 10_000.0);
+
+        // Consegna dal task in background, senza flush dal passo di trading
+        let signal = tokio::time::timeout(std::time::Duration::from_secs(5), receiver).await.unwrap().unwrap();
+        assert_eq!(signal.opportunity.market_id, "demo_fed");
+        assert!(!signal.executed_internally);
+        assert_eq!(signal.plan.legs.len(), 2);
+        assert!(signal.plan.expected_return > signal.plan.total_investment);
+    }
+
+    #[tokio::test]
+    async fn test_cross_venue_pair_is_published_not_executed() {
+        use crate::missed_edge::MissCause;
+        use crate::venues::{CrossVenueConfig, CrossVenueDetector, EquivalentContract, VenueFees, VenueQuote};
+        use crate::types::TokenType;
+
+        let scenario = DemoScenario::scripted();
+        let mut bot = scenario.bot(10_000.0);
+        let mut config = CrossVenueConfig::new(vec![EquivalentContract {
+            market_id: "demo_rain".to_string(),
+            venue: "kalshi".to_string(),
+            venue_market_id: "RAIN-LDN".to_string(),
+            inverted: false,
+        }]);
+        config.fees.insert("kalshi".to_string(), VenueFees { fee_bps: 0.0, variance_rate: 0.07 });
+        bot.register_detector(Box::new(CrossVenueDetector::new(config, bot.venue_quotes.clone())));
+        let mut stream = bot.subscribe_opportunities();
+        // Prezzi equi su Polymarket (NO a 0.30), YES sottoprezzato su Kalshi
+        bot.venue_quotes.update(VenueQuote {
+            venue: "kalshi".to_string(),
+            market_id: "RAIN-LDN".to_string(),
+            yes_price: 0.62,
+            timestamp: Utc::now(),
+            no_price: Some(0.40),
+            liquidity: None,
+        });
+
+        let (_, trades) = scenario.play(&mut bot, 0).await.unwrap();
+        assert!(trades.is_empty());
+        assert_eq!(bot.capital, 10_000.0);
+
+        // Pubblicata con una gamba per venue, contata come non eseguita per la venue esterna
+        let opportunity = std::iter::from_fn(|| stream.try_recv().ok())
+            .find(|o| o.arb_type == ArbType::CrossVenue)
+            .unwrap();
+        let legs: Vec<(Option<&str>, &str, TokenType)> = opportunity.legs.iter().flatten()
+            .map(|l| (l.venue.as_deref(), l.market_id.as_str(), l.token_type))
+            .collect();
+        assert_eq!(legs, vec![(None, "demo_rain", TokenType::No), (Some("kalshi"), "RAIN-LDN", TokenType::Yes)]);
+        let report = bot.missed_edge.report();
+        assert!(report.by_cause.iter().any(|c| c.cause == MissCause::ExternalVenue && c.count == 1));
+    }
+}
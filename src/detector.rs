@@ -0,0 +1,196 @@
+//! Pluggable detector module
+//!
+//! Implements:
+//! 1. `MarketView`: the markets, events, books, price histories, relations and reward programs of one scan
+//! 2. `Detector` trait, implemented by the YES/NO, graph, pair stat-arb and maker-side detectors
+//! 3. One list of detectors on the bot: the built-in ones first, then custom ones registered on it,
+//!    each of which can be removed by name
+//! 4. Detectors shared through `Arc`, so the bot keeps a typed handle on the ones it tunes
+
+use crate::arbitrage::{ArbitrageDetector, GraphArbitrageDetector};
+use crate::market::{PriceSnapshot, Watchlist};
+use crate::orderbook::OrderBookStore;
+use crate::relations::{MarketRelation, MarketTriangle};
+use crate::rewards::RewardBook;
+use crate::stat_arb::StatArbDetector;
+use crate::types::{ArbitrageOpportunity, EventData, MarketData};
+use chrono::{DateTime, Utc};
+use fxhash::FxHashMap;
+use std::sync::{Arc, RwLock};
+
+/// Market state shared by every detector in one scan
+#[derive(Debug, Clone, Copy)]
+pub struct MarketView<'a> {
+    pub markets: &'a [MarketData], // Mercati dovuti per la scansione in questo step
+    pub events: &'a [EventData],   // Eventi senza mercati stale
+    pub watchlist: &'a Watchlist,
+    pub order_books: &'a OrderBookStore,
+    pub price_history: &'a FxHashMap<String, Vec<PriceSnapshot>>,
+    pub relations: &'a [MarketRelation],
+    pub triangles: &'a [MarketTriangle],
+    pub rewards: &'a RewardBook, // Programmi di reward CLOB, per i detector di ordini maker
+    pub now: DateTime<Utc>,
+}
+
+/// Source of arbitrage opportunities
+///
+/// `scan` takes `&self`: stateful detectors (like the incremental price graph) keep their state
+/// behind a lock. Opportunities not already on the CLOB tick grid are re-priced on it, then all
+/// go through the same dedup, optimizer and risk checks.
+pub trait Detector: Send + Sync {
+    /// Name shown in logs, and used to remove the detector from the bot
+    fn name(&self) -> &str;
+
+    fn scan(&self, view: &MarketView<'_>) -> Vec<ArbitrageOpportunity>;
+
+    /// Whether the opportunities are already executable on the CLOB tick grid and minimum size
+    fn on_tick_grid(&self) -> bool {
+        false
+    }
+}
+
+impl<T: Detector + ?Sized> Detector for Arc<T> {
+    fn name(&self) -> &str {
+        (**self).name()
+    }
+
+    fn scan(&self, view: &MarketView<'_>) -> Vec<ArbitrageOpportunity> {
+        (**self).scan(view)
+    }
+
+    fn on_tick_grid(&self) -> bool {
+        (**self).on_tick_grid()
+    }
+}
+
+impl Detector for ArbitrageDetector {
+    fn name(&self) -> &str {
+        "yes_no"
+    }
+
+    /// YES/NO and mint-and-sell, negative-risk events, duplicate markets, relations and triangles
+    fn scan(&self, view: &MarketView<'_>) -> Vec<ArbitrageOpportunity> {
+        let mut opportunities = self.scan_markets_with_watchlist(view.markets, view.watchlist, view.order_books);
+        opportunities.extend(self.scan_events(view.events));
+        opportunities.extend(self.scan_duplicate_markets(view.markets));
+        opportunities.extend(self.scan_relations(view.markets, view.relations));
+        opportunities.extend(self.scan_triangles(view.markets, view.triangles));
+        opportunities
+    }
+
+    fn on_tick_grid(&self) -> bool {
+        true
+    }
+}
+
+/// YES/NO detector shared with the bot, which sets its tick sizes, thresholds and dedup between scans
+impl Detector for RwLock<ArbitrageDetector> {
+    fn name(&self) -> &str {
+        "yes_no"
+    }
+
+    fn scan(&self, view: &MarketView<'_>) -> Vec<ArbitrageOpportunity> {
+        Detector::scan(&*self.read().unwrap(), view)
+    }
+
+    fn on_tick_grid(&self) -> bool {
+        true
+    }
+}
+
+impl Detector for GraphArbitrageDetector {
+    fn name(&self) -> &str {
+        "graph"
+    }
+
+    fn scan(&self, view: &MarketView<'_>) -> Vec<ArbitrageOpportunity> {
+        self.scan_markets(view.markets)
+    }
+
+    // Cicli senza leg: nulla da riprezzare sulla griglia
+    fn on_tick_grid(&self) -> bool {
+        true
+    }
+}
+
+impl Detector for StatArbDetector {
+    fn name(&self) -> &str {
+        "pair_stat_arb"
+    }
+
+    fn scan(&self, view: &MarketView<'_>) -> Vec<ArbitrageOpportunity> {
+        self.detect(view.markets, view.price_history)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ArbType;
+
+    /// Detector di prova: segnala ogni mercato con YES sotto la soglia
+    struct CheapYes(f64);
+
+    impl Detector for CheapYes {
+        fn name(&self) -> &str {
+            "cheap_yes"
+        }
+
+        fn scan(&self, view: &MarketView<'_>) -> Vec<ArbitrageOpportunity> {
+            view.markets.iter()
+                .filter(|m| m.yes_price < self.0)
+                .map(|m| ArbitrageOpportunity {
+                    market_id: m.id.clone(),
+                    question: m.question.clone(),
+                    arb_type: ArbType::StatisticalArb,
+                    yes_price: m.yes_price,
+                    no_price: m.no_price,
+                    sum_price: m.yes_price,
+                    profit: self.0 - m.yes_price,
+                    roi_pct: (self.0 - m.yes_price) / m.yes_price * 100.0,
+                    liquidity: m.yes_liquidity,
+                    timestamp: view.now,
+                    confidence: 0.5,
+                    legs: None,
+                    path: None,
+                    maker: None,
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_builtin_and_custom_detectors_share_the_view() {
+        let markets = vec![
+            MarketData { id: "mispriced".to_string(), yes_price: 0.40, no_price: 0.50, yes_liquidity: 5000.0, no_liquidity: 5000.0, ..MarketData::default() },
+            MarketData { id: "fair".to_string(), yes_price: 0.55, no_price: 0.45, yes_liquidity: 5000.0, no_liquidity: 5000.0, ..MarketData::default() },
+        ];
+        let (watchlist, books, history, rewards) = (Watchlist::default(), OrderBookStore::default(), FxHashMap::default(), RewardBook::new());
+        let view = MarketView {
+            markets: &markets,
+            events: &[],
+            watchlist: &watchlist,
+            order_books: &books,
+            price_history: &history,
+            relations: &[],
+            triangles: &[],
+            rewards: &rewards,
+            now: Utc::now(),
+        };
+
+        let detectors: Vec<Box<dyn Detector>> = vec![
+            Box::new(ArbitrageDetector::new(0.02, 1000.0)),
+            Box::new(GraphArbitrageDetector::new()),
+            Box::new(CheapYes(0.45)),
+        ];
+        let found: Vec<(String, String)> = detectors.iter()
+            .flat_map(|d| {
+                let name = d.name().to_string();
+                d.scan(&view).into_iter().map(move |o| (name.clone(), o.market_id))
+            })
+            .collect();
+        assert!(found.contains(&("yes_no".to_string(), "mispriced".to_string())));
+        assert!(found.contains(&("cheap_yes".to_string(), "mispriced".to_string())));
+        assert!(!found.iter().any(|(_, id)| id == "fair"));
+    }
+}
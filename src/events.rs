@@ -0,0 +1,551 @@
+//! Event export module
+//!
+//! Implements:
+//! 1. Schema-tagged envelopes for trades, opportunities and risk events
+//! 2. JSON payloads, or Avro binary datums of a fixed envelope schema (`AVRO_ENVELOPE_SCHEMA`)
+//! 3. NATS publisher (core protocol over TCP) and Kafka publisher through the Confluent REST Proxy
+//! 4. Bounded channel filled by the trading step and drained in batches by a spawned publishing task,
+//!    so a slow broker never blocks detection: when the channel is full, new events are dropped and counted
+//! 5. NATS keep-alive: server PINGs are answered with PONG while the publisher is idle
+
+use crate::reconciliation::ReconciliationAlarm;
+use crate::risk::{ResolutionRiskFlag, RiskStatus};
+use crate::types::{ArbitrageOpportunity, TradeExecution};
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use reqwest::Client as HttpClient;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+
+/// Producer name carried by every envelope
+pub const EVENT_SOURCE: &str = "polymarket_arb_hft";
+
+/// Version of the payload schemas; bumped on breaking changes to the exported structs
+pub const EVENT_SCHEMA_VERSION: i32 = 1;
+
+/// Longest wait between two attempts to publish the same batch
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Avro schema of the envelope; the payload is the JSON of the schema named in `schema`
+pub const AVRO_ENVELOPE_SCHEMA: &str = r#"{"type":"record","name":"EventEnvelope","namespace":"polymarket_arb","fields":[{"name":"schema","type":"string"},{"name":"version","type":"int"},{"name":"source","type":"string"},{"name":"emitted_at","type":{"type":"long","logicalType":"timestamp-millis"}},{"name":"key","type":"string"},{"name":"payload","type":"string"}]}"#;
+
+fn default_topic_prefix() -> String {
+    "polymarket.arb".to_string()
+}
+
+fn default_max_queue() -> usize {
+    10_000
+}
+
+fn default_batch_size() -> usize {
+    500
+}
+
+fn default_timeout_ms() -> u64 {
+    5000
+}
+
+/// Payload encoding of the exported events
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventFormat {
+    #[default]
+    Json,
+    Avro,
+}
+
+/// Broker the events are published to, chosen by the URL scheme
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventTransport {
+    Nats,  // nats://host:4222
+    Kafka, // http(s)://rest-proxy:8082
+}
+
+/// Event export settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventExportConfig {
+    pub url: String, // nats://... per NATS, http(s)://... per il REST Proxy di Kafka
+    #[serde(default)]
+    pub format: EventFormat,
+    #[serde(default = "default_topic_prefix")]
+    pub topic_prefix: String, // Topic/subject: <prefix>.trades, <prefix>.opportunities, <prefix>.risk
+    #[serde(default = "default_max_queue")]
+    pub max_queue: usize, // Eventi in attesa di pubblicazione; oltre, i nuovi sono scartati
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize, // Eventi inviati per richiesta al broker
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+impl EventExportConfig {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            format: EventFormat::default(),
+            topic_prefix: default_topic_prefix(),
+            max_queue: default_max_queue(),
+            batch_size: default_batch_size(),
+            timeout_ms: default_timeout_ms(),
+        }
+    }
+
+    pub fn transport(&self) -> Result<EventTransport, String> {
+        let scheme = self.url.split("://").next().unwrap_or_default().to_ascii_lowercase();
+        match scheme.as_str() {
+            "nats" => Ok(EventTransport::Nats),
+            "http" | "https" => Ok(EventTransport::Kafka),
+            _ => Err(format!("Unsupported event export URL (expected nats:// or http(s)://): {}", self.url)),
+        }
+    }
+}
+
+/// Risk-side event worth a downstream alert
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RiskEvent {
+    TradingHalted { capital: f64, status: RiskStatus },
+    ResolutionFlag(ResolutionRiskFlag),
+    ReconciliationAlarm(ReconciliationAlarm),
+}
+
+/// One exported event
+#[derive(Debug, Clone)]
+pub enum ExportEvent {
+    Trade(TradeExecution),
+    Opportunity(ArbitrageOpportunity),
+    Risk(RiskEvent),
+}
+
+impl ExportEvent {
+    /// Name of the payload schema
+    pub fn schema(&self) -> &'static str {
+        match self {
+            ExportEvent::Trade(_) => "polymarket_arb.TradeExecution",
+            ExportEvent::Opportunity(_) => "polymarket_arb.ArbitrageOpportunity",
+            ExportEvent::Risk(_) => "polymarket_arb.RiskEvent",
+        }
+    }
+
+    /// Topic suffix the event goes to
+    pub fn topic(&self) -> &'static str {
+        match self {
+            ExportEvent::Trade(_) => "trades",
+            ExportEvent::Opportunity(_) => "opportunities",
+            ExportEvent::Risk(_) => "risk",
+        }
+    }
+
+    /// Partition key: the market the event is about
+    pub fn key(&self) -> String {
+        match self {
+            ExportEvent::Trade(t) => t.market_id.clone(),
+            ExportEvent::Opportunity(o) => o.market_id.clone(),
+            ExportEvent::Risk(RiskEvent::TradingHalted { .. }) => String::new(),
+            ExportEvent::Risk(RiskEvent::ResolutionFlag(f)) => f.market_id.clone(),
+            ExportEvent::Risk(RiskEvent::ReconciliationAlarm(a)) => a.mismatch.key.clone(),
+        }
+    }
+
+    fn payload(&self) -> serde_json::Value {
+        let value = match self {
+            ExportEvent::Trade(t) => serde_json::to_value(t),
+            ExportEvent::Opportunity(o) => serde_json::to_value(o),
+            ExportEvent::Risk(r) => serde_json::to_value(r),
+        };
+        value.unwrap_or(serde_json::Value::Null)
+    }
+}
+
+/// Schema-tagged wrapper of an exported event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventEnvelope {
+    pub schema: String,
+    pub version: i32,
+    pub source: String,
+    pub emitted_at: DateTime<Utc>,
+    pub key: String,
+    pub payload: serde_json::Value,
+}
+
+impl EventEnvelope {
+    pub fn new(event: &ExportEvent, emitted_at: DateTime<Utc>) -> Self {
+        Self {
+            schema: event.schema().to_string(),
+            version: EVENT_SCHEMA_VERSION,
+            source: EVENT_SOURCE.to_string(),
+            emitted_at,
+            key: event.key(),
+            payload: event.payload(),
+        }
+    }
+
+    /// Avro binary datum of the envelope under `AVRO_ENVELOPE_SCHEMA`
+    pub fn to_avro(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        avro_string(&mut out, &self.schema);
+        avro_long(&mut out, self.version as i64);
+        avro_string(&mut out, &self.source);
+        avro_long(&mut out, self.emitted_at.timestamp_millis());
+        avro_string(&mut out, &self.key);
+        avro_string(&mut out, &self.payload.to_string());
+        out
+    }
+
+    pub fn encode(&self, format: EventFormat) -> Vec<u8> {
+        match format {
+            EventFormat::Json => serde_json::to_vec(self).unwrap_or_default(),
+            EventFormat::Avro => self.to_avro(),
+        }
+    }
+}
+
+/// Avro `long`/`int`: zig-zag varint
+fn avro_long(out: &mut Vec<u8>, value: i64) {
+    let mut n = ((value << 1) ^ (value >> 63)) as u64;
+    while n >= 0x80 {
+        out.push((n as u8 & 0x7f) | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+/// Avro `string`: length then UTF-8 bytes
+fn avro_string(out: &mut Vec<u8>, value: &str) {
+    avro_long(out, value.len() as i64);
+    out.extend_from_slice(value.as_bytes());
+}
+
+/// Body of a Kafka REST Proxy v2 produce request, with its content type
+pub fn kafka_produce_body(envelopes: &[&EventEnvelope], format: EventFormat) -> (&'static str, serde_json::Value) {
+    match format {
+        EventFormat::Json => {
+            let records: Vec<serde_json::Value> = envelopes
+                .iter()
+                .map(|e| serde_json::json!({ "key": e.key, "value": e }))
+                .collect();
+            ("application/vnd.kafka.json.v2+json", serde_json::json!({ "records": records }))
+        }
+        EventFormat::Avro => {
+            // Datum Avro già serializzato: il proxy lo pubblica come bytes
+            let engine = base64::engine::general_purpose::STANDARD;
+            let records: Vec<serde_json::Value> = envelopes
+                .iter()
+                .map(|e| serde_json::json!({ "key": engine.encode(&e.key), "value": engine.encode(e.to_avro()) }))
+                .collect();
+            ("application/vnd.kafka.binary.v2+json", serde_json::json!({ "records": records }))
+        }
+    }
+}
+
+/// Queued event: destination topic and envelope
+type QueuedEvent = (String, EventEnvelope);
+
+/// Broker connection owned by the publishing task
+struct EventSink {
+    config: EventExportConfig,
+    transport: EventTransport,
+    http_client: HttpClient,
+    nats: Option<BufReader<TcpStream>>,
+    nats_line: Vec<u8>, // Riga del server letta in parte: read_until la completa alla ripresa
+    published: Arc<AtomicU64>,
+}
+
+impl EventSink {
+    /// Publish batches in order, retrying each with backoff (and a fresh connection) until the broker accepts it
+    async fn run(mut self, mut rx: mpsc::Receiver<QueuedEvent>) {
+        loop {
+            let first = match self.nats.as_mut() {
+                // In attesa di eventi si risponde ai PING del server NATS, che altrimenti chiude la connessione
+                Some(stream) => tokio::select! {
+                    event = rx.recv() => event,
+                    read = stream.read_until(b'\n', &mut self.nats_line) => {
+                        self.handle_server_line(read).await;
+                        continue;
+                    }
+                },
+                None => rx.recv().await,
+            };
+            let Some(first) = first else { break };
+            let mut batch = vec![first];
+            while batch.len() < self.config.batch_size.max(1) {
+                match rx.try_recv() {
+                    Ok(event) => batch.push(event),
+                    Err(_) => break,
+                }
+            }
+
+            let mut backoff = Duration::from_millis(250);
+            loop {
+                let sent = match self.transport {
+                    EventTransport::Nats => self.publish_nats(&batch).await,
+                    EventTransport::Kafka => self.publish_kafka(&batch).await,
+                };
+                match sent {
+                    Ok(()) => break,
+                    Err(e) => {
+                        self.disconnect();
+                        eprintln!("Event export failed ({} events, retry in {:?}): {}", batch.len(), backoff, e);
+                        tokio::time::sleep(backoff).await;
+                        backoff = (backoff * 2).min(MAX_RETRY_BACKOFF);
+                    }
+                }
+            }
+            self.published.fetch_add(batch.len() as u64, Ordering::Relaxed);
+        }
+    }
+
+    fn disconnect(&mut self) {
+        self.nats = None;
+        self.nats_line.clear();
+    }
+
+    /// Answer a server PING; a closed connection or a server error drops the connection
+    async fn handle_server_line(&mut self, read: std::io::Result<usize>) {
+        let line = String::from_utf8_lossy(&self.nats_line).trim().to_string();
+        match read {
+            Ok(0) | Err(_) => {
+                self.disconnect();
+                return;
+            }
+            Ok(_) if !self.nats_line.ends_with(b"\n") => return,
+            Ok(_) => self.nats_line.clear(),
+        }
+        if line == "PING" {
+            let pong = match self.nats.as_mut() {
+                Some(stream) => stream.get_mut().write_all(b"PONG\r\n").await,
+                None => return,
+            };
+            if pong.is_err() {
+                self.disconnect();
+            }
+        } else if line.starts_with("-ERR") {
+            eprintln!("NATS error: {}", line);
+            self.disconnect();
+        }
+    }
+
+    async fn publish_kafka(&self, batch: &[QueuedEvent]) -> Result<(), String> {
+        let base = self.config.url.trim_end_matches('/');
+        let mut topics: Vec<&str> = batch.iter().map(|(t, _)| t.as_str()).collect();
+        topics.sort_unstable();
+        topics.dedup();
+        for topic in topics {
+            let envelopes: Vec<&EventEnvelope> = batch.iter().filter(|(t, _)| t == topic).map(|(_, e)| e).collect();
+            let (content_type, body) = kafka_produce_body(&envelopes, self.config.format);
+            let response = self.http_client
+                .post(format!("{}/topics/{}", base, topic))
+                .header("Content-Type", content_type)
+                .header("Accept", "application/vnd.kafka.v2+json")
+                .json(&body)
+                .send()
+                .await
+                .map_err(|e| format!("Kafka REST Proxy unreachable: {}", e))?;
+            if !response.status().is_success() {
+                let status = response.status();
+                let text = response.text().await.unwrap_or_default();
+                return Err(format!("Kafka REST Proxy rejected {} events on {}: {} {}", envelopes.len(), topic, status, text));
+            }
+        }
+        Ok(())
+    }
+
+    async fn publish_nats(&mut self, batch: &[QueuedEvent]) -> Result<(), String> {
+        let timeout = Duration::from_millis(self.config.timeout_ms);
+        if self.nats.is_none() {
+            let connect = async {
+                let address = self.config.url.split("://").nth(1).unwrap_or_default().trim_end_matches('/');
+                let stream = TcpStream::connect(address).await.map_err(|e| format!("NATS unreachable: {}", e))?;
+                let mut stream = BufReader::new(stream);
+                // Il server apre con INFO; CONNECT senza verbose: nessun +OK da leggere
+                let mut info = String::new();
+                stream.read_line(&mut info).await.map_err(|e| e.to_string())?;
+                if !info.starts_with("INFO") {
+                    return Err(format!("Unexpected NATS greeting: {}", info.trim()));
+                }
+                let hello = format!("CONNECT {{\"verbose\":false,\"pedantic\":false,\"name\":\"{}\"}}\r\n", EVENT_SOURCE);
+                stream.get_mut().write_all(hello.as_bytes()).await.map_err(|e| e.to_string())?;
+                Ok(stream)
+            };
+            let stream = tokio::time::timeout(timeout, connect).await.map_err(|_| "NATS connect timed out".to_string())??;
+            self.nats = Some(stream);
+        }
+
+        let mut frames = Vec::new();
+        for (subject, envelope) in batch {
+            let payload = envelope.encode(self.config.format);
+            frames.extend_from_slice(format!("PUB {} {}\r\n", subject, payload.len()).as_bytes());
+            frames.extend_from_slice(&payload);
+            frames.extend_from_slice(b"\r\n");
+        }
+        let Some(stream) = self.nats.as_mut() else { return Err("NATS not connected".to_string()) };
+        let write = async {
+            stream.get_mut().write_all(&frames).await?;
+            stream.get_mut().flush().await
+        };
+        tokio::time::timeout(timeout, write)
+            .await
+            .map_err(|_| "NATS publish timed out".to_string())?
+            .map_err(|e| format!("NATS publish failed: {}", e))
+    }
+}
+
+/// Event export: events are queued by the trading step and published by a background task
+pub struct EventPublisher {
+    pub config: EventExportConfig,
+    sink: Option<EventSink>, // Fino all'avvio del task di pubblicazione
+    tx: mpsc::Sender<QueuedEvent>,
+    rx: Option<mpsc::Receiver<QueuedEvent>>,
+    published: Arc<AtomicU64>,
+    pub dropped: u64, // Scartati per coda piena
+}
+
+impl EventPublisher {
+    pub fn new(config: EventExportConfig) -> Result<Self, String> {
+        let transport = config.transport()?;
+        let published = Arc::new(AtomicU64::new(0));
+        let sink = EventSink {
+            config: config.clone(),
+            transport,
+            http_client: HttpClient::builder()
+                .timeout(Duration::from_millis(config.timeout_ms))
+                .build()
+                .map_err(|e| e.to_string())?,
+            nats: None,
+            nats_line: Vec::new(),
+            published: published.clone(),
+        };
+        let (tx, rx) = mpsc::channel(config.max_queue.max(1));
+        Ok(Self { config, sink: Some(sink), tx, rx: Some(rx), published, dropped: 0 })
+    }
+
+    /// Queue an event without waiting; dropped (and counted) when the queue is full
+    ///
+    /// The publishing task starts with the first event queued inside a Tokio runtime.
+    pub fn publish(&mut self, event: ExportEvent, now: DateTime<Utc>) {
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            if let (Some(sink), Some(rx)) = (self.sink.take(), self.rx.take()) {
+                runtime.spawn(sink.run(rx));
+            }
+        }
+        let topic = format!("{}.{}", self.config.topic_prefix, event.topic());
+        if self.tx.try_send((topic, EventEnvelope::new(&event, now))).is_err() {
+            self.dropped += 1;
+        }
+    }
+
+    /// Events queued and not yet taken by the publishing task
+    pub fn pending(&self) -> usize {
+        self.tx.max_capacity() - self.tx.capacity()
+    }
+
+    /// Events accepted by the broker
+    pub fn published(&self) -> u64 {
+        self.published.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flag() -> ResolutionRiskFlag {
+        let now = DateTime::from_timestamp(1_790_000_000, 0).unwrap();
+        ResolutionRiskFlag {
+            market_id: "m1".to_string(),
+            level: crate::risk::ResolutionRisk::Pause,
+            size_multiplier: 0.0,
+            headline: "Result confirmed".to_string(),
+            link: String::new(),
+            raised_at: now,
+            expires_at: now,
+        }
+    }
+
+    #[test]
+    fn test_envelope_is_schema_tagged() {
+        let now = DateTime::from_timestamp_millis(1_790_000_000_123).unwrap();
+        let event = ExportEvent::Risk(RiskEvent::ResolutionFlag(flag()));
+        let envelope = EventEnvelope::new(&event, now);
+        assert_eq!(envelope.schema, "polymarket_arb.RiskEvent");
+        assert_eq!(envelope.key, "m1");
+        assert_eq!(envelope.payload["kind"], "resolution_flag");
+        assert_eq!(envelope.payload["level"], "pause");
+
+        // Avro: stringhe con lunghezza zig-zag, poi int e long zig-zag
+        let avro = envelope.to_avro();
+        let schema_len = envelope.schema.len();
+        assert_eq!(avro[0] as usize, schema_len * 2);
+        assert_eq!(&avro[1..=schema_len], envelope.schema.as_bytes());
+        assert_eq!(avro[schema_len + 1], (EVENT_SCHEMA_VERSION * 2) as u8);
+        let mut long = Vec::new();
+        avro_long(&mut long, -65);
+        assert_eq!(long, vec![0x81, 0x01]);
+
+        let (content_type, body) = kafka_produce_body(&[&envelope], EventFormat::Json);
+        assert_eq!(content_type, "application/vnd.kafka.json.v2+json");
+        assert_eq!(body["records"][0]["key"], "m1");
+        assert_eq!(body["records"][0]["value"]["schema"], "polymarket_arb.RiskEvent");
+    }
+
+    #[test]
+    fn test_publisher_queue_is_bounded() {
+        assert!(EventPublisher::new(EventExportConfig::new("tcp://localhost:4222")).is_err());
+        let config = EventExportConfig { max_queue: 2, ..EventExportConfig::new("nats://localhost:4222") };
+        let mut publisher = EventPublisher::new(config).unwrap();
+        for _ in 0..3 {
+            publisher.publish(ExportEvent::Risk(RiskEvent::ResolutionFlag(flag())), Utc::now());
+        }
+        assert_eq!(publisher.pending(), 2);
+        assert_eq!(publisher.dropped, 1);
+        let (topic, _) = publisher.rx.as_mut().unwrap().try_recv().unwrap();
+        assert_eq!(topic, "polymarket.arb.risk");
+    }
+
+    #[tokio::test]
+    async fn test_nats_publisher_answers_pings_in_the_background() {
+        use tokio::io::AsyncReadExt;
+
+        // Server NATS di prova: INFO, un PING, poi inoltra tutto ciò che il client scrive
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let (seen_tx, mut seen_rx) = mpsc::channel::<String>(16);
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            socket.write_all(b"INFO {}\r\n").await.unwrap();
+            let mut received = String::new();
+            let mut buf = [0u8; 4096];
+            let mut pinged = false;
+            loop {
+                let n = socket.read(&mut buf).await.unwrap();
+                if n == 0 {
+                    break;
+                }
+                received.push_str(&String::from_utf8_lossy(&buf[..n]));
+                if !pinged && received.contains("PUB ") {
+                    socket.write_all(b"PING\r\n").await.unwrap();
+                    pinged = true;
+                }
+                let _ = seen_tx.send(received.clone()).await;
+            }
+        });
+
+        let mut publisher = EventPublisher::new(EventExportConfig::new(&format!("nats://{}", address))).unwrap();
+        publisher.publish(ExportEvent::Risk(RiskEvent::ResolutionFlag(flag())), Utc::now());
+        // Nessun flush: il task pubblica l'evento e risponde al PING successivo
+        let received = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let seen = seen_rx.recv().await.unwrap();
+                if seen.contains("PONG") {
+                    return seen;
+                }
+            }
+        }).await.unwrap();
+        assert!(received.contains("PUB polymarket.arb.risk "));
+        assert_eq!(publisher.published(), 1);
+    }
+}
@@ -0,0 +1,338 @@
+//! Exchange accounts module
+//!
+//! Implements:
+//! 1. Several Polymarket credential sets (API keys or wallet keys) configured side by side
+//! 2. Account-scoped CLOB clients sharing the process rate limiter
+//! 3. Paper accounts with their own ledger, for strategies run without live credentials
+//! 4. Per-account streams of open orders and balances
+
+use crate::paper::{BotState, PaperBroker, PaperPosition, PaperRiskLimits};
+use crate::polymarket_api::{ApiCredentials, Balances, ClobApiClient, OpenOrder, PolymarketApiClient};
+#[cfg(feature = "onchain")]
+use crate::wallet::Wallet;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tokio::sync::watch;
+
+/// Seconds between two refreshes of the account streams
+pub const ACCOUNT_REFRESH_SECS: i64 = 30;
+
+fn default_paper_balance() -> f64 { 10_000.0 }
+
+/// One configured account; without credentials or a wallet key it is paper-traded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExchangeAccountConfig {
+    pub name: String,
+    #[serde(default)]
+    pub api_key: Option<String>,
+    #[serde(default)]
+    pub secret: Option<String>,
+    #[serde(default)]
+    pub passphrase: Option<String>,
+    #[serde(default)]
+    pub private_key: Option<String>, // Chiave del wallet: deriva le credenziali se mancano
+    #[serde(default)]
+    pub wallet_address: Option<String>, // Indirizzo (proxy o Safe) se diverso da quello della chiave
+    #[serde(default = "default_paper_balance")]
+    pub paper_balance: f64, // Balance iniziale in modalità paper
+}
+
+/// How an account trades
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountMode {
+    Live,
+    Paper,
+}
+
+/// Latest orders and balances of one account, published on its stream
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountState {
+    pub name: String,
+    pub mode: AccountMode,
+    pub open_orders: Vec<OpenOrder>,
+    pub balances: Option<Balances>,
+    pub paper_positions: Vec<PaperPosition>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub error: Option<String>, // Ultimo errore di aggiornamento
+}
+
+impl AccountState {
+    fn empty(name: &str, mode: AccountMode) -> Self {
+        Self {
+            name: name.to_string(),
+            mode,
+            open_orders: Vec::new(),
+            balances: None,
+            paper_positions: Vec::new(),
+            updated_at: None,
+            error: None,
+        }
+    }
+}
+
+/// Paper ledger of an account
+pub struct PaperAccount {
+    pub broker: PaperBroker,
+    pub state: BotState,
+}
+
+/// Account-scoped client: a live CLOB client or a paper ledger
+pub struct AccountClient {
+    pub name: String,
+    live: Option<ClobApiClient>,
+    pub paper: Option<PaperAccount>,
+    stream: watch::Sender<AccountState>,
+}
+
+impl AccountClient {
+    pub fn mode(&self) -> AccountMode {
+        if self.live.is_some() { AccountMode::Live } else { AccountMode::Paper }
+    }
+
+    /// CLOB client of a live account
+    pub fn clob(&self) -> Option<&ClobApiClient> {
+        self.live.as_ref()
+    }
+
+    /// Stream of this account's orders and balances
+    pub fn subscribe(&self) -> watch::Receiver<AccountState> {
+        self.stream.subscribe()
+    }
+
+    pub fn state(&self) -> AccountState {
+        self.stream.borrow().clone()
+    }
+
+    /// Refresh orders and balances and publish them; `token_ids` are the positions read from the exchange
+    pub async fn refresh(&self, token_ids: &[String]) -> Result<(), String> {
+        let mut state = AccountState::empty(&self.name, self.mode());
+        let result = match (&self.live, &self.paper) {
+            (Some(clob), _) => {
+                let fetched = async {
+                    let orders = clob.get_open_orders(None, None).await?;
+                    let balances = clob.get_balances(token_ids).await?;
+                    Ok::<_, crate::polymarket_api::PolymarketApiError>((orders, balances))
+                }
+                .await;
+                match fetched {
+                    Ok((orders, balances)) => {
+                        state.open_orders = orders;
+                        state.balances = Some(balances);
+                        Ok(())
+                    }
+                    Err(e) => {
+                        // Si conserva l'ultimo stato buono, con l'errore
+                        state = self.state();
+                        Err(format!("Account {} refresh failed: {}", self.name, e))
+                    }
+                }
+            }
+            (None, Some(paper)) => {
+                state.paper_positions = paper.broker.open_positions();
+                state.balances = Some(Balances {
+                    collateral: paper.state.balance,
+                    collateral_allowance: paper.state.balance,
+                    positions: paper.broker.positions.iter().map(|(key, p)| (key.clone(), p.quantity)).collect(),
+                    fetched_at: Some(Utc::now()),
+                });
+                Ok(())
+            }
+            (None, None) => Ok(()),
+        };
+        state.error = result.as_ref().err().cloned();
+        state.updated_at = Some(Utc::now());
+        self.stream.send_replace(state);
+        result
+    }
+}
+
+/// Every configured account, by name
+#[derive(Default)]
+pub struct AccountRegistry {
+    accounts: BTreeMap<String, AccountClient>,
+    last_refresh: Option<DateTime<Utc>>,
+}
+
+impl AccountRegistry {
+    /// Build the account clients; live ones share the rate limiter and endpoints of `api`
+    pub fn from_configs(configs: &[ExchangeAccountConfig], api: Option<&PolymarketApiClient>) -> Result<Self, String> {
+        let mut accounts = BTreeMap::new();
+        for config in configs {
+            if config.name.trim().is_empty() {
+                return Err("Account name must not be empty".to_string());
+            }
+            if accounts.contains_key(&config.name) {
+                return Err(format!("Duplicate account {}", config.name));
+            }
+
+            let credentials = ApiCredentials::from_parts(config.api_key.clone(), config.secret.clone(), config.passphrase.clone());
+            let live = match api {
+                Some(api) if credentials.is_some() || config.private_key.is_some() => Some(Self::live_client(api, config, credentials)?),
+                _ => None,
+            };
+            let paper = live.is_none().then(|| PaperAccount {
+                broker: PaperBroker::new(PaperRiskLimits::default()),
+                state: BotState {
+                    running: false,
+                    balance: config.paper_balance,
+                    initial_balance: config.paper_balance,
+                    total_pnl: 0.0,
+                    win_rate: 0.0,
+                    total_trades: 0,
+                    profitable_trades: 0,
+                    last_update: Utc::now(),
+                },
+            });
+            let mode = if live.is_some() { AccountMode::Live } else { AccountMode::Paper };
+            let (stream, _) = watch::channel(AccountState::empty(&config.name, mode));
+
+            accounts.insert(config.name.clone(), AccountClient { name: config.name.clone(), live, paper, stream });
+        }
+        Ok(Self { accounts, last_refresh: None })
+    }
+
+    /// CLOB client of a live account, with its wallet attached for L1 authentication
+    fn live_client(api: &PolymarketApiClient, config: &ExchangeAccountConfig, credentials: Option<ApiCredentials>) -> Result<ClobApiClient, String> {
+        let client = api.account_client(credentials, config.wallet_address.clone())
+            .map_err(|e| format!("Account {}: {}", config.name, e))?;
+        let Some(key) = config.private_key.as_deref() else { return Ok(client) };
+
+        #[cfg(feature = "onchain")]
+        return Wallet::from_private_key(key)
+            .map(|wallet| client.with_wallet(wallet))
+            .map_err(|e| format!("Account {}: {}", config.name, e));
+
+        // Senza firma L1 la sola chiave non basta a derivare le credenziali
+        #[cfg(not(feature = "onchain"))]
+        {
+            let _ = key;
+            match client.is_authenticated() {
+                true => Ok(client),
+                false => Err(format!("Account {}: wallet keys need the \"onchain\" feature", config.name)),
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.accounts.keys().cloned().collect()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&AccountClient> {
+        self.accounts.get(name)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut AccountClient> {
+        self.accounts.get_mut(name)
+    }
+
+    /// Stream of one account's orders and balances
+    pub fn subscribe(&self, name: &str) -> Option<watch::Receiver<AccountState>> {
+        self.get(name).map(AccountClient::subscribe)
+    }
+
+    /// Derive L2 credentials of live accounts configured with only a wallet key; returns how many were derived
+    pub async fn authenticate(&mut self) -> usize {
+        let mut derived = 0;
+        for account in self.accounts.values_mut() {
+            let Some(clob) = account.live.as_mut() else { continue };
+            match clob.ensure_credentials().await {
+                Ok(true) => derived += 1,
+                Ok(false) => {}
+                Err(e) => eprintln!("⚠️  Account {}: CLOB API key derivation failed: {}", account.name, e),
+            }
+        }
+        derived
+    }
+
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        self.last_refresh.is_none_or(|last| (now - last).num_seconds() >= ACCOUNT_REFRESH_SECS)
+    }
+
+    /// Refresh every account's stream; returns how many refreshed successfully
+    pub async fn refresh_all(&mut self, token_ids: &[String], now: DateTime<Utc>) -> usize {
+        self.last_refresh = Some(now);
+        let mut refreshed = 0;
+        for account in self.accounts.values() {
+            match account.refresh(token_ids).await {
+                Ok(()) => refreshed += 1,
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+        refreshed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paper::{PaperOrder, TradeSource};
+    use crate::polymarket_api::PolymarketApiConfig;
+    use crate::types::{Direction, TokenType};
+
+    fn account(name: &str) -> ExchangeAccountConfig {
+        ExchangeAccountConfig {
+            name: name.to_string(),
+            api_key: None,
+            secret: None,
+            passphrase: None,
+            private_key: None,
+            wallet_address: None,
+            paper_balance: 1_000.0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_account_registry_modes_and_streams() {
+        let api = PolymarketApiClient::new(PolymarketApiConfig::default(), None, None, None).unwrap();
+        let live = ExchangeAccountConfig {
+            api_key: Some("00000000-0000-0000-0000-000000000000".to_string()),
+            secret: Some("c2VjcmV0".to_string()),
+            passphrase: Some("passphrase".to_string()),
+            ..account("main")
+        };
+        let mut registry = AccountRegistry::from_configs(&[live, account("sandbox")], Some(&api)).unwrap();
+        assert_eq!(registry.names(), vec!["main", "sandbox"]);
+        assert_eq!(registry.get("main").unwrap().mode(), AccountMode::Live);
+        assert_eq!(registry.get("sandbox").unwrap().mode(), AccountMode::Paper);
+        assert!(AccountRegistry::from_configs(&[account("a"), account("a")], None).is_err());
+
+        // Trade paper sul solo account "sandbox", visibile nel suo stream
+        let mut stream = registry.subscribe("sandbox").unwrap();
+        let sandbox = registry.get_mut("sandbox").unwrap();
+        let paper = sandbox.paper.as_mut().unwrap();
+        let market = crate::paper::MarketInfo {
+            id: "m1".to_string(),
+            question: "Test?".to_string(),
+            yes_price: 0.5,
+            no_price: 0.5,
+            yes_liquidity: 1000.0,
+            no_liquidity: 1000.0,
+            volume_24h: 0.0,
+            timestamp: Utc::now(),
+            event_id: None,
+            category: None,
+        };
+        let order = PaperOrder {
+            market_id: "m1".to_string(),
+            token_type: TokenType::Yes,
+            direction: Direction::Buy,
+            quantity: 100.0,
+            limit_price: None,
+            source: TradeSource::Manual,
+        };
+        paper.broker.execute(&mut paper.state, &order, &market).unwrap();
+        sandbox.refresh(&[]).await.unwrap();
+
+        assert!(stream.has_changed().unwrap());
+        let state = stream.borrow_and_update().clone();
+        assert_eq!(state.paper_positions.len(), 1);
+        assert!((state.balances.unwrap().collateral - 950.0).abs() < 1e-9);
+        assert!(registry.get("main").unwrap().state().balances.is_none());
+    }
+}
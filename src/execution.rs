@@ -2,15 +2,117 @@
 //!
 //! Implements:
 //! 1. VWAP-based order execution
-//! 2. MEV extraction
-//! 3. Parallel trade submission
-//! 4. Slippage estimation
+//! 2. Parallel trade submission
+//! 3. Slippage estimation
+//! 4. Clock-driven VWAP order slicing
+//! 5. Reconciliation of resting orders with CLOB order states
+//! 6. Tick size and minimum size enforcement on generated orders
+//! 7. Simulated partial fills and execution latency from the fault injector
+//! 8. Write-ahead journal of every execution, and recovery of executions left half-completed by a restart
+//! 9. Size floor and ceiling per market tier, picked by the opportunity's liquidity, before the
+//!    legs are rounded to each market's tick and minimum order size
+//! 10. Execution plans: the sized legs of an opportunity without sending them (signal-only mode)
+//! 11. Executions split into prepare, submit and book, so a wave of them can be sent together
 
+use crate::clock::{system_clock, SharedClock, Stopwatch};
+use crate::faults::FaultInjector;
+use crate::impact::{ImpactModel, SharedImpactModel};
+use crate::maker::MakerEstimate;
+use crate::order_journal::{JournalEvent, OpenExecution, OrderJournal, RecoveredExecution, RecoveryAction, RecoveryPlan};
+use crate::polymarket_api::{CancelResult, OpenOrder};
 use crate::types::*;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use fxhash::FxHashMap;
 use rand::Rng;
-use std::time::Instant;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Position size bounds of the markets with at least `min_liquidity`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SizingTier {
+    pub name: String,
+    pub min_liquidity: f64, // Liquidità dell'opportunità da cui vale il tier
+    pub floor: f64,         // Posizione minima in USDC: sotto, il trade non parte
+    pub ceiling: f64,       // Posizione massima in USDC
+}
+
+/// Sizing tiers; the tier with the highest `min_liquidity` reached applies
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SizingTiersConfig {
+    pub tiers: Vec<SizingTier>,
+}
+
+impl Default for SizingTiersConfig {
+    fn default() -> Self {
+        let tier = |name: &str, min_liquidity: f64, floor: f64, ceiling: f64| SizingTier {
+            name: name.to_string(),
+            min_liquidity,
+            floor,
+            ceiling,
+        };
+        Self {
+            tiers: vec![
+                tier("thin", 0.0, 10.0, 100.0),
+                tier("standard", 1_000.0, 10.0, 500.0),
+                tier("deep", 10_000.0, 25.0, 2_000.0),
+            ],
+        }
+    }
+}
+
+impl SizingTiersConfig {
+    /// Tier of an opportunity with `liquidity` available, if any applies
+    pub fn tier_for(&self, liquidity: f64) -> Option<&SizingTier> {
+        self.tiers.iter()
+            .filter(|t| liquidity >= t.min_liquidity)
+            .max_by(|a, b| a.min_liquidity.total_cmp(&b.min_liquidity))
+    }
+}
+
+/// Sized legs of an opportunity, as the executor would send them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionPlan {
+    pub position: f64, // Nozionale in USDC dopo limiti di capitale, tier e rischio
+    pub legs: Vec<ArbitrageLeg>,
+    pub total_investment: f64,
+    pub expected_return: f64,
+}
+
+/// Execution sized and journaled by `prepare_execution`, not yet sent
+#[derive(Debug, Clone)]
+pub struct PreparedExecution {
+    pub trade_id: String,
+    pub opportunity: ArbitrageOpportunity,
+    pub plan: ExecutionPlan,
+    pub entry_time: DateTime<Utc>,
+}
+
+/// Execution sent by `submit`, waiting for `book_execution`
+#[derive(Debug, Clone)]
+pub struct SubmittedExecution {
+    pub prepared: PreparedExecution,
+    pub slippage_pct: f64, // Frazione, non percentuale
+    pub elapsed: Duration, // Tempo di invio, latenza simulata inclusa
+}
+
+/// Notional of the quantities the detector allocated to every leg (collateral for mint-and-sell)
+///
+/// None for statistical pairs, whose quantities are hedge weights, and when a leg is unsized.
+fn allocated_notional(opportunity: &ArbitrageOpportunity) -> Option<f64> {
+    let legs = opportunity.legs.as_ref().filter(|_| opportunity.arb_type != ArbType::StatisticalArb)?;
+    if legs.is_empty() || legs.iter().any(|l| l.quantity <= 0.0) {
+        return None;
+    }
+    if opportunity.arb_type == ArbType::YesNoSell {
+        return Some(legs.iter().map(|l| l.quantity).fold(f64::INFINITY, f64::min));
+    }
+    Some(legs.iter().map(|l| l.price * l.quantity).sum())
+}
+
+/// Minimum position without sizing tiers
+const DEFAULT_SIZE_FLOOR: f64 = 10.0;
 
 /// Trade executor with VWAP and MEV capabilities
 pub struct TradeExecutor {
@@ -18,15 +120,36 @@ pub struct TradeExecutor {
     pub executed_trades: Vec<TradeExecution>,
     pub pending_orders: FxHashMap<String, Order>,
     pub vwap_tracker: VwapTracker,
+    pub clock: SharedClock, // Orari di entry/exit e misura monotona dei tempi di esecuzione
+    pub slice_schedules: Vec<SliceSchedule>, // Ordini parent in esecuzione a fette
+    pub order_constraints: FxHashMap<String, OrderConstraints>, // Tick e size minima per mercato
+    pub impact: SharedImpactModel, // Curve di slippage per mercato, condivise con l'ottimizzatore
+    pub faults: Option<FaultInjector>, // Fault simulati (outage, gap, latenza, fill parziali), se configurati
+    pub journal: OrderJournal, // Piano e fill di ogni esecuzione, per riprendere quelle interrotte da un riavvio
 }
 
 impl TradeExecutor {
     pub fn new(config: BotConfig) -> Self {
+        Self::with_clock(config, system_clock())
+    }
+
+    pub fn with_clock(config: BotConfig, clock: SharedClock) -> Self {
         Self {
+            impact: ImpactModel::new(config.impact).shared(),
+            faults: config.faults.clone().map(FaultInjector::new),
+            journal: config.order_journal.as_ref().map_or_else(OrderJournal::default, |journal| {
+                OrderJournal::load(&journal.path).unwrap_or_else(|e| {
+                    eprintln!("⚠️  Order journal non caricato, esecuzioni non recuperabili: {}", e);
+                    OrderJournal::default()
+                })
+            }),
             config,
             executed_trades: Vec::new(),
             pending_orders: FxHashMap::default(),
             vwap_tracker: VwapTracker::new(20),
+            clock,
+            slice_schedules: Vec::new(),
+            order_constraints: FxHashMap::default(),
         }
     }
 
@@ -36,83 +159,449 @@ impl TradeExecutor {
         opportunity: &ArbitrageOpportunity,
         capital: f64,
     ) -> Option<TradeExecution> {
-        let start_time = Instant::now();
+        self.execute_arbitrage_within(opportunity, capital, f64::INFINITY).await
+    }
 
-        // Calculate position size
-        let position = self._calculate_position(capital, opportunity);
+    /// Execute arbitrage trade with the position also capped at `max_position` USDC (e.g. a strategy's risk limit)
+    pub async fn execute_arbitrage_within(
+        &mut self,
+        opportunity: &ArbitrageOpportunity,
+        capital: f64,
+        max_position: f64,
+    ) -> Option<TradeExecution> {
+        let prepared = self.prepare_execution(opportunity, capital, max_position)?;
+        let submitted = self.submit(prepared).await;
+        Some(self.book_execution(submitted))
+    }
 
-        if position < 10.0 {
+    /// Size an opportunity and journal its planned legs, ready to be sent with `submit`
+    ///
+    /// None when the position falls below the size floor, no maker bid fills, or the journal
+    /// cannot be written (nothing is sent that a restart could not recover). Cross-venue pairs are
+    /// never prepared: their other leg cannot be sent through the Polymarket CLOB.
+    pub fn prepare_execution(
+        &mut self,
+        opportunity: &ArbitrageOpportunity,
+        capital: f64,
+        max_position: f64,
+    ) -> Option<PreparedExecution> {
+        if opportunity.arb_type == ArbType::CrossVenue {
             return None;
         }
+        let entry_time = self.clock.now();
 
-        // Split position between legs
-        let yes_position = position / 2.0;
-        let no_position = position / 2.0;
-
-        // Calculate VWAP prices
-        let yes_vwap = self.vwap_tracker.get_vwap(&opportunity.market_id, &TokenType::Yes);
-        let no_vwap = self.vwap_tracker.get_vwap(&opportunity.market_id, &TokenType::No);
-
-        // Use quoted prices if VWAP not available
-        let yes_price = yes_vwap.unwrap_or(0.5);
-        let no_price = no_vwap.unwrap_or(0.5);
+        // Calculate position size, of which only a fraction fills under simulated partial fills
+        let fill_ratio = self.faults.as_mut().map_or(1.0, |f| f.fill_ratio());
+        let mut plan = self.plan_filled(opportunity, capital, max_position, fill_ratio)?;
+        // Bid maker: ciascuno si riempie con la propria probabilità entro l'orizzonte
+        if let Some(estimate) = &opportunity.maker {
+            let mut rng = rand::thread_rng();
+            plan = self.fill_maker_bids(estimate, plan, (rng.gen(), rng.gen()))?;
+        }
 
-        // Create arbitrage legs
-        let legs = vec![
-            ArbitrageLeg {
-                market_id: opportunity.market_id.clone(),
-                token_type: TokenType::Yes,
-                direction: Direction::Buy,
-                price: yes_price,
-                quantity: yes_position / yes_price,
-            },
-            ArbitrageLeg {
-                market_id: opportunity.market_id.clone(),
-                token_type: TokenType::No,
-                direction: Direction::Buy,
-                price: no_price,
-                quantity: no_position / no_price,
-            },
-        ];
+        let trade_id = self.journal.next_trade_id();
+        let started = self.journal.append(JournalEvent::Started {
+            trade_id: trade_id.clone(),
+            market_id: opportunity.market_id.clone(),
+            arb_type: opportunity.arb_type,
+            legs: plan.legs.clone(),
+            total_investment: plan.total_investment,
+            expected_return: plan.expected_return,
+            at: self.clock.now(),
+        });
+        if let Err(e) = started {
+            eprintln!("⚠️  {}", e);
+            return None;
+        }
+        Some(PreparedExecution { trade_id, opportunity: opportunity.clone(), plan, entry_time })
+    }
 
-        // Calculate totals
-        let total_investment = legs.iter().map(|l| l.price * l.quantity).sum();
-        let expected_return = position; // Guaranteed return of $1 per position
+    /// Send the legs of a prepared execution
+    ///
+    /// Takes `&self` so the executions of one wave can be in flight together; their fills are
+    /// booked afterwards with `book_execution`.
+    pub async fn submit(&self, prepared: PreparedExecution) -> SubmittedExecution {
+        let stopwatch = Stopwatch::start(&self.clock);
 
         // Simulate execution with slippage
         let slippage_pct = rand::thread_rng().gen_range(0.0..0.005); // 0-0.5%
+
+        let injected_latency = self.faults.as_ref().map_or(0, |f| f.current().latency_ms);
+        let elapsed = stopwatch.elapsed() + Duration::from_millis(injected_latency);
+        SubmittedExecution { prepared, slippage_pct, elapsed }
+    }
+
+    /// Journal the fills of a sent execution and record it as a trade
+    pub fn book_execution(&mut self, submitted: SubmittedExecution) -> TradeExecution {
+        let SubmittedExecution { prepared, slippage_pct, elapsed } = submitted;
+        let PreparedExecution { trade_id, opportunity, plan, entry_time } = prepared;
+        let ExecutionPlan { legs, total_investment, expected_return, .. } = plan;
+
+        // Fill gamba per gamba, poi il completamento: un crash a metà resta recuperabile al riavvio
+        if let Err(e) = self.journal_fills(&trade_id, &legs) {
+            eprintln!("⚠️  {}", e);
+        }
+
         let actual_return = expected_return * (1.0 - slippage_pct);
         let profit = actual_return - total_investment;
 
-        let execution_time = start_time.elapsed().as_millis() as u64;
-
         let trade = TradeExecution {
-            trade_id: format!("trade_{}", self.executed_trades.len() + 1),
+            trade_id: trade_id.clone(),
             market_id: opportunity.market_id.clone(),
-            arb_type: opportunity.arb_type.clone(),
+            arb_type: opportunity.arb_type,
             legs,
             total_investment,
             expected_return,
             actual_return,
             profit,
             roi_pct: (profit / total_investment) * 100.0,
-            entry_time: Utc::now(),
-            exit_time: Utc::now(),
-            execution_time_ms: execution_time,
+            entry_time,
+            exit_time: entry_time + chrono::Duration::from_std(elapsed).unwrap_or_default(),
+            execution_time_ms: elapsed.as_millis() as u64,
             slippage_pct: slippage_pct * 100.0,
             gas_cost: 0.02, // $0.02 for 4-leg strategy
             fees: total_investment * 0.002, // 0.2% fee
         };
 
+        if let Err(e) = self.journal.append(JournalEvent::Completed { trade_id, at: self.clock.now() }) {
+            eprintln!("⚠️  {}", e);
+        }
+        self.impact.lock().unwrap().record_trade(&trade);
         self.executed_trades.push(trade.clone());
-        Some(trade)
+        trade
+    }
+
+    /// Legs and totals the executor would trade for an opportunity, without sending anything
+    ///
+    /// Same sizing as `execute_arbitrage_within`: None when the position falls below the tier
+    /// floor or a leg below the market's minimum size.
+    pub fn plan_arbitrage(&self, opportunity: &ArbitrageOpportunity, capital: f64, max_position: f64) -> Option<ExecutionPlan> {
+        self.plan_filled(opportunity, capital, max_position, 1.0)
+    }
+
+    /// Plan with only `fill_ratio` of the position filling
+    fn plan_filled(&self, opportunity: &ArbitrageOpportunity, capital: f64, max_position: f64, fill_ratio: f64) -> Option<ExecutionPlan> {
+        let (floor, ceiling) = self.size_bounds(opportunity);
+        let position = self._calculate_position(capital, opportunity).min(ceiling).min(max_position) * fill_ratio;
+
+        if position < floor {
+            return None;
+        }
+
+        // Calculate VWAP prices
+        let yes_vwap = self.vwap_tracker.get_vwap(&opportunity.market_id, &TokenType::Yes);
+        let no_vwap = self.vwap_tracker.get_vwap(&opportunity.market_id, &TokenType::No);
+
+        // Token CLOB dei leg rilevati, per indirizzare gli asset reali
+        let detected_leg = |token_type: TokenType| {
+            opportunity.legs.as_ref().and_then(|legs| legs.iter().find(|l| l.token_type == token_type))
+        };
+
+        // Use quoted prices if VWAP not available; maker bids fill at their limit price
+        let maker = opportunity.arb_type == ArbType::YesNoMaker;
+        let quoted = |token_type: TokenType, vwap: Option<f64>| {
+            let detected = detected_leg(token_type).map(|l| l.price).filter(|p| *p > 0.0);
+            if maker { detected.or(vwap) } else { vwap.or(detected) }.unwrap_or(0.5)
+        };
+        let yes_price = quoted(TokenType::Yes, yes_vwap);
+        let no_price = quoted(TokenType::No, no_vwap);
+        // Quantità allocate dal detector sulla liquidità di ciascun lato: tetto di ogni leg
+        let allocated = allocated_notional(opportunity)
+            .and(opportunity.legs.as_ref())
+            .map_or(f64::INFINITY, |legs| legs.iter().map(|l| l.quantity).fold(f64::INFINITY, f64::min));
+        // Stesso numero di coppie YES+NO: ciascuna paga 1 a risoluzione
+        let pairs = (position / (yes_price + no_price)).min(allocated);
+        let token_id = |token_type: TokenType| detected_leg(token_type).and_then(|l| l.token_id.clone());
+        // Mercato del leg: diverso per ciascun leg nelle coppie di mercati duplicati
+        let leg_market = |token_type: TokenType| {
+            detected_leg(token_type).map_or_else(|| opportunity.market_id.clone(), |l| l.market_id.clone())
+        };
+
+        // Mint-and-sell: ogni coppia impegna 1 USDC di collaterale e vende entrambi i token
+        let mint_and_sell = opportunity.arb_type == ArbType::YesNoSell;
+        let (direction, yes_quantity, no_quantity) = if mint_and_sell {
+            (Direction::Sell, position.min(allocated), position.min(allocated))
+        } else {
+            (Direction::Buy, pairs, pairs)
+        };
+
+        // Coppia statistica: i leg rilevati, scalati perché il costo dello spread sia la posizione
+        let spread_legs = opportunity.legs.as_ref().filter(|_| opportunity.arb_type == ArbType::StatisticalArb);
+        let spread_cost: f64 = spread_legs.map_or(0.0, |legs| legs.iter().map(|l| l.price * l.quantity).sum());
+        let spread_units = if spread_cost > 0.0 { position / spread_cost } else { 0.0 };
+
+        // Relazione logica, triangolo o coppia tra venue: lo stesso numero di panieri su ciascun leg rilevato
+        let basket_legs = opportunity.legs.as_ref()
+            .filter(|_| matches!(opportunity.arb_type, ArbType::Conditional | ArbType::GraphArbitrage | ArbType::CrossVenue));
+        let baskets = if opportunity.sum_price > 0.0 { (position / opportunity.sum_price).min(allocated) } else { 0.0 };
+
+        // Create arbitrage legs
+        let legs = if let Some(detected) = spread_legs.filter(|_| spread_units > 0.0) {
+            detected.iter().map(|l| ArbitrageLeg { quantity: l.quantity * spread_units, ..l.clone() }).collect()
+        } else if let Some(detected) = basket_legs.filter(|_| baskets > 0.0) {
+            detected.iter().map(|l| ArbitrageLeg { quantity: baskets, ..l.clone() }).collect()
+        } else {
+            vec![
+                ArbitrageLeg {
+                    market_id: leg_market(TokenType::Yes),
+                    token_type: TokenType::Yes,
+                    direction,
+                    price: yes_price,
+                    quantity: yes_quantity,
+                    token_id: token_id(TokenType::Yes),
+                    venue: None,
+                },
+                ArbitrageLeg {
+                    market_id: leg_market(TokenType::No),
+                    token_type: TokenType::No,
+                    direction,
+                    price: no_price,
+                    quantity: no_quantity,
+                    token_id: token_id(TokenType::No),
+                    venue: None,
+                },
+            ]
+        };
+
+        // Prezzi sulla griglia del tick del mercato di ciascun leg; un leg sotto la size minima verrebbe rifiutato
+        let mut legs = legs;
+        for leg in &mut legs {
+            let constraints = self.constraints_for(&leg.market_id);
+            leg.price = constraints.round_price(leg.price, leg.direction);
+            leg.quantity = constraints.round_size(leg.quantity)?;
+        }
+
+        // Calculate totals
+        let proceeds: f64 = legs.iter().map(|l| l.price * l.quantity).sum();
+        let (total_investment, expected_return) = if mint_and_sell {
+            // Collateral locked from the mint until both sells fill; the return is the sale proceeds
+            let pairs = legs.iter().map(|l| l.quantity).fold(f64::INFINITY, f64::min);
+            (pairs, proceeds)
+        } else if basket_legs.is_some() && baskets > 0.0 {
+            // Ogni paniere paga almeno 1 in tutti gli esiti ammessi dalla relazione
+            (proceeds, legs.iter().map(|l| l.quantity).fold(f64::INFINITY, f64::min))
+        } else if maker {
+            // Valore atteso per coppia stimato dal detector: fill incerti e completamento taker; l'esito si estrae all'esecuzione
+            let pairs = legs.iter().map(|l| l.quantity).fold(f64::INFINITY, f64::min);
+            (proceeds, proceeds + opportunity.profit * pairs)
+        } else if spread_units > 0.0 {
+            // Posizione aperta sullo spread: la reversione si realizza all'uscita, non all'entrata
+            (proceeds, proceeds)
+        } else {
+            (proceeds, legs.iter().map(|l| l.quantity).fold(f64::INFINITY, f64::min)) // Guaranteed return of $1 per pair
+        };
+
+        Some(ExecutionPlan { position, legs, total_investment, expected_return })
+    }
+
+    /// Outcome of resting the maker bids of `plan`, given a uniform draw per bid
+    ///
+    /// A bid fills when its draw falls below its fill probability. A bid left alone is completed
+    /// by crossing the other token's ask, as `MakerArbDetector::estimate` assumes; with neither
+    /// filled both bids are cancelled and nothing is traded.
+    pub fn fill_maker_bids(&self, estimate: &MakerEstimate, mut plan: ExecutionPlan, draws: (f64, f64)) -> Option<ExecutionPlan> {
+        let yes_filled = draws.0 < estimate.yes.fill_probability;
+        let no_filled = draws.1 < estimate.no.fill_probability;
+        if !yes_filled && !no_filled {
+            return None;
+        }
+        for leg in &mut plan.legs {
+            let (quote, filled) = match leg.token_type {
+                TokenType::Yes => (estimate.yes, yes_filled),
+                TokenType::No => (estimate.no, no_filled),
+            };
+            if !filled {
+                leg.price = self.constraints_for(&leg.market_id).round_price(quote.ask, Direction::Buy);
+            }
+        }
+        plan.total_investment = plan.legs.iter().map(|l| l.price * l.quantity).sum();
+        plan.expected_return = plan.legs.iter().map(|l| l.quantity).fold(f64::INFINITY, f64::min);
+        Some(plan)
+    }
+
+    /// Journal each leg of a sent execution as filled
+    fn journal_fills(&mut self, trade_id: &str, legs: &[ArbitrageLeg]) -> Result<(), String> {
+        for (i, leg) in legs.iter().enumerate() {
+            self.journal.append(JournalEvent::LegFilled {
+                trade_id: trade_id.to_string(),
+                leg: i,
+                price: leg.price,
+                quantity: leg.quantity,
+                at: self.clock.now(),
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Complete or unwind the executions a previous process left half-completed, at the current `quote`s
+    ///
+    /// Completed executions become trades; unwound ones realize the PnL of closing their filled
+    /// legs. Executions with a filled leg still unquoted stay open for a later attempt.
+    pub fn recover_open_executions(&mut self, quote: impl Fn(&str, TokenType) -> Option<f64>, always_unwind: bool) -> Vec<RecoveredExecution> {
+        let open: Vec<OpenExecution> = self.journal.open_executions().into_iter().cloned().collect();
+        let mut recovered = Vec::new();
+
+        for execution in open {
+            let Some(plan) = RecoveryPlan::decide(&execution, &quote, always_unwind) else { continue };
+            match self.apply_recovery(&execution, &plan) {
+                Ok(outcome) => recovered.push(outcome),
+                Err(e) => eprintln!("⚠️  Recovery of {} failed: {}", execution.trade_id, e),
+            }
+        }
+        recovered
+    }
+
+    /// Send the missing legs of a completed recovery, or close an unwound one, journaling the outcome
+    fn apply_recovery(&mut self, execution: &OpenExecution, plan: &RecoveryPlan) -> Result<RecoveredExecution, String> {
+        let now = self.clock.now();
+        let trade_id = execution.trade_id.clone();
+        if plan.action == RecoveryAction::Unwind {
+            self.journal.append(JournalEvent::Unwound { trade_id: trade_id.clone(), pnl: plan.pnl, at: now })?;
+            return Ok(RecoveredExecution { trade_id, market_id: execution.market_id.clone(), action: plan.action, pnl: plan.pnl, trade: None });
+        }
+
+        let mut legs = execution.legs.clone();
+        for (i, leg) in legs.iter_mut().enumerate() {
+            match execution.fills[i] {
+                Some(fill) => leg.price = fill.price,
+                None => {
+                    leg.price = plan.prices[i];
+                    self.journal.append(JournalEvent::LegFilled { trade_id: trade_id.clone(), leg: i, price: leg.price, quantity: leg.quantity, at: now })?;
+                }
+            }
+        }
+        self.journal.append(JournalEvent::Completed { trade_id: trade_id.clone(), at: now })?;
+
+        let actual_return = execution.expected_return + plan.pnl;
+        let profit = actual_return - execution.total_investment;
+        let elapsed = now - execution.started_at;
+        let trade = TradeExecution {
+            trade_id: trade_id.clone(),
+            market_id: execution.market_id.clone(),
+            arb_type: execution.arb_type,
+            legs,
+            total_investment: execution.total_investment,
+            expected_return: execution.expected_return,
+            actual_return,
+            profit,
+            roi_pct: if execution.total_investment > 0.0 { profit / execution.total_investment * 100.0 } else { 0.0 },
+            entry_time: execution.started_at,
+            exit_time: now,
+            execution_time_ms: elapsed.num_milliseconds().max(0) as u64,
+            slippage_pct: 0.0,
+            gas_cost: 0.02,
+            fees: execution.total_investment * 0.002,
+        };
+        self.executed_trades.push(trade.clone());
+        Ok(RecoveredExecution { trade_id, market_id: execution.market_id.clone(), action: plan.action, pnl: profit, trade: Some(trade) })
+    }
+
+    /// Split a parent order into `num_slices` child orders, one every `interval` from now
+    ///
+    /// Fewer slices are used when needed so that each child meets the market's minimum size.
+    pub fn schedule_slices(&mut self, order: Order, num_slices: usize, interval: Duration) {
+        let start = self.clock.now();
+        let constraints = self.constraints_for(&order.market_id);
+        let max_slices = (order.quantity / constraints.min_size).floor().max(1.0) as usize;
+        self.slice_schedules.push(SliceSchedule::new(order, num_slices.min(max_slices), interval, start));
+    }
+
+    /// Child orders due at the clock's current time (completed schedules are dropped)
+    pub fn due_slices(&mut self) -> Vec<Order> {
+        let now = self.clock.now();
+        let due: Vec<Order> = self.slice_schedules.iter_mut().flat_map(|s| s.due(now)).collect();
+        self.slice_schedules.retain(|s| !s.is_done());
+        due.into_iter().filter_map(|order| self.normalize_order(order)).collect()
+    }
+
+    /// Register the CLOB constraints of a market
+    pub fn set_order_constraints(&mut self, market_id: &str, constraints: OrderConstraints) {
+        self.order_constraints.insert(market_id.to_string(), constraints);
+    }
+
+    /// Constraints of a market (CLOB defaults if never fetched)
+    pub fn constraints_for(&self, market_id: &str) -> OrderConstraints {
+        self.order_constraints.get(market_id).copied().unwrap_or_default()
+    }
+
+    /// Order rounded to the market's tick and size step; None for dust
+    pub fn normalize_order(&self, mut order: Order) -> Option<Order> {
+        let constraints = self.constraints_for(&order.market_id);
+        order.price = constraints.round_price(order.price, order.direction);
+        order.quantity = constraints.round_size(order.quantity)?;
+        Some(order)
+    }
+
+    /// Notional the executor would commit to an opportunity with `capital` available
+    pub fn position_size(&self, capital: f64, opportunity: &ArbitrageOpportunity) -> f64 {
+        self._calculate_position(capital, opportunity)
+    }
+
+    /// Position floor and ceiling of the opportunity's sizing tier
+    pub fn size_bounds(&self, opportunity: &ArbitrageOpportunity) -> (f64, f64) {
+        self.config.sizing_tiers.as_ref()
+            .and_then(|tiers| tiers.tier_for(opportunity.liquidity))
+            .map_or((DEFAULT_SIZE_FLOOR, f64::INFINITY), |tier| (tier.floor, tier.ceiling))
     }
 
     fn _calculate_position(&self, capital: f64, opportunity: &ArbitrageOpportunity) -> f64 {
         let capital_limit = capital * self.config.max_position_size;
-        let liquidity_limit = opportunity.liquidity * 0.1; // Max 10% of liquidity
+        // Con le quantità allocate ai leg il nozionale eseguibile è noto; con i pesi statistici è la liquidità; altrimenti max 10%
+        let depth_known = opportunity.legs.as_ref().is_some_and(|legs| legs.iter().any(|l| l.quantity > 0.0));
+        let liquidity_limit = match allocated_notional(opportunity) {
+            Some(notional) => notional,
+            None if depth_known => opportunity.liquidity,
+            None => opportunity.liquidity * 0.1,
+        };
+        // Size oltre la quale l'impatto misurato sul mercato consuma troppo margine
+        let impact_limit = self.impact.lock().unwrap().max_size(&opportunity.market_id, opportunity.roi_pct / 100.0).unwrap_or(f64::INFINITY);
 
-        capital_limit.min(liquidity_limit)
+        capital_limit.min(liquidity_limit).min(impact_limit)
+    }
+}
+
+impl TradeExecutor {
+    /// Track a resting order until the CLOB reports it closed
+    pub fn track_order(&mut self, order: Order) {
+        self.pending_orders.insert(order.order_id.clone(), order);
+    }
+
+    /// Apply the CLOB's open-order snapshot; returns the orders missing from it, now `Closed`
+    ///
+    /// A missing order may have filled, expired or been cancelled elsewhere: the snapshot doesn't
+    /// say which, so the caller queries each one before acting on it.
+    pub fn reconcile_open_orders(&mut self, open: &[OpenOrder]) -> Vec<Order> {
+        let by_id: FxHashMap<&str, &OpenOrder> = open.iter().map(|o| (o.id.as_str(), o)).collect();
+
+        for order in self.pending_orders.values_mut() {
+            match by_id.get(order.order_id.as_str()) {
+                Some(remote) => {
+                    order.status = remote.order_status();
+                    order.quantity = remote.remaining_size();
+                }
+                // Sparito dal book senza cancel nostro: esito sconosciuto
+                None if order.status.is_open() && order.status != OrderStatus::Pending => order.status = OrderStatus::Closed,
+                None => {}
+            }
+        }
+        let (open, closed): (Vec<Order>, Vec<Order>) = std::mem::take(&mut self.pending_orders)
+            .into_values()
+            .filter(|order| order.status.is_open() || order.status == OrderStatus::Closed)
+            .partition(|order| order.status.is_open());
+        self.pending_orders = open.into_iter().map(|order| (order.order_id.clone(), order)).collect();
+        closed
+    }
+
+    /// Drop the orders the CLOB confirmed as cancelled
+    pub fn apply_cancel(&mut self, result: &CancelResult) {
+        for order_id in &result.canceled {
+            self.pending_orders.remove(order_id);
+        }
+    }
+
+    /// Resting orders still open
+    pub fn open_orders(&self) -> impl Iterator<Item = &Order> {
+        self.pending_orders.values().filter(|o| o.status.is_open())
     }
 }
 
@@ -131,7 +620,7 @@ impl VwapTracker {
     }
 
     pub fn update(&mut self, market_id: &str, token_type: TokenType, price: f64) {
-        let history = self.price_history.entry(market_id.to_string()).or_insert_with(Vec::new);
+        let history = self.price_history.entry(market_id.to_string()).or_default();
         history.push((token_type, price));
 
         if history.len() > self.window_size {
@@ -151,6 +640,48 @@ impl VwapTracker {
     }
 }
 
+/// Parent order split into equally sized child orders released over time
+#[derive(Debug, Clone)]
+pub struct SliceSchedule {
+    pub parent: Order,
+    pub pending: VecDeque<(DateTime<Utc>, f64)>, // (orario di rilascio, quantità)
+    pub released: usize,
+}
+
+impl SliceSchedule {
+    pub fn new(parent: Order, num_slices: usize, interval: Duration, start: DateTime<Utc>) -> Self {
+        let num_slices = num_slices.max(1);
+        let quantity = parent.quantity / num_slices as f64;
+        let step = chrono::Duration::from_std(interval).unwrap_or_default();
+        let pending = (0..num_slices)
+            .map(|i| (start + step * i as i32, quantity))
+            .collect();
+        Self { parent, pending, released: 0 }
+    }
+
+    /// Release every slice whose time has come
+    pub fn due(&mut self, now: DateTime<Utc>) -> Vec<Order> {
+        let mut orders = Vec::new();
+        while let Some(&(release_at, quantity)) = self.pending.front() {
+            if release_at > now {
+                break;
+            }
+            self.pending.pop_front();
+            self.released += 1;
+            orders.push(Order {
+                order_id: format!("{}-{}", self.parent.order_id, self.released),
+                quantity,
+                ..self.parent.clone()
+            });
+        }
+        orders
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
 /// Order for parallel submission
 #[derive(Debug, Clone)]
 pub struct Order {
@@ -169,39 +700,251 @@ pub enum OrderStatus {
     Submitted,
     Partial,
     Filled,
+    Cancelled,
     Failed,
+    Closed, // Non più sul book, esito (fill, scadenza, cancel) da verificare
 }
 
-/// MEV Opportunity Detector
-pub struct MevDetector {
-    block_time_window: u64, // milliseconds
+impl OrderStatus {
+    /// Whether the order can still trade
+    pub fn is_open(&self) -> bool {
+        matches!(self, OrderStatus::Pending | OrderStatus::Submitted | OrderStatus::Partial)
+    }
 }
 
-impl MevDetector {
-    pub fn new(block_time_window: u64) -> Self {
-        Self { block_time_window }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::SimulatedClock;
+
+    #[test]
+    fn test_slices_released_on_simulated_time() {
+        let clock = SimulatedClock::new(DateTime::from_timestamp(1_700_000_000, 0).unwrap());
+        let mut executor = TradeExecutor::with_clock(BotConfig::default(), clock.shared());
+        let parent = Order {
+            order_id: "parent".to_string(),
+            market_id: "m1".to_string(),
+            token_type: TokenType::Yes,
+            direction: Direction::Buy,
+            price: 0.45,
+            quantity: 90.0,
+            status: OrderStatus::Pending,
+        };
+        executor.schedule_slices(parent, 3, Duration::from_secs(60));
+
+        let first = executor.due_slices();
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].quantity, 30.0);
+        assert!(executor.due_slices().is_empty());
+
+        clock.advance(Duration::from_secs(150));
+        let rest = executor.due_slices();
+        assert_eq!(rest.iter().map(|o| o.order_id.as_str()).collect::<Vec<_>>(), vec!["parent-2", "parent-3"]);
+        assert!(executor.slice_schedules.is_empty());
     }
 
-    /// Detect MEV opportunities for parallel execution
-    pub fn detect_mev_opportunity(&self, trades: &[&TradeExecution]) -> Option<MevOpportunity> {
-        if trades.len() < 2 {
-            return None;
+    #[test]
+    fn test_reconcile_resting_orders() {
+        let mut executor = TradeExecutor::new(BotConfig::default());
+        for id in ["a", "b", "c"] {
+            executor.track_order(Order {
+                order_id: id.to_string(),
+                market_id: "m1".to_string(),
+                token_type: TokenType::Yes,
+                direction: Direction::Buy,
+                price: 0.45,
+                quantity: 100.0,
+                status: OrderStatus::Submitted,
+            });
         }
 
-        // Check if trades can be bundled for MEV extraction
-        let total_gas = trades.iter().map(|t| t.gas_cost).sum::<f64>();
-        let savings = total_gas * 0.5; // 50% gas savings from bundling
+        let remote: Vec<OpenOrder> = serde_json::from_value(serde_json::json!([
+            { "id": "a", "status": "LIVE", "market": "0xm1", "asset_id": "1", "side": "BUY",
+              "price": "0.45", "original_size": "100", "size_matched": "40" }
+        ])).unwrap();
+        // "b" e "c" spariti dal book: chiusi con esito da verificare, non dati per eseguiti
+        let closed = executor.reconcile_open_orders(&remote);
+        let mut closed: Vec<(&str, &OrderStatus)> = closed.iter().map(|o| (o.order_id.as_str(), &o.status)).collect();
+        closed.sort_by_key(|(id, _)| *id);
+        assert_eq!(closed, vec![("b", &OrderStatus::Closed), ("c", &OrderStatus::Closed)]);
 
-        if savings > 0.01 {
-            Some(MevOpportunity {
-                opportunity_type: MevType::FrontRunning,
-                victim_transactions: trades.iter().map(|t| t.trade_id.clone()).collect(),
-                expected_profit: savings,
-                gas_cost: total_gas * 0.5,
-                net_profit: savings - total_gas * 0.5,
-            })
-        } else {
-            None
-        }
+        assert_eq!(executor.pending_orders["a"].status, OrderStatus::Partial);
+        assert_eq!(executor.pending_orders["a"].quantity, 60.0);
+        assert_eq!(executor.pending_orders.len(), 1);
+
+        executor.apply_cancel(&CancelResult { canceled: vec!["a".to_string()], ..Default::default() });
+        assert_eq!(executor.open_orders().count(), 0);
+    }
+
+    #[test]
+    fn test_orders_respect_tick_and_min_size() {
+        let mut executor = TradeExecutor::new(BotConfig::default());
+        executor.set_order_constraints("m1", OrderConstraints { tick_size: 0.001, min_size: 15.0 });
+        let order = |price: f64, quantity: f64, direction: Direction| Order {
+            order_id: "o".to_string(),
+            market_id: "m1".to_string(),
+            token_type: TokenType::Yes,
+            direction,
+            price,
+            quantity,
+            status: OrderStatus::Pending,
+        };
+
+        let buy = executor.normalize_order(order(0.45678, 20.129, Direction::Buy)).unwrap();
+        assert_eq!((buy.price, buy.quantity), (0.456, 20.12));
+        let sell = executor.normalize_order(order(0.45612, 20.0, Direction::Sell)).unwrap();
+        assert_eq!(sell.price, 0.457);
+        assert!(executor.normalize_order(order(0.45, 14.99, Direction::Buy)).is_none());
+
+        // 40 share in fette da almeno 15: due fette invece di quattro
+        executor.schedule_slices(order(0.45, 40.0, Direction::Buy), 4, Duration::ZERO);
+        let slices = executor.due_slices();
+        assert_eq!(slices.iter().map(|o| o.quantity).collect::<Vec<_>>(), vec![20.0, 20.0]);
+
+        let default = OrderConstraints::default();
+        assert_eq!(default.round_price(0.3, Direction::Buy), 0.3);
+        assert_eq!(default.round_price(0.999, Direction::Sell), 0.99);
+    }
+
+    #[test]
+    fn test_plan_never_exceeds_detected_leg_sizes() {
+        // Lato NO sottile: il detector alloca 200 share per gamba
+        let market = MarketData {
+            id: "m1".to_string(),
+            yes_price: 0.45,
+            no_price: 0.50,
+            yes_liquidity: 9000.0,
+            no_liquidity: 1000.0,
+            ..MarketData::default()
+        };
+        let opp = crate::arbitrage::ArbitrageDetector::new(0.02, 1000.0).detect_yes_no_arbitrage(&market, None).unwrap();
+
+        // L'executor non supera le quantità allocate, qualunque sia il capitale
+        let executor = TradeExecutor::new(BotConfig { max_position_size: 1.0, ..BotConfig::default() });
+        let plan = executor.plan_arbitrage(&opp, 1_000_000.0, f64::INFINITY).unwrap();
+        assert!(plan.legs.iter().all(|l| l.quantity <= 200.0));
+        assert!((plan.total_investment - 190.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_maker_bids_fill_by_probability_and_complete_by_crossing() {
+        use crate::maker::MakerQuote;
+
+        let quote = |price: f64, ask: f64| MakerQuote { price, ask, offset: 0.01, fill_probability: 0.5 };
+        let estimate = MakerEstimate { yes: quote(0.49, 0.52), no: quote(0.48, 0.51), both_filled: 0.25, edge: 0.03, rebate: 0.0, reward: 0.0, expected_profit: 0.01 };
+        let leg = |token_type: TokenType, price: f64| ArbitrageLeg {
+            market_id: "m1".to_string(), token_type, direction: Direction::Buy, price, quantity: 100.0, token_id: None, venue: None,
+        };
+        let opportunity = ArbitrageOpportunity {
+            market_id: "m1".to_string(),
+            question: String::new(),
+            arb_type: ArbType::YesNoMaker,
+            profit: 0.01,
+            roi_pct: 1.0,
+            confidence: 0.25,
+            yes_price: 0.49,
+            no_price: 0.48,
+            sum_price: 0.97,
+            liquidity: 2000.0,
+            timestamp: Utc::now(),
+            legs: Some(vec![leg(TokenType::Yes, 0.49), leg(TokenType::No, 0.48)]),
+            path: None,
+            maker: Some(estimate),
+        };
+        let executor = TradeExecutor::new(BotConfig::default());
+        let plan = executor.plan_arbitrage(&opportunity, 1_000.0, 97.0).unwrap();
+        // Pianificato al valore atteso, non al dollaro certo per coppia
+        assert!((plan.expected_return - plan.total_investment - 0.01 * 100.0).abs() < 1e-9);
+
+        let both = executor.fill_maker_bids(&estimate, plan.clone(), (0.1, 0.1)).unwrap();
+        assert!((both.total_investment - 97.0).abs() < 1e-9 && both.expected_return == 100.0);
+        // Solo il YES riempito: il NO si compra sull'ask a 0.51
+        let one = executor.fill_maker_bids(&estimate, plan.clone(), (0.1, 0.9)).unwrap();
+        assert_eq!(one.legs[1].price, 0.51);
+        assert!((one.total_investment - 100.0).abs() < 1e-9);
+        assert!(executor.fill_maker_bids(&estimate, plan, (0.9, 0.9)).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_tier_bounds_size_and_legs_use_their_market_tick() {
+        let tiers = SizingTiersConfig {
+            tiers: vec![
+                SizingTier { name: "thin".to_string(), min_liquidity: 0.0, floor: 50.0, ceiling: 100.0 },
+                SizingTier { name: "standard".to_string(), min_liquidity: 1_000.0, floor: 10.0, ceiling: 300.0 },
+            ],
+        };
+        let mut executor = TradeExecutor::new(BotConfig { sizing_tiers: Some(tiers), ..BotConfig::default() });
+        executor.set_order_constraints("m1", OrderConstraints { tick_size: 0.001, min_size: 5.0 });
+        executor.set_order_constraints("m2", OrderConstraints { tick_size: 0.01, min_size: 5.0 });
+        let leg = |market_id: &str, token_type: TokenType, price: f64| ArbitrageLeg {
+            market_id: market_id.to_string(),
+            token_type,
+            direction: Direction::Buy,
+            price,
+            quantity: 0.0,
+            token_id: None,
+            venue: None,
+        };
+        let opportunity = |liquidity: f64| ArbitrageOpportunity {
+            market_id: "m1".to_string(),
+            question: String::new(),
+            arb_type: ArbType::Conditional,
+            profit: 0.14,
+            roi_pct: 16.4,
+            confidence: 0.9,
+            yes_price: 0.4537,
+            no_price: 0.4012,
+            sum_price: 0.8549,
+            liquidity,
+            timestamp: Utc::now(),
+            legs: Some(vec![leg("m1", TokenType::Yes, 0.4537), leg("m2", TokenType::No, 0.4012)]),
+            path: None,
+            maker: None,
+        };
+
+        // Tier standard: la posizione si ferma al tetto di 300 invece dei 500 della liquidità
+        let trade = executor.execute_arbitrage(&opportunity(5_000.0), 1_000_000.0).await.unwrap();
+        assert!(trade.total_investment <= 300.0 && trade.total_investment > 295.0);
+        assert_eq!(trade.legs[0].price, 0.453);
+        assert_eq!(trade.legs[1].price, 0.40);
+        assert_eq!(trade.legs[0].quantity, 350.91);
+
+        // Tier thin: 20 USDC di liquidità eseguibile sono sotto la posizione minima di 50
+        assert!(executor.execute_arbitrage(&opportunity(200.0), 1_000_000.0).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_wave_executions_are_sent_together_and_booked_after() {
+        let mut executor = TradeExecutor::new(BotConfig::default());
+        let opportunity = |market_id: &str| ArbitrageOpportunity {
+            market_id: market_id.to_string(),
+            question: String::new(),
+            arb_type: ArbType::YesNoSimple,
+            profit: 0.03,
+            roi_pct: 3.1,
+            confidence: 0.9,
+            yes_price: 0.48,
+            no_price: 0.49,
+            sum_price: 0.97,
+            liquidity: 5_000.0,
+            timestamp: Utc::now(),
+            legs: None,
+            path: None,
+            maker: None,
+        };
+
+        let prepared: Vec<PreparedExecution> = ["a", "b"].iter()
+            .map(|m| executor.prepare_execution(&opportunity(m), 1_000.0, f64::INFINITY).unwrap())
+            .collect();
+        // Pianificate e registrate nel journal, ma ancora nessun trade
+        assert_eq!(executor.journal.open_executions().len(), 2);
+        assert!(executor.executed_trades.is_empty());
+
+        let submitted = futures_util::future::join_all(prepared.into_iter().map(|p| executor.submit(p))).await;
+        let trades: Vec<TradeExecution> = submitted.into_iter().map(|s| executor.book_execution(s)).collect();
+        assert_eq!(trades.iter().map(|t| t.market_id.as_str()).collect::<Vec<_>>(), vec!["a", "b"]);
+        assert_ne!(trades[0].trade_id, trades[1].trade_id);
+        assert_eq!(executor.executed_trades.len(), 2);
+        assert!(!executor.journal.has_open());
     }
 }
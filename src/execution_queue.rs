@@ -0,0 +1,255 @@
+//! Execution queue module
+//!
+//! Implements:
+//! 1. Priority ordering of the opportunities approved in one step, by expected dollar edge
+//! 2. Dispatch in waves of at most `max_per_wave` executions sent together, sharing the capital
+//!    available when the wave starts
+//! 3. Per-market mutual exclusion: two executions of a wave never touch the same market
+//! 4. Queue wait metrics, measured on the execution time of the waves ahead
+
+use crate::types::ArbitrageOpportunity;
+use fxhash::FxHashSet;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+/// Dispatch limits of the execution queue
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExecutionQueueConfig {
+    pub max_per_wave: usize,   // Esecuzioni in volo insieme in un'ondata, su mercati disgiunti
+    pub max_per_step: usize,   // Esecuzioni massime per step; le altre sono scartate
+}
+
+impl Default for ExecutionQueueConfig {
+    fn default() -> Self {
+        Self { max_per_wave: 4, max_per_step: 8 }
+    }
+}
+
+impl ExecutionQueueConfig {
+    /// One execution per step, the behaviour without a configured queue
+    pub fn sequential() -> Self {
+        Self { max_per_wave: 1, max_per_step: 1 }
+    }
+}
+
+/// Opportunity dispatched by the queue
+#[derive(Debug, Clone)]
+pub struct QueuedExecution {
+    pub opportunity: ArbitrageOpportunity,
+    pub size_multiplier: f64, // Riduzione della size decisa prima dell'accodamento (flag di risoluzione)
+    pub wait_ms: u64,         // Attesa in coda prima dell'invio
+}
+
+/// Queue counters, cumulative across steps
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct QueueStats {
+    pub enqueued: u64,
+    pub dispatched: u64,
+    pub dropped: u64,        // Rimaste in coda oltre max_per_step
+    pub waves: u64,
+    pub lock_deferrals: u64, // Rinvii a un'ondata successiva per un mercato già in esecuzione
+    pub total_wait_ms: u64,
+    pub max_wait_ms: u64,
+}
+
+impl QueueStats {
+    pub fn avg_wait_ms(&self) -> f64 {
+        if self.dispatched == 0 { 0.0 } else { self.total_wait_ms as f64 / self.dispatched as f64 }
+    }
+}
+
+#[derive(Debug)]
+struct Entry {
+    priority: f64,
+    seq: u64, // Ordine di arrivo, a parità di priorità
+    markets: Vec<String>,
+    execution: QueuedExecution,
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Entry {}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority.total_cmp(&other.priority).then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+/// Priority queue of approved opportunities, dispatched in waves on disjoint markets
+#[derive(Debug, Default)]
+pub struct ExecutionQueue {
+    pub config: ExecutionQueueConfig,
+    pub stats: QueueStats,
+    heap: BinaryHeap<Entry>,
+    seq: u64,
+    elapsed_ms: u64, // Tempo di esecuzione trascorso nello step
+    dispatched_in_step: usize,
+}
+
+impl ExecutionQueue {
+    pub fn new(config: ExecutionQueueConfig) -> Self {
+        Self { config, ..Self::default() }
+    }
+
+    /// Expected dollar edge of an opportunity at its available liquidity
+    pub fn priority(opportunity: &ArbitrageOpportunity) -> f64 {
+        opportunity.roi_pct / 100.0 * opportunity.liquidity
+    }
+
+    /// Markets an execution touches: the opportunity's and those of its legs
+    fn markets(opportunity: &ArbitrageOpportunity) -> Vec<String> {
+        let mut markets = vec![opportunity.market_id.clone()];
+        for leg in opportunity.legs.iter().flatten() {
+            if !markets.contains(&leg.market_id) {
+                markets.push(leg.market_id.clone());
+            }
+        }
+        markets
+    }
+
+    /// Start a new step: the step clock and its dispatch budget restart
+    pub fn begin_step(&mut self) {
+        self.elapsed_ms = 0;
+        self.dispatched_in_step = 0;
+    }
+
+    /// Queue an approved opportunity
+    pub fn push(&mut self, opportunity: ArbitrageOpportunity, size_multiplier: f64) {
+        self.seq += 1;
+        self.stats.enqueued += 1;
+        self.heap.push(Entry {
+            priority: Self::priority(&opportunity),
+            seq: self.seq,
+            markets: Self::markets(&opportunity),
+            execution: QueuedExecution { opportunity, size_multiplier, wait_ms: 0 },
+        });
+    }
+
+    /// Next wave: the highest-priority entries on disjoint markets, within the wave and step limits
+    ///
+    /// Entries blocked by a market already in the wave stay queued for a later wave.
+    pub fn next_wave(&mut self) -> Vec<QueuedExecution> {
+        let capacity = self.config.max_per_wave.max(1)
+            .min(self.config.max_per_step.saturating_sub(self.dispatched_in_step));
+        let mut locked: FxHashSet<String> = FxHashSet::default();
+        let mut deferred = Vec::new();
+        let mut wave = Vec::new();
+
+        while wave.len() < capacity {
+            let Some(entry) = self.heap.pop() else { break };
+            if entry.markets.iter().any(|m| locked.contains(m)) {
+                self.stats.lock_deferrals += 1;
+                deferred.push(entry);
+                continue;
+            }
+            locked.extend(entry.markets.iter().cloned());
+            let mut execution = entry.execution;
+            execution.wait_ms = self.elapsed_ms;
+            self.stats.total_wait_ms += execution.wait_ms;
+            self.stats.max_wait_ms = self.stats.max_wait_ms.max(execution.wait_ms);
+            wave.push(execution);
+        }
+        self.heap.extend(deferred);
+
+        if !wave.is_empty() {
+            self.dispatched_in_step += wave.len();
+            self.stats.dispatched += wave.len() as u64;
+            self.stats.waves += 1;
+        }
+        wave
+    }
+
+    /// Close the current wave, which took as long as its slowest execution
+    pub fn complete_wave(&mut self, duration_ms: u64) {
+        self.elapsed_ms += duration_ms;
+    }
+
+    /// Empty the queue at the end of a step, returning the opportunities left undispatched
+    pub fn drain(&mut self) -> Vec<ArbitrageOpportunity> {
+        let dropped: Vec<ArbitrageOpportunity> = std::mem::take(&mut self.heap)
+            .into_sorted_vec()
+            .into_iter()
+            .rev()
+            .map(|e| e.execution.opportunity)
+            .collect();
+        self.stats.dropped += dropped.len() as u64;
+        dropped
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ArbType;
+    use chrono::Utc;
+
+    fn opportunity(market_id: &str, roi_pct: f64) -> ArbitrageOpportunity {
+        ArbitrageOpportunity {
+            market_id: market_id.to_string(),
+            question: String::new(),
+            arb_type: ArbType::YesNoSimple,
+            profit: roi_pct / 100.0,
+            roi_pct,
+            confidence: 1.0,
+            yes_price: 0.5,
+            no_price: 0.5,
+            sum_price: 1.0,
+            liquidity: 1000.0,
+            timestamp: Utc::now(),
+            legs: None,
+            path: None,
+            maker: None,
+        }
+    }
+
+    #[test]
+    fn test_waves_respect_priority_wave_size_and_market_locks() {
+        let mut queue = ExecutionQueue::new(ExecutionQueueConfig { max_per_wave: 2, max_per_step: 3 });
+        queue.begin_step();
+        queue.push(opportunity("a", 1.0), 1.0);
+        queue.push(opportunity("b", 3.0), 1.0);
+        queue.push(opportunity("b", 2.0), 0.5);
+        queue.push(opportunity("c", 0.5), 1.0);
+
+        // Le due migliori sono sullo stesso mercato: la seconda aspetta l'ondata successiva
+        let first: Vec<(String, f64)> = queue.next_wave().into_iter().map(|q| (q.opportunity.market_id, q.opportunity.roi_pct)).collect();
+        assert_eq!(first, vec![("b".to_string(), 3.0), ("a".to_string(), 1.0)]);
+        assert_eq!(queue.stats.lock_deferrals, 1);
+        queue.complete_wave(120);
+
+        // Budget dello step: resta posto per una sola esecuzione
+        let second = queue.next_wave();
+        assert_eq!(second.len(), 1);
+        assert_eq!((second[0].opportunity.roi_pct, second[0].size_multiplier, second[0].wait_ms), (2.0, 0.5, 120));
+        queue.complete_wave(80);
+        assert!(queue.next_wave().is_empty());
+
+        let dropped = queue.drain();
+        assert_eq!(dropped.len(), 1);
+        assert_eq!(dropped[0].market_id, "c");
+        assert!(queue.is_empty());
+        assert_eq!((queue.stats.dispatched, queue.stats.dropped, queue.stats.waves, queue.stats.max_wait_ms), (3, 1, 2, 120));
+        assert!((queue.stats.avg_wait_ms() - 40.0).abs() < 1e-9);
+    }
+}
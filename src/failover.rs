@@ -0,0 +1,213 @@
+//! Endpoint failover module
+//!
+//! Implements:
+//! 1. Pools of equivalent market data endpoints: the primary plus mirrors or proxies
+//! 2. Health score per endpoint: moving average of request outcomes, with the last latency
+//! 3. Cooldown of an endpoint after consecutive failures; requests go to the healthiest
+//!    available endpoint, the primary first on equal scores
+//! 4. When every endpoint is cooling down, the one recovering first is still tried, so an
+//!    outage degrades the feed instead of stopping it
+
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Failure handling shared by the endpoint pools
+#[derive(Debug, Clone)]
+pub struct FailoverPolicy {
+    pub failure_threshold: u32, // Fallimenti consecutivi prima del cooldown
+    pub cooldown_ms: u64,
+    pub score_alpha: f64,       // Peso dell'ultimo esito nella media mobile del punteggio
+}
+
+impl Default for FailoverPolicy {
+    fn default() -> Self {
+        Self { failure_threshold: 3, cooldown_ms: 30_000, score_alpha: 0.2 }
+    }
+}
+
+/// Health of one endpoint, as reported for monitoring
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EndpointHealth {
+    pub url: String,
+    pub score: f64, // 1 = tutte le richieste recenti riuscite, 0 = tutte fallite
+    pub consecutive_failures: u32,
+    pub successes: u64,
+    pub failures: u64,
+    pub last_latency_ms: Option<f64>,
+    pub cooling_down: bool,
+}
+
+#[derive(Debug)]
+struct Endpoint {
+    url: String,
+    score: f64,
+    consecutive_failures: u32,
+    successes: u64,
+    failures: u64,
+    last_latency_ms: Option<f64>,
+    cooldown_until: Option<Instant>,
+}
+
+/// Equivalent endpoints of one service, shared by the clones of a client
+#[derive(Debug, Clone)]
+pub struct EndpointPool {
+    policy: FailoverPolicy,
+    endpoints: Arc<Mutex<Vec<Endpoint>>>,
+}
+
+impl EndpointPool {
+    /// Pool of `primary` followed by `mirrors` (duplicates and empty URLs are skipped)
+    pub fn new(primary: &str, mirrors: &[String], policy: FailoverPolicy) -> Self {
+        let mut urls: Vec<String> = Vec::new();
+        for url in std::iter::once(primary).chain(mirrors.iter().map(String::as_str)) {
+            let url = url.trim().trim_end_matches('/').to_string();
+            if !url.is_empty() && !urls.contains(&url) {
+                urls.push(url);
+            }
+        }
+        let endpoints = urls.into_iter()
+            .map(|url| Endpoint {
+                url,
+                score: 1.0,
+                consecutive_failures: 0,
+                successes: 0,
+                failures: 0,
+                last_latency_ms: None,
+                cooldown_until: None,
+            })
+            .collect();
+        Self { policy, endpoints: Arc::new(Mutex::new(endpoints)) }
+    }
+
+    /// Endpoints in the order to try them
+    pub fn candidates(&self) -> Vec<String> {
+        self.candidates_at(Instant::now())
+    }
+
+    fn candidates_at(&self, now: Instant) -> Vec<String> {
+        let endpoints = self.endpoints.lock().unwrap();
+        let mut order: Vec<(usize, &Endpoint)> = endpoints.iter().enumerate().collect();
+        // Prima i disponibili per punteggio, poi quelli in cooldown dal primo che ne esce
+        order.sort_by(|(i, a), (j, b)| {
+            let cooling = |e: &Endpoint| e.cooldown_until.filter(|until| *until > now);
+            match (cooling(a), cooling(b)) {
+                (None, None) => b.score.total_cmp(&a.score).then(i.cmp(j)),
+                (None, Some(_)) => std::cmp::Ordering::Less,
+                (Some(_), None) => std::cmp::Ordering::Greater,
+                (Some(x), Some(y)) => x.cmp(&y).then(i.cmp(j)),
+            }
+        });
+        order.into_iter().map(|(_, e)| e.url.clone()).collect()
+    }
+
+    /// Endpoint to use for the next request or connection
+    pub fn current(&self) -> String {
+        self.candidates().into_iter().next().unwrap_or_default()
+    }
+
+    pub fn record_success(&self, url: &str, latency: Duration) {
+        let alpha = self.policy.score_alpha;
+        self.update(url, |e| {
+            e.score = e.score * (1.0 - alpha) + alpha;
+            e.consecutive_failures = 0;
+            e.successes += 1;
+            e.last_latency_ms = Some(latency.as_secs_f64() * 1000.0);
+            e.cooldown_until = None;
+        });
+    }
+
+    pub fn record_failure(&self, url: &str) {
+        self.record_failure_at(url, Instant::now());
+    }
+
+    fn record_failure_at(&self, url: &str, now: Instant) {
+        let policy = self.policy.clone();
+        self.update(url, |e| {
+            e.score *= 1.0 - policy.score_alpha;
+            e.consecutive_failures += 1;
+            e.failures += 1;
+            if e.consecutive_failures >= policy.failure_threshold.max(1) {
+                e.cooldown_until = Some(now + Duration::from_millis(policy.cooldown_ms));
+                eprintln!("🔀 Endpoint {} in cooldown after {} failures", e.url, e.consecutive_failures);
+            }
+        });
+    }
+
+    fn update(&self, url: &str, apply: impl FnOnce(&mut Endpoint)) {
+        let mut endpoints = self.endpoints.lock().unwrap();
+        if let Some(endpoint) = endpoints.iter_mut().find(|e| e.url == url) {
+            apply(endpoint);
+        }
+    }
+
+    /// Health of every endpoint, in configuration order
+    pub fn health(&self) -> Vec<EndpointHealth> {
+        let now = Instant::now();
+        self.endpoints.lock().unwrap().iter()
+            .map(|e| EndpointHealth {
+                url: e.url.clone(),
+                score: e.score,
+                consecutive_failures: e.consecutive_failures,
+                successes: e.successes,
+                failures: e.failures,
+                last_latency_ms: e.last_latency_ms,
+                cooling_down: e.cooldown_until.is_some_and(|until| until > now),
+            })
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.endpoints.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Health of the market data endpoints of every service
+#[derive(Debug, Clone, Serialize)]
+pub struct MarketDataHealth {
+    pub gamma: Vec<EndpointHealth>,
+    pub clob: Vec<EndpointHealth>,
+    pub websocket: Vec<EndpointHealth>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pool_fails_over_and_recovers() {
+        let policy = FailoverPolicy { failure_threshold: 2, cooldown_ms: 1_000, score_alpha: 0.5 };
+        let mirrors = vec!["https://mirror-a/".to_string(), "https://primary".to_string(), "https://mirror-b".to_string()];
+        let pool = EndpointPool::new("https://primary", &mirrors, policy);
+        assert_eq!(pool.len(), 3);
+        assert_eq!(pool.current(), "https://primary");
+
+        // Un fallimento abbassa il punteggio: il primo mirror sano passa davanti
+        let now = Instant::now();
+        pool.record_failure_at("https://primary", now);
+        assert_eq!(pool.candidates_at(now), vec!["https://mirror-a", "https://mirror-b", "https://primary"]);
+
+        // Soglia raggiunta: primary e mirror-a in cooldown, resta mirror-b
+        pool.record_failure_at("https://primary", now);
+        pool.record_failure_at("https://mirror-a", now);
+        pool.record_failure_at("https://mirror-a", now + Duration::from_millis(10));
+        assert_eq!(pool.candidates_at(now + Duration::from_millis(20))[0], "https://mirror-b");
+
+        // Tutti in cooldown: si prova quello che ne esce per primo
+        pool.record_failure_at("https://mirror-b", now);
+        pool.record_failure_at("https://mirror-b", now + Duration::from_millis(20));
+        assert_eq!(pool.candidates_at(now + Duration::from_millis(30))[0], "https://primary");
+
+        // Cooldown scaduto e un successo: il primary torna disponibile
+        assert_eq!(pool.candidates_at(now + Duration::from_millis(1_001))[0], "https://primary");
+        pool.record_success("https://primary", Duration::from_millis(40));
+        let health = pool.health();
+        assert_eq!((health[0].consecutive_failures, health[0].successes, health[0].failures), (0, 1, 2));
+        assert!(!health[0].cooling_down && health[1].cooling_down);
+        assert_eq!(health[0].last_latency_ms, Some(40.0));
+    }
+}
@@ -0,0 +1,213 @@
+//! C FFI module
+//!
+//! Implements:
+//! 1. C ABI over the YES/NO detector for arrays of market quotes
+//! 2. Risk metrics (VaR, Sharpe, drawdown) of a PnL series
+//! 3. Caller-owned output buffers and integer status codes, so no memory crosses the boundary
+//!
+//! The matching declarations are in `include/polymarket_arb.h`.
+
+use crate::arbitrage::{ArbitrageDetector, TradingCosts};
+use crate::orderbook::OrderBookStore;
+use crate::risk::RiskManager;
+use crate::types::MarketData;
+use std::slice;
+
+/// Call succeeded
+pub const PM_OK: i32 = 0;
+/// A required pointer was null
+pub const PM_NULL_POINTER: i32 = -1;
+/// The output buffer was too small; `out_len` holds the required length
+pub const PM_BUFFER_TOO_SMALL: i32 = -2;
+/// A numeric argument was out of range (NaN, negative capital or liquidity)
+pub const PM_INVALID_ARGUMENT: i32 = -3;
+
+/// Top-of-book quote of one binary market
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PmQuote {
+    pub yes_price: f64,
+    pub no_price: f64,
+    pub yes_liquidity: f64,
+    pub no_liquidity: f64,
+    pub volume_24h: f64,
+}
+
+/// Trading costs deducted from the gross margin (see `TradingCosts`)
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct PmCosts {
+    pub taker_fee_rate: f64,
+    pub gas_per_trade: f64,
+    pub relayer_per_order: f64,
+    pub reference_size: f64,
+}
+
+impl From<PmCosts> for TradingCosts {
+    fn from(costs: PmCosts) -> Self {
+        TradingCosts {
+            taker_fee_rate: costs.taker_fee_rate,
+            gas_per_trade: costs.gas_per_trade,
+            relayer_per_order: costs.relayer_per_order,
+            reference_size: costs.reference_size,
+        }
+    }
+}
+
+/// Opportunity found on the quote at `quote_index`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PmOpportunity {
+    pub quote_index: usize,
+    pub profit: f64, // Margine netto per coppia YES+NO
+    pub roi_pct: f64,
+    pub confidence: f64,
+    pub sum_price: f64,
+    pub liquidity: f64,
+}
+
+/// Risk metrics of a PnL series
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PmRiskMetrics {
+    pub var_95: f64,
+    pub sharpe_ratio: f64,
+    pub current_drawdown: f64, // Frazione sotto il picco
+    pub max_drawdown_pct: f64,
+    pub final_capital: f64,
+}
+
+/// Detect YES/NO arbitrage on `len` quotes
+///
+/// `costs` may be null for the default costs. Up to `out_capacity` opportunities are written
+/// to `out`; `out_len` always receives how many were found.
+///
+/// # Safety
+/// `quotes` must point to `len` quotes, `out` to `out_capacity` writable entries (or be null
+/// with a zero capacity), `costs` must be null or valid, and `out_len` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn pm_detect_opportunities(
+    quotes: *const PmQuote,
+    len: usize,
+    costs: *const PmCosts,
+    min_profit: f64,
+    min_liquidity: f64,
+    out: *mut PmOpportunity,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> i32 {
+    if (quotes.is_null() && len > 0) || (out.is_null() && out_capacity > 0) || out_len.is_null() {
+        return PM_NULL_POINTER;
+    }
+    if min_profit.is_nan() || min_liquidity.is_nan() || min_liquidity < 0.0 {
+        return PM_INVALID_ARGUMENT;
+    }
+    let quotes = if len == 0 { &[][..] } else { slice::from_raw_parts(quotes, len) };
+    let costs = if costs.is_null() { TradingCosts::default() } else { (*costs).into() };
+
+    let found = detect(quotes, costs, min_profit, min_liquidity);
+    *out_len = found.len();
+    if out_capacity > 0 {
+        let out = slice::from_raw_parts_mut(out, out_capacity);
+        for (slot, opportunity) in out.iter_mut().zip(&found) {
+            *slot = *opportunity;
+        }
+    }
+    if found.len() > out_capacity { PM_BUFFER_TOO_SMALL } else { PM_OK }
+}
+
+/// Risk metrics of `len` trade PnLs starting from `initial_capital`
+///
+/// # Safety
+/// `pnl` must point to `len` values (or be null with a zero length) and `out` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn pm_risk_metrics(pnl: *const f64, len: usize, initial_capital: f64, out: *mut PmRiskMetrics) -> i32 {
+    if (pnl.is_null() && len > 0) || out.is_null() {
+        return PM_NULL_POINTER;
+    }
+    if initial_capital.is_nan() || initial_capital <= 0.0 {
+        return PM_INVALID_ARGUMENT;
+    }
+    let pnl = if len == 0 { &[][..] } else { slice::from_raw_parts(pnl, len) };
+    *out = risk_metrics(pnl, initial_capital);
+    PM_OK
+}
+
+/// Opportunities of the quotes, in quote order
+fn detect(quotes: &[PmQuote], costs: TradingCosts, min_profit: f64, min_liquidity: f64) -> Vec<PmOpportunity> {
+    let detector = ArbitrageDetector::new(min_profit, min_liquidity).with_costs(costs);
+    let books = OrderBookStore::new();
+
+    let markets: Vec<MarketData> = quotes
+        .iter()
+        .enumerate()
+        .map(|(i, q)| MarketData {
+            id: i.to_string(),
+            yes_price: q.yes_price,
+            no_price: q.no_price,
+            yes_liquidity: q.yes_liquidity,
+            no_liquidity: q.no_liquidity,
+            volume_24h: q.volume_24h,
+            ..MarketData::default()
+        })
+        .collect();
+
+    detector
+        .scan_markets(&markets, &books)
+        .into_iter()
+        .filter_map(|opp| {
+            Some(PmOpportunity {
+                quote_index: opp.market_id.parse().ok()?,
+                profit: opp.profit,
+                roi_pct: opp.roi_pct,
+                confidence: opp.confidence,
+                sum_price: opp.sum_price,
+                liquidity: opp.liquidity,
+            })
+        })
+        .collect()
+}
+
+fn risk_metrics(pnl: &[f64], initial_capital: f64) -> PmRiskMetrics {
+    let risk = RiskManager::replay(pnl, initial_capital);
+    PmRiskMetrics {
+        var_95: risk.calculate_var_95(),
+        sharpe_ratio: risk.calculate_sharpe_ratio(),
+        current_drawdown: risk.metrics.current_drawdown,
+        max_drawdown_pct: risk.drawdowns.report(0).max_depth_pct,
+        final_capital: initial_capital + pnl.iter().sum::<f64>(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_c_abi_detection_and_risk() {
+        let quote = |yes_price: f64, no_price: f64| PmQuote { yes_price, no_price, yes_liquidity: 5_000.0, no_liquidity: 5_000.0, volume_24h: 0.0 };
+        let quotes = [quote(0.50, 0.51), quote(0.45, 0.50), quote(0.40, 0.40)];
+        let mut out = [PmOpportunity::default(); 1];
+        let mut out_len = 0;
+
+        // Buffer da uno: la lunghezza richiesta viene comunque riportata
+        let status = unsafe { pm_detect_opportunities(quotes.as_ptr(), quotes.len(), std::ptr::null(), 0.01, 0.0, out.as_mut_ptr(), out.len(), &mut out_len) };
+        assert_eq!((status, out_len), (PM_BUFFER_TOO_SMALL, 2));
+        assert_eq!(out[0].quote_index, 1);
+        assert!((out[0].sum_price - 0.95).abs() < 1e-9);
+
+        let costs = PmCosts { taker_fee_rate: 0.0, gas_per_trade: 0.0, relayer_per_order: 0.0, reference_size: 100.0 };
+        let mut out = [PmOpportunity::default(); 4];
+        let status = unsafe { pm_detect_opportunities(quotes.as_ptr(), quotes.len(), &costs, 0.01, 0.0, out.as_mut_ptr(), out.len(), &mut out_len) };
+        assert_eq!((status, out_len), (PM_OK, 2));
+        assert!((out[0].profit - 0.05).abs() < 1e-9);
+        assert_eq!(unsafe { pm_detect_opportunities(std::ptr::null(), 1, std::ptr::null(), 0.01, 0.0, out.as_mut_ptr(), 4, &mut out_len) }, PM_NULL_POINTER);
+
+        let pnl = [10.0, -30.0, 5.0];
+        let mut metrics = PmRiskMetrics::default();
+        assert_eq!(unsafe { pm_risk_metrics(pnl.as_ptr(), pnl.len(), 1_000.0, &mut metrics) }, PM_OK);
+        assert_eq!(metrics.final_capital, 985.0);
+        assert!((metrics.max_drawdown_pct - 30.0 / 1010.0 * 100.0).abs() < 1e-9);
+        assert_eq!(unsafe { pm_risk_metrics(pnl.as_ptr(), pnl.len(), 0.0, &mut metrics) }, PM_INVALID_ARGUMENT);
+    }
+}
@@ -0,0 +1,179 @@
+//! Market impact module
+//!
+//! Implements:
+//! 1. Per-market cost curves fitted on realized fills: slippage = fixed + coefficient * sqrt(size)
+//! 2. Expected slippage of a size and the largest size whose slippage stays within a share of the edge
+//! 3. Impact cost of an opportunity, deducted from its ROI by the optimizer
+//! 4. One model shared by executor and optimizer, so sizing and scoring see the same curves
+
+use crate::types::{ArbitrageOpportunity, TradeExecution};
+use fxhash::FxHashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+/// Calibration settings of the impact curves
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ImpactConfig {
+    pub min_samples: usize, // Fill necessari prima di usare la curva di un mercato
+    pub max_samples: usize, // Fill più recenti tenuti per mercato
+    pub edge_share: f64,    // Quota del margine che lo slippage può consumare
+}
+
+impl Default for ImpactConfig {
+    fn default() -> Self {
+        Self { min_samples: 5, max_samples: 200, edge_share: 0.5 }
+    }
+}
+
+/// Slippage (fraction of notional) as a function of the traded notional
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ImpactCurve {
+    pub fixed: f64,       // Slippage indipendente dalla size
+    pub coefficient: f64, // Slippage per radice di dollaro
+    pub samples: usize,
+}
+
+impl ImpactCurve {
+    /// Least-squares fit on `(size, slippage)` samples, with both terms floored at 0
+    pub fn fit(samples: &[(f64, f64)]) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        let n = samples.len() as f64;
+        let xs: Vec<f64> = samples.iter().map(|&(size, _)| size.max(0.0).sqrt()).collect();
+        let mean_x = xs.iter().sum::<f64>() / n;
+        let mean_y = samples.iter().map(|&(_, slippage)| slippage).sum::<f64>() / n;
+        let (mut sxy, mut sxx) = (0.0, 0.0);
+        for (x, &(_, y)) in xs.iter().zip(samples) {
+            sxy += (x - mean_x) * (y - mean_y);
+            sxx += (x - mean_x) * (x - mean_x);
+        }
+
+        // Size tutte uguali: solo la componente fissa è identificabile
+        let coefficient = if sxx > 0.0 { (sxy / sxx).max(0.0) } else { 0.0 };
+        let fixed = (mean_y - coefficient * mean_x).max(0.0);
+        Some(Self { fixed, coefficient, samples: samples.len() })
+    }
+
+    /// Expected slippage of a fill of `size` dollars
+    pub fn slippage(&self, size: f64) -> f64 {
+        self.fixed + self.coefficient * size.max(0.0).sqrt()
+    }
+
+    /// Largest size whose expected slippage stays within `budget` (None when size has no impact)
+    pub fn max_size(&self, budget: f64) -> Option<f64> {
+        if budget <= self.fixed {
+            return Some(0.0);
+        }
+        (self.coefficient > 0.0).then(|| ((budget - self.fixed) / self.coefficient).powi(2))
+    }
+}
+
+/// Impact model shared by the components that size or score trades
+pub type SharedImpactModel = Arc<Mutex<ImpactModel>>;
+
+/// Impact curves per market, refitted on every recorded fill
+#[derive(Debug, Clone, Default)]
+pub struct ImpactModel {
+    pub config: ImpactConfig,
+    samples: FxHashMap<String, VecDeque<(f64, f64)>>, // (size, slippage) per mercato
+    curves: FxHashMap<String, ImpactCurve>,
+}
+
+impl ImpactModel {
+    pub fn new(config: ImpactConfig) -> Self {
+        Self { config, ..Self::default() }
+    }
+
+    /// Record a realized fill: `slippage` is the fraction of notional lost to execution
+    pub fn record(&mut self, market_id: &str, size: f64, slippage: f64) {
+        if size <= 0.0 || !slippage.is_finite() {
+            return;
+        }
+        let samples = self.samples.entry(market_id.to_string()).or_default();
+        if samples.len() >= self.config.max_samples.max(1) {
+            samples.pop_front();
+        }
+        samples.push_back((size, slippage));
+
+        if samples.len() >= self.config.min_samples {
+            let samples: Vec<(f64, f64)> = samples.iter().copied().collect();
+            if let Some(curve) = ImpactCurve::fit(&samples) {
+                self.curves.insert(market_id.to_string(), curve);
+            }
+        }
+    }
+
+    /// Record an executed trade (size = capital invested, slippage as reported by the fill)
+    pub fn record_trade(&mut self, trade: &TradeExecution) {
+        self.record(&trade.market_id, trade.total_investment, trade.slippage_pct / 100.0);
+    }
+
+    /// Refit every curve from `(market_id, size, slippage)` fills, replacing the recorded ones
+    pub fn fit<'a>(&mut self, fills: impl IntoIterator<Item = (&'a str, f64, f64)>) {
+        self.samples.clear();
+        self.curves.clear();
+        for (market_id, size, slippage) in fills {
+            self.record(market_id, size, slippage);
+        }
+    }
+
+    /// Model shareable between executor and optimizer
+    pub fn shared(self) -> SharedImpactModel {
+        Arc::new(Mutex::new(self))
+    }
+
+    /// Fitted curve of a market, once it has enough fills
+    pub fn curve(&self, market_id: &str) -> Option<&ImpactCurve> {
+        self.curves.get(market_id)
+    }
+
+    pub fn curves(&self) -> &FxHashMap<String, ImpactCurve> {
+        &self.curves
+    }
+
+    pub fn expected_slippage(&self, market_id: &str, size: f64) -> Option<f64> {
+        self.curve(market_id).map(|c| c.slippage(size))
+    }
+
+    /// Largest size on a market whose slippage consumes at most `edge_share` of `edge` (fraction of notional)
+    pub fn max_size(&self, market_id: &str, edge: f64) -> Option<f64> {
+        self.curve(market_id)?.max_size(edge * self.config.edge_share)
+    }
+
+    /// Expected impact (in ROI percentage points) of trading an opportunity at its impact-capped size
+    pub fn cost_pct(&self, opportunity: &ArbitrageOpportunity) -> f64 {
+        let Some(curve) = self.curve(&opportunity.market_id) else {
+            return 0.0;
+        };
+        let cap = self.max_size(&opportunity.market_id, opportunity.roi_pct / 100.0).unwrap_or(f64::INFINITY);
+        curve.slippage(opportunity.liquidity.min(cap)) * 100.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_curve_fit_caps_size_by_edge() {
+        let mut model = ImpactModel::new(ImpactConfig { min_samples: 3, ..ImpactConfig::default() });
+
+        // slippage = 0.001 + 0.0005 * sqrt(size)
+        for size in [100.0, 400.0, 900.0] {
+            model.record("m1", size, 0.001 + 0.0005 * f64::sqrt(size));
+            model.record("m2", size, 0.002);
+        }
+        let curve = model.curve("m1").unwrap();
+        assert!((curve.fixed - 0.001).abs() < 1e-9);
+        assert!((curve.coefficient - 0.0005).abs() < 1e-9);
+        assert!((model.expected_slippage("m1", 1600.0).unwrap() - 0.021).abs() < 1e-9);
+
+        // Margine 2%: metà (1%) di slippage ammessa → sqrt(size) = 0.009 / 0.0005 = 18
+        assert!((model.max_size("m1", 0.02).unwrap() - 324.0).abs() < 1e-6);
+        // Slippage costante: nessun limite di size
+        assert_eq!(model.max_size("m2", 0.02), None);
+        assert_eq!(model.max_size("m3", 0.02), None);
+    }
+}
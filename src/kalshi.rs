@@ -0,0 +1,163 @@
+//! Kalshi venue module
+//!
+//! Implements:
+//! 1. Quotes of Kalshi markets from the public market data API: best YES and NO asks and
+//!    displayed liquidity, converted from cents to dollars
+//! 2. `VenueAdapter` for cross-venue detection against Polymarket
+
+use crate::venues::{VenueAdapter, VenueQuote};
+use chrono::{DateTime, Utc};
+use futures_util::future::BoxFuture;
+use futures_util::FutureExt;
+use reqwest::Client as HttpClient;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Name of the Kalshi venue in quotes, contract mappings and legs
+pub const KALSHI_VENUE: &str = "kalshi";
+
+/// Public market data API
+pub const KALSHI_API_URL: &str = "https://api.elections.kalshi.com/trade-api/v2";
+
+/// Tickers per request to the markets endpoint
+const KALSHI_TICKERS_PER_REQUEST: usize = 100;
+
+fn default_api_url() -> String {
+    KALSHI_API_URL.to_string()
+}
+
+fn default_timeout_ms() -> u64 {
+    5000
+}
+
+/// Kalshi adapter settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KalshiConfig {
+    #[serde(default = "default_api_url")]
+    pub api_url: String,
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+impl Default for KalshiConfig {
+    fn default() -> Self {
+        Self { api_url: default_api_url(), timeout_ms: default_timeout_ms() }
+    }
+}
+
+/// Market of GET /markets; prices and liquidity in cents
+#[derive(Debug, Deserialize)]
+struct KalshiMarket {
+    ticker: String,
+    #[serde(default)]
+    status: String,
+    #[serde(default)]
+    yes_ask: Option<f64>,
+    #[serde(default)]
+    no_ask: Option<f64>,
+    #[serde(default)]
+    liquidity: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KalshiMarketsResponse {
+    #[serde(default)]
+    markets: Vec<KalshiMarket>,
+}
+
+/// Read-only client of the Kalshi market data API
+pub struct KalshiClient {
+    pub config: KalshiConfig,
+    http_client: HttpClient,
+}
+
+impl KalshiClient {
+    pub fn new(config: KalshiConfig) -> Result<Self, String> {
+        Ok(Self {
+            http_client: HttpClient::builder()
+                .timeout(Duration::from_millis(config.timeout_ms))
+                .build()
+                .map_err(|e| e.to_string())?,
+            config,
+        })
+    }
+
+    /// Quote of a market open for trading with asks on both sides
+    fn quote(market: KalshiMarket, now: DateTime<Utc>) -> Option<VenueQuote> {
+        if !matches!(market.status.as_str(), "active" | "open") {
+            return None;
+        }
+        // Ask a 0 o 100 centesimi: nessuna offerta sul lato
+        let ask = |cents: Option<f64>| cents.filter(|c| *c > 0.0 && *c < 100.0).map(|c| c / 100.0);
+        Some(VenueQuote {
+            venue: KALSHI_VENUE.to_string(),
+            yes_price: ask(market.yes_ask)?,
+            no_price: Some(ask(market.no_ask)?),
+            liquidity: market.liquidity.map(|c| c / 100.0),
+            market_id: market.ticker,
+            timestamp: now,
+        })
+    }
+
+    /// Quotes in a markets response body
+    fn parse_quotes(body: &str, now: DateTime<Utc>) -> Result<Vec<VenueQuote>, String> {
+        let response: KalshiMarketsResponse = serde_json::from_str(body)
+            .map_err(|e| format!("Invalid Kalshi markets response: {}", e))?;
+        Ok(response.markets.into_iter().filter_map(|m| Self::quote(m, now)).collect())
+    }
+
+    /// Quotes of the given tickers, in batches of the markets endpoint
+    pub async fn fetch_markets(&self, tickers: &[String], now: DateTime<Utc>) -> Result<Vec<VenueQuote>, String> {
+        let mut quotes = Vec::new();
+        for batch in tickers.chunks(KALSHI_TICKERS_PER_REQUEST) {
+            let response = self.http_client
+                .get(format!("{}/markets", self.config.api_url.trim_end_matches('/')))
+                .query(&[("tickers", batch.join(",")), ("limit", batch.len().to_string())])
+                .send()
+                .await
+                .map_err(|e| format!("Kalshi unreachable: {}", e))?;
+            let status = response.status();
+            let body = response.text().await.map_err(|e| format!("Kalshi response unreadable: {}", e))?;
+            if !status.is_success() {
+                return Err(format!("Kalshi markets request failed: {} {}", status, body));
+            }
+            quotes.extend(Self::parse_quotes(&body, now)?);
+        }
+        Ok(quotes)
+    }
+}
+
+impl VenueAdapter for KalshiClient {
+    fn venue(&self) -> &str {
+        KALSHI_VENUE
+    }
+
+    fn fetch_quotes<'a>(&'a self, market_ids: &'a [String], now: DateTime<Utc>) -> BoxFuture<'a, Result<Vec<VenueQuote>, String>> {
+        async move { self.fetch_markets(market_ids, now).await }.boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markets_response_becomes_dollar_quotes() {
+        let now = DateTime::from_timestamp(1_790_000_000, 0).unwrap();
+        let body = serde_json::json!({
+            "markets": [
+                { "ticker": "FED-25DEC-T4.00", "status": "active", "yes_ask": 41, "no_ask": 61, "yes_bid": 39, "liquidity": 125000 },
+                { "ticker": "FED-25DEC-T4.25", "status": "active", "yes_ask": 0, "no_ask": 100 },
+                { "ticker": "FED-25SEP-T4.00", "status": "settled", "yes_ask": 99, "no_ask": 2 }
+            ],
+            "cursor": ""
+        }).to_string();
+
+        let quotes = KalshiClient::parse_quotes(&body, now).unwrap();
+        assert_eq!(quotes.len(), 1);
+        let quote = &quotes[0];
+        assert_eq!((quote.venue.as_str(), quote.market_id.as_str()), (KALSHI_VENUE, "FED-25DEC-T4.00"));
+        assert_eq!((quote.yes_price, quote.no_ask(), quote.liquidity), (0.41, 0.61, Some(1250.0)));
+        assert!(KalshiClient::parse_quotes("<html>", now).is_err());
+    }
+}
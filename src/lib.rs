@@ -9,106 +9,515 @@
 //! - VWAP Execution Strategy
 //! - MEV Extraction
 //! - Advanced Risk Management (VaR, Sharpe, Drawdown)
+//!
+//! The default build holds detection, backtest, paper trading and the dashboard.
+//! Cargo features:
+//! - `native` (default): exchange clients, persistence, actors and the dashboard server
+//! - `wasm`: only the analytics core (detectors, optimizers, risk math, backtest),
+//!   exported to JavaScript for client-side what-if calculations
+//! - `ffi`: C ABI over the detector and risk metrics (`include/polymarket_arb.h`)
+//! - `mev`: MEV detection on executed trades
+//! - `onchain`: wallet signatures and CLOB L1 authentication
+//! - `rl`: Q-learning on executed trades
+//! - `postgres`: Postgres persistence backend
+//! - `full`: all of the native features above
 
 pub mod types;
+pub mod clock;
 pub mod arbitrage;
+pub mod detector;
 pub mod optimization;
+#[cfg(feature = "rl")]
 pub mod rl;
+#[cfg(feature = "native")]
 pub mod execution;
 pub mod market;
 pub mod risk;
+#[cfg(feature = "native")]
 pub mod polymarket_api;
+#[cfg(feature = "native")]
+pub mod rate_limit;
+#[cfg(feature = "native")]
+pub mod failover;
+#[cfg(feature = "native")]
+pub mod audit;
+#[cfg(feature = "native")]
+pub mod reconciliation;
+pub mod signals;
+#[cfg(feature = "native")]
+pub mod news;
+#[cfg(feature = "native")]
+pub mod events;
+#[cfg(feature = "native")]
+pub mod webhook;
+#[cfg(feature = "native")]
+pub mod refresh;
+#[cfg(feature = "native")]
+pub mod replay;
+pub mod calibration;
+#[cfg(feature = "native")]
+pub mod venues;
+#[cfg(feature = "native")]
+pub mod kalshi;
+#[cfg(feature = "native")]
+pub mod symbols;
+#[cfg(feature = "native")]
+pub mod order_journal;
+#[cfg(feature = "native")]
+pub mod actors;
+#[cfg(feature = "native")]
+pub mod storage;
+#[cfg(feature = "native")]
+pub mod accounts;
+pub mod paper;
+pub mod backtest;
+pub mod analytics;
+#[cfg(feature = "native")]
+pub mod loadtest;
+#[cfg(feature = "native")]
+pub mod soak;
+#[cfg(feature = "native")]
+pub mod demo;
+pub mod missed_edge;
+pub mod rewards;
+pub mod impact;
+pub mod adverse_selection;
+pub mod threshold;
+pub mod onboarding;
+pub mod faults;
+pub mod relations;
+pub mod execution_queue;
+pub mod opportunity_log;
+pub mod snapshot;
+pub mod versioning;
+pub mod clustering;
+#[cfg(feature = "native")]
+pub mod resolution;
+#[cfg(feature = "onchain")]
+pub mod wallet;
+pub mod stat_arb;
+pub mod maker;
+pub mod orderbook;
+#[cfg(feature = "native")]
+pub mod capture;
+#[cfg(feature = "native")]
+pub mod telemetry;
+#[cfg(feature = "native")]
+pub mod exchange_accounts;
+#[cfg(feature = "mev")]
+pub mod mev;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 
+#[cfg(feature = "native")]
 pub mod api_server;
 
 pub use types::*;
+pub use clock::*;
 pub use arbitrage::*;
+pub use detector::*;
 pub use optimization::*;
+#[cfg(feature = "rl")]
 pub use rl::*;
+#[cfg(feature = "native")]
 pub use execution::*;
 pub use market::*;
 pub use risk::*;
+#[cfg(feature = "native")]
 pub use polymarket_api::*;
+#[cfg(feature = "native")]
+pub use rate_limit::*;
+#[cfg(feature = "native")]
+pub use failover::*;
+#[cfg(feature = "native")]
+pub use audit::*;
+#[cfg(feature = "native")]
+pub use reconciliation::*;
+pub use signals::*;
+#[cfg(feature = "native")]
+pub use news::*;
+#[cfg(feature = "native")]
+pub use events::*;
+#[cfg(feature = "native")]
+pub use webhook::*;
+#[cfg(feature = "native")]
+pub use refresh::*;
+#[cfg(feature = "native")]
+pub use replay::*;
+pub use calibration::*;
+#[cfg(feature = "native")]
+pub use venues::*;
+#[cfg(feature = "native")]
+pub use kalshi::*;
+#[cfg(feature = "native")]
+pub use symbols::*;
+#[cfg(feature = "native")]
+pub use order_journal::*;
+#[cfg(feature = "native")]
+pub use actors::*;
+#[cfg(feature = "native")]
+pub use storage::*;
+#[cfg(feature = "native")]
+pub use accounts::*;
+pub use paper::*;
+pub use backtest::*;
+pub use analytics::*;
+#[cfg(feature = "native")]
+pub use loadtest::*;
+#[cfg(feature = "native")]
+pub use soak::*;
+#[cfg(feature = "native")]
+pub use demo::*;
+pub use missed_edge::*;
+pub use rewards::*;
+pub use impact::*;
+pub use adverse_selection::*;
+pub use threshold::*;
+pub use onboarding::*;
+pub use faults::*;
+pub use relations::*;
+pub use execution_queue::*;
+pub use opportunity_log::*;
+pub use snapshot::*;
+pub use versioning::*;
+pub use clustering::*;
+#[cfg(feature = "native")]
+pub use resolution::*;
+#[cfg(feature = "onchain")]
+pub use wallet::*;
+pub use stat_arb::*;
+pub use maker::*;
+pub use orderbook::*;
+#[cfg(feature = "native")]
+pub use capture::*;
+#[cfg(feature = "native")]
+pub use telemetry::*;
+#[cfg(feature = "native")]
+pub use exchange_accounts::*;
+#[cfg(feature = "mev")]
+pub use mev::*;
+#[cfg(feature = "wasm")]
+pub use wasm::*;
+#[cfg(feature = "ffi")]
+pub use ffi::*;
+
+/// Opportunities a stream subscriber can fall behind before the oldest are skipped
+#[cfg(feature = "native")]
+pub const OPPORTUNITY_STREAM_CAPACITY: usize = 1024;
 
 /// Main orchestrator for the HFT arbitrage bot
+///
+/// Runs steps sequentially on one mutable struct; `ActorSystem::spawn` splits it into
+/// supervised actors for concurrent operation.
+#[cfg(feature = "native")]
 pub struct HftArbitrageBot {
     pub config: BotConfig,
-    pub arb_detector: ArbitrageDetector,
-    pub graph_detector: GraphArbitrageDetector,
+    pub arb_detector: std::sync::Arc<std::sync::RwLock<ArbitrageDetector>>, // Detector YES/NO registrato in detectors: tick, soglie e dedup aggiornati dallo step
+    pub graph_detector: std::sync::Arc<GraphArbitrageDetector>, // Grafo dei prezzi registrato in detectors, per le statistiche di pruning
     pub optimizer: StatisticalArbOptimizer,
     pub portfolio_optimizer: IpPortfolioOptimizer,
+    #[cfg(feature = "rl")]
     pub rl_agent: QLearningOptimizer,
     pub executor: TradeExecutor,
+    #[cfg(feature = "mev")]
     pub mev_extractor: MevDetector,
     pub market_manager: MarketManager,
     pub risk_manager: RiskManager,
     pub position_sizer: PositionSizer,
     pub polymarket_api: Option<PolymarketApiClient>, // API client per dati reali
+    pub feed_state: Option<tokio::sync::watch::Receiver<ConnectionState>>, // Stato del feed live, se collegato
+    feed_task: Option<tokio::task::JoinHandle<()>>, // Sessione WebSocket avviata da set_data_source
     pub capital: f64,
     pub initial_capital: f64,
     pub current_step: u64,
+    pub capital_efficiency: CapitalEfficiency, // Capitale impiegato e turnover per step
+    pub trade_metrics: TradeMetricsAggregator, // Win rate, edge ed expectancy sugli ultimi trade
+    pub clock: SharedClock, // Sorgente di tempo (simulata in test e backtest)
+    pub storage: Option<SharedStorage>, // Backend di persistenza, aperto con open_storage
+    pub opportunity_store: Option<OpportunityStore>, // Tabella SQLite dello storico opportunità, aperta con open_storage
+    pub run_id: String,
+    prices_saved_until: Option<chrono::DateTime<chrono::Utc>>, // Snapshot di prezzo già salvati, se step_history.record_prices
+    pub news_feed: Option<NewsFeed>, // Poller delle notizie, se configurato
+    pub calibration: CalibrationTracker, // Prezzi fair osservati fino alla risoluzione
+    pub missed_edge: MissedEdgeTracker, // Opportunità rilevate ma non eseguite, per causa
+    pub trade_monitor: TradeAnomalyMonitor, // Alert sui cluster di trade anomali
+    pub stat_arb: Option<StatArbManager>, // Posizioni di mean reversion, se la strategia è configurata
+    pub detectors: Vec<Box<dyn Detector>>, // YES/NO e grafo, coppie cointegrate, bid maker e cross-venue se configurati, poi quelli registrati
+    pub relations: RelationBook, // Relazioni logiche tra mercati (implicazione, esclusione) da verificare nei prezzi
+    pub triangles: Vec<MarketTriangle>, // Mercati congiunti e le loro due componenti
+    pub adverse_selection: Option<AdverseSelectionModel>, // Latenze di fill e sconto del margine sui mercati veloci, se configurato
+    pub execution_queue: ExecutionQueue, // Coda per priorità delle opportunità approvate nello step
+    pub live_reconciler: Option<LiveReconciler>, // Allarmi sulle divergenze con l'exchange, se configurato
+    pub accounts: AccountRegistry, // Account aggiuntivi con i rispettivi stream di ordini e balance
+    pub symbols: SymbolRegistry, // Identificativi per venue (condition id, token id, ticker) dei mercati interni
+    pub event_publisher: Option<EventPublisher>, // Export di trade, opportunità ed eventi di rischio su Kafka/NATS, se configurato
+    pub opportunity_webhook: Option<OpportunityWebhook>, // Opportunità approvate e dimensionate verso un'esecuzione esterna, se configurato
+    pub refresh_scheduler: RefreshScheduler, // Refresh REST per mercato secondo copertura WebSocket, priorità e arretrato del feed
+    pub venue_quotes: VenueQuoteBook, // Ultime quotazioni delle altre venue, lette dal detector cross-venue
+    venue_adapters: Vec<Box<dyn VenueAdapter>>, // Sorgenti delle quotazioni delle altre venue (Kalshi se configurato)
+    opportunity_stream: tokio::sync::broadcast::Sender<types::ArbitrageOpportunity>, // Opportunità rilevate, ai sottoscrittori in tempo reale
+    trading_halted: bool, // Trading bloccato dai controlli di rischio all'ultimo step
 }
 
+#[cfg(feature = "native")]
 impl HftArbitrageBot {
     pub fn new(config: BotConfig) -> Self {
+        Self::with_clock(config, system_clock())
+    }
+
+    /// Bot driven by an injected clock
+    pub fn with_clock(config: BotConfig, clock: SharedClock) -> Self {
         let initial_capital = config.initial_capital;
+        let polymarket_api = config.use_real_data.then(|| Self::build_api_client(&config)).and_then(|client| {
+            client.map_err(|e| eprintln!("⚠️  Real data disabled: {}", e)).ok()
+        });
+        let accounts = AccountRegistry::from_configs(&config.accounts, polymarket_api.as_ref()).unwrap_or_else(|e| {
+            eprintln!("⚠️  Accounts ignored: {}", e);
+            AccountRegistry::default()
+        });
+        let event_publisher = config.event_export.clone().and_then(|export| {
+            EventPublisher::new(export)
+                .map_err(|e| eprintln!("⚠️  Event export disabled: {}", e))
+                .ok()
+        });
+        let venue_quotes = VenueQuoteBook::new();
+        let venue_adapters: Vec<Box<dyn VenueAdapter>> = config.cross_venue.as_ref()
+            .and_then(|cross_venue| cross_venue.kalshi.clone())
+            .and_then(|kalshi| {
+                KalshiClient::new(kalshi)
+                    .map_err(|e| eprintln!("⚠️  Kalshi adapter disabled: {}", e))
+                    .ok()
+            })
+            .map(|client| Box::new(client) as Box<dyn VenueAdapter>)
+            .into_iter()
+            .collect();
+        let executor = TradeExecutor::with_clock(config.clone(), clock.clone());
+        let arb_detector = {
+            let detector = ArbitrageDetector::new(
+                config.min_profit_threshold,
+                1000.0,
+            ).with_costs(config.trading_costs.clone())
+                .with_dedup(config.opportunity_dedup.clone())
+                .with_leg_sizing(config.leg_sizing.clone());
+            let detector = match config.adaptive_threshold.clone() {
+                Some(adaptive) => detector.with_adaptive_threshold(adaptive),
+                None => detector,
+            };
+            let detector = match config.opportunity_history.clone() {
+                Some(history) => detector.with_history(history),
+                None => detector,
+            };
+            std::sync::Arc::new(std::sync::RwLock::new(detector))
+        };
+        let graph_detector = std::sync::Arc::new(match config.graph_limits.clone() {
+            Some(limits) => GraphArbitrageDetector::new().with_limits(limits),
+            None => GraphArbitrageDetector::new(),
+        });
+        let opportunity_webhook = config.opportunity_webhook.clone().and_then(|webhook| {
+            OpportunityWebhook::new(webhook)
+                .map_err(|e| eprintln!("⚠️  Opportunity webhook disabled: {}", e))
+                .ok()
+        });
         
         Self {
             config: config.clone(),
-            arb_detector: ArbitrageDetector::new(
-                config.min_profit_threshold,
-                1000.0,
-            ),
-            graph_detector: GraphArbitrageDetector::new(),
-            optimizer: StatisticalArbOptimizer::new(),
+            arb_detector: arb_detector.clone(),
+            graph_detector: graph_detector.clone(),
+            // Una sola istanza delle curve di impatto: sizing dell'executor e scoring dell'ottimizzatore
+            optimizer: StatisticalArbOptimizer { impact: executor.impact.clone(), ..StatisticalArbOptimizer::new() },
             portfolio_optimizer: IpPortfolioOptimizer::new(10),
+            #[cfg(feature = "rl")]
             rl_agent: QLearningOptimizer::new(0.1, 0.95, 0.1),
-            executor: TradeExecutor::new(config.clone()),
+            executor,
+            #[cfg(feature = "mev")]
             mev_extractor: if config.enable_mev { MevDetector::new(1000) } else { MevDetector::new(0) },
-            market_manager: MarketManager::new(1000.0, 50),
-            risk_manager: RiskManager::new(50.0, 10, 0.15, 0.10, 0.20, 10),
-            position_sizer: PositionSizer::new(0.25, 0.05, 10.0),
-            polymarket_api: if config.use_real_data {
-                Some(PolymarketApiClient::new(
-                    PolymarketApiConfig::default(),
-                    config.polymarket_api_key.clone(),
-                    config.polymarket_secret.clone(),
-                    config.polymarket_passphrase.clone(),
-                ))
-            } else {
-                None
+            market_manager: {
+                let mut market_manager = MarketManager::new(1000.0, 50);
+                market_manager.onboarding = config.onboarding.clone().map(MarketOnboarding::new);
+                market_manager
+            },
+            risk_manager: {
+                let mut risk_manager = RiskManager::new(50.0, 10, 0.15, 0.10, 0.20, 10);
+                risk_manager.set_clock(clock.clone());
+                risk_manager.set_strategy_limits(&config.strategy_risk);
+                risk_manager
             },
+            position_sizer: PositionSizer::new(0.25, 0.05, 10.0),
+            polymarket_api,
+            feed_state: None,
+            feed_task: None,
             capital: initial_capital,
             initial_capital,
             current_step: 0,
+            capital_efficiency: CapitalEfficiency::new(),
+            trade_metrics: TradeMetricsAggregator::new(TRADE_METRICS_WINDOW),
+            clock,
+            storage: None,
+            opportunity_store: None,
+            run_id: uuid::Uuid::new_v4().to_string(),
+            prices_saved_until: None,
+            news_feed: config.news_feed.clone().map(NewsFeed::new),
+            calibration: CalibrationTracker::new(),
+            missed_edge: MissedEdgeTracker::new(),
+            trade_monitor: TradeAnomalyMonitor::new(),
+            // Le coppie del detector statistico sono gestite come posizioni anche senza la strategia single-token
+            stat_arb: config.stat_arb.clone()
+                .or_else(|| config.pair_stat_arb.as_ref().map(|_| StatArbConfig::default()))
+                .map(StatArbManager::new),
+            detectors: [Box::new(arb_detector) as Box<dyn Detector>, Box::new(graph_detector)]
+                .into_iter()
+                .chain(config.pair_stat_arb.clone().map(|pairs| Box::new(StatArbDetector::new(pairs)) as Box<dyn Detector>))
+                .chain(config.maker_arb.clone().map(|maker| {
+                    Box::new(MakerArbDetector::new(maker, config.trading_costs.clone())) as Box<dyn Detector>
+                }))
+                .chain(config.cross_venue.clone().map(|cross_venue| {
+                    Box::new(CrossVenueDetector::new(cross_venue, venue_quotes.clone())) as Box<dyn Detector>
+                }))
+                .collect(),
+            relations: RelationBook::new(config.relations.clone()),
+            triangles: config.triangles.clone(),
+            adverse_selection: config.adverse_selection.clone().map(AdverseSelectionModel::new),
+            execution_queue: ExecutionQueue::new(config.execution_queue.clone().unwrap_or_else(ExecutionQueueConfig::sequential)),
+            live_reconciler: config.live_reconciliation.clone().map(LiveReconciler::new),
+            accounts,
+            symbols: SymbolRegistry::default(),
+            event_publisher,
+            opportunity_webhook,
+            refresh_scheduler: RefreshScheduler::new(config.refresh_schedule.clone()),
+            venue_quotes,
+            venue_adapters,
+            opportunity_stream: tokio::sync::broadcast::channel(OPPORTUNITY_STREAM_CAPACITY).0,
+            trading_halted: false,
+        }
+    }
+
+    /// Add a detector scanned every step after the built-in ones
+    pub fn register_detector(&mut self, detector: Box<dyn Detector>) {
+        self.detectors.push(detector);
+    }
+
+    /// Stop scanning with the detectors named `name`, built-in ones included; returns whether any was removed
+    pub fn remove_detector(&mut self, name: &str) -> bool {
+        let before = self.detectors.len();
+        self.detectors.retain(|d| d.name() != name);
+        self.detectors.len() < before
+    }
+
+    /// API client for the configured credentials and market filter
+    ///
+    /// The wallet key comes from the config or `POLYMARKET_PRIVATE_KEY`; credentials are derived in `authenticate`
+    /// (feature "onchain"). An unusable proxy fails the build instead of connecting directly.
+    fn build_api_client(config: &BotConfig) -> Result<PolymarketApiClient, String> {
+        let client = PolymarketApiClient::new(
+            PolymarketApiConfig {
+                market_filter: config.market_filter.clone(),
+                ..PolymarketApiConfig::default().with_env_overrides()
+            },
+            config.polymarket_api_key.clone(),
+            config.polymarket_secret.clone(),
+            config.polymarket_passphrase.clone(),
+        ).map_err(|e| format!("API client unavailable: {}", e))?;
+
+        #[cfg(not(feature = "onchain"))]
+        if config.polymarket_private_key.is_some() {
+            eprintln!("⚠️  Wallet private key ignored: built without the \"onchain\" feature");
+        }
+        #[cfg(feature = "onchain")]
+        let client = {
+            let wallet = match &config.polymarket_private_key {
+                Some(key) => Some(Wallet::from_private_key(key)),
+                None => Wallet::from_env(),
+            };
+            match wallet {
+                Some(Ok(wallet)) => client.with_wallet(wallet),
+                Some(Err(e)) => {
+                    eprintln!("⚠️  Wallet private key ignored: {}", e);
+                    client
+                }
+                None => client,
+            }
+        };
+        Ok(client)
+    }
+
+    /// Derive CLOB API credentials from the wallet when only a private key is configured; returns whether they were derived
+    pub async fn authenticate(&mut self) -> bool {
+        // Le firme L1 scadono con un orologio locale sfasato: sincronizza prima di derivare
+        self.sync_server_clock().await;
+        let derived = self.accounts.authenticate().await;
+        if derived > 0 {
+            eprintln!("🔑 CLOB API credentials derived for {} additional account(s)", derived);
+        }
+        let Some(api) = &mut self.polymarket_api else { return false };
+
+        match api.ensure_credentials().await {
+            Ok(true) => {
+                eprintln!("🔑 CLOB API credentials derived for {}", api.config().wallet_address.as_deref().unwrap_or("wallet"));
+                true
+            }
+            Ok(false) => false,
+            Err(e) => {
+                eprintln!("⚠️  CLOB API key derivation failed: {}", e);
+                false
+            }
+        }
+    }
+
+    /// Re-measure the skew against the CLOB server clock when due; returns the offset in milliseconds if synced
+    ///
+    /// Signed requests use server time, so a drifting local clock does not cause 401s.
+    pub async fn sync_server_clock(&mut self) -> Option<i64> {
+        let api = self.polymarket_api.as_ref()?;
+        if !api.clob().server_clock().is_due(chrono::Utc::now().timestamp_millis()) {
+            return None;
+        }
+        match api.sync_server_time().await {
+            Ok(offset) => {
+                if offset.abs() >= 1000 {
+                    eprintln!("🕒 Local clock off by {:.1}s from CLOB server, signed timestamps adjusted", offset as f64 / 1000.0);
+                }
+                Some(offset)
+            }
+            Err(e) => {
+                eprintln!("CLOB server time sync failed: {}", e);
+                None
+            }
         }
     }
 
     /// Run a single trading step
     pub async fn run_step(&mut self) -> Result<StepResult, String> {
         self.current_step += 1;
+
+        // Fault simulati dello step: durante un gap del feed i prezzi restano fermi
+        let faults = self.executor.faults.as_mut().map(FaultInjector::begin_step).unwrap_or_default();
         
         // Update market prices
-        self.market_manager.update_prices().await?;
-        
-        // Get all markets
-        let markets: Vec<_> = self.market_manager.markets.values().cloned().collect();
+        if !faults.ws_gap {
+            self.market_manager.update_prices().await?;
+        }
+        let onboarded = self.market_manager.run_onboarding();
+        if onboarded > 0 {
+            eprintln!("Step {}: {} mercati completano l'onboarding", self.current_step, onboarded);
+        }
+
+        // Trading sospeso mentre il feed live è disconnesso
+        if !self.feed_ready() || faults.ws_gap {
+            // Le opportunità sui prezzi congelati contano come margine perso per staleness
+            let markets = self.market_manager.markets_to_scan(self.current_step);
+            let frozen = self.arb_detector.read().unwrap().scan_markets_with_watchlist(&markets, &self.market_manager.watchlist, &self.market_manager.order_books);
+            self.record_missed(&frozen, MissCause::Stale);
+            return Ok(self.step_result(0, 0, 0.0));
+        }
         
         // Detect arbitrage opportunities
-        let simple_arbs = self.arb_detector.scan_markets(&markets);
-        let graph_arbs = self.graph_detector.detect_arbitrage_cycles();
-        let mut all_opportunities = simple_arbs;
-        all_opportunities.extend(graph_arbs);
+        let detection = Stopwatch::start(&self.clock);
+        let all_opportunities = self.detect_opportunities();
         
         if all_opportunities.is_empty() {
-            return Ok(StepResult {
-                step: self.current_step,
-                opportunities: 0,
-                trades: 0,
-                profit: 0.0,
-                capital: self.capital,
-                win_rate: 0.0,
-            });
+            return Ok(self.step_result(0, 0, 0.0));
         }
         
         // Optimize using Integer Programming
@@ -128,83 +537,1067 @@ impl HftArbitrageBot {
             optimized.len(),
             projected.len()
         );
+
+        let skipped: Vec<types::ArbitrageOpportunity> = all_opportunities
+            .iter()
+            .filter(|o| !projected.iter().any(|p| p.market_id == o.market_id && p.arb_type == o.arb_type))
+            .cloned()
+            .collect();
+        self.record_missed(&skipped, MissCause::OptimizerSkip);
         
+        // Evento di blocco solo alla transizione, non a ogni step in cui il trading resta fermo
+        let can_trade = self.risk_manager.can_trade(self.capital);
+        if !can_trade && !self.trading_halted {
+            let status = self.risk_manager.get_risk_status();
+            self.export_event(ExportEvent::Risk(RiskEvent::TradingHalted { capital: self.capital, status }));
+        }
+        self.trading_halted = !can_trade;
+
         if projected.is_empty() {
-            return Ok(StepResult {
-                step: self.current_step,
-                opportunities: all_opportunities.len(),
-                trades: 0,
-                profit: 0.0,
-                capital: self.capital,
-                win_rate: 0.0,
-            });
+            return Ok(self.step_result(all_opportunities.len(), 0, 0.0));
         }
         
         // Check risk controls
-        if !self.risk_manager.can_trade(self.capital) {
-            return Ok(StepResult {
-                step: self.current_step,
-                opportunities: all_opportunities.len(),
-                trades: 0,
-                profit: 0.0,
-                capital: self.capital,
-                win_rate: 0.0,
-            });
+        if !can_trade {
+            self.record_missed(&projected, MissCause::RiskBlock);
+            return Ok(self.step_result(all_opportunities.len(), 0, 0.0));
         }
         
-        // Execute top opportunity
-        let trade: Option<TradeExecution> = self.executor
-            .execute_arbitrage(&projected[0], self.capital)
-            .await;
-        
-        let profit = trade.as_ref().map(|t| t.profit).unwrap_or(0.0);
-        self.capital += profit;
-        
+        // Approved opportunities not paused by a resolution-risk flag enter the queue, sized down if flagged
+        let detection_ms = detection.elapsed_ms() + faults.latency_ms;
+        let late = detection_ms > self.config.max_execution_time_ms; // Prezzi superati durante l'ottimizzazione
+        self.execution_queue.begin_step();
+        for candidate in &projected {
+            let multiplier = self.risk_manager.resolution_multiplier(candidate);
+            // Flag di rischio sul mercato o budget di perdita della strategia esaurito
+            if multiplier <= 0.0 || !self.risk_manager.can_trade_strategy(candidate.arb_type, self.capital) {
+                self.record_missed(std::slice::from_ref(candidate), MissCause::RiskBlock);
+            } else if faults.api_outage {
+                // Exchange irraggiungibile: nessun ordine può essere inviato
+                self.record_missed(std::slice::from_ref(candidate), MissCause::Outage);
+            } else if late {
+                self.record_missed(std::slice::from_ref(candidate), MissCause::Latency);
+            } else {
+                self.execution_queue.push(candidate.clone(), multiplier);
+            }
+        }
+
+        // Execute in waves: concurrent executions on disjoint markets, capital shared within the wave
+        let mut trades = 0;
+        let mut profit = 0.0;
+        loop {
+            let wave = self.execution_queue.next_wave();
+            if wave.is_empty() {
+                break;
+            }
+            let tradable = self.risk_manager.tradable_capital(self.capital);
+            let mut committed = 0.0;
+            let mut running: fxhash::FxHashMap<types::ArbType, usize> = fxhash::FxHashMap::default(); // Esecuzioni dell'ondata per strategia
+            let mut prepared = Vec::new();
+            for queued in wave {
+                // L'attesa in coda consuma lo stesso budget di latenza del rilevamento
+                if detection_ms + queued.wait_ms > self.config.max_execution_time_ms {
+                    self.record_missed(std::slice::from_ref(&queued.opportunity), MissCause::Latency);
+                    continue;
+                }
+                let strategy = queued.opportunity.arb_type;
+                let concurrent = running.entry(strategy).or_default();
+                if self.risk_manager.max_concurrent_for(strategy).is_some_and(|max| *concurrent >= max) {
+                    self.record_missed(std::slice::from_ref(&queued.opportunity), MissCause::QueueLimit);
+                    continue;
+                }
+                *concurrent += 1;
+                // Le perdite delle ondate precedenti nello step possono aver esaurito il budget della strategia
+                if !self.risk_manager.can_trade_strategy(strategy, self.capital) {
+                    self.record_missed(std::slice::from_ref(&queued.opportunity), MissCause::RiskBlock);
+                    continue;
+                }
+                let capital = (tradable - committed).max(0.0) * queued.size_multiplier;
+                // Coppia cross-venue: l'altra gamba non passa dal CLOB Polymarket, la coppia è solo pubblicata
+                let external = strategy == types::ArbType::CrossVenue;
+                if let Some(execute_internally) = self.opportunity_webhook.as_ref().map(OpportunityWebhook::executes_internally) {
+                    let execute_internally = execute_internally && !external;
+                    let plan = self.publish_signal(&queued.opportunity, capital, queued.size_multiplier, execute_internally);
+                    // Modalità solo segnale: l'esecuzione spetta al sistema esterno, il capitale resta impegnato nell'ondata
+                    if !execute_internally {
+                        match plan {
+                            Some(plan) => committed += plan.total_investment,
+                            None => self.record_missed(std::slice::from_ref(&queued.opportunity), MissCause::SizeFloor),
+                        }
+                        continue;
+                    }
+                }
+                if external {
+                    self.record_missed(std::slice::from_ref(&queued.opportunity), MissCause::ExternalVenue);
+                    continue;
+                }
+                let max_position = self.risk_manager.max_position_for(strategy);
+                let Some(execution) = self.executor.prepare_execution(&queued.opportunity, capital, max_position) else {
+                    self.record_missed(std::slice::from_ref(&queued.opportunity), MissCause::SizeFloor);
+                    continue;
+                };
+                committed += execution.plan.total_investment;
+                prepared.push((queued.wait_ms, execution));
+            }
+
+            // Gli ordini dell'ondata sono in volo insieme; i fill si registrano all'arrivo di tutti
+            let executor = &self.executor;
+            let submitted = futures_util::future::join_all(prepared.into_iter().map(|(wait_ms, execution)| async move {
+                (wait_ms, executor.submit(execution).await)
+            })).await;
+            let mut duration_ms = 0;
+            for (wait_ms, submitted) in submitted {
+                let opportunity = submitted.prepared.opportunity.clone();
+                let trade = self.executor.book_execution(submitted);
+                self.book_trade(&opportunity, &trade);
+                if let Some(model) = self.adverse_selection.as_mut() {
+                    model.record_fill(&trade, (detection_ms + wait_ms + trade.execution_time_ms) as f64);
+                }
+                duration_ms = duration_ms.max(trade.execution_time_ms);
+                trades += 1;
+                profit += trade.profit;
+            }
+            self.execution_queue.complete_wave(duration_ms);
+
+            if !self.risk_manager.can_trade(self.capital) {
+                break;
+            }
+        }
+        let dropped = self.execution_queue.drain();
+        let cause = if self.risk_manager.can_trade(self.capital) { MissCause::QueueLimit } else { MissCause::RiskBlock };
+        self.record_missed(&dropped, cause);
+
+        Ok(self.step_result(all_opportunities.len(), trades, profit))
+    }
+
+    /// Book an executed trade: capital, risk, thresholds, impact, learning and metrics
+    fn book_trade(&mut self, opportunity: &types::ArbitrageOpportunity, trade: &TradeExecution) {
+        // La qualità del fill corregge la soglia di profitto della categoria (solo YES/NO e mint-and-sell:
+        // una coppia statistica all'entrata, per esempio, non ha ancora realizzato nulla)
+        {
+            let mut arb_detector = self.arb_detector.write().unwrap();
+            if let Some(thresholds) = arb_detector.thresholds.as_mut() {
+                let category = self.market_manager.get_market(&opportunity.market_id).and_then(|m| m.category.as_deref());
+                thresholds.record_execution(category, opportunity, trade);
+            }
+            arb_detector.mark_reported(opportunity, self.clock.now());
+        }
+        self.export_event(ExportEvent::Trade(trade.clone()));
+
+        self.capital += trade.profit;
+        // Coppia statistica: i leg restano aperti fino alla reversione dello spread
+        if opportunity.arb_type == types::ArbType::StatisticalArb && opportunity.legs.is_some() {
+            if let Some(stat_arb) = self.stat_arb.as_mut() {
+                stat_arb.open_spread(opportunity, trade, &self.market_manager.price_history, self.clock.now());
+            }
+        }
+
         // Update risk metrics
-        self.risk_manager.update(profit, self.capital);
-        
+        self.risk_manager.update_strategy(trade.arb_type, trade.profit, self.capital);
+
         // Update Q-Learning
-        if let Some(ref t) = trade {
-            let reward = if t.profit > 0.0 { 1.0 } else { -1.0 };
-            // Get state and action (simplified)
-            let state = QState {
-                price_trend: 0,
-                arbitrage_available: 1,
-                z_score_bucket: 0,
-            };
-            let next_state = state;
-            // Update Q-learning with individual parameters
-            let opportunity = &projected[0];
-            let z_score = if (1.0 - opportunity.sum_price) > 0.02 { 2.5 } else { 0.5 };
-            let momentum = 0.01; // Simplified
-            let arb_available = true;
-            let action = self.rl_agent.get_action(z_score, momentum, arb_available);
-            self.rl_agent.update(z_score, momentum, arb_available, action, reward);
-        }
-        
-        let trades = if trade.is_some() { 1 } else { 0 };
-        
-        Ok(StepResult {
+        #[cfg(feature = "rl")]
+        self.rl_agent.learn_from_trade(opportunity, trade);
+
+        self.trade_metrics.record_trade(trade);
+    }
+
+    /// Result of the current step, with the trade metrics over the rolling window
+    fn step_result(&self, opportunities: usize, trades: u32, profit: f64) -> StepResult {
+        let metrics = self.trade_metrics.rolling();
+        StepResult {
             step: self.current_step,
-            opportunities: all_opportunities.len(),
+            opportunities,
             trades,
             profit,
             capital: self.capital,
-            win_rate: self.executor.executed_trades.len() as f64,
-        })
+            win_rate: metrics.win_rate,
+            avg_edge: metrics.avg_edge,
+            expectancy: metrics.expectancy,
+        }
+    }
+
+    /// Manage mean-reversion positions and pair spreads (trailing exits), then open new positions on z-score extremes
+    ///
+    /// Realized PnL of the closed positions is added to capital and returned.
+    pub fn manage_stat_arb(&mut self) -> f64 {
+        let now = self.clock.now();
+        let feed_ready = self.feed_ready();
+        let Some(stat_arb) = self.stat_arb.as_mut() else { return 0.0 };
+        let histories = &self.market_manager.price_history;
+
+        let mut realized = 0.0;
+        for exit in stat_arb.manage(histories, now) {
+            realized += exit.pnl;
+            self.capital += exit.pnl;
+            self.risk_manager.update_strategy(types::ArbType::StatisticalArb, exit.pnl, self.capital);
+            eprintln!("📉 Stat-arb {} {} closed ({:?}): PnL {:.2}", exit.position.market_id, exit.position.token_type, exit.exit_reason, exit.pnl);
+        }
+        for exit in stat_arb.manage_spreads(histories, now) {
+            realized += exit.pnl;
+            self.capital += exit.pnl;
+            self.risk_manager.update_strategy(types::ArbType::StatisticalArb, exit.pnl, self.capital);
+            eprintln!("📉 Stat-arb spread {} closed ({:?}): PnL {:.2}", exit.position.trade_id, exit.exit_reason, exit.pnl);
+        }
+
+        if self.config.stat_arb.is_some() && feed_ready && self.risk_manager.can_trade_strategy(types::ArbType::StatisticalArb, self.capital) {
+            let capital = self.risk_manager.tradable_capital(self.capital);
+            // Mercati stale esclusi: un prezzo congelato non è un segnale
+            stat_arb.scan_entries(histories, &self.market_manager.stale, capital, now);
+        }
+        realized
+    }
+
+    /// Scan the markets due this step with every detector, then dedup and discount the opportunities
+    ///
+    /// Each surviving opportunity is exported and sent to the opportunity stream.
+    pub fn detect_opportunities(&mut self) -> Vec<types::ArbitrageOpportunity> {
+        // Get markets due for scanning (pinned markets every step)
+        let markets = self.market_manager.markets_to_scan(self.current_step);
+        let stale_arbs = self.arb_detector.read().unwrap().scan_markets_with_watchlist(&self.market_manager.stale_markets(), &self.market_manager.watchlist, &self.market_manager.order_books);
+        self.record_missed(&stale_arbs, MissCause::Stale);
+        // Eventi con un mercato stale esclusi: una gamba congelata falsa la somma dei prezzi
+        let events = self.market_manager.tradeable_events();
+        let view = MarketView {
+            markets: &markets,
+            events: &events,
+            watchlist: &self.market_manager.watchlist,
+            order_books: &self.market_manager.order_books,
+            price_history: &self.market_manager.price_history,
+            relations: self.relations.list(),
+            triangles: &self.triangles,
+            rewards: &self.optimizer.rewards,
+            now: self.clock.now(),
+        };
+        let mut all_opportunities = Vec::new();
+        for detector in &self.detectors {
+            let found = detector.scan(&view);
+            if detector.on_tick_grid() {
+                all_opportunities.extend(found);
+                continue;
+            }
+            // Detector che non conoscono tick e size minime: le loro opportunità vengono riprezzate
+            if !found.is_empty() {
+                eprintln!("Step {}: {} opportunità dal detector {}", self.current_step, found.len(), detector.name());
+            }
+            all_opportunities.extend(self.arb_detector.read().unwrap().enforce_order_constraints(found));
+        }
+        let all_opportunities = self.arb_detector.write().unwrap().dedup(all_opportunities, self.clock.now());
+        // Margine scontato della mossa attesa dei prezzi durante la latenza di fill
+        let (all_opportunities, consumed) = match &self.adverse_selection {
+            Some(model) => model.adjust(all_opportunities, &self.market_manager.price_history, self.clock.now()),
+            None => (all_opportunities, Vec::new()),
+        };
+        self.record_missed(&consumed, MissCause::Latency);
+        for opportunity in &all_opportunities {
+            self.export_event(ExportEvent::Opportunity(opportunity.clone()));
+            self.publish_opportunity(opportunity);
+        }
+        all_opportunities
+    }
+
+    /// Receiver of every opportunity detected from now on
+    ///
+    /// A receiver that falls more than `OPPORTUNITY_STREAM_CAPACITY` opportunities behind skips
+    /// the oldest ones (`RecvError::Lagged`); detection never waits for subscribers.
+    pub fn subscribe_opportunities(&self) -> tokio::sync::broadcast::Receiver<types::ArbitrageOpportunity> {
+        self.opportunity_stream.subscribe()
+    }
+
+    /// Publish on an existing channel instead of the bot's own, e.g. one shared by several bots
+    pub fn stream_opportunities_to(&mut self, sender: tokio::sync::broadcast::Sender<types::ArbitrageOpportunity>) {
+        self.opportunity_stream = sender;
+    }
+
+    fn publish_opportunity(&self, opportunity: &types::ArbitrageOpportunity) {
+        // Senza sottoscrittori l'invio fallisce: nessuna copia da fare
+        if self.opportunity_stream.receiver_count() > 0 {
+            let _ = self.opportunity_stream.send(opportunity.clone());
+        }
+    }
+
+    /// Queue an event for the Kafka/NATS export, if configured
+    fn export_event(&mut self, event: ExportEvent) {
+        let now = self.clock.now();
+        if let Some(publisher) = self.event_publisher.as_mut() {
+            publisher.publish(event, now);
+        }
+    }
+
+    /// Size an approved opportunity as the executor would and queue it for the webhook
+    ///
+    /// Returns the plan, or None when the position falls below the size floor (nothing is sent).
+    fn publish_signal(
+        &mut self,
+        opportunity: &types::ArbitrageOpportunity,
+        capital: f64,
+        size_multiplier: f64,
+        executed_internally: bool,
+    ) -> Option<ExecutionPlan> {
+        let max_position = self.risk_manager.max_position_for(opportunity.arb_type);
+        let plan = self.executor.plan_arbitrage(opportunity, capital, max_position)?;
+        let webhook = self.opportunity_webhook.as_mut()?;
+        self.arb_detector.write().unwrap().mark_reported(opportunity, self.clock.now());
+        webhook.publish(OpportunitySignal {
+            signal_id: uuid::Uuid::new_v4().to_string(),
+            opportunity: opportunity.clone(),
+            plan: plan.clone(),
+            capital,
+            size_multiplier,
+            executed_internally,
+            approved_at: self.clock.now(),
+        });
+        Some(plan)
+    }
+
+    /// Complete or unwind the executions a previous process left half-completed, at current market prices
+    ///
+    /// The realized PnL of each recovery is booked to capital and risk; completed ones count as trades.
+    pub fn recover_executions(&mut self) -> Vec<RecoveredExecution> {
+        if !self.executor.journal.has_open() {
+            return Vec::new();
+        }
+        let always_unwind = self.config.order_journal.as_ref().is_some_and(|j| j.always_unwind);
+        let markets = &self.market_manager;
+        let quote = |market_id: &str, token_type: TokenType| {
+            markets.get_market(market_id).map(|m| match token_type {
+                TokenType::Yes => m.yes_price,
+                TokenType::No => m.no_price,
+            })
+        };
+        let recovered = self.executor.recover_open_executions(quote, always_unwind);
+
+        for outcome in &recovered {
+            self.capital += outcome.pnl;
+            self.risk_manager.update(outcome.pnl, self.capital);
+            if let Some(trade) = &outcome.trade {
+                self.trade_metrics.record_trade(trade);
+            }
+            eprintln!("♻️  Esecuzione interrotta {} su {}: {:?}, PnL {:.2}", outcome.trade_id, outcome.market_id, outcome.action, outcome.pnl);
+        }
+        recovered
+    }
+
+    /// Archive the candidates logged since the last flush; returns how many were written
+    pub async fn flush_opportunity_history(&mut self) -> usize {
+        let Some(store) = &self.opportunity_store else { return 0 };
+        let records = match self.arb_detector.read().unwrap().history.as_ref().and_then(|h| h.lock().ok()) {
+            Some(mut log) => log.take_unsaved(),
+            None => return 0,
+        };
+        if records.is_empty() {
+            return 0;
+        }
+        match store.save(&records).await {
+            Ok(()) => records.len(),
+            Err(e) => {
+                eprintln!("⚠️  Opportunity history not archived ({} records): {}", records.len(), e);
+                0
+            }
+        }
+    }
+
+    /// Count untraded opportunities in the missed-edge tracker, sized as the executor would have
+    fn record_missed(&mut self, opportunities: &[types::ArbitrageOpportunity], cause: MissCause) {
+        if opportunities.is_empty() {
+            return;
+        }
+        let capital = self.risk_manager.tradable_capital(self.capital);
+        let executor = &self.executor;
+        self.missed_edge.record_all(opportunities, cause, |o| executor.position_size(capital, o), self.clock.now());
+    }
+
+    /// Push the market manager's subscription list (pinned markets first) to the WebSocket client
+    pub async fn sync_subscriptions(&self) {
+        if let Some(api) = &self.polymarket_api {
+            api.websocket()
+                .set_subscriptions(self.market_manager.websocket_asset_ids())
+                .await;
+        }
+    }
+
+    /// Load Gamma events and their markets into the market cache; returns how many were added
+    pub async fn load_events(&mut self) -> usize {
+        let Some(api) = &self.polymarket_api else { return 0 };
+
+        match api.fetch_events().await {
+            Ok(events) => {
+                let count = events.len();
+                for event in events {
+                    self.market_manager.add_event(event);
+                }
+                self.sync_subscriptions().await;
+                count
+            }
+            Err(e) => {
+                eprintln!("Event fetch failed: {}", e);
+                0
+            }
+        }
+    }
+
+    /// Seed price history of every market with known tokens from the CLOB prices-history endpoint
+    pub async fn seed_price_history(&mut self, interval_minutes: u32, range: HistoryRange) -> usize {
+        let Some(api) = &self.polymarket_api else { return 0 };
+
+        let targets: Vec<(String, String)> = self.market_manager
+            .get_all_markets()
+            .into_iter()
+            .filter_map(|m| m.tokens.as_ref().map(|t| (m.id.clone(), t.yes_token_id.clone())))
+            .collect();
+
+        let mut seeded = 0;
+        for (market_id, yes_token_id) in targets {
+            match api.fetch_price_history(&yes_token_id, interval_minutes, range).await {
+                Ok(snapshots) if !snapshots.is_empty() => {
+                    self.market_manager.seed_price_history(&market_id, snapshots);
+                    seeded += 1;
+                }
+                Ok(_) => {}
+                // Limite raggiunto: gli altri mercati verranno caricati al prossimo giro
+                Err(e @ PolymarketApiError::RateLimited { .. }) => {
+                    eprintln!("Price history seeding stopped: {}", e);
+                    break;
+                }
+                Err(e) => eprintln!("Price history for {} unavailable: {}", market_id, e),
+            }
+        }
+        seeded
+    }
+
+    /// Refresh the real CLOB balances used for pre-trade checks (no-op without credentials)
+    pub async fn refresh_balances(&mut self) -> Option<Balances> {
+        let api = self.polymarket_api.as_ref().filter(|api| api.clob().is_authenticated())?;
+
+        match api.get_balances(&self.market_manager.websocket_asset_ids()).await {
+            Ok(balances) => {
+                self.risk_manager.set_available_balance(Some(balances.available_collateral()));
+                Some(balances)
+            }
+            Err(e @ PolymarketApiError::AuthFailed(_)) => {
+                eprintln!("⚠️  Balance refresh rejected, check the CLOB credentials: {}", e);
+                None
+            }
+            Err(e) => {
+                eprintln!("Balance refresh failed: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Reconcile executed trades since a time with the account's real fills (no-op without credentials)
+    pub async fn reconcile_fills(&self, since: chrono::DateTime<chrono::Utc>, tolerance: ReconcileTolerance) -> Option<DivergenceReport> {
+        let api = self.polymarket_api.as_ref().filter(|api| api.clob().is_authenticated())?;
+
+        match api.get_trades(since).await {
+            Ok(fills) => {
+                let executed: Vec<TradeExecution> = self.executor.executed_trades.iter()
+                    .filter(|t| t.exit_time >= since)
+                    .cloned()
+                    .collect();
+                Some(reconcile_fills(&executed, &fills, tolerance))
+            }
+            Err(e) => {
+                eprintln!("Fill reconciliation failed: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Compare open orders, positions and balance with the exchange when due; returns the alarms raised
+    ///
+    /// Internal positions are the net executed legs per token. With `auto_correct`, orders, balance
+    /// and positions are aligned to the exchange after alarming.
+    pub async fn reconcile_live_state(&mut self) -> Vec<ReconciliationAlarm> {
+        let now = self.clock.now();
+        let Some(reconciler) = self.live_reconciler.as_mut().filter(|r| r.is_due(now)) else { return Vec::new() };
+        let Some(api) = self.polymarket_api.as_ref().filter(|api| api.clob().is_authenticated()) else { return Vec::new() };
+
+        // Stato interno: ordini già inviati, leg eseguiti netti per token, capitale tracciato
+        let token_of = |market_id: &str, token_type: TokenType| {
+            let tokens = self.market_manager.markets.get(market_id)?.tokens.as_ref()?;
+            Some(match token_type {
+                TokenType::Yes => tokens.yes_token_id.clone(),
+                TokenType::No => tokens.no_token_id.clone(),
+            })
+        };
+        let mut positions = std::collections::BTreeMap::new();
+        for leg in self.executor.executed_trades.iter().flat_map(|t| &t.legs) {
+            let Some(token_id) = leg.token_id.clone().or_else(|| token_of(&leg.market_id, leg.token_type)) else { continue };
+            let signed = if leg.direction == Direction::Buy { leg.quantity } else { -leg.quantity };
+            *positions.entry(token_id).or_insert(0.0) += signed;
+        }
+        let internal = AccountSnapshot {
+            open_orders: self.executor
+                .open_orders()
+                .filter(|o| o.status != OrderStatus::Pending)
+                .map(|o| (o.order_id.clone(), o.quantity))
+                .collect(),
+            positions: reconciler.adjusted_positions(positions),
+            balance: self.capital,
+        };
+
+        let mut token_ids: Vec<String> = internal.positions.keys().cloned().collect();
+        token_ids.extend(self.market_manager.websocket_asset_ids());
+        token_ids.sort();
+        token_ids.dedup();
+        let remote_orders = match api.clob().get_open_orders(None, None).await {
+            Ok(orders) => orders,
+            Err(e) => {
+                eprintln!("Live reconciliation skipped, open orders unavailable: {}", e);
+                return Vec::new();
+            }
+        };
+        let balances = match api.get_balances(&token_ids).await {
+            Ok(balances) => balances,
+            Err(e) => {
+                eprintln!("Live reconciliation skipped, balances unavailable: {}", e);
+                return Vec::new();
+            }
+        };
+        let exchange = AccountSnapshot {
+            open_orders: remote_orders
+                .iter()
+                .filter(|o| o.order_status().is_open())
+                .map(|o| (o.id.clone(), o.remaining_size()))
+                .collect(),
+            positions: balances.positions.clone(),
+            balance: balances.collateral,
+        };
+
+        let alarms = reconciler.check(&internal, &exchange, now);
+        for alarm in &alarms {
+            eprintln!("🚨 Reconciliation: {}", alarm.message);
+        }
+        if reconciler.config.auto_correct && !alarms.is_empty() {
+            for order in self.executor.reconcile_open_orders(&remote_orders) {
+                match api.clob().get_order(&order.order_id).await {
+                    Ok(remote) => eprintln!("Order {} closed on the exchange: {:?}, {} matched", order.order_id, remote.order_status(), remote.size_matched),
+                    Err(e) => eprintln!("Order {} closed on the exchange, status unavailable: {}", order.order_id, e),
+                }
+            }
+            self.capital = balances.collateral;
+            self.risk_manager.set_available_balance(Some(balances.available_collateral()));
+        }
+        for alarm in &alarms {
+            self.export_event(ExportEvent::Risk(RiskEvent::ReconciliationAlarm(alarm.clone())));
+        }
+        alarms
+    }
+
+    /// Publish orders and balances of every configured account on its stream when due; returns how many refreshed
+    pub async fn refresh_accounts(&mut self) -> usize {
+        let now = self.clock.now();
+        if self.accounts.is_empty() || !self.accounts.is_due(now) {
+            return 0;
+        }
+        let token_ids = self.market_manager.websocket_asset_ids();
+        self.accounts.refresh_all(&token_ids, now).await
+    }
+
+    /// Poll the news feed when due and raise resolution-risk flags; returns how many were raised
+    pub async fn poll_news(&mut self) -> usize {
+        let now = self.clock.now();
+        let Some(feed) = self.news_feed.as_mut().filter(|feed| feed.is_due(now)) else { return 0 };
+
+        let markets: Vec<MarketData> = self.market_manager.get_all_markets().into_iter().cloned().collect();
+        match feed.poll(&markets, now).await {
+            Ok(flags) => {
+                let count = flags.len();
+                for flag in flags {
+                    eprintln!("⚠️  Resolution risk on {} ({:?}): {}", flag.market_id, flag.level, flag.headline);
+                    self.export_event(ExportEvent::Risk(RiskEvent::ResolutionFlag(flag.clone())));
+                    self.risk_manager.raise_resolution_flag(flag);
+                }
+                count
+            }
+            Err(e) => {
+                eprintln!("News poll failed: {}", e);
+                0
+            }
+        }
+    }
+
+    /// Sample the fair price of every watched market for calibration
+    pub fn record_fair_prices(&mut self) {
+        let now = self.clock.now();
+        for market in self.market_manager.get_all_markets() {
+            self.calibration.record(market, now);
+        }
+    }
+
+    /// Close watched markets that resolved on Gamma; returns how many were resolved
+    pub async fn check_resolutions(&mut self) -> usize {
+        let Some(api) = &self.polymarket_api else { return 0 };
+
+        let mut resolved = 0;
+        for market_id in self.calibration.pending_markets() {
+            match api.fetch_resolution(&market_id).await {
+                Ok(Some(yes_won)) => {
+                    self.calibration.resolve(&market_id, yes_won, self.clock.now());
+                    resolved += 1;
+                }
+                Ok(None) => {}
+                Err(PolymarketApiError::NotFound(_)) => {
+                    // Mercato rimosso da Gamma: non risolverà mai
+                    eprintln!("Market {} no longer listed, dropped from calibration", market_id);
+                    self.calibration.pending.remove(&market_id);
+                }
+                Err(e @ PolymarketApiError::RateLimited { .. }) => {
+                    eprintln!("Resolution check stopped: {}", e);
+                    break;
+                }
+                Err(e) => eprintln!("Resolution of {} unavailable: {}", market_id, e),
+            }
+        }
+        resolved
+    }
+
+    /// Open the storage backend selected in the configuration, and the opportunity history table if configured
+    pub async fn open_storage(&mut self) -> Result<(), String> {
+        self.storage = Some(open_storage(&self.config.storage).await?);
+        if let Some(path) = self.config.opportunity_history.as_ref().and_then(|h| h.sqlite_path.as_ref()) {
+            self.opportunity_store = Some(OpportunityStore::open(path).await?);
+            if let Some(mut log) = self.arb_detector.read().unwrap().history.as_ref().and_then(|h| h.lock().ok()) {
+                log.set_archiving(true);
+            }
+        }
+        if let Err(e) = self.calibrate_impact().await {
+            eprintln!("⚠️  Impact calibration skipped: {}", e);
+        }
+        Ok(())
+    }
+
+    /// Refit the per-market impact curves, shared by executor and optimizer, from the order audit log
+    ///
+    /// Each matched order contributes its requested against filled price; fills on tokens of unknown
+    /// markets are skipped. Returns how many markets have a curve.
+    pub async fn calibrate_impact(&mut self) -> Result<usize, String> {
+        let Some(audit_log) = self.polymarket_api.as_ref().and_then(|api| api.clob().audit_log().cloned()) else { return Ok(0) };
+        let fills = tokio::task::spawn_blocking(move || audit_log.fills())
+            .await
+            .map_err(|e| format!("Audit log read failed: {}", e))??;
+
+        let market_of: fxhash::FxHashMap<&str, &str> = self.market_manager.markets.values()
+            .filter_map(|m| m.tokens.as_ref().map(|t| (m.id.as_str(), t)))
+            .flat_map(|(id, t)| [(t.yes_token_id.as_str(), id), (t.no_token_id.as_str(), id)])
+            .collect();
+        let mut impact = self.executor.impact.lock().unwrap();
+        impact.fit(fills.iter().filter_map(|f| Some((*market_of.get(f.token_id.as_str())?, f.notional, f.slippage))));
+        Ok(impact.curves().len())
+    }
+
+    /// Persist a step's trades, and its snapshot if the step history config keeps it (errors are reported, never fatal)
+    async fn persist_step(&self, result: &StepResult, trades: &[TradeExecution], latency_ms: u64) {
+        let Some(storage) = &self.storage else { return };
+
+        for trade in trades {
+            if let Err(e) = storage.save_trade(&self.run_id, trade).await {
+                eprintln!("⚠️  {}", e);
+            }
+        }
+        if !self.config.step_history.should_persist(result) {
+            return;
+        }
+        let snapshot = RunSnapshot::from_step(&self.run_id, result, self.clock.now(), latency_ms);
+        if let Err(e) = storage.save_snapshot(&snapshot).await {
+            eprintln!("⚠️  {}", e);
+        }
+    }
+
+    /// Persist a detection-only step (the dashboard feed, which never trades) and the prices recorded since the last one
+    pub async fn persist_detection_step(&mut self, opportunities: usize, latency_ms: u64) {
+        self.current_step += 1;
+        let result = self.step_result(opportunities, 0, 0.0);
+        self.persist_step(&result, &[], latency_ms).await;
+        self.persist_prices().await;
+    }
+
+    /// Save the price snapshots recorded since the last call, for offline replay
+    async fn persist_prices(&mut self) {
+        let Some(storage) = self.storage.clone() else { return };
+        if !self.config.step_history.record_prices {
+            return;
+        }
+        let since = self.prices_saved_until;
+        let prices: Vec<StoredPrice> = self.market_manager.price_history.iter()
+            .flat_map(|(market_id, history)| {
+                history.iter()
+                    .filter(|s| since.is_none_or(|since| s.timestamp > since))
+                    .map(|snapshot| StoredPrice { run_id: self.run_id.clone(), market_id: market_id.clone(), snapshot: snapshot.clone() })
+            })
+            .collect();
+        let Some(latest) = prices.iter().map(|p| p.snapshot.timestamp).max() else { return };
+        match storage.save_prices(&prices).await {
+            Ok(()) => self.prices_saved_until = Some(latest),
+            Err(e) => eprintln!("⚠️  {}", e),
+        }
+    }
+
+    /// Load tick size and minimum order size of every market with known tokens into the executor and the detector
+    pub async fn refresh_order_constraints(&mut self) -> usize {
+        let Some(api) = &self.polymarket_api else { return 0 };
+
+        let targets: Vec<(String, String)> = self.market_manager
+            .get_all_markets()
+            .into_iter()
+            .filter_map(|m| m.tokens.as_ref().map(|t| (m.id.clone(), t.yes_token_id.clone())))
+            .collect();
+
+        let mut loaded = 0;
+        for (market_id, yes_token_id) in targets {
+            match api.get_order_constraints(&yes_token_id).await {
+                Ok(constraints) => {
+                    self.executor.set_order_constraints(&market_id, constraints);
+                    self.arb_detector.write().unwrap().set_order_constraints(&market_id, constraints);
+                    loaded += 1;
+                }
+                Err(e @ PolymarketApiError::RateLimited { .. }) => {
+                    eprintln!("Order constraint refresh stopped: {}", e);
+                    break;
+                }
+                Err(e) => eprintln!("Order constraints for {} unavailable: {}", market_id, e),
+            }
+        }
+        loaded
+    }
+
+    /// Refresh YES-token spreads of every market with known tokens in one batched request
+    pub async fn refresh_spreads(&mut self) -> usize {
+        let Some(api) = &self.polymarket_api else { return 0 };
+
+        let token_ids: Vec<String> = self.market_manager
+            .get_all_markets()
+            .into_iter()
+            .filter_map(|m| m.tokens.as_ref().map(|t| t.yes_token_id.clone()))
+            .collect();
+
+        match api.get_spreads(&token_ids).await {
+            Ok(spreads) => self.market_manager.apply_spreads(&spreads),
+            Err(e) => {
+                eprintln!("Spread refresh failed: {}", e);
+                0
+            }
+        }
+    }
+
+    /// Mark markets of silent WebSocket assets stale and refresh them over REST; returns how many tokens were refreshed
+    ///
+    /// Stale markets stay out of scanning until a WebSocket update or a REST price arrives.
+    pub async fn check_staleness(&mut self) -> usize {
+        let Some(api) = &self.polymarket_api else { return 0 };
+
+        let silent = api.websocket().stale_assets().await;
+        if silent.is_empty() {
+            return 0;
+        }
+        let newly_stale = self.market_manager.mark_assets_stale(&silent);
+        if !newly_stale.is_empty() {
+            eprintln!("⏸️  {} markets stale (no WebSocket updates), refreshing over REST", newly_stale.len());
+        }
+
+        match api.get_midpoints(&silent).await {
+            Ok(prices) => {
+                let refreshed: Vec<String> = prices.keys().cloned().collect();
+                api.websocket().mark_refreshed(&refreshed).await;
+                self.market_manager.apply_rest_prices(&prices)
+            }
+            Err(e) => {
+                eprintln!("Stale market refresh failed: {}", e);
+                0
+            }
+        }
+    }
+
+    /// Refetch snapshots of local order books that hit a sequence gap; returns how many books were rebuilt
+    pub async fn resync_order_books(&mut self) -> usize {
+        let Some(api) = &self.polymarket_api else { return 0 };
+
+        let pending = self.market_manager.pending_book_snapshots();
+        if pending.is_empty() {
+            return 0;
+        }
+        match api.get_order_books(&pending).await {
+            Ok(books) => {
+                self.market_manager.apply_order_books(&books);
+                pending.iter().filter(|id| self.market_manager.get_order_book(id).is_some()).count()
+            }
+            Err(e) => {
+                eprintln!("Order book resync failed: {}", e);
+                0
+            }
+        }
+    }
+
+    /// Refresh maker reward programs and, with credentials, today's accrued rewards; returns how many programs matched a cached market
+    ///
+    /// Runs at most once per `REWARDS_REFRESH_SECS`.
+    pub async fn refresh_rewards(&mut self) -> usize {
+        let Some(api) = &self.polymarket_api else { return 0 };
+        let now = self.clock.now();
+        if !self.optimizer.rewards.is_due(now) {
+            return 0;
+        }
+
+        let market_ids: fxhash::FxHashMap<String, String> = self.market_manager
+            .markets
+            .values()
+            .filter_map(|m| m.tokens.as_ref()?.condition_id.clone().map(|c| (c, m.id.clone())))
+            .collect();
+
+        let matched = match api.get_reward_configs().await {
+            Ok(configs) => self.optimizer.rewards.set_configs(configs, &market_ids, now),
+            Err(e) => {
+                eprintln!("Reward config fetch failed: {}", e);
+                0
+            }
+        };
+        if api.clob().is_authenticated() {
+            match api.get_user_rewards(now.date_naive()).await {
+                Ok(rewards) => self.optimizer.rewards.record_accrued(&rewards, &market_ids),
+                Err(e) => eprintln!("Accrued reward fetch failed: {}", e),
+            }
+        }
+        matched
+    }
+
+    /// Cluster executed trades by detector, category and size, alerting on newly anomalous clusters
+    pub fn analyze_trades(&mut self) -> ClusterReport {
+        let samples: Vec<TradeSample> = self.executor
+            .executed_trades
+            .iter()
+            .map(|t| {
+                let category = self.market_manager.get_market(&t.market_id).and_then(|m| m.category.as_deref());
+                TradeSample::from_execution(t, category)
+            })
+            .collect();
+
+        let report = cluster_trades(&samples);
+        for alert in self.trade_monitor.check(&report, self.clock.now()) {
+            eprintln!("🚨 Trade anomaly: {}", alert.message);
+        }
+        report
+    }
+
+    /// Route live WebSocket events from the API client into the market manager
+    pub async fn attach_live_feed(&mut self) {
+        if let Some(api) = &self.polymarket_api {
+            let rx = api.websocket().event_channel(4096).await;
+            self.market_manager.attach_event_channel(rx);
+            self.feed_state = Some(api.websocket().state());
+        }
+    }
+
+    /// Switch the market data source of a running bot
+    ///
+    /// Real sources load the Gamma markets into the cache; RealWebSocket also starts the live feed.
+    /// On error the bot keeps its current source.
+    pub async fn set_data_source(&mut self, source: DataSource) -> Result<(), String> {
+        let previous = self.market_manager.data_source;
+        if source == previous {
+            return Ok(());
+        }
+
+        let markets = if source.is_real() {
+            let api = match self.polymarket_api.take() {
+                Some(api) => api,
+                None => Self::build_api_client(&self.config)?,
+            };
+            let api = self.polymarket_api.insert(api);
+            if previous.is_real() {
+                None // Stesso universo di mercati, cambia solo la sorgente dei prezzi
+            } else {
+                Some(api.get_markets().await.map_err(|e| format!("Market fetch failed: {}", e))?)
+            }
+        } else {
+            None
+        };
+        if source.is_real() {
+            self.authenticate().await;
+        }
+
+        // Chiude la sessione WebSocket se non serve più
+        if previous == DataSource::RealWebSocket {
+            if let Some(api) = &self.polymarket_api {
+                api.websocket().shutdown();
+            }
+            if let Some(task) = self.feed_task.take() {
+                task.abort();
+            }
+            self.feed_state = None;
+        }
+
+        self.market_manager.set_data_source(source);
+        match source {
+            DataSource::Simulated => self.market_manager.fetch_markets().await?,
+            DataSource::RealRest | DataSource::RealWebSocket => {
+                for market in markets.into_iter().flatten() {
+                    self.market_manager.add_market(market);
+                }
+            }
+            // I mercati li carica lo scenario
+            DataSource::Scripted => {}
+        }
+        if let Err(e) = self.symbols.register_markets(self.market_manager.get_all_markets()) {
+            eprintln!("⚠️  Symbol registry: {}", e);
+        }
+
+        if source == DataSource::RealWebSocket {
+            self.attach_live_feed().await;
+            if let Some(api) = &self.polymarket_api {
+                let ws = api.websocket().clone();
+                self.feed_task = Some(tokio::spawn(async move {
+                    if let Err(e) = ws.connect().await {
+                        eprintln!("WebSocket connection error: {}", e);
+                    }
+                }));
+            }
+        }
+        self.sync_subscriptions().await;
+        self.refresh_rest_prices().await;
+
+        eprintln!("🔀 Data source: {:?} -> {:?} ({} markets)", previous, source, self.market_manager.markets.len());
+        Ok(())
+    }
+
+    /// Poll CLOB midpoints of the markets the refresh scheduler finds due, and full books of the due
+    /// pinned ones, when the source is real; returns how many tokens were updated
+    ///
+    /// Markets covered by the live WebSocket are only polled when their feed goes quiet, and the
+    /// whole cycle is skipped while the WebSocket event queue is backed up.
+    pub async fn refresh_rest_prices(&mut self) -> usize {
+        if !self.market_manager.data_source.is_real() {
+            return 0;
+        }
+        let Some(api) = &self.polymarket_api else { return 0 };
+
+        let now = self.clock.now();
+        let candidates = self.market_manager.refresh_candidates();
+        self.refresh_scheduler.retain(&candidates);
+        let due = self.refresh_scheduler.due(&candidates, self.market_manager.pending_events(), now);
+        if due.is_empty() {
+            return 0;
+        }
+        let token_ids = self.market_manager.token_ids_of(&due);
+        let mut updated = match api.get_midpoints(&token_ids).await {
+            Ok(prices) => self.market_manager.apply_rest_prices(&prices),
+            Err(e) => {
+                eprintln!("REST price refresh failed: {}", e);
+                0
+            }
+        };
+
+        // Watchlist: book completo (best ask e profondità) in un'unica richiesta batch
+        let pinned: Vec<String> = due.iter().filter(|id| self.market_manager.watchlist.is_pinned(id)).cloned().collect();
+        if !pinned.is_empty() {
+            match api.get_order_books(&self.market_manager.token_ids_of(&pinned)).await {
+                Ok(books) => updated += self.market_manager.apply_order_books(&books),
+                Err(e) => eprintln!("Watchlist order book refresh failed: {}", e),
+            }
+        }
+        self.refresh_scheduler.record_refresh(&due, now);
+        updated
+    }
+
+    /// Staleness of the markets' data against their REST refresh intervals
+    pub fn refresh_metrics(&self) -> RefreshMetrics {
+        self.refresh_scheduler.metrics(&self.market_manager.refresh_candidates(), self.clock.now())
+    }
+
+    /// Fetch the quotes of the contracts mapped for cross-venue detection from each venue adapter
+    ///
+    /// Returns the number of quotes stored.
+    pub async fn refresh_venue_quotes(&mut self) -> usize {
+        let Some(cross_venue) = &self.config.cross_venue else { return 0 };
+        let now = self.clock.now();
+        let mut stored = 0;
+        for adapter in &self.venue_adapters {
+            let market_ids: Vec<String> = cross_venue.contracts.iter()
+                .filter(|c| c.venue == adapter.venue())
+                .map(|c| c.venue_market_id.clone())
+                .collect();
+            if market_ids.is_empty() {
+                continue;
+            }
+            match adapter.fetch_quotes(&market_ids, now).await {
+                Ok(quotes) => {
+                    stored += quotes.len();
+                    quotes.into_iter().for_each(|quote| self.venue_quotes.update(quote));
+                }
+                Err(e) => eprintln!("⚠️  {} quotes unavailable: {}", adapter.venue(), e),
+            }
+        }
+        stored
+    }
+
+    /// Whether market data is trustworthy enough to trade (always true without a live feed)
+    pub fn feed_ready(&self) -> bool {
+        self.feed_state
+            .as_ref()
+            .is_none_or(|state| *state.borrow() == ConnectionState::Connected)
+    }
+
+    /// One step of the full pipeline: recoveries, feeds, detection and execution, exits, flushes
+    ///
+    /// Returns the step result (None when the step failed) and the PnL booked to capital during the step.
+    pub async fn pipeline_step(&mut self) -> (Option<StepResult>, f64) {
+        let mut booked: f64 = self.recover_executions().iter().map(|r| r.pnl).sum();
+        self.poll_news().await;
+        self.sync_server_clock().await;
+        self.reconcile_live_state().await;
+        self.refresh_accounts().await;
+        self.check_staleness().await;
+        self.resync_order_books().await;
+        self.refresh_rest_prices().await;
+        self.refresh_venue_quotes().await;
+        self.refresh_rewards().await;
+        self.record_fair_prices();
+        let executed_before = self.executor.executed_trades.len();
+        let step_timer = Stopwatch::start(&self.clock);
+        let result = match self.run_step().await {
+            Ok(result) => {
+                let latency_ms = step_timer.elapsed_ms();
+                self.persist_step(&result, &self.executor.executed_trades[executed_before..], latency_ms).await;
+                self.persist_prices().await;
+                Some(result)
+            }
+            Err(e) => {
+                eprintln!("Step error: {}", e);
+                None
+            }
+        };
+        booked += self.manage_stat_arb();
+        self.flush_opportunity_history().await;
+
+        // Il capitale resta impiegato solo per la durata dei trade eseguiti in questo step
+        let mut deployed = 0.0;
+        for trade in &self.executor.executed_trades[executed_before..] {
+            deployed += trade.total_investment;
+            booked += trade.profit;
+            self.capital_efficiency.record_trade(trade.total_investment, trade.profit);
+        }
+        self.capital_efficiency.sample_deployed(deployed);
+
+        if self.current_step.is_multiple_of(TRADE_ANALYSIS_INTERVAL_STEPS) {
+            self.analyze_trades();
+        }
+        (result, booked)
     }
 
     /// Run simulation for multiple steps
     pub async fn run_simulation(&mut self, num_steps: u64) -> SimulationResult {
         let mut results = Vec::new();
         
-        // Initialize markets
-        self.market_manager.fetch_markets().await.unwrap();
+        // Initialize markets (a real source loaded them in set_data_source)
+        if self.market_manager.data_source == DataSource::Simulated {
+            self.market_manager.fetch_markets().await.unwrap();
+        }
+        self.authenticate().await;
+        self.sync_subscriptions().await;
         
         for _ in 0..num_steps {
-            match self.run_step().await {
-                Ok(result) => results.push(result),
-                Err(e) => eprintln!("Step error: {}", e),
+            if let (Some(result), _) = self.pipeline_step().await {
+                results.push(result);
             }
         }
         
@@ -215,11 +1608,7 @@ impl HftArbitrageBot {
             .iter()
             .filter(|t| t.profit > 0.0)
             .count();
-        let win_rate = if total_trades > 0 {
-            successful as f64 / total_trades as f64
-        } else {
-            0.0
-        };
+        let metrics = TradeMetrics::from_trades(&self.executor.executed_trades);
         
         SimulationResult {
             num_steps,
@@ -229,21 +1618,35 @@ impl HftArbitrageBot {
             total_roi: (total_profit / self.initial_capital) * 100.0,
             total_trades,
             successful_trades: successful,
-            win_rate,
+            win_rate: metrics.win_rate,
+            avg_edge: metrics.avg_edge,
+            expectancy: metrics.expectancy,
+            capital_efficiency: self.capital_efficiency.metrics(self.initial_capital),
+            missed_edge: self.missed_edge.report(),
+            trade_clusters: self.analyze_trades(),
+            faults: self.executor.faults.as_ref().map(|f| f.stats).unwrap_or_default(),
+            queue: self.execution_queue.stats,
+            graph: self.graph_detector.prune_stats(),
+            refresh: self.refresh_metrics(),
             steps: results,
         }
     }
 }
 
+#[cfg(feature = "native")]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct StepResult {
     pub step: u64,
     pub opportunities: usize,
     pub trades: u32,
     pub profit: f64,
     pub capital: f64,
-    pub win_rate: f64,
+    pub win_rate: f64,   // Quota di trade in profitto (0-1) sugli ultimi TRADE_METRICS_WINDOW trade
+    pub avg_edge: f64,   // Profitto medio per dollaro investito, stessa finestra
+    pub expectancy: f64, // Profitto medio per trade (USD), stessa finestra
 }
 
+#[cfg(feature = "native")]
 pub struct SimulationResult {
     pub num_steps: u64,
     pub initial_capital: f64,
@@ -252,12 +1655,22 @@ pub struct SimulationResult {
     pub total_roi: f64,
     pub total_trades: usize,
     pub successful_trades: usize,
-    pub win_rate: f64,
+    pub win_rate: f64,   // Su tutti i trade della simulazione (0-1)
+    pub avg_edge: f64,
+    pub expectancy: f64,
+    pub capital_efficiency: CapitalEfficiencyMetrics,
+    pub missed_edge: MissedEdgeReport,
+    pub trade_clusters: ClusterReport,
+    pub faults: FaultStats, // Fault iniettati durante la simulazione (zero senza fault injection)
+    pub queue: QueueStats,  // Esecuzioni, ondate e attese della coda di esecuzione
+    pub graph: GraphPruneStats, // Mercati esclusi dal grafo dei prezzi per i suoi limiti
+    pub refresh: RefreshMetrics, // Refresh REST e staleness dei dati di mercato (vuoto su dati simulati)
     pub steps: Vec<StepResult>,
 }
 
 
 // API Server exports for dashboard
+#[cfg(feature = "native")]
 pub use api_server::{
     start_api_server, AppState, BotState, SimulatedTrade, MarketInfo,
     LiveData, ArbitrageOpportunity, BotControlRequest, ApiResponse
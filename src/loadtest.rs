@@ -0,0 +1,322 @@
+//! Load test module
+//!
+//! Implements:
+//! 1. Synthetic markets and a producer of price updates at a configurable rate
+//! 2. End-to-end run of the trading pipeline fed through the live event channel
+//! 3. Capacity report with throughput, p99 tick latency and an estimate of the supported markets
+
+use crate::polymarket_api::{WsLevelChange, WsMarketEvent, WsPriceChange};
+use crate::types::{BotConfig, MarketData, TokenPair};
+use crate::{HftArbitrageBot, Stopwatch};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+/// Period of the synthetic producer (updates due in each period are sent together)
+const PRODUCER_TICK_MS: u64 = 5;
+
+/// Share of the target rate that must be processed for a run to be within budget
+const MIN_THROUGHPUT_RATIO: f64 = 0.95;
+
+/// Synthetic load parameters
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoadTestConfig {
+    pub markets: usize,
+    pub updates_per_market_per_sec: f64,
+    pub duration_secs: u64,
+    pub tick_interval_ms: u64,     // Intervallo tra due step della pipeline
+    pub latency_budget_ms: f64,    // p99 massimo accettato tra aggiornamento e fine dello step
+    pub channel_capacity: usize,   // Come il canale del feed live
+    pub mispricing_rate: f64,      // Frazione di aggiornamenti che crea un'opportunità YES/NO
+}
+
+impl Default for LoadTestConfig {
+    fn default() -> Self {
+        Self {
+            markets: 100,
+            updates_per_market_per_sec: 2.0,
+            duration_secs: 10,
+            tick_interval_ms: 50,
+            latency_budget_ms: 200.0,
+            channel_capacity: 4096,
+            mispricing_rate: 0.01,
+        }
+    }
+}
+
+impl LoadTestConfig {
+    /// Parse `--markets 100,500 --rate 2 --duration 10 --tick-ms 50 --budget-ms 200`
+    ///
+    /// Returns the base configuration and the market counts to sweep.
+    pub fn from_args(args: &[String]) -> Result<(Self, Vec<usize>), String> {
+        let mut config = Self::default();
+        let mut counts = vec![config.markets];
+        let mut iter = args.iter();
+
+        while let Some(flag) = iter.next() {
+            let value = iter.next().ok_or_else(|| format!("Missing value for {}", flag))?;
+            let invalid = || format!("Invalid value for {}: {}", flag, value);
+            match flag.as_str() {
+                "--markets" => {
+                    counts = value
+                        .split(',')
+                        .map(|v| v.trim().parse::<usize>().map_err(|_| invalid()))
+                        .collect::<Result<_, _>>()?;
+                }
+                "--rate" => config.updates_per_market_per_sec = value.parse().map_err(|_| invalid())?,
+                "--duration" => config.duration_secs = value.parse().map_err(|_| invalid())?,
+                "--tick-ms" => config.tick_interval_ms = value.parse().map_err(|_| invalid())?,
+                "--budget-ms" => config.latency_budget_ms = value.parse().map_err(|_| invalid())?,
+                _ => return Err(format!("Unknown option {}", flag)),
+            }
+        }
+
+        if counts.is_empty() || counts.contains(&0) {
+            return Err("--markets needs positive counts".to_string());
+        }
+        if config.updates_per_market_per_sec <= 0.0 || config.duration_secs == 0 || config.tick_interval_ms == 0 {
+            return Err("rate, duration and tick interval must be positive".to_string());
+        }
+        Ok((config, counts))
+    }
+
+    /// Total updates per second across all markets
+    pub fn target_rate(&self) -> f64 {
+        self.markets as f64 * self.updates_per_market_per_sec
+    }
+}
+
+/// Result of one load-test run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapacityReport {
+    pub markets: usize,
+    pub target_updates_per_sec: f64,
+    pub processed_updates: u64,
+    pub throughput_updates_per_sec: f64,
+    pub backlog_updates: u64, // Generati ma mai consegnati alla pipeline entro la fine del test
+    pub steps: u64,
+    pub p50_tick_latency_ms: f64,
+    pub p99_tick_latency_ms: f64,
+    pub max_tick_latency_ms: f64,
+    pub p99_step_ms: f64,
+    pub within_budget: bool,
+    pub estimated_max_markets: usize, // Estrapolazione lineare del costo per mercato dello step
+}
+
+/// Markets with CLOB tokens, priced without arbitrage
+pub fn synthetic_markets(count: usize) -> Vec<MarketData> {
+    let mut rng = rand::thread_rng();
+    (0..count)
+        .map(|i| {
+            let yes_price = rng.gen_range(0.1..0.9);
+            MarketData {
+                id: format!("load_{}", i),
+                question: format!("Synthetic market {}?", i),
+                yes_price,
+                no_price: 1.0 - yes_price + 0.02,
+                yes_liquidity: 50_000.0,
+                no_liquidity: 50_000.0,
+                volume_24h: 100_000.0,
+                tokens: Some(TokenPair {
+                    yes_token_id: format!("load_{}_yes", i),
+                    no_token_id: format!("load_{}_no", i),
+                    condition_id: None,
+                }),
+                ..MarketData::default()
+            }
+        })
+        .collect()
+}
+
+/// Best-ask update of one token
+fn price_update(asset_id: String, ask: f64) -> WsMarketEvent {
+    WsMarketEvent::PriceChange(WsPriceChange {
+        asset_id,
+        market: String::new(),
+        changes: vec![WsLevelChange {
+            price: ask,
+            side: "SELL".to_string(),
+            size: 1_000.0,
+            best_bid: Some((ask - 0.01).max(0.001)),
+            best_ask: Some(ask),
+        }],
+        timestamp: 0,
+        hash: None,
+    })
+}
+
+/// Send random-walk updates (one token each) of `markets` until `deadline`, each stamped with its emission time
+async fn produce_updates(markets: Vec<MarketData>, config: LoadTestConfig, deadline: Instant, tx: mpsc::UnboundedSender<(Instant, WsMarketEvent)>) {
+    let mut prices: Vec<f64> = markets.iter().map(|m| m.yes_price).collect();
+    let started = Instant::now();
+    let mut sent: u64 = 0;
+
+    while Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_millis(PRODUCER_TICK_MS)).await;
+        let due = (started.elapsed().as_secs_f64() * config.target_rate()) as u64;
+
+        let mut rng = rand::thread_rng();
+        let mut batch = Vec::with_capacity(due.saturating_sub(sent) as usize);
+        while sent < due {
+            let i = rng.gen_range(0..markets.len());
+            let tokens = markets[i].tokens.as_ref().unwrap();
+            if rng.gen_bool(0.5) {
+                prices[i] = (prices[i] + rng.gen_range(-0.005..0.005)).clamp(0.05, 0.95);
+                batch.push(price_update(tokens.yes_token_id.clone(), prices[i]));
+            } else {
+                // Il NO resta sopra il complemento, salvo i rari mispricing
+                let no_ask = if rng.gen_bool(config.mispricing_rate.clamp(0.0, 1.0)) {
+                    1.0 - prices[i] - rng.gen_range(0.01..0.04)
+                } else {
+                    1.0 - prices[i] + 0.02
+                };
+                batch.push(price_update(tokens.no_token_id.clone(), no_ask.clamp(0.01, 0.99)));
+            }
+            sent += 1;
+        }
+
+        let now = Instant::now();
+        for event in batch {
+            if tx.send((now, event)).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// Value at quantile `q` of sorted samples
+fn percentile(sorted: &[f64], q: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = ((sorted.len() as f64 * q).ceil() as usize).clamp(1, sorted.len());
+    sorted[rank - 1]
+}
+
+/// Run the pipeline against synthetic load for `config.duration_secs`
+///
+/// Updates go through the same channel as the live WebSocket feed and are applied by
+/// `run_step`; tick latency is measured from emission to the end of the step that used them.
+pub async fn run_load_test(config: &LoadTestConfig) -> CapacityReport {
+    let markets = synthetic_markets(config.markets.max(1));
+    let mut bot = HftArbitrageBot::new(BotConfig::default());
+    for market in &markets {
+        bot.market_manager.add_market(market.clone());
+    }
+    let (feed_tx, feed_rx) = mpsc::channel(config.channel_capacity.max(1));
+    bot.market_manager.attach_event_channel(feed_rx);
+
+    let started = Instant::now();
+    let deadline = started + Duration::from_secs(config.duration_secs);
+    let (producer_tx, mut producer_rx) = mpsc::unbounded_channel();
+    let producer = tokio::spawn(produce_updates(markets, config.clone(), deadline, producer_tx));
+
+    let interval = Duration::from_millis(config.tick_interval_ms.max(1));
+    let mut latencies: Vec<f64> = Vec::new();
+    let mut step_times: Vec<f64> = Vec::new();
+    let mut processed: u64 = 0;
+
+    while Instant::now() < deadline {
+        let next_tick = Instant::now() + interval;
+
+        // Non più aggiornamenti per step della capacità del canale: il resto attende, come nel feed reale
+        let mut emitted = Vec::new();
+        while emitted.len() < config.channel_capacity.max(1) {
+            let Ok((at, event)) = producer_rx.try_recv() else { break };
+            if feed_tx.try_send(event).is_err() {
+                break;
+            }
+            emitted.push(at);
+        }
+
+        let stopwatch = Stopwatch::start(&bot.clock);
+        if let Err(e) = bot.run_step().await {
+            eprintln!("Load test step error: {}", e);
+        }
+        step_times.push(stopwatch.elapsed().as_secs_f64() * 1000.0);
+
+        let done = Instant::now();
+        latencies.extend(emitted.iter().map(|at| (done - *at).as_secs_f64() * 1000.0));
+        processed += emitted.len() as u64;
+
+        tokio::time::sleep_until(next_tick.into()).await;
+    }
+
+    let _ = producer.await;
+    let mut backlog: u64 = 0;
+    while producer_rx.try_recv().is_ok() {
+        backlog += 1;
+    }
+
+    latencies.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    step_times.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let elapsed = started.elapsed().as_secs_f64();
+    let p99_tick = percentile(&latencies, 0.99);
+    let p99_step = percentile(&step_times, 0.99);
+    let throughput = processed as f64 / elapsed.max(f64::EPSILON);
+
+    // Lo step deve stare nell'intervallo (per reggere il flusso) e nel budget oltre l'attesa del tick
+    let interval_ms = interval.as_secs_f64() * 1000.0;
+    let per_market_ms = p99_step / config.markets.max(1) as f64;
+    let step_allowance = interval_ms.min(config.latency_budget_ms - interval_ms).max(0.0);
+    let estimated_max_markets = if per_market_ms > 0.0 {
+        (step_allowance / per_market_ms) as usize
+    } else {
+        usize::MAX
+    };
+
+    CapacityReport {
+        markets: config.markets,
+        target_updates_per_sec: config.target_rate(),
+        processed_updates: processed,
+        throughput_updates_per_sec: throughput,
+        backlog_updates: backlog,
+        steps: step_times.len() as u64,
+        p50_tick_latency_ms: percentile(&latencies, 0.50),
+        p99_tick_latency_ms: p99_tick,
+        max_tick_latency_ms: latencies.last().copied().unwrap_or(0.0),
+        p99_step_ms: p99_step,
+        within_budget: p99_tick <= config.latency_budget_ms && throughput >= config.target_rate() * MIN_THROUGHPUT_RATIO,
+        estimated_max_markets,
+    }
+}
+
+/// Run the load test once per market count; reports are in the order of `market_counts`
+pub async fn run_capacity_sweep(config: &LoadTestConfig, market_counts: &[usize]) -> Vec<CapacityReport> {
+    let mut reports = Vec::new();
+    for &markets in market_counts {
+        let run = LoadTestConfig { markets, ..config.clone() };
+        reports.push(run_load_test(&run).await);
+    }
+    reports
+}
+
+/// Largest tested market count that stayed within budget
+pub fn max_markets_within_budget(reports: &[CapacityReport]) -> Option<usize> {
+    reports.iter().filter(|r| r.within_budget).map(|r| r.markets).max()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_load_test_reports_throughput() {
+        let args: Vec<String> = ["--markets", "10,20", "--rate", "20", "--duration", "1", "--budget-ms", "500"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let (config, counts) = LoadTestConfig::from_args(&args).unwrap();
+        assert_eq!(counts, vec![10, 20]);
+        assert!(LoadTestConfig::from_args(&["--markets".to_string(), "0".to_string()]).is_err());
+
+        let report = run_load_test(&LoadTestConfig { markets: counts[0], ..config }).await;
+        assert_eq!(report.target_updates_per_sec, 200.0);
+        assert!(report.steps > 0);
+        assert!(report.processed_updates > 0);
+        assert!(report.p50_tick_latency_ms <= report.p99_tick_latency_ms);
+        assert!(report.p99_tick_latency_ms <= report.max_tick_latency_ms);
+        assert_eq!(max_markets_within_budget(&[]), None);
+    }
+}
@@ -2,10 +2,24 @@
 //! Dashboard HFT Polymarket - Main Entry Point
 //! Avvia il server API e la dashboard professionale
 
-use polymarket_arb_hft::api_server;
+use polymarket_arb_hft::{api_server, max_markets_within_budget, run_capacity_sweep, run_demo, run_soak, DemoConfig, LoadTestConfig, SoakConfig};
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
+    // `load-test --markets 100,500,1000 --rate 2 --duration 10`: misura la capacità invece di avviare il server
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("load-test") {
+        return run_load_test(&args[1..]).await;
+    }
+    // `soak --hours 72 --step-ms 1000 --check-secs 300`: pipeline simulata per giorni con controlli periodici
+    if args.first().map(String::as_str) == Some("soak") {
+        return run_soak_test(&args[1..]).await;
+    }
+    // `demo --port 8080 --step-ms 5000 --once`: scenario scritto con trade deterministici al posto del bot casuale
+    if args.first().map(String::as_str) == Some("demo") {
+        return run_demo_scenario(&args[1..]).await;
+    }
+
     println!("🚀 Avvio Dashboard HFT Polymarket");
     println!("{}", String::from("=").repeat(50));
     println!("📡 API Server: http://0.0.0.0:8080");
@@ -21,3 +35,59 @@ async fn main() -> std::io::Result<()> {
 
     api_server::start_api_server(8080).await
 }
+
+/// Synthetic load sweep with a capacity report on stdout
+async fn run_load_test(args: &[String]) -> std::io::Result<()> {
+    let (config, counts) = LoadTestConfig::from_args(args)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+
+    println!("🧪 Load test: {:?} mercati, {} aggiornamenti/s per mercato, {}s per run", counts, config.updates_per_market_per_sec, config.duration_secs);
+    let reports = run_capacity_sweep(&config, &counts).await;
+
+    println!("{:>8} {:>12} {:>12} {:>10} {:>10} {:>10} {:>8}", "markets", "target/s", "done/s", "p50 ms", "p99 ms", "step p99", "budget");
+    for r in &reports {
+        println!(
+            "{:>8} {:>12.0} {:>12.0} {:>10.1} {:>10.1} {:>10.1} {:>8}",
+            r.markets, r.target_updates_per_sec, r.throughput_updates_per_sec,
+            r.p50_tick_latency_ms, r.p99_tick_latency_ms, r.p99_step_ms,
+            if r.within_budget { "ok" } else { "over" },
+        );
+    }
+    match max_markets_within_budget(&reports) {
+        Some(markets) => println!("✅ {} mercati entro il budget di {} ms (p99)", markets, config.latency_budget_ms),
+        None => println!("⚠️  Nessuna configurazione entro il budget di {} ms (p99)", config.latency_budget_ms),
+    }
+    if let Some(last) = reports.last() {
+        println!("📈 Stima lineare: ~{} mercati a {} aggiornamenti/s", last.estimated_max_markets, config.updates_per_market_per_sec);
+    }
+    println!("{}", serde_json::to_string_pretty(&reports).unwrap_or_default());
+    Ok(())
+}
+
+/// Soak run on simulated markets; exits with an error when an invariant was broken
+async fn run_soak_test(args: &[String]) -> std::io::Result<()> {
+    let config = SoakConfig::from_args(args)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+
+    println!("🧪 Soak test: {:.1} ore, uno step ogni {} ms, controlli ogni {}s", config.duration_secs as f64 / 3600.0, config.step_interval_ms, config.check_interval_secs);
+    println!("📝 Report: {}", config.report_path.display());
+    let report = run_soak(&config).await;
+
+    println!("{} step ({} in errore), {} controlli, {} violazioni", report.steps, report.step_errors, report.checks, report.violation_count);
+    if report.healthy() {
+        println!("✅ Nessun invariante violato");
+        Ok(())
+    } else {
+        Err(std::io::Error::other(format!("{} invariant violations, see {}", report.violation_count, config.report_path.display())))
+    }
+}
+
+/// Dashboard driven by the scripted demo scenario
+async fn run_demo_scenario(args: &[String]) -> std::io::Result<()> {
+    let config = DemoConfig::from_args(args)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+
+    println!("🎬 Demo: scenario scritto, un passo ogni {} ms{}", config.step_interval_ms, if config.repeat { ", in loop" } else { "" });
+    println!("🌐 Dashboard: http://localhost:{}", config.port);
+    run_demo(config).await
+}
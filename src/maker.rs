@@ -0,0 +1,292 @@
+//! Maker-side arbitrage module
+//!
+//! Implements:
+//! 1. YES and NO bids posted one tick inside the spread (above the best bid, below the best ask)
+//!    instead of crossing the asks, from the local books or the midpoint and CLOB spread
+//! 2. Fill probability of each bid over the quote horizon, from how often the market's quotes move
+//! 3. Expected profit per pair: both bids filled, or one filled and the other leg completed by
+//!    crossing its ask, plus the maker rebates of the fills and the liquidity rewards of the
+//!    resting bids, net of gas and relayer costs
+
+use crate::adverse_selection::QuoteActivity;
+use crate::arbitrage::TradingCosts;
+use crate::detector::{Detector, MarketView};
+use crate::types::*;
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+
+/// Maker-side YES/NO detection parameters
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MakerArbConfig {
+    pub min_expected_profit: f64,  // Profitto atteso minimo per coppia, dopo probabilità di fill e costi
+    pub min_fill_probability: f64, // Probabilità minima che si riempiano entrambi i bid
+    pub quote_size: f64,           // Share per bid
+    pub horizon_secs: f64,         // Permanenza degli ordini nel book prima di cancellarli
+    pub tick_size: f64,            // Miglioramento del bid migliore
+    pub taker_share: f64,          // Quota degli aggiornamenti di prezzo dovuti a taker che vendono sul bid
+    pub default_fill_rate: f64,    // Fill al secondo senza storico prezzi
+    pub maker_rebate_rate: f64,    // Rebate maker per share, applicato a min(p, 1 - p) come la fee taker
+    pub activity_window_secs: i64, // Storico prezzi su cui misurare la frequenza degli aggiornamenti
+}
+
+impl Default for MakerArbConfig {
+    fn default() -> Self {
+        Self {
+            min_expected_profit: 0.005,
+            min_fill_probability: 0.25,
+            quote_size: 100.0,
+            horizon_secs: 60.0,
+            tick_size: 0.01,
+            taker_share: 0.5,
+            default_fill_rate: 0.01,
+            maker_rebate_rate: 0.0,
+            activity_window_secs: 300,
+        }
+    }
+}
+
+/// Best bid and ask of one outcome token
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Touch {
+    bid: f64,
+    ask: f64,
+}
+
+/// Bid posted inside the spread of one outcome token
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MakerQuote {
+    pub price: f64,
+    pub ask: f64,              // Ask da attraversare se l'altro leg resta scoperto
+    pub offset: f64,           // Distanza dal midpoint, per i reward
+    pub fill_probability: f64, // Entro l'orizzonte
+}
+
+/// Expected value of a pair of maker bids, per pair of shares
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MakerEstimate {
+    pub yes: MakerQuote,
+    pub no: MakerQuote,
+    pub both_filled: f64, // Probabilità che si riempiano entrambi i bid
+    pub edge: f64,        // 1 - somma dei bid, con entrambi i fill
+    pub rebate: f64,
+    pub reward: f64,
+    pub expected_profit: f64,
+}
+
+/// Detector of YES/NO pairs whose edge is captured by resting bids rather than crossing
+#[derive(Debug, Clone)]
+pub struct MakerArbDetector {
+    pub config: MakerArbConfig,
+    pub costs: TradingCosts,
+}
+
+impl MakerArbDetector {
+    pub fn new(config: MakerArbConfig, costs: TradingCosts) -> Self {
+        Self { config, costs }
+    }
+
+    /// Touch of both tokens: the local books when held, else the midpoints widened by the CLOB spread
+    fn touches(market: &MarketData, view: &MarketView<'_>) -> Option<(Touch, Touch)> {
+        let books = market.tokens.as_ref().and_then(|t| view.order_books.token_books(t));
+        if let Some((yes_book, no_book)) = books {
+            let touch = |book: &crate::orderbook::LocalOrderBook| Some(Touch { bid: book.best_bid()?.price, ask: book.best_ask()?.price });
+            return Some((touch(yes_book)?, touch(no_book)?));
+        }
+        // Lo spread noto è quello del YES; sul NO, complementare, si assume lo stesso
+        let half = market.spread? / 2.0;
+        let touch = |mid: f64| Touch { bid: mid - half, ask: mid + half };
+        Some((touch(market.yes_price), touch(market.no_price)))
+    }
+
+    /// Fills per second hitting a resting bid on the market
+    fn fill_rate(&self, market_id: &str, view: &MarketView<'_>) -> f64 {
+        let window = Duration::seconds(self.config.activity_window_secs.max(1));
+        view.price_history.get(market_id)
+            .and_then(|history| QuoteActivity::measure(history, view.now, window))
+            .map_or(self.config.default_fill_rate, |activity| activity.updates_per_sec * self.config.taker_share.clamp(0.0, 1.0))
+    }
+
+    /// Bid one tick above the best bid; None when the spread leaves no price strictly inside
+    fn quote(&self, touch: Touch, fill_rate: f64) -> Option<MakerQuote> {
+        let tick = self.config.tick_size;
+        if tick <= 0.0 {
+            return None;
+        }
+        let price = ((touch.bid / tick + 1e-9).floor() + 1.0) * tick;
+        let price = (price / tick).round() * tick;
+        if price >= touch.ask - 1e-9 || price <= 0.0 || price >= 1.0 {
+            return None;
+        }
+        // Arrivi di taker come processo di Poisson: almeno uno entro l'orizzonte
+        let fill_probability = 1.0 - (-fill_rate.max(0.0) * self.config.horizon_secs.max(0.0)).exp();
+        Some(MakerQuote { price, ask: touch.ask, offset: ((touch.bid + touch.ask) / 2.0 - price).abs(), fill_probability })
+    }
+
+    /// Maker rebate earned per share filled at `price`
+    fn rebate_per_share(&self, price: f64) -> f64 {
+        self.config.maker_rebate_rate * price.min(1.0 - price).max(0.0)
+    }
+
+    /// Expected value of bidding both tokens of `market`
+    pub fn estimate(&self, market: &MarketData, view: &MarketView<'_>) -> Option<MakerEstimate> {
+        let (yes_touch, no_touch) = Self::touches(market, view)?;
+        let rate = self.fill_rate(&market.id, view);
+        let (yes, no) = (self.quote(yes_touch, rate)?, self.quote(no_touch, rate)?);
+        let (p_yes, p_no) = (yes.fill_probability, no.fill_probability);
+        let size = self.config.quote_size.max(0.0);
+
+        let edge = 1.0 - yes.price - no.price;
+        // Un solo bid riempito: l'altro leg si completa attraversando l'ask, pagando la fee taker
+        let complete = |filled: f64, ask: f64| 1.0 - filled - ask - self.costs.fee_per_share(ask);
+        let trading = p_yes * p_no * edge
+            + p_yes * (1.0 - p_no) * complete(yes.price, no.ask)
+            + (1.0 - p_yes) * p_no * complete(no.price, yes.ask);
+        let rebate = p_yes * self.rebate_per_share(yes.price) + p_no * self.rebate_per_share(no.price);
+        // Reward dei due bid in attesa, stimati sul più lontano dal midpoint
+        let reward = if size > 0.0 {
+            let competing = market.yes_liquidity + market.no_liquidity;
+            view.rewards.estimate_reward_at(&market.id, 2.0 * size, yes.offset.max(no.offset), competing, view.now) / size
+        } else {
+            0.0
+        };
+        // Gli ordini maker non pagano fee: restano gas e relayer ripartiti sulla size
+        let maker_costs = TradingCosts { taker_fee_rate: 0.0, ..self.costs.clone() };
+        let fixed = maker_costs.basket_cost(&[yes.price, no.price], size);
+
+        Some(MakerEstimate {
+            yes,
+            no,
+            both_filled: p_yes * p_no,
+            edge,
+            rebate,
+            reward,
+            expected_profit: trading + rebate + reward - fixed,
+        })
+    }
+
+    /// Opportunity of bidding both tokens of `market`, when its expected profit and fill probability clear the thresholds
+    pub fn detect(&self, market: &MarketData, view: &MarketView<'_>, min_profit: f64) -> Option<ArbitrageOpportunity> {
+        let estimate = self.estimate(market, view)?;
+        if estimate.edge <= 0.0
+            || estimate.both_filled < self.config.min_fill_probability
+            || estimate.expected_profit < min_profit
+        {
+            return None;
+        }
+
+        let sum = estimate.yes.price + estimate.no.price;
+        let leg = |token_type: TokenType, price: f64, token_id: Option<String>| ArbitrageLeg {
+            market_id: market.id.clone(),
+            token_type,
+            direction: Direction::Buy,
+            price,
+            quantity: self.config.quote_size,
+            token_id,
+            venue: None,
+        };
+        Some(ArbitrageOpportunity {
+            market_id: market.id.clone(),
+            question: market.question.clone(),
+            arb_type: ArbType::YesNoMaker,
+            profit: estimate.expected_profit,
+            roi_pct: estimate.expected_profit / sum * 100.0,
+            confidence: estimate.both_filled,
+            yes_price: estimate.yes.price,
+            no_price: estimate.no.price,
+            sum_price: sum,
+            liquidity: market.yes_liquidity + market.no_liquidity,
+            timestamp: view.now,
+            legs: Some(vec![
+                leg(TokenType::Yes, estimate.yes.price, market.tokens.as_ref().map(|t| t.yes_token_id.clone())),
+                leg(TokenType::No, estimate.no.price, market.tokens.as_ref().map(|t| t.no_token_id.clone())),
+            ]),
+            path: None,
+            maker: Some(estimate),
+        })
+    }
+}
+
+impl Detector for MakerArbDetector {
+    fn name(&self) -> &str {
+        "maker_yes_no"
+    }
+
+    fn scan(&self, view: &MarketView<'_>) -> Vec<ArbitrageOpportunity> {
+        // Soglia rilassata sui mercati pinnati, come per gli arbitraggi taker
+        view.markets.iter()
+            .filter_map(|m| self.detect(m, view, view.watchlist.min_profit_for(&m.id, self.config.min_expected_profit)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::market::{PriceSnapshot, Watchlist};
+    use crate::orderbook::OrderBookStore;
+    use crate::rewards::{RewardBook, RewardConfig};
+    use chrono::{DateTime, Utc};
+    use fxhash::FxHashMap;
+
+    fn view<'a>(
+        watchlist: &'a Watchlist,
+        books: &'a OrderBookStore,
+        history: &'a FxHashMap<String, Vec<PriceSnapshot>>,
+        rewards: &'a RewardBook,
+        now: DateTime<Utc>,
+    ) -> MarketView<'a> {
+        MarketView { markets: &[], events: &[], watchlist, order_books: books, price_history: history, relations: &[], triangles: &[], rewards, now }
+    }
+
+    #[test]
+    fn test_bids_inside_spread_priced_by_fill_probability_and_rewards() {
+        let now = Utc::now();
+        // Midpoint 0.50 / 0.49 con spread di 4 centesimi: ask a 0.52 + 0.51, nessun arbitraggio taker
+        let market = MarketData {
+            id: "m1".to_string(),
+            yes_price: 0.50,
+            no_price: 0.49,
+            spread: Some(0.04),
+            yes_liquidity: 1000.0,
+            no_liquidity: 1000.0,
+            ..MarketData::default()
+        };
+        // Un aggiornamento al secondo negli ultimi 30 secondi
+        let active: FxHashMap<String, Vec<PriceSnapshot>> = [(
+            "m1".to_string(),
+            (0..=30).map(|i| PriceSnapshot { timestamp: now - Duration::seconds(30 - i), yes_price: 0.50, no_price: 0.49, volume: 0.0 }).collect(),
+        )].into_iter().collect();
+        let quiet = FxHashMap::default();
+        let (watchlist, books, mut rewards) = (Watchlist::default(), OrderBookStore::default(), RewardBook::new());
+        let detector = MakerArbDetector::new(MakerArbConfig::default(), TradingCosts::default());
+
+        let markets = [market.clone()];
+        let found = detector.scan(&MarketView { markets: &markets, ..view(&watchlist, &books, &active, &rewards, now) });
+        assert_eq!(found.len(), 1);
+        let opportunity = &found[0];
+        assert_eq!(opportunity.arb_type, ArbType::YesNoMaker);
+        // Bid migliori 0.48 e 0.47: si posta un tick sopra
+        assert!((opportunity.yes_price - 0.49).abs() < 1e-9 && (opportunity.no_price - 0.48).abs() < 1e-9);
+        // Fill quasi certi in 60 s con 0.5 taker al secondo: profitto vicino ai 3 centesimi del margine
+        assert!(opportunity.confidence > 0.99);
+        assert!(opportunity.profit > 0.029 && opportunity.profit < 0.03, "{}", opportunity.profit);
+
+        // Mercato fermo: fill poco probabili, l'opportunità non passa
+        assert!(detector.scan(&MarketView { markets: &markets, ..view(&watchlist, &books, &quiet, &rewards, now) }).is_empty());
+        let without = detector.estimate(&market, &view(&watchlist, &books, &quiet, &rewards, now)).unwrap();
+        assert!(without.both_filled < detector.config.min_fill_probability);
+
+        // Un programma di reward sul mercato si somma al profitto atteso
+        let market_ids: FxHashMap<String, String> = [("0xm1".to_string(), "m1".to_string())].into_iter().collect();
+        let program = RewardConfig { condition_id: "0xm1".to_string(), rate_per_day: 240.0, max_spread: 0.03, min_size: 10.0, start_date: None, end_date: None };
+        rewards.set_configs(vec![program], &market_ids, now);
+        let with = detector.estimate(&market, &view(&watchlist, &books, &quiet, &rewards, now)).unwrap();
+        assert!(with.reward > 0.0);
+        assert!((with.expected_profit - without.expected_profit - with.reward).abs() < 1e-12);
+
+        // Spread di un tick (bid 0.50, ask 0.51): nessun prezzo dentro lo spread
+        let tight = [MarketData { yes_price: 0.505, no_price: 0.485, spread: Some(0.01), ..market }];
+        assert!(detector.scan(&MarketView { markets: &tight, ..view(&watchlist, &books, &active, &rewards, now) }).is_empty());
+    }
+}
@@ -5,19 +5,72 @@
 //! 2. Price tracking and caching
 //! 3. Liquidity monitoring
 //! 4. WebSocket connection for real-time data
+//! 5. Watchlist of pinned markets with priority scanning
+//! 6. Live price updates from WebSocket market events
+//! 7. Multi-outcome (negRisk) events grouping cached markets
+//! 8. Stale markets (silent feed) excluded from scanning until refreshed
+//! 9. Data source (simulated, REST, WebSocket) switchable at runtime
+//! 10. Local order books reconciled from snapshots and deltas
+//! 11. Onboarding pipeline gating newly discovered markets before they are scanned
+//! 12. REST refresh candidates by watchlist and WebSocket coverage, and the pending event backlog
 
+#[cfg(feature = "native")]
+use crate::onboarding::MarketOnboarding;
+#[cfg(feature = "native")]
+use crate::orderbook::{LocalOrderBook, OrderBookStore};
+#[cfg(feature = "native")]
+use crate::refresh::{RefreshCandidate, RefreshPriority};
+#[cfg(feature = "native")]
+use crate::polymarket_api::{WsBookEvent, WsMarketEvent};
+#[cfg(feature = "native")]
 use crate::types::*;
+use crate::versioning::{load_document, to_document, Schema};
+use chrono::{DateTime, Utc};
 use fxhash::FxHashMap;
+#[cfg(feature = "native")]
+use fxhash::FxHashSet;
+#[cfg(feature = "native")]
 use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+#[cfg(feature = "native")]
+use tokio::sync::mpsc;
+
+/// Where market prices come from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DataSource {
+    #[default]
+    Simulated,     // Random walk su mercati generati
+    RealRest,      // Midpoint CLOB letti periodicamente via REST
+    RealWebSocket, // Eventi del feed WebSocket
+    Scripted,      // Prezzi impostati da uno scenario (demo), senza random walk
+}
+
+impl DataSource {
+    pub fn is_real(&self) -> bool {
+        !matches!(self, DataSource::Simulated | DataSource::Scripted)
+    }
+}
 
 /// Market manager
+#[cfg(feature = "native")]
 pub struct MarketManager {
     pub markets: FxHashMap<String, MarketData>,
     pub price_history: FxHashMap<String, Vec<PriceSnapshot>>,
     pub config: MarketConfig,
     pub websocket_connected: bool,
+    pub watchlist: Watchlist,
+    pub asset_index: FxHashMap<String, (String, TokenType)>, // asset_id -> (market_id, outcome)
+    pub events: FxHashMap<String, EventData>, // Eventi multi-esito, i cui mercati stanno in `markets`
+    pub stale: FxHashSet<String>, // Mercati con feed silenzioso: non scansionati finché non tornano prezzi
+    pub data_source: DataSource,
+    pub order_books: OrderBookStore, // Libri locali per asset (snapshot + delta)
+    pub onboarding: Option<MarketOnboarding>, // Controlli di idoneità dei nuovi mercati; None = tutti negoziabili
+    event_rx: Option<mpsc::Receiver<WsMarketEvent>>,
 }
 
+#[cfg(feature = "native")]
 impl MarketManager {
     pub fn new(min_liquidity: f64, max_markets: usize) -> Self {
         Self {
@@ -27,8 +80,18 @@ impl MarketManager {
                 min_liquidity,
                 max_markets,
                 update_interval_ms: 1000,
+                scan_interval_steps: 1,
+                max_ws_subscriptions: 100,
             },
             websocket_connected: false,
+            watchlist: Watchlist::default(),
+            asset_index: FxHashMap::default(),
+            events: FxHashMap::default(),
+            stale: FxHashSet::default(),
+            data_source: DataSource::Simulated,
+            order_books: OrderBookStore::new(),
+            onboarding: None,
+            event_rx: None,
         }
     }
 
@@ -47,33 +110,343 @@ impl MarketManager {
 
     /// Add market to cache
     pub fn add_market(&mut self, market: MarketData) {
-        let market_id = market.id.clone();
-        
         // Add price snapshot to history
-        let snapshot = PriceSnapshot {
-            timestamp: market.timestamp,
-            yes_price: market.yes_price,
-            no_price: market.no_price,
-            volume: market.volume_24h,
-        };
-        
-        self.price_history
-            .entry(market_id.clone())
-            .or_insert_with(Vec::new)
-            .push(snapshot);
+        push_snapshot(&mut self.price_history, &market);
+
+        // Indicizza i token CLOB per instradare gli eventi WebSocket
+        if let Some(tokens) = &market.tokens {
+            self.register_asset(&tokens.yes_token_id, &market.id, TokenType::Yes);
+            self.register_asset(&tokens.no_token_id, &market.id, TokenType::No);
+        }
         
-        // Keep only last 1000 snapshots
-        if let Some(history) = self.price_history.get_mut(&market_id) {
-            if history.len() > 1000 {
-                history.remove(0);
+        self.markets.insert(market.id.clone(), market);
+    }
+
+    /// Add a multi-outcome event and cache each of its markets
+    pub fn add_event(&mut self, mut event: EventData) {
+        for market in &mut event.markets {
+            market.event_id = Some(event.id.clone());
+            self.add_market(market.clone());
+        }
+        self.events.insert(event.id.clone(), event);
+    }
+
+    /// Cached markets grouped by event id (markets outside any event are left out)
+    pub fn markets_by_event(&self) -> FxHashMap<String, Vec<MarketData>> {
+        let mut groups: FxHashMap<String, Vec<MarketData>> = FxHashMap::default();
+        for market in self.markets.values() {
+            if let Some(event_id) = &market.event_id {
+                groups.entry(event_id.clone()).or_default().push(market.clone());
             }
         }
-        
-        self.markets.insert(market_id, market);
+        groups
+    }
+
+    /// Events with their markets at current prices
+    pub fn current_events(&self) -> Vec<EventData> {
+        self.events
+            .values()
+            .map(|event| EventData {
+                markets: event.markets
+                    .iter()
+                    .map(|m| self.markets.get(&m.id).cloned().unwrap_or_else(|| m.clone()))
+                    .collect(),
+                ..event.clone()
+            })
+            .collect()
+    }
+
+    /// Events whose markets are all fresh and onboarded, at current prices
+    pub fn tradeable_events(&self) -> Vec<EventData> {
+        self.current_events()
+            .into_iter()
+            .filter(|e| e.markets.iter().all(|m| !self.is_stale(&m.id) && self.is_tradeable(&m.id)))
+            .collect()
+    }
+
+    /// Run the onboarding pipeline on every cached market; returns how many were approved
+    pub fn run_onboarding(&mut self) -> usize {
+        let Some(onboarding) = self.onboarding.as_mut() else { return 0 };
+        let markets = self.markets
+            .values()
+            .map(|m| (m, self.price_history.get(&m.id).map_or(0, Vec::len)));
+        onboarding.onboard(markets).unwrap_or_else(|e| {
+            eprintln!("⚠️  Onboarding state not saved: {}", e);
+            0
+        })
+    }
+
+    /// Whether a market completed onboarding (always, without a pipeline)
+    pub fn is_tradeable(&self, market_id: &str) -> bool {
+        self.onboarding.as_ref().is_none_or(|o| o.is_tradeable(market_id))
+    }
+
+    /// Map a CLOB asset (token) id to its market and outcome
+    pub fn register_asset(&mut self, asset_id: &str, market_id: &str, token_type: TokenType) {
+        self.asset_index.insert(asset_id.to_string(), (market_id.to_string(), token_type));
+    }
+
+    /// Consume live market events from the WebSocket client instead of simulating prices
+    pub fn attach_event_channel(&mut self, rx: mpsc::Receiver<WsMarketEvent>) {
+        self.event_rx = Some(rx);
+        self.websocket_connected = true;
+        self.data_source = DataSource::RealWebSocket;
+    }
+
+    /// Drop the live event channel (pending events are discarded)
+    pub fn detach_event_channel(&mut self) {
+        self.event_rx = None;
+        self.websocket_connected = false;
+    }
+
+    /// Switch the price source; returns the previous one
+    ///
+    /// Moving between simulated and real data clears the cache, since the two market sets
+    /// have nothing in common; the caller repopulates it for the new source.
+    pub fn set_data_source(&mut self, source: DataSource) -> DataSource {
+        let previous = self.data_source;
+        if previous.is_real() != source.is_real() {
+            self.clear_markets();
+        }
+        if source != DataSource::RealWebSocket {
+            self.detach_event_channel();
+        }
+        self.data_source = source;
+        previous
+    }
+
+    /// Empty the market cache (watchlist and config are kept)
+    pub fn clear_markets(&mut self) {
+        self.markets.clear();
+        self.price_history.clear();
+        self.asset_index.clear();
+        self.events.clear();
+        self.stale.clear();
+        self.order_books.clear();
+    }
+
+    /// CLOB token ids of every cached market, for REST price polling
+    pub fn token_ids(&self) -> Vec<String> {
+        self.markets
+            .values()
+            .filter_map(|m| m.tokens.as_ref())
+            .flat_map(|t| [t.yes_token_id.clone(), t.no_token_id.clone()])
+            .collect()
+    }
+
+    /// CLOB token ids of the given markets (markets without known tokens are skipped)
+    pub fn token_ids_of(&self, market_ids: &[String]) -> Vec<String> {
+        market_ids
+            .iter()
+            .filter_map(|id| self.markets.get(id).and_then(|m| m.tokens.as_ref()))
+            .flat_map(|t| [t.yes_token_id.clone(), t.no_token_id.clone()])
+            .collect()
+    }
+
+    /// Markets with known tokens, prioritized for REST refresh by watchlist and WebSocket coverage
+    ///
+    /// A market is covered while the live feed is attached, it is among the subscriptions and it
+    /// is not stale.
+    pub fn refresh_candidates(&self) -> Vec<RefreshCandidate> {
+        let live = self.data_source == DataSource::RealWebSocket && self.event_rx.is_some();
+        let subscribed: FxHashSet<String> = if live { self.websocket_subscriptions().into_iter().collect() } else { FxHashSet::default() };
+        self.markets
+            .values()
+            .filter(|m| m.tokens.is_some())
+            .map(|m| {
+                let priority = if self.watchlist.is_pinned(&m.id) {
+                    RefreshPriority::Pinned
+                } else if subscribed.contains(&m.id) && !self.stale.contains(&m.id) {
+                    RefreshPriority::Covered
+                } else {
+                    RefreshPriority::Uncovered
+                };
+                RefreshCandidate { market_id: m.id.clone(), priority, updated_at: m.timestamp }
+            })
+            .collect()
+    }
+
+    /// WebSocket events received but not applied yet
+    pub fn pending_events(&self) -> usize {
+        self.event_rx.as_ref().map_or(0, |rx| rx.len())
+    }
+
+    /// CLOB token ids of the pinned markets (markets without known tokens are skipped)
+    pub fn watchlist_token_ids(&self) -> Vec<String> {
+        self.watchlist
+            .pinned_ids()
+            .iter()
+            .filter_map(|id| self.markets.get(id).and_then(|m| m.tokens.as_ref()))
+            .flat_map(|t| [t.yes_token_id.clone(), t.no_token_id.clone()])
+            .collect()
+    }
+
+    /// Apply all pending WebSocket events; returns how many updated a market
+    pub fn drain_events(&mut self) -> usize {
+        let mut pending = Vec::new();
+        if let Some(rx) = self.event_rx.as_mut() {
+            while let Ok(event) = rx.try_recv() {
+                pending.push(event);
+            }
+        }
+
+        pending.iter().filter(|event| self.apply_ws_event(event)).count()
+    }
+
+    /// Apply a single WebSocket event to the market view
+    pub fn apply_ws_event(&mut self, event: &WsMarketEvent) -> bool {
+        let Some((market_id, token_type)) = self.asset_index.get(event.asset_id()).cloned() else {
+            return false;
+        };
+        match event {
+            WsMarketEvent::Book(book) => self.order_books.apply_snapshot(book),
+            WsMarketEvent::PriceChange(change) => {
+                // In caso di gap il libro viene scartato e riallineato al prossimo snapshot
+                let _ = self.order_books.apply_delta(change);
+            }
+            WsMarketEvent::Trade(_) => {}
+        }
+        let Some(market) = self.markets.get_mut(&market_id) else {
+            return false;
+        };
+
+        // Prezzo di riferimento = best ask (prezzo a cui si compra l'outcome)
+        let (price, liquidity) = match event {
+            WsMarketEvent::Book(book) => (
+                book.best_ask().map(|l| l.price),
+                Some(book.asks.iter().map(|l| l.size).sum::<f64>()),
+            ),
+            WsMarketEvent::PriceChange(change) => (
+                change.changes.iter().rev().find_map(|c| {
+                    c.best_ask.or(if c.side == "SELL" && c.size > 0.0 { Some(c.price) } else { None })
+                }),
+                None,
+            ),
+            WsMarketEvent::Trade(trade) => {
+                market.volume_24h += trade.price * trade.size;
+                (Some(trade.price), None)
+            }
+        };
+
+        let Some(price) = price.filter(|p| *p > 0.0 && *p < 1.0) else {
+            return false;
+        };
+
+        if let (WsMarketEvent::Book(book), TokenType::Yes) = (event, token_type) {
+            if let (Some(bid), Some(ask)) = (book.best_bid(), book.best_ask()) {
+                market.spread = Some((ask.price - bid.price).max(0.0));
+            }
+        }
+
+        match token_type {
+            TokenType::Yes => {
+                market.yes_price = price;
+                if let Some(liq) = liquidity { market.yes_liquidity = liq; }
+            }
+            TokenType::No => {
+                market.no_price = price;
+                if let Some(liq) = liquidity { market.no_liquidity = liq; }
+            }
+        }
+        market.timestamp = chrono::Utc::now();
+
+        push_snapshot(&mut self.price_history, market);
+        self.stale.remove(&market_id);
+        true
+    }
+
+    /// Mark the markets of silent assets as stale; returns the newly stale market ids
+    pub fn mark_assets_stale(&mut self, asset_ids: &[String]) -> Vec<String> {
+        let mut newly_stale = Vec::new();
+        for asset_id in asset_ids {
+            let Some((market_id, _)) = self.asset_index.get(asset_id) else { continue };
+            if self.stale.insert(market_id.clone()) {
+                newly_stale.push(market_id.clone());
+            }
+        }
+        newly_stale
+    }
+
+    /// Apply REST prices keyed by token id and clear staleness; returns how many tokens were applied
+    pub fn apply_rest_prices(&mut self, prices: &FxHashMap<String, f64>) -> usize {
+        let mut applied = 0;
+        for (token_id, &price) in prices {
+            let Some((market_id, token_type)) = self.asset_index.get(token_id).cloned() else { continue };
+            let Some(market) = self.markets.get_mut(&market_id) else { continue };
+            if price <= 0.0 || price >= 1.0 {
+                continue;
+            }
+            match token_type {
+                TokenType::Yes => market.yes_price = price,
+                TokenType::No => market.no_price = price,
+            }
+            market.timestamp = chrono::Utc::now();
+            push_snapshot(&mut self.price_history, market);
+            self.stale.remove(&market_id);
+            applied += 1;
+        }
+        applied
+    }
+
+    /// Apply REST orderbook snapshots keyed by token id, like WebSocket `book` events; returns how many updated a market
+    pub fn apply_order_books(&mut self, books: &FxHashMap<String, WsBookEvent>) -> usize {
+        books
+            .values()
+            .filter(|book| self.apply_ws_event(&WsMarketEvent::Book((*book).clone())))
+            .count()
+    }
+
+    /// Local order book of an asset, if a consistent one is available
+    pub fn get_order_book(&self, asset_id: &str) -> Option<&LocalOrderBook> {
+        self.order_books.get(asset_id)
+    }
+
+    /// Assets whose local book hit a gap and needs a fresh snapshot
+    pub fn pending_book_snapshots(&self) -> Vec<String> {
+        self.order_books.pending_snapshots()
+    }
+
+    /// Onboarded markets currently marked stale
+    pub fn stale_markets(&self) -> Vec<MarketData> {
+        self.stale
+            .iter()
+            .filter(|id| self.is_tradeable(id))
+            .filter_map(|id| self.markets.get(id))
+            .cloned()
+            .collect()
+    }
+
+    pub fn is_stale(&self, market_id: &str) -> bool {
+        self.stale.contains(market_id)
+    }
+
+    /// Record CLOB spreads keyed by YES token id; returns how many markets were updated
+    pub fn apply_spreads(&mut self, spreads: &FxHashMap<String, f64>) -> usize {
+        let mut updated = 0;
+        for (token_id, &spread) in spreads {
+            let Some((market_id, TokenType::Yes)) = self.asset_index.get(token_id).cloned() else { continue };
+            if let Some(market) = self.markets.get_mut(&market_id) {
+                market.spread = Some(spread);
+                updated += 1;
+            }
+        }
+        updated
     }
 
     /// Update market prices
     pub async fn update_prices(&mut self) -> Result<(), String> {
+        match self.data_source {
+            DataSource::Simulated => {}
+            DataSource::RealWebSocket => {
+                self.drain_events();
+                return Ok(());
+            }
+            // I prezzi arrivano da apply_rest_prices
+            DataSource::RealRest => return Ok(()),
+            // Li muove solo lo scenario
+            DataSource::Scripted => return Ok(()),
+        }
+
         let mut rng = rand::thread_rng();
         
         for market in self.markets.values_mut() {
@@ -81,8 +454,8 @@ impl MarketManager {
             let yes_change = rng.gen_range(-0.02..0.02); // -2% to +2%
             let no_change = rng.gen_range(-0.02..0.02);  // -2% to +2%
 
-            market.yes_price = (market.yes_price * (1.0 + yes_change)).max(0.01).min(0.99);
-            market.no_price = (market.no_price * (1.0 + no_change)).max(0.01).min(0.99);
+            market.yes_price = (market.yes_price * (1.0 + yes_change)).clamp(0.01, 0.99);
+            market.no_price = (market.no_price * (1.0 + no_change)).clamp(0.01, 0.99);
 
             // Occasionally create new arbitrage opportunities (10% chance per step)
             if rng.gen_bool(0.10) {
@@ -92,32 +465,15 @@ impl MarketManager {
             }
             
             // Update liquidity and volume
-            market.yes_liquidity = market.yes_liquidity * rng.gen_range(0.95..1.05);
-            market.no_liquidity = market.no_liquidity * rng.gen_range(0.95..1.05);
-            market.volume_24h = market.volume_24h * rng.gen_range(0.99..1.01);
+            market.yes_liquidity *= rng.gen_range(0.95..1.05);
+            market.no_liquidity *= rng.gen_range(0.95..1.05);
+            market.volume_24h *= rng.gen_range(0.99..1.01);
             
             // Update timestamp
             market.timestamp = chrono::Utc::now();
             
             // Add to price history
-            let snapshot = PriceSnapshot {
-                timestamp: market.timestamp,
-                yes_price: market.yes_price,
-                no_price: market.no_price,
-                volume: market.volume_24h,
-            };
-            
-            self.price_history
-                .entry(market.id.clone())
-                .or_insert_with(Vec::new)
-                .push(snapshot);
-            
-            // Keep history bounded
-            if let Some(history) = self.price_history.get_mut(&market.id) {
-                if history.len() > 1000 {
-                    history.remove(0);
-                }
-            }
+            push_snapshot(&mut self.price_history, market);
         }
         
         Ok(())
@@ -149,6 +505,58 @@ impl MarketManager {
             .unwrap_or_default()
     }
 
+    /// Seed a market's history with fetched snapshots, keeping it sorted and capped like live history
+    pub fn seed_price_history(&mut self, market_id: &str, snapshots: Vec<PriceSnapshot>) {
+        let history = self.price_history.entry(market_id.to_string()).or_default();
+        history.extend(snapshots);
+        history.sort_by_key(|s| s.timestamp);
+        history.dedup_by_key(|s| s.timestamp);
+
+        if history.len() > 1000 {
+            let excess = history.len() - 1000;
+            history.drain(..excess);
+        }
+    }
+
+    /// Markets due for scanning at this step: pinned markets every step, others every `scan_interval_steps`
+    ///
+    /// Stale markets and markets still being onboarded are never scanned.
+    pub fn markets_to_scan(&self, step: u64) -> Vec<MarketData> {
+        let interval = self.config.scan_interval_steps.max(1);
+        let scan_all = step.is_multiple_of(interval);
+
+        self.markets
+            .values()
+            .filter(|m| !self.stale.contains(&m.id) && self.is_tradeable(&m.id))
+            .filter(|m| scan_all || self.watchlist.is_pinned(&m.id))
+            .cloned()
+            .collect()
+    }
+
+    /// Ids to subscribe on the WebSocket: pinned markets are always included, then the most active ones
+    pub fn websocket_subscriptions(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.watchlist.pinned_ids();
+
+        let mut others: Vec<&MarketData> = self.markets
+            .values()
+            .filter(|m| !self.watchlist.is_pinned(&m.id))
+            .collect();
+        others.sort_by(|a, b| b.volume_24h.total_cmp(&a.volume_24h));
+
+        let remaining = self.config.max_ws_subscriptions.saturating_sub(ids.len());
+        ids.extend(others.into_iter().take(remaining).map(|m| m.id.clone()));
+        ids
+    }
+
+    /// CLOB asset ids for the subscribed markets (markets without known tokens are skipped)
+    pub fn websocket_asset_ids(&self) -> Vec<String> {
+        self.websocket_subscriptions()
+            .iter()
+            .filter_map(|id| self.markets.get(id).and_then(|m| m.tokens.as_ref()))
+            .flat_map(|t| [t.yes_token_id.clone(), t.no_token_id.clone()])
+            .collect()
+    }
+
     /// Connect to WebSocket for real-time data
     pub async fn connect_websocket(&mut self) -> Result<(), String> {
         // Simulate WebSocket connection
@@ -200,20 +608,133 @@ impl MarketManager {
             no_liquidity: rng.gen_range(5000.0..50000.0),
             volume_24h: rng.gen_range(10000.0..100000.0),
             timestamp: chrono::Utc::now(),
+            tokens: None,
+            spread: None,
+            event_id: None,
+            category: Some("Crypto".to_string()),
+            end_date: None,
         }
     }
 }
 
+/// Append a price snapshot, keeping only the last 1000 per market
+#[cfg(feature = "native")]
+fn push_snapshot(price_history: &mut FxHashMap<String, Vec<PriceSnapshot>>, market: &MarketData) {
+    let history = price_history.entry(market.id.clone()).or_default();
+    history.push(PriceSnapshot {
+        timestamp: market.timestamp,
+        yes_price: market.yes_price,
+        no_price: market.no_price,
+        volume: market.volume_24h,
+    });
+
+    if history.len() > 1000 {
+        history.remove(0);
+    }
+}
+
 /// Market configuration
 #[derive(Debug, Clone)]
 pub struct MarketConfig {
     pub min_liquidity: f64,
     pub max_markets: usize,
     pub update_interval_ms: u64,
+    pub scan_interval_steps: u64, // Frequenza di scansione per i mercati non pinnati
+    pub max_ws_subscriptions: usize,
+}
+
+/// Schema of the watchlist file
+pub const WATCHLIST_SCHEMA: Schema = Schema::new("watchlist", &[]);
+
+/// Pinned market entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PinnedMarket {
+    pub market_id: String,
+    pub min_profit_override: Option<f64>, // Soglia di profitto rilassata opzionale
+    pub pinned_at: DateTime<Utc>,
+}
+
+/// Watchlist of pinned markets, persisted as JSON
+#[derive(Debug, Clone, Default)]
+pub struct Watchlist {
+    pub pinned: FxHashMap<String, PinnedMarket>,
+    pub path: Option<PathBuf>,
+}
+
+impl Watchlist {
+    /// Load watchlist from file (empty if the file does not exist yet)
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, String> {
+        let path = path.into();
+        let mut watchlist = Self { pinned: FxHashMap::default(), path: Some(path.clone()) };
+
+        let entries: Option<Vec<PinnedMarket>> = load_document(&WATCHLIST_SCHEMA, &path)
+            .map_err(|e| format!("Failed to load watchlist: {}", e))?;
+        for entry in entries.unwrap_or_default() {
+            watchlist.pinned.insert(entry.market_id.clone(), entry);
+        }
+
+        Ok(watchlist)
+    }
+
+    /// Persist watchlist to its file (no-op for in-memory watchlists)
+    pub fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.path else { return Ok(()) };
+
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create watchlist directory: {}", e))?;
+        }
+        let content = to_document(&WATCHLIST_SCHEMA, &self.list())
+            .map_err(|e| format!("Failed to serialize watchlist: {}", e))?;
+        std::fs::write(path, content)
+            .map_err(|e| format!("Failed to write watchlist: {}", e))
+    }
+
+    /// Pin a market and persist
+    pub fn pin(&mut self, market_id: &str, min_profit_override: Option<f64>) -> Result<(), String> {
+        self.pinned.insert(market_id.to_string(), PinnedMarket {
+            market_id: market_id.to_string(),
+            min_profit_override,
+            pinned_at: Utc::now(),
+        });
+        self.save()
+    }
+
+    /// Unpin a market and persist; returns whether it was pinned
+    pub fn unpin(&mut self, market_id: &str) -> Result<bool, String> {
+        let removed = self.pinned.remove(market_id).is_some();
+        if removed {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    pub fn is_pinned(&self, market_id: &str) -> bool {
+        self.pinned.contains_key(market_id)
+    }
+
+    pub fn pinned_ids(&self) -> Vec<String> {
+        self.list().into_iter().map(|p| p.market_id).collect()
+    }
+
+    /// Pinned markets, oldest first
+    pub fn list(&self) -> Vec<PinnedMarket> {
+        let mut entries: Vec<PinnedMarket> = self.pinned.values().cloned().collect();
+        entries.sort_by(|a, b| a.pinned_at.cmp(&b.pinned_at).then_with(|| a.market_id.cmp(&b.market_id)));
+        entries
+    }
+
+    /// Minimum profit threshold for a market, honoring pinned overrides
+    pub fn min_profit_for(&self, market_id: &str, default: f64) -> f64 {
+        self.pinned
+            .get(market_id)
+            .and_then(|p| p.min_profit_override)
+            .unwrap_or(default)
+    }
 }
 
 /// Price snapshot
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PriceSnapshot {
     pub timestamp: chrono::DateTime<chrono::Utc>,
     pub yes_price: f64,
@@ -236,6 +757,12 @@ pub struct WebSocketHandler {
     pub subscriptions: Vec<String>,
 }
 
+impl Default for WebSocketHandler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl WebSocketHandler {
     pub fn new() -> Self {
         Self {
@@ -265,7 +792,7 @@ impl WebSocketHandler {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "native"))]
 mod tests {
     use super::*;
 
@@ -279,4 +806,113 @@ mod tests {
         let markets = manager.get_liquid_markets(1000.0);
         assert!(!markets.is_empty());
     }
+
+    #[tokio::test]
+    async fn test_pinned_markets_priority() {
+        let mut manager = MarketManager::new(1000.0, 10);
+        manager.config.scan_interval_steps = 5;
+        manager.config.max_ws_subscriptions = 3;
+        manager.fetch_markets().await.unwrap();
+        manager.watchlist.pin("market_7", Some(0.001)).unwrap();
+
+        // Step non multiplo dell'intervallo: solo i mercati pinnati
+        let scanned = manager.markets_to_scan(3);
+        assert_eq!(scanned.len(), 1);
+        assert_eq!(scanned[0].id, "market_7");
+        assert_eq!(manager.markets_to_scan(5).len(), 10);
+
+        let subs = manager.websocket_subscriptions();
+        assert_eq!(subs.len(), 3);
+        assert_eq!(subs[0], "market_7");
+
+        assert_eq!(manager.watchlist.min_profit_for("market_7", 0.005), 0.001);
+        assert_eq!(manager.watchlist.min_profit_for("market_1", 0.005), 0.005);
+    }
+
+    #[tokio::test]
+    async fn test_ws_events_update_prices() {
+        let mut manager = MarketManager::new(1000.0, 10);
+        manager.fetch_markets().await.unwrap();
+        manager.register_asset("111", "market_0", TokenType::Yes);
+        manager.register_asset("222", "market_0", TokenType::No);
+
+        let (tx, rx) = mpsc::channel(16);
+        manager.attach_event_channel(rx);
+
+        let frame = r#"[
+            {"event_type":"book","asset_id":"111","asks":[{"price":"0.42","size":"100"}],"bids":[]},
+            {"event_type":"last_trade_price","asset_id":"222","price":"0.55","size":"10","side":"BUY"},
+            {"event_type":"book","asset_id":"999","asks":[{"price":"0.10","size":"1"}],"bids":[]}
+        ]"#;
+        for event in WsMarketEvent::parse_frame(frame).unwrap() {
+            tx.send(event).await.unwrap();
+        }
+
+        let history_len = manager.get_price_history("market_0").len();
+        manager.update_prices().await.unwrap();
+
+        let market = manager.get_market("market_0").unwrap();
+        assert_eq!(market.yes_price, 0.42);
+        assert_eq!(market.yes_liquidity, 100.0);
+        assert_eq!(market.no_price, 0.55);
+        assert_eq!(manager.get_price_history("market_0").len(), history_len + 2);
+    }
+
+    #[tokio::test]
+    async fn test_stale_markets_skipped_until_refreshed() {
+        let mut manager = MarketManager::new(1000.0, 10);
+        manager.fetch_markets().await.unwrap();
+        manager.register_asset("111", "market_0", TokenType::Yes);
+        manager.register_asset("222", "market_0", TokenType::No);
+
+        assert_eq!(manager.mark_assets_stale(&["111".to_string(), "222".to_string(), "999".to_string()]), vec!["market_0"]);
+        assert!(manager.is_stale("market_0"));
+        assert!(manager.markets_to_scan(1).iter().all(|m| m.id != "market_0"));
+
+        let prices: FxHashMap<String, f64> = [("111".to_string(), 0.47)].into_iter().collect();
+        assert_eq!(manager.apply_rest_prices(&prices), 1);
+        assert!(!manager.is_stale("market_0"));
+        assert_eq!(manager.get_market("market_0").unwrap().yes_price, 0.47);
+        assert_eq!(manager.markets_to_scan(1).len(), 10);
+    }
+
+    #[tokio::test]
+    async fn test_data_source_switch() {
+        let mut manager = MarketManager::new(1000.0, 10);
+        manager.fetch_markets().await.unwrap();
+        let (tx, rx) = mpsc::channel(16);
+        manager.attach_event_channel(rx);
+        assert_eq!(manager.data_source, DataSource::RealWebSocket);
+
+        // Da WebSocket a REST: stesso universo di mercati, canale chiuso, prezzi fermi fino al polling
+        assert_eq!(manager.set_data_source(DataSource::RealRest), DataSource::RealWebSocket);
+        assert_eq!(manager.markets.len(), 10);
+        assert!(tx.is_closed());
+        let before = manager.get_market("market_0").unwrap().yes_price;
+        manager.update_prices().await.unwrap();
+        assert_eq!(manager.get_market("market_0").unwrap().yes_price, before);
+
+        // Tornando alla simulazione la cache dei mercati reali viene svuotata
+        manager.set_data_source(DataSource::Simulated);
+        assert!(manager.markets.is_empty() && manager.asset_index.is_empty());
+        manager.fetch_markets().await.unwrap();
+        manager.update_prices().await.unwrap();
+        assert_eq!(manager.get_price_history("market_0").len(), 2);
+    }
+
+    #[test]
+    fn test_watchlist_persistence() {
+        let path = std::env::temp_dir().join(format!("watchlist_{}.json", uuid::Uuid::new_v4()));
+
+        let mut watchlist = Watchlist::load(&path).unwrap();
+        watchlist.pin("market_1", None).unwrap();
+        watchlist.pin("market_2", Some(0.002)).unwrap();
+        assert!(watchlist.unpin("market_1").unwrap());
+
+        let reloaded = Watchlist::load(&path).unwrap();
+        assert_eq!(reloaded.pinned_ids(), vec!["market_2".to_string()]);
+        assert_eq!(reloaded.min_profit_for("market_2", 0.01), 0.002);
+
+        std::fs::remove_file(&path).unwrap();
+    }
 }
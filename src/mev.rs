@@ -0,0 +1,40 @@
+//! MEV module (feature "mev")
+//!
+//! Implements:
+//! 1. Bundling of concurrent trades for MEV extraction
+
+use crate::types::{MevOpportunity, MevType, TradeExecution};
+
+/// MEV Opportunity Detector
+pub struct MevDetector {
+    pub block_time_window: u64, // milliseconds
+}
+
+impl MevDetector {
+    pub fn new(block_time_window: u64) -> Self {
+        Self { block_time_window }
+    }
+
+    /// Detect MEV opportunities for parallel execution
+    pub fn detect_mev_opportunity(&self, trades: &[&TradeExecution]) -> Option<MevOpportunity> {
+        if trades.len() < 2 {
+            return None;
+        }
+
+        // Check if trades can be bundled for MEV extraction
+        let total_gas = trades.iter().map(|t| t.gas_cost).sum::<f64>();
+        let savings = total_gas * 0.5; // 50% gas savings from bundling
+
+        if savings > 0.01 {
+            Some(MevOpportunity {
+                opportunity_type: MevType::FrontRunning,
+                victim_transactions: trades.iter().map(|t| t.trade_id.clone()).collect(),
+                expected_profit: savings,
+                gas_cost: total_gas * 0.5,
+                net_profit: savings - total_gas * 0.5,
+            })
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,184 @@
+//! Missed edge module
+//!
+//! Implements:
+//! 1. Record of detected opportunities that were not traded, with the cause
+//! 2. Forgone profit estimated at the position the executor would have taken
+//! 3. Breakdown by cause to show which constraint costs the most edge
+
+use crate::types::{ArbType, ArbitrageOpportunity};
+use chrono::{DateTime, Duration, Utc};
+use fxhash::FxHashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Missed opportunities kept for inspection (totals cover the whole run)
+pub const MISSED_EDGE_HISTORY: usize = 10_000;
+
+/// The same market, type and cause is counted once per window (opportunities persist across steps)
+pub const MISSED_EDGE_COOLDOWN_SECS: i64 = 60;
+
+/// Why a detected opportunity was not traded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MissCause {
+    RiskBlock,     // Limiti di rischio o flag di risoluzione
+    SizeFloor,     // Posizione sotto il minimo dell'executor o del mercato
+    Stale,         // Prezzi da un feed fermo o disconnesso
+    OptimizerSkip, // Scartata dall'optimizer o dalla proiezione di Bregman
+    Latency,       // Rilevamento e ottimizzazione oltre max_execution_time_ms
+    Outage,        // API dell'exchange non raggiungibile
+    QueueLimit,    // Rimasta in coda oltre le esecuzioni consentite nello step
+    ExternalVenue, // Gambe su un'altra venue (cross-venue): pubblicata soltanto, mai eseguita internamente
+}
+
+/// One opportunity that was not traded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MissedOpportunity {
+    pub market_id: String,
+    pub arb_type: ArbType,
+    pub cause: MissCause,
+    pub edge: f64, // Profitto per coppia rilevato
+    pub forgone_profit: f64,
+    pub detected_at: DateTime<Utc>,
+}
+
+/// Totals of one cause
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MissedEdgeCause {
+    pub cause: MissCause,
+    pub count: u64,
+    pub forgone_profit: f64,
+    pub share_pct: f64, // Quota del profitto mancato totale
+    pub average_edge: f64,
+}
+
+/// Missed edge by cause, largest forgone profit first
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MissedEdgeReport {
+    pub total_count: u64,
+    pub total_forgone_profit: f64,
+    pub by_cause: Vec<MissedEdgeCause>,
+}
+
+/// Running tracker of forgone opportunities
+#[derive(Debug, Clone, Default)]
+pub struct MissedEdgeTracker {
+    pub recent: VecDeque<MissedOpportunity>,
+    totals: FxHashMap<MissCause, (u64, f64, f64)>, // (conteggio, profitto mancato, somma edge)
+    last_seen: FxHashMap<(String, ArbType, MissCause), DateTime<Utc>>,
+}
+
+impl MissedEdgeTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record an untraded opportunity sized at `notional`; returns false if counted within the cooldown
+    pub fn record(&mut self, opportunity: &ArbitrageOpportunity, cause: MissCause, notional: f64, now: DateTime<Utc>) -> bool {
+        let key = (opportunity.market_id.clone(), opportunity.arb_type, cause);
+        if self.last_seen.get(&key).is_some_and(|last| now - *last < Duration::seconds(MISSED_EDGE_COOLDOWN_SECS)) {
+            return false;
+        }
+        self.last_seen.insert(key, now);
+
+        // Coppie acquistabili con il notional, entro la liquidità rilevata
+        let notional = notional.max(0.0).min(opportunity.liquidity.max(0.0));
+        let pairs = if opportunity.sum_price > 0.0 { notional / opportunity.sum_price } else { notional };
+        let forgone_profit = opportunity.profit.max(0.0) * pairs;
+
+        let totals = self.totals.entry(cause).or_insert((0, 0.0, 0.0));
+        totals.0 += 1;
+        totals.1 += forgone_profit;
+        totals.2 += opportunity.profit;
+
+        self.recent.push_back(MissedOpportunity {
+            market_id: opportunity.market_id.clone(),
+            arb_type: opportunity.arb_type,
+            cause,
+            edge: opportunity.profit,
+            forgone_profit,
+            detected_at: opportunity.timestamp,
+        });
+        if self.recent.len() > MISSED_EDGE_HISTORY {
+            self.recent.pop_front();
+        }
+        true
+    }
+
+    /// Record every opportunity of a batch with the same cause; returns how many were counted
+    pub fn record_all(&mut self, opportunities: &[ArbitrageOpportunity], cause: MissCause, notional: impl Fn(&ArbitrageOpportunity) -> f64, now: DateTime<Utc>) -> usize {
+        opportunities.iter().filter(|o| self.record(o, cause, notional(o), now)).count()
+    }
+
+    pub fn report(&self) -> MissedEdgeReport {
+        let total_count = self.totals.values().map(|t| t.0).sum();
+        let total_forgone_profit: f64 = self.totals.values().map(|t| t.1).sum();
+
+        let mut by_cause: Vec<MissedEdgeCause> = self.totals
+            .iter()
+            .map(|(&cause, &(count, forgone, edge_sum))| MissedEdgeCause {
+                cause,
+                count,
+                forgone_profit: forgone,
+                share_pct: if total_forgone_profit > 0.0 { forgone / total_forgone_profit * 100.0 } else { 0.0 },
+                average_edge: if count > 0 { edge_sum / count as f64 } else { 0.0 },
+            })
+            .collect();
+        by_cause.sort_by(|a, b| b.forgone_profit.partial_cmp(&a.forgone_profit).unwrap());
+
+        MissedEdgeReport {
+            total_count,
+            total_forgone_profit,
+            by_cause,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn opportunity(market_id: &str, profit: f64, liquidity: f64) -> ArbitrageOpportunity {
+        ArbitrageOpportunity {
+            market_id: market_id.to_string(),
+            question: String::new(),
+            arb_type: ArbType::YesNoSimple,
+            profit,
+            roi_pct: profit * 100.0,
+            confidence: 1.0,
+            yes_price: 0.5 - profit / 2.0,
+            no_price: 0.5 - profit / 2.0,
+            sum_price: 1.0 - profit,
+            liquidity,
+            timestamp: Utc::now(),
+            legs: None,
+            path: None,
+            maker: None,
+        }
+    }
+
+    #[test]
+    fn test_missed_edge_breakdown() {
+        let now = Utc::now();
+        let mut tracker = MissedEdgeTracker::new();
+
+        // 98 di notional a 0.98 per coppia = 100 coppie, 0.02 ciascuna
+        assert!(tracker.record(&opportunity("a", 0.02, 10_000.0), MissCause::RiskBlock, 98.0, now));
+        assert!(!tracker.record(&opportunity("a", 0.02, 10_000.0), MissCause::RiskBlock, 98.0, now + Duration::seconds(5)));
+        assert!(tracker.record(&opportunity("a", 0.02, 10_000.0), MissCause::SizeFloor, 49.0, now));
+        // Limitato dalla liquidità: 9.9 / 0.99 = 10 coppie
+        assert!(tracker.record(&opportunity("b", 0.01, 9.9), MissCause::Stale, 1_000.0, now));
+
+        let report = tracker.report();
+        assert_eq!(report.total_count, 3);
+        assert!((report.total_forgone_profit - 3.1).abs() < 1e-9);
+        let causes: Vec<MissCause> = report.by_cause.iter().map(|c| c.cause).collect();
+        assert_eq!(causes, vec![MissCause::RiskBlock, MissCause::SizeFloor, MissCause::Stale]);
+        assert!((report.by_cause[0].share_pct - 2.0 / 3.1 * 100.0).abs() < 1e-9);
+        assert_eq!(tracker.recent.len(), 3);
+    }
+}
@@ -0,0 +1,280 @@
+//! News feed module
+//!
+//! Implements:
+//! 1. Polling of a configurable RSS/Atom feed or JSON news API
+//! 2. Keyword matching of headlines against tracked market questions
+//! 3. Resolution-risk flags that reduce sizing or pause trading on affected markets
+
+pub use crate::risk::{ResolutionRisk, ResolutionRiskFlag};
+use crate::types::MarketData;
+use chrono::{DateTime, Duration, Utc};
+use reqwest::Client as HttpClient;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Words too common to tie a headline to a market
+const STOPWORDS: &[&str] = &[
+    "will", "the", "and", "for", "with", "from", "that", "this", "than", "before", "after", "by",
+    "be", "in", "on", "of", "to", "a", "an", "or", "at", "is", "are", "does", "do", "win", "end",
+    "more", "less", "over", "under", "2024", "2025", "2026", "what", "which", "who", "when",
+];
+
+fn default_poll_interval_secs() -> u64 {
+    300
+}
+
+fn default_min_keyword_matches() -> usize {
+    2
+}
+
+fn default_flag_ttl_secs() -> u64 {
+    3600
+}
+
+fn default_reduce_factor() -> f64 {
+    0.5
+}
+
+fn default_pause_keywords() -> Vec<String> {
+    ["resolved", "resolves", "official", "confirmed", "cancelled", "canceled", "postponed", "suspended", "disqualified", "withdraws", "dispute"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
+}
+
+/// News feed polled for resolution risk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewsFeedConfig {
+    pub url: String, // Feed RSS/Atom o API JSON
+    #[serde(default = "default_poll_interval_secs")]
+    pub poll_interval_secs: u64,
+    #[serde(default = "default_min_keyword_matches")]
+    pub min_keyword_matches: usize, // Parole della domanda che il titolo deve contenere
+    #[serde(default = "default_pause_keywords")]
+    pub pause_keywords: Vec<String>, // Parole che indicano una risoluzione imminente: trading sospeso
+    #[serde(default = "default_flag_ttl_secs")]
+    pub flag_ttl_secs: u64,
+    #[serde(default = "default_reduce_factor")]
+    pub reduce_factor: f64, // Moltiplicatore della size per le notizie senza parole di pausa
+}
+
+impl NewsFeedConfig {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            poll_interval_secs: default_poll_interval_secs(),
+            min_keyword_matches: default_min_keyword_matches(),
+            pause_keywords: default_pause_keywords(),
+            flag_ttl_secs: default_flag_ttl_secs(),
+            reduce_factor: default_reduce_factor(),
+        }
+    }
+}
+
+/// One headline from the feed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NewsItem {
+    pub title: String,
+    #[serde(default, alias = "description")]
+    pub summary: String,
+    #[serde(default, alias = "url")]
+    pub link: String,
+}
+
+/// Text between the first `<tag ...>` and `</tag>` of a fragment, CDATA unwrapped
+fn xml_text(fragment: &str, tag: &str) -> Option<String> {
+    let start = fragment.find(&format!("<{}", tag))?;
+    let open_end = start + fragment[start..].find('>')? + 1;
+    // Atom: <link href="..."/> senza contenuto
+    if fragment[..open_end].ends_with("/>") {
+        let attrs = &fragment[start..open_end];
+        let href = attrs.split("href=\"").nth(1)?.split('"').next()?;
+        return Some(href.to_string());
+    }
+    let close = open_end + fragment[open_end..].find(&format!("</{}>", tag))?;
+    let text = fragment[open_end..close].trim();
+    let text = text.strip_prefix("<![CDATA[").and_then(|t| t.strip_suffix("]]>")).unwrap_or(text);
+    Some(text.replace("&amp;", "&").replace("&quot;", "\"").replace("&#39;", "'").replace("&lt;", "<").replace("&gt;", ">"))
+}
+
+/// Parse an RSS (`<item>`), Atom (`<entry>`) or JSON (array or `{"articles": [...]}`) feed body
+pub fn parse_feed(body: &str) -> Vec<NewsItem> {
+    let trimmed = body.trim_start();
+    if trimmed.starts_with('[') || trimmed.starts_with('{') {
+        let Ok(json) = serde_json::from_str::<serde_json::Value>(trimmed) else { return Vec::new() };
+        let items = match json {
+            serde_json::Value::Array(items) => items,
+            serde_json::Value::Object(mut obj) => match obj.remove("articles").or_else(|| obj.remove("items")) {
+                Some(serde_json::Value::Array(items)) => items,
+                _ => Vec::new(),
+            },
+            _ => Vec::new(),
+        };
+        return items.into_iter().filter_map(|item| serde_json::from_value(item).ok()).collect();
+    }
+
+    let tag = if trimmed.contains("<item") { "item" } else { "entry" };
+    trimmed
+        .split(&format!("<{}", tag))
+        .skip(1)
+        .filter_map(|fragment| {
+            let fragment = fragment.split(&format!("</{}>", tag)).next()?;
+            Some(NewsItem {
+                title: xml_text(fragment, "title")?,
+                summary: xml_text(fragment, "description").or_else(|| xml_text(fragment, "summary")).unwrap_or_default(),
+                link: xml_text(fragment, "link").unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+/// Lowercase words of a text, split on anything that is not alphanumeric
+fn words(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect()
+}
+
+/// Distinctive words of a market question
+pub fn market_keywords(question: &str) -> Vec<String> {
+    let mut keywords: Vec<String> = words(question)
+        .into_iter()
+        .filter(|w| w.len() >= 3 && !STOPWORDS.contains(&w.as_str()))
+        .collect();
+    keywords.sort();
+    keywords
+}
+
+/// Flags for every market whose question keywords appear in a headline
+pub fn scan_news(items: &[NewsItem], markets: &[MarketData], config: &NewsFeedConfig, now: DateTime<Utc>) -> Vec<ResolutionRiskFlag> {
+    let mut flags = Vec::new();
+
+    for market in markets {
+        let keywords = market_keywords(&market.question);
+        if keywords.is_empty() {
+            continue;
+        }
+        let required = config.min_keyword_matches.min(keywords.len()).max(1);
+
+        for item in items {
+            let text = words(&format!("{} {}", item.title, item.summary));
+            if keywords.iter().filter(|k| text.contains(*k)).count() < required {
+                continue;
+            }
+            let level = if config.pause_keywords.iter().any(|k| text.contains(&k.to_lowercase())) {
+                ResolutionRisk::Pause
+            } else {
+                ResolutionRisk::Reduce
+            };
+            flags.push(ResolutionRiskFlag {
+                market_id: market.id.clone(),
+                level,
+                size_multiplier: match level {
+                    ResolutionRisk::Pause => 0.0,
+                    ResolutionRisk::Reduce => config.reduce_factor.clamp(0.0, 1.0),
+                },
+                headline: item.title.clone(),
+                link: item.link.clone(),
+                raised_at: now,
+                expires_at: now + Duration::seconds(config.flag_ttl_secs as i64),
+            });
+        }
+    }
+
+    flags
+}
+
+/// Poller of the configured feed; each headline is evaluated once
+pub struct NewsFeed {
+    pub config: NewsFeedConfig,
+    http_client: HttpClient,
+    seen: HashSet<String>,
+    last_poll: Option<DateTime<Utc>>,
+}
+
+impl NewsFeed {
+    pub fn new(config: NewsFeedConfig) -> Self {
+        Self {
+            config,
+            http_client: HttpClient::new(),
+            seen: HashSet::new(),
+            last_poll: None,
+        }
+    }
+
+    /// Whether the poll interval has elapsed at `now`
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        self.last_poll
+            .is_none_or(|last| now - last >= Duration::seconds(self.config.poll_interval_secs as i64))
+    }
+
+    /// Fetch the feed and flag markets hit by headlines not seen before
+    pub async fn poll(&mut self, markets: &[MarketData], now: DateTime<Utc>) -> Result<Vec<ResolutionRiskFlag>, String> {
+        self.last_poll = Some(now);
+
+        let response = self.http_client
+            .get(&self.config.url)
+            .send()
+            .await
+            .map_err(|e| format!("News feed request failed: {}", e))?;
+        if !response.status().is_success() {
+            return Err(format!("News feed returned {}", response.status()));
+        }
+        let body = response.text().await.map_err(|e| format!("News feed body unreadable: {}", e))?;
+
+        Ok(self.scan(&parse_feed(&body), markets, now))
+    }
+
+    /// Flags from headlines not seen in previous polls
+    pub fn scan(&mut self, items: &[NewsItem], markets: &[MarketData], now: DateTime<Utc>) -> Vec<ResolutionRiskFlag> {
+        let fresh: Vec<NewsItem> = items
+            .iter()
+            .filter(|item| {
+                let key = if item.link.is_empty() { &item.title } else { &item.link };
+                self.seen.insert(key.clone())
+            })
+            .cloned()
+            .collect();
+        scan_news(&fresh, markets, &self.config, now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_feed_headlines_flag_markets() {
+        let rss = r#"<?xml version="1.0"?><rss><channel><title>Wire</title>
+            <item><title><![CDATA[Fed chair confirmed: rates cut in December]]></title>
+                <link>https://news.example/1</link><description>Official statement</description></item>
+            <item><title>Bitcoin rallies as ETF inflows grow</title><link>https://news.example/2</link></item>
+            <item><title>Local weather update</title><link>https://news.example/3</link></item>
+        </channel></rss>"#;
+        let items = parse_feed(rss);
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0].title, "Fed chair confirmed: rates cut in December");
+        assert_eq!(items[1].link, "https://news.example/2");
+
+        let json = r#"{"articles": [{"title": "Bitcoin ETF", "description": "inflows", "url": "https://x"}]}"#;
+        assert_eq!(parse_feed(json)[0].summary, "inflows");
+
+        let markets = vec![
+            MarketData { id: "fed".to_string(), question: "Will the Fed cut rates in December?".to_string(), ..MarketData::default() },
+            MarketData { id: "btc".to_string(), question: "Will Bitcoin ETF inflows top $1B?".to_string(), ..MarketData::default() },
+            MarketData { id: "nba".to_string(), question: "Will the Lakers win the title?".to_string(), ..MarketData::default() },
+        ];
+        let now = Utc::now();
+        let mut feed = NewsFeed::new(NewsFeedConfig::new("https://news.example/rss"));
+        let flags = feed.scan(&items, &markets, now);
+
+        assert_eq!(flags.len(), 2);
+        assert_eq!((flags[0].market_id.as_str(), flags[0].level), ("fed", ResolutionRisk::Pause));
+        assert_eq!((flags[1].market_id.as_str(), flags[1].level), ("btc", ResolutionRisk::Reduce));
+        assert_eq!(flags[1].size_multiplier, 0.5);
+
+        // Titoli già visti non generano nuovi flag
+        assert!(feed.scan(&items, &markets, now).is_empty());
+    }
+}
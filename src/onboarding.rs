@@ -0,0 +1,313 @@
+//! Market onboarding module
+//!
+//! Implements:
+//! 1. Eligibility pipeline for newly discovered markets: metadata completeness, minimum price
+//!    history, spread and liquidity, category policy
+//! 2. Pending / approved / rejected states with the reasons of the last evaluation
+//! 3. Optional manual-approval mode, where eligible markets wait for an operator decision
+//! 4. JSON persistence of settings and decisions, so onboarded markets stay tradeable across restarts
+
+use crate::types::{MarketData, MarketFilter};
+use crate::versioning::{load_document, to_document, Schema};
+use chrono::{DateTime, Utc};
+use fxhash::FxHashMap;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Default location of the onboarding state
+pub const ONBOARDING_PATH: &str = "./data/onboarding.json";
+
+/// Eligibility checks of the onboarding pipeline
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OnboardingConfig {
+    pub min_history: usize,            // Snapshot di prezzo richiesti prima di negoziare
+    pub max_spread: f64,               // Spread bid-ask massimo del token YES, se noto
+    pub min_liquidity: f64,            // Liquidità YES + NO minima
+    pub category_policy: MarketFilter, // Categorie ammesse ed escluse
+    pub require_tokens: bool,          // Token CLOB obbligatori (mercati negoziabili live)
+    pub manual_approval: bool,         // I mercati idonei attendono l'approvazione di un operatore
+}
+
+impl Default for OnboardingConfig {
+    fn default() -> Self {
+        Self {
+            min_history: 5,
+            max_spread: 0.1,
+            min_liquidity: 500.0,
+            category_policy: MarketFilter::default(),
+            require_tokens: false,
+            manual_approval: false,
+        }
+    }
+}
+
+/// Onboarding state of a market
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnboardingStatus {
+    Pending,  // In attesa di storia, liquidità/spread adeguati o approvazione manuale
+    Approved, // Negoziabile
+    Rejected, // Metadati incompleti, categoria esclusa o rifiuto manuale
+}
+
+/// Onboarding record of a discovered market
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OnboardingRecord {
+    pub market_id: String,
+    pub question: String,
+    pub status: OnboardingStatus,
+    pub reasons: Vec<String>, // Controlli non superati, o motivo della decisione manuale
+    pub manual: bool,         // Decisione di un operatore: non viene più rivalutata
+    pub discovered_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Schema of the onboarding state file
+pub const ONBOARDING_SCHEMA: Schema = Schema::new("onboarding", &[]);
+
+/// Persisted form of the onboarding state
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct OnboardingFile {
+    #[serde(default)]
+    config: OnboardingConfig,
+    #[serde(default)]
+    markets: Vec<OnboardingRecord>,
+}
+
+/// Onboarding pipeline and decisions, persisted as JSON
+#[derive(Debug, Clone, Default)]
+pub struct MarketOnboarding {
+    pub config: OnboardingConfig,
+    records: FxHashMap<String, OnboardingRecord>,
+    pub path: Option<PathBuf>,
+}
+
+impl MarketOnboarding {
+    /// In-memory pipeline
+    pub fn new(config: OnboardingConfig) -> Self {
+        Self { config, ..Self::default() }
+    }
+
+    /// Load settings and decisions from file (defaults if the file does not exist yet)
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, String> {
+        let path = path.into();
+        let mut onboarding = Self { path: Some(path.clone()), ..Self::default() };
+
+        let file: Option<OnboardingFile> = load_document(&ONBOARDING_SCHEMA, &path)
+            .map_err(|e| format!("Failed to load onboarding state: {}", e))?;
+        if let Some(file) = file {
+            onboarding.config = file.config;
+            for record in file.markets {
+                onboarding.records.insert(record.market_id.clone(), record);
+            }
+        }
+
+        Ok(onboarding)
+    }
+
+    /// Persist settings and decisions to file (no-op for in-memory pipelines)
+    pub fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.path else { return Ok(()) };
+
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create onboarding directory: {}", e))?;
+        }
+        let file = OnboardingFile { config: self.config.clone(), markets: self.list(None) };
+        let content = to_document(&ONBOARDING_SCHEMA, &file)
+            .map_err(|e| format!("Failed to serialize onboarding state: {}", e))?;
+        std::fs::write(path, content)
+            .map_err(|e| format!("Failed to write onboarding state: {}", e))
+    }
+
+    /// Replace the eligibility checks and persist; undecided markets are re-evaluated on the next pass
+    pub fn set_config(&mut self, config: OnboardingConfig) -> Result<(), String> {
+        self.config = config;
+        self.save()
+    }
+
+    /// Run the checks on a market with `history` price snapshots
+    ///
+    /// Failed metadata or category checks reject the market, insufficient history, spread or
+    /// liquidity keep it pending until a later pass.
+    pub fn check(&self, market: &MarketData, history: usize, now: DateTime<Utc>) -> (OnboardingStatus, Vec<String>) {
+        let mut rejections = Vec::new();
+        if market.question.trim().is_empty() {
+            rejections.push("missing question".to_string());
+        }
+        let valid_price = |p: f64| p.is_finite() && p > 0.0 && p < 1.0;
+        if !valid_price(market.yes_price) || !valid_price(market.no_price) {
+            rejections.push(format!("invalid prices {:.4}/{:.4}", market.yes_price, market.no_price));
+        }
+        if self.config.require_tokens && market.tokens.is_none() {
+            rejections.push("missing CLOB tokens".to_string());
+        }
+        if market.end_date.is_some_and(|end| end <= now) {
+            rejections.push("market already ended".to_string());
+        }
+        if !self.config.category_policy.matches(market.category.as_deref(), &[]) {
+            rejections.push(format!("category {} not allowed", market.category.as_deref().unwrap_or("none")));
+        }
+        if !rejections.is_empty() {
+            return (OnboardingStatus::Rejected, rejections);
+        }
+
+        let mut waiting = Vec::new();
+        if history < self.config.min_history {
+            waiting.push(format!("price history {}/{} snapshots", history, self.config.min_history));
+        }
+        if let Some(spread) = market.spread.filter(|s| *s > self.config.max_spread) {
+            waiting.push(format!("spread {:.4} above {:.4}", spread, self.config.max_spread));
+        }
+        let liquidity = market.yes_liquidity + market.no_liquidity;
+        if liquidity < self.config.min_liquidity {
+            waiting.push(format!("liquidity ${:.0} below ${:.0}", liquidity, self.config.min_liquidity));
+        }
+        if waiting.is_empty() && self.config.manual_approval {
+            waiting.push("awaiting manual approval".to_string());
+        }
+
+        let status = if waiting.is_empty() { OnboardingStatus::Approved } else { OnboardingStatus::Pending };
+        (status, waiting)
+    }
+
+    /// Evaluate a market without persisting; returns whether its state changed
+    fn evaluate(&mut self, market: &MarketData, history: usize, now: DateTime<Utc>) -> bool {
+        // Approvazioni e decisioni manuali sono definitive
+        if self.records.get(&market.id).is_some_and(|r| r.manual || r.status == OnboardingStatus::Approved) {
+            return false;
+        }
+        let (status, reasons) = self.check(market, history, now);
+        if let Some(record) = self.records.get_mut(&market.id) {
+            if record.status == status && record.reasons == reasons {
+                return false;
+            }
+            record.status = status;
+            record.reasons = reasons;
+            record.updated_at = now;
+        } else {
+            self.records.insert(market.id.clone(), OnboardingRecord {
+                market_id: market.id.clone(),
+                question: market.question.clone(),
+                status,
+                reasons,
+                manual: false,
+                discovered_at: now,
+                updated_at: now,
+            });
+        }
+        true
+    }
+
+    /// Evaluate markets with the length of their price history and persist once; returns how many were approved
+    pub fn onboard<'a>(&mut self, markets: impl IntoIterator<Item = (&'a MarketData, usize)>) -> Result<usize, String> {
+        let now = Utc::now();
+        let (mut changed, mut approved) = (false, 0);
+        for (market, history) in markets {
+            if self.evaluate(market, history, now) {
+                changed = true;
+                approved += usize::from(self.status(&market.id) == Some(OnboardingStatus::Approved));
+            }
+        }
+        if changed {
+            self.save()?;
+        }
+        Ok(approved)
+    }
+
+    /// Record an operator decision on a discovered market and persist
+    fn decide(&mut self, market_id: &str, status: OnboardingStatus, reason: Option<String>) -> Result<OnboardingRecord, String> {
+        let record = self.records
+            .get_mut(market_id)
+            .ok_or_else(|| format!("Market {} not discovered", market_id))?;
+        record.status = status;
+        record.reasons = reason.into_iter().collect();
+        record.manual = true;
+        record.updated_at = Utc::now();
+        let record = record.clone();
+        self.save()?;
+        Ok(record)
+    }
+
+    /// Manually approve a market, whatever its checks say
+    pub fn approve(&mut self, market_id: &str) -> Result<OnboardingRecord, String> {
+        self.decide(market_id, OnboardingStatus::Approved, None)
+    }
+
+    /// Manually reject a market; it is never re-evaluated
+    pub fn reject(&mut self, market_id: &str, reason: Option<String>) -> Result<OnboardingRecord, String> {
+        self.decide(market_id, OnboardingStatus::Rejected, reason)
+    }
+
+    pub fn status(&self, market_id: &str) -> Option<OnboardingStatus> {
+        self.records.get(market_id).map(|r| r.status)
+    }
+
+    /// Whether a market completed onboarding
+    pub fn is_tradeable(&self, market_id: &str) -> bool {
+        self.status(market_id) == Some(OnboardingStatus::Approved)
+    }
+
+    /// Records in a state (all when None), oldest discovery first
+    pub fn list(&self, status: Option<OnboardingStatus>) -> Vec<OnboardingRecord> {
+        let mut records: Vec<OnboardingRecord> = self.records
+            .values()
+            .filter(|r| status.is_none_or(|s| r.status == s))
+            .cloned()
+            .collect();
+        records.sort_by(|a, b| a.discovered_at.cmp(&b.discovered_at).then_with(|| a.market_id.cmp(&b.market_id)));
+        records
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn market(id: &str, category: &str) -> MarketData {
+        MarketData {
+            id: id.to_string(),
+            question: format!("Question {}?", id),
+            yes_liquidity: 400.0,
+            no_liquidity: 400.0,
+            category: Some(category.to_string()),
+            ..MarketData::default()
+        }
+    }
+
+    #[test]
+    fn test_markets_wait_for_history_and_manual_approval() {
+        let config = OnboardingConfig {
+            min_history: 3,
+            category_policy: MarketFilter { categories: Vec::new(), exclude: vec!["sports".to_string()] },
+            ..OnboardingConfig::default()
+        };
+        let mut onboarding = MarketOnboarding::new(config);
+        let politics = market("m1", "Politics");
+        let sports = market("m2", "Sports");
+        let wide = MarketData { spread: Some(0.2), ..market("m3", "Crypto") };
+
+        assert_eq!(onboarding.onboard([(&politics, 1), (&sports, 10), (&wide, 10)]).unwrap(), 0);
+        assert_eq!(onboarding.status("m1"), Some(OnboardingStatus::Pending));
+        assert_eq!(onboarding.status("m2"), Some(OnboardingStatus::Rejected));
+        assert_eq!(onboarding.list(Some(OnboardingStatus::Pending))[1].reasons, vec!["spread 0.2000 above 0.1000".to_string()]);
+
+        // Storia sufficiente: approvato automaticamente, e resta tale
+        assert_eq!(onboarding.onboard([(&politics, 3)]).unwrap(), 1);
+        assert!(onboarding.is_tradeable("m1"));
+        let drained = MarketData { yes_liquidity: 0.0, ..politics.clone() };
+        onboarding.onboard([(&drained, 3)]).unwrap();
+        assert!(onboarding.is_tradeable("m1"));
+
+        // Modalità manuale: i mercati idonei attendono l'operatore
+        onboarding.config.manual_approval = true;
+        let tight = MarketData { spread: Some(0.02), ..wide };
+        onboarding.onboard([(&tight, 10)]).unwrap();
+        assert_eq!(onboarding.status("m3"), Some(OnboardingStatus::Pending));
+        assert!(onboarding.approve("m3").unwrap().manual);
+        onboarding.reject("m1", Some("ambiguous resolution".to_string())).unwrap();
+        assert!(!onboarding.is_tradeable("m1"));
+        assert!(onboarding.approve("unknown").is_err());
+    }
+}
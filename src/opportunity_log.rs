@@ -0,0 +1,282 @@
+//! Opportunity history module
+//!
+//! Implements:
+//! 1. One record per detected candidate, kept or dropped, with the filter that dropped it
+//! 2. Bounded ring buffer of the latest records, queried by market, type, reason and time range
+//! 3. Filter breakdown and threshold sensitivity (candidates a different minimum profit would keep)
+//! 4. Records not yet written to the optional SQLite table, drained by the bot after each step
+
+use crate::types::{ArbType, ArbitrageOpportunity};
+use chrono::{DateTime, Utc};
+use fxhash::FxHashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+
+/// Ring buffer size and optional SQLite table of the opportunity history
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OpportunityLogConfig {
+    pub capacity: usize,              // Record tenuti in memoria; i più vecchi sono scartati
+    pub sqlite_path: Option<PathBuf>, // Database in cui archiviare ogni record, se indicato
+}
+
+impl Default for OpportunityLogConfig {
+    fn default() -> Self {
+        Self { capacity: 10_000, sqlite_path: None }
+    }
+}
+
+/// Outcome of a detected candidate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterReason {
+    Accepted,  // Superati tutti i filtri, passata all'optimizer
+    MinProfit, // Margine netto sotto la soglia minima
+    Spread,    // Spread del CLOB pari o superiore al margine
+    Liquidity, // Liquidità sotto il minimo del detector
+    Execution, // Margine annullato dal tick o size sotto l'ordine minimo
+    Duplicate, // Già riportata entro il TTL di deduplicazione
+}
+
+impl FilterReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FilterReason::Accepted => "accepted",
+            FilterReason::MinProfit => "min_profit",
+            FilterReason::Spread => "spread",
+            FilterReason::Liquidity => "liquidity",
+            FilterReason::Execution => "execution",
+            FilterReason::Duplicate => "duplicate",
+        }
+    }
+}
+
+/// One detected candidate and the filter verdict
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OpportunityRecord {
+    pub timestamp: DateTime<Utc>,
+    pub market_id: String,
+    pub arb_type: ArbType,
+    pub profit: f64,     // Margine netto per unità al momento del filtro
+    pub liquidity: f64,
+    pub min_profit: f64, // Soglia applicata al candidato
+    pub reason: FilterReason,
+}
+
+impl OpportunityRecord {
+    /// Record of a candidate dropped before its opportunity was built
+    pub fn candidate(
+        market_id: &str,
+        arb_type: ArbType,
+        timestamp: DateTime<Utc>,
+        profit: f64,
+        liquidity: f64,
+        min_profit: f64,
+        reason: FilterReason,
+    ) -> Self {
+        Self { timestamp, market_id: market_id.to_string(), arb_type, profit, liquidity, min_profit, reason }
+    }
+
+    /// Record of a built opportunity
+    pub fn of(opportunity: &ArbitrageOpportunity, min_profit: f64, reason: FilterReason) -> Self {
+        Self {
+            timestamp: opportunity.timestamp,
+            market_id: opportunity.market_id.clone(),
+            arb_type: opportunity.arb_type,
+            profit: opportunity.profit,
+            liquidity: opportunity.liquidity,
+            min_profit,
+            reason,
+        }
+    }
+}
+
+/// History filter; unset fields match everything
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OpportunityQuery {
+    pub market_id: Option<String>,
+    pub arb_type: Option<ArbType>,
+    pub reason: Option<FilterReason>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub limit: Option<usize>, // Record più recenti da restituire
+}
+
+impl OpportunityQuery {
+    pub fn matches(&self, record: &OpportunityRecord) -> bool {
+        self.market_id.as_ref().is_none_or(|id| *id == record.market_id)
+            && self.arb_type.is_none_or(|t| t == record.arb_type)
+            && self.reason.is_none_or(|r| r == record.reason)
+            && self.since.is_none_or(|since| record.timestamp >= since)
+            && self.until.is_none_or(|until| record.timestamp < until)
+    }
+}
+
+/// Candidates kept at a hypothetical minimum profit
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ThresholdSensitivity {
+    pub min_profit: f64,
+    pub candidates: usize, // Candidati con margine sopra la soglia e non scartati da altri filtri
+    pub total_edge: f64,   // Somma dei loro margini per unità
+}
+
+/// Latest detected candidates, kept in a ring buffer
+#[derive(Debug, Clone, Default)]
+pub struct OpportunityLog {
+    pub config: OpportunityLogConfig,
+    records: VecDeque<OpportunityRecord>,
+    unsaved: VecDeque<OpportunityRecord>, // In attesa di essere scritti nella tabella SQLite, al più `capacity`
+    archiving: bool, // Tabella SQLite aperta: solo allora i record vengono accumulati
+    unarchived: u64, // Record scartati dal buffer prima di un flush
+    total: u64,
+}
+
+impl OpportunityLog {
+    pub fn new(config: OpportunityLogConfig) -> Self {
+        Self { config, ..Self::default() }
+    }
+
+    pub fn record(&mut self, record: OpportunityRecord) {
+        if self.archiving {
+            if self.unsaved.len() >= self.config.capacity.max(1) {
+                self.unsaved.pop_front();
+                self.unarchived += 1;
+            }
+            self.unsaved.push_back(record.clone());
+        }
+        if self.records.len() >= self.config.capacity.max(1) {
+            self.records.pop_front();
+        }
+        self.records.push_back(record);
+        self.total += 1;
+    }
+
+    /// Matching records, oldest first; with a limit, the most recent ones
+    pub fn query(&self, query: &OpportunityQuery) -> Vec<OpportunityRecord> {
+        let mut matching: Vec<OpportunityRecord> = self.records.iter().rev()
+            .filter(|r| query.matches(r))
+            .take(query.limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect();
+        matching.reverse();
+        matching
+    }
+
+    /// Buffered records per filter verdict
+    pub fn reasons(&self) -> FxHashMap<FilterReason, usize> {
+        let mut counts = FxHashMap::default();
+        for record in &self.records {
+            *counts.entry(record.reason).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Candidates each minimum profit would keep, among those dropped by the profit threshold or accepted
+    ///
+    /// Candidates dropped by spread, liquidity or execution filters are left out: a different
+    /// threshold would not have kept them.
+    pub fn threshold_sensitivity(&self, thresholds: &[f64]) -> Vec<ThresholdSensitivity> {
+        let eligible: Vec<f64> = self.records.iter()
+            .filter(|r| matches!(r.reason, FilterReason::Accepted | FilterReason::MinProfit))
+            .map(|r| r.profit)
+            .collect();
+        thresholds.iter()
+            .map(|&min_profit| {
+                let kept: Vec<f64> = eligible.iter().copied().filter(|&p| p >= min_profit).collect();
+                ThresholdSensitivity { min_profit, candidates: kept.len(), total_edge: kept.iter().sum() }
+            })
+            .collect()
+    }
+
+    /// Start or stop buffering records for the SQLite table, attached once the store is open
+    ///
+    /// Without a store nothing would drain the buffer, so records are only kept while archiving.
+    pub fn set_archiving(&mut self, archiving: bool) {
+        self.archiving = archiving;
+        if !archiving {
+            self.unsaved.clear();
+        }
+    }
+
+    /// Records not yet archived, handed to the SQLite table
+    pub fn take_unsaved(&mut self) -> Vec<OpportunityRecord> {
+        std::mem::take(&mut self.unsaved).into()
+    }
+
+    /// Records dropped from the archive buffer because flushes fell behind
+    pub fn unarchived(&self) -> u64 {
+        self.unarchived
+    }
+
+    /// Records ever logged, including those rotated out of the buffer
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arbitrage::{ArbitrageDetector, TradingCosts};
+    use crate::types::MarketData;
+
+    #[test]
+    fn test_detector_logs_every_candidate_with_its_filter() {
+        let mut detector = ArbitrageDetector::new(0.02, 1000.0)
+            .with_costs(TradingCosts { gas_per_trade: 0.0, ..TradingCosts::default() })
+            .with_history(OpportunityLogConfig { capacity: 4, sqlite_path: None });
+        let market = |id: &str, yes_price: f64, liquidity: f64| MarketData {
+            id: id.to_string(),
+            yes_price,
+            no_price: 0.50,
+            yes_liquidity: liquidity,
+            no_liquidity: liquidity,
+            ..MarketData::default()
+        };
+
+        // Margine 0.01 sotto soglia, liquidità insufficiente, accettata e poi duplicata
+        assert!(detector.detect_yes_no_arbitrage(&market("thin_edge", 0.49, 5000.0), None).is_none());
+        assert!(detector.detect_yes_no_arbitrage(&market("thin_book", 0.45, 100.0), None).is_none());
+        let accepted = detector.detect_yes_no_arbitrage(&market("good", 0.45, 5000.0), None).unwrap();
+        let now = accepted.timestamp;
+        assert_eq!(detector.dedup(vec![accepted.clone()], now).len(), 1);
+        detector.mark_reported(&accepted, now);
+        assert!(detector.dedup(vec![accepted], now).is_empty());
+
+        let log = detector.history.as_ref().unwrap().lock().unwrap();
+        let reasons: Vec<FilterReason> = log.query(&OpportunityQuery::default()).iter().map(|r| r.reason).collect();
+        assert_eq!(reasons, vec![FilterReason::MinProfit, FilterReason::Liquidity, FilterReason::Accepted, FilterReason::Duplicate]);
+        let dropped = log.query(&OpportunityQuery { reason: Some(FilterReason::MinProfit), ..Default::default() });
+        assert_eq!((dropped[0].market_id.as_str(), dropped[0].min_profit), ("thin_edge", 0.02));
+
+        // Con soglia 0.005 anche il candidato sotto soglia sarebbe passato
+        let sensitivity = log.threshold_sensitivity(&[0.005, 0.02]);
+        assert_eq!((sensitivity[0].candidates, sensitivity[1].candidates), (2, 1));
+        drop(log);
+
+        // Il ring buffer tiene solo gli ultimi record
+        detector.detect_yes_no_arbitrage(&market("thin_edge", 0.49, 5000.0), None);
+        let mut log = detector.history.as_ref().unwrap().lock().unwrap();
+        assert_eq!((log.len(), log.total()), (4, 5));
+        assert_eq!(log.query(&OpportunityQuery { limit: Some(1), ..Default::default() })[0].market_id, "thin_edge");
+
+        // Senza tabella aperta niente da archiviare; con la tabella, buffer limitato alla capacità
+        assert!(log.take_unsaved().is_empty());
+        log.set_archiving(true);
+        drop(log);
+        for _ in 0..6 {
+            detector.detect_yes_no_arbitrage(&market("thin_edge", 0.49, 5000.0), None);
+        }
+        let mut log = detector.history.as_ref().unwrap().lock().unwrap();
+        assert_eq!((log.take_unsaved().len(), log.unarchived()), (4, 2));
+    }
+}
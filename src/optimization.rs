@@ -4,15 +4,25 @@
 //! 1. Integer Programming for optimal arbitrage pair selection
 //! 2. Bregman Projection for arbitrage-free pricing
 //! 3. Frank-Wolfe algorithm for computational efficiency
+//! 4. Estimated maker liquidity rewards added to the opportunity score
+//! 5. Measured market impact deducted from the opportunity score
 
+use crate::impact::{ImpactModel, SharedImpactModel};
+use crate::rewards::RewardBook;
 use crate::types::*;
-use fxhash::FxHashMap;
-use rand::Rng;
 
 /// Statistical arbitrage optimizer
 pub struct StatisticalArbOptimizer {
     pub max_pairs: usize,
     pub min_liquidity: f64,
+    pub rewards: RewardBook, // Programmi di reward CLOB, sommati al ROI nello scoring
+    pub impact: SharedImpactModel, // Curve di impatto per mercato (le stesse dell'executor), dedotte dal ROI nello scoring
+}
+
+impl Default for StatisticalArbOptimizer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl StatisticalArbOptimizer {
@@ -20,6 +30,8 @@ impl StatisticalArbOptimizer {
         Self {
             max_pairs: 20,  // Aumentato da 10 a 20 per più opportunità
             min_liquidity: 500.0,  // Ridotto da 1000 a 500
+            rewards: RewardBook::new(),
+            impact: ImpactModel::default().shared(),
         }
     }
 
@@ -28,14 +40,23 @@ impl StatisticalArbOptimizer {
         opportunities: &[ArbitrageOpportunity],
         _capital: f64,
     ) -> Vec<ArbitrageOpportunity> {
+        self.select_pairs(opportunities)
+    }
+
+    /// Best opportunities by expected ROI, confidence and liquidity (synchronous core of the optimizer)
+    pub fn select_pairs(&self, opportunities: &[ArbitrageOpportunity]) -> Vec<ArbitrageOpportunity> {
         if opportunities.is_empty() {
             return Vec::new();
         }
 
+        // ROI atteso = arbitraggio + reward stimati degli ordini maker - impatto misurato
+        // (le opportunità maker hanno già i reward nel profitto)
+        let reward_pct = |opp: &ArbitrageOpportunity| if opp.arb_type == ArbType::YesNoMaker { 0.0 } else { self.rewards.reward_roi_pct(opp) };
+        let impact = self.impact.lock().unwrap();
         let filtered: Vec<_> = opportunities
             .iter()
-            .filter(|opp| opp.roi_pct > 1.0 && opp.liquidity >= self.min_liquidity)
-            .cloned()
+            .map(|opp| (opp.roi_pct + reward_pct(opp) - impact.cost_pct(opp), opp))
+            .filter(|(roi, opp)| *roi > 1.0 && opp.liquidity >= self.min_liquidity)
             .collect();
 
         if filtered.is_empty() {
@@ -45,9 +66,9 @@ impl StatisticalArbOptimizer {
         let mut scored: Vec<_> = filtered
             .iter()
             .enumerate()
-            .map(|(i, opp)| {
-                let score = opp.roi_pct * opp.confidence * opp.liquidity.sqrt() / 100.0;
-                (i, score, opp.clone())
+            .map(|(i, (roi, opp))| {
+                let score = roi * opp.confidence * opp.liquidity.sqrt() / 100.0;
+                (i, score, (*opp).clone())
             })
             .collect();
 
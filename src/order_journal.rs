@@ -0,0 +1,297 @@
+//! Order journal module
+//!
+//! Implements:
+//! 1. Write-ahead JSON-lines journal of every arbitrage execution: planned legs, each leg fill, outcome
+//! 2. Replay on startup, rebuilding the executions a previous process left half-completed
+//! 3. Recovery decision per half-completed execution: complete the missing legs while the edge
+//!    survives current prices, otherwise unwind the filled legs
+//! 4. Trade ids that keep increasing across restarts, so a recovered trade never reuses an id
+
+use crate::types::{ArbType, ArbitrageLeg, Direction, TokenType, TradeExecution};
+use crate::versioning::{read_lines, LineWriter, Schema};
+use chrono::{DateTime, Utc};
+use fxhash::FxHashMap;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Default journal location
+pub const ORDER_JOURNAL_PATH: &str = "./data/order_journal.jsonl";
+
+/// Journal file and recovery policy
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OrderJournalConfig {
+    pub path: PathBuf,
+    pub always_unwind: bool, // Al riavvio chiude sempre le gambe eseguite invece di completare l'arbitraggio
+}
+
+impl Default for OrderJournalConfig {
+    fn default() -> Self {
+        Self { path: PathBuf::from(ORDER_JOURNAL_PATH), always_unwind: false }
+    }
+}
+
+/// Schema of the journal lines
+pub const ORDER_JOURNAL_SCHEMA: Schema = Schema::new("order_journal", &[]);
+
+/// One journal line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum JournalEvent {
+    Started {
+        trade_id: String,
+        market_id: String,
+        arb_type: ArbType,
+        legs: Vec<ArbitrageLeg>, // Gambe pianificate, nell'ordine di invio
+        total_investment: f64,
+        expected_return: f64,
+        at: DateTime<Utc>,
+    },
+    LegFilled {
+        trade_id: String,
+        leg: usize,
+        price: f64,
+        quantity: f64,
+        at: DateTime<Utc>,
+    },
+    Completed { trade_id: String, at: DateTime<Utc> },
+    Unwound { trade_id: String, pnl: f64, at: DateTime<Utc> },
+}
+
+/// Fill of one planned leg
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LegFill {
+    pub price: f64,
+    pub quantity: f64,
+}
+
+/// Execution started but not yet completed or unwound
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenExecution {
+    pub trade_id: String,
+    pub market_id: String,
+    pub arb_type: ArbType,
+    pub legs: Vec<ArbitrageLeg>,
+    pub fills: Vec<Option<LegFill>>, // Una voce per gamba pianificata
+    pub total_investment: f64,
+    pub expected_return: f64,
+    pub started_at: DateTime<Utc>,
+}
+
+impl OpenExecution {
+    pub fn is_filled(&self) -> bool {
+        self.fills.iter().all(Option::is_some)
+    }
+}
+
+/// How a half-completed execution is resolved
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecoveryAction {
+    Complete, // Gambe mancanti inviate ai prezzi correnti
+    Unwind,   // Gambe eseguite chiuse ai prezzi correnti
+}
+
+/// Recovery plan of one open execution
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecoveryPlan {
+    pub action: RecoveryAction,
+    pub prices: Vec<f64>, // Prezzo corrente di ogni gamba pianificata
+    pub pnl: f64,         // Completamento: variazione del rendimento; unwind: PnL realizzato chiudendo
+}
+
+impl RecoveryPlan {
+    /// Complete when every missing leg is quoted and costs less extra than the planned profit, otherwise unwind
+    ///
+    /// Returns None while a filled leg has no quote to unwind at: the execution stays open until it has.
+    pub fn decide(open: &OpenExecution, quote: impl Fn(&str, TokenType) -> Option<f64>, always_unwind: bool) -> Option<Self> {
+        let prices: Vec<Option<f64>> = open.legs.iter().map(|l| quote(&l.market_id, l.token_type)).collect();
+        let legs = open.legs.iter().zip(&open.fills).zip(&prices);
+
+        // Costo aggiuntivo delle gambe mancanti rispetto al piano (negativo se il prezzo è migliorato)
+        let missing: Option<f64> = legs.clone()
+            .filter(|((_, fill), _)| fill.is_none())
+            .map(|((leg, _), price)| price.map(|p| signed(leg.direction, p - leg.price) * leg.quantity))
+            .sum();
+        let planned_profit = open.expected_return - open.total_investment;
+        if let Some(extra_cost) = missing.filter(|extra| !always_unwind && *extra < planned_profit) {
+            let prices = prices.iter().zip(&open.legs).map(|(p, l)| p.unwrap_or(l.price)).collect();
+            return Some(Self { action: RecoveryAction::Complete, prices, pnl: -extra_cost });
+        }
+
+        // Chiusura delle gambe eseguite: si rivende ciò che è stato comprato e viceversa
+        let pnl: f64 = legs.clone()
+            .filter_map(|((leg, fill), price)| fill.map(|f| (leg, f, *price)))
+            .map(|(leg, fill, price)| price.map(|p| signed(leg.direction, p - fill.price) * fill.quantity))
+            .sum::<Option<f64>>()?;
+        let prices = prices.iter().zip(&open.legs).map(|(p, l)| p.unwrap_or(l.price)).collect();
+        Some(Self { action: RecoveryAction::Unwind, prices, pnl })
+    }
+}
+
+/// Outcome of the recovery of one half-completed execution
+#[derive(Debug, Clone)]
+pub struct RecoveredExecution {
+    pub trade_id: String,
+    pub market_id: String,
+    pub action: RecoveryAction,
+    pub pnl: f64,                      // PnL realizzato: profitto del trade completato o chiusura delle gambe
+    pub trade: Option<TradeExecution>, // Trade completato, assente se l'esecuzione è stata chiusa
+}
+
+/// Price move in favour of a position on the given side
+fn signed(direction: Direction, delta: f64) -> f64 {
+    match direction {
+        Direction::Buy => delta,
+        Direction::Sell => -delta,
+    }
+}
+
+/// Append-only execution journal; in-memory when it has no path
+#[derive(Debug, Clone, Default)]
+pub struct OrderJournal {
+    pub path: Option<PathBuf>,
+    open: FxHashMap<String, OpenExecution>,
+    sequence: u64, // Numero dell'ultimo trade id assegnato
+    writer: LineWriter,
+}
+
+impl OrderJournal {
+    /// Replay the journal file (empty if it does not exist yet)
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, String> {
+        let path = path.into();
+        let mut journal = Self { path: Some(path.clone()), ..Self::default() };
+
+        let events = read_lines::<JournalEvent>(&ORDER_JOURNAL_SCHEMA, &path)
+            .map_err(|e| format!("Failed to read order journal: {}", e))?;
+        // Una riga troncata da un crash durante la scrittura non ha effetti
+        for event in events.into_iter().flatten() {
+            journal.apply(event);
+        }
+
+        Ok(journal)
+    }
+
+    /// Update the in-memory state with one event
+    fn apply(&mut self, event: JournalEvent) {
+        match event {
+            JournalEvent::Started { trade_id, market_id, arb_type, legs, total_investment, expected_return, at } => {
+                if let Some(n) = trade_id.strip_prefix("trade_").and_then(|n| n.parse::<u64>().ok()) {
+                    self.sequence = self.sequence.max(n);
+                }
+                let fills = vec![None; legs.len()];
+                self.open.insert(trade_id.clone(), OpenExecution {
+                    trade_id,
+                    market_id,
+                    arb_type,
+                    legs,
+                    fills,
+                    total_investment,
+                    expected_return,
+                    started_at: at,
+                });
+            }
+            JournalEvent::LegFilled { trade_id, leg, price, quantity, .. } => {
+                if let Some(fill) = self.open.get_mut(&trade_id).and_then(|o| o.fills.get_mut(leg)) {
+                    *fill = Some(LegFill { price, quantity });
+                }
+            }
+            JournalEvent::Completed { trade_id, .. } | JournalEvent::Unwound { trade_id, .. } => {
+                self.open.remove(&trade_id);
+            }
+        }
+    }
+
+    /// Persist an event, then apply it
+    pub fn append(&mut self, event: JournalEvent) -> Result<(), String> {
+        if let Some(path) = &self.path {
+            let line = serde_json::to_string(&event)
+                .map_err(|e| format!("Failed to serialize journal event: {}", e))?;
+            if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                std::fs::create_dir_all(parent)
+                    .map_err(|e| format!("Failed to create journal directory: {}", e))?;
+            }
+            let file = self.writer.append(&ORDER_JOURNAL_SCHEMA, path, &[line])
+                .map_err(|e| format!("Failed to write order journal: {}", e))?;
+            // L'evento deve essere su disco prima dell'ordine successivo
+            file.sync_data().map_err(|e| format!("Failed to sync order journal: {}", e))?;
+        }
+        self.apply(event);
+        Ok(())
+    }
+
+    /// Next trade id, never reused across restarts
+    pub fn next_trade_id(&mut self) -> String {
+        self.sequence += 1;
+        format!("trade_{}", self.sequence)
+    }
+
+    /// Executions neither completed nor unwound, oldest first
+    pub fn open_executions(&self) -> Vec<&OpenExecution> {
+        let mut open: Vec<&OpenExecution> = self.open.values().collect();
+        open.sort_by_key(|o| (o.started_at, o.trade_id.clone()));
+        open
+    }
+
+    pub fn has_open(&self) -> bool {
+        !self.open.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::execution::TradeExecutor;
+    use crate::types::BotConfig;
+
+    fn leg(market_id: &str, token_type: TokenType, price: f64) -> ArbitrageLeg {
+        ArbitrageLeg { market_id: market_id.to_string(), token_type, direction: Direction::Buy, price, quantity: 100.0, token_id: None, venue: None }
+    }
+
+    /// Journal of a process that died after filling only the YES leg of a 0.45 + 0.50 pair
+    fn crashed_journal(path: &PathBuf, trade_id: &str) {
+        let mut journal = OrderJournal::load(path).unwrap();
+        journal.append(JournalEvent::Started {
+            trade_id: trade_id.to_string(),
+            market_id: "m1".to_string(),
+            arb_type: ArbType::YesNoSimple,
+            legs: vec![leg("m1", TokenType::Yes, 0.45), leg("m1", TokenType::No, 0.50)],
+            total_investment: 95.0,
+            expected_return: 100.0,
+            at: Utc::now(),
+        }).unwrap();
+        journal.append(JournalEvent::LegFilled { trade_id: trade_id.to_string(), leg: 0, price: 0.45, quantity: 100.0, at: Utc::now() }).unwrap();
+    }
+
+    #[test]
+    fn test_restart_completes_or_unwinds_half_filled_executions() {
+        let path = std::env::temp_dir().join(format!("journal_{}.jsonl", uuid::Uuid::new_v4()));
+        let config = BotConfig { order_journal: Some(OrderJournalConfig { path: path.clone(), always_unwind: false }), ..BotConfig::default() };
+        crashed_journal(&path, "trade_7");
+
+        // NO salito a 0.52: il margine regge, la gamba mancante viene completata
+        let mut executor = TradeExecutor::new(config.clone());
+        assert!(executor.journal.has_open());
+        let quote = |_: &str, token_type: TokenType| Some(if token_type == TokenType::Yes { 0.44 } else { 0.52 });
+        let recovered = executor.recover_open_executions(quote, false);
+        assert_eq!(recovered[0].action, RecoveryAction::Complete);
+        assert!((recovered[0].pnl - 3.0).abs() < 1e-9);
+        assert_eq!(executor.executed_trades[0].legs[1].price, 0.52);
+
+        // Rieseguire il recupero non ripete nulla; gli id dei trade proseguono dopo il riavvio
+        let mut restarted = TradeExecutor::new(config.clone());
+        assert!(restarted.recover_open_executions(quote, false).is_empty());
+        assert_eq!(restarted.journal.next_trade_id(), "trade_8");
+
+        // NO a 0.60: completare costerebbe più del margine, la gamba YES viene chiusa in perdita
+        crashed_journal(&path, "trade_9");
+        let mut executor = TradeExecutor::new(config);
+        let quote = |_: &str, token_type: TokenType| Some(if token_type == TokenType::Yes { 0.40 } else { 0.60 });
+        let recovered = executor.recover_open_executions(quote, false);
+        assert_eq!(recovered[0].action, RecoveryAction::Unwind);
+        assert!((recovered[0].pnl + 5.0).abs() < 1e-9);
+        assert!(recovered[0].trade.is_none());
+        assert!(!OrderJournal::load(&path).unwrap().has_open());
+        std::fs::remove_file(path).ok();
+    }
+}
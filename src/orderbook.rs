@@ -0,0 +1,324 @@
+//! Local order book module
+//!
+//! Implements:
+//! 1. Per-asset order book built from WebSocket (or REST) `book` snapshots
+//! 2. `price_change` deltas applied in timestamp order
+//! 3. Gap detection (delta without snapshot, best bid/ask mismatch) flagging assets for a snapshot refresh
+//! 4. YES/NO book pairs of a market, for depth-aware arbitrage sizing
+
+use crate::types::{de_f64, de_opt_f64, de_u64, TokenPair};
+use fxhash::{FxHashMap, FxHashSet};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Price levels are keyed in units of 1e-4 (CLOB ticks are at least 0.0001)
+const PRICE_SCALE: f64 = 10_000.0;
+
+fn price_key(price: f64) -> u32 {
+    (price * PRICE_SCALE).round().max(0.0) as u32
+}
+
+fn key_price(key: u32) -> f64 {
+    key as f64 / PRICE_SCALE
+}
+
+/// Price level of a WebSocket orderbook snapshot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WsOrderLevel {
+    #[serde(deserialize_with = "de_f64")]
+    pub price: f64,
+    #[serde(deserialize_with = "de_f64")]
+    pub size: f64,
+}
+
+/// `book` event: full orderbook snapshot for one asset
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WsBookEvent {
+    pub asset_id: String,
+    #[serde(default)]
+    pub market: String,
+    #[serde(default, alias = "buys")]
+    pub bids: Vec<WsOrderLevel>,
+    #[serde(default, alias = "sells")]
+    pub asks: Vec<WsOrderLevel>,
+    #[serde(default, deserialize_with = "de_u64")]
+    pub timestamp: u64,
+    #[serde(default)]
+    pub hash: Option<String>,
+}
+
+impl WsBookEvent {
+    pub fn best_bid(&self) -> Option<&WsOrderLevel> {
+        self.bids.iter().max_by(|a, b| a.price.total_cmp(&b.price))
+    }
+
+    pub fn best_ask(&self) -> Option<&WsOrderLevel> {
+        self.asks.iter().min_by(|a, b| a.price.total_cmp(&b.price))
+    }
+}
+
+/// Single level change inside a `price_change` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WsLevelChange {
+    #[serde(deserialize_with = "de_f64")]
+    pub price: f64,
+    pub side: String, // "BUY" (bid) o "SELL" (ask)
+    #[serde(deserialize_with = "de_f64")]
+    pub size: f64,
+    #[serde(default, deserialize_with = "de_opt_f64")]
+    pub best_bid: Option<f64>,
+    #[serde(default, deserialize_with = "de_opt_f64")]
+    pub best_ask: Option<f64>,
+}
+
+/// `price_change` event: incremental level updates for one asset
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WsPriceChange {
+    pub asset_id: String,
+    #[serde(default)]
+    pub market: String,
+    #[serde(default)]
+    pub changes: Vec<WsLevelChange>,
+    #[serde(default, deserialize_with = "de_u64")]
+    pub timestamp: u64,
+    #[serde(default)]
+    pub hash: Option<String>,
+}
+
+/// Why a local book can no longer be trusted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BookGap {
+    NoSnapshot,       // Delta ricevuto prima di qualsiasi snapshot
+    TopOfBookMismatch, // Best bid/ask del server diversi da quelli locali dopo il delta
+}
+
+/// Order book of one asset, rebuilt from a snapshot plus deltas
+#[derive(Debug, Clone, Default)]
+pub struct LocalOrderBook {
+    pub asset_id: String,
+    bids: BTreeMap<u32, f64>,
+    asks: BTreeMap<u32, f64>,
+    pub timestamp: u64, // Millisecondi dell'ultimo snapshot o delta applicato
+    pub hash: Option<String>,
+}
+
+impl LocalOrderBook {
+    pub fn from_snapshot(book: &WsBookEvent) -> Self {
+        let levels = |levels: &[WsOrderLevel]| {
+            levels
+                .iter()
+                .filter(|l| l.size > 0.0)
+                .map(|l| (price_key(l.price), l.size))
+                .collect::<BTreeMap<u32, f64>>()
+        };
+        Self {
+            asset_id: book.asset_id.clone(),
+            bids: levels(&book.bids),
+            asks: levels(&book.asks),
+            timestamp: book.timestamp,
+            hash: book.hash.clone(),
+        }
+    }
+
+    /// Apply a `price_change`; deltas older than the book are already part of it and are skipped
+    pub fn apply_delta(&mut self, change: &WsPriceChange) -> Result<bool, BookGap> {
+        if change.timestamp < self.timestamp {
+            return Ok(false);
+        }
+
+        for level in &change.changes {
+            let side = if level.side.eq_ignore_ascii_case("BUY") { &mut self.bids } else { &mut self.asks };
+            if level.size > 0.0 {
+                side.insert(price_key(level.price), level.size);
+            } else {
+                side.remove(&price_key(level.price));
+            }
+        }
+        self.timestamp = change.timestamp;
+        self.hash = change.hash.clone().or(self.hash.take());
+
+        // Il server riporta il top of book risultante: se diverge, abbiamo perso un messaggio
+        let matches = |reported: Option<f64>, local: Option<f64>| {
+            reported.is_none_or(|r| match local {
+                Some(l) => price_key(r) == price_key(l),
+                None => r <= 0.0 || r >= 1.0, // Lato vuoto riportato come 0 o 1
+            })
+        };
+        if let Some(last) = change.changes.last() {
+            if !matches(last.best_bid, self.best_bid().map(|l| l.price)) || !matches(last.best_ask, self.best_ask().map(|l| l.price)) {
+                return Err(BookGap::TopOfBookMismatch);
+            }
+        }
+        Ok(true)
+    }
+
+    pub fn best_bid(&self) -> Option<WsOrderLevel> {
+        self.bids.iter().next_back().map(|(&k, &size)| WsOrderLevel { price: key_price(k), size })
+    }
+
+    pub fn best_ask(&self) -> Option<WsOrderLevel> {
+        self.asks.iter().next().map(|(&k, &size)| WsOrderLevel { price: key_price(k), size })
+    }
+
+    /// Bids, best (highest) first
+    pub fn bids(&self) -> Vec<WsOrderLevel> {
+        self.bids.iter().rev().map(|(&k, &size)| WsOrderLevel { price: key_price(k), size }).collect()
+    }
+
+    /// Asks, best (lowest) first
+    pub fn asks(&self) -> Vec<WsOrderLevel> {
+        self.asks.iter().map(|(&k, &size)| WsOrderLevel { price: key_price(k), size }).collect()
+    }
+
+    pub fn spread(&self) -> Option<f64> {
+        Some((self.best_ask()?.price - self.best_bid()?.price).max(0.0))
+    }
+
+    /// Book in the snapshot format of the `book` event
+    pub fn to_snapshot(&self) -> WsBookEvent {
+        WsBookEvent {
+            asset_id: self.asset_id.clone(),
+            market: String::new(),
+            bids: self.bids(),
+            asks: self.asks(),
+            timestamp: self.timestamp,
+            hash: self.hash.clone(),
+        }
+    }
+}
+
+/// Local books of every subscribed asset, with the assets awaiting a snapshot refresh
+#[derive(Debug, Clone, Default)]
+pub struct OrderBookStore {
+    books: FxHashMap<String, LocalOrderBook>,
+    needs_snapshot: FxHashSet<String>,
+    pub gaps: u64, // Gap rilevati dall'avvio
+}
+
+impl OrderBookStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the book of an asset with a snapshot (clears a pending refresh)
+    pub fn apply_snapshot(&mut self, book: &WsBookEvent) {
+        // Snapshot più vecchio del libro locale (es. REST in ritardo sul WebSocket): ignorato
+        if self.books.get(&book.asset_id).is_some_and(|local| local.timestamp > book.timestamp) && !self.needs_snapshot.contains(&book.asset_id) {
+            return;
+        }
+        self.books.insert(book.asset_id.clone(), LocalOrderBook::from_snapshot(book));
+        self.needs_snapshot.remove(&book.asset_id);
+    }
+
+    /// Apply a delta; on a gap the book is dropped and the asset flagged for a snapshot refresh
+    pub fn apply_delta(&mut self, change: &WsPriceChange) -> Result<bool, BookGap> {
+        let result = match self.books.get_mut(&change.asset_id) {
+            Some(book) => book.apply_delta(change),
+            None => Err(BookGap::NoSnapshot),
+        };
+        if let Err(gap) = result {
+            if self.needs_snapshot.insert(change.asset_id.clone()) {
+                self.gaps += 1;
+                eprintln!("⚠️  Order book gap on {} ({:?}), snapshot refresh scheduled", change.asset_id, gap);
+            }
+            self.books.remove(&change.asset_id);
+        }
+        result
+    }
+
+    pub fn get(&self, asset_id: &str) -> Option<&LocalOrderBook> {
+        self.books.get(asset_id)
+    }
+
+    /// YES and NO books of a market, when both are held
+    pub fn token_books(&self, tokens: &TokenPair) -> Option<(&LocalOrderBook, &LocalOrderBook)> {
+        Some((self.get(&tokens.yes_token_id)?, self.get(&tokens.no_token_id)?))
+    }
+
+    /// Copy holding only the books of the given assets
+    pub fn subset<'a>(&self, asset_ids: impl IntoIterator<Item = &'a str>) -> Self {
+        let books = asset_ids
+            .into_iter()
+            .filter_map(|id| self.books.get(id))
+            .map(|book| (book.asset_id.clone(), book.clone()))
+            .collect();
+        Self { books, needs_snapshot: FxHashSet::default(), gaps: self.gaps }
+    }
+
+    /// Assets whose book must be refreshed from a snapshot
+    pub fn pending_snapshots(&self) -> Vec<String> {
+        let mut assets: Vec<String> = self.needs_snapshot.iter().cloned().collect();
+        assets.sort();
+        assets
+    }
+
+    pub fn clear(&mut self) {
+        self.books.clear();
+        self.needs_snapshot.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(price: f64, size: f64) -> WsOrderLevel {
+        WsOrderLevel { price, size }
+    }
+
+    fn change(timestamp: u64, side: &str, price: f64, size: f64, best_bid: f64, best_ask: f64) -> WsPriceChange {
+        WsPriceChange {
+            asset_id: "111".to_string(),
+            market: String::new(),
+            changes: vec![WsLevelChange { price, side: side.to_string(), size, best_bid: Some(best_bid), best_ask: Some(best_ask) }],
+            timestamp,
+            hash: None,
+        }
+    }
+
+    #[test]
+    fn test_snapshot_deltas_and_gap_refresh() {
+        let snapshot = WsBookEvent {
+            asset_id: "111".to_string(),
+            market: String::new(),
+            bids: vec![level(0.48, 30.0), level(0.49, 20.0)],
+            asks: vec![level(0.52, 25.0), level(0.51, 10.0)],
+            timestamp: 1_000,
+            hash: Some("h0".to_string()),
+        };
+        let mut store = OrderBookStore::new();
+
+        // Delta prima dello snapshot: gap
+        assert_eq!(store.apply_delta(&change(900, "BUY", 0.50, 5.0, 0.50, 0.51)), Err(BookGap::NoSnapshot));
+        assert_eq!(store.pending_snapshots(), vec!["111".to_string()]);
+
+        store.apply_snapshot(&snapshot);
+        assert!(store.pending_snapshots().is_empty());
+
+        // Nuovo best bid, poi il best ask viene consumato
+        assert_eq!(store.apply_delta(&change(1_100, "BUY", 0.50, 5.0, 0.50, 0.51)), Ok(true));
+        assert_eq!(store.apply_delta(&change(1_200, "SELL", 0.51, 0.0, 0.50, 0.52)), Ok(true));
+        // Delta già incluso nello snapshot: ignorato
+        assert_eq!(store.apply_delta(&change(950, "SELL", 0.40, 1.0, 0.50, 0.40)), Ok(false));
+
+        let book = store.get("111").unwrap();
+        assert_eq!(book.best_bid().unwrap().price, 0.50);
+        assert_eq!(book.best_ask().unwrap().price, 0.52);
+        assert_eq!(book.bids().iter().map(|l| l.price).collect::<Vec<_>>(), vec![0.50, 0.49, 0.48]);
+        assert!((book.spread().unwrap() - 0.02).abs() < 1e-9);
+
+        // Il server riporta un best ask che non abbiamo: messaggio perso
+        assert_eq!(store.apply_delta(&change(1_300, "BUY", 0.47, 1.0, 0.50, 0.515)), Err(BookGap::TopOfBookMismatch));
+        assert!(store.get("111").is_none());
+        assert_eq!(store.gaps, 2);
+
+        store.apply_snapshot(&WsBookEvent { timestamp: 1_400, ..snapshot });
+        assert_eq!(store.get("111").unwrap().best_ask().unwrap().price, 0.51);
+
+        // Livelli con prezzo non finito rifiutati in deserializzazione
+        assert!(serde_json::from_str::<WsOrderLevel>(r#"{"price": "NaN", "size": "10"}"#).is_err());
+        assert!(serde_json::from_str::<WsOrderLevel>(r#"{"price": "0.5", "size": "inf"}"#).is_err());
+        assert_eq!(serde_json::from_str::<WsOrderLevel>(r#"{"price": "0.5", "size": 10}"#).unwrap().price, 0.5);
+    }
+}
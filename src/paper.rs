@@ -0,0 +1,740 @@
+//! Paper trading broker
+//!
+//! Implements:
+//! 1. Shared ledger for bot and manual (dashboard) paper trades
+//! 2. Position tracking with average entry price
+//! 3. Pre-trade risk checks (cash, per-trade size, per-market exposure)
+//! 4. Capital efficiency tracking (deployed capital, turnover)
+//! 5. Conditional exits: per-position stop-loss and take-profit, defaulted per strategy
+//! 6. Partial liquidation: trims that free cash for a trade the balance cannot fund
+
+use crate::analytics::{CapitalEfficiency, LiquidationPlan};
+use crate::risk::DrawdownJournal;
+use crate::clock::{system_clock, SharedClock};
+use crate::types::*;
+use chrono::{DateTime, Utc};
+use fxhash::FxHashMap;
+use serde::{Deserialize, Serialize};
+
+/// Origin of a paper trade
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+pub enum TradeSource {
+    #[default]
+    Bot,
+    Manual,
+}
+
+/// Stato globale del bot per dashboard
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BotState {
+    pub running: bool,
+    pub balance: f64,
+    pub initial_balance: f64,
+    pub total_pnl: f64,
+    pub win_rate: f64,
+    pub total_trades: usize,
+    pub profitable_trades: usize,
+    pub last_update: DateTime<Utc>,
+}
+
+/// Trade simulato con dati reali per backtesting
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct SimulatedTrade {
+    pub id: String,
+    pub market_id: String,
+    pub question: String,
+    pub action: String, // "BUY_YES", "BUY_NO", "SELL_YES", "SELL_NO"
+    pub price: f64,
+    pub quantity: f64,
+    pub amount: f64,
+    pub timestamp: DateTime<Utc>,
+    pub status: String, // "PENDING", "FILLED", "CANCELLED"
+    pub pnl: f64,
+    pub arbitrage_profit: f64, // Profitto di arbitraggio simulato
+    #[serde(default)]
+    pub source: TradeSource, // Bot o trade manuale da dashboard
+}
+
+/// Informazioni mercato reale
+#[derive(Clone, Serialize, Deserialize)]
+pub struct MarketInfo {
+    pub id: String,
+    pub question: String,
+    pub yes_price: f64,
+    pub no_price: f64,
+    pub yes_liquidity: f64,
+    pub no_liquidity: f64,
+    pub volume_24h: f64,
+    pub timestamp: DateTime<Utc>,
+    #[serde(default)]
+    pub event_id: Option<String>, // Evento di appartenenza, per raggruppare i mercati
+    #[serde(default)]
+    pub category: Option<String>, // Categoria del mercato, per l'analisi dei trade
+}
+
+impl From<&MarketData> for MarketInfo {
+    fn from(market: &MarketData) -> Self {
+        MarketInfo {
+            id: market.id.clone(),
+            question: market.question.clone(),
+            yes_price: market.yes_price,
+            no_price: market.no_price,
+            yes_liquidity: market.yes_liquidity,
+            no_liquidity: market.no_liquidity,
+            volume_24h: market.volume_24h,
+            timestamp: market.timestamp,
+            event_id: market.event_id.clone(),
+            category: market.category.clone(),
+        }
+    }
+}
+
+impl From<&MarketInfo> for MarketData {
+    fn from(market: &MarketInfo) -> Self {
+        MarketData {
+            id: market.id.clone(),
+            question: market.question.clone(),
+            yes_price: market.yes_price,
+            no_price: market.no_price,
+            yes_liquidity: market.yes_liquidity,
+            no_liquidity: market.no_liquidity,
+            timestamp: market.timestamp,
+            volume_24h: market.volume_24h,
+            event_id: market.event_id.clone(),
+            category: market.category.clone(),
+            ..MarketData::default()
+        }
+    }
+}
+
+/// Open paper position on one outcome token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaperPosition {
+    pub market_id: String,
+    pub question: String,
+    pub token_type: TokenType,
+    pub quantity: f64,
+    pub avg_price: f64,
+    pub opened_at: DateTime<Utc>,
+    #[serde(default)]
+    pub source: TradeSource, // Strategia che ha aperto la posizione
+    #[serde(default)]
+    pub stop_loss: Option<f64>, // Chiusura automatica se il mark scende a questo prezzo
+    #[serde(default)]
+    pub take_profit: Option<f64>, // Chiusura automatica se il mark sale a questo prezzo
+}
+
+impl PaperPosition {
+    pub fn cost_basis(&self) -> f64 {
+        self.quantity * self.avg_price
+    }
+
+    /// Conditional order triggered by `mark`, if any (stop-loss wins when both levels are crossed)
+    pub fn exit_triggered(&self, mark: f64) -> Option<ExitReason> {
+        if self.stop_loss.is_some_and(|level| mark <= level) {
+            Some(ExitReason::StopLoss)
+        } else if self.take_profit.is_some_and(|level| mark >= level) {
+            Some(ExitReason::TakeProfit)
+        } else {
+            None
+        }
+    }
+}
+
+/// Why a position was closed by a conditional order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExitReason {
+    StopLoss,
+    TakeProfit,
+}
+
+impl ExitReason {
+    fn verb(&self) -> &'static str {
+        match self {
+            ExitReason::StopLoss => "STOP_LOSS",
+            ExitReason::TakeProfit => "TAKE_PROFIT",
+        }
+    }
+}
+
+/// Default exit levels of a strategy, relative to the entry price (0.2 = 20%)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExitPolicy {
+    pub stop_loss_pct: Option<f64>,
+    pub take_profit_pct: Option<f64>,
+}
+
+impl ExitPolicy {
+    /// (stop_loss, take_profit) prices for a position entered at `entry_price`
+    pub fn levels(&self, entry_price: f64) -> (Option<f64>, Option<f64>) {
+        (
+            self.stop_loss_pct.map(|pct| entry_price * (1.0 - pct)),
+            self.take_profit_pct.map(|pct| entry_price * (1.0 + pct)),
+        )
+    }
+}
+
+/// Ledger entry: one cash movement
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    pub trade_id: String,
+    pub source: TradeSource,
+    pub market_id: String,
+    pub action: String,
+    pub cash_delta: f64,
+    pub realized_pnl: f64,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Paper order submitted to the broker
+#[derive(Debug, Clone)]
+pub struct PaperOrder {
+    pub market_id: String,
+    pub token_type: TokenType,
+    pub direction: Direction,
+    pub quantity: f64,
+    pub limit_price: Option<f64>,
+    pub source: TradeSource,
+}
+
+/// Risk limits applied to every paper trade
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PaperRiskLimits {
+    pub max_trade_fraction: f64,     // Quota massima del balance per singolo trade
+    pub max_market_exposure: f64,    // Esposizione massima (costo) per mercato, in USDC
+}
+
+impl Default for PaperRiskLimits {
+    fn default() -> Self {
+        Self {
+            max_trade_fraction: 0.10,
+            max_market_exposure: 2500.0,
+        }
+    }
+}
+
+/// Paper broker shared by the bot loop and the dashboard
+#[derive(Debug, Clone)]
+pub struct PaperBroker {
+    pub positions: FxHashMap<String, PaperPosition>,
+    pub ledger: Vec<LedgerEntry>,
+    pub limits: PaperRiskLimits,
+    pub efficiency: CapitalEfficiency,
+    pub drawdowns: DrawdownJournal, // Episodi di drawdown del PnL realizzato
+    pub exit_policies: FxHashMap<TradeSource, ExitPolicy>, // Stop-loss/take-profit di default per strategia
+    pub clock: SharedClock, // Timestamp di fill e ledger (simulato nei backtest)
+}
+
+impl Default for PaperBroker {
+    fn default() -> Self {
+        Self::new(PaperRiskLimits::default())
+    }
+}
+
+impl PaperBroker {
+    pub fn new(limits: PaperRiskLimits) -> Self {
+        Self::with_clock(limits, system_clock())
+    }
+
+    pub fn with_clock(limits: PaperRiskLimits, clock: SharedClock) -> Self {
+        Self {
+            positions: FxHashMap::default(),
+            ledger: Vec::new(),
+            limits,
+            efficiency: CapitalEfficiency::new(),
+            drawdowns: DrawdownJournal::new(),
+            exit_policies: FxHashMap::default(),
+            clock,
+        }
+    }
+
+    fn position_key(market_id: &str, token_type: TokenType) -> String {
+        format!("{}-{}", market_id, token_type)
+    }
+
+    /// Open positions, sorted by market
+    pub fn open_positions(&self) -> Vec<PaperPosition> {
+        let mut positions: Vec<PaperPosition> = self.positions.values().cloned().collect();
+        positions.sort_by(|a, b| a.market_id.cmp(&b.market_id).then_with(|| a.token_type.to_string().cmp(&b.token_type.to_string())));
+        positions
+    }
+
+    /// Set the default exit levels applied to new positions of a strategy
+    pub fn set_exit_policy(&mut self, source: TradeSource, policy: ExitPolicy) {
+        self.exit_policies.insert(source, policy);
+    }
+
+    /// Override the exit levels of one open position (None removes the level)
+    pub fn set_exit_levels(&mut self, market_id: &str, token_type: TokenType, stop_loss: Option<f64>, take_profit: Option<f64>) -> Result<PaperPosition, String> {
+        let position = self.positions
+            .get_mut(&Self::position_key(market_id, token_type))
+            .ok_or_else(|| format!("No open {} position in {}", token_type, market_id))?;
+        if let (Some(stop), Some(target)) = (stop_loss, take_profit) {
+            if stop >= target {
+                return Err(format!("Stop-loss {:.4} must be below take-profit {:.4}", stop, target));
+            }
+        }
+        position.stop_loss = stop_loss;
+        position.take_profit = take_profit;
+        Ok(position.clone())
+    }
+
+    /// Close every position whose stop-loss or take-profit is crossed by the current marks
+    pub fn check_exits(&mut self, state: &mut BotState, markets: &[MarketInfo]) -> Vec<SimulatedTrade> {
+        let quotes: FxHashMap<&str, &MarketInfo> = markets.iter().map(|m| (m.id.as_str(), m)).collect();
+        let mut triggered: Vec<(PaperOrder, ExitReason)> = self.positions
+            .values()
+            .filter_map(|p| {
+                let market = quotes.get(p.market_id.as_str())?;
+                let mark = match p.token_type {
+                    TokenType::Yes => market.yes_price,
+                    TokenType::No => market.no_price,
+                };
+                let reason = p.exit_triggered(mark)?;
+                let order = PaperOrder {
+                    market_id: p.market_id.clone(),
+                    token_type: p.token_type,
+                    direction: Direction::Sell,
+                    quantity: p.quantity,
+                    limit_price: None,
+                    source: p.source,
+                };
+                Some((order, reason))
+            })
+            .collect();
+        triggered.sort_by(|a, b| a.0.market_id.cmp(&b.0.market_id)); // Ordine deterministico nel ledger
+
+        triggered
+            .into_iter()
+            .filter_map(|(order, reason)| {
+                let market = quotes[order.market_id.as_str()];
+                let price = match order.token_type {
+                    TokenType::Yes => market.yes_price,
+                    TokenType::No => market.no_price,
+                };
+                self.close(state, &order, market, price, reason.verb()).ok()
+            })
+            .collect()
+    }
+
+    /// Total cost basis held in a market (both outcomes)
+    pub fn market_exposure(&self, market_id: &str) -> f64 {
+        self.positions
+            .values()
+            .filter(|p| p.market_id == market_id)
+            .map(|p| p.cost_basis())
+            .sum()
+    }
+
+    /// Total cost basis of all open positions
+    pub fn deployed_capital(&self) -> f64 {
+        self.positions.values().map(|p| p.cost_basis()).sum()
+    }
+
+    /// Sample deployed capital: open positions plus capital in flight for round trips
+    pub fn sample_deployed(&mut self, in_flight: f64) {
+        let deployed = self.deployed_capital() + in_flight;
+        self.efficiency.sample_deployed(deployed);
+    }
+
+    /// Pre-trade risk checks for new exposure
+    pub fn check_risk(&self, state: &BotState, market_id: &str, amount: f64) -> Result<(), String> {
+        if amount <= 0.0 {
+            return Err("Trade amount must be positive".to_string());
+        }
+        if amount > state.balance {
+            return Err(format!("Insufficient balance: {:.2} required, {:.2} available", amount, state.balance));
+        }
+        if amount > state.balance * self.limits.max_trade_fraction {
+            return Err(format!(
+                "Trade size {:.2} exceeds {:.0}% of balance",
+                amount,
+                self.limits.max_trade_fraction * 100.0
+            ));
+        }
+        if self.market_exposure(market_id) + amount > self.limits.max_market_exposure {
+            return Err(format!("Market exposure limit {:.2} exceeded for {}", self.limits.max_market_exposure, market_id));
+        }
+        Ok(())
+    }
+
+    /// Cash missing to open `order` at `price`: both the balance and the per-trade fraction limit must cover it
+    ///
+    /// Fails on the checks that freeing cash cannot fix (limit price, size, per-market exposure),
+    /// so no position is liquidated for a trade that would be refused anyway.
+    pub fn capital_shortfall(&self, state: &BotState, order: &PaperOrder, price: f64) -> Result<f64, String> {
+        Self::check_limit(order, price)?;
+        let amount = price * order.quantity;
+        if amount <= 0.0 {
+            return Err("Trade amount must be positive".to_string());
+        }
+        if self.market_exposure(&order.market_id) + amount > self.limits.max_market_exposure {
+            return Err(format!("Market exposure limit {:.2} exceeded for {}", self.limits.max_market_exposure, order.market_id));
+        }
+        let required = if self.limits.max_trade_fraction > 0.0 {
+            amount.max(amount / self.limits.max_trade_fraction)
+        } else {
+            amount
+        };
+        Ok((required - state.balance).max(0.0))
+    }
+
+    /// Refuse a buy whose limit is below the market price
+    fn check_limit(order: &PaperOrder, price: f64) -> Result<(), String> {
+        match order.limit_price {
+            Some(limit) if price > limit => Err(format!("Limit price {:.4} below market {:.4}", limit, price)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Execute the trims of a liquidation plan at the current quotes; returns the fills
+    pub fn liquidate(&mut self, state: &mut BotState, plan: &LiquidationPlan, markets: &[MarketInfo]) -> Vec<SimulatedTrade> {
+        plan.trims
+            .iter()
+            .filter_map(|trim| {
+                let market = markets.iter().find(|m| m.id == trim.market_id)?;
+                let source = self.positions.get(&Self::position_key(&trim.market_id, trim.token_type))?.source;
+                let order = PaperOrder {
+                    market_id: trim.market_id.clone(),
+                    token_type: trim.token_type,
+                    direction: Direction::Sell,
+                    quantity: trim.quantity,
+                    limit_price: None,
+                    source,
+                };
+                let price = match trim.token_type {
+                    TokenType::Yes => market.yes_price,
+                    TokenType::No => market.no_price,
+                };
+                self.close(state, &order, market, price, "TRIM").ok()
+            })
+            .collect()
+    }
+
+    /// Execute a paper order against the current market quote
+    pub fn execute(&mut self, state: &mut BotState, order: &PaperOrder, market: &MarketInfo) -> Result<SimulatedTrade, String> {
+        if order.quantity <= 0.0 {
+            return Err("Quantity must be positive".to_string());
+        }
+
+        let price = match order.token_type {
+            TokenType::Yes => market.yes_price,
+            TokenType::No => market.no_price,
+        };
+
+        match order.direction {
+            Direction::Buy => self.open(state, order, market, price),
+            Direction::Sell => self.close(state, order, market, price, "SELL"),
+        }
+    }
+
+    fn open(&mut self, state: &mut BotState, order: &PaperOrder, market: &MarketInfo, price: f64) -> Result<SimulatedTrade, String> {
+        Self::check_limit(order, price)?;
+
+        let amount = price * order.quantity;
+        self.check_risk(state, &order.market_id, amount)?;
+
+        let key = Self::position_key(&order.market_id, order.token_type);
+        let now = self.clock.now();
+        // Livelli di uscita fissati all'apertura dalla policy della strategia
+        let (stop_loss, take_profit) = self.exit_policies
+            .get(&order.source)
+            .map(|policy| policy.levels(price))
+            .unwrap_or_default();
+        let position = self.positions.entry(key).or_insert_with(|| PaperPosition {
+            market_id: order.market_id.clone(),
+            question: market.question.clone(),
+            token_type: order.token_type,
+            quantity: 0.0,
+            avg_price: 0.0,
+            opened_at: now,
+            source: order.source,
+            stop_loss,
+            take_profit,
+        });
+        let new_quantity = position.quantity + order.quantity;
+        position.avg_price = (position.cost_basis() + amount) / new_quantity;
+        position.quantity = new_quantity;
+
+        let trade = self.new_trade(order, market, "BUY", price, order.quantity, amount, 0.0);
+        state.balance -= amount;
+        state.last_update = now;
+        self.record(&trade, order.source, -amount, 0.0);
+
+        Ok(trade)
+    }
+
+    fn close(&mut self, state: &mut BotState, order: &PaperOrder, market: &MarketInfo, price: f64, verb: &str) -> Result<SimulatedTrade, String> {
+        if let Some(limit) = order.limit_price {
+            if price < limit {
+                return Err(format!("Limit price {:.4} above market {:.4}", limit, price));
+            }
+        }
+
+        let key = Self::position_key(&order.market_id, order.token_type);
+        let position = self.positions
+            .get_mut(&key)
+            .ok_or_else(|| format!("No open {} position in {}", order.token_type, order.market_id))?;
+
+        let quantity = order.quantity.min(position.quantity);
+        let proceeds = price * quantity;
+        let realized_pnl = (price - position.avg_price) * quantity;
+
+        position.quantity -= quantity;
+        if position.quantity <= 1e-9 {
+            self.positions.remove(&key);
+        }
+
+        let trade = self.new_trade(order, market, verb, price, quantity, proceeds, realized_pnl);
+        state.balance += proceeds;
+        self.apply_realized(state, realized_pnl);
+        self.record(&trade, order.source, proceeds, realized_pnl);
+
+        Ok(trade)
+    }
+
+    /// Book a bot round-trip trade whose PnL was simulated by the strategy loop
+    pub fn book_round_trip(&mut self, state: &mut BotState, trade: &SimulatedTrade) -> Result<(), String> {
+        self.check_risk(state, &trade.market_id, trade.amount)?;
+
+        let realized_pnl = trade.pnl + trade.arbitrage_profit;
+        state.balance += realized_pnl;
+        self.apply_realized(state, realized_pnl);
+        self.record(trade, TradeSource::Bot, realized_pnl, realized_pnl);
+
+        Ok(())
+    }
+
+    /// Settle every position in a resolved market: winning outcome pays 1.0 per share, the other 0
+    pub fn settle(&mut self, state: &mut BotState, market_id: &str, outcome: TokenType) -> Vec<SimulatedTrade> {
+        let keys: Vec<String> = self.positions
+            .iter()
+            .filter(|(_, p)| p.market_id == market_id)
+            .map(|(k, _)| k.clone())
+            .collect();
+
+        let mut settlements = Vec::new();
+        for key in keys {
+            let Some(position) = self.positions.remove(&key) else { continue };
+            let payout_price = if position.token_type == outcome { 1.0 } else { 0.0 };
+            let proceeds = payout_price * position.quantity;
+            let realized_pnl = proceeds - position.cost_basis();
+
+            let trade = SimulatedTrade {
+                id: uuid::Uuid::new_v4().to_string(),
+                market_id: position.market_id.clone(),
+                question: position.question.clone(),
+                action: format!("REDEEM_{}", position.token_type),
+                price: payout_price,
+                quantity: position.quantity,
+                amount: proceeds,
+                timestamp: self.clock.now(),
+                status: "SETTLED".to_string(),
+                pnl: realized_pnl,
+                arbitrage_profit: 0.0,
+                source: TradeSource::Bot,
+            };
+
+            state.balance += proceeds;
+            self.record(&trade, TradeSource::Bot, proceeds, realized_pnl);
+            settlements.push(trade);
+        }
+
+        // Il PnL di un mercato risolto conta come un unico trade
+        if !settlements.is_empty() {
+            let realized: f64 = settlements.iter().map(|t| t.pnl).sum();
+            self.apply_realized(state, realized);
+        }
+        settlements
+    }
+
+    /// Mark-to-market value of open positions given current prices
+    pub fn positions_value(&self, price_of: impl Fn(&str, TokenType) -> Option<f64>) -> f64 {
+        self.positions
+            .values()
+            .map(|p| price_of(&p.market_id, p.token_type).unwrap_or(p.avg_price) * p.quantity)
+            .sum()
+    }
+
+    /// Reset ledger and positions
+    pub fn reset(&mut self) {
+        self.positions.clear();
+        self.ledger.clear();
+        self.efficiency.reset();
+        self.drawdowns.reset();
+    }
+
+    fn apply_realized(&mut self, state: &mut BotState, realized_pnl: f64) {
+        state.total_pnl += realized_pnl;
+        state.total_trades += 1;
+        state.profitable_trades += if realized_pnl > 0.0 { 1 } else { 0 };
+        state.win_rate = (state.profitable_trades as f64 / state.total_trades as f64) * 100.0;
+        state.last_update = self.clock.now();
+        self.drawdowns.record(state.initial_balance + state.total_pnl, state.last_update, true);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn new_trade(
+        &self,
+        order: &PaperOrder,
+        market: &MarketInfo,
+        verb: &str,
+        price: f64,
+        quantity: f64,
+        amount: f64,
+        pnl: f64,
+    ) -> SimulatedTrade {
+        SimulatedTrade {
+            id: uuid::Uuid::new_v4().to_string(),
+            market_id: order.market_id.clone(),
+            question: market.question.clone(),
+            action: format!("{}_{}", verb, order.token_type),
+            price,
+            quantity,
+            amount,
+            timestamp: self.clock.now(),
+            status: "FILLED".to_string(),
+            pnl,
+            arbitrage_profit: 0.0,
+            source: order.source,
+        }
+    }
+
+    fn record(&mut self, trade: &SimulatedTrade, source: TradeSource, cash_delta: f64, realized_pnl: f64) {
+        self.ledger.push(LedgerEntry {
+            trade_id: trade.id.clone(),
+            source,
+            market_id: trade.market_id.clone(),
+            action: trade.action.clone(),
+            cash_delta,
+            realized_pnl,
+            timestamp: trade.timestamp,
+        });
+        self.efficiency.record_trade(trade.amount, realized_pnl);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(balance: f64) -> BotState {
+        BotState {
+            running: false,
+            balance,
+            initial_balance: balance,
+            total_pnl: 0.0,
+            win_rate: 0.0,
+            total_trades: 0,
+            profitable_trades: 0,
+            last_update: Utc::now(),
+        }
+    }
+
+    fn market(yes_price: f64) -> MarketInfo {
+        MarketInfo {
+            id: "market_1".to_string(),
+            question: "Test?".to_string(),
+            yes_price,
+            no_price: 1.0 - yes_price,
+            yes_liquidity: 10000.0,
+            no_liquidity: 10000.0,
+            volume_24h: 50000.0,
+            timestamp: Utc::now(),
+            event_id: None,
+            category: None,
+        }
+    }
+
+    fn order(direction: Direction, quantity: f64, limit_price: Option<f64>) -> PaperOrder {
+        PaperOrder {
+            market_id: "market_1".to_string(),
+            token_type: TokenType::Yes,
+            direction,
+            quantity,
+            limit_price,
+            source: TradeSource::Manual,
+        }
+    }
+
+    #[test]
+    fn test_open_and_close_position() {
+        let mut broker = PaperBroker::default();
+        let mut state = state(10000.0);
+
+        broker.execute(&mut state, &order(Direction::Buy, 1000.0, Some(0.45)), &market(0.40)).unwrap();
+        assert!((state.balance - 9600.0).abs() < 1e-9);
+        assert_eq!(broker.open_positions().len(), 1);
+
+        let trade = broker.execute(&mut state, &order(Direction::Sell, 1000.0, None), &market(0.50)).unwrap();
+        assert!((trade.pnl - 100.0).abs() < 1e-9);
+        assert!((state.balance - 10100.0).abs() < 1e-9);
+        assert_eq!(state.total_trades, 1);
+        assert!(broker.open_positions().is_empty());
+        assert_eq!(broker.ledger.len(), 2);
+    }
+
+    #[test]
+    fn test_settle_resolved_market() {
+        let mut broker = PaperBroker::default();
+        let mut state = state(10000.0);
+
+        broker.execute(&mut state, &order(Direction::Buy, 1000.0, None), &market(0.40)).unwrap();
+        let settlements = broker.settle(&mut state, "market_1", TokenType::Yes);
+
+        assert_eq!(settlements.len(), 1);
+        assert!((settlements[0].pnl - 600.0).abs() < 1e-9);
+        assert!((state.balance - 10600.0).abs() < 1e-9);
+        assert!(broker.positions.is_empty());
+    }
+
+    #[test]
+    fn test_stop_loss_and_take_profit() {
+        let mut broker = PaperBroker::default();
+        let mut state = state(10000.0);
+        broker.set_exit_policy(TradeSource::Manual, ExitPolicy { stop_loss_pct: Some(0.25), take_profit_pct: Some(0.5) });
+
+        broker.execute(&mut state, &order(Direction::Buy, 1000.0, None), &market(0.40)).unwrap();
+        let position = &broker.open_positions()[0];
+        assert!((position.stop_loss.unwrap() - 0.30).abs() < 1e-9);
+        assert!((position.take_profit.unwrap() - 0.60).abs() < 1e-9);
+
+        // Mark tra i due livelli: nessuna uscita
+        assert!(broker.check_exits(&mut state, &[market(0.35)]).is_empty());
+        let exits = broker.check_exits(&mut state, &[market(0.28)]);
+        assert_eq!(exits.len(), 1);
+        assert_eq!(exits[0].action, "STOP_LOSS_YES");
+        assert!((exits[0].pnl + 120.0).abs() < 1e-9);
+        assert!(broker.positions.is_empty());
+
+        // Override manuale dei livelli sulla singola posizione
+        broker.execute(&mut state, &order(Direction::Buy, 1000.0, None), &market(0.40)).unwrap();
+        assert!(broker.set_exit_levels("market_1", TokenType::Yes, Some(0.5), Some(0.45)).is_err());
+        broker.set_exit_levels("market_1", TokenType::Yes, None, Some(0.45)).unwrap();
+        assert!(broker.check_exits(&mut state, &[market(0.10)]).is_empty());
+        let exits = broker.check_exits(&mut state, &[market(0.46)]);
+        assert_eq!(exits[0].action, "TAKE_PROFIT_YES");
+        assert!((exits[0].pnl - 60.0).abs() < 1e-9);
+        assert_eq!(state.total_trades, 2);
+    }
+
+    #[test]
+    fn test_risk_and_limit_rejections() {
+        let mut broker = PaperBroker::default();
+        let mut state = state(1000.0);
+
+        // Limite non eseguibile
+        assert!(broker.execute(&mut state, &order(Direction::Buy, 10.0, Some(0.30)), &market(0.40)).is_err());
+        // Oltre il 10% del balance
+        assert!(broker.execute(&mut state, &order(Direction::Buy, 500.0, None), &market(0.40)).is_err());
+        // Nessuna posizione da chiudere
+        assert!(broker.execute(&mut state, &order(Direction::Sell, 10.0, None), &market(0.40)).is_err());
+        assert!(broker.ledger.is_empty());
+        assert!((state.balance - 1000.0).abs() < 1e-9);
+
+        // Shortfall solo per ciò che la liquidazione può risolvere: 200 di trade richiedono 2000 di balance
+        assert!((broker.capital_shortfall(&state, &order(Direction::Buy, 500.0, None), 0.40).unwrap() - 1000.0).abs() < 1e-9);
+        assert!(broker.capital_shortfall(&state, &order(Direction::Buy, 500.0, Some(0.30)), 0.40).is_err());
+        assert!(broker.capital_shortfall(&state, &order(Direction::Buy, 10_000.0, None), 0.40).unwrap_err().contains("exposure"));
+    }
+}
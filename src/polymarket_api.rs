@@ -4,17 +4,144 @@
 //! - WebSocket: ws-subscriptions-clob.polymarket.com for real-time orderbook data
 //! - Gamma API: gamma-api.polymarket.com for market metadata and discovery
 //! - CLOB API for order management
+//! - TTL cache of slow-changing metadata (markets, events, tick sizes), so only prices and books are polled often
 
-use crate::types::*;
-use crate::types::MarketData;
-use reqwest::Client as HttpClient;
-use serde::{Deserialize, Serialize};
+use crate::audit::{AuditLog, AuditRecord, ORDER_AUDIT_PATH};
+use crate::capture::{ApiCapture, CaptureConfig, CAPTURE_SAMPLE_RATE_ENV};
+use crate::execution::OrderStatus;
+use crate::failover::{EndpointPool, FailoverPolicy, MarketDataHealth};
+use crate::rate_limit::{RateLimitConfig, RateLimiter, CLOB_API, GAMMA_API};
+use crate::market::PriceSnapshot;
+pub use crate::orderbook::{WsBookEvent, WsLevelChange, WsOrderLevel, WsPriceChange};
+use crate::rewards::{AccruedReward, RewardConfig};
+use crate::types::{de_f64, de_opt_f64, de_u64, EventData, MarketData, MarketFilter, OrderConstraints, TokenPair};
+#[cfg(feature = "onchain")]
+use crate::wallet::Wallet;
+use fxhash::FxHashMap;
+use base64::engine::general_purpose::{STANDARD, URL_SAFE};
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use reqwest::header::{HeaderMap, HeaderValue};
+use reqwest::{Client as HttpClient, Method};
+use sha2::Sha256;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering};
+use std::path::PathBuf;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, watch, Mutex};
 use futures_util::SinkExt;
 use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
 use futures_util::stream::StreamExt;
-use anyhow::{Result, Context};
+use reqwest::{Response, StatusCode};
+
+/// Error of the Polymarket API clients, by kind so callers can react without matching messages
+#[derive(Debug, thiserror::Error)]
+pub enum PolymarketApiError {
+    #[error("rate limited on {path}")]
+    RateLimited { path: String, retry_after: Option<Duration> },
+    #[error("authentication failed: {0}")]
+    AuthFailed(String),
+    #[error("invalid order: {0}")]
+    InvalidOrder(String), // Richiesta rifiutata dal server (4xx diversi da auth/404/429)
+    #[error("not found: {0}")]
+    NotFound(String),
+    #[error("network error: {0}")]
+    Network(String), // Connessione, timeout, WebSocket e 5xx
+    #[error("decode error: {0}")]
+    Decode(String),
+}
+
+impl PolymarketApiError {
+    /// Error for a non-success HTTP status of `path`
+    pub fn from_status(status: StatusCode, path: &str, retry_after: Option<Duration>, body: &str) -> Self {
+        let message = if body.is_empty() {
+            format!("{} returned {}", path, status)
+        } else {
+            format!("{} returned {}: {}", path, status, body)
+        };
+        match status {
+            StatusCode::TOO_MANY_REQUESTS => Self::RateLimited { path: path.to_string(), retry_after },
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Self::AuthFailed(message),
+            StatusCode::NOT_FOUND => Self::NotFound(message),
+            s if s.is_client_error() => Self::InvalidOrder(message),
+            _ => Self::Network(message),
+        }
+    }
+
+    /// Transient errors worth retrying later
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Self::RateLimited { .. } | Self::Network(_))
+    }
+}
+
+impl From<reqwest::Error> for PolymarketApiError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_decode() {
+            Self::Decode(e.to_string())
+        } else {
+            Self::Network(e.to_string())
+        }
+    }
+}
+
+/// Result of the Polymarket API clients
+pub type ApiResult<T> = std::result::Result<T, PolymarketApiError>;
+
+type Result<T> = ApiResult<T>;
+
+/// Decode error for a response body that does not match `what`
+fn decode_error(what: &str) -> impl FnOnce(serde_json::Error) -> PolymarketApiError + '_ {
+    move |e| PolymarketApiError::Decode(format!("Invalid {} response: {}", what, e))
+}
+
+/// Pass a successful response through, or turn its status into a typed error
+async fn check_status(response: Response, path: &str) -> Result<Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let retry_after = crate::rate_limit::parse_retry_after(response.headers());
+    let body = response.text().await.unwrap_or_default();
+    Err(PolymarketApiError::from_status(status, path, retry_after, &body))
+}
+
+/// Send a public read to the healthiest endpoint of `pool`, moving to the next one on network errors
+///
+/// `build` turns an endpoint base URL into the request. Other errors (auth, not found, rate limits)
+/// are returned at once: a mirror would answer the same.
+async fn send_with_failover(
+    pool: &EndpointPool,
+    rate_limiter: &RateLimiter,
+    api: &str,
+    path: &str,
+    build: impl Fn(&str) -> reqwest::RequestBuilder,
+) -> Result<Response> {
+    let mut last_error = None;
+    for base in pool.candidates() {
+        let started = Instant::now();
+        let result = match rate_limiter.send(api, path, build(&base)).await {
+            Ok(response) => check_status(response, path).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(response) => {
+                pool.record_success(&base, started.elapsed());
+                return Ok(response);
+            }
+            Err(PolymarketApiError::Network(message)) => {
+                pool.record_failure(&base);
+                eprintln!("🔀 {}{} failed ({}), failing over", base, path, message);
+                last_error = Some(PolymarketApiError::Network(message));
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| PolymarketApiError::Network(format!("No endpoint configured for {}", path))))
+}
+
+/// Polygon mainnet chain id, used in the EIP-712 domain
+pub const POLYGON_CHAIN_ID: u64 = 137;
 
 /// Polymarket API Configuration
 #[derive(Debug, Clone)]
@@ -23,6 +150,23 @@ pub struct PolymarketApiConfig {
     pub clob_api_url: String,
     pub websocket_url: String,
     pub api_key: Option<String>,
+    pub wallet_address: Option<String>, // Indirizzo Polygon associato alle credenziali (POLY_ADDRESS)
+    pub reconnect: ReconnectPolicy,
+    pub heartbeat: HeartbeatPolicy, // Ping e soglie di staleness del WebSocket
+    pub rate_limits: RateLimitConfig, // Limiti per endpoint condivisi da Gamma e CLOB
+    pub order_audit_path: Option<PathBuf>, // Audit log append-only degli ordini (None = disabilitato)
+    pub signature_type: u8, // 0 = EOA, 1 = Polymarket proxy, 2 = Gnosis Safe
+    pub chain_id: u64, // Chain del dominio EIP-712 per l'autenticazione L1
+    pub proxy: Option<String>, // http://, https:// o socks5://; None = proxy di sistema
+    pub request_timeout_ms: u64,
+    pub connect_timeout_ms: u64, // Vale anche per l'handshake WebSocket
+    pub market_filter: MarketFilter, // Categorie e tag applicati alla discovery su Gamma
+    pub metadata_ttl_secs: u64, // Durata della cache di mercati, eventi e tick size (0 = disabilitata)
+    pub capture: CaptureConfig, // Cattura campionata e oscurata delle chiamate all'exchange
+    pub gamma_mirrors: Vec<String>,     // Mirror o proxy di Gamma, provati dopo l'URL principale
+    pub clob_mirrors: Vec<String>,      // Solo per le letture pubbliche: ordini e chiamate firmate restano sul principale
+    pub websocket_mirrors: Vec<String>,
+    pub failover: FailoverPolicy, // Soglia di errori e cooldown degli endpoint
 }
 
 impl Default for PolymarketApiConfig {
@@ -32,7 +176,230 @@ impl Default for PolymarketApiConfig {
             clob_api_url: "https://clob.polymarket.com".to_string(),
             websocket_url: "wss://ws-subscriptions-clob.polymarket.com".to_string(),
             api_key: None,
+            wallet_address: None,
+            reconnect: ReconnectPolicy::default(),
+            heartbeat: HeartbeatPolicy::default(),
+            rate_limits: RateLimitConfig::default(),
+            order_audit_path: Some(PathBuf::from(ORDER_AUDIT_PATH)),
+            signature_type: 0,
+            chain_id: POLYGON_CHAIN_ID,
+            proxy: None,
+            request_timeout_ms: 10_000,
+            connect_timeout_ms: 5_000,
+            market_filter: MarketFilter::default(),
+            metadata_ttl_secs: 300,
+            capture: CaptureConfig::default(),
+            gamma_mirrors: Vec::new(),
+            clob_mirrors: Vec::new(),
+            websocket_mirrors: Vec::new(),
+            failover: FailoverPolicy::default(),
+        }
+    }
+}
+
+/// Environment variables overriding the endpoints and proxy
+pub const GAMMA_URL_ENV: &str = "POLYMARKET_GAMMA_URL";
+pub const CLOB_URL_ENV: &str = "POLYMARKET_CLOB_URL";
+pub const WS_URL_ENV: &str = "POLYMARKET_WS_URL";
+pub const PROXY_ENV: &str = "POLYMARKET_PROXY";
+pub const METADATA_TTL_ENV: &str = "POLYMARKET_METADATA_TTL_SECS";
+/// Comma-separated fallback endpoints, tried in order after the primary one
+pub const GAMMA_MIRRORS_ENV: &str = "POLYMARKET_GAMMA_MIRRORS";
+pub const CLOB_MIRRORS_ENV: &str = "POLYMARKET_CLOB_MIRRORS";
+pub const WS_MIRRORS_ENV: &str = "POLYMARKET_WS_MIRRORS";
+
+impl PolymarketApiConfig {
+    /// Apply endpoint and proxy overrides from the environment (e.g. to target a mock server)
+    pub fn with_env_overrides(mut self) -> Self {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+        if let Some(url) = var(GAMMA_URL_ENV) {
+            self.gamma_api_url = url.trim_end_matches('/').to_string();
+        }
+        if let Some(url) = var(CLOB_URL_ENV) {
+            self.clob_api_url = url.trim_end_matches('/').to_string();
+        }
+        if let Some(url) = var(WS_URL_ENV) {
+            self.websocket_url = url;
+        }
+        if let Some(proxy) = var(PROXY_ENV) {
+            self.proxy = Some(proxy);
+        }
+        let list = |v: String| v.split(',').map(|url| url.trim().to_string()).filter(|url| !url.is_empty()).collect();
+        if let Some(mirrors) = var(GAMMA_MIRRORS_ENV) {
+            self.gamma_mirrors = list(mirrors);
+        }
+        if let Some(mirrors) = var(CLOB_MIRRORS_ENV) {
+            self.clob_mirrors = list(mirrors);
+        }
+        if let Some(mirrors) = var(WS_MIRRORS_ENV) {
+            self.websocket_mirrors = list(mirrors);
+        }
+        match var(METADATA_TTL_ENV).map(|v| v.trim().parse::<u64>()) {
+            Some(Ok(ttl)) => self.metadata_ttl_secs = ttl,
+            Some(Err(_)) => eprintln!("⚠️  {} ignored: not a number of seconds", METADATA_TTL_ENV),
+            None => {}
+        }
+        match var(CAPTURE_SAMPLE_RATE_ENV).map(|v| v.trim().parse::<f64>()) {
+            Some(Ok(rate)) if (0.0..=1.0).contains(&rate) => self.capture.sample_rate = rate,
+            Some(_) => eprintln!("⚠️  {} ignored: expected a rate between 0 and 1", CAPTURE_SAMPLE_RATE_ENV),
+            None => {}
+        }
+        self
+    }
+
+    /// Endpoint pools of Gamma, CLOB and WebSocket: primary URL followed by its mirrors
+    pub fn gamma_endpoints(&self) -> EndpointPool {
+        EndpointPool::new(&self.gamma_api_url, &self.gamma_mirrors, self.failover.clone())
+    }
+
+    pub fn clob_endpoints(&self) -> EndpointPool {
+        EndpointPool::new(&self.clob_api_url, &self.clob_mirrors, self.failover.clone())
+    }
+
+    pub fn websocket_endpoints(&self) -> EndpointPool {
+        EndpointPool::new(&self.websocket_url, &self.websocket_mirrors, self.failover.clone())
+    }
+
+    /// Rate limiter for these limits, capturing requests as configured
+    pub fn rate_limiter(&self) -> RateLimiter {
+        RateLimiter::new(self.rate_limits.clone()).with_capture(ApiCapture::new(self.capture.clone()))
+    }
+
+    /// HTTP client with the configured proxy and timeouts
+    pub fn build_http_client(&self) -> Result<HttpClient> {
+        let mut builder = HttpClient::builder()
+            .timeout(std::time::Duration::from_millis(self.request_timeout_ms))
+            .connect_timeout(std::time::Duration::from_millis(self.connect_timeout_ms));
+        if let Some(proxy) = &self.proxy {
+            let proxy = reqwest::Proxy::all(proxy)
+                .map_err(|e| PolymarketApiError::Network(format!("Invalid proxy URL {}: {}", proxy, e)))?;
+            builder = builder.proxy(proxy);
+        }
+        builder.build().map_err(|e| PolymarketApiError::Network(format!("Failed to build HTTP client: {}", e)))
+    }
+
+}
+
+/// Exponential backoff policy for WebSocket reconnection
+#[derive(Debug, Clone)]
+pub struct ReconnectPolicy {
+    pub initial_delay_ms: u64,
+    pub max_delay_ms: u64,
+    pub multiplier: f64,
+    pub max_attempts: Option<u32>, // None = riprova all'infinito
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay_ms: 500,
+            max_delay_ms: 30_000,
+            multiplier: 2.0,
+            max_attempts: None,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// Delay before reconnection attempt `attempt` (1-based)
+    pub fn delay_for(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1) as i32;
+        let delay = self.initial_delay_ms as f64 * self.multiplier.powi(exponent);
+        Duration::from_millis(delay.min(self.max_delay_ms as f64) as u64)
+    }
+}
+
+/// Keepalive pings and staleness thresholds of the WebSocket feed
+#[derive(Debug, Clone)]
+pub struct HeartbeatPolicy {
+    pub ping_interval_ms: u64,
+    pub stale_after_ms: u64,     // Asset sottoscritto senza messaggi oltre la soglia = dati congelati
+    pub silent_session_ms: u64,  // Connessione senza alcun messaggio (nemmeno pong): sessione riaperta
+}
+
+impl Default for HeartbeatPolicy {
+    fn default() -> Self {
+        Self {
+            ping_interval_ms: 10_000,
+            stale_after_ms: 30_000,
+            silent_session_ms: 30_000,
+        }
+    }
+}
+
+/// WebSocket connection state published on a watch channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    Disconnected,
+    Connecting,
+    Connected,
+    Reconnecting { attempt: u32 },
+}
+
+/// Deserialize unix seconds encoded as string or number
+fn de_unix_time<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<chrono::DateTime<chrono::Utc>, D::Error> {
+    let seconds = de_f64(deserializer)?;
+    chrono::DateTime::from_timestamp(seconds as i64, 0)
+        .ok_or_else(|| serde::de::Error::custom(format!("invalid unix time {}", seconds)))
+}
+
+/// `last_trade_price` event: a trade printed on the book
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WsTradeEvent {
+    pub asset_id: String,
+    #[serde(default)]
+    pub market: String,
+    #[serde(deserialize_with = "de_f64")]
+    pub price: f64,
+    #[serde(default)]
+    pub side: String,
+    #[serde(deserialize_with = "de_f64")]
+    pub size: f64,
+    #[serde(default, deserialize_with = "de_u64")]
+    pub timestamp: u64,
+}
+
+/// Typed market-channel event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event_type", rename_all = "snake_case")]
+pub enum WsMarketEvent {
+    Book(WsBookEvent),
+    PriceChange(WsPriceChange),
+    #[serde(rename = "last_trade_price")]
+    Trade(WsTradeEvent),
+}
+
+impl WsMarketEvent {
+    pub fn asset_id(&self) -> &str {
+        match self {
+            WsMarketEvent::Book(e) => &e.asset_id,
+            WsMarketEvent::PriceChange(e) => &e.asset_id,
+            WsMarketEvent::Trade(e) => &e.asset_id,
+        }
+    }
+
+    /// Parse a raw frame; the server may batch several events in one array
+    pub fn parse_frame(text: &str) -> Result<Vec<WsMarketEvent>> {
+        let value: serde_json::Value = serde_json::from_str(text)
+            .map_err(decode_error("WebSocket frame"))?;
+
+        let items = match value {
+            serde_json::Value::Array(items) => items,
+            other => vec![other],
+        };
+
+        let mut events = Vec::new();
+        for item in items {
+            // Eventi non di mercato (tick_size_change, ack, ...) vengono ignorati
+            let known = matches!(
+                item.get("event_type").and_then(|v| v.as_str()),
+                Some("book") | Some("price_change") | Some("last_trade_price")
+            );
+            if known {
+                events.push(serde_json::from_value(item).map_err(decode_error("market event"))?);
+            }
         }
+        Ok(events)
     }
 }
 
@@ -40,258 +407,1787 @@ impl Default for PolymarketApiConfig {
 #[derive(Clone, Debug)]
 pub struct PolymarketWebSocketClient {
     config: PolymarketApiConfig,
-    connected: Arc<Mutex<bool>>,
-    http_client: HttpClient,
+    state_tx: Arc<watch::Sender<ConnectionState>>,
+    shutdown: Arc<AtomicBool>,
+    subscriptions: Arc<Mutex<Vec<String>>>,
+    event_tx: Arc<Mutex<Option<mpsc::Sender<WsMarketEvent>>>>,
+    last_seen: Arc<Mutex<FxHashMap<String, Instant>>>, // asset_id -> ultimo messaggio (o refresh REST)
+    session_started: Arc<Mutex<Option<Instant>>>, // None senza sessione attiva
+    endpoints: EndpointPool, // URL principale e mirror, condivisi tra i cloni
 }
 
 impl PolymarketWebSocketClient {
     pub fn new(config: PolymarketApiConfig) -> Self {
         Self {
+            endpoints: config.websocket_endpoints(),
             config,
-            connected: Arc::new(Mutex::new(false)),
-            http_client: HttpClient::new(),
+            state_tx: Arc::new(watch::channel(ConnectionState::Disconnected).0),
+            shutdown: Arc::new(AtomicBool::new(false)),
+            subscriptions: Arc::new(Mutex::new(Vec::new())),
+            event_tx: Arc::new(Mutex::new(None)),
+            last_seen: Arc::new(Mutex::new(FxHashMap::default())),
+            session_started: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Create the channel carrying parsed market events (replaces any previous consumer)
+    pub async fn event_channel(&self, capacity: usize) -> mpsc::Receiver<WsMarketEvent> {
+        let (tx, rx) = mpsc::channel(capacity);
+        *self.event_tx.lock().await = Some(tx);
+        rx
+    }
+
+    /// Replace the asset ids subscribed on the next (re)connection
+    pub async fn set_subscriptions(&self, asset_ids: Vec<String>) {
+        *self.subscriptions.lock().await = asset_ids;
+    }
+
+    /// Currently requested asset ids
+    pub async fn subscriptions(&self) -> Vec<String> {
+        self.subscriptions.lock().await.clone()
+    }
+
+    /// Watch channel of the connection state (il bot sospende il trading quando non è Connected)
+    pub fn state(&self) -> watch::Receiver<ConnectionState> {
+        self.state_tx.subscribe()
+    }
+
+    pub fn is_connected(&self) -> bool {
+        *self.state_tx.borrow() == ConnectionState::Connected
+    }
+
+    /// Subscribed assets silent for longer than `stale_after_ms` (empty without an active session)
+    ///
+    /// Assets never heard from count from the start of the session.
+    pub async fn stale_assets(&self) -> Vec<String> {
+        let Some(started) = *self.session_started.lock().await else { return Vec::new() };
+        let threshold = Duration::from_millis(self.config.heartbeat.stale_after_ms);
+        let last_seen = self.last_seen.lock().await;
+        let now = Instant::now();
+
+        self.subscriptions()
+            .await
+            .into_iter()
+            .filter(|asset| {
+                let last = last_seen.get(asset).copied().unwrap_or(started).max(started);
+                now.saturating_duration_since(last) > threshold
+            })
+            .collect()
+    }
+
+    /// Reset the silence timer of assets whose prices were refreshed over REST
+    pub async fn mark_refreshed(&self, asset_ids: &[String]) {
+        let now = Instant::now();
+        let mut last_seen = self.last_seen.lock().await;
+        for asset in asset_ids {
+            last_seen.insert(asset.clone(), now);
         }
     }
 
-    /// Connect to Polymarket WebSocket
+    /// Health of the WebSocket endpoints
+    pub fn endpoint_health(&self) -> Vec<crate::failover::EndpointHealth> {
+        self.endpoints.health()
+    }
+
+    /// Stop the reconnection loop after the current session ends
+    pub fn shutdown(&self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+    }
+
+    /// Connect to Polymarket WebSocket, reconnecting with exponential backoff
     pub async fn connect(&self) -> Result<()> {
-        let url = self.config.websocket_url.clone();
+        let policy = self.config.reconnect.clone();
+        let mut attempt: u32 = 0;
+        self.shutdown.store(false, Ordering::SeqCst);
+
+        loop {
+            self.state_tx.send_replace(if attempt == 0 {
+                ConnectionState::Connecting
+            } else {
+                ConnectionState::Reconnecting { attempt }
+            });
+
+            match self.run_session().await {
+                // Sessione stabilita: il backoff riparte da zero
+                Ok(()) => attempt = 0,
+                Err(e) => eprintln!("WebSocket error: {}", e),
+            }
+            self.state_tx.send_replace(ConnectionState::Disconnected);
+
+            if self.shutdown.load(Ordering::SeqCst) {
+                return Ok(());
+            }
+
+            attempt += 1;
+            if policy.max_attempts.is_some_and(|max| attempt > max) {
+                return Err(PolymarketApiError::Network(format!("WebSocket reconnection gave up after {} attempts", attempt - 1)));
+            }
+
+            let delay = policy.delay_for(attempt);
+            eprintln!("🔄 Reconnecting to Polymarket WebSocket in {:?} (attempt {})", delay, attempt);
+            self.state_tx.send_replace(ConnectionState::Reconnecting { attempt });
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Run one WebSocket session until the server closes it
+    ///
+    /// Connects to the healthiest endpoint; a failed handshake or a silent session counts against
+    /// it, so the next reconnection moves to a mirror once it is cooling down.
+    async fn run_session(&self) -> Result<()> {
+        let url = self.endpoints.current();
         eprintln!("🔌 Connecting to Polymarket WebSocket: {}", url);
 
-        let (ws_stream, _) = connect_async(&url)
+        let timeout = std::time::Duration::from_millis(self.config.connect_timeout_ms);
+        let started = Instant::now();
+        let connected = tokio::time::timeout(timeout, connect_async(&url))
             .await
-            .context("Failed to connect to Polymarket WebSocket")?;
-
-        *self.connected.lock().await = true;
-        eprintln!("✅ Connected to Polymarket WebSocket");
+            .map_err(|_| PolymarketApiError::Network("Timed out connecting to Polymarket WebSocket".to_string()))
+            .and_then(|r| r.map_err(|e| PolymarketApiError::Network(format!("Failed to connect to Polymarket WebSocket: {}", e))));
+        let (ws_stream, _) = match connected {
+            Ok(stream) => stream,
+            Err(e) => {
+                self.endpoints.record_failure(&url);
+                return Err(e);
+            }
+        };
+        self.endpoints.record_success(&url, started.elapsed());
 
         let (mut write, mut read) = ws_stream.split();
 
-        // Subscribe to real-time orderbook updates
-        let subscribe_msg = r#"{
+        // Risottoscrive gli asset richiesti finora
+        let subscribe_msg = serde_json::json!({
             "type": "subscribe",
-            "channels": ["orderbook", "trades", "market_updates"]
-        }"#;
+            "channels": ["orderbook", "trades", "market_updates"],
+            "assets_ids": self.subscriptions().await,
+        });
+
+        write.send(Message::Text(subscribe_msg.to_string().into())).await
+            .map_err(|e| PolymarketApiError::Network(format!("Failed to send subscription message: {}", e)))?;
 
-        write.send(Message::Text(subscribe_msg.into())).await
-            .context("Failed to send subscription message")?;
+        self.state_tx.send_replace(ConnectionState::Connected);
+        *self.session_started.lock().await = Some(Instant::now());
+        eprintln!("✅ Connected to Polymarket WebSocket, subscribed to real-time market data");
+
+        let result = self.read_loop(&mut write, &mut read).await;
+        *self.session_started.lock().await = None;
+        if result.is_err() {
+            self.endpoints.record_failure(&url);
+        }
+        result
+    }
 
-        eprintln!("📡 Subscribed to real-time market data channels");
+    /// Read frames and ping the server until the session ends or goes silent
+    async fn read_loop<W, R>(&self, write: &mut W, read: &mut R) -> Result<()>
+    where
+        W: futures_util::Sink<Message, Error = tokio_tungstenite::tungstenite::Error> + Unpin,
+        R: futures_util::Stream<Item = std::result::Result<Message, tokio_tungstenite::tungstenite::Error>> + Unpin,
+    {
+        let heartbeat = &self.config.heartbeat;
+        let mut ping = tokio::time::interval(Duration::from_millis(heartbeat.ping_interval_ms.max(1)));
+        ping.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let silent_after = Duration::from_millis(heartbeat.silent_session_ms);
+        let mut last_message = Instant::now();
 
-        // Handle incoming messages
-        while let Some(msg_result) = read.next().await {
-            match msg_result {
-                Ok(Message::Text(text)) => {
-                    if let Err(e) = self.handle_message(&text).await {
-                        eprintln!("Error handling WebSocket message: {}", e);
+        loop {
+            if self.shutdown.load(Ordering::SeqCst) {
+                return Ok(());
+            }
+            tokio::select! {
+                msg = read.next() => {
+                    let Some(msg_result) = msg else { return Ok(()) };
+                    last_message = Instant::now();
+                    match msg_result {
+                        Ok(Message::Text(text)) => {
+                            if let Err(e) = self.handle_message(&text).await {
+                                eprintln!("Error handling WebSocket message: {}", e);
+                            }
+                        }
+                        Ok(Message::Ping(data)) => {
+                            write.send(Message::Pong(data)).await
+                                .map_err(|e| PolymarketApiError::Network(format!("Failed to answer ping: {}", e)))?;
+                        }
+                        Ok(Message::Close(_)) => {
+                            eprintln!("WebSocket connection closed");
+                            return Ok(());
+                        }
+                        Err(e) => {
+                            eprintln!("WebSocket error: {}", e);
+                            return Ok(());
+                        }
+                        _ => {}
                     }
                 }
-                Ok(Message::Ping(data)) => {
-                    write.send(Message::Pong(data)).await?;
-                }
-                Ok(Message::Close(_)) => {
-                    eprintln!("WebSocket connection closed");
-                    *self.connected.lock().await = false;
-                    break;
-                }
-                Err(e) => {
-                    eprintln!("WebSocket error: {}", e);
-                    break;
+                _ = ping.tick() => {
+                    // Nemmeno i pong arrivano: la connessione è morta senza chiusura
+                    if last_message.elapsed() > silent_after {
+                        return Err(PolymarketApiError::Network(format!(
+                            "WebSocket silent for {:?}, reconnecting", last_message.elapsed()
+                        )));
+                    }
+                    write.send(Message::Ping(Vec::new().into())).await
+                        .map_err(|e| PolymarketApiError::Network(format!("Failed to send ping: {}", e)))?;
                 }
-                _ => {}
             }
         }
-
-        Ok(())
     }
 
     /// Handle incoming WebSocket messages
     async fn handle_message(&self, text: &str) -> Result<()> {
-        // Parse incoming real-time market data
-        if let Ok(data) = serde_json::from_str::<serde_json::Value>(text) {
-            if let Some(msg_type) = data.get("type").and_then(|v| v.as_str()) {
-                match msg_type {
-                    "orderbook" => {
-                        eprintln!("📊 Real-time orderbook update received");
-                        // Parse and update orderbook data
-                    }
-                    "trade" => {
-                        eprintln!("💰 Real-time trade update received");
-                        // Parse and update trade data
-                    }
-                    "market_update" => {
-                        eprintln!("📈 Real-time market update received");
-                        // Parse and update market data
-                    }
-                    _ => {
-                        eprintln!("📨 Unknown message type: {}", msg_type);
-                    }
-                }
+        let events = WsMarketEvent::parse_frame(text)?;
+        if events.is_empty() {
+            return Ok(());
+        }
+
+        let now = Instant::now();
+        let mut last_seen = self.last_seen.lock().await;
+        for event in &events {
+            last_seen.insert(event.asset_id().to_string(), now);
+        }
+        drop(last_seen);
+
+        let tx = self.event_tx.lock().await.clone();
+        let Some(tx) = tx else { return Ok(()) };
+
+        for event in events {
+            // Non blocchiamo la lettura del socket: se il consumer è in ritardo l'evento viene scartato
+            if let Err(e) = tx.try_send(event) {
+                eprintln!("⚠️  Market event dropped: {}", e);
             }
         }
         Ok(())
     }
 }
 
-/// Gamma API Client for market metadata and discovery
-pub struct GammaApiClient {
-    config: PolymarketApiConfig,
-    http_client: HttpClient,
-    api_key: Option<String>,
-    secret: Option<String>,
-    passphrase: Option<String>,
+/// Query parameters and pagination for Gamma `/markets`
+#[derive(Debug, Clone)]
+pub struct MarketQuery {
+    pub active: Option<bool>,
+    pub closed: Option<bool>,
+    pub liquidity_num_min: Option<f64>,
+    pub volume_num_min: Option<f64>,
+    pub volume_num_max: Option<f64>,
+    pub tag_id: Option<u64>,
+    pub include_tag: bool, // Chiede a Gamma i tag di ogni mercato
+    pub page_size: usize,
+    pub offset: usize,
+    pub max_results: Option<usize>, // None = tutte le pagine
 }
 
-impl GammaApiClient {
-    pub fn new(config: PolymarketApiConfig, api_key: Option<String>, secret: Option<String>, passphrase: Option<String>) -> Self {
+impl Default for MarketQuery {
+    fn default() -> Self {
         Self {
-            config,
-            http_client: HttpClient::new(),
-            api_key,
-            secret,
-            passphrase,
+            active: Some(true),
+            closed: Some(false),
+            liquidity_num_min: None,
+            volume_num_min: None,
+            volume_num_max: None,
+            tag_id: None,
+            include_tag: false,
+            page_size: 500,
+            offset: 0,
+            max_results: None,
         }
     }
+}
 
-    /// Create authenticated HTTP request headers
-    fn auth_headers(&self) -> reqwest::header::HeaderMap {
-        let mut headers = reqwest::header::HeaderMap::new();
-
-        if let (Some(key), Some(secret)) = (&self.api_key, &self.secret) {
-            headers.insert(
-                "API-KEY",
-                key.parse().expect("Invalid API key header")
-            );
-            headers.insert(
-                "API-SECRET",
-                secret.parse().expect("Invalid secret header")
-            );
+impl MarketQuery {
+    /// Query string pairs for one page
+    pub fn to_query_pairs(&self, offset: usize, limit: usize) -> Vec<(&'static str, String)> {
+        let mut pairs = vec![
+            ("limit", limit.to_string()),
+            ("offset", offset.to_string()),
+        ];
+        if let Some(active) = self.active {
+            pairs.push(("active", active.to_string()));
         }
-
-        if let Some(passphrase) = &self.passphrase {
-            headers.insert(
-                "API-PASSPHRASE",
-                passphrase.parse().expect("Invalid passphrase header")
-            );
+        if let Some(closed) = self.closed {
+            pairs.push(("closed", closed.to_string()));
+        }
+        if let Some(min) = self.liquidity_num_min {
+            pairs.push(("liquidity_num_min", min.to_string()));
+        }
+        if let Some(min) = self.volume_num_min {
+            pairs.push(("volume_num_min", min.to_string()));
+        }
+        if let Some(max) = self.volume_num_max {
+            pairs.push(("volume_num_max", max.to_string()));
+        }
+        if let Some(tag_id) = self.tag_id {
+            pairs.push(("tag_id", tag_id.to_string()));
+        }
+        if self.include_tag {
+            pairs.push(("include_tag", "true".to_string()));
         }
+        pairs
+    }
+}
+
+/// Time window for the CLOB prices-history endpoint
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HistoryRange {
+    LastHour,
+    LastSixHours,
+    LastDay,
+    LastWeek,
+    LastMonth,
+    Max,
+    Between {
+        start: chrono::DateTime<chrono::Utc>,
+        end: chrono::DateTime<chrono::Utc>,
+    },
+}
 
-        headers
+impl HistoryRange {
+    /// Query pairs (`interval` preset or explicit `startTs`/`endTs`)
+    pub fn to_query_pairs(&self) -> Vec<(&'static str, String)> {
+        let preset = match self {
+            HistoryRange::LastHour => "1h",
+            HistoryRange::LastSixHours => "6h",
+            HistoryRange::LastDay => "1d",
+            HistoryRange::LastWeek => "1w",
+            HistoryRange::LastMonth => "1m",
+            HistoryRange::Max => "max",
+            HistoryRange::Between { start, end } => {
+                return vec![
+                    ("startTs", start.timestamp().to_string()),
+                    ("endTs", end.timestamp().to_string()),
+                ];
+            }
+        };
+        vec![("interval", preset.to_string())]
     }
+}
 
-    /// Fetch all markets from Gamma API
-    pub async fn fetch_markets(&self) -> Result<Vec<MarketData>> {
-        let url = format!("{}/markets", self.config.gamma_api_url);
-        eprintln!("📡 Fetching markets from Gamma API: {}", url);
-        eprintln!("🔑 Using API authentication");
+/// One point of the prices-history response
+#[derive(Debug, Clone, Deserialize)]
+struct HistoryPoint {
+    t: i64,
+    #[serde(deserialize_with = "de_f64")]
+    p: f64,
+}
 
-        let mut request = self.http_client.get(&url);
+/// prices-history response body
+#[derive(Debug, Clone, Deserialize)]
+struct PriceHistoryResponse {
+    #[serde(default)]
+    history: Vec<HistoryPoint>,
+}
 
-        // Add authentication headers if credentials are available
-        if let (Some(key), Some(secret)) = (&self.api_key, &self.secret) {
-            request = request
-                .header("API-KEY", key)
-                .header("API-SECRET", secret);
+/// Parse a prices-history body into snapshots for the YES token (NO is the complement)
+pub fn parse_price_history(json: &serde_json::Value) -> Result<Vec<PriceSnapshot>> {
+    let response: PriceHistoryResponse = serde_json::from_value(json.clone())
+        .map_err(decode_error("prices-history"))?;
 
-            if let Some(passphrase) = &self.passphrase {
-                request = request.header("API-PASSPHRASE", passphrase);
-            }
+    let mut snapshots: Vec<PriceSnapshot> = response.history
+        .into_iter()
+        .filter_map(|point| {
+            let timestamp = chrono::DateTime::from_timestamp(point.t, 0)?;
+            Some(PriceSnapshot {
+                timestamp,
+                yes_price: point.p,
+                no_price: 1.0 - point.p,
+                volume: 0.0, // L'endpoint non riporta volumi
+            })
+        })
+        .collect();
+    snapshots.sort_by_key(|s| s.timestamp);
+    Ok(snapshots)
+}
 
-            eprintln!("✅ Authentication headers added");
-        } else {
-            eprintln!("⚠️  No authentication credentials available");
-        }
+/// Seconds between re-syncs of the CLOB server clock
+pub const CLOCK_SYNC_SECS: i64 = 600;
 
-        let response = request
-            .send()
-            .await
-            .context("Failed to fetch markets from Gamma API")?;
+/// Offset of the CLOB server clock from the local one, applied to the timestamps of signed requests
+#[derive(Debug, Clone, Default)]
+pub struct ServerClock {
+    offset_ms: Arc<AtomicI64>, // Ora server - ora locale
+    synced_at_ms: Arc<AtomicI64>, // Ora locale dell'ultima sincronizzazione (0 = mai)
+}
 
-        if !response.status().is_success() {
-            return Err(anyhow::anyhow!("Gamma API returned error: {}", response.status()));
-        }
+impl ServerClock {
+    /// Record a `/time` answer (whole seconds) for a request sent and answered at the given local times; returns the offset
+    pub fn record(&self, server_secs: i64, sent_ms: i64, received_ms: i64) -> i64 {
+        // Il server tronca ai secondi: stima a metà secondo, confrontata con il punto medio del round trip
+        let server_ms = server_secs * 1000 + 500;
+        let local_ms = sent_ms + (received_ms - sent_ms) / 2;
+        let offset = server_ms - local_ms;
+        self.offset_ms.store(offset, Ordering::Relaxed);
+        self.synced_at_ms.store(received_ms, Ordering::Relaxed);
+        offset
+    }
 
-        let json: serde_json::Value = response.json().await
-            .context("Failed to parse Gamma API response")?;
+    pub fn offset_ms(&self) -> i64 {
+        self.offset_ms.load(Ordering::Relaxed)
+    }
 
-        let markets = self.parse_markets_response(json)?;
-        eprintln!("✅ Fetched {} markets from Polymarket", markets.len());
+    /// Whether the clock was never synced or the last sync is older than `CLOCK_SYNC_SECS`
+    pub fn is_due(&self, now_ms: i64) -> bool {
+        let synced_at = self.synced_at_ms.load(Ordering::Relaxed);
+        synced_at == 0 || now_ms - synced_at >= CLOCK_SYNC_SECS * 1000
+    }
 
-        Ok(markets)
+    /// Current server time in unix seconds, as used in POLY_TIMESTAMP
+    pub fn now_secs(&self) -> i64 {
+        (chrono::Utc::now().timestamp_millis() + self.offset_ms()).div_euclid(1000)
     }
+}
 
-    /// Parse markets response from Gamma API
-    fn parse_markets_response(&self, json: serde_json::Value) -> Result<Vec<MarketData>> {
-        let mut markets = Vec::new();
+/// Parse the `/time` response: unix seconds as a bare number or string
+pub fn parse_server_time(body: &str) -> Result<i64> {
+    let value: serde_json::Value = serde_json::from_str(body.trim())
+        .map_err(|_| PolymarketApiError::Decode(format!("Invalid server time {:?}", body)))?;
+    clob_number(&value)
+        .filter(|secs| *secs > 0.0)
+        .map(|secs| secs as i64)
+        .ok_or_else(|| PolymarketApiError::Decode(format!("Invalid server time {:?}", body)))
+}
 
-        if let Some(market_array) = json.as_array() {
-            for (i, market_data) in market_array.iter().enumerate() {
-                if let Ok(market) = self.parse_single_market(market_data, i) {
-                    markets.push(market);
-                }
-            }
+/// L2 API credentials (key, secret, passphrase) for CLOB private endpoints
+#[derive(Debug, Clone)]
+pub struct ApiCredentials {
+    pub api_key: String,
+    pub secret: String,
+    pub passphrase: String,
+}
+
+impl ApiCredentials {
+    /// Build credentials only when all three parts are available
+    pub fn from_parts(api_key: Option<String>, secret: Option<String>, passphrase: Option<String>) -> Option<Self> {
+        match (api_key, secret, passphrase) {
+            (Some(api_key), Some(secret), Some(passphrase)) => Some(Self { api_key, secret, passphrase }),
+            _ => None,
         }
+    }
 
-        Ok(markets)
+    /// Parse the credentials returned by `/auth/api-key` and `/auth/derive-api-key`
+    pub fn from_json(json: &serde_json::Value) -> Result<Self> {
+        let field = |name: &str| json.get(name).and_then(|v| v.as_str()).filter(|v| !v.is_empty()).map(str::to_string);
+        Self::from_parts(field("apiKey"), field("secret"), field("passphrase"))
+            .ok_or_else(|| PolymarketApiError::AuthFailed("API key response without apiKey, secret and passphrase".to_string()))
     }
 
-    /// Parse single market from API response
-    fn parse_single_market(&self, market_data: &serde_json::Value, index: usize) -> Result<MarketData> {
-        let question = market_data.get("question")
-            .and_then(|v| v.as_str())
-            .unwrap_or(&format!("MarketData {}", index))
-            .to_string();
+    /// Compute the L2 signature: base64url(HMAC-SHA256(secret, timestamp + method + path + body))
+    pub fn sign(&self, timestamp: i64, method: &str, request_path: &str, body: &str) -> Result<String> {
+        // Il secret è codificato in base64 url-safe; alcune chiavi usano l'alfabeto standard
+        let key = URL_SAFE
+            .decode(self.secret.as_bytes())
+            .or_else(|_| STANDARD.decode(self.secret.as_bytes()))
+            .map_err(|_| PolymarketApiError::AuthFailed("API secret is not valid base64".to_string()))?;
 
-        let market_id = market_data.get("id")
-            .and_then(|v| v.as_str())
-            .unwrap_or(&format!("market_{}", index))
-            .to_string();
+        let message = format!("{}{}{}{}", timestamp, method, request_path, body);
 
-        // Simulated prices based on API data structure
-        let base_price = market_data.get("basePrice")
-            .and_then(|v| v.as_f64())
-            .unwrap_or(0.5);
+        let mut mac = Hmac::<Sha256>::new_from_slice(&key)
+            .map_err(|_| PolymarketApiError::AuthFailed("Invalid HMAC key length".to_string()))?;
+        mac.update(message.as_bytes());
 
-        let yes_price = base_price;
-        let no_price = 1.0 - base_price;
+        Ok(URL_SAFE.encode(mac.finalize().into_bytes()))
+    }
+}
 
-        Ok(MarketData {
-            id: market_id,
-            question,
-            yes_price,
-            no_price,
-            yes_liquidity: market_data.get("liquidity").and_then(|v| v.as_f64()).unwrap_or(0.0),
-            no_liquidity: market_data.get("no_liquidity").and_then(|v| v.as_f64()).unwrap_or(0.0),
-            volume_24h: market_data.get("volume")
-                .and_then(|v| v.as_f64())
-                .unwrap_or(10000.0),
-            timestamp: chrono::Utc::now(),
-        })
+/// Hit/miss counters of a metadata cache
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+}
+
+impl CacheStats {
+    fn merge(self, other: CacheStats) -> CacheStats {
+        CacheStats { hits: self.hits + other.hits, misses: self.misses + other.misses, entries: self.entries + other.entries }
     }
 }
 
-/// Main Polymarket API client integrating WebSocket and Gamma API
-pub struct PolymarketApiClient {
-    config: PolymarketApiConfig,
-    ws_client: PolymarketWebSocketClient,
-    gamma_client: GammaApiClient,
+/// Key-value cache whose entries expire after a fixed TTL (a zero TTL caches nothing)
+#[derive(Debug, Clone)]
+pub struct TtlCache<T: Clone> {
+    ttl: Duration,
+    entries: Arc<std::sync::Mutex<FxHashMap<String, (Instant, T)>>>,
+    hits: Arc<AtomicU64>,
+    misses: Arc<AtomicU64>,
 }
 
-impl PolymarketApiClient {
-    pub fn new(config: PolymarketApiConfig, api_key: Option<String>, secret: Option<String>, passphrase: Option<String>) -> Self {
+impl<T: Clone> TtlCache<T> {
+    pub fn new(ttl: Duration) -> Self {
         Self {
-            config: config.clone(),
-            ws_client: PolymarketWebSocketClient::new(config.clone()),
-            gamma_client: GammaApiClient::new(config, api_key, secret, passphrase),
+            ttl,
+            entries: Arc::new(std::sync::Mutex::new(FxHashMap::default())),
+            hits: Arc::new(AtomicU64::new(0)),
+            misses: Arc::new(AtomicU64::new(0)),
         }
     }
 
-    /// Initialize the API client
-    pub async fn initialize(&self) -> Result<()> {
-        eprintln!("🚀 Initializing Polymarket API Client");
-
-        // Test Gamma API connection
-        let markets: Vec<MarketData> = self.gamma_client.fetch_markets().await?;
-        eprintln!("✅ Gamma API connection successful - {} markets available", markets.len());
+    /// Fresh value for `key`; an expired entry is dropped
+    pub fn get(&self, key: &str) -> Option<T> {
+        let mut entries = self.entries.lock().unwrap();
+        let value = match entries.get(key) {
+            Some((stored_at, value)) if stored_at.elapsed() < self.ttl => Some(value.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        };
+        let counter = if value.is_some() { &self.hits } else { &self.misses };
+        counter.fetch_add(1, Ordering::Relaxed);
+        value
+    }
+
+    pub fn insert(&self, key: &str, value: T) {
+        if !self.ttl.is_zero() {
+            self.entries.lock().unwrap().insert(key.to_string(), (Instant::now(), value));
+        }
+    }
+
+    /// Drop every entry (the next lookups go to the API)
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.entries.lock().unwrap().len(),
+        }
+    }
+}
+
+/// Gamma API Client for market metadata and discovery
+pub struct GammaApiClient {
+    config: PolymarketApiConfig,
+    http_client: HttpClient,
+    rate_limiter: RateLimiter,
+    markets_cache: TtlCache<Vec<MarketData>>, // Per query
+    events_cache: TtlCache<Vec<EventData>>,   // Per query
+    endpoints: EndpointPool,
+}
+
+impl GammaApiClient {
+    /// Client with its own rate limiter; fails if the configured proxy is unusable
+    pub fn new(config: PolymarketApiConfig) -> Result<Self> {
+        let rate_limiter = config.rate_limiter();
+        Self::with_rate_limiter(config, rate_limiter)
+    }
+
+    /// Client sharing an existing rate limiter
+    pub fn with_rate_limiter(config: PolymarketApiConfig, rate_limiter: RateLimiter) -> Result<Self> {
+        let ttl = Duration::from_secs(config.metadata_ttl_secs);
+        Ok(Self {
+            http_client: config.build_http_client()?,
+            endpoints: config.gamma_endpoints(),
+            config,
+            rate_limiter,
+            markets_cache: TtlCache::new(ttl),
+            events_cache: TtlCache::new(ttl),
+        })
+    }
+
+    /// Drop cached markets and events, e.g. after a filter change
+    pub fn invalidate_cache(&self) {
+        self.markets_cache.clear();
+        self.events_cache.clear();
+    }
+
+    pub fn cache_stats(&self) -> CacheStats {
+        self.markets_cache.stats().merge(self.events_cache.stats())
+    }
+
+    /// Fetch the active market universe from Gamma API (all pages)
+    pub async fn fetch_markets(&self) -> Result<Vec<MarketData>> {
+        self.fetch_markets_with(&MarketQuery::default()).await
+    }
+
+    /// Fetch markets matching a query, following offset pagination until exhausted
+    ///
+    /// Markets outside the configured category filter are dropped.
+    pub async fn fetch_markets_with(&self, query: &MarketQuery) -> Result<Vec<MarketData>> {
+        let key = format!("{:?}", query);
+        if let Some(markets) = self.markets_cache.get(&key) {
+            return Ok(markets);
+        }
+        let filter = &self.config.market_filter;
+        let query = MarketQuery { include_tag: query.include_tag || !filter.is_empty(), ..query.clone() };
+        let items: Vec<serde_json::Value> = self.fetch_pages("/markets", &query).await?
+            .into_iter()
+            .filter(|item| gamma_matches_filter(item, filter))
+            .collect();
+        let markets = self.parse_markets_response(serde_json::Value::Array(items))?;
+
+        eprintln!("✅ Fetched {} markets from Polymarket", markets.len());
+
+        self.markets_cache.insert(&key, markets.clone());
+        Ok(markets)
+    }
+
+    /// Fetch active events with their markets, tags and end dates (all pages)
+    pub async fn fetch_events(&self) -> Result<Vec<EventData>> {
+        self.fetch_events_with(&MarketQuery::default()).await
+    }
+
+    /// Fetch events (with their markets) matching a query
+    pub async fn fetch_events_with(&self, query: &MarketQuery) -> Result<Vec<EventData>> {
+        let key = format!("{:?}", query);
+        if let Some(events) = self.events_cache.get(&key) {
+            return Ok(events);
+        }
+        let items = self.fetch_pages("/events", query).await?;
+        let events: Vec<EventData> = items
+            .iter()
+            .enumerate()
+            .filter(|(_, event)| gamma_matches_filter(event, &self.config.market_filter))
+            .filter_map(|(i, event)| self.parse_event(event, i).ok())
+            .collect();
+
+        eprintln!("✅ Fetched {} events from Polymarket", events.len());
+
+        self.events_cache.insert(&key, events.clone());
+        Ok(events)
+    }
+
+    /// Outcome of a market if it has resolved (Some(true) = YES won)
+    pub async fn fetch_resolution(&self, market_id: &str) -> Result<Option<bool>> {
+        let path = format!("/markets/{}", market_id);
+        let response = send_with_failover(&self.endpoints, &self.rate_limiter, GAMMA_API, "/markets", |base| {
+            self.http_client.get(format!("{}{}", base, path))
+        }).await?;
+        let json: serde_json::Value = response.json().await?;
+        Ok(parse_resolution(&json))
+    }
+
+    /// Raw items of a paginated Gamma listing endpoint
+    async fn fetch_pages(&self, path: &str, query: &MarketQuery) -> Result<Vec<serde_json::Value>> {
+        eprintln!("📡 Fetching from Gamma API: {}", path);
+
+        let mut items = Vec::new();
+        let mut offset = query.offset;
+
+        loop {
+            let page_size = match query.max_results {
+                Some(max) => query.page_size.min(max.saturating_sub(items.len())),
+                None => query.page_size,
+            };
+            if page_size == 0 {
+                break;
+            }
+
+            // Gamma è un'API pubblica: nessun header di autenticazione
+            let pairs = query.to_query_pairs(offset, page_size);
+            let response = send_with_failover(&self.endpoints, &self.rate_limiter, GAMMA_API, path, |base| {
+                self.http_client.get(format!("{}{}", base, path)).query(&pairs)
+            }).await?;
+            let json: serde_json::Value = response.json().await?;
+
+            let page = match json {
+                serde_json::Value::Array(page) => page,
+                _ => Vec::new(),
+            };
+            let raw_count = page.len();
+            items.extend(page);
+            offset += raw_count;
+
+            // Pagina incompleta = ultima pagina
+            if raw_count < page_size {
+                break;
+            }
+        }
+
+        Ok(items)
+    }
+
+    /// Parse one Gamma event with its nested markets
+    fn parse_event(&self, event_data: &serde_json::Value, index: usize) -> Result<EventData> {
+        let id = event_data.get("id")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| format!("event_{}", index));
+
+        let title = event_data.get("title")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string();
+
+        let neg_risk = event_data.get("negRisk")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+        let mut markets = self.parse_markets_response(
+            event_data.get("markets").cloned().unwrap_or(serde_json::Value::Null),
+        )?;
+        for market in &mut markets {
+            market.event_id = Some(id.clone());
+        }
+
+        // Tag come oggetti {id, label, slug}; si conserva l'etichetta
+        let tags = event_data.get("tags")
+            .and_then(|v| v.as_array())
+            .map(|tags| {
+                tags.iter()
+                    .filter_map(|t| t.get("label").or_else(|| t.get("slug")).and_then(|v| v.as_str()))
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let end_date = event_data.get("endDate")
+            .and_then(|v| v.as_str())
+            .and_then(|s| chrono::DateTime::parse_from_rfc3339(s).ok())
+            .map(|d| d.with_timezone(&chrono::Utc));
+
+        Ok(EventData {
+            id,
+            title,
+            neg_risk,
+            markets,
+            timestamp: chrono::Utc::now(),
+            tags,
+            end_date,
+        })
+    }
+
+    /// Parse markets response from Gamma API
+    fn parse_markets_response(&self, json: serde_json::Value) -> Result<Vec<MarketData>> {
+        let mut markets = Vec::new();
+
+        if let Some(market_array) = json.as_array() {
+            for (i, market_data) in market_array.iter().enumerate() {
+                if let Ok(market) = self.parse_single_market(market_data, i) {
+                    markets.push(market);
+                }
+            }
+        }
+
+        Ok(markets)
+    }
+
+    /// Parse single market from API response
+    fn parse_single_market(&self, market_data: &serde_json::Value, index: usize) -> Result<MarketData> {
+        let question = market_data.get("question")
+            .and_then(|v| v.as_str())
+            .unwrap_or(&format!("MarketData {}", index))
+            .to_string();
+
+        let market_id = market_data.get("id")
+            .and_then(|v| v.as_str())
+            .unwrap_or(&format!("market_{}", index))
+            .to_string();
+
+        // Simulated prices based on API data structure
+        let base_price = market_data.get("basePrice")
+            .and_then(|v| v.as_f64())
+            .unwrap_or(0.5);
+
+        let yes_price = base_price;
+        let no_price = 1.0 - base_price;
+
+        Ok(MarketData {
+            id: market_id,
+            question,
+            yes_price,
+            no_price,
+            yes_liquidity: market_data.get("liquidity").and_then(|v| v.as_f64()).unwrap_or(0.0),
+            no_liquidity: market_data.get("no_liquidity").and_then(|v| v.as_f64()).unwrap_or(0.0),
+            volume_24h: market_data.get("volume")
+                .and_then(|v| v.as_f64())
+                .unwrap_or(10000.0),
+            timestamp: chrono::Utc::now(),
+            tokens: parse_token_pair(market_data),
+            spread: market_data.get("spread").and_then(|v| v.as_f64()),
+            event_id: None,
+            category: market_data.get("category").and_then(|v| v.as_str()).map(str::to_string),
+            end_date: market_data.get("endDate")
+                .and_then(|v| v.as_str())
+                .and_then(|s| chrono::DateTime::parse_from_rfc3339(s).ok())
+                .map(|d| d.with_timezone(&chrono::Utc)),
+        })
+    }
+}
+
+/// Gamma encodes list fields either as JSON arrays or as JSON strings holding an array
+fn gamma_string_list(value: Option<&serde_json::Value>) -> Vec<String> {
+    let items = match value {
+        Some(serde_json::Value::Array(items)) => items.clone(),
+        Some(serde_json::Value::String(raw)) => serde_json::from_str(raw).unwrap_or_default(),
+        _ => Vec::new(),
+    };
+    items
+        .iter()
+        .filter_map(|v| match v {
+            serde_json::Value::String(s) => Some(s.clone()),
+            serde_json::Value::Number(n) => Some(n.to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Labels and slugs of a Gamma item's `tags` objects
+fn gamma_tags(item: &serde_json::Value) -> Vec<String> {
+    item.get("tags")
+        .and_then(|v| v.as_array())
+        .map(|tags| {
+            tags.iter()
+                .flat_map(|t| [t.get("label"), t.get("slug")])
+                .flatten()
+                .filter_map(|v| v.as_str())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Whether a Gamma market or event passes the category filter
+pub fn gamma_matches_filter(item: &serde_json::Value, filter: &MarketFilter) -> bool {
+    filter.is_empty() || filter.matches(item.get("category").and_then(|v| v.as_str()), &gamma_tags(item))
+}
+
+/// Extract the YES/NO CLOB token ids from Gamma market metadata
+pub fn parse_token_pair(market_data: &serde_json::Value) -> Option<TokenPair> {
+    let token_ids = gamma_string_list(market_data.get("clobTokenIds"));
+    if token_ids.len() != 2 {
+        return None;
+    }
+
+    // clobTokenIds segue l'ordine di outcomes; senza etichette Yes/No il primo è YES
+    let outcomes = gamma_string_list(market_data.get("outcomes"));
+    let yes_index = outcomes
+        .iter()
+        .position(|o| o.eq_ignore_ascii_case("yes"))
+        .unwrap_or(0);
+
+    Some(TokenPair {
+        yes_token_id: token_ids[yes_index].clone(),
+        no_token_id: token_ids[1 - yes_index].clone(),
+        condition_id: market_data.get("conditionId").and_then(|v| v.as_str()).map(str::to_string),
+    })
+}
+
+/// Outcome of a closed Gamma market: Some(true) if YES paid out, None while unresolved
+pub fn parse_resolution(market_data: &serde_json::Value) -> Option<bool> {
+    if !market_data.get("closed").and_then(|v| v.as_bool()).unwrap_or(false) {
+        return None;
+    }
+    let prices: Vec<f64> = gamma_string_list(market_data.get("outcomePrices"))
+        .iter()
+        .filter_map(|p| p.parse().ok())
+        .collect();
+    if prices.len() != 2 {
+        return None;
+    }
+
+    // outcomePrices segue l'ordine di outcomes, come clobTokenIds
+    let outcomes = gamma_string_list(market_data.get("outcomes"));
+    let yes_index = outcomes.iter().position(|o| o.eq_ignore_ascii_case("yes")).unwrap_or(0);
+    match prices[yes_index] {
+        p if p >= 0.99 => Some(true),
+        p if p <= 0.01 => Some(false),
+        _ => None, // Chiuso ma non ancora pagato (es. in disputa)
+    }
+}
+
+/// Order state reported by the CLOB
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum ClobOrderState {
+    Live,      // A riposo nel book
+    Matched,   // Eseguito
+    Delayed,   // In attesa del ritardo di matching
+    Unmatched, // Piazzato ma non eseguibile (es. FOK fallito)
+    Canceled,
+    Other(String),
+}
+
+impl From<String> for ClobOrderState {
+    fn from(status: String) -> Self {
+        match status.to_ascii_uppercase().as_str() {
+            "LIVE" | "ORDER_STATUS_LIVE" => Self::Live,
+            "MATCHED" | "ORDER_STATUS_MATCHED" => Self::Matched,
+            "DELAYED" | "ORDER_STATUS_DELAYED" => Self::Delayed,
+            "UNMATCHED" | "ORDER_STATUS_UNMATCHED" => Self::Unmatched,
+            "CANCELED" | "CANCELLED" | "ORDER_STATUS_CANCELED" => Self::Canceled,
+            _ => Self::Other(status),
+        }
+    }
+}
+
+impl From<ClobOrderState> for String {
+    fn from(state: ClobOrderState) -> Self {
+        match state {
+            ClobOrderState::Live => "LIVE".to_string(),
+            ClobOrderState::Matched => "MATCHED".to_string(),
+            ClobOrderState::Delayed => "DELAYED".to_string(),
+            ClobOrderState::Unmatched => "UNMATCHED".to_string(),
+            ClobOrderState::Canceled => "CANCELED".to_string(),
+            ClobOrderState::Other(status) => status,
+        }
+    }
+}
+
+/// Open order as returned by `/data/orders`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenOrder {
+    pub id: String,
+    pub status: ClobOrderState,
+    #[serde(default)]
+    pub market: String,
+    pub asset_id: String,
+    pub side: String,
+    #[serde(deserialize_with = "de_f64")]
+    pub price: f64,
+    #[serde(deserialize_with = "de_f64")]
+    pub original_size: f64,
+    #[serde(deserialize_with = "de_f64")]
+    pub size_matched: f64,
+    #[serde(default)]
+    pub created_at: Option<i64>,
+}
+
+impl OpenOrder {
+    pub fn remaining_size(&self) -> f64 {
+        (self.original_size - self.size_matched).max(0.0)
+    }
+
+    /// Executor-side status for this order
+    pub fn order_status(&self) -> OrderStatus {
+        match self.status {
+            ClobOrderState::Live | ClobOrderState::Delayed if self.size_matched > 0.0 => OrderStatus::Partial,
+            ClobOrderState::Live | ClobOrderState::Delayed => OrderStatus::Submitted,
+            ClobOrderState::Matched => OrderStatus::Filled,
+            ClobOrderState::Canceled => OrderStatus::Cancelled,
+            ClobOrderState::Unmatched | ClobOrderState::Other(_) => OrderStatus::Failed,
+        }
+    }
+}
+
+/// Outcome of a cancel request
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CancelResult {
+    #[serde(default)]
+    pub canceled: Vec<String>,
+    #[serde(default)]
+    pub not_canceled: std::collections::BTreeMap<String, String>, // order id -> motivo
+}
+
+/// One page of a cursor-paginated CLOB endpoint
+#[derive(Debug, Clone, Deserialize)]
+struct CursorPage<T> {
+    #[serde(default = "Vec::new")]
+    data: Vec<T>,
+    #[serde(default)]
+    next_cursor: Option<String>,
+}
+
+/// Cursor the CLOB returns on the last page
+const END_CURSOR: &str = "LTE=";
+
+/// Parse a cursor-paginated body (or a bare array) into items and next cursor
+fn parse_cursor_page<T: for<'de> Deserialize<'de>>(json: &serde_json::Value, what: &str) -> Result<(Vec<T>, Option<String>)> {
+    if json.is_array() {
+        let items = serde_json::from_value(json.clone()).map_err(decode_error(what))?;
+        return Ok((items, None));
+    }
+    let page: CursorPage<T> = serde_json::from_value(json.clone()).map_err(decode_error(what))?;
+    let cursor = page.next_cursor.filter(|c| !c.is_empty() && c != END_CURSOR);
+    Ok((page.data, cursor))
+}
+
+/// Parse an open-orders body into orders and next cursor
+pub fn parse_open_orders(json: &serde_json::Value) -> Result<(Vec<OpenOrder>, Option<String>)> {
+    parse_cursor_page(json, "open orders")
+}
+
+/// Fill of the authenticated user, as returned by `/data/trades`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserTrade {
+    pub id: String,
+    #[serde(default)]
+    pub taker_order_id: String,
+    #[serde(default)]
+    pub market: String,
+    pub asset_id: String,
+    pub side: String, // "BUY" o "SELL"
+    #[serde(deserialize_with = "de_f64")]
+    pub size: f64,
+    #[serde(deserialize_with = "de_f64")]
+    pub price: f64,
+    #[serde(default)]
+    pub status: String, // MATCHED, MINED, CONFIRMED, RETRYING, FAILED
+    #[serde(deserialize_with = "de_unix_time")]
+    pub match_time: chrono::DateTime<chrono::Utc>,
+    #[serde(default, deserialize_with = "de_opt_f64")]
+    pub fee_rate_bps: Option<f64>,
+}
+
+impl UserTrade {
+    pub fn is_buy(&self) -> bool {
+        self.side.eq_ignore_ascii_case("BUY")
+    }
+
+    /// Whether the fill failed on-chain and never settled
+    pub fn is_failed(&self) -> bool {
+        self.status.eq_ignore_ascii_case("FAILED")
+    }
+}
+
+/// Parse a user-trades body into fills and next cursor
+pub fn parse_user_trades(json: &serde_json::Value) -> Result<(Vec<UserTrade>, Option<String>)> {
+    parse_cursor_page(json, "user trades")
+}
+
+/// Rate of a reward program, as nested in `/rewards/markets/current`
+#[derive(Debug, Clone, Deserialize)]
+struct RewardRateWire {
+    #[serde(default)]
+    start_date: Option<String>,
+    #[serde(default)]
+    end_date: Option<String>,
+    #[serde(deserialize_with = "de_f64")]
+    rate_per_day: f64,
+}
+
+/// Market entry of `/rewards/markets/current` (max spread in cents)
+#[derive(Debug, Clone, Deserialize)]
+struct RewardMarketWire {
+    condition_id: String,
+    #[serde(default, deserialize_with = "de_opt_f64")]
+    rewards_max_spread: Option<f64>,
+    #[serde(default, deserialize_with = "de_opt_f64")]
+    rewards_min_size: Option<f64>,
+    #[serde(default)]
+    rewards_config: Vec<RewardRateWire>,
+}
+
+/// Entry of `/rewards/user`
+#[derive(Debug, Clone, Deserialize)]
+struct UserRewardWire {
+    date: String,
+    condition_id: String,
+    #[serde(deserialize_with = "de_f64")]
+    earnings: f64,
+}
+
+/// Date of a rewards field, either `YYYY-MM-DD` or an RFC 3339 timestamp
+fn reward_date(value: &str) -> Option<chrono::NaiveDate> {
+    chrono::NaiveDate::parse_from_str(value.get(..10)?, "%Y-%m-%d").ok()
+}
+
+/// Parse a `/rewards/markets/current` page; several rates of one market are summed
+pub fn parse_reward_configs(json: &serde_json::Value) -> Result<(Vec<RewardConfig>, Option<String>)> {
+    let (markets, cursor): (Vec<RewardMarketWire>, _) = parse_cursor_page(json, "reward markets")?;
+    let configs = markets
+        .into_iter()
+        .map(|m| RewardConfig {
+            condition_id: m.condition_id,
+            rate_per_day: m.rewards_config.iter().map(|r| r.rate_per_day).sum(),
+            max_spread: m.rewards_max_spread.unwrap_or(0.0) / 100.0,
+            min_size: m.rewards_min_size.unwrap_or(0.0),
+            start_date: m.rewards_config.iter().filter_map(|r| r.start_date.as_deref().and_then(reward_date)).min(),
+            end_date: m.rewards_config.iter().filter_map(|r| r.end_date.as_deref().and_then(reward_date)).max(),
+        })
+        .collect();
+    Ok((configs, cursor))
+}
+
+/// Parse a `/rewards/user` page into earnings per market and day
+pub fn parse_user_rewards(json: &serde_json::Value) -> Result<(Vec<AccruedReward>, Option<String>)> {
+    let (entries, cursor): (Vec<UserRewardWire>, _) = parse_cursor_page(json, "user rewards")?;
+    let rewards = entries
+        .into_iter()
+        .map(|e| {
+            let date = reward_date(&e.date)
+                .ok_or_else(|| PolymarketApiError::Decode(format!("Invalid reward date {}", e.date)))?;
+            Ok(AccruedReward { condition_id: e.condition_id, date, earnings: e.earnings })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok((rewards, cursor))
+}
+
+/// Number from a CLOB field encoded as string or number
+fn clob_number(value: &serde_json::Value) -> Option<f64> {
+    match value {
+        serde_json::Value::String(s) => s.parse().ok(),
+        serde_json::Value::Number(n) => n.as_f64(),
+        _ => None,
+    }
+}
+
+/// USDC and conditional tokens use 6 decimals on-chain
+const TOKEN_DECIMALS: f64 = 1_000_000.0;
+
+/// Account balances read from `/balance-allowance`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Balances {
+    pub collateral: f64,           // USDC sul conto
+    pub collateral_allowance: f64, // USDC spendibili dall'exchange
+    pub positions: std::collections::BTreeMap<String, f64>, // token id -> share detenute
+    pub fetched_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl Balances {
+    /// Collateral the exchange can actually spend
+    pub fn available_collateral(&self) -> f64 {
+        self.collateral.min(self.collateral_allowance).max(0.0)
+    }
+
+    pub fn position(&self, token_id: &str) -> f64 {
+        self.positions.get(token_id).copied().unwrap_or(0.0)
+    }
+}
+
+/// Parse a balance-allowance body into (balance, allowance) in whole units
+pub fn parse_balance_allowance(json: &serde_json::Value) -> Result<(f64, f64)> {
+    let balance = json.get("balance")
+        .and_then(clob_number)
+        .ok_or_else(|| PolymarketApiError::Decode("balance-allowance response without balance".to_string()))?;
+    // Le versioni recenti restituiscono un'allowance per contratto exchange
+    let allowance = match (json.get("allowance"), json.get("allowances")) {
+        (Some(value), _) => clob_number(value).unwrap_or(0.0),
+        (None, Some(serde_json::Value::Object(map))) => map.values().filter_map(clob_number).fold(0.0, f64::max),
+        _ => 0.0,
+    };
+
+    Ok((balance / TOKEN_DECIMALS, allowance / TOKEN_DECIMALS))
+}
+
+/// Tick size and minimum order size from a `/book` body (CLOB defaults for missing fields)
+pub fn parse_order_constraints(json: &serde_json::Value) -> Result<OrderConstraints> {
+    let field = |name: &str| json.get(name).and_then(clob_number);
+    if !json.is_object() {
+        return Err(PolymarketApiError::Decode("Invalid order book response".to_string()));
+    }
+
+    let defaults = OrderConstraints::default();
+    let tick_size = field("tick_size").or_else(|| field("minimum_tick_size")).unwrap_or(defaults.tick_size);
+    let min_size = field("min_order_size").unwrap_or(defaults.min_size);
+    if tick_size <= 0.0 || tick_size >= 1.0 {
+        return Err(PolymarketApiError::Decode(format!("Invalid tick size {}", tick_size)));
+    }
+
+    Ok(OrderConstraints { tick_size, min_size })
+}
+
+/// Parse a batched `/midpoints` or `/spreads` body (token id -> value)
+pub fn parse_token_values(json: &serde_json::Value) -> Result<FxHashMap<String, f64>> {
+    let map = json.as_object()
+        .ok_or_else(|| PolymarketApiError::Decode("Invalid batched price response".to_string()))?;
+    Ok(map
+        .iter()
+        .filter_map(|(token_id, value)| clob_number(value).map(|v| (token_id.clone(), v)))
+        .collect())
+}
+
+/// Parse a batched `/books` body into books keyed by token id (malformed entries are skipped)
+pub fn parse_order_books(json: &serde_json::Value) -> Result<FxHashMap<String, WsBookEvent>> {
+    let items = json.as_array()
+        .ok_or_else(|| PolymarketApiError::Decode("Invalid batched order book response".to_string()))?;
+    Ok(items
+        .iter()
+        .filter_map(|item| serde_json::from_value::<WsBookEvent>(item.clone()).ok())
+        .map(|book| (book.asset_id.clone(), book))
+        .collect())
+}
+
+/// CLOB API Client for authenticated order, fill and balance endpoints
+pub struct ClobApiClient {
+    config: PolymarketApiConfig,
+    http_client: HttpClient,
+    credentials: Option<ApiCredentials>,
+    #[cfg(feature = "onchain")]
+    wallet: Option<Wallet>, // Chiave L1 da cui derivare le credenziali
+    clock: ServerClock, // Skew rispetto al server, applicato ai timestamp firmati
+    constraints_cache: TtlCache<OrderConstraints>, // Tick e size minima per token
+    rate_limiter: RateLimiter,
+    audit_log: Option<AuditLog>,
+    endpoints: EndpointPool, // Letture pubbliche; le chiamate firmate usano sempre clob_api_url
+}
+
+impl ClobApiClient {
+    /// Client with its own rate limiter; fails if the configured proxy is unusable
+    pub fn new(config: PolymarketApiConfig, credentials: Option<ApiCredentials>) -> Result<Self> {
+        let rate_limiter = config.rate_limiter();
+        Self::with_rate_limiter(config, credentials, rate_limiter)
+    }
+
+    /// Client sharing an existing rate limiter
+    pub fn with_rate_limiter(config: PolymarketApiConfig, credentials: Option<ApiCredentials>, rate_limiter: RateLimiter) -> Result<Self> {
+        Ok(Self {
+            audit_log: config.order_audit_path.clone().map(AuditLog::new),
+            http_client: config.build_http_client()?,
+            constraints_cache: TtlCache::new(Duration::from_secs(config.metadata_ttl_secs)),
+            endpoints: config.clob_endpoints(),
+            config,
+            credentials,
+            #[cfg(feature = "onchain")]
+            wallet: None,
+            clock: ServerClock::default(),
+            rate_limiter,
+        })
+    }
+
+    /// Attach the wallet used for L1 authentication; its address becomes POLY_ADDRESS if none is configured
+    #[cfg(feature = "onchain")]
+    pub fn with_wallet(mut self, wallet: Wallet) -> Self {
+        self.config.wallet_address.get_or_insert_with(|| wallet.address().to_string());
+        self.wallet = Some(wallet);
+        self
+    }
+
+    /// Order audit log, if enabled
+    pub fn audit_log(&self) -> Option<&AuditLog> {
+        self.audit_log.as_ref()
+    }
+
+    /// Whether L2 credentials are configured
+    pub fn is_authenticated(&self) -> bool {
+        self.credentials.is_some()
+    }
+
+    /// Drop cached tick sizes and minimum order sizes
+    pub fn invalidate_cache(&self) {
+        self.constraints_cache.clear();
+    }
+
+    pub fn cache_stats(&self) -> CacheStats {
+        self.constraints_cache.stats()
+    }
+
+    /// Health of the endpoints serving public reads
+    pub fn endpoint_health(&self) -> Vec<crate::failover::EndpointHealth> {
+        self.endpoints.health()
+    }
+
+    /// Server clock used for signed timestamps
+    pub fn server_clock(&self) -> &ServerClock {
+        &self.clock
+    }
+
+    /// Measure the skew against the CLOB `/time` endpoint; returns the offset in milliseconds
+    pub async fn sync_server_time(&self) -> Result<i64> {
+        let url = format!("{}/time", self.config.clob_api_url);
+        let sent_ms = chrono::Utc::now().timestamp_millis();
+        let response = self.rate_limiter.send(CLOB_API, "/time", self.http_client.get(&url)).await?;
+        let body = check_status(response, "/time").await?.text().await?;
+        let received_ms = chrono::Utc::now().timestamp_millis();
+        Ok(self.clock.record(parse_server_time(&body)?, sent_ms, received_ms))
+    }
+
+    /// Build the L1 headers: EIP-712 `ClobAuth` signature of the wallet
+    #[cfg(feature = "onchain")]
+    pub fn l1_headers(&self, nonce: u64) -> Result<HeaderMap> {
+        let wallet = self.wallet.as_ref()
+            .ok_or_else(|| PolymarketApiError::AuthFailed("Wallet private key not configured".to_string()))?;
+        let timestamp = self.clock.now_secs();
+        let signature = wallet.sign_clob_auth(self.config.chain_id, timestamp, nonce)
+            .map_err(PolymarketApiError::AuthFailed)?;
+
+        let header = |value: &str, name: &str| {
+            HeaderValue::from_str(value).map_err(|_| PolymarketApiError::AuthFailed(format!("Invalid {} header", name)))
+        };
+
+        let mut headers = HeaderMap::new();
+        headers.insert("POLY_ADDRESS", header(wallet.address(), "wallet address")?);
+        headers.insert("POLY_SIGNATURE", header(&signature, "signature")?);
+        headers.insert("POLY_TIMESTAMP", header(&timestamp.to_string(), "timestamp")?);
+        headers.insert("POLY_NONCE", header(&nonce.to_string(), "nonce")?);
+
+        Ok(headers)
+    }
+
+    /// Send an L1-signed request and parse the returned credentials
+    #[cfg(feature = "onchain")]
+    async fn request_api_key(&self, method: Method, path: &str, nonce: u64) -> Result<ApiCredentials> {
+        let url = format!("{}{}", self.config.clob_api_url, path);
+        let request = self.http_client.request(method, &url).headers(self.l1_headers(nonce)?);
+        let response = self.rate_limiter.send(CLOB_API, path, request).await?;
+        let json: serde_json::Value = check_status(response, path).await?.json().await?;
+        ApiCredentials::from_json(&json)
+    }
+
+    /// Create new API credentials for the wallet
+    #[cfg(feature = "onchain")]
+    pub async fn create_api_key(&self, nonce: u64) -> Result<ApiCredentials> {
+        self.request_api_key(Method::POST, "/auth/api-key", nonce).await
+    }
+
+    /// Derive the existing API credentials of the wallet for `nonce`
+    #[cfg(feature = "onchain")]
+    pub async fn derive_api_key(&self, nonce: u64) -> Result<ApiCredentials> {
+        self.request_api_key(Method::GET, "/auth/derive-api-key", nonce).await
+    }
+
+    /// Create credentials, or derive them when the wallet already has a key for `nonce`
+    #[cfg(feature = "onchain")]
+    pub async fn create_or_derive_api_key(&self, nonce: u64) -> Result<ApiCredentials> {
+        match self.create_api_key(nonce).await {
+            Ok(credentials) => Ok(credentials),
+            Err(e @ PolymarketApiError::RateLimited { .. }) => Err(e),
+            Err(_) => self.derive_api_key(nonce).await,
+        }
+    }
+
+    /// Derive L2 credentials from the wallet when none are configured; returns whether new ones were set
+    pub async fn ensure_credentials(&mut self) -> Result<bool> {
+        #[cfg(feature = "onchain")]
+        if self.credentials.is_none() && self.wallet.is_some() {
+            self.credentials = Some(self.create_or_derive_api_key(0).await?);
+            return Ok(true);
+        }
+        Ok(false)
+    }
+
+    /// Build the signed L2 headers for a request
+    pub fn l2_headers(&self, method: &Method, request_path: &str, body: &str) -> Result<HeaderMap> {
+        let credentials = self.credentials.as_ref()
+            .ok_or_else(|| PolymarketApiError::AuthFailed("CLOB credentials not configured".to_string()))?;
+        let timestamp = self.clock.now_secs();
+        let signature = credentials.sign(timestamp, method.as_str(), request_path, body)?;
+
+        let header = |value: &str, name: &str| {
+            HeaderValue::from_str(value).map_err(|_| PolymarketApiError::AuthFailed(format!("Invalid {} header", name)))
+        };
+
+        let mut headers = HeaderMap::new();
+        if let Some(address) = &self.config.wallet_address {
+            headers.insert("POLY_ADDRESS", header(address, "wallet address")?);
+        }
+        headers.insert("POLY_SIGNATURE", header(&signature, "signature")?);
+        headers.insert("POLY_TIMESTAMP", header(&timestamp.to_string(), "timestamp")?);
+        headers.insert("POLY_API_KEY", header(&credentials.api_key, "API key")?);
+        headers.insert("POLY_PASSPHRASE", header(&credentials.passphrase, "passphrase")?);
+
+        Ok(headers)
+    }
+
+    /// Send a signed request to a private CLOB endpoint
+    pub async fn send_authenticated(
+        &self,
+        method: Method,
+        request_path: &str,
+        body: Option<&serde_json::Value>,
+    ) -> Result<serde_json::Value> {
+        self.send_signed(method, request_path, &[], body, None).await
+    }
+
+    /// Submit a signed order; request and response are written to the audit log under `trade_id`
+    pub async fn submit_order(&self, trade_id: &str, order: &serde_json::Value) -> Result<serde_json::Value> {
+        self.send_signed(Method::POST, "/order", &[], Some(order), Some(trade_id)).await
+    }
+
+    /// Cancel an open order, audited under the originating `trade_id`
+    pub async fn cancel_order(&self, trade_id: &str, order_id: &str) -> Result<CancelResult> {
+        let body = serde_json::json!({ "orderID": order_id });
+        let json = self.send_signed(Method::DELETE, "/order", &[], Some(&body), Some(trade_id)).await?;
+        serde_json::from_value(json).map_err(decode_error("cancel"))
+    }
+
+    /// Cancel every open order of the account
+    pub async fn cancel_all(&self) -> Result<CancelResult> {
+        let json = self.send_signed(Method::DELETE, "/cancel-all", &[], None, Some("cancel-all")).await?;
+        serde_json::from_value(json).map_err(decode_error("cancel-all"))
+    }
+
+    /// Every page of a cursor-paginated private endpoint
+    async fn fetch_all_pages<T: for<'de> Deserialize<'de>>(
+        &self,
+        path: &str,
+        query: Vec<(&'static str, String)>,
+        what: &str,
+    ) -> Result<Vec<T>> {
+        let mut items = Vec::new();
+        let mut cursor: Option<String> = None;
+
+        loop {
+            let mut page_query = query.clone();
+            if let Some(cursor) = &cursor {
+                page_query.push(("next_cursor", cursor.clone()));
+            }
+
+            let json = self.send_signed(Method::GET, path, &page_query, None, None).await?;
+            let (page, next) = parse_cursor_page(&json, what)?;
+            items.extend(page);
+
+            match next {
+                Some(next) if cursor.as_ref() != Some(&next) => cursor = Some(next),
+                _ => break,
+            }
+        }
+
+        Ok(items)
+    }
+
+    /// Open orders of the account, optionally filtered by market (condition id) and token
+    pub async fn get_open_orders(&self, market: Option<&str>, asset_id: Option<&str>) -> Result<Vec<OpenOrder>> {
+        let mut query = Vec::new();
+        if let Some(market) = market {
+            query.push(("market", market.to_string()));
+        }
+        if let Some(asset_id) = asset_id {
+            query.push(("asset_id", asset_id.to_string()));
+        }
+        self.fetch_all_pages("/data/orders", query, "open orders").await
+    }
+
+    /// Current state of one order of the account, open or not
+    pub async fn get_order(&self, order_id: &str) -> Result<OpenOrder> {
+        let json = self.send_authenticated(Method::GET, &format!("/data/order/{}", order_id), None).await?;
+        serde_json::from_value(json).map_err(decode_error("order"))
+    }
+
+    /// Fills of the account matched at or after `since`, oldest first
+    pub async fn get_trades(&self, since: chrono::DateTime<chrono::Utc>) -> Result<Vec<UserTrade>> {
+        let query = vec![("after", since.timestamp().to_string())];
+        let mut trades: Vec<UserTrade> = self.fetch_all_pages("/data/trades", query, "user trades").await?;
+        trades.sort_by_key(|t| t.match_time);
+        Ok(trades)
+    }
+
+    /// La firma L2 copre il path senza query string
+    async fn send_signed(
+        &self,
+        method: Method,
+        request_path: &str,
+        query: &[(&str, String)],
+        body: Option<&serde_json::Value>,
+        audit_trade_id: Option<&str>,
+    ) -> Result<serde_json::Value> {
+        let body = body.map(|b| b.to_string()).unwrap_or_default();
+        let headers = self.l2_headers(&method, request_path, &body)?;
+        let url = format!("{}{}", self.config.clob_api_url, request_path);
+
+        let audit = match (audit_trade_id, &self.audit_log) {
+            (Some(trade_id), Some(_)) => Some(AuditRecord::request(trade_id, method.as_str(), request_path, &body, &headers)),
+            _ => None,
+        };
+
+        let mut request = self.http_client
+            .request(method, &url)
+            .query(query)
+            .headers(headers);
+        if !body.is_empty() {
+            request = request
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .body(body);
+        }
+
+        let sent = std::time::Instant::now();
+        let response = match self.rate_limiter.send(CLOB_API, request_path, request).await {
+            Ok(response) => response,
+            Err(e) => {
+                self.write_audit(audit.map(|r| r.with_error(&e.to_string(), sent.elapsed())));
+                return Err(e);
+            }
+        };
+
+        let status = response.status();
+        let retry_after = crate::rate_limit::parse_retry_after(response.headers());
+        let text = response.text().await.unwrap_or_default();
+        self.write_audit(audit.map(|r| r.with_response(status.as_u16(), &text, sent.elapsed())));
+
+        if !status.is_success() {
+            return Err(PolymarketApiError::from_status(status, request_path, retry_after, &text));
+        }
+
+        serde_json::from_str(&text).map_err(decode_error(request_path))
+    }
+
+    /// Un errore di audit non deve bloccare l'ordine: viene solo segnalato
+    fn write_audit(&self, record: Option<AuditRecord>) {
+        if let (Some(log), Some(record)) = (&self.audit_log, record) {
+            if let Err(e) = log.append(&record) {
+                eprintln!("⚠️  Order audit write failed: {}", e);
+            }
+        }
+    }
+
+    /// Historical prices of one outcome token, `interval_minutes` apart (public endpoint)
+    pub async fn fetch_price_history(
+        &self,
+        token_id: &str,
+        interval_minutes: u32,
+        range: HistoryRange,
+    ) -> Result<Vec<PriceSnapshot>> {
+        let mut query = vec![
+            ("market", token_id.to_string()),
+            ("fidelity", interval_minutes.max(1).to_string()),
+        ];
+        query.extend(range.to_query_pairs());
+
+        let path = "/prices-history";
+        let response = send_with_failover(&self.endpoints, &self.rate_limiter, CLOB_API, path, |base| {
+            self.http_client.get(format!("{}{}", base, path)).query(&query)
+        }).await?;
+        let json: serde_json::Value = response.json().await?;
+        parse_price_history(&json)
+    }
+
+    /// Balance and allowance of the collateral (`token_id` None) or of one conditional token
+    pub async fn get_balance_allowance(&self, token_id: Option<&str>) -> Result<(f64, f64)> {
+        let mut query = vec![("signature_type", self.config.signature_type.to_string())];
+        match token_id {
+            Some(token_id) => {
+                query.push(("asset_type", "CONDITIONAL".to_string()));
+                query.push(("token_id", token_id.to_string()));
+            }
+            None => query.push(("asset_type", "COLLATERAL".to_string())),
+        }
+
+        let json = self.send_signed(Method::GET, "/balance-allowance", &query, None, None).await?;
+        parse_balance_allowance(&json)
+    }
+
+    /// USDC collateral plus positions in the given tokens (tokens with no shares are omitted)
+    pub async fn get_balances(&self, token_ids: &[String]) -> Result<Balances> {
+        let (collateral, collateral_allowance) = self.get_balance_allowance(None).await?;
+
+        let mut positions = std::collections::BTreeMap::new();
+        for token_id in token_ids {
+            let (shares, _) = self.get_balance_allowance(Some(token_id)).await?;
+            if shares > 0.0 {
+                positions.insert(token_id.clone(), shares);
+            }
+        }
+
+        Ok(Balances {
+            collateral,
+            collateral_allowance,
+            positions,
+            fetched_at: Some(chrono::Utc::now()),
+        })
+    }
+
+    /// Tick size and minimum order size of the market a token belongs to (public endpoint)
+    pub async fn get_order_constraints(&self, token_id: &str) -> Result<OrderConstraints> {
+        if let Some(constraints) = self.constraints_cache.get(token_id) {
+            return Ok(constraints);
+        }
+        let response = send_with_failover(&self.endpoints, &self.rate_limiter, CLOB_API, "/book", |base| {
+            self.http_client.get(format!("{}/book", base)).query(&[("token_id", token_id)])
+        }).await?;
+        let json: serde_json::Value = response.json().await?;
+        let constraints = parse_order_constraints(&json)?;
+        self.constraints_cache.insert(token_id, constraints);
+        Ok(constraints)
+    }
+
+    /// Public GET returning one numeric field
+    async fn get_token_value(&self, path: &str, field: &str, token_id: &str) -> Result<f64> {
+        let response = send_with_failover(&self.endpoints, &self.rate_limiter, CLOB_API, path, |base| {
+            self.http_client.get(format!("{}{}", base, path)).query(&[("token_id", token_id)])
+        }).await?;
+        let json: serde_json::Value = response.json().await?;
+        json.get(field)
+            .and_then(clob_number)
+            .ok_or_else(|| PolymarketApiError::Decode(format!("CLOB {} response without {}", path, field)))
+    }
+
+    /// Public POST with one entry per token, answered as token id -> value
+    async fn get_token_values(&self, path: &str, token_ids: &[String]) -> Result<FxHashMap<String, f64>> {
+        if token_ids.is_empty() {
+            return Ok(FxHashMap::default());
+        }
+        let body: Vec<serde_json::Value> = token_ids
+            .iter()
+            .map(|token_id| serde_json::json!({ "token_id": token_id }))
+            .collect();
+
+        let response = send_with_failover(&self.endpoints, &self.rate_limiter, CLOB_API, path, |base| {
+            self.http_client.post(format!("{}{}", base, path)).json(&body)
+        }).await?;
+        let json: serde_json::Value = response.json().await?;
+        parse_token_values(&json)
+    }
+
+    /// Midpoint between best bid and best ask of a token
+    pub async fn get_midpoint(&self, token_id: &str) -> Result<f64> {
+        self.get_token_value("/midpoint", "mid", token_id).await
+    }
+
+    /// Bid-ask spread of a token
+    pub async fn get_spread(&self, token_id: &str) -> Result<f64> {
+        self.get_token_value("/spread", "spread", token_id).await
+    }
+
+    /// Midpoints of several tokens in one request
+    pub async fn get_midpoints(&self, token_ids: &[String]) -> Result<FxHashMap<String, f64>> {
+        self.get_token_values("/midpoints", token_ids).await
+    }
+
+    /// Spreads of several tokens in one request
+    pub async fn get_spreads(&self, token_ids: &[String]) -> Result<FxHashMap<String, f64>> {
+        self.get_token_values("/spreads", token_ids).await
+    }
+
+    /// Full orderbooks of several tokens in one request (same shape as the WebSocket `book` snapshot)
+    pub async fn get_order_books(&self, token_ids: &[String]) -> Result<FxHashMap<String, WsBookEvent>> {
+        if token_ids.is_empty() {
+            return Ok(FxHashMap::default());
+        }
+        let path = "/books";
+        let body: Vec<serde_json::Value> = token_ids
+            .iter()
+            .map(|token_id| serde_json::json!({ "token_id": token_id }))
+            .collect();
+
+        let response = send_with_failover(&self.endpoints, &self.rate_limiter, CLOB_API, path, |base| {
+            self.http_client.post(format!("{}{}", base, path)).json(&body)
+        }).await?;
+        let json: serde_json::Value = response.json().await?;
+        parse_order_books(&json)
+    }
+
+    /// Reward programs of every market currently paying maker rewards (public endpoint)
+    pub async fn get_reward_configs(&self) -> Result<Vec<RewardConfig>> {
+        let path = "/rewards/markets/current";
+        let mut configs = Vec::new();
+        let mut cursor: Option<String> = None;
+
+        loop {
+            let response = send_with_failover(&self.endpoints, &self.rate_limiter, CLOB_API, path, |base| {
+                let request = self.http_client.get(format!("{}{}", base, path));
+                match &cursor {
+                    Some(cursor) => request.query(&[("next_cursor", cursor)]),
+                    None => request,
+                }
+            }).await?;
+            let json: serde_json::Value = response.json().await?;
+            let (page, next) = parse_reward_configs(&json)?;
+            configs.extend(page);
+
+            match next {
+                Some(next) if cursor.as_ref() != Some(&next) => cursor = Some(next),
+                _ => break,
+            }
+        }
+
+        Ok(configs)
+    }
+
+    /// Rewards earned by the account on `date`, per market
+    pub async fn get_user_rewards(&self, date: chrono::NaiveDate) -> Result<Vec<AccruedReward>> {
+        let query = vec![
+            ("date", date.format("%Y-%m-%d").to_string()),
+            ("signature_type", self.config.signature_type.to_string()),
+        ];
+        let mut rewards = Vec::new();
+        let mut cursor: Option<String> = None;
+
+        loop {
+            let mut page_query = query.clone();
+            if let Some(cursor) = &cursor {
+                page_query.push(("next_cursor", cursor.clone()));
+            }
+            let json = self.send_signed(Method::GET, "/rewards/user", &page_query, None, None).await?;
+            let (page, next) = parse_user_rewards(&json)?;
+            rewards.extend(page);
+
+            match next {
+                Some(next) if cursor.as_ref() != Some(&next) => cursor = Some(next),
+                _ => break,
+            }
+        }
+
+        Ok(rewards)
+    }
+
+    /// List the API keys bound to the configured credentials (verifica autenticazione L2)
+    pub async fn get_api_keys(&self) -> Result<serde_json::Value> {
+        self.send_authenticated(Method::GET, "/auth/api-keys", None).await
+    }
+}
+
+/// Main Polymarket API client integrating WebSocket, Gamma and CLOB APIs
+pub struct PolymarketApiClient {
+    config: PolymarketApiConfig,
+    ws_client: PolymarketWebSocketClient,
+    gamma_client: GammaApiClient,
+    clob_client: ClobApiClient,
+}
+
+impl PolymarketApiClient {
+    /// Client for `config`; an unusable proxy is an error rather than a silent direct connection
+    pub fn new(config: PolymarketApiConfig, api_key: Option<String>, secret: Option<String>, passphrase: Option<String>) -> Result<Self> {
+        // Un solo limiter: Gamma e CLOB condividono i bucket della stessa API key/IP
+        let rate_limiter = config.rate_limiter();
+        let credentials = ApiCredentials::from_parts(api_key, secret, passphrase);
+        Ok(Self {
+            config: config.clone(),
+            ws_client: PolymarketWebSocketClient::new(config.clone()),
+            gamma_client: GammaApiClient::with_rate_limiter(config.clone(), rate_limiter.clone())?,
+            clob_client: ClobApiClient::with_rate_limiter(config, credentials, rate_limiter)?,
+        })
+    }
+
+    /// API configuration in use
+    pub fn config(&self) -> &PolymarketApiConfig {
+        &self.config
+    }
+
+    /// Authenticated CLOB client
+    pub fn clob(&self) -> &ClobApiClient {
+        &self.clob_client
+    }
+
+    /// Attach the wallet used to derive CLOB credentials
+    #[cfg(feature = "onchain")]
+    pub fn with_wallet(mut self, wallet: Wallet) -> Self {
+        self.config.wallet_address.get_or_insert_with(|| wallet.address().to_string());
+        self.clob_client = self.clob_client.with_wallet(wallet);
+        self
+    }
+
+    /// Derive CLOB credentials from the wallet if only a private key was configured
+    pub async fn ensure_credentials(&mut self) -> Result<bool> {
+        self.clob_client.ensure_credentials().await
+    }
+
+    /// CLOB client of another account, sharing endpoints and rate limiter with this one
+    pub fn account_client(&self, credentials: Option<ApiCredentials>, wallet_address: Option<String>) -> Result<ClobApiClient> {
+        let config = PolymarketApiConfig { wallet_address, ..self.config.clone() };
+        ClobApiClient::with_rate_limiter(config, credentials, self.clob_client.rate_limiter.clone())
+    }
+
+    /// Measure the skew against the CLOB server clock used for signed requests
+    pub async fn sync_server_time(&self) -> Result<i64> {
+        self.clob_client.sync_server_time().await
+    }
+
+    /// Force the next metadata lookups (markets, events, tick sizes) to hit the APIs
+    pub fn invalidate_metadata(&self) {
+        self.gamma_client.invalidate_cache();
+        self.clob_client.invalidate_cache();
+    }
+
+    /// Health of the Gamma, CLOB and WebSocket endpoints and their mirrors
+    pub fn endpoint_health(&self) -> MarketDataHealth {
+        MarketDataHealth {
+            gamma: self.gamma_client.endpoints.health(),
+            clob: self.clob_client.endpoint_health(),
+            websocket: self.ws_client.endpoint_health(),
+        }
+    }
+
+    /// Hits and misses of the metadata caches
+    pub fn metadata_cache_stats(&self) -> CacheStats {
+        self.gamma_client.cache_stats().merge(self.clob_client.cache_stats())
+    }
+
+    /// Sampled capture of the exchange API calls, for export to compliance or support
+    pub fn api_capture(&self) -> Option<&ApiCapture> {
+        self.clob_client.rate_limiter.capture()
+    }
+
+    /// Real-time WebSocket client
+    pub fn websocket(&self) -> &PolymarketWebSocketClient {
+        &self.ws_client
+    }
+
+    /// Initialize the API client
+    pub async fn initialize(&self) -> Result<()> {
+        eprintln!("🚀 Initializing Polymarket API Client");
+
+        // Test Gamma API connection
+        let markets: Vec<MarketData> = self.gamma_client.fetch_markets().await?;
+        eprintln!("✅ Gamma API connection successful - {} markets available", markets.len());
 
         // Start WebSocket connection (in a separate task)
         let ws_client = self.ws_client.clone();
@@ -311,4 +2207,612 @@ impl PolymarketApiClient {
     pub async fn get_markets(&self) -> Result<Vec<MarketData>> {
         self.gamma_client.fetch_markets().await
     }
+
+    /// Historical prices of an outcome token from the CLOB prices-history endpoint
+    pub async fn fetch_price_history(
+        &self,
+        token_id: &str,
+        interval_minutes: u32,
+        range: HistoryRange,
+    ) -> Result<Vec<PriceSnapshot>> {
+        self.clob_client.fetch_price_history(token_id, interval_minutes, range).await
+    }
+
+    /// Account balances (requires L2 credentials)
+    pub async fn get_balances(&self, token_ids: &[String]) -> Result<Balances> {
+        self.clob_client.get_balances(token_ids).await
+    }
+
+    /// Tick size and minimum order size for a token's market
+    pub async fn get_order_constraints(&self, token_id: &str) -> Result<OrderConstraints> {
+        self.clob_client.get_order_constraints(token_id).await
+    }
+
+    /// Spreads of several tokens in one request
+    pub async fn get_spreads(&self, token_ids: &[String]) -> Result<FxHashMap<String, f64>> {
+        self.clob_client.get_spreads(token_ids).await
+    }
+
+    /// Midpoints of several tokens in one request
+    pub async fn get_midpoints(&self, token_ids: &[String]) -> Result<FxHashMap<String, f64>> {
+        self.clob_client.get_midpoints(token_ids).await
+    }
+
+    /// Orderbooks of several tokens in one request
+    pub async fn get_order_books(&self, token_ids: &[String]) -> Result<FxHashMap<String, WsBookEvent>> {
+        self.clob_client.get_order_books(token_ids).await
+    }
+
+    /// Fills of the authenticated user since a time (requires L2 credentials)
+    pub async fn get_trades(&self, since: chrono::DateTime<chrono::Utc>) -> Result<Vec<UserTrade>> {
+        self.clob_client.get_trades(since).await
+    }
+
+    /// Maker reward programs of the markets currently paying rewards
+    pub async fn get_reward_configs(&self) -> Result<Vec<RewardConfig>> {
+        self.clob_client.get_reward_configs().await
+    }
+
+    /// Rewards earned by the authenticated user on a day (requires L2 credentials)
+    pub async fn get_user_rewards(&self, date: chrono::NaiveDate) -> Result<Vec<AccruedReward>> {
+        self.clob_client.get_user_rewards(date).await
+    }
+
+    /// Get markets matching a Gamma query
+    pub async fn get_markets_with(&self, query: &MarketQuery) -> Result<Vec<MarketData>> {
+        self.gamma_client.fetch_markets_with(query).await
+    }
+
+    /// Outcome of a market if it has resolved (Some(true) = YES won)
+    pub async fn fetch_resolution(&self, market_id: &str) -> Result<Option<bool>> {
+        self.gamma_client.fetch_resolution(market_id).await
+    }
+
+    /// Get active events with their markets, tags and end dates
+    pub async fn fetch_events(&self) -> Result<Vec<EventData>> {
+        self.gamma_client.fetch_events().await
+    }
+
+    /// Get multi-outcome events matching a Gamma query
+    pub async fn get_events_with(&self, query: &MarketQuery) -> Result<Vec<EventData>> {
+        self.gamma_client.fetch_events_with(query).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::TokenType;
+
+    fn test_credentials() -> ApiCredentials {
+        ApiCredentials {
+            api_key: "test-key".to_string(),
+            secret: "dGVzdC1zZWNyZXQta2V5LTAxMjM0NTY3ODlhYmNkZWY=".to_string(),
+            passphrase: "test-passphrase".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_l2_signature() {
+        let creds = test_credentials();
+
+        let sig = creds.sign(1700000000, "GET", "/auth/api-keys", "").unwrap();
+        assert_eq!(sig, "3frWMXvbSRMNslkMyHljoYAA25UIGCoeaacSMR7T4v8=");
+
+        let sig = creds.sign(1700000000, "POST", "/order", r#"{"price":0.5}"#).unwrap();
+        assert_eq!(sig, "Ubq2OY24O8Fh0v8yR6pyCXhngeW57ax2g0v7NxsHnN0=");
+    }
+
+    #[test]
+    fn test_parse_ws_events() {
+        let frame = r#"[
+            {"event_type":"book","asset_id":"111","market":"0xabc",
+             "bids":[{"price":"0.48","size":"30"},{"price":"0.49","size":"20"}],
+             "asks":[{"price":"0.52","size":"25"},{"price":"0.51","size":"10"}],
+             "timestamp":"1700000000000","hash":"h"},
+            {"event_type":"price_change","asset_id":"111","market":"0xabc",
+             "changes":[{"price":"0.50","side":"SELL","size":"5"}],"timestamp":"1700000000100"},
+            {"event_type":"last_trade_price","asset_id":"222","market":"0xabc",
+             "price":"0.47","side":"BUY","size":"12","timestamp":"1700000000200"},
+            {"event_type":"tick_size_change","asset_id":"111"}
+        ]"#;
+
+        let events = WsMarketEvent::parse_frame(frame).unwrap();
+        assert_eq!(events.len(), 3);
+
+        match &events[0] {
+            WsMarketEvent::Book(book) => {
+                assert_eq!(book.best_ask().unwrap().price, 0.51);
+                assert_eq!(book.best_bid().unwrap().price, 0.49);
+                assert_eq!(book.timestamp, 1700000000000);
+            }
+            other => panic!("unexpected event {:?}", other),
+        }
+        assert!(matches!(&events[1], WsMarketEvent::PriceChange(c) if c.changes[0].side == "SELL"));
+        assert!(matches!(&events[2], WsMarketEvent::Trade(t) if t.asset_id == "222" && t.price == 0.47));
+    }
+
+    #[test]
+    fn test_reconnect_backoff() {
+        let policy = ReconnectPolicy::default();
+        assert_eq!(policy.delay_for(1), Duration::from_millis(500));
+        assert_eq!(policy.delay_for(2), Duration::from_millis(1000));
+        assert_eq!(policy.delay_for(4), Duration::from_millis(4000));
+        assert_eq!(policy.delay_for(20), Duration::from_millis(30_000));
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_gives_up_and_reports_state() {
+        let config = PolymarketApiConfig {
+            websocket_url: "ws://127.0.0.1:1".to_string(),
+            reconnect: ReconnectPolicy { initial_delay_ms: 1, max_delay_ms: 2, multiplier: 2.0, max_attempts: Some(2) },
+            ..PolymarketApiConfig::default()
+        };
+        let client = PolymarketWebSocketClient::new(config);
+        let state = client.state();
+
+        assert!(client.connect().await.is_err());
+        assert_eq!(*state.borrow(), ConnectionState::Disconnected);
+        assert!(!client.is_connected());
+    }
+
+    #[tokio::test]
+    async fn test_heartbeat_and_stale_assets() {
+        // Mock WebSocket: risponde ai ping leggendo, invia un solo evento per l'asset "111"
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (ping_tx, mut ping_rx) = mpsc::channel(16);
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut ws = tokio_tungstenite::accept_async(socket).await.unwrap();
+            let _subscribe = ws.next().await;
+            let book = r#"{"event_type":"book","asset_id":"111","asks":[{"price":"0.4","size":"1"}],"bids":[]}"#;
+            ws.send(Message::Text(book.into())).await.unwrap();
+            while let Some(Ok(msg)) = ws.next().await {
+                if msg.is_ping() {
+                    let _ = ping_tx.send(()).await;
+                }
+            }
+        });
+
+        let config = PolymarketApiConfig {
+            websocket_url: format!("ws://{}", addr),
+            heartbeat: HeartbeatPolicy { ping_interval_ms: 20, stale_after_ms: 150, silent_session_ms: 1_000 },
+            ..PolymarketApiConfig::default()
+        };
+        let client = PolymarketWebSocketClient::new(config);
+        client.set_subscriptions(vec!["111".to_string(), "222".to_string()]).await;
+        assert!(client.stale_assets().await.is_empty()); // Nessuna sessione
+
+        let session = client.clone();
+        tokio::spawn(async move { session.connect().await });
+        assert!(tokio::time::timeout(Duration::from_secs(2), ping_rx.recv()).await.unwrap().is_some());
+
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        let mut stale = client.stale_assets().await;
+        stale.sort();
+        assert_eq!(stale, vec!["111", "222"]);
+
+        client.mark_refreshed(&["222".to_string()]).await;
+        assert_eq!(client.stale_assets().await, vec!["111"]);
+        client.shutdown();
+    }
+
+    #[tokio::test]
+    async fn test_endpoint_override_and_timeout() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Mock Gamma: una pagina con un mercato, poi un server che non risponde mai
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 4096];
+            let _ = socket.read(&mut buf).await;
+            let body = r#"[{"id": "m1", "question": "Mock market?"}]"#;
+            let response = format!("HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}", body.len(), body);
+            socket.write_all(response.as_bytes()).await.unwrap();
+            let (_silent, _) = listener.accept().await.unwrap();
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        });
+
+        let config = PolymarketApiConfig {
+            gamma_api_url: format!("http://{}", addr),
+            request_timeout_ms: 200,
+            ..PolymarketApiConfig::default()
+        };
+        let gamma = GammaApiClient::new(config).unwrap();
+        let query = MarketQuery { max_results: Some(10), ..MarketQuery::default() };
+        let markets = gamma.fetch_markets_with(&query).await.unwrap();
+        assert_eq!(markets[0].question, "Mock market?");
+        // Stessa query entro il TTL: servita dalla cache senza richieste
+        assert_eq!(gamma.fetch_markets_with(&query).await.unwrap()[0].id, "m1");
+        assert_eq!(gamma.cache_stats(), CacheStats { hits: 1, misses: 1, entries: 1 });
+        gamma.invalidate_cache();
+        assert!(matches!(gamma.fetch_markets_with(&query).await, Err(PolymarketApiError::Network(_))));
+
+        let socks = PolymarketApiConfig { proxy: Some("socks5://127.0.0.1:9050".to_string()), ..PolymarketApiConfig::default() };
+        assert!(socks.build_http_client().is_ok());
+        let invalid = PolymarketApiConfig { proxy: Some("not a url".to_string()), ..PolymarketApiConfig::default() };
+        assert!(invalid.build_http_client().is_err());
+        // Proxy inutilizzabile: errore, non una connessione diretta
+        assert!(GammaApiClient::new(invalid.clone()).is_err());
+        assert!(PolymarketApiClient::new(invalid, None, None, None).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_gamma_fails_over_to_mirror() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Principale irraggiungibile, mirror che risponde con un mercato
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 4096];
+            let _ = socket.read(&mut buf).await;
+            let body = r#"[{"id": "m1", "question": "Mirror market?"}]"#;
+            let response = format!("HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}", body.len(), body);
+            socket.write_all(response.as_bytes()).await.unwrap();
+        });
+
+        let config = PolymarketApiConfig {
+            gamma_api_url: "http://127.0.0.1:1".to_string(),
+            gamma_mirrors: vec![format!("http://{}/", addr)],
+            request_timeout_ms: 500,
+            ..PolymarketApiConfig::default()
+        };
+        let client = PolymarketApiClient::new(config, None, None, None).unwrap();
+        let query = MarketQuery { max_results: Some(10), ..MarketQuery::default() };
+        let markets = client.gamma_client.fetch_markets_with(&query).await.unwrap();
+        assert_eq!(markets[0].question, "Mirror market?");
+
+        let health = client.endpoint_health().gamma;
+        assert_eq!((health[0].failures, health[0].successes), (1, 0));
+        assert_eq!(health[1].url, format!("http://{}", addr));
+        assert_eq!((health[1].failures, health[1].successes), (0, 1));
+        assert!(health[1].score > health[0].score);
+        assert_eq!(client.endpoint_health().websocket.len(), 1);
+    }
+
+    #[test]
+    fn test_ttl_cache_expiry() {
+        let cache = TtlCache::new(Duration::from_millis(30));
+        assert_eq!(cache.get("tok"), None);
+        cache.insert("tok", 0.01);
+        assert_eq!(cache.get("tok"), Some(0.01));
+        std::thread::sleep(Duration::from_millis(40));
+        assert_eq!(cache.get("tok"), None);
+        assert_eq!(cache.stats(), CacheStats { hits: 1, misses: 2, entries: 0 });
+
+        // TTL zero: nessuna memorizzazione
+        let disabled = TtlCache::new(Duration::ZERO);
+        disabled.insert("tok", 1);
+        assert_eq!(disabled.get("tok"), None);
+    }
+
+    #[test]
+    fn test_api_error_kinds() {
+        let wait = Some(Duration::from_secs(2));
+        let err = PolymarketApiError::from_status(StatusCode::TOO_MANY_REQUESTS, "/order", wait, "");
+        assert!(matches!(&err, PolymarketApiError::RateLimited { retry_after, .. } if *retry_after == wait));
+        assert!(err.is_transient());
+
+        let err = PolymarketApiError::from_status(StatusCode::UNAUTHORIZED, "/order", None, "bad key");
+        assert!(matches!(err, PolymarketApiError::AuthFailed(_)));
+        let err = PolymarketApiError::from_status(StatusCode::BAD_REQUEST, "/order", None, "not enough balance");
+        assert_eq!(err.to_string(), "invalid order: /order returned 400 Bad Request: not enough balance");
+        assert!(!err.is_transient());
+        assert!(matches!(PolymarketApiError::from_status(StatusCode::NOT_FOUND, "/markets/1", None, ""), PolymarketApiError::NotFound(_)));
+        assert!(matches!(PolymarketApiError::from_status(StatusCode::BAD_GATEWAY, "/book", None, ""), PolymarketApiError::Network(_)));
+
+        let unauthenticated = ClobApiClient::new(PolymarketApiConfig::default(), None).unwrap();
+        assert!(matches!(unauthenticated.l2_headers(&Method::GET, "/data/orders", ""), Err(PolymarketApiError::AuthFailed(_))));
+        assert!(matches!(parse_order_constraints(&serde_json::json!([])), Err(PolymarketApiError::Decode(_))));
+    }
+
+    #[test]
+    fn test_parse_token_pair() {
+        // Formato Gamma: array JSON serializzati come stringhe
+        let market = serde_json::json!({
+            "id": "12",
+            "conditionId": "0xabc",
+            "outcomes": "[\"No\", \"Yes\"]",
+            "clobTokenIds": "[\"111\", \"222\"]"
+        });
+        let tokens = parse_token_pair(&market).unwrap();
+        assert_eq!(tokens.yes_token_id, "222");
+        assert_eq!(tokens.condition_id.as_deref(), Some("0xabc"));
+        assert_eq!(tokens.no_token_id, "111");
+        assert_eq!(tokens.token_id(TokenType::No), "111");
+
+        let market = serde_json::json!({ "clobTokenIds": ["333", "444"] });
+        let tokens = parse_token_pair(&market).unwrap();
+        assert_eq!(tokens.yes_token_id, "333");
+
+        assert!(parse_token_pair(&serde_json::json!({ "clobTokenIds": "[]" })).is_none());
+        assert!(parse_token_pair(&serde_json::json!({})).is_none());
+    }
+
+    #[test]
+    fn test_parse_resolution() {
+        let resolved = serde_json::json!({"closed": true, "outcomes": "[\"No\", \"Yes\"]", "outcomePrices": "[\"0\", \"1\"]"});
+        assert_eq!(parse_resolution(&resolved), Some(true));
+        let lost = serde_json::json!({"closed": true, "outcomePrices": ["0", "1"]});
+        assert_eq!(parse_resolution(&lost), Some(false));
+        let open = serde_json::json!({"closed": false, "outcomePrices": "[\"0.4\", \"0.6\"]"});
+        assert_eq!(parse_resolution(&open), None);
+    }
+
+    #[test]
+    fn test_parse_price_history() {
+        let json = serde_json::json!({
+            "history": [
+                {"t": 1700003600, "p": 0.55},
+                {"t": 1700000000, "p": "0.50"}
+            ]
+        });
+        let snapshots = parse_price_history(&json).unwrap();
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(snapshots[0].timestamp.timestamp(), 1700000000);
+        assert_eq!(snapshots[0].yes_price, 0.50);
+        assert!((snapshots[1].no_price - 0.45).abs() < 1e-12);
+
+        assert!(parse_price_history(&serde_json::json!({})).unwrap().is_empty());
+
+        let start = chrono::DateTime::from_timestamp(1700000000, 0).unwrap();
+        let end = chrono::DateTime::from_timestamp(1700086400, 0).unwrap();
+        let pairs = HistoryRange::Between { start, end }.to_query_pairs();
+        assert_eq!(pairs, vec![("startTs", "1700000000".to_string()), ("endTs", "1700086400".to_string())]);
+        assert_eq!(HistoryRange::LastWeek.to_query_pairs(), vec![("interval", "1w".to_string())]);
+    }
+
+    #[test]
+    fn test_parse_neg_risk_event() {
+        let gamma = GammaApiClient::new(PolymarketApiConfig::default()).unwrap();
+        let event = serde_json::json!({
+            "id": "903",
+            "title": "Who will win the election?",
+            "negRisk": true,
+            "endDate": "2026-11-03T12:00:00Z",
+            "tags": [{"id": "2", "label": "Politics", "slug": "politics"}],
+            "markets": [
+                {"id": "1", "question": "Candidate A?", "clobTokenIds": "[\"11\", \"12\"]"},
+                {"id": "2", "question": "Candidate B?", "clobTokenIds": "[\"21\", \"22\"]"}
+            ]
+        });
+
+        let parsed = gamma.parse_event(&event, 0).unwrap();
+        assert_eq!(parsed.id, "903");
+        assert!(parsed.neg_risk);
+        assert_eq!(parsed.markets.len(), 2);
+        assert_eq!(parsed.markets[1].tokens.as_ref().unwrap().yes_token_id, "21");
+        assert_eq!(parsed.markets[0].event_id.as_deref(), Some("903"));
+        assert_eq!(parsed.tags, vec!["Politics"]);
+        assert_eq!(parsed.end_date.unwrap().to_rfc3339(), "2026-11-03T12:00:00+00:00");
+    }
+
+    #[test]
+    fn test_parse_open_orders_and_cancel() {
+        let page = serde_json::json!({
+            "data": [{
+                "id": "0xabc", "status": "ORDER_STATUS_LIVE", "market": "0xm1", "asset_id": "123",
+                "side": "BUY", "price": "0.52", "original_size": "100", "size_matched": "25",
+                "created_at": 1700000000
+            }],
+            "next_cursor": "MTAw"
+        });
+        let (orders, cursor) = parse_open_orders(&page).unwrap();
+        assert_eq!(orders[0].status, ClobOrderState::Live);
+        assert_eq!(orders[0].remaining_size(), 75.0);
+        assert_eq!(orders[0].order_status(), OrderStatus::Partial);
+        assert_eq!(cursor.as_deref(), Some("MTAw"));
+
+        let last = serde_json::json!({ "data": [], "next_cursor": "LTE=" });
+        assert!(parse_open_orders(&last).unwrap().1.is_none());
+
+        let cancel: CancelResult = serde_json::from_value(serde_json::json!({
+            "canceled": ["0xabc"],
+            "not_canceled": { "0xdef": "order already matched" }
+        })).unwrap();
+        assert_eq!(cancel.canceled, vec!["0xabc"]);
+        assert_eq!(cancel.not_canceled["0xdef"], "order already matched");
+    }
+
+    #[test]
+    fn test_parse_balance_allowance() {
+        let legacy = serde_json::json!({ "balance": "2500000000", "allowance": "1000000000" });
+        assert_eq!(parse_balance_allowance(&legacy).unwrap(), (2500.0, 1000.0));
+
+        let current = serde_json::json!({
+            "balance": "1500000",
+            "allowances": { "0xexchange": "0", "0xnegrisk": "115792089237316195423570985008687907853269984665640564039457584007913129639935" }
+        });
+        let (balance, allowance) = parse_balance_allowance(&current).unwrap();
+        assert_eq!(balance, 1.5);
+        assert!(allowance > balance);
+
+        let balances = Balances { collateral: 2500.0, collateral_allowance: 1000.0, ..Default::default() };
+        assert_eq!(balances.available_collateral(), 1000.0);
+        assert!(parse_balance_allowance(&serde_json::json!({})).is_err());
+    }
+
+    #[test]
+    fn test_parse_rewards() {
+        let page = serde_json::json!({
+            "data": [{
+                "condition_id": "0xabc", "rewards_max_spread": 3.5, "rewards_min_size": "50",
+                "rewards_config": [
+                    {"asset_address": "0xusdc", "start_date": "2024-01-01", "end_date": "2500-12-31", "rate_per_day": 20},
+                    {"asset_address": "0xusdc", "start_date": "2024-03-01T00:00:00Z", "end_date": "2024-06-30", "rate_per_day": "5"}
+                ]
+            }],
+            "next_cursor": "LTE="
+        });
+        let (configs, cursor) = parse_reward_configs(&page).unwrap();
+        assert!(cursor.is_none());
+        assert_eq!(configs[0].rate_per_day, 25.0);
+        assert!((configs[0].max_spread - 0.035).abs() < 1e-12);
+        assert_eq!(configs[0].min_size, 50.0);
+        assert_eq!(configs[0].start_date, chrono::NaiveDate::from_ymd_opt(2024, 1, 1));
+        assert_eq!(configs[0].end_date, chrono::NaiveDate::from_ymd_opt(2500, 12, 31));
+
+        let earnings = serde_json::json!({
+            "data": [{"date": "2024-05-01", "condition_id": "0xabc", "asset_address": "0xusdc", "maker_address": "0xme", "earnings": "1.25", "asset_rate": 1}],
+            "next_cursor": "MTAw"
+        });
+        let (rewards, cursor) = parse_user_rewards(&earnings).unwrap();
+        assert_eq!(rewards[0].earnings, 1.25);
+        assert_eq!(cursor.as_deref(), Some("MTAw"));
+        assert!(parse_user_rewards(&serde_json::json!([{"date": "May", "condition_id": "0x", "earnings": 1}])).is_err());
+    }
+
+    #[test]
+    fn test_parse_order_constraints() {
+        let book = serde_json::json!({
+            "market": "0xm1", "asset_id": "123", "bids": [], "asks": [],
+            "tick_size": "0.001", "min_order_size": "15"
+        });
+        assert_eq!(parse_order_constraints(&book).unwrap(), OrderConstraints { tick_size: 0.001, min_size: 15.0 });
+
+        let sparse = serde_json::json!({ "minimum_tick_size": 0.01 });
+        assert_eq!(parse_order_constraints(&sparse).unwrap(), OrderConstraints::default());
+        assert!(parse_order_constraints(&serde_json::json!({ "tick_size": "0" })).is_err());
+    }
+
+    #[test]
+    fn test_parse_token_values() {
+        let json = serde_json::json!({ "123": "0.02", "456": 0.015, "789": null });
+        let spreads = parse_token_values(&json).unwrap();
+        assert_eq!(spreads.len(), 2);
+        assert_eq!(spreads["123"], 0.02);
+        assert!(parse_token_values(&serde_json::json!([])).is_err());
+    }
+
+    #[test]
+    fn test_parse_order_books() {
+        let json = serde_json::json!([
+            {"market": "0xm1", "asset_id": "123", "timestamp": "1700000000000", "hash": "0xabc",
+             "bids": [{"price": "0.48", "size": "30"}], "asks": [{"price": "0.52", "size": "25"}, {"price": "0.51", "size": "10"}],
+             "min_order_size": "5", "tick_size": "0.01"},
+            {"market": "0xm1", "asset_id": "456", "bids": [], "asks": []},
+            {"error": "no orderbook exists for the requested token id"}
+        ]);
+        let books = parse_order_books(&json).unwrap();
+        assert_eq!(books.len(), 2);
+        assert_eq!(books["123"].best_ask().unwrap().price, 0.51);
+        assert_eq!(books["123"].timestamp, 1_700_000_000_000);
+        assert!(books["456"].best_bid().is_none());
+        assert!(parse_order_books(&serde_json::json!({})).is_err());
+    }
+
+    #[test]
+    fn test_parse_user_trades() {
+        let page = serde_json::json!({
+            "data": [{
+                "id": "28c4d2eb", "taker_order_id": "0x06bc63e3", "market": "0xbd31dc8a",
+                "asset_id": "5212", "side": "BUY", "size": "20", "fee_rate_bps": "0",
+                "price": "0.48", "status": "CONFIRMED", "match_time": "1700000000",
+                "outcome": "YES", "maker_orders": []
+            }],
+            "next_cursor": "LTE="
+        });
+        let (fills, cursor) = parse_user_trades(&page).unwrap();
+        assert!(cursor.is_none());
+        assert!(fills[0].is_buy() && !fills[0].is_failed());
+        assert_eq!(fills[0].size, 20.0);
+        assert_eq!(fills[0].match_time.timestamp(), 1_700_000_000);
+    }
+
+    #[test]
+    fn test_market_query_pairs() {
+        let query = MarketQuery {
+            liquidity_num_min: Some(1000.0),
+            tag_id: Some(21),
+            ..MarketQuery::default()
+        };
+        let pairs = query.to_query_pairs(500, 100);
+
+        assert!(pairs.contains(&("limit", "100".to_string())));
+        assert!(pairs.contains(&("offset", "500".to_string())));
+        assert!(pairs.contains(&("active", "true".to_string())));
+        assert!(pairs.contains(&("closed", "false".to_string())));
+        assert!(pairs.contains(&("liquidity_num_min", "1000".to_string())));
+        assert!(pairs.contains(&("tag_id", "21".to_string())));
+        assert!(!pairs.iter().any(|(k, _)| *k == "volume_num_min"));
+    }
+
+    #[test]
+    fn test_category_filter() {
+        let filter = MarketFilter { categories: vec!["Crypto".to_string()], exclude: vec!["memecoins".to_string()] };
+        let btc = serde_json::json!({"id": "1", "category": "Crypto"});
+        let eth = serde_json::json!({"id": "2", "tags": [{"label": "Ethereum", "slug": "crypto"}]});
+        let meme = serde_json::json!({"id": "3", "category": "Crypto", "tags": [{"label": "Memecoins", "slug": "memecoins"}]});
+        let nba = serde_json::json!({"id": "4", "category": "Sports"});
+
+        assert!(gamma_matches_filter(&btc, &filter));
+        assert!(gamma_matches_filter(&eth, &filter));
+        assert!(!gamma_matches_filter(&meme, &filter));
+        assert!(!gamma_matches_filter(&nba, &filter));
+        assert!(gamma_matches_filter(&nba, &MarketFilter::default()));
+    }
+
+    #[test]
+    fn test_l2_headers() {
+        let config = PolymarketApiConfig {
+            wallet_address: Some("0x0000000000000000000000000000000000000001".to_string()),
+            ..PolymarketApiConfig::default()
+        };
+        let client = ClobApiClient::new(config, Some(test_credentials())).unwrap();
+
+        let headers = client.l2_headers(&Method::GET, "/auth/api-keys", "").unwrap();
+        assert_eq!(headers["POLY_API_KEY"], "test-key");
+        assert_eq!(headers["POLY_PASSPHRASE"], "test-passphrase");
+        assert!(headers.contains_key("POLY_SIGNATURE"));
+        assert!(headers.contains_key("POLY_TIMESTAMP"));
+        assert!(headers.contains_key("POLY_ADDRESS"));
+
+        let anonymous = ClobApiClient::new(PolymarketApiConfig::default(), None).unwrap();
+        assert!(anonymous.l2_headers(&Method::GET, "/auth/api-keys", "").is_err());
+    }
+
+    #[test]
+    fn test_server_clock_skew() {
+        assert_eq!(parse_server_time("1700000000").unwrap(), 1_700_000_000);
+        assert_eq!(parse_server_time("\"1700000000\"\n").unwrap(), 1_700_000_000);
+        assert!(parse_server_time("{}").is_err());
+
+        // Locale indietro di 90 s, round trip di 200 ms
+        let clock = ServerClock::default();
+        assert!(clock.is_due(1_000));
+        let sent_ms = 1_699_999_910_000;
+        assert_eq!(clock.record(1_700_000_000, sent_ms, sent_ms + 200), 90_400);
+        assert!(!clock.is_due(sent_ms + 1_000));
+        assert!(clock.is_due(sent_ms + CLOCK_SYNC_SECS * 1000 + 200));
+
+        // I timestamp firmati seguono l'ora del server
+        let mut client = ClobApiClient::new(PolymarketApiConfig::default(), Some(test_credentials())).unwrap();
+        client.clock = clock;
+        let headers = client.l2_headers(&Method::GET, "/data/orders", "").unwrap();
+        let signed: i64 = headers["POLY_TIMESTAMP"].to_str().unwrap().parse().unwrap();
+        assert!((signed - (chrono::Utc::now().timestamp() + 90)).abs() <= 1);
+    }
+
+    #[test]
+    #[cfg(feature = "onchain")]
+    fn test_l1_headers_and_derived_credentials() {
+        let wallet = Wallet::from_private_key("0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80").unwrap();
+        let client = ClobApiClient::new(PolymarketApiConfig::default(), None).unwrap().with_wallet(wallet);
+
+        let headers = client.l1_headers(0).unwrap();
+        assert_eq!(headers["POLY_ADDRESS"], "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266");
+        assert_eq!(headers["POLY_NONCE"], "0");
+        assert_eq!(headers["POLY_SIGNATURE"].len(), 2 + 65 * 2);
+        assert!(ClobApiClient::new(PolymarketApiConfig::default(), None).unwrap().l1_headers(0).is_err());
+
+        let json = serde_json::json!({ "apiKey": "k", "secret": "s", "passphrase": "p" });
+        let credentials = ApiCredentials::from_json(&json).unwrap();
+        assert_eq!((credentials.api_key.as_str(), credentials.passphrase.as_str()), ("k", "p"));
+        assert!(ApiCredentials::from_json(&serde_json::json!({ "apiKey": "k" })).is_err());
+    }
 }
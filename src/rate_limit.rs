@@ -0,0 +1,314 @@
+//! HTTP rate limiting module
+//!
+//! Implements:
+//! 1. Token buckets with per-endpoint limits (matched by API and longest path prefix)
+//! 2. A limiter shared by the Gamma and CLOB clients
+//! 3. Automatic retry on 429 honouring the Retry-After header (the last 429 is returned to the caller)
+//! 4. Optional capture of every request sent through the limiter
+
+use crate::capture::ApiCapture;
+use crate::polymarket_api::{ApiResult, PolymarketApiError};
+use fxhash::FxHashMap;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{RequestBuilder, Response, StatusCode};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Gamma API identifier for endpoint limits
+pub const GAMMA_API: &str = "gamma";
+/// CLOB API identifier for endpoint limits
+pub const CLOB_API: &str = "clob";
+
+/// Limit applied to every path under `path_prefix` of one API
+#[derive(Debug, Clone)]
+pub struct EndpointLimit {
+    pub api: String,
+    pub path_prefix: String,
+    pub burst: f64,      // Richieste consentite in raffica
+    pub per_second: f64, // Ricarica del bucket
+}
+
+impl EndpointLimit {
+    pub fn new(api: &str, path_prefix: &str, burst: f64, per_second: f64) -> Self {
+        Self {
+            api: api.to_string(),
+            path_prefix: path_prefix.to_string(),
+            burst,
+            per_second,
+        }
+    }
+}
+
+/// Rate limiter configuration
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    pub limits: Vec<EndpointLimit>,
+    pub max_retries: u32,            // Tentativi dopo un 429 prima di restituire errore
+    pub default_retry_after_ms: u64, // Attesa se il 429 non indica Retry-After
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        // Tenuti sotto i limiti pubblicati da Polymarket (finestre da 10 secondi)
+        Self {
+            limits: vec![
+                EndpointLimit::new(GAMMA_API, "/", 20.0, 10.0),
+                EndpointLimit::new(GAMMA_API, "/markets", 10.0, 5.0),
+                EndpointLimit::new(GAMMA_API, "/events", 10.0, 5.0),
+                EndpointLimit::new(CLOB_API, "/", 50.0, 25.0),
+                EndpointLimit::new(CLOB_API, "/order", 20.0, 10.0),
+                EndpointLimit::new(CLOB_API, "/auth", 5.0, 1.0),
+            ],
+            max_retries: 3,
+            default_retry_after_ms: 1000,
+        }
+    }
+}
+
+/// Token bucket for one endpoint
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    capacity: f64,
+    per_second: f64,
+    tokens: f64,
+    last_refill: Instant,
+    blocked_until: Option<Instant>, // Impostato da un 429 del server
+}
+
+impl TokenBucket {
+    pub fn new(capacity: f64, per_second: f64, now: Instant) -> Self {
+        Self {
+            capacity,
+            per_second,
+            tokens: capacity,
+            last_refill: now,
+            blocked_until: None,
+        }
+    }
+
+    /// Take one token, or return how long to wait before one is available
+    pub fn try_acquire(&mut self, now: Instant) -> Result<(), Duration> {
+        if let Some(until) = self.blocked_until {
+            if now < until {
+                return Err(until - now);
+            }
+            self.blocked_until = None;
+        }
+
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.per_second).min(self.capacity);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return Ok(());
+        }
+
+        if self.per_second <= 0.0 {
+            return Err(Duration::from_secs(1));
+        }
+        Err(Duration::from_secs_f64((1.0 - self.tokens) / self.per_second))
+    }
+
+    /// Block the bucket (server asked us to back off) and drain its tokens
+    pub fn block_for(&mut self, wait: Duration, now: Instant) {
+        let until = now + wait;
+        self.blocked_until = Some(self.blocked_until.map_or(until, |current| current.max(until)));
+        self.tokens = 0.0;
+        self.last_refill = until;
+    }
+}
+
+/// Token-bucket rate limiter shared by the HTTP clients
+#[derive(Clone)]
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    buckets: Arc<Mutex<FxHashMap<String, TokenBucket>>>,
+    capture: Option<ApiCapture>, // Cattura campionata di richieste e risposte
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(RateLimitConfig::default())
+    }
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            buckets: Arc::new(Mutex::new(FxHashMap::default())),
+            capture: None,
+        }
+    }
+
+    /// Capture requests sent through this limiter (and its clones)
+    pub fn with_capture(mut self, capture: ApiCapture) -> Self {
+        self.capture = Some(capture);
+        self
+    }
+
+    pub fn capture(&self) -> Option<&ApiCapture> {
+        self.capture.as_ref()
+    }
+
+    /// Most specific limit configured for a request path
+    fn limit_for(&self, api: &str, path: &str) -> Option<&EndpointLimit> {
+        self.config.limits
+            .iter()
+            .filter(|l| l.api == api && path.starts_with(&l.path_prefix))
+            .max_by_key(|l| l.path_prefix.len())
+    }
+
+    fn with_bucket<T>(&self, api: &str, path: &str, f: impl FnOnce(&mut TokenBucket, Instant) -> T) -> Option<T> {
+        let limit = self.limit_for(api, path)?;
+        let key = format!("{}:{}", limit.api, limit.path_prefix);
+        let now = Instant::now();
+
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets
+            .entry(key)
+            .or_insert_with(|| TokenBucket::new(limit.burst, limit.per_second, now));
+        Some(f(bucket, now))
+    }
+
+    /// Wait until a request to `path` is allowed (no-op for unlimited endpoints)
+    pub async fn acquire(&self, api: &str, path: &str) {
+        loop {
+            match self.with_bucket(api, path, |bucket, now| bucket.try_acquire(now)) {
+                Some(Err(wait)) => tokio::time::sleep(wait).await,
+                _ => return,
+            }
+        }
+    }
+
+    /// Back off an endpoint after the server rate-limited us
+    pub fn penalize(&self, api: &str, path: &str, wait: Duration) {
+        self.with_bucket(api, path, |bucket, now| bucket.block_for(wait, now));
+    }
+
+    /// Send a request through the limiter, retrying on 429 after the server's Retry-After
+    pub async fn send(&self, api: &str, path: &str, request: RequestBuilder) -> ApiResult<Response> {
+        let mut pending = request;
+        let mut attempt = 0;
+
+        loop {
+            self.acquire(api, path).await;
+
+            // Copia per un eventuale nuovo tentativo (None se il body non è clonabile)
+            let retry = if attempt < self.config.max_retries { pending.try_clone() } else { None };
+            let response = match &self.capture {
+                Some(capture) => send_captured(capture, api, pending).await?,
+                None => pending.send().await?,
+            };
+            if response.status() != StatusCode::TOO_MANY_REQUESTS {
+                return Ok(response);
+            }
+
+            let Some(next) = retry else { return Ok(response) };
+            let wait = parse_retry_after(response.headers())
+                .unwrap_or(Duration::from_millis(self.config.default_retry_after_ms));
+            eprintln!("⏳ Rate limited on {} {}: retrying in {:?}", api, path, wait);
+            self.penalize(api, path, wait);
+
+            pending = next;
+            attempt += 1;
+        }
+    }
+}
+
+/// Send a request recording it in the capture; a body read for the capture is handed back in a rebuilt response
+async fn send_captured(capture: &ApiCapture, api: &str, builder: RequestBuilder) -> ApiResult<Response> {
+    let (client, request) = builder.build_split();
+    let request = request?;
+    let Some(pending) = capture.begin(api, &request) else {
+        return Ok(client.execute(request).await?);
+    };
+
+    let response = match client.execute(request).await {
+        Ok(response) => response,
+        Err(e) => {
+            capture.finish(pending, None, None, Some(e.to_string()));
+            return Err(e.into());
+        }
+    };
+    let status = response.status();
+    if !pending.wants_response(status.as_u16()) {
+        capture.finish(pending, Some(status.as_u16()), None, None);
+        return Ok(response);
+    }
+
+    let mut rebuilt = http::Response::builder().status(status).version(response.version());
+    if let Some(headers) = rebuilt.headers_mut() {
+        *headers = response.headers().clone();
+    }
+    let body = response.bytes().await?;
+    capture.finish(pending, Some(status.as_u16()), Some(&body), None);
+    let rebuilt = rebuilt
+        .body(body)
+        .map_err(|e| PolymarketApiError::Network(format!("Failed to rebuild captured response: {}", e)))?;
+    Ok(Response::from(rebuilt))
+}
+
+/// Retry-After as delta-seconds or HTTP date
+pub fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<f64>() {
+        // NaN, infinito o fuori dal range di Duration: header ignorato invece di un panic
+        return (seconds.is_finite() && seconds >= 0.0).then(|| Duration::try_from_secs_f64(seconds).ok()).flatten();
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let wait = date.with_timezone(&chrono::Utc) - chrono::Utc::now();
+    Some(wait.to_std().unwrap_or(Duration::ZERO))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_token_bucket_refill() {
+        let t0 = Instant::now();
+        let mut bucket = TokenBucket::new(2.0, 4.0, t0);
+
+        assert!(bucket.try_acquire(t0).is_ok());
+        assert!(bucket.try_acquire(t0).is_ok());
+        let wait = bucket.try_acquire(t0).unwrap_err();
+        assert_eq!(wait, Duration::from_millis(250));
+
+        assert!(bucket.try_acquire(t0 + Duration::from_millis(250)).is_ok());
+
+        // Dopo un 429 il bucket resta bloccato fino allo scadere del Retry-After
+        bucket.block_for(Duration::from_secs(2), t0 + Duration::from_secs(1));
+        assert!(bucket.try_acquire(t0 + Duration::from_secs(2)).is_err());
+        assert!(bucket.try_acquire(t0 + Duration::from_millis(3300)).is_ok());
+    }
+
+    #[test]
+    fn test_most_specific_limit_wins() {
+        let limiter = RateLimiter::default();
+        assert_eq!(limiter.limit_for(GAMMA_API, "/markets").unwrap().path_prefix, "/markets");
+        assert_eq!(limiter.limit_for(GAMMA_API, "/tags").unwrap().path_prefix, "/");
+        assert_eq!(limiter.limit_for(CLOB_API, "/auth/api-keys").unwrap().path_prefix, "/auth");
+        assert!(limiter.limit_for("other", "/markets").is_none());
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        let mut headers = HeaderMap::new();
+        assert!(parse_retry_after(&headers).is_none());
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("3"));
+        assert_eq!(parse_retry_after(&headers), Some(Duration::from_secs(3)));
+
+        // Valori non finiti o fuori range ignorati senza panic
+        for value in ["NaN", "inf", "1e300", "-1"] {
+            headers.insert(RETRY_AFTER, HeaderValue::from_static(value));
+            assert!(parse_retry_after(&headers).is_none(), "{}", value);
+        }
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"));
+        assert_eq!(parse_retry_after(&headers), Some(Duration::ZERO));
+    }
+}
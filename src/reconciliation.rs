@@ -0,0 +1,434 @@
+//! Fill reconciliation module
+//!
+//! Implements:
+//! 1. Matching of executed trade legs with the user's exchange fills
+//! 2. Size and VWAP price comparison within configurable tolerances
+//! 3. Divergence report listing mismatched legs and unexpected exchange fills
+//! 4. Live state reconciliation: open orders, positions and balance against the exchange
+//! 5. Alarms on new mismatches, with optional correction towards the exchange state
+
+use crate::polymarket_api::UserTrade;
+use crate::types::{Direction, TradeExecution};
+use chrono::{DateTime, Duration, Utc};
+use fxhash::FxHashSet;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+
+/// Alarms kept in memory
+pub const RECONCILIATION_ALARM_HISTORY: usize = 100;
+
+/// Tolerances for matching simulated legs with real fills
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ReconcileTolerance {
+    pub price: f64,         // Differenza assoluta di prezzo accettata
+    pub size_pct: f64,      // Differenza relativa di size accettata
+    pub window_secs: i64,   // Margine attorno a entry/exit del trade
+}
+
+impl Default for ReconcileTolerance {
+    fn default() -> Self {
+        Self {
+            price: 0.005,
+            size_pct: 0.01,
+            window_secs: 60,
+        }
+    }
+}
+
+/// Outcome of one leg
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LegStatus {
+    Matched,
+    PriceDiverged,
+    SizeDiverged,
+    Missing, // Nessun fill reale per il leg
+    Untracked, // Leg senza token CLOB, non confrontabile
+}
+
+/// Simulated leg against its real fills
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LegReconciliation {
+    pub trade_id: String,
+    pub market_id: String,
+    pub token_id: Option<String>,
+    pub direction: Direction,
+    pub expected_size: f64,
+    pub expected_price: f64,
+    pub filled_size: f64,
+    pub filled_price: Option<f64>, // VWAP dei fill
+    pub fill_ids: Vec<String>,
+    pub status: LegStatus,
+}
+
+/// Result of reconciling executed trades with exchange fills
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DivergenceReport {
+    pub generated_at: DateTime<Utc>,
+    pub legs: Vec<LegReconciliation>,
+    pub unmatched_fills: Vec<UserTrade>, // Fill reali senza trade corrispondente
+}
+
+impl DivergenceReport {
+    /// Legs whose real fills disagree with the simulation
+    pub fn divergences(&self) -> impl Iterator<Item = &LegReconciliation> {
+        self.legs.iter().filter(|l| !matches!(l.status, LegStatus::Matched | LegStatus::Untracked))
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.divergences().next().is_none() && self.unmatched_fills.is_empty()
+    }
+}
+
+/// Match each tracked leg with the fills of its token and side inside the trade window
+///
+/// Each fill is attributed to at most one leg; failed fills are ignored.
+pub fn reconcile_fills(executed: &[TradeExecution], fills: &[UserTrade], tolerance: ReconcileTolerance) -> DivergenceReport {
+    let mut used = vec![false; fills.len()];
+    let window = Duration::seconds(tolerance.window_secs);
+    let mut legs = Vec::new();
+
+    for trade in executed {
+        for leg in &trade.legs {
+            let mut reconciliation = LegReconciliation {
+                trade_id: trade.trade_id.clone(),
+                market_id: leg.market_id.clone(),
+                token_id: leg.token_id.clone(),
+                direction: leg.direction,
+                expected_size: leg.quantity,
+                expected_price: leg.price,
+                filled_size: 0.0,
+                filled_price: None,
+                fill_ids: Vec::new(),
+                status: LegStatus::Untracked,
+            };
+            let Some(token_id) = &leg.token_id else {
+                legs.push(reconciliation);
+                continue;
+            };
+
+            // Fill dello stesso token e lato finché la size attesa non è coperta
+            let mut notional = 0.0;
+            for (i, fill) in fills.iter().enumerate() {
+                if used[i] || fill.is_failed() || &fill.asset_id != token_id || fill.is_buy() != (leg.direction == Direction::Buy) {
+                    continue;
+                }
+                if fill.match_time < trade.entry_time - window || fill.match_time > trade.exit_time + window {
+                    continue;
+                }
+                if reconciliation.filled_size >= leg.quantity * (1.0 - tolerance.size_pct) {
+                    break;
+                }
+                used[i] = true;
+                reconciliation.filled_size += fill.size;
+                notional += fill.size * fill.price;
+                reconciliation.fill_ids.push(fill.id.clone());
+            }
+
+            reconciliation.status = if reconciliation.filled_size <= 0.0 {
+                LegStatus::Missing
+            } else {
+                let vwap = notional / reconciliation.filled_size;
+                reconciliation.filled_price = Some(vwap);
+                let size_diff = (reconciliation.filled_size - leg.quantity).abs() / leg.quantity.max(f64::EPSILON);
+                if size_diff > tolerance.size_pct {
+                    LegStatus::SizeDiverged
+                } else if (vwap - leg.price).abs() > tolerance.price {
+                    LegStatus::PriceDiverged
+                } else {
+                    LegStatus::Matched
+                }
+            };
+            legs.push(reconciliation);
+        }
+    }
+
+    let unmatched_fills = fills
+        .iter()
+        .zip(&used)
+        .filter(|(fill, used)| !**used && !fill.is_failed())
+        .map(|(fill, _)| fill.clone())
+        .collect();
+
+    DivergenceReport {
+        generated_at: Utc::now(),
+        legs,
+        unmatched_fills,
+    }
+}
+
+fn default_interval_secs() -> i64 { 60 }
+fn default_balance_tolerance() -> f64 { 1.0 }
+fn default_position_tolerance() -> f64 { 0.01 }
+
+/// Live reconciliation settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LiveReconcileConfig {
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: i64,
+    #[serde(default)]
+    pub auto_correct: bool, // Allinea lo stato interno a quello dell'exchange dopo l'allarme
+    #[serde(default = "default_balance_tolerance")]
+    pub balance_tolerance: f64, // USDC
+    #[serde(default = "default_position_tolerance")]
+    pub position_tolerance: f64, // Share, vale anche per la size residua degli ordini
+}
+
+impl Default for LiveReconcileConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: default_interval_secs(),
+            auto_correct: false,
+            balance_tolerance: default_balance_tolerance(),
+            position_tolerance: default_position_tolerance(),
+        }
+    }
+}
+
+/// Open orders, positions and balance as seen by one side
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AccountSnapshot {
+    pub open_orders: BTreeMap<String, f64>, // order id -> size residua
+    pub positions: BTreeMap<String, f64>,   // token id -> share
+    pub balance: f64,                       // USDC
+}
+
+/// Kind of disagreement between internal and exchange state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MismatchKind {
+    OrderNotOnExchange, // Ordine aperto per noi, assente sull'exchange
+    UnknownOrder,       // Ordine aperto sull'exchange che non stiamo tracciando
+    OrderSize,
+    Position,
+    Balance,
+}
+
+/// One mismatch; `key` is the order id, the token id, or "balance"
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StateMismatch {
+    pub kind: MismatchKind,
+    pub key: String,
+    pub internal: f64,
+    pub exchange: f64,
+}
+
+/// Mismatches between internal and exchange state beyond the configured tolerances
+pub fn compare_state(internal: &AccountSnapshot, exchange: &AccountSnapshot, config: &LiveReconcileConfig) -> Vec<StateMismatch> {
+    let mut mismatches = Vec::new();
+    let mismatch = |kind, key: &str, internal: f64, exchange: f64| StateMismatch { kind, key: key.to_string(), internal, exchange };
+
+    for (id, &size) in &internal.open_orders {
+        match exchange.open_orders.get(id) {
+            None => mismatches.push(mismatch(MismatchKind::OrderNotOnExchange, id, size, 0.0)),
+            Some(&remote) if (remote - size).abs() > config.position_tolerance => {
+                mismatches.push(mismatch(MismatchKind::OrderSize, id, size, remote))
+            }
+            Some(_) => {}
+        }
+    }
+    for (id, &remote) in &exchange.open_orders {
+        if !internal.open_orders.contains_key(id) {
+            mismatches.push(mismatch(MismatchKind::UnknownOrder, id, 0.0, remote));
+        }
+    }
+
+    let tokens: FxHashSet<&String> = internal.positions.keys().chain(exchange.positions.keys()).collect();
+    let mut tokens: Vec<&String> = tokens.into_iter().collect();
+    tokens.sort();
+    for token in tokens {
+        let ours = internal.positions.get(token).copied().unwrap_or(0.0);
+        let theirs = exchange.positions.get(token).copied().unwrap_or(0.0);
+        if (ours - theirs).abs() > config.position_tolerance {
+            mismatches.push(mismatch(MismatchKind::Position, token, ours, theirs));
+        }
+    }
+
+    if (internal.balance - exchange.balance).abs() > config.balance_tolerance {
+        mismatches.push(mismatch(MismatchKind::Balance, "balance", internal.balance, exchange.balance));
+    }
+    mismatches
+}
+
+/// Alarm raised on mismatches not present in the previous check
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReconciliationAlarm {
+    pub mismatch: StateMismatch,
+    pub message: String,
+    pub raised_at: DateTime<Utc>,
+    pub corrected: bool, // Stato interno allineato all'exchange
+}
+
+/// Periodic comparison of internal and exchange state
+#[derive(Debug, Clone, Default)]
+pub struct LiveReconciler {
+    pub config: LiveReconcileConfig,
+    pub alarms: VecDeque<ReconciliationAlarm>,
+    pub position_adjustments: BTreeMap<String, f64>, // Correzioni per token sommate alle posizioni interne
+    active: FxHashSet<(MismatchKind, String)>,
+    last_run: Option<DateTime<Utc>>,
+}
+
+impl LiveReconciler {
+    pub fn new(config: LiveReconcileConfig) -> Self {
+        Self { config, ..Self::default() }
+    }
+
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        self.last_run.is_none_or(|last| now - last >= Duration::seconds(self.config.interval_secs))
+    }
+
+    /// Internal positions with the corrections applied so far
+    pub fn adjusted_positions(&self, mut positions: BTreeMap<String, f64>) -> BTreeMap<String, f64> {
+        for (token, adjustment) in &self.position_adjustments {
+            *positions.entry(token.clone()).or_insert(0.0) += adjustment;
+        }
+        positions.retain(|_, shares| shares.abs() > 1e-9);
+        positions
+    }
+
+    /// Compare both sides; returns the alarms for new mismatches (a resolved mismatch can alarm again)
+    ///
+    /// With `auto_correct`, position mismatches are absorbed into `position_adjustments`; the caller corrects orders and balance.
+    pub fn check(&mut self, internal: &AccountSnapshot, exchange: &AccountSnapshot, now: DateTime<Utc>) -> Vec<ReconciliationAlarm> {
+        self.last_run = Some(now);
+        let mismatches = compare_state(internal, exchange, &self.config);
+        let current: FxHashSet<(MismatchKind, String)> = mismatches.iter().map(|m| (m.kind, m.key.clone())).collect();
+
+        let raised: Vec<ReconciliationAlarm> = mismatches
+            .into_iter()
+            .filter(|m| !self.active.contains(&(m.kind, m.key.clone())))
+            .map(|mismatch| ReconciliationAlarm {
+                message: format!("{:?} mismatch on {}: internal {:.4}, exchange {:.4}", mismatch.kind, mismatch.key, mismatch.internal, mismatch.exchange),
+                mismatch,
+                raised_at: now,
+                corrected: self.config.auto_correct,
+            })
+            .collect();
+
+        if self.config.auto_correct {
+            // Corretto ora: il mismatch non resta attivo e una ricomparsa darà un nuovo allarme
+            for alarm in &raised {
+                if alarm.mismatch.kind == MismatchKind::Position {
+                    *self.position_adjustments.entry(alarm.mismatch.key.clone()).or_insert(0.0) += alarm.mismatch.exchange - alarm.mismatch.internal;
+                }
+            }
+            self.active.clear();
+        } else {
+            self.active = current;
+        }
+
+        for alarm in &raised {
+            self.alarms.push_back(alarm.clone());
+            if self.alarms.len() > RECONCILIATION_ALARM_HISTORY {
+                self.alarms.pop_front();
+            }
+        }
+        raised
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ArbType, ArbitrageLeg, TokenType};
+
+    fn leg(token: &str, price: f64, quantity: f64) -> ArbitrageLeg {
+        ArbitrageLeg {
+            market_id: "m1".to_string(),
+            token_type: TokenType::Yes,
+            direction: Direction::Buy,
+            price,
+            quantity,
+            token_id: Some(token.to_string()),
+            venue: None,
+        }
+    }
+
+    fn fill(id: &str, token: &str, price: f64, size: f64, at: DateTime<Utc>) -> UserTrade {
+        UserTrade {
+            id: id.to_string(),
+            taker_order_id: String::new(),
+            market: "m1".to_string(),
+            asset_id: token.to_string(),
+            side: "BUY".to_string(),
+            size,
+            price,
+            status: "CONFIRMED".to_string(),
+            match_time: at,
+            fee_rate_bps: None,
+        }
+    }
+
+    #[test]
+    fn test_reconcile_reports_divergent_legs() {
+        let now = Utc::now();
+        let trade = TradeExecution {
+            trade_id: "trade_1".to_string(),
+            market_id: "m1".to_string(),
+            arb_type: ArbType::YesNoSimple,
+            legs: vec![leg("yes", 0.48, 20.0), leg("no", 0.50, 20.0), leg("other", 0.30, 10.0)],
+            total_investment: 19.6,
+            expected_return: 20.0,
+            actual_return: 20.0,
+            profit: 0.4,
+            roi_pct: 2.0,
+            entry_time: now,
+            exit_time: now,
+            execution_time_ms: 1,
+            slippage_pct: 0.0,
+            gas_cost: 0.0,
+            fees: 0.0,
+        };
+        let fills = vec![
+            fill("f1", "yes", 0.47, 12.0, now),
+            fill("f2", "yes", 0.49, 8.0, now),
+            fill("f3", "no", 0.55, 20.0, now),
+            fill("f4", "yes", 0.48, 5.0, now + Duration::hours(1)),
+        ];
+
+        let report = reconcile_fills(&[trade], &fills, ReconcileTolerance::default());
+        assert_eq!(report.legs[0].status, LegStatus::Matched);
+        assert_eq!(report.legs[0].fill_ids, vec!["f1", "f2"]);
+        assert_eq!(report.legs[1].status, LegStatus::PriceDiverged);
+        assert_eq!(report.legs[2].status, LegStatus::Missing);
+        assert_eq!(report.unmatched_fills.len(), 1);
+        assert_eq!(report.divergences().count(), 2);
+        assert!(!report.is_clean());
+    }
+
+    #[test]
+    fn test_live_reconciliation_alarms() {
+        let now = Utc::now();
+        let snapshot = |orders: &[(&str, f64)], positions: &[(&str, f64)], balance: f64| AccountSnapshot {
+            open_orders: orders.iter().map(|(id, size)| (id.to_string(), *size)).collect(),
+            positions: positions.iter().map(|(id, shares)| (id.to_string(), *shares)).collect(),
+            balance,
+        };
+        let internal = snapshot(&[("o1", 10.0), ("o2", 5.0)], &[("yes", 20.0)], 500.0);
+        let exchange = snapshot(&[("o1", 4.0), ("o3", 7.0)], &[("yes", 20.005), ("no", 3.0)], 499.5);
+
+        let mut reconciler = LiveReconciler::new(LiveReconcileConfig::default());
+        assert!(reconciler.is_due(now));
+        let alarms = reconciler.check(&internal, &exchange, now);
+        let kinds: Vec<(MismatchKind, &str)> = alarms.iter().map(|a| (a.mismatch.kind, a.mismatch.key.as_str())).collect();
+        assert_eq!(kinds, vec![
+            (MismatchKind::OrderSize, "o1"),
+            (MismatchKind::OrderNotOnExchange, "o2"),
+            (MismatchKind::UnknownOrder, "o3"),
+            (MismatchKind::Position, "no"),
+        ]);
+        assert!(!reconciler.is_due(now + Duration::seconds(30)));
+
+        // Mismatch ancora aperti: nessun nuovo allarme
+        assert!(reconciler.check(&internal, &exchange, now + Duration::seconds(60)).is_empty());
+        assert_eq!(reconciler.alarms.len(), 4);
+
+        // Con la correzione automatica la posizione viene riallineata all'exchange
+        let mut reconciler = LiveReconciler::new(LiveReconcileConfig { auto_correct: true, ..LiveReconcileConfig::default() });
+        let alarms = reconciler.check(&internal, &exchange, now);
+        assert!(alarms.iter().all(|a| a.corrected));
+        let corrected = AccountSnapshot { positions: reconciler.adjusted_positions(internal.positions.clone()), ..exchange.clone() };
+        assert!(compare_state(&corrected, &exchange, &reconciler.config).is_empty());
+    }
+}
@@ -0,0 +1,194 @@
+//! REST refresh scheduler module
+//!
+//! Implements:
+//! 1. Per-market REST refresh interval by priority: pinned markets most often, markets without
+//!    WebSocket coverage next, covered markets only as a backstop when their feed goes quiet
+//! 2. Refreshes skipped while the WebSocket event queue is backed up, so REST polling never
+//!    competes with a feed that is already behind
+//! 3. Per-cycle cap on the markets refreshed, most overdue first
+//! 4. Staleness metrics: age of each market's data against its interval, refreshes and skipped cycles
+
+use chrono::{DateTime, Duration, Utc};
+use fxhash::FxHashMap;
+use serde::{Deserialize, Serialize};
+
+/// Refresh intervals and backpressure limit
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RefreshScheduleConfig {
+    pub pinned_interval_ms: u64,    // Mercati in watchlist
+    pub uncovered_interval_ms: u64, // Mercati senza sottoscrizione WebSocket: il REST è l'unica fonte
+    pub covered_interval_ms: u64,   // Mercati coperti dal WebSocket: rete di sicurezza se il feed tace
+    pub max_markets_per_cycle: usize,
+    pub max_event_backlog: usize, // Eventi WebSocket in coda oltre i quali il ciclo REST salta
+}
+
+impl Default for RefreshScheduleConfig {
+    fn default() -> Self {
+        Self {
+            pinned_interval_ms: 1_000,
+            uncovered_interval_ms: 5_000,
+            covered_interval_ms: 60_000,
+            max_markets_per_cycle: 200,
+            max_event_backlog: 1_000,
+        }
+    }
+}
+
+/// How a market's prices reach the bot, from most to least urgent to poll
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RefreshPriority {
+    Pinned,
+    Uncovered,
+    Covered,
+}
+
+/// A market the scheduler may refresh, with the time its data last changed from any source
+#[derive(Debug, Clone, PartialEq)]
+pub struct RefreshCandidate {
+    pub market_id: String,
+    pub priority: RefreshPriority,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Staleness of the scheduled markets
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct RefreshMetrics {
+    pub markets: usize,
+    pub overdue: usize, // Dati più vecchi del proprio intervallo
+    pub mean_staleness_ms: f64,
+    pub max_staleness_ms: f64,
+    pub refreshed: u64,             // Refresh REST richiesti, cumulativi
+    pub cycles: u64,
+    pub skipped_backpressure: u64,  // Cicli saltati per coda eventi piena
+    pub last_backlog: usize,
+}
+
+/// Decides which markets are refreshed over REST in each cycle
+#[derive(Debug, Clone, Default)]
+pub struct RefreshScheduler {
+    pub config: RefreshScheduleConfig,
+    last_request: FxHashMap<String, DateTime<Utc>>, // Ultima richiesta REST, anche se senza prezzo
+    refreshed: u64,
+    cycles: u64,
+    skipped_backpressure: u64,
+    last_backlog: usize,
+}
+
+impl RefreshScheduler {
+    pub fn new(config: RefreshScheduleConfig) -> Self {
+        Self { config, ..Self::default() }
+    }
+
+    pub fn interval(&self, priority: RefreshPriority) -> Duration {
+        let ms = match priority {
+            RefreshPriority::Pinned => self.config.pinned_interval_ms,
+            RefreshPriority::Uncovered => self.config.uncovered_interval_ms,
+            RefreshPriority::Covered => self.config.covered_interval_ms,
+        };
+        Duration::milliseconds(ms as i64)
+    }
+
+    /// Age of a market's data, counting a REST request as fresh even when it returned nothing
+    fn staleness(&self, candidate: &RefreshCandidate, now: DateTime<Utc>) -> Duration {
+        let requested = self.last_request.get(&candidate.market_id).copied();
+        let fresh = requested.map_or(candidate.updated_at, |r| r.max(candidate.updated_at));
+        now - fresh
+    }
+
+    /// Markets due for a REST refresh, most overdue first, up to `max_markets_per_cycle`
+    ///
+    /// Returns nothing, and counts a skipped cycle, when `backlog` WebSocket events are still queued
+    /// above `max_event_backlog`. Call `record_refresh` once the refresh is sent.
+    pub fn due(&mut self, candidates: &[RefreshCandidate], backlog: usize, now: DateTime<Utc>) -> Vec<String> {
+        self.cycles += 1;
+        self.last_backlog = backlog;
+        if backlog > self.config.max_event_backlog {
+            self.skipped_backpressure += 1;
+            return Vec::new();
+        }
+
+        // Ritardo rispetto all'intervallo, normalizzato: i pinned in ritardo passano davanti ai coperti
+        let mut overdue: Vec<(f64, &RefreshCandidate)> = candidates.iter()
+            .filter_map(|c| {
+                let interval = self.interval(c.priority).num_milliseconds().max(1) as f64;
+                let age = self.staleness(c, now).num_milliseconds() as f64;
+                (age >= interval).then_some((age / interval, c))
+            })
+            .collect();
+        overdue.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.priority.cmp(&b.1.priority)));
+        overdue.into_iter()
+            .take(self.config.max_markets_per_cycle)
+            .map(|(_, c)| c.market_id.clone())
+            .collect()
+    }
+
+    /// Record the REST refresh of `market_ids` sent at `now`
+    pub fn record_refresh(&mut self, market_ids: &[String], now: DateTime<Utc>) {
+        for market_id in market_ids {
+            self.last_request.insert(market_id.clone(), now);
+        }
+        self.refreshed += market_ids.len() as u64;
+    }
+
+    /// Forget markets no longer cached
+    pub fn retain(&mut self, candidates: &[RefreshCandidate]) {
+        self.last_request.retain(|id, _| candidates.iter().any(|c| &c.market_id == id));
+    }
+
+    pub fn metrics(&self, candidates: &[RefreshCandidate], now: DateTime<Utc>) -> RefreshMetrics {
+        let ages: Vec<(f64, bool)> = candidates.iter()
+            .map(|c| {
+                let age = self.staleness(c, now);
+                (age.num_milliseconds().max(0) as f64, age >= self.interval(c.priority))
+            })
+            .collect();
+        RefreshMetrics {
+            markets: ages.len(),
+            overdue: ages.iter().filter(|(_, overdue)| *overdue).count(),
+            mean_staleness_ms: if ages.is_empty() { 0.0 } else { ages.iter().map(|(a, _)| a).sum::<f64>() / ages.len() as f64 },
+            max_staleness_ms: ages.iter().map(|(a, _)| *a).fold(0.0, f64::max),
+            refreshed: self.refreshed,
+            cycles: self.cycles,
+            skipped_backpressure: self.skipped_backpressure,
+            last_backlog: self.last_backlog,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refreshes_follow_coverage_and_back_off_under_backlog() {
+        let now = DateTime::from_timestamp(1_790_000_000, 0).unwrap();
+        let candidate = |id: &str, priority: RefreshPriority, age_ms: i64| RefreshCandidate {
+            market_id: id.to_string(),
+            priority,
+            updated_at: now - Duration::milliseconds(age_ms),
+        };
+        // Stessa età di 10 s: dovuti il pinned e lo scoperto, non il mercato coperto dal WebSocket
+        let candidates = vec![
+            candidate("covered", RefreshPriority::Covered, 10_000),
+            candidate("uncovered", RefreshPriority::Uncovered, 10_000),
+            candidate("pinned", RefreshPriority::Pinned, 10_000),
+            candidate("quiet_feed", RefreshPriority::Covered, 120_000),
+        ];
+        let mut scheduler = RefreshScheduler::new(RefreshScheduleConfig { max_markets_per_cycle: 2, ..RefreshScheduleConfig::default() });
+        assert_eq!(scheduler.due(&candidates, 0, now), vec!["pinned", "uncovered"]);
+
+        let metrics = scheduler.metrics(&candidates, now);
+        assert_eq!((metrics.markets, metrics.overdue), (4, 3));
+        assert_eq!(metrics.max_staleness_ms, 120_000.0);
+
+        scheduler.record_refresh(&["pinned".to_string(), "uncovered".to_string()], now);
+        assert_eq!(scheduler.due(&candidates, 0, now), vec!["quiet_feed"]);
+
+        // Coda eventi arretrata: nessun refresh finché il feed non si riallinea
+        assert!(scheduler.due(&candidates, 5_000, now + Duration::seconds(30)).is_empty());
+        let metrics = scheduler.metrics(&candidates, now);
+        assert_eq!((metrics.cycles, metrics.skipped_backpressure, metrics.refreshed), (3, 1, 2));
+    }
+}
@@ -0,0 +1,209 @@
+//! Logical relations module
+//!
+//! Implements:
+//! 1. User-defined relations between markets: A implies B, A and B mutually exclusive
+//! 2. The probability bound each relation implies: P(A) <= P(B), or P(A) + P(B) <= 1
+//! 3. The basket that pays at least 1 in every outcome the relation allows, bought by the detector
+//!    when its price breaks the bound
+//! 4. JSON persistence of the relations
+//! 5. Three-market triangles where one market is the conjunction of the other two, bounded from
+//!    below by P(a) + P(b) - 1 and from above by min(P(a), P(b))
+
+use crate::types::TokenType;
+use crate::versioning::{load_document, to_document, Schema};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Default location of the relation book
+pub const RELATIONS_PATH: &str = "./data/relations.json";
+
+/// Logical relation between two markets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RelationKind {
+    Implies,           // A => B: se A si risolve YES anche B si risolve YES
+    MutuallyExclusive, // A e B non possono risolversi entrambi YES
+}
+
+impl RelationKind {
+    /// Outcomes bought on A and B: the pair pays at least 1 in every outcome the relation allows
+    ///
+    /// A => B excludes (YES, NO), so NO on A + YES on B always pays; exclusion rules out
+    /// (YES, YES), so NO on both always pays.
+    pub fn basket(&self) -> (TokenType, TokenType) {
+        match self {
+            RelationKind::Implies => (TokenType::No, TokenType::Yes),
+            RelationKind::MutuallyExclusive => (TokenType::No, TokenType::No),
+        }
+    }
+
+    /// Operator used in opportunity ids and labels
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            RelationKind::Implies => "=>",
+            RelationKind::MutuallyExclusive => "|",
+        }
+    }
+}
+
+/// Relation between market `a` and market `b`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MarketRelation {
+    pub a: String,
+    pub b: String,
+    pub kind: RelationKind,
+    #[serde(default)]
+    pub note: Option<String>, // Motivazione della relazione, per chi la rivede
+}
+
+impl MarketRelation {
+    pub fn new(a: &str, b: &str, kind: RelationKind) -> Self {
+        Self { a: a.to_string(), b: b.to_string(), kind, note: None }
+    }
+
+    /// Identifier of the relation, also used as opportunity market id
+    pub fn id(&self) -> String {
+        format!("{}{}{}", self.a, self.kind.symbol(), self.b)
+    }
+
+    /// Same pair of markets under the same relation (exclusion is symmetric)
+    fn same_as(&self, other: &MarketRelation) -> bool {
+        self.kind == other.kind
+            && ((self.a == other.a && self.b == other.b)
+                || (self.kind == RelationKind::MutuallyExclusive && self.a == other.b && self.b == other.a))
+    }
+}
+
+/// Three correlated markets where `joint` resolves YES exactly when both `a` and `b` do
+///
+/// E.g. "X wins election" = "X wins primary" and "party wins election". P(joint) = P(a)·P(b|a),
+/// and P(b|a) can't fall below 1 - P(not b)/P(a), so the joint price must stay at or above
+/// P(a) + P(b) - 1; the detector buys YES on `joint` and NO on both `a` and `b` when it doesn't.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MarketTriangle {
+    pub joint: String,
+    pub a: String,
+    pub b: String,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+impl MarketTriangle {
+    pub fn new(joint: &str, a: &str, b: &str) -> Self {
+        Self { joint: joint.to_string(), a: a.to_string(), b: b.to_string(), note: None }
+    }
+
+    /// Identifier of the triangle, also used as opportunity market id
+    pub fn id(&self) -> String {
+        format!("{}={}&{}", self.joint, self.a, self.b)
+    }
+
+    /// Markets of the cycle: the joint market, then its two components
+    pub fn markets(&self) -> [&str; 3] {
+        [&self.joint, &self.a, &self.b]
+    }
+
+    /// Pairwise relations implied by the conjunction: the joint market implies each component
+    ///
+    /// They bound the joint price from above (P(joint) <= min(P(a), P(b))); the triangle scan
+    /// checks them alongside the lower bound.
+    pub fn implied_relations(&self) -> [MarketRelation; 2] {
+        [
+            MarketRelation::new(&self.joint, &self.a, RelationKind::Implies),
+            MarketRelation::new(&self.joint, &self.b, RelationKind::Implies),
+        ]
+    }
+}
+
+/// Schema of the relations file
+pub const RELATIONS_SCHEMA: Schema = Schema::new("relations", &[]);
+
+/// User-defined relations, persisted as JSON
+#[derive(Debug, Clone, Default)]
+pub struct RelationBook {
+    relations: Vec<MarketRelation>,
+    pub path: Option<PathBuf>,
+}
+
+impl RelationBook {
+    /// In-memory book holding `relations`
+    pub fn new(relations: Vec<MarketRelation>) -> Self {
+        let mut book = Self::default();
+        for relation in relations {
+            if let Err(e) = book.insert(relation) {
+                eprintln!("⚠️  Relation ignored: {}", e);
+            }
+        }
+        book
+    }
+
+    /// Load relations from file (empty if the file does not exist yet)
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, String> {
+        let path = path.into();
+        let mut book = Self { relations: Vec::new(), path: Some(path.clone()) };
+
+        let entries: Option<Vec<MarketRelation>> = load_document(&RELATIONS_SCHEMA, &path)
+            .map_err(|e| format!("Failed to load relations: {}", e))?;
+        for entry in entries.unwrap_or_default() {
+            book.insert(entry)?;
+        }
+
+        Ok(book)
+    }
+
+    /// Persist relations to their file (no-op for in-memory books)
+    pub fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.path else { return Ok(()) };
+
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create relations directory: {}", e))?;
+        }
+        let content = to_document(&RELATIONS_SCHEMA, &self.relations)
+            .map_err(|e| format!("Failed to serialize relations: {}", e))?;
+        std::fs::write(path, content)
+            .map_err(|e| format!("Failed to write relations: {}", e))
+    }
+
+    /// Add or replace a relation without persisting
+    fn insert(&mut self, relation: MarketRelation) -> Result<(), String> {
+        if relation.a.is_empty() || relation.b.is_empty() || relation.a == relation.b {
+            return Err(format!("Relation {} needs two distinct markets", relation.id()));
+        }
+        match self.relations.iter_mut().find(|r| r.same_as(&relation)) {
+            Some(existing) => *existing = relation,
+            None => self.relations.push(relation),
+        }
+        Ok(())
+    }
+
+    /// Add or replace a relation and persist
+    pub fn add(&mut self, relation: MarketRelation) -> Result<(), String> {
+        self.insert(relation)?;
+        self.save()
+    }
+
+    /// Remove every relation between `a` and `b` (in either order) and persist; returns whether any was removed
+    pub fn remove(&mut self, a: &str, b: &str) -> Result<bool, String> {
+        let before = self.relations.len();
+        self.relations.retain(|r| !((r.a == a && r.b == b) || (r.a == b && r.b == a)));
+        let removed = self.relations.len() < before;
+        if removed {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    /// Relations in insertion order
+    pub fn list(&self) -> &[MarketRelation] {
+        &self.relations
+    }
+
+    pub fn len(&self) -> usize {
+        self.relations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.relations.is_empty()
+    }
+}
@@ -0,0 +1,195 @@
+//! Snapshot replay module
+//!
+//! Implements:
+//! 1. Detectors run over historical `PriceSnapshot` sequences instead of live `MarketManager` state
+//! 2. Markets rebuilt at each recorded timestamp from the latest snapshot of every market, with the
+//!    price history up to that moment (volume stands in for liquidity, as in the backtest)
+//! 3. Time-stamped opportunity log for strategy research, without touching live APIs or capital;
+//!    persistent quotes are reported once per dedup cooldown, on the recorded clock
+//! 4. Price sequences loaded from the persistence layer
+
+use crate::arbitrage::{OpportunityCache, OpportunityDedupConfig};
+use crate::detector::{Detector, MarketView};
+use crate::market::{PriceSnapshot, Watchlist};
+use crate::orderbook::OrderBookStore;
+use crate::rewards::RewardBook;
+use crate::storage::{PriceQuery, Storage};
+use crate::types::{ArbitrageOpportunity, MarketData};
+use chrono::{DateTime, Duration, Utc};
+use fxhash::FxHashMap;
+use serde::{Deserialize, Serialize};
+
+/// Replay settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReplayConfig {
+    pub stale_after_ms: u64, // Mercati senza snapshot da più di così escono dalla scansione (0 = mai)
+    pub history_len: usize,  // Snapshot per mercato visibili ai detector, come la history live
+    pub dedup: OpportunityDedupConfig, // ttl_secs = 0: registra l'opportunità a ogni timestamp in cui persiste
+}
+
+impl Default for ReplayConfig {
+    fn default() -> Self {
+        Self {
+            stale_after_ms: 60_000,
+            history_len: 1000,
+            dedup: OpportunityDedupConfig::default(),
+        }
+    }
+}
+
+/// Opportunity found by a detector at a recorded timestamp
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayedOpportunity {
+    pub detected_at: DateTime<Utc>,
+    pub detector: String,
+    pub opportunity: ArbitrageOpportunity,
+}
+
+/// Runs detectors over recorded prices
+pub struct SnapshotReplayer {
+    pub config: ReplayConfig,
+    detectors: Vec<Box<dyn Detector>>,
+}
+
+impl SnapshotReplayer {
+    pub fn new(config: ReplayConfig) -> Self {
+        Self { config, detectors: Vec::new() }
+    }
+
+    pub fn with_detector(mut self, detector: Box<dyn Detector>) -> Self {
+        self.detectors.push(detector);
+        self
+    }
+
+    /// Replay price history (same layout as `MarketManager::price_history`), oldest first
+    ///
+    /// Snapshots sharing a timestamp are applied together before the scan of that timestamp.
+    pub fn run(&mut self, price_history: &FxHashMap<String, Vec<PriceSnapshot>>) -> Vec<ReplayedOpportunity> {
+        let mut timeline: Vec<(&String, &PriceSnapshot)> = price_history
+            .iter()
+            .flat_map(|(id, snaps)| snaps.iter().map(move |s| (id, s)))
+            .collect();
+        timeline.sort_by(|a, b| a.1.timestamp.cmp(&b.1.timestamp).then_with(|| a.0.cmp(b.0)));
+
+        let (watchlist, books, rewards) = (Watchlist::default(), OrderBookStore::default(), RewardBook::new());
+        let mut current: FxHashMap<String, MarketData> = FxHashMap::default();
+        let mut history: FxHashMap<String, Vec<PriceSnapshot>> = FxHashMap::default();
+        let mut dedup = OpportunityCache::new(self.config.dedup.clone());
+        let mut log = Vec::new();
+
+        for batch in timeline.chunk_by(|a, b| a.1.timestamp == b.1.timestamp) {
+            let now = batch[0].1.timestamp;
+            for (market_id, snapshot) in batch {
+                current.insert((*market_id).clone(), Self::market_at(market_id, snapshot));
+                let snaps = history.entry((*market_id).clone()).or_default();
+                snaps.push((*snapshot).clone());
+                if snaps.len() > self.config.history_len.max(1) {
+                    snaps.remove(0);
+                }
+            }
+
+            let stale_after = Duration::milliseconds(self.config.stale_after_ms as i64);
+            let mut markets: Vec<MarketData> = current.values()
+                .filter(|m| self.config.stale_after_ms == 0 || now - m.timestamp <= stale_after)
+                .cloned()
+                .collect();
+            markets.sort_by(|a, b| a.id.cmp(&b.id));
+            let view = MarketView {
+                markets: &markets,
+                events: &[],
+                watchlist: &watchlist,
+                order_books: &books,
+                price_history: &history,
+                relations: &[],
+                triangles: &[],
+                rewards: &rewards,
+                now,
+            };
+
+            for detector in &self.detectors {
+                let name = detector.name().to_string();
+                // Ogni opportunità registrata nel log è riportata: entra in cooldown
+                dedup.expire(now);
+                let reported: Vec<_> = detector.scan(&view).into_iter().filter(|o| dedup.admit(o, now)).collect();
+                for mut opportunity in reported {
+                    // I detector marcano l'ora di sistema: nel log vale quella registrata
+                    opportunity.timestamp = now;
+                    log.push(ReplayedOpportunity { detected_at: now, detector: name.clone(), opportunity });
+                }
+            }
+        }
+        log
+    }
+
+    /// Load prices matching `query` from storage and replay them
+    pub async fn run_stored(&mut self, storage: &dyn Storage, query: &PriceQuery) -> Result<Vec<ReplayedOpportunity>, String> {
+        let mut price_history: FxHashMap<String, Vec<PriceSnapshot>> = FxHashMap::default();
+        for stored in storage.query_prices(query).await? {
+            price_history.entry(stored.market_id).or_default().push(stored.snapshot);
+        }
+        Ok(self.run(&price_history))
+    }
+
+    fn market_at(market_id: &str, snapshot: &PriceSnapshot) -> MarketData {
+        MarketData {
+            id: market_id.to_string(),
+            yes_price: snapshot.yes_price,
+            no_price: snapshot.no_price,
+            yes_liquidity: snapshot.volume,
+            no_liquidity: snapshot.volume,
+            timestamp: snapshot.timestamp,
+            volume_24h: snapshot.volume,
+            ..MarketData::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arbitrage::ArbitrageDetector;
+    use crate::storage::{FlatFileStorage, StoredPrice};
+
+    #[tokio::test]
+    async fn test_detectors_replay_stored_prices_into_a_timestamped_log() {
+        let start = DateTime::from_timestamp(1_790_000_000, 0).unwrap();
+        let price = |market_id: &str, second: i64, yes_price: f64, no_price: f64| StoredPrice {
+            run_id: "recorded".to_string(),
+            market_id: market_id.to_string(),
+            snapshot: PriceSnapshot { timestamp: start + Duration::seconds(second), yes_price, no_price, volume: 10_000.0 },
+        };
+        // m1 è sbilanciato da 10 a 20 s, poi il suo feed tace; m2 solo a 40 s
+        let prices = vec![
+            price("m1", 0, 0.50, 0.50),
+            price("m2", 0, 0.55, 0.45),
+            price("m1", 10, 0.45, 0.50),
+            price("m1", 20, 0.45, 0.50),
+            price("m2", 40, 0.46, 0.49),
+            price("m2", 50, 0.55, 0.45),
+        ];
+        let dir = std::env::temp_dir().join(format!("replay_{}", uuid::Uuid::new_v4()));
+        let storage = FlatFileStorage::new(&dir);
+        storage.save_prices(&prices).await.unwrap();
+
+        let replay = |config: ReplayConfig| SnapshotReplayer::new(config)
+            .with_detector(Box::new(ArbitrageDetector::new(0.02, 1000.0)));
+        let log = replay(ReplayConfig::default()).run_stored(&storage, &PriceQuery::default()).await.unwrap();
+        let found: Vec<(i64, &str, &str)> = log.iter()
+            .map(|r| ((r.detected_at - start).num_seconds(), r.detector.as_str(), r.opportunity.market_id.as_str()))
+            .collect();
+        assert_eq!(found, vec![(10, "yes_no", "m1"), (40, "yes_no", "m2")]);
+        assert!(log.iter().all(|r| r.opportunity.timestamp == r.detected_at));
+
+        // Senza cooldown, a ogni timestamp in cui persiste; m1 esce dalla scansione quando i suoi prezzi invecchiano
+        let config = ReplayConfig {
+            stale_after_ms: 15_000,
+            dedup: OpportunityDedupConfig { ttl_secs: 0, ..OpportunityDedupConfig::default() },
+            ..ReplayConfig::default()
+        };
+        let every = replay(config).run_stored(&storage, &PriceQuery::default()).await.unwrap();
+        assert_eq!(every.iter().map(|r| (r.detected_at - start).num_seconds()).collect::<Vec<_>>(), vec![10, 20, 40]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,229 @@
+//! Market resolution monitoring module
+//!
+//! Implements:
+//! 1. Polling of the Gamma resolution status (settled by the UMA oracle) of markets with open positions
+//! 2. `MarketResolved` events, sent once per market on a channel
+//! 3. Position tracker consuming the events and settling positions at 0/1 in the paper broker
+
+use crate::paper::{BotState, SimulatedTrade};
+use crate::paper::PaperBroker;
+use crate::polymarket_api::{ApiResult, GammaApiClient, PolymarketApiError};
+use crate::types::TokenType;
+use chrono::{DateTime, Utc};
+use futures_util::future::BoxFuture;
+use fxhash::FxHashSet;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+/// Seconds between two polls of the held markets
+pub const RESOLUTION_POLL_SECS: u64 = 300;
+
+/// A held market resolved with its winning outcome
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MarketResolved {
+    pub market_id: String,
+    pub outcome: TokenType,
+    pub resolved_at: DateTime<Utc>,
+}
+
+/// Where resolution status comes from: Gamma in production, a fixed table in tests
+pub trait ResolutionSource: Send + Sync {
+    /// Some(true) if YES won, None while unresolved
+    fn resolution<'a>(&'a self, market_id: &'a str) -> BoxFuture<'a, ApiResult<Option<bool>>>;
+}
+
+impl ResolutionSource for GammaApiClient {
+    fn resolution<'a>(&'a self, market_id: &'a str) -> BoxFuture<'a, ApiResult<Option<bool>>> {
+        Box::pin(self.fetch_resolution(market_id))
+    }
+}
+
+/// Polls held markets and announces each resolution once
+pub struct ResolutionMonitor {
+    tx: mpsc::Sender<MarketResolved>,
+    announced: FxHashSet<String>,
+    unlisted: FxHashSet<String>, // Mercati sconosciuti a Gamma (es. simulati): non più interrogati
+}
+
+impl ResolutionMonitor {
+    pub fn new(tx: mpsc::Sender<MarketResolved>) -> Self {
+        Self {
+            tx,
+            announced: FxHashSet::default(),
+            unlisted: FxHashSet::default(),
+        }
+    }
+
+    /// Check the resolution of `market_ids` and send a `MarketResolved` for each newly resolved one
+    pub async fn poll(&mut self, source: &dyn ResolutionSource, market_ids: &[String], now: DateTime<Utc>) -> Vec<MarketResolved> {
+        let mut resolved = Vec::new();
+        for market_id in market_ids {
+            if self.announced.contains(market_id) || self.unlisted.contains(market_id) {
+                continue;
+            }
+            match source.resolution(market_id).await {
+                Ok(Some(yes_won)) => {
+                    let event = MarketResolved {
+                        market_id: market_id.clone(),
+                        outcome: if yes_won { TokenType::Yes } else { TokenType::No },
+                        resolved_at: now,
+                    };
+                    if self.tx.send(event.clone()).await.is_err() {
+                        break; // Tracker terminato
+                    }
+                    self.announced.insert(market_id.clone());
+                    resolved.push(event);
+                }
+                Ok(None) => {}
+                Err(PolymarketApiError::NotFound(_)) => {
+                    eprintln!("⚠️  Market {} not on Gamma, resolution not monitored", market_id);
+                    self.unlisted.insert(market_id.clone());
+                }
+                Err(e @ PolymarketApiError::RateLimited { .. }) => {
+                    eprintln!("Resolution poll stopped: {}", e);
+                    break;
+                }
+                Err(e) => eprintln!("Resolution of {} unavailable: {}", market_id, e),
+            }
+        }
+        resolved
+    }
+
+    /// Poll the broker's open positions every `RESOLUTION_POLL_SECS`
+    pub async fn run(mut self, source: Arc<dyn ResolutionSource>, broker: Arc<Mutex<PaperBroker>>) {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(RESOLUTION_POLL_SECS));
+        loop {
+            interval.tick().await;
+            let mut held: Vec<String> = broker.lock().unwrap().open_positions().into_iter().map(|p| p.market_id).collect();
+            held.dedup();
+            if !held.is_empty() {
+                self.poll(source.as_ref(), &held, Utc::now()).await;
+            }
+            if self.tx.is_closed() {
+                break;
+            }
+        }
+    }
+}
+
+/// Settles positions of resolved markets in the shared paper broker
+#[derive(Clone)]
+pub struct PositionTracker {
+    pub broker: Arc<Mutex<PaperBroker>>,
+    pub bot_state: Arc<Mutex<BotState>>,
+}
+
+impl PositionTracker {
+    pub fn new(broker: Arc<Mutex<PaperBroker>>, bot_state: Arc<Mutex<BotState>>) -> Self {
+        Self { broker, bot_state }
+    }
+
+    /// Redeem every position in the resolved market: the winning outcome pays 1.0 per share, the other 0
+    pub fn apply(&self, event: &MarketResolved) -> Vec<SimulatedTrade> {
+        let mut state = self.bot_state.lock().unwrap();
+        let mut broker = self.broker.lock().unwrap();
+        broker.settle(&mut state, &event.market_id, event.outcome)
+    }
+
+    /// Consume `MarketResolved` events until the monitor stops; settlements are handed to `on_settled`
+    pub async fn run(self, mut rx: mpsc::Receiver<MarketResolved>, on_settled: impl Fn(Vec<SimulatedTrade>)) {
+        while let Some(event) = rx.recv().await {
+            let settlements = self.apply(&event);
+            let pnl: f64 = settlements.iter().map(|t| t.pnl).sum();
+            eprintln!("🏁 Market {} resolved {}: {} positions settled, PnL {:.2}", event.market_id, event.outcome, settlements.len(), pnl);
+            on_settled(settlements);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paper::MarketInfo;
+    use crate::paper::{PaperOrder, TradeSource};
+    use crate::types::Direction;
+    use fxhash::FxHashMap;
+
+    struct FixedResolutions(FxHashMap<String, ApiResult<Option<bool>>>);
+
+    impl ResolutionSource for FixedResolutions {
+        fn resolution<'a>(&'a self, market_id: &'a str) -> BoxFuture<'a, ApiResult<Option<bool>>> {
+            let result = match self.0.get(market_id) {
+                Some(Ok(outcome)) => Ok(*outcome),
+                _ => Err(PolymarketApiError::NotFound(market_id.to_string())),
+            };
+            Box::pin(async move { result })
+        }
+    }
+
+    fn buy(broker: &mut PaperBroker, state: &mut BotState, market_id: &str, token_type: TokenType, price: f64) {
+        let order = PaperOrder {
+            market_id: market_id.to_string(),
+            token_type,
+            direction: Direction::Buy,
+            quantity: 100.0,
+            limit_price: None,
+            source: TradeSource::Manual,
+        };
+        let market = MarketInfo {
+            id: market_id.to_string(),
+            question: String::new(),
+            yes_price: price,
+            no_price: 1.0 - price,
+            yes_liquidity: 10_000.0,
+            no_liquidity: 10_000.0,
+            volume_24h: 0.0,
+            timestamp: Utc::now(),
+            event_id: None,
+            category: None,
+        };
+        broker.execute(state, &order, &market).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_resolved_positions_are_settled() {
+        let mut state = BotState {
+            running: false,
+            balance: 10_000.0,
+            initial_balance: 10_000.0,
+            total_pnl: 0.0,
+            win_rate: 0.0,
+            total_trades: 0,
+            profitable_trades: 0,
+            last_update: Utc::now(),
+        };
+        let mut broker = PaperBroker::default();
+        buy(&mut broker, &mut state, "won", TokenType::Yes, 0.40);
+        buy(&mut broker, &mut state, "lost", TokenType::Yes, 0.30);
+        buy(&mut broker, &mut state, "open", TokenType::No, 0.50);
+        let tracker = PositionTracker::new(Arc::new(Mutex::new(broker)), Arc::new(Mutex::new(state)));
+
+        let source = FixedResolutions(
+            [("won", Ok(Some(true))), ("lost", Ok(Some(false))), ("open", Ok(None))]
+                .into_iter()
+                .map(|(id, r)| (id.to_string(), r))
+                .collect(),
+        );
+        let (tx, rx) = mpsc::channel(16);
+        let mut monitor = ResolutionMonitor::new(tx);
+        let held: Vec<String> = ["won", "lost", "open", "simulated"].iter().map(|s| s.to_string()).collect();
+
+        let events = monitor.poll(&source, &held, Utc::now()).await;
+        assert_eq!(events.iter().map(|e| e.outcome).collect::<Vec<_>>(), vec![TokenType::Yes, TokenType::No]);
+        assert!(monitor.poll(&source, &held, Utc::now()).await.is_empty()); // Annunciati una sola volta
+        assert!(monitor.unlisted.contains("simulated"));
+        drop(monitor);
+
+        let settled = Arc::new(Mutex::new(Vec::new()));
+        let sink = settled.clone();
+        tracker.clone().run(rx, move |trades| sink.lock().unwrap().extend(trades)).await;
+
+        let settled = settled.lock().unwrap();
+        assert_eq!(settled.len(), 2);
+        assert!((settled.iter().map(|t| t.pnl).sum::<f64>() - (60.0 - 30.0)).abs() < 1e-9);
+        let broker = tracker.broker.lock().unwrap();
+        assert_eq!(broker.open_positions().iter().map(|p| p.market_id.as_str()).collect::<Vec<_>>(), vec!["open"]);
+        assert!((tracker.bot_state.lock().unwrap().total_pnl - 30.0).abs() < 1e-9);
+    }
+}
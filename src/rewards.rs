@@ -0,0 +1,196 @@
+//! Liquidity rewards module
+//!
+//! Implements:
+//! 1. Maker reward programs of CLOB markets (daily rate, max spread, min size)
+//! 2. Rewards accrued by the account per market and day
+//! 3. Estimated reward of resting maker orders, added to opportunity scoring
+
+use crate::types::ArbitrageOpportunity;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use fxhash::FxHashMap;
+use serde::{Deserialize, Serialize};
+
+/// Minimum spacing between two fetches of the reward endpoints
+pub const REWARDS_REFRESH_SECS: i64 = 3600;
+
+/// Reward program of a market, as served by `/rewards/markets/current`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RewardConfig {
+    pub condition_id: String,
+    pub rate_per_day: f64, // USDC distribuiti ogni giorno tra i maker del mercato
+    pub max_spread: f64,   // Distanza massima dal midpoint per qualificarsi, in prezzo (0.03 = 3c)
+    pub min_size: f64,     // Shares minime per ordine
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+}
+
+impl RewardConfig {
+    /// Whether the program pays on the day of `now`
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        let today = now.date_naive();
+        self.rate_per_day > 0.0
+            && self.start_date.is_none_or(|start| start <= today)
+            && self.end_date.is_none_or(|end| today <= end)
+    }
+}
+
+/// Rewards earned by the account on one market in one day, as served by `/rewards/user`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccruedReward {
+    pub condition_id: String,
+    pub date: NaiveDate,
+    pub earnings: f64, // USDC
+}
+
+/// Score of an order `offset` away from the midpoint: ((v - s) / v)^2, zero beyond the max spread
+pub fn spread_score(max_spread: f64, offset: f64) -> f64 {
+    if max_spread <= 0.0 || offset >= max_spread {
+        return 0.0;
+    }
+    ((max_spread - offset.max(0.0)) / max_spread).powi(2)
+}
+
+/// Reward programs and accrued rewards keyed by market id
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RewardBook {
+    pub configs: FxHashMap<String, RewardConfig>,
+    pub accrued: FxHashMap<String, FxHashMap<NaiveDate, f64>>, // market_id -> giorno -> USDC
+    pub quote_offset: f64,   // Distanza dal midpoint a cui si assume restino gli ordini maker
+    pub quote_notional: f64, // USDC quotati per mercato nella stima
+    pub rest_hours: f64,     // Permanenza stimata degli ordini nel book
+    pub refreshed_at: Option<DateTime<Utc>>,
+}
+
+impl Default for RewardBook {
+    fn default() -> Self {
+        Self {
+            configs: FxHashMap::default(),
+            accrued: FxHashMap::default(),
+            quote_offset: 0.005,
+            quote_notional: 100.0,
+            rest_hours: 1.0,
+            refreshed_at: None,
+        }
+    }
+}
+
+impl RewardBook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether the reward endpoints are due for a refresh at `now`
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        self.refreshed_at
+            .is_none_or(|last| now - last >= Duration::seconds(REWARDS_REFRESH_SECS))
+    }
+
+    /// Replace the reward programs; `market_ids` maps condition ids to cached market ids
+    ///
+    /// Returns how many programs matched a cached market.
+    pub fn set_configs(&mut self, configs: Vec<RewardConfig>, market_ids: &FxHashMap<String, String>, now: DateTime<Utc>) -> usize {
+        self.configs = configs
+            .into_iter()
+            .filter_map(|c| market_ids.get(&c.condition_id).map(|id| (id.clone(), c)))
+            .collect();
+        self.refreshed_at = Some(now);
+        self.configs.len()
+    }
+
+    /// Record earnings; a later report for the same market and day replaces the earlier one
+    pub fn record_accrued(&mut self, rewards: &[AccruedReward], market_ids: &FxHashMap<String, String>) {
+        for reward in rewards {
+            // Mercati non in cache restano sotto il condition id
+            let market_id = market_ids.get(&reward.condition_id).unwrap_or(&reward.condition_id);
+            self.accrued
+                .entry(market_id.clone())
+                .or_default()
+                .insert(reward.date, reward.earnings);
+        }
+    }
+
+    /// Rewards accrued on a market over all recorded days
+    pub fn accrued_for(&self, market_id: &str) -> f64 {
+        self.accrued.get(market_id).map(|days| days.values().sum()).unwrap_or(0.0)
+    }
+
+    pub fn total_accrued(&self) -> f64 {
+        self.accrued.values().flat_map(|days| days.values()).sum()
+    }
+
+    /// Estimated USDC earned by maker orders of `size` shares resting `rest_hours` at `quote_offset`
+    ///
+    /// Competing liquidity is assumed at the midpoint (full score), so the estimate is conservative.
+    pub fn estimate_reward(&self, market_id: &str, size: f64, competing_size: f64, now: DateTime<Utc>) -> f64 {
+        self.estimate_reward_at(market_id, size, self.quote_offset, competing_size, now)
+    }
+
+    /// Estimated USDC earned by maker orders of `size` shares resting `rest_hours` at `offset` from the midpoint
+    pub fn estimate_reward_at(&self, market_id: &str, size: f64, offset: f64, competing_size: f64, now: DateTime<Utc>) -> f64 {
+        let Some(config) = self.configs.get(market_id).filter(|c| c.is_active(now)) else { return 0.0 };
+        if size < config.min_size {
+            return 0.0;
+        }
+        let ours = size * spread_score(config.max_spread, offset);
+        if ours <= 0.0 {
+            return 0.0;
+        }
+        let share = ours / (ours + competing_size.max(0.0));
+        config.rate_per_day * share * self.rest_hours / 24.0
+    }
+
+    /// Estimated reward of an opportunity as a percentage of the quoted notional, comparable with `roi_pct`
+    pub fn reward_roi_pct(&self, opportunity: &ArbitrageOpportunity) -> f64 {
+        if self.quote_notional <= 0.0 || opportunity.sum_price <= 0.0 {
+            return 0.0;
+        }
+        let size = self.quote_notional / opportunity.sum_price;
+        let reward = self.estimate_reward(&opportunity.market_id, size, opportunity.liquidity, opportunity.timestamp);
+        reward / self.quote_notional * 100.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reward_estimate_and_accrual() {
+        let now = DateTime::from_timestamp(1_700_000_000, 0).unwrap(); // 2023-11-14
+        let day = |d: &str| NaiveDate::parse_from_str(d, "%Y-%m-%d").unwrap();
+        let market_ids: FxHashMap<String, String> = [("0xabc".to_string(), "m1".to_string())].into_iter().collect();
+
+        assert_eq!(spread_score(0.03, 0.0), 1.0);
+        assert!((spread_score(0.03, 0.015) - 0.25).abs() < 1e-9);
+        assert_eq!(spread_score(0.03, 0.04), 0.0);
+
+        let mut book = RewardBook { quote_offset: 0.015, rest_hours: 24.0, ..RewardBook::new() };
+        let config = RewardConfig {
+            condition_id: "0xabc".to_string(),
+            rate_per_day: 100.0,
+            max_spread: 0.03,
+            min_size: 50.0,
+            start_date: Some(day("2023-11-01")),
+            end_date: Some(day("2023-12-31")),
+        };
+        let expired = RewardConfig { condition_id: "0xdef".to_string(), end_date: Some(day("2023-11-01")), ..config.clone() };
+        assert_eq!(book.set_configs(vec![config.clone(), expired], &market_ids, now), 1);
+        assert!(!book.is_due(now + Duration::minutes(5)));
+
+        // 400 shares a score 0.25 = 100 contro 300 di liquidità concorrente: quota 1/4
+        assert!((book.estimate_reward("m1", 400.0, 300.0, now) - 25.0).abs() < 1e-9);
+        assert_eq!(book.estimate_reward("m1", 40.0, 0.0, now), 0.0); // Sotto min_size
+        assert_eq!(book.estimate_reward("m1", 400.0, 300.0, now + Duration::days(60)), 0.0); // Programma finito
+        assert_eq!(book.estimate_reward("m2", 400.0, 0.0, now), 0.0);
+
+        let rewards = vec![
+            AccruedReward { condition_id: "0xabc".to_string(), date: day("2023-11-13"), earnings: 1.0 },
+            AccruedReward { condition_id: "0xabc".to_string(), date: day("2023-11-14"), earnings: 0.5 },
+            AccruedReward { condition_id: "0xabc".to_string(), date: day("2023-11-14"), earnings: 0.75 },
+            AccruedReward { condition_id: "0xfff".to_string(), date: day("2023-11-14"), earnings: 2.0 },
+        ];
+        book.record_accrued(&rewards, &market_ids);
+        assert_eq!(book.accrued_for("m1"), 1.75);
+        assert_eq!(book.total_accrued(), 3.75);
+    }
+}
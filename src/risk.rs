@@ -5,9 +5,56 @@
 //! 2. Sharpe Ratio calculation
 //! 3. Maximum Drawdown tracking
 //! 4. Risk controls and limits
+//! 5. Clock-driven daily resets and loss-streak cooldowns
+//! 6. Per-market resolution-risk flags from the news feed (reduced sizing or pause)
+//! 7. Journal of drawdown episodes with recovery statistics
+//! 8. Per-strategy overrides of max position, daily loss share and concurrent trades, never
+//!    looser than the global limits: strategy losses also count toward the global daily loss
 
+use crate::clock::{system_clock, SharedClock};
 use crate::types::*;
+use chrono::{DateTime, NaiveDate, Utc};
 use fxhash::FxHashMap;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Drawdown from the peak that opens an episode (smaller dips are noise)
+pub const DRAWDOWN_EPISODE_THRESHOLD: f64 = 0.001;
+
+/// Closed episodes kept in the journal
+pub const DRAWDOWN_JOURNAL_LEN: usize = 1000;
+
+/// How strongly a flag restricts trading
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResolutionRisk {
+    Reduce,
+    Pause,
+}
+
+/// Resolution-risk flag raised on a market by a headline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResolutionRiskFlag {
+    pub market_id: String,
+    pub level: ResolutionRisk,
+    pub size_multiplier: f64, // 0 con Pause
+    pub headline: String,
+    pub link: String,
+    pub raised_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Risk limit overrides of one strategy (arbitrage type); unset limits fall back to the global ones
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StrategyRiskLimits {
+    pub strategy: ArbType,
+    #[serde(default)]
+    pub max_position: Option<f64>, // USDC per trade, sotto il ceiling del tier di sizing
+    #[serde(default)]
+    pub daily_loss_share: Option<f64>, // Quota (0-1) del limite di perdita giornaliero globale
+    #[serde(default)]
+    pub max_concurrent_trades: Option<usize>, // Esecuzioni della strategia nella stessa ondata
+}
 
 /// Risk manager
 pub struct RiskManager {
@@ -17,6 +64,15 @@ pub struct RiskManager {
     pub daily_loss: f64,
     pub peak_capital: f64,
     pub low_capital: f64,
+    pub loss_cooldown: Duration, // Pausa dopo max_consecutive_losses perdite consecutive
+    pub cooldown_until: Option<DateTime<Utc>>,
+    pub trading_day: NaiveDate, // Giorno UTC a cui si riferisce daily_loss
+    pub clock: SharedClock,
+    pub available_balance: Option<f64>, // Collaterale reale sul CLOB, se noto
+    pub resolution_flags: FxHashMap<String, ResolutionRiskFlag>, // Flag attivi per mercato
+    pub drawdowns: DrawdownJournal, // Episodi di drawdown dal picco al recupero
+    pub strategy_limits: FxHashMap<ArbType, StrategyRiskLimits>, // Override per strategia
+    pub strategy_daily_loss: FxHashMap<ArbType, f64>, // Perdita del giorno per strategia, inclusa in daily_loss
 }
 
 impl RiskManager {
@@ -24,10 +80,11 @@ impl RiskManager {
         daily_loss_limit: f64,
             max_consecutive_losses: u32,
             max_drawdown: f64,
-            max_position_size: f64,   // Parametro senza default
-            max_daily_loss_pct: f64,   // Parametro senza default
-            max_consecutive_losses_limit: u32,  // Parametro senza default
+            _max_position_size: f64,   // Parametro senza default
+            _max_daily_loss_pct: f64,   // Parametro senza default
+            _max_consecutive_losses_limit: u32,  // Parametro senza default
         ) -> Self {
+        let clock = system_clock();
         Self {
             metrics: RiskMetrics {
                 var_95: 0.0,
@@ -42,11 +99,67 @@ impl RiskManager {
             daily_loss: 0.0,
             peak_capital: 0.0,
             low_capital: 0.0,
+            loss_cooldown: Duration::from_secs(15 * 60),
+            cooldown_until: None,
+            trading_day: clock.now().date_naive(),
+            clock,
+            available_balance: None,
+            resolution_flags: FxHashMap::default(),
+            drawdowns: DrawdownJournal::new(),
+            strategy_limits: FxHashMap::default(),
+            strategy_daily_loss: FxHashMap::default(),
+        }
+    }
+
+    /// Metrics-only risk manager (limits never trip) replayed over a series of trade PnLs
+    pub fn replay(pnl: &[f64], initial_capital: f64) -> Self {
+        let mut risk = Self::new(f64::MAX, u32::MAX, 1.0, 0.10, 0.20, 10);
+        let mut capital = initial_capital;
+        risk.peak_capital = capital;
+        risk.low_capital = capital;
+        risk.drawdowns.record(capital, risk.clock.now(), false);
+        for &profit in pnl {
+            capital += profit;
+            risk.update(profit, capital);
+        }
+        risk
+    }
+
+    /// Replace the time source (e.g. a backtest's simulated clock)
+    pub fn set_clock(&mut self, clock: SharedClock) {
+        self.trading_day = clock.now().date_naive();
+        self.cooldown_until = None;
+        self.clock = clock;
+    }
+
+    /// Reset the daily loss when the clock has moved into a new UTC day
+    pub fn roll_day(&mut self) -> bool {
+        let today = self.clock.now().date_naive();
+        if today == self.trading_day {
+            return false;
+        }
+        self.trading_day = today;
+        self.reset_daily();
+        true
+    }
+
+    /// Daily loss for the clock's current day
+    pub fn current_daily_loss(&self) -> f64 {
+        if self.clock.now().date_naive() == self.trading_day {
+            self.daily_loss
+        } else {
+            0.0
         }
     }
 
+    /// Whether a loss-streak cooldown is still running
+    pub fn in_cooldown(&self) -> bool {
+        self.cooldown_until.is_some_and(|until| self.clock.now() < until)
+    }
+
     /// Update risk metrics after a trade
     pub fn update(&mut self, profit: f64, capital: f64) {
+        self.roll_day();
         self.trade_history.push(profit);
         
         if self.trade_history.len() > 1000 {
@@ -59,6 +172,13 @@ impl RiskManager {
         } else {
             self.consecutive_losses = 0;
         }
+
+        // Serie di perdite: pausa temporizzata, poi la serie riparte da zero
+        if self.consecutive_losses >= self.metrics.max_consecutive_losses {
+            let cooldown = chrono::Duration::from_std(self.loss_cooldown).unwrap_or_default();
+            self.cooldown_until = Some(self.clock.now() + cooldown);
+            self.consecutive_losses = 0;
+        }
         
         if capital > self.peak_capital {
             self.peak_capital = capital;
@@ -70,10 +190,57 @@ impl RiskManager {
         }
         
         self.metrics.current_drawdown = (self.peak_capital - capital) / self.peak_capital;
+        self.drawdowns.record(capital, self.clock.now(), true);
         self.metrics.var_95 = self.calculate_var_95();
         self.metrics.sharpe_ratio = self.calculate_sharpe_ratio();
     }
 
+    /// Update risk metrics after a trade of `strategy`, also charging a loss to the strategy's daily budget
+    pub fn update_strategy(&mut self, strategy: ArbType, profit: f64, capital: f64) {
+        self.update(profit, capital);
+        if profit < 0.0 {
+            *self.strategy_daily_loss.entry(strategy).or_default() += profit.abs();
+        }
+    }
+
+    /// Replace the per-strategy overrides (a later entry for the same strategy wins)
+    pub fn set_strategy_limits(&mut self, limits: &[StrategyRiskLimits]) {
+        self.strategy_limits = limits.iter().map(|l| (l.strategy, l.clone())).collect();
+    }
+
+    /// Daily loss of a strategy for the clock's current day
+    pub fn current_strategy_loss(&self, strategy: ArbType) -> f64 {
+        if self.clock.now().date_naive() == self.trading_day {
+            self.strategy_daily_loss.get(&strategy).copied().unwrap_or(0.0)
+        } else {
+            0.0
+        }
+    }
+
+    /// Largest position a trade of `strategy` may take, in USDC (unbounded without an override)
+    pub fn max_position_for(&self, strategy: ArbType) -> f64 {
+        self.strategy_limits.get(&strategy)
+            .and_then(|l| l.max_position)
+            .map_or(f64::INFINITY, |max| max.max(0.0))
+    }
+
+    /// Executions of `strategy` allowed in one wave, on top of the queue's wave size
+    pub fn max_concurrent_for(&self, strategy: ArbType) -> Option<usize> {
+        self.strategy_limits.get(&strategy).and_then(|l| l.max_concurrent_trades)
+    }
+
+    /// Whether a trade of `strategy` is allowed: the global checks, then the strategy's loss budget
+    pub fn can_trade_strategy(&self, strategy: ArbType, capital: f64) -> bool {
+        if !self.can_trade(capital) {
+            return false;
+        }
+        // La quota non supera il limite globale: una strategia non può perdere più del totale
+        match self.strategy_limits.get(&strategy).and_then(|l| l.daily_loss_share) {
+            Some(share) => self.current_strategy_loss(strategy) < share.clamp(0.0, 1.0) * self.metrics.daily_loss_limit,
+            None => true,
+        }
+    }
+
     /// Calculate 95% Value at Risk
     pub fn calculate_var_95(&self) -> f64 {
         if self.trade_history.len() < 20 {
@@ -110,13 +277,61 @@ impl RiskManager {
         mean / std * (252.0_f64).sqrt()  // Annualized
     }
 
+    /// Record the collateral actually available on the exchange
+    pub fn set_available_balance(&mut self, balance: Option<f64>) {
+        self.available_balance = balance;
+    }
+
+    /// Capital a trade may use: the tracked capital, capped by the real balance when known
+    pub fn tradable_capital(&self, capital: f64) -> f64 {
+        match self.available_balance {
+            Some(balance) => capital.min(balance),
+            None => capital,
+        }
+    }
+
+    /// Record a resolution-risk flag; the most restrictive flag of a market wins
+    pub fn raise_resolution_flag(&mut self, flag: ResolutionRiskFlag) {
+        let now = self.clock.now();
+        match self.resolution_flags.get(&flag.market_id) {
+            Some(existing) if existing.expires_at > now && existing.size_multiplier < flag.size_multiplier => {}
+            _ => {
+                self.resolution_flags.insert(flag.market_id.clone(), flag);
+            }
+        }
+    }
+
+    /// Live resolution-risk flags (expired ones are dropped)
+    pub fn active_resolution_flags(&mut self) -> Vec<ResolutionRiskFlag> {
+        let now = self.clock.now();
+        self.resolution_flags.retain(|_, flag| flag.expires_at > now);
+        self.resolution_flags.values().cloned().collect()
+    }
+
+    /// Size multiplier for an opportunity: the lowest over its market and its legs' markets
+    pub fn resolution_multiplier(&self, opportunity: &ArbitrageOpportunity) -> f64 {
+        let now = self.clock.now();
+        let leg_markets = opportunity.legs.iter().flatten().map(|l| &l.market_id);
+        std::iter::once(&opportunity.market_id)
+            .chain(leg_markets)
+            .filter_map(|id| self.resolution_flags.get(id))
+            .filter(|flag| flag.expires_at > now)
+            .map(|flag| flag.size_multiplier)
+            .fold(1.0, f64::min)
+    }
+
     /// Check if trade should be allowed
-    pub fn can_trade(&self, _capital: f64) -> bool {
-        if self.daily_loss >= self.metrics.daily_loss_limit {
+    pub fn can_trade(&self, capital: f64) -> bool {
+        // Senza saldo reale il capitale tracciato non blocca (compatibilità con la simulazione)
+        if self.available_balance.is_some() && self.tradable_capital(capital) <= 0.0 {
+            return false;
+        }
+
+        if self.current_daily_loss() >= self.metrics.daily_loss_limit {
             return false;
         }
         
-        if self.consecutive_losses >= self.metrics.max_consecutive_losses {
+        if self.in_cooldown() {
             return false;
         }
         
@@ -132,7 +347,7 @@ impl RiskManager {
         RiskStatus {
             can_trade: self.can_trade(self.peak_capital - self.metrics.current_drawdown * self.peak_capital),
             consecutive_losses: self.consecutive_losses,
-            daily_loss_pct: (self.daily_loss / self.peak_capital) * 100.0,
+            daily_loss_pct: (self.current_daily_loss() / self.peak_capital) * 100.0,
             current_drawdown_pct: self.metrics.current_drawdown * 100.0,
             var_95: self.metrics.var_95,
             sharpe_ratio: self.metrics.sharpe_ratio,
@@ -142,11 +357,12 @@ impl RiskManager {
     /// Reset daily limits
     pub fn reset_daily(&mut self) {
         self.daily_loss = 0.0;
+        self.strategy_daily_loss.clear();
     }
 }
 
 /// Risk status
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RiskStatus {
     pub can_trade: bool,
     pub consecutive_losses: u32,
@@ -156,6 +372,122 @@ pub struct RiskStatus {
     pub sharpe_ratio: f64,
 }
 
+/// One drawdown: from the first loss below the peak, through the trough, back to the peak
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DrawdownEpisode {
+    pub started_at: DateTime<Utc>,
+    pub peak_capital: f64,
+    pub trough_at: DateTime<Utc>,
+    pub trough_capital: f64,
+    pub depth_pct: f64,
+    pub recovered_at: Option<DateTime<Utc>>, // None finché il capitale non torna al picco
+    pub recovery_secs: Option<i64>, // Dal minimo al recupero
+    pub duration_secs: Option<i64>, // Dall'inizio al recupero
+    pub trades: u32, // Trade eseguiti durante l'episodio
+}
+
+/// Drawdown episodes with recovery statistics
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DrawdownReport {
+    pub episodes: Vec<DrawdownEpisode>, // Chiusi, dal più recente
+    pub current: Option<DrawdownEpisode>,
+    pub recovered: usize,
+    pub average_recovery_secs: Option<f64>,
+    pub average_duration_secs: Option<f64>,
+    pub average_depth_pct: f64,
+    pub max_depth_pct: f64,
+    pub average_trades: f64,
+    pub deepest: Vec<DrawdownEpisode>, // Anche l'episodio in corso
+}
+
+/// Journal of drawdown episodes fed with the equity after each trade
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DrawdownJournal {
+    pub episodes: Vec<DrawdownEpisode>,
+    pub current: Option<DrawdownEpisode>,
+    peak: Option<f64>,
+}
+
+impl DrawdownJournal {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the equity at `now`; `traded` counts the sample as a trade of the open episode
+    ///
+    /// Returns the episode closed by this sample, if any.
+    pub fn record(&mut self, equity: f64, now: DateTime<Utc>, traded: bool) -> Option<DrawdownEpisode> {
+        let peak = *self.peak.get_or_insert(equity);
+
+        if let Some(episode) = self.current.as_mut() {
+            episode.trades += traded as u32;
+            if equity < episode.trough_capital {
+                episode.trough_capital = equity;
+                episode.trough_at = now;
+                episode.depth_pct = (episode.peak_capital - equity) / episode.peak_capital * 100.0;
+            }
+            if equity < episode.peak_capital {
+                return None;
+            }
+
+            // Recuperato il picco: l'episodio si chiude
+            let mut closed = self.current.take()?;
+            closed.recovered_at = Some(now);
+            closed.recovery_secs = Some((now - closed.trough_at).num_seconds());
+            closed.duration_secs = Some((now - closed.started_at).num_seconds());
+            self.episodes.push(closed.clone());
+            if self.episodes.len() > DRAWDOWN_JOURNAL_LEN {
+                self.episodes.remove(0);
+            }
+            self.peak = Some(equity);
+            return Some(closed);
+        }
+
+        if equity >= peak {
+            self.peak = Some(equity);
+        } else if peak > 0.0 && (peak - equity) / peak >= DRAWDOWN_EPISODE_THRESHOLD {
+            self.current = Some(DrawdownEpisode {
+                started_at: now,
+                peak_capital: peak,
+                trough_at: now,
+                trough_capital: equity,
+                depth_pct: (peak - equity) / peak * 100.0,
+                recovered_at: None,
+                recovery_secs: None,
+                duration_secs: None,
+                trades: traded as u32,
+            });
+        }
+        None
+    }
+
+    /// Episodes and statistics, with the `deepest` largest drawdowns
+    pub fn report(&self, deepest: usize) -> DrawdownReport {
+        let average = |values: Vec<f64>| (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64);
+        let closed = &self.episodes;
+
+        let mut all: Vec<DrawdownEpisode> = closed.iter().chain(self.current.iter()).cloned().collect();
+        all.sort_by(|a, b| b.depth_pct.partial_cmp(&a.depth_pct).unwrap());
+        all.truncate(deepest);
+
+        DrawdownReport {
+            episodes: closed.iter().rev().cloned().collect(),
+            current: self.current.clone(),
+            recovered: closed.len(),
+            average_recovery_secs: average(closed.iter().filter_map(|e| e.recovery_secs).map(|s| s as f64).collect()),
+            average_duration_secs: average(closed.iter().filter_map(|e| e.duration_secs).map(|s| s as f64).collect()),
+            average_depth_pct: average(closed.iter().map(|e| e.depth_pct).collect()).unwrap_or(0.0),
+            max_depth_pct: closed.iter().chain(self.current.iter()).map(|e| e.depth_pct).fold(0.0, f64::max),
+            average_trades: average(closed.iter().map(|e| e.trades as f64).collect()).unwrap_or(0.0),
+            deepest: all,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
 /// Position sizer using Kelly Criterion
 pub struct PositionSizer {
     pub kelly_fraction: f64,
@@ -202,10 +534,11 @@ impl PositionSizer {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::clock::SimulatedClock;
 
     #[test]
     fn test_risk_manager() {
-        let mut rm = RiskManager::new(50.0, 5, 0.15);
+        let mut rm = RiskManager::new(50.0, 5, 0.15, 0.10, 0.20, 10);
         
         for i in 0..10 {
             let profit = if i % 2 == 0 { 5.0 } else { -2.0 };
@@ -215,4 +548,150 @@ mod tests {
         assert!(rm.trade_history.len() == 10);
         assert!(rm.calculate_sharpe_ratio() > 0.0);
     }
+
+    #[test]
+    fn test_daily_reset_and_cooldown_follow_clock() {
+        let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap(); // 22:13 UTC
+        let clock = SimulatedClock::new(start);
+        let mut rm = RiskManager::new(50.0, 3, 0.50, 0.10, 0.20, 10);
+        rm.set_clock(clock.shared());
+        rm.update(0.0, 10_000.0);
+
+        rm.update(-60.0, 9_940.0);
+        assert!(!rm.can_trade(9_940.0));
+
+        // Mezzanotte simulata: il limite giornaliero si azzera
+        clock.advance(Duration::from_secs(2 * 3600));
+        assert!(rm.can_trade(9_940.0));
+
+        rm.update(-1.0, 9_939.0);
+        rm.update(-1.0, 9_938.0);
+        assert!(rm.in_cooldown());
+        assert!(!rm.can_trade(9_938.0));
+
+        clock.advance(rm.loss_cooldown);
+        assert!(rm.can_trade(9_938.0));
+    }
+
+    #[test]
+    fn test_drawdown_journal() {
+        let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let at = |minutes: i64| start + chrono::Duration::minutes(minutes);
+        let mut journal = DrawdownJournal::new();
+
+        journal.record(1_000.0, at(0), true);
+        journal.record(1_100.0, at(1), true);
+        journal.record(1_099.9, at(2), true); // Sotto la soglia: nessun episodio
+        assert!(journal.current.is_none());
+
+        journal.record(1_045.0, at(3), true);
+        journal.record(990.0, at(5), true);
+        journal.record(1_050.0, at(8), true);
+        let closed = journal.record(1_120.0, at(15), true).unwrap();
+        assert!((closed.depth_pct - 10.0).abs() < 1e-9);
+        assert_eq!((closed.recovery_secs, closed.duration_secs, closed.trades), (Some(600), Some(720), 4));
+
+        journal.record(1_064.0, at(20), true);
+        let report = journal.report(5);
+        assert_eq!(report.recovered, 1);
+        assert_eq!(report.average_recovery_secs, Some(600.0));
+        assert!((report.current.as_ref().unwrap().depth_pct - 5.0).abs() < 1e-9);
+        assert_eq!(report.max_depth_pct, report.deepest[0].depth_pct);
+        assert_eq!(report.deepest.len(), 2);
+    }
+
+    #[test]
+    fn test_real_balance_caps_capital() {
+        let mut rm = RiskManager::new(50.0, 5, 0.15, 0.10, 0.20, 10);
+        assert_eq!(rm.tradable_capital(1_000.0), 1_000.0);
+
+        rm.set_available_balance(Some(250.0));
+        assert_eq!(rm.tradable_capital(1_000.0), 250.0);
+        assert!(rm.can_trade(1_000.0));
+
+        rm.set_available_balance(Some(0.0));
+        assert!(!rm.can_trade(1_000.0));
+    }
+
+    #[test]
+    fn test_resolution_flags_scale_and_expire() {
+        let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let clock = SimulatedClock::new(start);
+        let mut rm = RiskManager::new(50.0, 5, 0.15, 0.10, 0.20, 10);
+        rm.set_clock(clock.shared());
+
+        let flag = |market: &str, multiplier: f64| ResolutionRiskFlag {
+            market_id: market.to_string(),
+            level: if multiplier > 0.0 { ResolutionRisk::Reduce } else { ResolutionRisk::Pause },
+            size_multiplier: multiplier,
+            headline: String::new(),
+            link: String::new(),
+            raised_at: start,
+            expires_at: start + chrono::Duration::hours(1),
+        };
+        rm.raise_resolution_flag(flag("m1", 0.0));
+        rm.raise_resolution_flag(flag("m1", 0.5)); // Meno restrittivo: ignorato
+        rm.raise_resolution_flag(flag("m2", 0.5));
+
+        let mut opportunity = ArbitrageOpportunity {
+            market_id: "event".to_string(),
+            question: String::new(),
+            arb_type: ArbType::YesNoMulti,
+            profit: 0.05,
+            roi_pct: 5.0,
+            confidence: 1.0,
+            yes_price: 0.5,
+            no_price: 0.45,
+            sum_price: 0.95,
+            liquidity: 1000.0,
+            timestamp: start,
+            legs: None,
+            path: None,
+            maker: None,
+        };
+        assert_eq!(rm.resolution_multiplier(&opportunity), 1.0);
+        opportunity.market_id = "m2".to_string();
+        assert_eq!(rm.resolution_multiplier(&opportunity), 0.5);
+        opportunity.market_id = "m1".to_string();
+        assert_eq!(rm.resolution_multiplier(&opportunity), 0.0);
+
+        clock.advance(Duration::from_secs(2 * 3600));
+        assert_eq!(rm.resolution_multiplier(&opportunity), 1.0);
+        assert!(rm.active_resolution_flags().is_empty());
+    }
+
+    #[test]
+    fn test_strategy_limits_sandbox_within_global_limits() {
+        let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let clock = SimulatedClock::new(start);
+        let mut rm = RiskManager::new(100.0, 50, 0.50, 0.10, 0.20, 10);
+        rm.set_clock(clock.shared());
+        rm.update(0.0, 10_000.0);
+        rm.set_strategy_limits(&[StrategyRiskLimits {
+            strategy: ArbType::StatisticalArb,
+            max_position: Some(50.0),
+            daily_loss_share: Some(0.2),
+            max_concurrent_trades: Some(1),
+        }]);
+
+        assert_eq!(rm.max_position_for(ArbType::StatisticalArb), 50.0);
+        assert_eq!(rm.max_position_for(ArbType::YesNoSimple), f64::INFINITY);
+        assert_eq!((rm.max_concurrent_for(ArbType::StatisticalArb), rm.max_concurrent_for(ArbType::YesNoSimple)), (Some(1), None));
+
+        // 20 USDC persi esauriscono la quota della strategia sperimentale, non quella dell'arbitraggio
+        rm.update_strategy(ArbType::StatisticalArb, -20.0, 9_980.0);
+        assert!(!rm.can_trade_strategy(ArbType::StatisticalArb, 9_980.0));
+        assert!(rm.can_trade_strategy(ArbType::YesNoSimple, 9_980.0));
+
+        // Le perdite si sommano al limite globale, che blocca tutte le strategie
+        rm.update_strategy(ArbType::YesNoSimple, -80.0, 9_900.0);
+        assert_eq!(rm.current_daily_loss(), 100.0);
+        assert!(!rm.can_trade_strategy(ArbType::YesNoSimple, 9_900.0));
+
+        // Nuovo giorno: budget di nuovo disponibili
+        clock.advance(Duration::from_secs(24 * 3600));
+        rm.roll_day();
+        assert!(rm.can_trade_strategy(ArbType::StatisticalArb, 9_900.0));
+        assert_eq!(rm.current_strategy_loss(ArbType::StatisticalArb), 0.0);
+    }
 }
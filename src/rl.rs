@@ -1,15 +1,22 @@
-//! Reinforcement Learning module for adaptive trading
+//! Reinforcement Learning module for adaptive trading (feature "rl")
 //!
 //! Implements:
 //! 1. Q-Learning for adaptive trade signals
-//! 2. EMRT (Empirical Mean Reversion Time) for mean reversion detection
-//! 3. Model-free RL framework
+//! 2. Model-free RL framework
+//! 3. Versioned Q-table files, so learned values survive crate upgrades
 
-use crate::types::*;
+use crate::types::{ArbitrageOpportunity, TradeExecution};
+use crate::versioning::{load_document, to_document, Schema};
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::Path;
+
+/// Schema of saved Q-tables
+pub const Q_TABLE_SCHEMA: Schema = Schema::new("q_table", &[]);
 
 /// Q-Learning optimizer for adaptive trading signals
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QLearningOptimizer {
     q_table: HashMap<String, HashMap<usize, f64>>,
     epsilon: f64,
@@ -27,6 +34,23 @@ impl QLearningOptimizer {
         }
     }
 
+    /// Persist the Q-table and learning rates
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create Q-table directory: {}", e))?;
+        }
+        let content = to_document(&Q_TABLE_SCHEMA, self)
+            .map_err(|e| format!("Failed to serialize Q-table: {}", e))?;
+        std::fs::write(path, content)
+            .map_err(|e| format!("Failed to write Q-table: {}", e))
+    }
+
+    /// Load a Q-table saved by this or an older version (None if the file does not exist yet)
+    pub fn load(path: &Path) -> Result<Option<Self>, String> {
+        load_document(&Q_TABLE_SCHEMA, path).map_err(|e| format!("Failed to load Q-table: {}", e))
+    }
+
     /// Get state key from market conditions
     fn get_state_key(&self, z_score: f64, momentum: f64, arb_available: bool) -> String {
         let z_bucket = if z_score > 2.0 { "high" } else if z_score < -2.0 { "low" } else { "mid" };
@@ -86,64 +110,15 @@ impl QLearningOptimizer {
             actions.insert(action, new_q);
         }
     }
-}
-
-/// EMRT (Empirical Mean Reversion Time) Calculator
-pub struct EmrtCalculator {
-    window: usize,
-    threshold: f64,
-}
 
-impl EmrtCalculator {
-    pub fn new(window: usize, threshold: f64) -> Self {
-        Self { window, threshold }
-    }
-
-    /// Calculate EMRT for a price series
-    pub fn calculate_emrt(&self, prices: &[f64]) -> f64 {
-        if prices.len() < 2 {
-            return 0.0;
-        }
-
-        let mut reversion_times = Vec::new();
-        let mut current_trend_start = 0;
-        let mut current_trend = if prices[1] > prices[0] { 1.0 } else { -1.0 };
-
-        for i in 1..prices.len() {
-            let trend = if prices[i] > prices[i-1] { 1.0 } else { -1.0 };
-
-            if trend != current_trend {
-                reversion_times.push(i - current_trend_start);
-                current_trend_start = i;
-                current_trend = trend;
-            }
-        }
-
-        if reversion_times.is_empty() {
-            return prices.len() as f64;
-        }
-
-        let sum: f64 = reversion_times.iter().map(|&x| x as f64).sum();
-        sum / reversion_times.len() as f64
-    }
-
-    /// Find optimal hedge ratio for pair trading
-    pub fn find_hedge_ratio(&self, asset1: &[f64], asset2: &[f64]) -> f64 {
-        if asset1.len() != asset2.len() || asset1.len() < 10 {
-            return 1.0;
-        }
-
-        let mut best_a = None;
-        for i in -3i32..=3 {
-            let a = i as f64;
-            let spread: Vec<f64> = asset1.iter().zip(asset2.iter()).
-                map(|(p1, p2)| a * p1 + p2).collect();
-            let emrt = self.calculate_emrt(&spread);
-            let current = (emrt, a);
-            best_a = Some(best_a.map_or(current, |prev: (f64, f64)| if prev.0 < current.0 { prev } else { current }));
-        }
-
-        best_a.map(|(_, a)| a).unwrap_or(1.0)
+    /// Reinforce the agent with the outcome of an executed opportunity
+    pub fn learn_from_trade(&mut self, opportunity: &ArbitrageOpportunity, trade: &TradeExecution) {
+        let reward = if trade.profit > 0.0 { 1.0 } else { -1.0 };
+        let z_score = if (1.0 - opportunity.sum_price) > 0.02 { 2.5 } else { 0.5 };
+        let momentum = 0.01; // Simplified
+        let arb_available = true;
+        let action = self.get_action(z_score, momentum, arb_available);
+        self.update(z_score, momentum, arb_available, action, reward);
     }
 }
 
@@ -181,7 +156,7 @@ impl TradingState {
             0.0
         };
 
-        let price_z_score = (z_score.clamp(-3.0, 3.0) as i32);
+        let price_z_score = z_score.clamp(-3.0, 3.0) as i32;
 
         let momentum = if prev_price > 0.0 {
             (last_price - prev_price) / prev_price
@@ -222,10 +197,15 @@ mod tests {
     }
 
     #[test]
-    fn test_emrt() {
-        let calculator = EmrtCalculator::new(10, 0.01);
-        let prices = vec![100.0, 101.0, 99.0, 100.0];
-        let emrt = calculator.calculate_emrt(&prices);
-        assert!(emrt > 0.0);
+    fn test_q_table_roundtrip() {
+        let mut optimizer = QLearningOptimizer::new(0.1, 0.1, 0.95);
+        let action = optimizer.get_action(0.5, 0.01, true);
+        optimizer.update(0.5, 0.01, true, action, 1.0);
+
+        let path = std::env::temp_dir().join(format!("q_table_{}.json", uuid::Uuid::new_v4()));
+        optimizer.save(&path).unwrap();
+        let loaded = QLearningOptimizer::load(&path).unwrap().unwrap();
+        assert_eq!(loaded.q_table, optimizer.q_table);
+        std::fs::remove_file(&path).unwrap();
     }
 }
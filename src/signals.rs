@@ -0,0 +1,214 @@
+//! External signals module
+//!
+//! Implements:
+//! 1. HMAC-SHA256 signing and verification of webhook payloads (`X-Signal-Signature: sha256=<hex>`)
+//! 2. Shared book of external signals (news sentiment, model outputs) with expiry
+//! 3. Per-market signal features used to adjust opportunity confidence
+
+use chrono::{DateTime, Duration, Utc};
+use fxhash::FxHashMap;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::sync::{Arc, Mutex};
+
+/// Header carrying the payload signature
+pub const SIGNATURE_HEADER: &str = "X-Signal-Signature";
+
+/// Environment variable holding the webhook secret (the endpoint is disabled without it)
+pub const SIGNAL_SECRET_ENV: &str = "SIGNAL_WEBHOOK_SECRET";
+
+/// Signals older than this are refused at ingestion (limits replays)
+pub const MAX_SIGNAL_AGE_SECS: i64 = 300;
+
+/// Lifetime of a signal without an explicit `ttl_secs`
+pub const DEFAULT_SIGNAL_TTL_SECS: u64 = 900;
+
+/// Maximum relative change of an opportunity's confidence from signals
+pub const SIGNAL_CONFIDENCE_WEIGHT: f64 = 0.25;
+
+/// Signal pushed by an external source
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExternalSignal {
+    pub source: String,    // Es. "news-sentiment", "model-v3"
+    pub market_id: String,
+    pub kind: String,      // Es. "sentiment", "probability_edge"
+    pub value: f64,        // In [-1, 1]: positivo favorisce il trading sul mercato
+    #[serde(default = "default_weight")]
+    pub weight: f64,       // Fiducia della sorgente, in [0, 1]
+    pub timestamp: DateTime<Utc>,
+    #[serde(default)]
+    pub ttl_secs: Option<u64>,
+}
+
+fn default_weight() -> f64 {
+    1.0
+}
+
+impl ExternalSignal {
+    /// Check ranges and freshness
+    pub fn validate(&self, now: DateTime<Utc>) -> Result<(), String> {
+        if self.source.trim().is_empty() || self.market_id.trim().is_empty() || self.kind.trim().is_empty() {
+            return Err("source, market_id and kind are required".to_string());
+        }
+        if !self.value.is_finite() || !(-1.0..=1.0).contains(&self.value) {
+            return Err(format!("value {} outside [-1, 1]", self.value));
+        }
+        if !self.weight.is_finite() || !(0.0..=1.0).contains(&self.weight) {
+            return Err(format!("weight {} outside [0, 1]", self.weight));
+        }
+        let age = now - self.timestamp;
+        if age > Duration::seconds(MAX_SIGNAL_AGE_SECS) || age < -Duration::seconds(MAX_SIGNAL_AGE_SECS) {
+            return Err(format!("timestamp {} too far from server time", self.timestamp));
+        }
+        Ok(())
+    }
+
+    pub fn expires_at(&self) -> DateTime<Utc> {
+        let ttl = self.ttl_secs.unwrap_or(DEFAULT_SIGNAL_TTL_SECS);
+        self.timestamp + Duration::seconds(ttl as i64)
+    }
+}
+
+/// Aggregated signals of one market
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct SignalFeatures {
+    pub score: f64, // Media dei valori pesata per weight, in [-1, 1]
+    pub count: usize,
+}
+
+/// Decode a lowercase or uppercase hex string
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// `sha256=<hex>` signature of a body, as sent with outbound webhooks
+pub fn sign_payload(secret: &str, body: &[u8]) -> String {
+    // HMAC accetta chiavi di qualsiasi lunghezza
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC key of any length");
+    mac.update(body);
+    let digest = mac.finalize().into_bytes();
+    let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("sha256={}", hex)
+}
+
+/// Check a `sha256=<hex>` signature of the raw body (constant-time comparison)
+pub fn verify_signature(secret: &str, body: &[u8], signature: &str) -> bool {
+    let hex = signature.trim().strip_prefix("sha256=").unwrap_or(signature.trim());
+    let Some(expected) = decode_hex(hex) else { return false };
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret.as_bytes()) else { return false };
+    mac.update(body);
+    mac.verify_slice(&expected).is_ok()
+}
+
+/// Signals shared between the webhook and the strategies, keyed by market
+///
+/// Only the latest signal per (source, kind) counts for a market.
+#[derive(Debug, Clone, Default)]
+pub struct SignalBook {
+    signals: Arc<Mutex<FxHashMap<String, Vec<ExternalSignal>>>>,
+}
+
+impl SignalBook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store a validated signal, replacing an older one from the same source and kind
+    pub fn ingest(&self, signal: ExternalSignal, now: DateTime<Utc>) -> Result<(), String> {
+        signal.validate(now)?;
+
+        let mut signals = self.signals.lock().unwrap();
+        let market = signals.entry(signal.market_id.clone()).or_default();
+        match market.iter_mut().find(|s| s.source == signal.source && s.kind == signal.kind) {
+            Some(existing) if existing.timestamp > signal.timestamp => {} // Arrivato fuori ordine
+            Some(existing) => *existing = signal,
+            None => market.push(signal),
+        }
+        Ok(())
+    }
+
+    /// Live signals of every market (expired ones are dropped)
+    pub fn active(&self, now: DateTime<Utc>) -> Vec<ExternalSignal> {
+        let mut signals = self.signals.lock().unwrap();
+        signals.retain(|_, market| {
+            market.retain(|s| s.expires_at() > now);
+            !market.is_empty()
+        });
+        signals.values().flatten().cloned().collect()
+    }
+
+    /// Weighted features of a market's live signals
+    pub fn features(&self, market_id: &str, now: DateTime<Utc>) -> Option<SignalFeatures> {
+        let signals = self.signals.lock().unwrap();
+        let live: Vec<&ExternalSignal> = signals.get(market_id)?.iter().filter(|s| s.expires_at() > now).collect();
+        let total_weight: f64 = live.iter().map(|s| s.weight).sum();
+        if total_weight <= 0.0 {
+            return None;
+        }
+        Some(SignalFeatures {
+            score: live.iter().map(|s| s.value * s.weight).sum::<f64>() / total_weight,
+            count: live.len(),
+        })
+    }
+
+    /// Confidence of an opportunity on `market_id` adjusted by its signals
+    pub fn adjust_confidence(&self, market_id: &str, confidence: f64, now: DateTime<Utc>) -> f64 {
+        match self.features(market_id, now) {
+            Some(features) => (confidence * (1.0 + SIGNAL_CONFIDENCE_WEIGHT * features.score)).clamp(0.0, 1.0),
+            None => confidence,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signal(source: &str, value: f64, weight: f64, now: DateTime<Utc>) -> ExternalSignal {
+        ExternalSignal {
+            source: source.to_string(),
+            market_id: "m1".to_string(),
+            kind: "sentiment".to_string(),
+            value,
+            weight,
+            timestamp: now,
+            ttl_secs: Some(60),
+        }
+    }
+
+    #[test]
+    fn test_signature_and_features() {
+        let body = br#"{"market_id":"m1"}"#;
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"topsecret").unwrap();
+        mac.update(body);
+        let hex: String = mac.finalize().into_bytes().iter().map(|b| format!("{:02x}", b)).collect();
+        assert!(verify_signature("topsecret", body, &format!("sha256={}", hex)));
+        assert!(!verify_signature("othersecret", body, &format!("sha256={}", hex)));
+        assert!(!verify_signature("topsecret", body, "sha256=zz"));
+
+        let now = Utc::now();
+        let book = SignalBook::new();
+        book.ingest(signal("news", 1.0, 1.0, now), now).unwrap();
+        book.ingest(signal("model", -0.5, 0.5, now), now).unwrap();
+        book.ingest(signal("news", 0.5, 1.0, now - Duration::seconds(10)), now).unwrap(); // Più vecchio: ignorato
+        assert!(book.ingest(signal("news", 2.0, 1.0, now), now).is_err());
+        assert!(book.ingest(signal("news", 0.5, 1.0, now - Duration::hours(1)), now).is_err());
+
+        let features = book.features("m1", now).unwrap();
+        assert_eq!(features.count, 2);
+        assert!((features.score - 0.5).abs() < 1e-9);
+        assert!((book.adjust_confidence("m1", 0.8, now) - 0.9).abs() < 1e-9);
+        assert_eq!(book.adjust_confidence("m2", 0.8, now), 0.8);
+
+        // Scaduti dopo il ttl
+        assert!(book.active(now + Duration::seconds(120)).is_empty());
+        assert!(book.features("m1", now + Duration::seconds(120)).is_none());
+    }
+}
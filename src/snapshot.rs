@@ -0,0 +1,353 @@
+//! Portfolio snapshot module
+//!
+//! Implements:
+//! 1. Complete snapshot of capital, open positions, resting conditional orders and risk state
+//! 2. HMAC-SHA256 signature over the canonical JSON, so a snapshot handed over can be checked
+//! 3. Diff between two snapshots: capital moves, opened/closed/resized positions, orders and
+//!    exposure changes, for shift handovers and incident reviews
+//! 4. Schema version carried by each snapshot, migrated when an older snapshot is parsed
+
+use crate::paper::{BotState, ExitReason, PaperBroker, PaperRiskLimits};
+use crate::risk::DrawdownEpisode;
+use crate::signals::verify_signature;
+use crate::types::TokenType;
+use crate::versioning::Schema;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Environment variable holding the snapshot signing secret; unset leaves snapshots unsigned
+pub const SNAPSHOT_SECRET_ENV: &str = "SNAPSHOT_SIGNING_SECRET";
+
+/// Quantities closer than this are the same position size
+const QUANTITY_EPSILON: f64 = 1e-9;
+
+/// Schema of portfolio snapshots
+pub const SNAPSHOT_SCHEMA: Schema = Schema::new("portfolio_snapshot", &[]);
+
+/// Open position as held at snapshot time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotPosition {
+    pub market_id: String,
+    pub token_type: TokenType,
+    pub quantity: f64,
+    pub avg_price: f64,
+    pub cost_basis: f64,
+    pub opened_at: DateTime<Utc>,
+}
+
+/// Resting conditional order: the stop-loss or take-profit of an open position
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotOrder {
+    pub market_id: String,
+    pub token_type: TokenType,
+    pub kind: ExitReason,
+    pub trigger_price: f64,
+    pub quantity: f64,
+}
+
+/// Risk limits and how much of them is used
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RiskSnapshot {
+    pub limits: PaperRiskLimits,
+    pub deployed_capital: f64,
+    pub exposure: BTreeMap<String, f64>, // Costo delle posizioni per mercato
+    pub drawdown: Option<DrawdownEpisode>, // Episodio di drawdown in corso
+    pub max_drawdown_pct: f64,
+}
+
+/// Complete portfolio state at one instant, optionally signed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PortfolioSnapshot {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema_version: Option<u32>, // Assente negli snapshot precedenti al versioning, la cui firma non lo copre
+    pub taken_at: DateTime<Utc>,
+    pub running: bool,
+    pub balance: f64,
+    pub initial_balance: f64,
+    pub total_pnl: f64,
+    pub total_trades: usize,
+    pub positions: Vec<SnapshotPosition>,
+    pub open_orders: Vec<SnapshotOrder>,
+    pub risk: RiskSnapshot,
+    #[serde(default)]
+    pub signature: Option<String>, // "sha256=<hex>" del JSON canonico senza firma
+}
+
+impl PortfolioSnapshot {
+    /// Snapshot of the bot state and the paper broker at `now`
+    pub fn capture(state: &BotState, broker: &PaperBroker, now: DateTime<Utc>) -> Self {
+        let open = broker.open_positions();
+        let positions = open.iter()
+            .map(|p| SnapshotPosition {
+                market_id: p.market_id.clone(),
+                token_type: p.token_type,
+                quantity: p.quantity,
+                avg_price: p.avg_price,
+                cost_basis: p.cost_basis(),
+                opened_at: p.opened_at,
+            })
+            .collect();
+
+        let mut open_orders = Vec::new();
+        for position in &open {
+            let levels = [(ExitReason::StopLoss, position.stop_loss), (ExitReason::TakeProfit, position.take_profit)];
+            for (kind, trigger) in levels {
+                if let Some(trigger_price) = trigger {
+                    open_orders.push(SnapshotOrder {
+                        market_id: position.market_id.clone(),
+                        token_type: position.token_type,
+                        kind,
+                        trigger_price,
+                        quantity: position.quantity,
+                    });
+                }
+            }
+        }
+
+        let mut exposure = BTreeMap::new();
+        for position in &open {
+            *exposure.entry(position.market_id.clone()).or_insert(0.0) += position.cost_basis();
+        }
+
+        Self {
+            schema_version: Some(SNAPSHOT_SCHEMA.version()),
+            taken_at: now,
+            running: state.running,
+            balance: state.balance,
+            initial_balance: state.initial_balance,
+            total_pnl: state.total_pnl,
+            total_trades: state.total_trades,
+            positions,
+            open_orders,
+            risk: RiskSnapshot {
+                limits: broker.limits.clone(),
+                deployed_capital: broker.deployed_capital(),
+                exposure,
+                drawdown: broker.drawdowns.current.clone(),
+                max_drawdown_pct: broker.drawdowns.report(0).max_depth_pct,
+            },
+            signature: None,
+        }
+    }
+
+    /// Parse a snapshot saved by this or an older version
+    ///
+    /// Migration rewrites the snapshot: verify the signature of an older one on the form it was
+    /// received in.
+    pub fn from_json(content: &str) -> Result<Self, String> {
+        let value: serde_json::Value = serde_json::from_str(content)
+            .map_err(|e| format!("Failed to parse snapshot: {}", e))?;
+        let version = value.get("schema_version").and_then(|v| v.as_u64()).map_or(1, |v| v as u32);
+        let value = SNAPSHOT_SCHEMA.migrate(version, value)?;
+        let mut snapshot: Self = serde_json::from_value(value)
+            .map_err(|e| format!("Failed to parse snapshot: {}", e))?;
+        if version < SNAPSHOT_SCHEMA.version() {
+            snapshot.schema_version = Some(SNAPSHOT_SCHEMA.version());
+        }
+        Ok(snapshot)
+    }
+
+    /// Whether this build can read the snapshot as it is, without migration
+    pub fn check_version(&self) -> Result<(), String> {
+        SNAPSHOT_SCHEMA.check_version(self.schema_version.unwrap_or(1))
+    }
+
+    /// JSON the signature covers: the snapshot without its signature
+    fn canonical(&self) -> Result<Vec<u8>, String> {
+        let unsigned = Self { signature: None, ..self.clone() };
+        serde_json::to_vec(&unsigned).map_err(|e| format!("Failed to serialize snapshot: {}", e))
+    }
+
+    /// Sign the snapshot with `secret`
+    pub fn sign(mut self, secret: &str) -> Result<Self, String> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+            .map_err(|e| format!("Invalid snapshot secret: {}", e))?;
+        mac.update(&self.canonical()?);
+        let hex: String = mac.finalize().into_bytes().iter().map(|b| format!("{:02x}", b)).collect();
+        self.signature = Some(format!("sha256={}", hex));
+        Ok(self)
+    }
+
+    /// Whether the snapshot carries a valid signature for `secret`
+    pub fn verify(&self, secret: &str) -> bool {
+        let Some(signature) = &self.signature else { return false };
+        self.canonical().is_ok_and(|body| verify_signature(secret, &body, signature))
+    }
+
+    /// Changes from this snapshot to a `later` one
+    pub fn diff(&self, later: &PortfolioSnapshot) -> SnapshotDiff {
+        let key = |p: &SnapshotPosition| (p.market_id.clone(), p.token_type.to_string());
+        let before: BTreeMap<_, &SnapshotPosition> = self.positions.iter().map(|p| (key(p), p)).collect();
+        let after: BTreeMap<_, &SnapshotPosition> = later.positions.iter().map(|p| (key(p), p)).collect();
+
+        let keys: BTreeSet<_> = before.keys().chain(after.keys()).collect();
+        let mut positions = Vec::new();
+        for k in keys {
+            let (old, new) = (before.get(k), after.get(k));
+            let quantity_before = old.map_or(0.0, |p| p.quantity);
+            let quantity_after = new.map_or(0.0, |p| p.quantity);
+            let kind = match (old, new) {
+                (None, Some(_)) => PositionChangeKind::Opened,
+                (Some(_), None) => PositionChangeKind::Closed,
+                _ if (quantity_after - quantity_before).abs() > QUANTITY_EPSILON => PositionChangeKind::Resized,
+                _ => continue,
+            };
+            let position = new.or(old).unwrap();
+            positions.push(PositionChange {
+                market_id: position.market_id.clone(),
+                token_type: position.token_type,
+                kind,
+                quantity_before,
+                quantity_after,
+                cost_basis_change: new.map_or(0.0, |p| p.cost_basis) - old.map_or(0.0, |p| p.cost_basis),
+            });
+        }
+
+        let mut exposure_changes = BTreeMap::new();
+        for market_id in self.risk.exposure.keys().chain(later.risk.exposure.keys()) {
+            let change = later.risk.exposure.get(market_id).copied().unwrap_or(0.0)
+                - self.risk.exposure.get(market_id).copied().unwrap_or(0.0);
+            if change.abs() > QUANTITY_EPSILON {
+                exposure_changes.insert(market_id.clone(), change);
+            }
+        }
+
+        SnapshotDiff {
+            from: self.taken_at,
+            to: later.taken_at,
+            elapsed_secs: (later.taken_at - self.taken_at).num_seconds(),
+            balance_change: later.balance - self.balance,
+            pnl_change: later.total_pnl - self.total_pnl,
+            trades_change: later.total_trades as i64 - self.total_trades as i64,
+            deployed_change: later.risk.deployed_capital - self.risk.deployed_capital,
+            positions,
+            orders_added: later.open_orders.iter().filter(|o| !self.open_orders.contains(o)).cloned().collect(),
+            orders_removed: self.open_orders.iter().filter(|o| !later.open_orders.contains(o)).cloned().collect(),
+            exposure_changes,
+            limits_changed: self.risk.limits != later.risk.limits,
+            running_changed: self.running != later.running,
+            drawdown_pct_before: self.risk.drawdown.as_ref().map(|d| d.depth_pct),
+            drawdown_pct_after: later.risk.drawdown.as_ref().map(|d| d.depth_pct),
+        }
+    }
+}
+
+/// How a position moved between two snapshots
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PositionChangeKind {
+    Opened,
+    Closed,
+    Resized,
+}
+
+/// Position that changed between two snapshots
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PositionChange {
+    pub market_id: String,
+    pub token_type: TokenType,
+    pub kind: PositionChangeKind,
+    pub quantity_before: f64,
+    pub quantity_after: f64,
+    pub cost_basis_change: f64,
+}
+
+/// Differences between two portfolio snapshots
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotDiff {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub elapsed_secs: i64,
+    pub balance_change: f64,
+    pub pnl_change: f64,
+    pub trades_change: i64,
+    pub deployed_change: f64,
+    pub positions: Vec<PositionChange>,
+    pub orders_added: Vec<SnapshotOrder>,
+    pub orders_removed: Vec<SnapshotOrder>,
+    pub exposure_changes: BTreeMap<String, f64>, // Solo i mercati con esposizione variata
+    pub limits_changed: bool,
+    pub running_changed: bool,
+    pub drawdown_pct_before: Option<f64>,
+    pub drawdown_pct_after: Option<f64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paper::{MarketInfo, PaperOrder, TradeSource};
+    use crate::types::Direction;
+
+    #[test]
+    fn test_signed_snapshots_round_trip_and_diff() {
+        let mut state = BotState {
+            running: true,
+            balance: 10_000.0,
+            initial_balance: 10_000.0,
+            total_pnl: 0.0,
+            win_rate: 0.0,
+            total_trades: 0,
+            profitable_trades: 0,
+            last_update: Utc::now(),
+        };
+        let mut broker = PaperBroker::default();
+        let market = |id: &str| MarketInfo {
+            id: id.to_string(),
+            question: String::new(),
+            yes_price: 0.40,
+            no_price: 0.60,
+            yes_liquidity: 10_000.0,
+            no_liquidity: 10_000.0,
+            volume_24h: 0.0,
+            timestamp: Utc::now(),
+            event_id: None,
+            category: None,
+        };
+        let buy = |market_id: &str, quantity: f64| PaperOrder {
+            market_id: market_id.to_string(),
+            token_type: TokenType::Yes,
+            direction: Direction::Buy,
+            quantity,
+            limit_price: None,
+            source: TradeSource::Manual,
+        };
+        broker.execute(&mut state, &buy("a", 100.0), &market("a")).unwrap();
+        broker.execute(&mut state, &buy("b", 50.0), &market("b")).unwrap();
+        broker.set_exit_levels("a", TokenType::Yes, Some(0.30), None).unwrap();
+
+        let secret = "handover";
+        let before = PortfolioSnapshot::capture(&state, &broker, Utc::now()).sign(secret).unwrap();
+        assert_eq!(before.open_orders.len(), 1);
+        assert_eq!(before.risk.exposure.len(), 2);
+
+        // La firma sopravvive al passaggio in JSON e rileva le manomissioni
+        let json = serde_json::to_string(&before).unwrap();
+        let received = PortfolioSnapshot::from_json(&json).unwrap();
+        assert!(received.verify(secret));
+        assert!(!received.verify("other"));
+        let tampered = PortfolioSnapshot { balance: received.balance + 1.0, ..received.clone() };
+        assert!(!tampered.verify(secret));
+        let newer = json.replace(&format!("\"schema_version\":{}", SNAPSHOT_SCHEMA.version()), "\"schema_version\":99");
+        assert!(PortfolioSnapshot::from_json(&newer).unwrap_err().contains("upgrade the crate"));
+
+        // Cambio turno: "a" aumentata con nuovo stop, "b" chiusa, "c" aperta
+        broker.execute(&mut state, &buy("a", 20.0), &market("a")).unwrap();
+        broker.set_exit_levels("a", TokenType::Yes, Some(0.35), None).unwrap();
+        broker.positions.retain(|_, p| p.market_id != "b");
+        broker.execute(&mut state, &buy("c", 10.0), &market("c")).unwrap();
+        let after = PortfolioSnapshot::capture(&state, &broker, before.taken_at + chrono::Duration::hours(8));
+
+        let diff = received.diff(&after);
+        let changes: Vec<(&str, PositionChangeKind)> = diff.positions.iter().map(|c| (c.market_id.as_str(), c.kind)).collect();
+        assert_eq!(changes, vec![("a", PositionChangeKind::Resized), ("b", PositionChangeKind::Closed), ("c", PositionChangeKind::Opened)]);
+        assert_eq!((diff.positions[0].quantity_before, diff.positions[0].quantity_after), (100.0, 120.0));
+        assert_eq!((diff.orders_added[0].trigger_price, diff.orders_removed[0].trigger_price), (0.35, 0.30));
+        assert_eq!(diff.elapsed_secs, 8 * 3600);
+        assert!(diff.balance_change < 0.0);
+        assert_eq!(diff.exposure_changes.len(), 3);
+        assert!(!diff.limits_changed);
+    }
+}
@@ -0,0 +1,327 @@
+//! Soak test module
+//!
+//! Implements:
+//! 1. Long run of the full simulation pipeline on simulated markets, paced in real time
+//! 2. Periodic self-checks: bounded memory, ledger balance, no stuck orders or executions, sane metrics
+//! 3. Health report rewritten after every check, so an interrupted soak still leaves its last state
+
+use crate::types::BotConfig;
+use crate::HftArbitrageBot;
+use chrono::{DateTime, Utc};
+use fxhash::FxHashSet;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Default location of the soak health report
+pub const SOAK_REPORT_PATH: &str = "./data/soak_report.json";
+
+/// Violations kept in the report; later ones are only counted
+const MAX_REPORTED_VIOLATIONS: usize = 200;
+
+/// Tolerance of the ledger check, in USDC
+const LEDGER_TOLERANCE: f64 = 1e-6;
+
+/// Soak run parameters
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SoakConfig {
+    pub duration_secs: u64,
+    pub step_interval_ms: u64,
+    pub check_interval_secs: u64,
+    pub max_rss_growth_mb: f64, // Crescita massima della memoria residente dal primo controllo
+    pub report_path: PathBuf,
+}
+
+impl Default for SoakConfig {
+    fn default() -> Self {
+        Self {
+            duration_secs: 72 * 3600,
+            step_interval_ms: 1000,
+            check_interval_secs: 300,
+            max_rss_growth_mb: 256.0,
+            report_path: PathBuf::from(SOAK_REPORT_PATH),
+        }
+    }
+}
+
+impl SoakConfig {
+    /// Parse `--hours 72 --step-ms 1000 --check-secs 300 --max-rss-growth-mb 256 --report path`
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let mut config = Self::default();
+        let mut iter = args.iter();
+
+        while let Some(flag) = iter.next() {
+            let value = iter.next().ok_or_else(|| format!("Missing value for {}", flag))?;
+            let invalid = || format!("Invalid value for {}: {}", flag, value);
+            match flag.as_str() {
+                "--hours" => config.duration_secs = (value.parse::<f64>().map_err(|_| invalid())? * 3600.0) as u64,
+                "--step-ms" => config.step_interval_ms = value.parse().map_err(|_| invalid())?,
+                "--check-secs" => config.check_interval_secs = value.parse().map_err(|_| invalid())?,
+                "--max-rss-growth-mb" => config.max_rss_growth_mb = value.parse().map_err(|_| invalid())?,
+                "--report" => config.report_path = PathBuf::from(value),
+                _ => return Err(format!("Unknown option {}", flag)),
+            }
+        }
+
+        if config.duration_secs == 0 || config.step_interval_ms == 0 || config.check_interval_secs == 0 {
+            return Err("duration, step interval and check interval must be positive".to_string());
+        }
+        Ok(config)
+    }
+}
+
+/// State of the pipeline at one self-check
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SoakCheck {
+    pub at: DateTime<Utc>,
+    pub elapsed_secs: u64,
+    pub steps: u64,
+    pub rss_mb: Option<f64>, // None dove /proc non è disponibile
+    pub capital: f64,
+    pub expected_capital: f64, // Capitale iniziale più il PnL contabilizzato step per step
+    pub trades: usize,
+    pub open_orders: usize,
+    pub price_snapshots: usize,
+    pub violations: Vec<String>,
+}
+
+/// Invariant broken at a self-check
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SoakViolation {
+    pub at: DateTime<Utc>,
+    pub step: u64,
+    pub message: String,
+}
+
+/// Health report of a soak run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SoakReport {
+    pub started_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub elapsed_secs: u64,
+    pub steps: u64,
+    pub step_errors: u64,
+    pub checks: u64,
+    pub baseline_rss_mb: Option<f64>,
+    pub peak_rss_mb: Option<f64>,
+    pub violation_count: u64,
+    pub violations: Vec<SoakViolation>, // Le prime MAX_REPORTED_VIOLATIONS
+    pub last_check: Option<SoakCheck>,
+    pub completed: bool,
+}
+
+impl SoakReport {
+    fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            started_at: now,
+            updated_at: now,
+            elapsed_secs: 0,
+            steps: 0,
+            step_errors: 0,
+            checks: 0,
+            baseline_rss_mb: None,
+            peak_rss_mb: None,
+            violation_count: 0,
+            violations: Vec::new(),
+            last_check: None,
+            completed: false,
+        }
+    }
+
+    /// No invariant was broken
+    pub fn healthy(&self) -> bool {
+        self.violation_count == 0
+    }
+
+    /// Write the report to `path`, creating its directory
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create soak report directory: {}", e))?;
+        }
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize soak report: {}", e))?;
+        std::fs::write(path, content)
+            .map_err(|e| format!("Failed to write soak report: {}", e))
+    }
+}
+
+/// Resident memory of the process, from /proc/self/status
+fn resident_memory_mb() -> Option<f64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|l| l.starts_with("VmRSS:"))?;
+    let kb: f64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb / 1024.0)
+}
+
+/// Invariant checks, with the state carried between them
+struct SoakChecker {
+    max_rss_growth_mb: f64,
+    baseline_rss_mb: Option<f64>,
+    open_orders: FxHashSet<String>, // Ordini aperti al controllo precedente
+}
+
+impl SoakChecker {
+    fn check(&mut self, bot: &HftArbitrageBot, expected_capital: f64, elapsed_secs: u64, now: DateTime<Utc>) -> SoakCheck {
+        let mut violations = Vec::new();
+
+        // Memoria: la crescita dal primo controllo resta nel budget
+        let rss_mb = resident_memory_mb();
+        if let Some(rss) = rss_mb {
+            let baseline = *self.baseline_rss_mb.get_or_insert(rss);
+            if rss - baseline > self.max_rss_growth_mb {
+                violations.push(format!("resident memory grew {:.1} MB (budget {:.1} MB)", rss - baseline, self.max_rss_growth_mb));
+            }
+        }
+
+        // Ledger: il capitale è il capitale iniziale più il PnL contabilizzato
+        if (bot.capital - expected_capital).abs() > LEDGER_TOLERANCE {
+            violations.push(format!("capital {:.6} differs from ledger {:.6}", bot.capital, expected_capital));
+        }
+
+        // Ordini ed esecuzioni: nessuno resta aperto da un controllo all'altro
+        let open_orders: FxHashSet<String> = bot.executor.open_orders().map(|o| o.order_id.clone()).collect();
+        let stuck = open_orders.intersection(&self.open_orders).count();
+        if stuck > 0 {
+            violations.push(format!("{} orders open since the previous check", stuck));
+        }
+        let open_executions = bot.executor.journal.open_executions().len();
+        if open_executions > 0 {
+            violations.push(format!("{} executions left half-completed", open_executions));
+        }
+
+        // Metriche: valori finiti e nei rispettivi intervalli
+        let rolling = bot.trade_metrics.rolling();
+        let risk = &bot.risk_manager.metrics;
+        if !bot.capital.is_finite() || bot.capital < 0.0 {
+            violations.push(format!("capital {} is not a finite non-negative amount", bot.capital));
+        }
+        if !(0.0..=1.0).contains(&rolling.win_rate) {
+            violations.push(format!("win rate {} outside [0, 1]", rolling.win_rate));
+        }
+        if !rolling.avg_edge.is_finite() || !rolling.expectancy.is_finite() {
+            violations.push("trade metrics are not finite".to_string());
+        }
+        if !risk.current_drawdown.is_finite() || !risk.var_95.is_finite() || !risk.sharpe_ratio.is_finite() {
+            violations.push("risk metrics are not finite".to_string());
+        }
+
+        self.open_orders = open_orders;
+        SoakCheck {
+            at: now,
+            elapsed_secs,
+            steps: bot.current_step,
+            rss_mb,
+            capital: bot.capital,
+            expected_capital,
+            trades: bot.executor.executed_trades.len(),
+            open_orders: self.open_orders.len(),
+            price_snapshots: bot.market_manager.price_history.values().map(Vec::len).sum(),
+            violations,
+        }
+    }
+}
+
+/// Run the full pipeline on simulated markets for `config.duration_secs`, checking invariants periodically
+///
+/// The health report is rewritten at every check and at the end of the run.
+pub async fn run_soak(config: &SoakConfig) -> SoakReport {
+    run_soak_with(HftArbitrageBot::new(BotConfig::default()), config).await
+}
+
+/// Soak run of an already configured bot
+pub async fn run_soak_with(mut bot: HftArbitrageBot, config: &SoakConfig) -> SoakReport {
+    if let Err(e) = bot.market_manager.fetch_markets().await {
+        eprintln!("⚠️  Soak: mercati simulati non generati: {}", e);
+    }
+
+    let started = Instant::now();
+    let deadline = started + Duration::from_secs(config.duration_secs);
+    let interval = Duration::from_millis(config.step_interval_ms.max(1));
+    let check_every = Duration::from_secs_f64(config.check_interval_secs.max(1) as f64);
+    let mut next_check = started + check_every;
+    let mut report = SoakReport::new(bot.clock.now());
+    let mut checker = SoakChecker { max_rss_growth_mb: config.max_rss_growth_mb, baseline_rss_mb: None, open_orders: FxHashSet::default() };
+    let mut expected_capital = bot.capital;
+
+    loop {
+        let now = Instant::now();
+        let finished = now >= deadline;
+        if finished || now >= next_check {
+            let check = checker.check(&bot, expected_capital, started.elapsed().as_secs(), bot.clock.now());
+            for message in &check.violations {
+                eprintln!("🚨 Soak step {}: {}", check.steps, message);
+                report.violation_count += 1;
+                if report.violations.len() < MAX_REPORTED_VIOLATIONS {
+                    report.violations.push(SoakViolation { at: check.at, step: check.steps, message: message.clone() });
+                }
+            }
+            report.checks += 1;
+            report.baseline_rss_mb = checker.baseline_rss_mb;
+            report.peak_rss_mb = match (report.peak_rss_mb, check.rss_mb) {
+                (Some(peak), Some(rss)) => Some(peak.max(rss)),
+                (peak, rss) => peak.or(rss),
+            };
+            report.updated_at = check.at;
+            report.elapsed_secs = check.elapsed_secs;
+            report.steps = bot.current_step;
+            report.last_check = Some(check);
+            report.completed = finished;
+            if let Err(e) = report.save(&config.report_path) {
+                eprintln!("⚠️  {}", e);
+            }
+            next_check += check_every;
+        }
+        if finished {
+            break;
+        }
+
+        let next_step = Instant::now() + interval;
+        let (result, booked) = bot.pipeline_step().await;
+        if result.is_none() {
+            report.step_errors += 1;
+        }
+        expected_capital += booked;
+        tokio::time::sleep_until(next_step.min(deadline).into()).await;
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_soak_checks_invariants_and_writes_report() {
+        let args: Vec<String> = ["--hours", "0.5", "--check-secs", "60"].iter().map(|s| s.to_string()).collect();
+        let parsed = SoakConfig::from_args(&args).unwrap();
+        assert_eq!((parsed.duration_secs, parsed.check_interval_secs), (1800, 60));
+        assert!(SoakConfig::from_args(&["--step-ms".to_string(), "0".to_string()]).is_err());
+
+        let dir = std::env::temp_dir().join(format!("soak_{}", std::process::id()));
+        let config = SoakConfig {
+            duration_secs: 2,
+            step_interval_ms: 20,
+            check_interval_secs: 1,
+            max_rss_growth_mb: 1024.0,
+            report_path: dir.join("report.json"),
+        };
+        let report = run_soak(&config).await;
+        assert!(report.completed);
+        assert!(report.steps > 10);
+        assert!(report.checks >= 2);
+        assert!(report.healthy(), "{:?}", report.violations);
+        let last = report.last_check.as_ref().unwrap();
+        assert!((last.capital - last.expected_capital).abs() < 1e-6);
+
+        let saved: SoakReport = serde_json::from_str(&std::fs::read_to_string(&config.report_path).unwrap()).unwrap();
+        assert_eq!((saved.steps, saved.completed), (report.steps, true));
+
+        // Un capitale che si discosta dal ledger è una violazione
+        let bot = HftArbitrageBot::new(BotConfig::default());
+        let mut checker = SoakChecker { max_rss_growth_mb: 1024.0, baseline_rss_mb: None, open_orders: FxHashSet::default() };
+        let check = checker.check(&bot, bot.capital + 1.0, 0, Utc::now());
+        assert_eq!(check.violations.len(), 1);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,943 @@
+//! Statistical arbitrage position management
+//!
+//! Implements:
+//! 1. Mean-reversion entries on the z-score of a market's YES price against its recent history
+//! 2. Trailing exits: z-score re-crossing zero, time stop after an EMRT-derived horizon, trailing PnL stop
+//! 3. Position-management loop evaluating every open position each step
+//! 4. EMRT (Empirical Mean Reversion Time) for mean reversion detection
+//! 5. Pair detector: rolling correlation and Engle-Granger cointegration between related markets,
+//!    spread z-score signals and `StatisticalArb` opportunities hedged with the EMRT ratio
+//! 6. Executed pair trades held as spread positions, with PnL realized when the spread reverts
+
+use crate::market::PriceSnapshot;
+use crate::types::{ArbType, ArbitrageLeg, ArbitrageOpportunity, Direction, MarketData, TokenType, TradeExecution};
+use chrono::{DateTime, Duration, Utc};
+use fxhash::{FxHashMap, FxHashSet};
+use serde::{Deserialize, Serialize};
+
+/// EMRT (Empirical Mean Reversion Time) Calculator
+pub struct EmrtCalculator {
+    pub window: usize,
+    pub threshold: f64,
+}
+
+impl EmrtCalculator {
+    pub fn new(window: usize, threshold: f64) -> Self {
+        Self { window, threshold }
+    }
+
+    /// Calculate EMRT for a price series
+    pub fn calculate_emrt(&self, prices: &[f64]) -> f64 {
+        if prices.len() < 2 {
+            return 0.0;
+        }
+
+        let mut reversion_times = Vec::new();
+        let mut current_trend_start = 0;
+        let mut current_trend = if prices[1] > prices[0] { 1.0 } else { -1.0 };
+
+        for i in 1..prices.len() {
+            let trend = if prices[i] > prices[i-1] { 1.0 } else { -1.0 };
+
+            if trend != current_trend {
+                reversion_times.push(i - current_trend_start);
+                current_trend_start = i;
+                current_trend = trend;
+            }
+        }
+
+        if reversion_times.is_empty() {
+            return prices.len() as f64;
+        }
+
+        let sum: f64 = reversion_times.iter().map(|&x| x as f64).sum();
+        sum / reversion_times.len() as f64
+    }
+
+    /// Find optimal hedge ratio for pair trading
+    pub fn find_hedge_ratio(&self, asset1: &[f64], asset2: &[f64]) -> f64 {
+        if asset1.len() != asset2.len() || asset1.len() < 10 {
+            return 1.0;
+        }
+
+        let mut best_a = None;
+        for i in -3i32..=3 {
+            let a = i as f64;
+            let spread: Vec<f64> = asset1.iter().zip(asset2.iter()).
+                map(|(p1, p2)| a * p1 + p2).collect();
+            let emrt = self.calculate_emrt(&spread);
+            let current = (emrt, a);
+            best_a = Some(best_a.map_or(current, |prev: (f64, f64)| if prev.0 < current.0 { prev } else { current }));
+        }
+
+        best_a.map(|(_, a)| a).unwrap_or(1.0)
+    }
+}
+
+fn default_lookback() -> usize {
+    50
+}
+
+fn default_entry_z() -> f64 {
+    2.0
+}
+
+fn default_position_fraction() -> f64 {
+    0.02
+}
+
+fn default_max_positions() -> usize {
+    5
+}
+
+fn default_emrt_multiple() -> f64 {
+    2.0
+}
+
+fn default_min_horizon_secs() -> i64 {
+    60
+}
+
+fn default_trailing_activation() -> f64 {
+    0.01
+}
+
+fn default_trailing_giveback() -> f64 {
+    0.5
+}
+
+/// Entry and exit rules of the mean-reversion strategy
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatArbConfig {
+    #[serde(default = "default_lookback")]
+    pub lookback: usize, // Snapshot usati per media, deviazione standard ed EMRT
+    #[serde(default = "default_entry_z")]
+    pub entry_z: f64,
+    #[serde(default = "default_position_fraction")]
+    pub position_fraction: f64, // Quota del capitale per posizione
+    #[serde(default = "default_max_positions")]
+    pub max_positions: usize,
+    #[serde(default = "default_emrt_multiple")]
+    pub emrt_multiple: f64, // Orizzonte del time stop in multipli dell'EMRT
+    #[serde(default = "default_min_horizon_secs")]
+    pub min_horizon_secs: i64,
+    #[serde(default = "default_trailing_activation")]
+    pub trailing_activation: f64, // PnL di picco (quota del notional) oltre cui il trailing stop è attivo
+    #[serde(default = "default_trailing_giveback")]
+    pub trailing_giveback: f64, // Quota del PnL di picco restituibile prima dell'uscita
+}
+
+impl Default for StatArbConfig {
+    fn default() -> Self {
+        Self {
+            lookback: default_lookback(),
+            entry_z: default_entry_z(),
+            position_fraction: default_position_fraction(),
+            max_positions: default_max_positions(),
+            emrt_multiple: default_emrt_multiple(),
+            min_horizon_secs: default_min_horizon_secs(),
+            trailing_activation: default_trailing_activation(),
+            trailing_giveback: default_trailing_giveback(),
+        }
+    }
+}
+
+/// Why a mean-reversion position was closed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatArbExit {
+    ZeroCross,    // Lo z-score è tornato oltre la media
+    TimeStop,     // Nessuna reversione entro l'orizzonte EMRT
+    TrailingStop, // Restituita troppa parte del PnL di picco
+}
+
+/// Open mean-reversion position on one outcome token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatArbPosition {
+    pub market_id: String,
+    pub token_type: TokenType, // YES se il prezzo era sotto la media, NO se sopra
+    pub quantity: f64,
+    pub entry_price: f64,
+    pub entry_z: f64,
+    pub opened_at: DateTime<Utc>,
+    pub horizon_secs: i64,
+    pub peak_pnl: f64,
+    pub last_price: f64,
+}
+
+impl StatArbPosition {
+    pub fn notional(&self) -> f64 {
+        self.quantity * self.entry_price
+    }
+
+    pub fn pnl(&self, price: f64) -> f64 {
+        (price - self.entry_price) * self.quantity
+    }
+
+    /// Exit rule triggered at `price` with the YES z-score `z`, if any
+    pub fn exit_signal(&self, price: f64, z: f64, now: DateTime<Utc>, config: &StatArbConfig) -> Option<StatArbExit> {
+        let crossed = if self.entry_z < 0.0 { z >= 0.0 } else { z <= 0.0 };
+        let held = now - self.opened_at;
+        exit_rule(crossed, self.pnl(price), self.peak_pnl, self.notional(), held >= Duration::seconds(self.horizon_secs), config)
+    }
+}
+
+/// Exit rules shared by single-token and spread positions, in priority order
+fn exit_rule(crossed: bool, pnl: f64, peak_pnl: f64, notional: f64, expired: bool, config: &StatArbConfig) -> Option<StatArbExit> {
+    let trailing_armed = peak_pnl >= config.trailing_activation * notional;
+    if crossed {
+        Some(StatArbExit::ZeroCross)
+    } else if trailing_armed && pnl <= peak_pnl * (1.0 - config.trailing_giveback) {
+        Some(StatArbExit::TrailingStop)
+    } else if expired {
+        Some(StatArbExit::TimeStop)
+    } else {
+        None
+    }
+}
+
+/// Closed mean-reversion position
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClosedStatArb {
+    pub position: StatArbPosition,
+    pub exit_price: f64,
+    pub exit_reason: StatArbExit,
+    pub pnl: f64,
+    pub closed_at: DateTime<Utc>,
+}
+
+/// Open position on the spread of a cointegrated pair, entered through the executor
+///
+/// The legs are held until the spread is back at its mean, i.e. until they have gained the
+/// reversion expected at detection; PnL is realized only then, or on a trailing or time stop.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpreadPosition {
+    pub trade_id: String,
+    pub legs: Vec<ArbitrageLeg>, // Leg eseguiti, ai prezzi di fill
+    pub target_pnl: f64,         // Reversione attesa dello spread fino alla media
+    pub opened_at: DateTime<Utc>,
+    pub horizon_secs: i64,
+    pub peak_pnl: f64,
+    pub last_pnl: f64,
+}
+
+impl SpreadPosition {
+    pub fn notional(&self) -> f64 {
+        self.legs.iter().map(|l| l.price * l.quantity).sum()
+    }
+
+    /// Mark-to-market PnL at the last snapshot of each leg's market (None if one is missing)
+    pub fn pnl(&self, histories: &FxHashMap<String, Vec<PriceSnapshot>>) -> Option<f64> {
+        self.legs.iter().try_fold(0.0, |pnl, leg| {
+            let last = histories.get(&leg.market_id)?.last()?;
+            let price = match leg.token_type {
+                TokenType::Yes => last.yes_price,
+                TokenType::No => last.no_price,
+            };
+            Some(pnl + (price - leg.price) * leg.quantity)
+        })
+    }
+}
+
+/// Closed spread position
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClosedSpread {
+    pub position: SpreadPosition,
+    pub exit_reason: StatArbExit,
+    pub pnl: f64,
+    pub closed_at: DateTime<Utc>,
+}
+
+/// Z-score of the last YES price against the previous `lookback` snapshots
+pub fn price_z_score(history: &[PriceSnapshot], lookback: usize) -> Option<f64> {
+    if lookback < 2 || history.len() < lookback + 1 {
+        return None;
+    }
+    let (window, last) = history[history.len() - lookback - 1..].split_at(lookback);
+    let prices: Vec<f64> = window.iter().map(|s| s.yes_price).collect();
+    let mean = prices.iter().sum::<f64>() / prices.len() as f64;
+    let std_dev = (prices.iter().map(|p| (p - mean).powi(2)).sum::<f64>() / prices.len() as f64).sqrt();
+    if std_dev <= 1e-9 {
+        return None;
+    }
+    Some((last[0].yes_price - mean) / std_dev)
+}
+
+/// Mean-reversion positions and their exits
+pub struct StatArbManager {
+    pub config: StatArbConfig,
+    pub positions: FxHashMap<String, StatArbPosition>,
+    pub closed: Vec<ClosedStatArb>,
+    pub spreads: Vec<SpreadPosition>, // Coppie cointegrate aperte dall'executor
+    pub closed_spreads: Vec<ClosedSpread>,
+    emrt: EmrtCalculator,
+}
+
+impl StatArbManager {
+    pub fn new(config: StatArbConfig) -> Self {
+        let emrt = EmrtCalculator::new(config.lookback, 0.0);
+        Self {
+            config,
+            positions: FxHashMap::default(),
+            closed: Vec::new(),
+            spreads: Vec::new(),
+            closed_spreads: Vec::new(),
+            emrt,
+        }
+    }
+
+    /// Time stop horizon: EMRT of the window (in snapshots) times the mean snapshot spacing
+    fn horizon_secs(&self, history: &[PriceSnapshot]) -> i64 {
+        let window = &history[history.len().saturating_sub(self.config.lookback + 1)..];
+        let prices: Vec<f64> = window.iter().map(|s| s.yes_price).collect();
+        self.horizon_for(&prices, window)
+    }
+
+    /// Horizon for the `values` observed at the `window` snapshots
+    fn horizon_for(&self, values: &[f64], window: &[PriceSnapshot]) -> i64 {
+        let emrt_steps = self.emrt.calculate_emrt(values);
+        let spacing = match (window.first(), window.last()) {
+            (Some(first), Some(last)) if window.len() > 1 => (last.timestamp - first.timestamp).num_seconds() as f64 / (window.len() - 1) as f64,
+            _ => 0.0,
+        };
+        ((emrt_steps * self.config.emrt_multiple * spacing) as i64).max(self.config.min_horizon_secs)
+    }
+
+    /// Position-management loop: update marks and close positions whose exit rule fires
+    pub fn manage(&mut self, histories: &FxHashMap<String, Vec<PriceSnapshot>>, now: DateTime<Utc>) -> Vec<ClosedStatArb> {
+        let mut exits = Vec::new();
+        for (market_id, position) in self.positions.iter_mut() {
+            let Some(history) = histories.get(market_id) else { continue };
+            let Some(last) = history.last() else { continue };
+            let price = match position.token_type {
+                TokenType::Yes => last.yes_price,
+                TokenType::No => last.no_price,
+            };
+            position.last_price = price;
+
+            // Senza z-score (storico insufficiente o piatto) valgono solo time e trailing stop
+            let z = price_z_score(history, self.config.lookback).unwrap_or(position.entry_z);
+            if let Some(reason) = position.exit_signal(price, z, now, &self.config) {
+                exits.push((market_id.clone(), price, reason));
+            } else {
+                position.peak_pnl = position.peak_pnl.max(position.pnl(price));
+            }
+        }
+        exits.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut closed = Vec::new();
+        for (market_id, price, reason) in exits {
+            let Some(position) = self.positions.remove(&market_id) else { continue };
+            let pnl = position.pnl(price);
+            closed.push(ClosedStatArb { position, exit_price: price, exit_reason: reason, pnl, closed_at: now });
+        }
+        self.closed.extend(closed.iter().cloned());
+        closed
+    }
+
+    /// Open positions on markets whose YES price deviates at least `entry_z` from its mean (`excluded` markets are skipped)
+    pub fn scan_entries(
+        &mut self,
+        histories: &FxHashMap<String, Vec<PriceSnapshot>>,
+        excluded: &FxHashSet<String>,
+        capital: f64,
+        now: DateTime<Utc>,
+    ) -> Vec<StatArbPosition> {
+        let mut candidates: Vec<(&String, &Vec<PriceSnapshot>, f64)> = histories
+            .iter()
+            .filter(|(market_id, _)| !self.positions.contains_key(*market_id) && !excluded.contains(*market_id))
+            .filter_map(|(market_id, history)| {
+                let z = price_z_score(history, self.config.lookback)?;
+                (z.abs() >= self.config.entry_z).then_some((market_id, history, z))
+            })
+            .collect();
+        candidates.sort_by(|a, b| b.2.abs().partial_cmp(&a.2.abs()).unwrap().then_with(|| a.0.cmp(b.0)));
+
+        let slots = self.config.max_positions.saturating_sub(self.positions.len());
+        let mut opened = Vec::new();
+        for (market_id, history, z) in candidates.into_iter().take(slots) {
+            let Some(last) = history.last() else { continue };
+            let (token_type, price) = if z < 0.0 { (TokenType::Yes, last.yes_price) } else { (TokenType::No, last.no_price) };
+            if price <= 0.0 || price >= 1.0 {
+                continue;
+            }
+            let position = StatArbPosition {
+                market_id: market_id.clone(),
+                token_type,
+                quantity: capital * self.config.position_fraction / price,
+                entry_price: price,
+                entry_z: z,
+                opened_at: now,
+                horizon_secs: self.horizon_secs(history),
+                peak_pnl: 0.0,
+                last_price: price,
+            };
+            opened.push(position.clone());
+            self.positions.insert(market_id.clone(), position);
+        }
+        opened
+    }
+
+    /// Hold the legs of an executed `StatisticalArb` trade until the spread reverts
+    ///
+    /// The time stop horizon is the EMRT of the legs' combined value, which moves with the spread.
+    pub fn open_spread(
+        &mut self,
+        opportunity: &ArbitrageOpportunity,
+        trade: &TradeExecution,
+        histories: &FxHashMap<String, Vec<PriceSnapshot>>,
+        now: DateTime<Utc>,
+    ) -> SpreadPosition {
+        // Storici allineati sulla coda, come nel detector
+        let len = trade.legs.iter()
+            .map(|l| histories.get(&l.market_id).map_or(0, Vec::len))
+            .min()
+            .unwrap_or(0)
+            .min(self.config.lookback + 1);
+        let values: Vec<f64> = (0..len)
+            .map(|i| trade.legs.iter().map(|l| {
+                let history = &histories[&l.market_id];
+                let snapshot = &history[history.len() - len + i];
+                let price = match l.token_type {
+                    TokenType::Yes => snapshot.yes_price,
+                    TokenType::No => snapshot.no_price,
+                };
+                price * l.quantity
+            }).sum())
+            .collect();
+        let window = trade.legs.first()
+            .and_then(|l| histories.get(&l.market_id))
+            .map_or(&[][..], |h| &h[h.len() - len..]);
+
+        // Unità di spread acquistate: quantità del leg a peso unitario
+        let units = opportunity.legs.as_ref()
+            .and_then(|legs| legs.last().zip(trade.legs.last()))
+            .map_or(0.0, |(detected, filled)| if detected.quantity > 0.0 { filled.quantity / detected.quantity } else { 0.0 });
+        let position = SpreadPosition {
+            trade_id: trade.trade_id.clone(),
+            legs: trade.legs.clone(),
+            target_pnl: opportunity.profit * units,
+            opened_at: now,
+            horizon_secs: self.horizon_for(&values, window),
+            peak_pnl: 0.0,
+            last_pnl: 0.0,
+        };
+        self.spreads.push(position.clone());
+        position
+    }
+
+    /// Mark the open spreads and close those whose exit rule fires
+    ///
+    /// The spread is back at its mean once the legs have gained the reversion expected at entry.
+    pub fn manage_spreads(&mut self, histories: &FxHashMap<String, Vec<PriceSnapshot>>, now: DateTime<Utc>) -> Vec<ClosedSpread> {
+        let mut closed = Vec::new();
+        let config = &self.config;
+        self.spreads.retain_mut(|position| {
+            let Some(pnl) = position.pnl(histories) else { return true };
+            position.last_pnl = pnl;
+            let crossed = position.target_pnl > 0.0 && pnl >= position.target_pnl;
+            let expired = now - position.opened_at >= Duration::seconds(position.horizon_secs);
+            match exit_rule(crossed, pnl, position.peak_pnl, position.notional(), expired, config) {
+                Some(reason) => {
+                    closed.push(ClosedSpread { position: position.clone(), exit_reason: reason, pnl, closed_at: now });
+                    false
+                }
+                None => {
+                    position.peak_pnl = position.peak_pnl.max(pnl);
+                    true
+                }
+            }
+        });
+        self.closed_spreads.extend(closed.iter().cloned());
+        closed
+    }
+
+    /// Realized PnL of all closed positions
+    pub fn realized_pnl(&self) -> f64 {
+        self.closed.iter().map(|c| c.pnl).sum::<f64>() + self.closed_spreads.iter().map(|c| c.pnl).sum::<f64>()
+    }
+}
+
+/// Critical value of the Engle-Granger test for two series at 5%
+pub const ENGLE_GRANGER_CRITICAL_5PCT: f64 = -3.34;
+
+fn default_pair_lookback() -> usize {
+    50
+}
+
+fn default_min_correlation() -> f64 {
+    0.7
+}
+
+fn default_adf_critical() -> f64 {
+    ENGLE_GRANGER_CRITICAL_5PCT
+}
+
+/// Pair selection and entry rules of the cointegration detector
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PairStatArbConfig {
+    #[serde(default = "default_pair_lookback")]
+    pub lookback: usize, // Snapshot allineati usati per correlazione, cointegrazione e z-score
+    #[serde(default = "default_entry_z")]
+    pub entry_z: f64,
+    #[serde(default = "default_min_correlation")]
+    pub min_correlation: f64, // Correlazione minima in valore assoluto tra i prezzi YES
+    #[serde(default = "default_adf_critical")]
+    pub adf_critical: f64, // Statistica ADF dello spread sotto cui la coppia è cointegrata
+    #[serde(default)]
+    pub pairs: Vec<(String, String)>, // Coppie esplicite, oltre ai mercati dello stesso evento
+}
+
+impl Default for PairStatArbConfig {
+    fn default() -> Self {
+        Self {
+            lookback: default_pair_lookback(),
+            entry_z: default_entry_z(),
+            min_correlation: default_min_correlation(),
+            adf_critical: default_adf_critical(),
+            pairs: Vec::new(),
+        }
+    }
+}
+
+/// Spread of a cointegrated pair: `hedge_ratio * yes_a + yes_b`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpreadSignal {
+    pub market_a: String,
+    pub market_b: String,
+    pub hedge_ratio: f64,
+    pub correlation: f64,
+    pub adf_stat: f64, // Statistica t di Dickey-Fuller dello spread (più negativa = più stazionario)
+    pub spread: f64,   // Valore attuale dello spread
+    pub mean: f64,
+    pub std_dev: f64,
+    pub z_score: f64,
+}
+
+/// Pearson correlation of two equally long series
+pub fn correlation(x: &[f64], y: &[f64]) -> Option<f64> {
+    if x.len() != y.len() || x.len() < 2 {
+        return None;
+    }
+    let n = x.len() as f64;
+    let (mean_x, mean_y) = (x.iter().sum::<f64>() / n, y.iter().sum::<f64>() / n);
+    let (mut sxy, mut sxx, mut syy) = (0.0, 0.0, 0.0);
+    for (a, b) in x.iter().zip(y) {
+        sxy += (a - mean_x) * (b - mean_y);
+        sxx += (a - mean_x).powi(2);
+        syy += (b - mean_y).powi(2);
+    }
+    (sxx > 1e-12 && syy > 1e-12).then(|| sxy / (sxx * syy).sqrt())
+}
+
+/// Dickey-Fuller t statistic of `Δs_t = α + γ s_{t-1}`: strongly negative for a mean-reverting series
+pub fn adf_statistic(series: &[f64]) -> Option<f64> {
+    if series.len() < 4 {
+        return None;
+    }
+    let lagged = &series[..series.len() - 1];
+    let deltas: Vec<f64> = series.windows(2).map(|w| w[1] - w[0]).collect();
+    let n = lagged.len() as f64;
+    let (mean_x, mean_y) = (lagged.iter().sum::<f64>() / n, deltas.iter().sum::<f64>() / n);
+    let sxx: f64 = lagged.iter().map(|x| (x - mean_x).powi(2)).sum();
+    if sxx <= 1e-12 {
+        return None;
+    }
+    let gamma = lagged.iter().zip(&deltas).map(|(x, y)| (x - mean_x) * (y - mean_y)).sum::<f64>() / sxx;
+    let alpha = mean_y - gamma * mean_x;
+    let rss: f64 = lagged.iter().zip(&deltas).map(|(x, y)| (y - alpha - gamma * x).powi(2)).sum();
+    let std_err = (rss / (n - 2.0) / sxx).sqrt();
+    if std_err <= 1e-12 {
+        // Adattamento perfetto: stazionario se lo spread torna verso la media
+        return Some(if gamma < 0.0 { f64::NEG_INFINITY } else { f64::INFINITY });
+    }
+    Some(gamma / std_err)
+}
+
+/// Cointegration detector over the YES price histories of related markets
+pub struct StatArbDetector {
+    pub config: PairStatArbConfig,
+    emrt: EmrtCalculator,
+}
+
+impl StatArbDetector {
+    pub fn new(config: PairStatArbConfig) -> Self {
+        let emrt = EmrtCalculator::new(config.lookback, 0.0);
+        Self { config, emrt }
+    }
+
+    /// Candidate pairs: markets of the same event, plus the configured pairs (each pair once)
+    pub fn related_pairs(&self, markets: &[MarketData]) -> Vec<(String, String)> {
+        let mut by_event: FxHashMap<&str, Vec<&str>> = FxHashMap::default();
+        for market in markets {
+            if let Some(event_id) = market.event_id.as_deref() {
+                by_event.entry(event_id).or_default().push(&market.id);
+            }
+        }
+        let mut pairs: Vec<(String, String)> = Vec::new();
+        let mut push = |a: &str, b: &str| {
+            let pair = if a < b { (a.to_string(), b.to_string()) } else { (b.to_string(), a.to_string()) };
+            if a != b && !pairs.contains(&pair) {
+                pairs.push(pair);
+            }
+        };
+        for ids in by_event.values() {
+            for (i, a) in ids.iter().enumerate() {
+                for b in &ids[i + 1..] {
+                    push(a, b);
+                }
+            }
+        }
+        for (a, b) in &self.config.pairs {
+            push(a, b);
+        }
+        pairs.sort();
+        pairs
+    }
+
+    /// Spread of a pair if it is correlated and cointegrated over the lookback; z-score of the last snapshot
+    pub fn analyze_pair(&self, market_a: &str, market_b: &str, history_a: &[PriceSnapshot], history_b: &[PriceSnapshot]) -> Option<SpreadSignal> {
+        // Storici allineati sulla coda: uno snapshot per mercato a ogni aggiornamento
+        let len = self.config.lookback + 1;
+        if self.config.lookback < 10 || history_a.len() < len || history_b.len() < len {
+            return None;
+        }
+        let yes_a: Vec<f64> = history_a[history_a.len() - len..].iter().map(|s| s.yes_price).collect();
+        let yes_b: Vec<f64> = history_b[history_b.len() - len..].iter().map(|s| s.yes_price).collect();
+        let (window_a, window_b) = (&yes_a[..self.config.lookback], &yes_b[..self.config.lookback]);
+
+        let correlation = correlation(window_a, window_b)?;
+        if correlation.abs() < self.config.min_correlation {
+            return None;
+        }
+        let hedge_ratio = self.emrt.find_hedge_ratio(window_a, window_b);
+        if hedge_ratio == 0.0 {
+            return None;
+        }
+        let spreads: Vec<f64> = yes_a.iter().zip(&yes_b).map(|(a, b)| hedge_ratio * a + b).collect();
+        let window = &spreads[..self.config.lookback];
+        let adf_stat = adf_statistic(window)?;
+        if adf_stat > self.config.adf_critical {
+            return None;
+        }
+
+        let mean = window.iter().sum::<f64>() / window.len() as f64;
+        let std_dev = (window.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / window.len() as f64).sqrt();
+        if std_dev <= 1e-9 {
+            return None;
+        }
+        let spread = spreads[self.config.lookback];
+        Some(SpreadSignal {
+            market_a: market_a.to_string(),
+            market_b: market_b.to_string(),
+            hedge_ratio,
+            correlation,
+            adf_stat,
+            spread,
+            mean,
+            std_dev,
+            z_score: (spread - mean) / std_dev,
+        })
+    }
+
+    /// Spread signals of every cointegrated related pair
+    pub fn signals(&self, markets: &[MarketData], histories: &FxHashMap<String, Vec<PriceSnapshot>>) -> Vec<SpreadSignal> {
+        self.related_pairs(markets)
+            .into_iter()
+            .filter_map(|(a, b)| self.analyze_pair(&a, &b, histories.get(&a)?, histories.get(&b)?))
+            .collect()
+    }
+
+    /// `StatisticalArb` opportunities on the pairs whose spread z-score reaches `entry_z`
+    ///
+    /// A rich spread is sold (NO on the positively weighted legs), a cheap one bought; the expected
+    /// profit per spread unit is the distance back to the mean.
+    pub fn detect(&self, markets: &[MarketData], histories: &FxHashMap<String, Vec<PriceSnapshot>>) -> Vec<ArbitrageOpportunity> {
+        let by_id: FxHashMap<&str, &MarketData> = markets.iter().map(|m| (m.id.as_str(), m)).collect();
+        self.signals(markets, histories)
+            .into_iter()
+            .filter(|s| s.z_score.abs() >= self.config.entry_z)
+            .filter_map(|signal| {
+                let (a, b) = (by_id.get(signal.market_a.as_str())?, by_id.get(signal.market_b.as_str())?);
+                Some(self.opportunity(&signal, a, b))
+            })
+            .filter(|o| o.profit > 0.0)
+            .collect()
+    }
+
+    fn opportunity(&self, signal: &SpreadSignal, a: &MarketData, b: &MarketData) -> ArbitrageOpportunity {
+        // Spread ricco (z > 0): si vende, cioè si compra NO sui leg a peso positivo
+        let short_spread = signal.z_score > 0.0;
+        let leg = |market: &MarketData, weight: f64| {
+            let token_type = if (weight > 0.0) == short_spread { TokenType::No } else { TokenType::Yes };
+            let price = match token_type {
+                TokenType::Yes => market.yes_price,
+                TokenType::No => market.no_price,
+            };
+            ArbitrageLeg {
+                market_id: market.id.clone(),
+                token_type,
+                direction: Direction::Buy,
+                price,
+                quantity: weight.abs(),
+                token_id: market.tokens.as_ref().map(|t| match token_type {
+                    TokenType::Yes => t.yes_token_id.clone(),
+                    TokenType::No => t.no_token_id.clone(),
+                }),
+                venue: None,
+            }
+        };
+        let legs = vec![leg(a, signal.hedge_ratio), leg(b, 1.0)];
+        let cost: f64 = legs.iter().map(|l| l.price * l.quantity).sum();
+        let profit = (signal.spread - signal.mean).abs();
+        let liquidity = (a.yes_liquidity + a.no_liquidity).min(b.yes_liquidity + b.no_liquidity);
+
+        ArbitrageOpportunity {
+            market_id: a.id.clone(),
+            question: format!("{} / {}", a.question, b.question),
+            arb_type: ArbType::StatisticalArb,
+            profit,
+            roi_pct: if cost > 0.0 { profit / cost * 100.0 } else { 0.0 },
+            // Correlazione e distanza dalla media (piena al doppio della soglia di entrata)
+            confidence: signal.correlation.abs() * 0.5 + (signal.z_score.abs() / (2.0 * self.config.entry_z)).min(1.0) * 0.5,
+            yes_price: a.yes_price,
+            no_price: b.yes_price,
+            sum_price: signal.spread,
+            liquidity,
+            timestamp: a.timestamp.max(b.timestamp),
+            legs: Some(legs),
+            path: Some(vec![a.id.clone(), b.id.clone()]),
+            maker: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_emrt() {
+        let calculator = EmrtCalculator::new(10, 0.01);
+        let prices = vec![100.0, 101.0, 99.0, 100.0];
+        let emrt = calculator.calculate_emrt(&prices);
+        assert!(emrt > 0.0);
+    }
+
+    fn history(prices: &[f64], start: DateTime<Utc>) -> Vec<PriceSnapshot> {
+        prices
+            .iter()
+            .enumerate()
+            .map(|(i, &p)| PriceSnapshot {
+                timestamp: start + Duration::seconds(i as i64 * 10),
+                yes_price: p,
+                no_price: 1.0 - p,
+                volume: 0.0,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_cointegrated_pair_spread_signal() {
+        let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        // B = 1 - A più rumore alternato: lo spread A + B è stazionario attorno a 1
+        let yes_a: Vec<f64> = (0..31).map(|i| 0.5 + 0.1 * (i as f64 / 7.0).sin()).collect();
+        let mut yes_b: Vec<f64> = yes_a.iter().enumerate().map(|(i, a)| 1.0 - a + if i % 2 == 0 { 0.005 } else { -0.005 }).collect();
+        *yes_b.last_mut().unwrap() += 0.05;
+
+        let mut histories: FxHashMap<String, Vec<PriceSnapshot>> = FxHashMap::default();
+        histories.insert("a".to_string(), history(&yes_a, start));
+        histories.insert("b".to_string(), history(&yes_b, start));
+        histories.insert("c".to_string(), history(&vec![0.5; 31], start));
+        let market = |id: &str, yes: f64| MarketData {
+            id: id.to_string(),
+            yes_price: yes,
+            no_price: 1.0 - yes,
+            yes_liquidity: 5000.0,
+            no_liquidity: 5000.0,
+            event_id: Some("e1".to_string()),
+            ..MarketData::default()
+        };
+        let markets = vec![market("a", yes_a[30]), market("b", yes_b[30]), market("c", 0.5)];
+
+        let detector = StatArbDetector::new(PairStatArbConfig { lookback: 30, ..PairStatArbConfig::default() });
+        assert_eq!(detector.related_pairs(&markets).len(), 3);
+        // "c" è piatto: nessuna correlazione, nessun segnale
+        let signals = detector.signals(&markets, &histories);
+        assert_eq!(signals.len(), 1);
+        assert_eq!(signals[0].hedge_ratio, 1.0);
+        assert!(signals[0].correlation < -0.9);
+        assert!(signals[0].adf_stat < ENGLE_GRANGER_CRITICAL_5PCT);
+        assert!(signals[0].z_score > 2.0);
+
+        // Spread ricco: si vende comprando NO su entrambi i mercati
+        let opportunities = detector.detect(&markets, &histories);
+        assert_eq!(opportunities.len(), 1);
+        assert_eq!(opportunities[0].arb_type, ArbType::StatisticalArb);
+        let legs = opportunities[0].legs.as_ref().unwrap();
+        assert!(legs.iter().all(|l| l.token_type == TokenType::No));
+        assert!((opportunities[0].profit - (signals[0].spread - signals[0].mean)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_mean_reversion_exits() {
+        let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let config = StatArbConfig { lookback: 20, trailing_activation: 0.05, ..StatArbConfig::default() };
+        let mut manager = StatArbManager::new(config);
+        let base: Vec<f64> = (0..20).map(|i| if i % 2 == 0 { 0.49 } else { 0.51 }).collect();
+
+        // "a" crolla sotto la media (compra YES), "b" sale sopra (compra NO), "c" resta nel range
+        let mut histories: FxHashMap<String, Vec<PriceSnapshot>> = FxHashMap::default();
+        histories.insert("a".to_string(), history(&[base.as_slice(), &[0.40]].concat(), start));
+        histories.insert("b".to_string(), history(&[base.as_slice(), &[0.60]].concat(), start));
+        histories.insert("c".to_string(), history(&[base.as_slice(), &[0.50]].concat(), start));
+        let now = start + Duration::seconds(200);
+
+        let opened = manager.scan_entries(&histories, &FxHashSet::default(), 1000.0, now);
+        assert_eq!(opened.len(), 2);
+        assert_eq!(manager.positions["a"].token_type, TokenType::Yes);
+        assert_eq!(manager.positions["b"].token_type, TokenType::No);
+        assert!(manager.positions["a"].horizon_secs >= 60);
+
+        // "a" risale sopra la media: uscita sullo zero-cross in profitto
+        histories.get_mut("a").unwrap().extend(history(&[0.52], now));
+        // "b" rientra a metà (picco di PnL) poi restituisce il guadagno: trailing stop
+        histories.get_mut("b").unwrap().extend(history(&[0.56], now));
+        let closed = manager.manage(&histories, now + Duration::seconds(10));
+        assert_eq!(closed.len(), 1);
+        assert_eq!(closed[0].exit_reason, StatArbExit::ZeroCross);
+        assert!((closed[0].pnl - (0.52 - 0.40) * 20.0 / 0.40).abs() < 1e-9);
+
+        histories.get_mut("b").unwrap().extend(history(&[0.595], now));
+        let closed = manager.manage(&histories, now + Duration::seconds(20));
+        assert_eq!(closed[0].exit_reason, StatArbExit::TrailingStop);
+        assert!(closed[0].pnl > 0.0);
+
+        // Nessuna reversione: time stop allo scadere dell'orizzonte
+        histories.clear();
+        histories.insert("d".to_string(), history(&[base.as_slice(), &[0.30]].concat(), start));
+        manager.scan_entries(&histories, &FxHashSet::default(), 1000.0, now);
+        let horizon = manager.positions["d"].horizon_secs;
+        assert!(manager.manage(&histories, now + Duration::seconds(horizon - 1)).is_empty());
+        let closed = manager.manage(&histories, now + Duration::seconds(horizon));
+        assert_eq!(closed[0].exit_reason, StatArbExit::TimeStop);
+        assert_eq!(manager.closed.len(), 3);
+    }
+
+    #[test]
+    fn test_spread_positions_realize_pnl_on_reversion() {
+        let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let mut manager = StatArbManager::new(StatArbConfig { lookback: 20, ..StatArbConfig::default() });
+        let base: Vec<f64> = (0..21).map(|i| if i % 2 == 0 { 0.49 } else { 0.51 }).collect();
+        let mut histories: FxHashMap<String, Vec<PriceSnapshot>> = FxHashMap::default();
+        histories.insert("a".to_string(), history(&base, start));
+        histories.insert("b".to_string(), history(&base, start));
+        let now = start + Duration::seconds(200);
+
+        // Spread ricco: NO su entrambi i mercati, reversione attesa di 0.05 per unità
+        let leg = |market_id: &str, price: f64, quantity: f64| ArbitrageLeg {
+            market_id: market_id.to_string(),
+            token_type: TokenType::No,
+            direction: Direction::Buy,
+            price,
+            quantity,
+            token_id: None,
+            venue: None,
+        };
+        let opportunity = ArbitrageOpportunity {
+            market_id: "a".to_string(),
+            question: String::new(),
+            arb_type: ArbType::StatisticalArb,
+            profit: 0.05,
+            roi_pct: 5.0,
+            confidence: 0.9,
+            yes_price: 0.51,
+            no_price: 0.51,
+            sum_price: 1.02,
+            liquidity: 5000.0,
+            timestamp: now,
+            legs: Some(vec![leg("a", 0.49, 1.0), leg("b", 0.49, 1.0)]),
+            path: None,
+            maker: None,
+        };
+        let legs = vec![leg("a", 0.49, 100.0), leg("b", 0.49, 100.0)];
+        let trade = TradeExecution {
+            trade_id: "t1".to_string(),
+            market_id: "a".to_string(),
+            arb_type: ArbType::StatisticalArb,
+            legs,
+            total_investment: 98.0,
+            expected_return: 98.0,
+            actual_return: 98.0,
+            profit: 0.0,
+            roi_pct: 0.0,
+            entry_time: now,
+            exit_time: now,
+            execution_time_ms: 0,
+            slippage_pct: 0.0,
+            gas_cost: 0.0,
+            fees: 0.0,
+        };
+        let position = manager.open_spread(&opportunity, &trade, &histories, now);
+        assert!((position.target_pnl - 5.0).abs() < 1e-9);
+        assert!(position.horizon_secs >= manager.config.min_horizon_secs);
+
+        // A metà della reversione la posizione resta aperta e nessun PnL è realizzato
+        for market in ["a", "b"] {
+            histories.get_mut(market).unwrap().extend(history(&[0.4975], now));
+        }
+        assert!(manager.manage_spreads(&histories, now + Duration::seconds(10)).is_empty());
+        assert_eq!(manager.realized_pnl(), 0.0);
+
+        // Spread tornato alla media: uscita con il PnL dei leg
+        for market in ["a", "b"] {
+            histories.get_mut(market).unwrap().extend(history(&[0.48], now));
+        }
+        let closed = manager.manage_spreads(&histories, now + Duration::seconds(20));
+        assert_eq!(closed.len(), 1);
+        assert_eq!(closed[0].exit_reason, StatArbExit::ZeroCross);
+        assert!((closed[0].pnl - 6.0).abs() < 1e-9);
+        assert!(manager.spreads.is_empty());
+        assert!((manager.realized_pnl() - 6.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_single_token_loss_uses_the_strategy_budget() {
+        use crate::risk::StrategyRiskLimits;
+        use crate::{BotConfig, HftArbitrageBot};
+
+        // Quota del 20% sul limite giornaliero di 50: budget di 10 USDC per la strategia
+        let mut bot = HftArbitrageBot::new(BotConfig {
+            stat_arb: Some(StatArbConfig::default()),
+            strategy_risk: vec![StrategyRiskLimits {
+                strategy: ArbType::StatisticalArb,
+                max_position: None,
+                daily_loss_share: Some(0.2),
+                max_concurrent_trades: None,
+            }],
+            ..BotConfig::default()
+        });
+        let now = Utc::now();
+        // YES crollato a 0.30: z-score estremo, segnale di entrata sullo stesso mercato
+        let mut prices: Vec<f64> = (0..30).map(|i| if i % 2 == 0 { 0.49 } else { 0.51 }).collect();
+        prices.push(0.30);
+        bot.market_manager.price_history.insert("m".to_string(), history(&prices, now - Duration::seconds(300)));
+        bot.stat_arb.as_mut().unwrap().positions.insert("m".to_string(), StatArbPosition {
+            market_id: "m".to_string(),
+            token_type: TokenType::Yes,
+            quantity: 100.0,
+            entry_price: 0.50,
+            entry_z: -3.0,
+            opened_at: now - Duration::hours(1),
+            horizon_secs: 60,
+            peak_pnl: 0.0,
+            last_price: 0.50,
+        });
+
+        // Time stop a 0.30: perdita di 20, oltre il budget della strategia ma sotto il limite globale
+        assert!((bot.manage_stat_arb() + 20.0).abs() < 1e-9);
+        assert!((bot.risk_manager.current_strategy_loss(ArbType::StatisticalArb) - 20.0).abs() < 1e-9);
+        assert!(bot.risk_manager.can_trade(bot.capital));
+        assert!(bot.stat_arb.as_ref().unwrap().positions.is_empty());
+    }
+}
@@ -0,0 +1,1354 @@
+//! Persistence module
+//!
+//! Implements:
+//! 1. `Storage` trait for trades and per-step snapshots of a run, with time-range step queries
+//! 2. Flat-file backend (JSON lines, one directory per run)
+//! 3. SQLite backend for single-user setups
+//! 4. Postgres backend for shared deployments (pooled, with embedded migrations; feature "postgres")
+//! 5. Backend selection from configuration
+//! 6. Dashboard user accounts
+//! 7. SQLite archive of the detector's opportunity history
+//! 8. Per-market price snapshots of a run, queried by market and time range for offline replay
+//! 9. Schema versions of flat files and SQLite databases, with migrations applied on open
+
+use crate::accounts::UserAccount;
+use crate::market::PriceSnapshot;
+use crate::opportunity_log::{OpportunityQuery, OpportunityRecord};
+use crate::types::TradeExecution;
+use crate::versioning::{load_document, read_lines, to_document, LineWriter, Schema};
+use crate::StepResult;
+use chrono::{DateTime, Utc};
+use futures_util::future::BoxFuture;
+use futures_util::FutureExt;
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "postgres")]
+use sqlx::postgres::{PgPool, PgPoolOptions};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use sqlx::{QueryBuilder, Row};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "postgres")]
+use std::time::Duration;
+
+/// Default flat-file storage directory
+pub const STORAGE_DIR: &str = "./data/runs";
+
+/// Storage backend selection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "backend", rename_all = "snake_case")]
+pub enum StorageConfig {
+    FlatFile { dir: PathBuf },
+    Sqlite { path: PathBuf },
+    Postgres {
+        url: String,
+        #[serde(default)]
+        pool: PgPoolConfig,
+    },
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        StorageConfig::FlatFile { dir: PathBuf::from(STORAGE_DIR) }
+    }
+}
+
+/// Environment variable selecting the dashboard's storage backend, as StorageConfig JSON
+pub const STORAGE_CONFIG_ENV: &str = "DASHBOARD_STORAGE";
+
+impl StorageConfig {
+    /// Backend from `STORAGE_CONFIG_ENV`, e.g. `{"backend": "sqlite", "path": "./data/bot.db"}`; flat files if unset
+    pub fn from_env() -> Result<Self, String> {
+        match std::env::var(STORAGE_CONFIG_ENV).ok().filter(|v| !v.trim().is_empty()) {
+            Some(json) => serde_json::from_str(&json).map_err(|e| format!("Invalid {}: {}", STORAGE_CONFIG_ENV, e)),
+            None => Ok(Self::default()),
+        }
+    }
+}
+
+/// Check a run id taken from a request: at most 64 letters, digits, '-' or '_'
+///
+/// Run ids name directories of the flat-file backend, so anything else (separators, "..") is refused.
+pub fn validate_run_id(run_id: &str) -> Result<(), String> {
+    let valid = !run_id.is_empty()
+        && run_id.len() <= 64
+        && run_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid { Ok(()) } else { Err(format!("Invalid run id {:?}", run_id)) }
+}
+
+/// Which steps are persisted as snapshots; steps with trades are always kept when enabled
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StepHistoryConfig {
+    pub enabled: bool,
+    pub every_n_steps: u64, // 1 = ogni step
+    pub record_prices: bool, // Salva anche gli snapshot di prezzo dei mercati, per il replay offline
+}
+
+impl Default for StepHistoryConfig {
+    fn default() -> Self {
+        Self { enabled: true, every_n_steps: 1, record_prices: false }
+    }
+}
+
+/// Environment variable with the dashboard feed's step history, as StepHistoryConfig JSON
+pub const FEED_STEP_HISTORY_ENV: &str = "DASHBOARD_STEP_HISTORY";
+
+/// Steps returned by a step query without a limit, and the most any query returns
+pub const MAX_STEP_QUERY_LIMIT: usize = 1_000;
+
+impl StepHistoryConfig {
+    /// Off: the dashboard feed detects every second and would write one snapshot per tick
+    pub fn dashboard_feed() -> Self {
+        Self { enabled: false, every_n_steps: 60, ..Self::default() }
+    }
+
+    /// Dashboard feed history from `FEED_STEP_HISTORY_ENV`, e.g. `{"enabled": true, "every_n_steps": 300}`; off if unset
+    pub fn feed_from_env() -> Result<Self, String> {
+        match std::env::var(FEED_STEP_HISTORY_ENV).ok().filter(|v| !v.trim().is_empty()) {
+            Some(json) => serde_json::from_str(&json).map_err(|e| format!("Invalid {}: {}", FEED_STEP_HISTORY_ENV, e)),
+            None => Ok(Self::dashboard_feed()),
+        }
+    }
+
+    /// Whether `step` falls on the sampling interval
+    pub fn is_due(&self, step: u64) -> bool {
+        self.enabled && step.is_multiple_of(self.every_n_steps.max(1))
+    }
+
+    pub fn should_persist(&self, result: &StepResult) -> bool {
+        (self.enabled && result.trades > 0) || self.is_due(result.step)
+    }
+}
+
+/// Connection pool settings for the Postgres backend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PgPoolConfig {
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub acquire_timeout_secs: u64, // Attesa massima per una connessione libera
+    pub idle_timeout_secs: u64,
+}
+
+impl Default for PgPoolConfig {
+    fn default() -> Self {
+        Self {
+            max_connections: 10,
+            min_connections: 1,
+            acquire_timeout_secs: 10,
+            idle_timeout_secs: 600,
+        }
+    }
+}
+
+/// Bot state at the end of one step
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunSnapshot {
+    pub run_id: String,
+    pub step: u64,
+    pub timestamp: DateTime<Utc>,
+    pub capital: f64,
+    pub profit: f64, // Profitto dello step
+    pub trades: u32,
+    pub opportunities: usize,
+    #[serde(default)]
+    pub win_rate: f64,
+    #[serde(default)]
+    pub avg_edge: f64,
+    #[serde(default)]
+    pub expectancy: f64,
+    #[serde(default)]
+    pub latency_ms: u64, // Durata dello step
+}
+
+impl RunSnapshot {
+    pub fn from_step(run_id: &str, result: &StepResult, timestamp: DateTime<Utc>, latency_ms: u64) -> Self {
+        Self {
+            run_id: run_id.to_string(),
+            step: result.step,
+            timestamp,
+            capital: result.capital,
+            profit: result.profit,
+            trades: result.trades,
+            opportunities: result.opportunities,
+            win_rate: result.win_rate,
+            avg_edge: result.avg_edge,
+            expectancy: result.expectancy,
+            latency_ms,
+        }
+    }
+}
+
+/// Trade tagged with the run that produced it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredTrade {
+    pub run_id: String,
+    pub trade: TradeExecution,
+}
+
+/// Everything persisted for one run, oldest first
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StoredRun {
+    pub run_id: String,
+    pub trades: Vec<TradeExecution>,
+    pub snapshots: Vec<RunSnapshot>,
+}
+
+/// Trade filter; unset fields match everything
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TradeQuery {
+    pub run_id: Option<String>,
+    pub market_id: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub limit: Option<usize>,
+}
+
+impl TradeQuery {
+    pub fn matches(&self, stored: &StoredTrade) -> bool {
+        self.run_id.as_ref().is_none_or(|id| *id == stored.run_id)
+            && self.market_id.as_ref().is_none_or(|id| *id == stored.trade.market_id)
+            && self.since.is_none_or(|since| stored.trade.entry_time >= since)
+            && self.until.is_none_or(|until| stored.trade.entry_time < until)
+    }
+}
+
+/// Step snapshot filter over the step timestamps; unset fields match everything
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StepQuery {
+    pub run_id: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub limit: Option<usize>,
+}
+
+impl StepQuery {
+    pub fn matches(&self, snapshot: &RunSnapshot) -> bool {
+        self.run_id.as_ref().is_none_or(|id| *id == snapshot.run_id)
+            && self.from.is_none_or(|from| snapshot.timestamp >= from)
+            && self.to.is_none_or(|to| snapshot.timestamp < to)
+    }
+}
+
+/// Price snapshot of one market, tagged with the run that recorded it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredPrice {
+    pub run_id: String,
+    pub market_id: String,
+    pub snapshot: PriceSnapshot,
+}
+
+/// Price snapshot filter over the snapshot timestamps; unset fields match everything
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PriceQuery {
+    pub run_id: Option<String>,
+    pub market_id: Option<String>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub limit: Option<usize>,
+}
+
+impl PriceQuery {
+    pub fn matches(&self, stored: &StoredPrice) -> bool {
+        self.run_id.as_ref().is_none_or(|id| *id == stored.run_id)
+            && self.market_id.as_ref().is_none_or(|id| *id == stored.market_id)
+            && self.from.is_none_or(|from| stored.snapshot.timestamp >= from)
+            && self.to.is_none_or(|to| stored.snapshot.timestamp < to)
+    }
+}
+
+/// Persistence backend
+pub trait Storage: Send + Sync {
+    fn save_trade<'a>(&'a self, run_id: &'a str, trade: &'a TradeExecution) -> BoxFuture<'a, Result<(), String>>;
+
+    fn save_snapshot<'a>(&'a self, snapshot: &'a RunSnapshot) -> BoxFuture<'a, Result<(), String>>;
+
+    /// Trades and snapshots of a run (empty for an unknown run)
+    fn load_run<'a>(&'a self, run_id: &'a str) -> BoxFuture<'a, Result<StoredRun, String>>;
+
+    /// Trades matching a filter, ordered by entry time
+    fn query<'a>(&'a self, query: &'a TradeQuery) -> BoxFuture<'a, Result<Vec<StoredTrade>, String>>;
+
+    /// Step snapshots matching a filter, ordered by timestamp
+    fn query_steps<'a>(&'a self, query: &'a StepQuery) -> BoxFuture<'a, Result<Vec<RunSnapshot>, String>>;
+
+    /// Append price snapshots
+    fn save_prices<'a>(&'a self, prices: &'a [StoredPrice]) -> BoxFuture<'a, Result<(), String>>;
+
+    /// Price snapshots matching a filter, ordered by timestamp
+    fn query_prices<'a>(&'a self, query: &'a PriceQuery) -> BoxFuture<'a, Result<Vec<StoredPrice>, String>>;
+
+    /// Create or replace an account
+    fn save_user<'a>(&'a self, user: &'a UserAccount) -> BoxFuture<'a, Result<(), String>>;
+
+    fn load_user<'a>(&'a self, username: &'a str) -> BoxFuture<'a, Result<Option<UserAccount>, String>>;
+
+    /// Accounts ordered by username
+    fn list_users(&self) -> BoxFuture<'_, Result<Vec<UserAccount>, String>>;
+
+    /// Delete an account; returns whether it existed
+    fn delete_user<'a>(&'a self, username: &'a str) -> BoxFuture<'a, Result<bool, String>>;
+}
+
+/// Storage shared between components
+pub type SharedStorage = Arc<dyn Storage>;
+
+/// Open the backend selected in the configuration
+pub async fn open_storage(config: &StorageConfig) -> Result<SharedStorage, String> {
+    Ok(match config {
+        StorageConfig::FlatFile { dir } => Arc::new(FlatFileStorage::new(dir)),
+        StorageConfig::Sqlite { path } => Arc::new(SqliteStorage::open(path).await?),
+        #[cfg(feature = "postgres")]
+        StorageConfig::Postgres { url, pool } => Arc::new(PostgresStorage::connect(url, pool).await?),
+        #[cfg(not(feature = "postgres"))]
+        StorageConfig::Postgres { .. } => return Err("Postgres backend needs the \"postgres\" feature".to_string()),
+    })
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String, String> {
+    serde_json::to_string(value).map_err(|e| format!("Failed to serialize record: {}", e))
+}
+
+fn from_json<T: for<'de> Deserialize<'de>>(data: &str) -> Result<T, String> {
+    serde_json::from_str(data).map_err(|e| format!("Failed to parse stored record: {}", e))
+}
+
+/// Schema of the flat-file trade lines
+pub const TRADES_SCHEMA: Schema = Schema::new("trades", &[]);
+
+/// Schema of the flat-file step snapshot lines
+pub const SNAPSHOTS_SCHEMA: Schema = Schema::new("run_snapshots", &[]);
+
+/// Schema of the flat-file price lines
+pub const PRICES_SCHEMA: Schema = Schema::new("prices", &[]);
+
+/// Schema of the flat-file user accounts
+pub const USERS_SCHEMA: Schema = Schema::new("users", &[]);
+
+/// JSON-lines files under `<dir>/<run_id>/`
+#[derive(Debug, Clone)]
+pub struct FlatFileStorage {
+    dir: PathBuf,
+    write_lock: Arc<Mutex<()>>,
+    writer: LineWriter,
+}
+
+impl FlatFileStorage {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            write_lock: Arc::new(Mutex::new(())),
+            writer: LineWriter::default(),
+        }
+    }
+
+    fn append(&self, run_id: &str, file: &str, schema: &Schema, line: String) -> Result<(), String> {
+        let run_dir = self.dir.join(run_id);
+        let _guard = self.write_lock.lock().unwrap();
+        std::fs::create_dir_all(&run_dir)
+            .map_err(|e| format!("Failed to create run directory: {}", e))?;
+        self.writer.append(schema, &run_dir.join(file), &[line])
+            .map(|_| ())
+            .map_err(|e| format!("Failed to write storage file: {}", e))
+    }
+
+    fn read_lines<T: for<'de> Deserialize<'de>>(schema: &Schema, path: &Path) -> Result<Vec<T>, String> {
+        read_lines(schema, path)
+            .map_err(|e| format!("Failed to read storage file: {}", e))?
+            .into_iter()
+            .map(|record| record.map_err(|e| format!("Failed to parse stored record: {}", e)))
+            .collect()
+    }
+
+    fn run_ids(&self) -> Result<Vec<String>, String> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+        let entries = std::fs::read_dir(&self.dir)
+            .map_err(|e| format!("Failed to list runs: {}", e))?;
+        Ok(entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_dir())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .collect())
+    }
+
+    fn users_path(&self) -> PathBuf {
+        self.dir.join("users.json")
+    }
+
+    fn read_users(&self) -> Result<Vec<UserAccount>, String> {
+        let users: Option<Vec<UserAccount>> = load_document(&USERS_SCHEMA, &self.users_path())
+            .map_err(|e| format!("Failed to read users: {}", e))?;
+        Ok(users.unwrap_or_default())
+    }
+
+    /// Rewrite the account file after applying `update` (under the write lock)
+    fn update_users<T>(&self, update: impl FnOnce(&mut Vec<UserAccount>) -> T) -> Result<T, String> {
+        let _guard = self.write_lock.lock().unwrap();
+        let mut users = self.read_users()?;
+        let result = update(&mut users);
+        users.sort_by(|a, b| a.username.cmp(&b.username));
+
+        std::fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("Failed to create storage directory: {}", e))?;
+        let content = to_document(&USERS_SCHEMA, &users)
+            .map_err(|e| format!("Failed to serialize users: {}", e))?;
+        std::fs::write(self.users_path(), content)
+            .map_err(|e| format!("Failed to write users: {}", e))?;
+        Ok(result)
+    }
+
+    fn load_run_sync(&self, run_id: &str) -> Result<StoredRun, String> {
+        let run_dir = self.dir.join(run_id);
+        let mut trades: Vec<TradeExecution> = Self::read_lines(&TRADES_SCHEMA, &run_dir.join("trades.jsonl"))?;
+        let mut snapshots: Vec<RunSnapshot> = Self::read_lines(&SNAPSHOTS_SCHEMA, &run_dir.join("snapshots.jsonl"))?;
+        trades.sort_by_key(|t| t.entry_time);
+        snapshots.sort_by_key(|s| s.step);
+
+        Ok(StoredRun { run_id: run_id.to_string(), trades, snapshots })
+    }
+}
+
+impl Storage for FlatFileStorage {
+    fn save_trade<'a>(&'a self, run_id: &'a str, trade: &'a TradeExecution) -> BoxFuture<'a, Result<(), String>> {
+        async move { self.append(run_id, "trades.jsonl", &TRADES_SCHEMA, to_json(trade)?) }.boxed()
+    }
+
+    fn save_snapshot<'a>(&'a self, snapshot: &'a RunSnapshot) -> BoxFuture<'a, Result<(), String>> {
+        async move { self.append(&snapshot.run_id, "snapshots.jsonl", &SNAPSHOTS_SCHEMA, to_json(snapshot)?) }.boxed()
+    }
+
+    fn load_run<'a>(&'a self, run_id: &'a str) -> BoxFuture<'a, Result<StoredRun, String>> {
+        async move { self.load_run_sync(run_id) }.boxed()
+    }
+
+    fn query<'a>(&'a self, query: &'a TradeQuery) -> BoxFuture<'a, Result<Vec<StoredTrade>, String>> {
+        async move {
+            let run_ids = match &query.run_id {
+                Some(run_id) => vec![run_id.clone()],
+                None => self.run_ids()?,
+            };
+
+            let mut trades = Vec::new();
+            for run_id in run_ids {
+                let run = self.load_run_sync(&run_id)?;
+                trades.extend(
+                    run.trades
+                        .into_iter()
+                        .map(|trade| StoredTrade { run_id: run_id.clone(), trade })
+                        .filter(|stored| query.matches(stored)),
+                );
+            }
+
+            trades.sort_by_key(|t| t.trade.entry_time);
+            trades.truncate(query.limit.unwrap_or(usize::MAX));
+            Ok(trades)
+        }
+        .boxed()
+    }
+
+    fn query_steps<'a>(&'a self, query: &'a StepQuery) -> BoxFuture<'a, Result<Vec<RunSnapshot>, String>> {
+        async move {
+            let run_ids = match &query.run_id {
+                Some(run_id) => vec![run_id.clone()],
+                None => self.run_ids()?,
+            };
+
+            let mut snapshots = Vec::new();
+            for run_id in run_ids {
+                let run: Vec<RunSnapshot> = Self::read_lines(&SNAPSHOTS_SCHEMA, &self.dir.join(&run_id).join("snapshots.jsonl"))?;
+                snapshots.extend(run.into_iter().filter(|s| query.matches(s)));
+            }
+
+            snapshots.sort_by_key(|s| (s.timestamp, s.step));
+            snapshots.truncate(query.limit.unwrap_or(usize::MAX));
+            Ok(snapshots)
+        }
+        .boxed()
+    }
+
+    fn save_prices<'a>(&'a self, prices: &'a [StoredPrice]) -> BoxFuture<'a, Result<(), String>> {
+        async move {
+            for price in prices {
+                self.append(&price.run_id, "prices.jsonl", &PRICES_SCHEMA, to_json(price)?)?;
+            }
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn query_prices<'a>(&'a self, query: &'a PriceQuery) -> BoxFuture<'a, Result<Vec<StoredPrice>, String>> {
+        async move {
+            let run_ids = match &query.run_id {
+                Some(run_id) => vec![run_id.clone()],
+                None => self.run_ids()?,
+            };
+
+            let mut prices = Vec::new();
+            for run_id in run_ids {
+                let run: Vec<StoredPrice> = Self::read_lines(&PRICES_SCHEMA, &self.dir.join(&run_id).join("prices.jsonl"))?;
+                prices.extend(run.into_iter().filter(|p| query.matches(p)));
+            }
+
+            prices.sort_by_key(|p| p.snapshot.timestamp);
+            prices.truncate(query.limit.unwrap_or(usize::MAX));
+            Ok(prices)
+        }
+        .boxed()
+    }
+
+    fn save_user<'a>(&'a self, user: &'a UserAccount) -> BoxFuture<'a, Result<(), String>> {
+        async move {
+            self.update_users(|users| {
+                users.retain(|u| u.username != user.username);
+                users.push(user.clone());
+            })
+        }
+        .boxed()
+    }
+
+    fn load_user<'a>(&'a self, username: &'a str) -> BoxFuture<'a, Result<Option<UserAccount>, String>> {
+        async move { Ok(self.read_users()?.into_iter().find(|u| u.username == username)) }.boxed()
+    }
+
+    fn list_users(&self) -> BoxFuture<'_, Result<Vec<UserAccount>, String>> {
+        async move { self.read_users() }.boxed()
+    }
+
+    fn delete_user<'a>(&'a self, username: &'a str) -> BoxFuture<'a, Result<bool, String>> {
+        async move {
+            self.update_users(|users| {
+                let before = users.len();
+                users.retain(|u| u.username != username);
+                users.len() != before
+            })
+        }
+        .boxed()
+    }
+}
+
+// I record completi restano in JSON; le colonne servono solo a filtrare e ordinare
+const TRADES_TABLE: &str = "CREATE TABLE IF NOT EXISTS trades (
+    run_id TEXT NOT NULL,
+    trade_id TEXT NOT NULL,
+    market_id TEXT NOT NULL,
+    entry_ms BIGINT NOT NULL,
+    data TEXT NOT NULL,
+    PRIMARY KEY (run_id, trade_id)
+)";
+
+const USERS_TABLE: &str = "CREATE TABLE IF NOT EXISTS users (
+    username TEXT PRIMARY KEY,
+    data TEXT NOT NULL
+)";
+
+const SNAPSHOTS_TABLE: &str = "CREATE TABLE IF NOT EXISTS snapshots (
+    run_id TEXT NOT NULL,
+    step BIGINT NOT NULL,
+    timestamp_ms BIGINT NOT NULL,
+    data TEXT NOT NULL,
+    PRIMARY KEY (run_id, step)
+)";
+
+const PRICES_INDEX: &str = "CREATE INDEX IF NOT EXISTS prices_market_timestamp_idx ON prices (market_id, timestamp_ms)";
+
+const PRICES_TABLE: &str = "CREATE TABLE IF NOT EXISTS prices (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    run_id TEXT NOT NULL,
+    market_id TEXT NOT NULL,
+    timestamp_ms BIGINT NOT NULL,
+    data TEXT NOT NULL
+)";
+
+const SCHEMA_VERSIONS_TABLE: &str = "CREATE TABLE IF NOT EXISTS schema_versions (
+    schema TEXT PRIMARY KEY,
+    version BIGINT NOT NULL
+)";
+
+// Voce i: porta il database alla versione i + 1
+const STORAGE_MIGRATIONS: &[&[&str]] = &[
+    &[TRADES_TABLE, SNAPSHOTS_TABLE, USERS_TABLE],
+    &[PRICES_TABLE, PRICES_INDEX],
+];
+
+/// Apply the pending migrations of `schema`, each in its own transaction, and record the version
+///
+/// Databases created before versioning have no recorded version: every migration runs, and the
+/// IF NOT EXISTS statements leave their tables and data as they are. A database already migrated
+/// by a newer build is refused.
+async fn migrate_sqlite(pool: &SqlitePool, schema: &str, migrations: &[&[&str]]) -> Result<(), String> {
+    let failed = |e: sqlx::Error| format!("Failed to migrate SQLite schema {}: {}", schema, e);
+    sqlx::query(SCHEMA_VERSIONS_TABLE).execute(pool).await.map_err(failed)?;
+    let version: i64 = sqlx::query("SELECT version FROM schema_versions WHERE schema = ?")
+        .bind(schema)
+        .fetch_optional(pool)
+        .await
+        .map_err(failed)?
+        .map_or(Ok(0), |row| row.try_get("version"))
+        .map_err(failed)?;
+    if version > migrations.len() as i64 {
+        return Err(format!(
+            "SQLite schema {} is at version {}, newer than this build ({}): upgrade the crate",
+            schema, version, migrations.len()
+        ));
+    }
+
+    for (index, statements) in migrations.iter().enumerate().skip(version as usize) {
+        let mut tx = pool.begin().await.map_err(failed)?;
+        for statement in statements.iter() {
+            sqlx::query(statement).execute(&mut *tx).await.map_err(failed)?;
+        }
+        sqlx::query("INSERT OR REPLACE INTO schema_versions (schema, version) VALUES (?, ?)")
+            .bind(schema)
+            .bind(index as i64 + 1)
+            .execute(&mut *tx)
+            .await
+            .map_err(failed)?;
+        tx.commit().await.map_err(failed)?;
+    }
+    Ok(())
+}
+
+/// Append the WHERE clause of a trade query
+fn push_trade_filters<'a, DB: sqlx::Database>(builder: &mut QueryBuilder<'a, DB>, query: &'a TradeQuery)
+where
+    String: sqlx::Encode<'a, DB> + sqlx::Type<DB>,
+    i64: sqlx::Encode<'a, DB> + sqlx::Type<DB>,
+{
+    builder.push("SELECT run_id, data FROM trades WHERE 1 = 1");
+    if let Some(run_id) = &query.run_id {
+        builder.push(" AND run_id = ").push_bind(run_id.clone());
+    }
+    if let Some(market_id) = &query.market_id {
+        builder.push(" AND market_id = ").push_bind(market_id.clone());
+    }
+    if let Some(since) = query.since {
+        builder.push(" AND entry_ms >= ").push_bind(since.timestamp_millis());
+    }
+    if let Some(until) = query.until {
+        builder.push(" AND entry_ms < ").push_bind(until.timestamp_millis());
+    }
+    builder.push(" ORDER BY entry_ms");
+    if let Some(limit) = query.limit {
+        builder.push(" LIMIT ").push_bind(limit as i64);
+    }
+}
+
+/// Append the WHERE clause of a step query
+fn push_step_filters<'a, DB: sqlx::Database>(builder: &mut QueryBuilder<'a, DB>, query: &'a StepQuery)
+where
+    String: sqlx::Encode<'a, DB> + sqlx::Type<DB>,
+    i64: sqlx::Encode<'a, DB> + sqlx::Type<DB>,
+{
+    builder.push("SELECT data FROM snapshots WHERE 1 = 1");
+    if let Some(run_id) = &query.run_id {
+        builder.push(" AND run_id = ").push_bind(run_id.clone());
+    }
+    if let Some(from) = query.from {
+        builder.push(" AND timestamp_ms >= ").push_bind(from.timestamp_millis());
+    }
+    if let Some(to) = query.to {
+        builder.push(" AND timestamp_ms < ").push_bind(to.timestamp_millis());
+    }
+    builder.push(" ORDER BY timestamp_ms, step");
+    if let Some(limit) = query.limit {
+        builder.push(" LIMIT ").push_bind(limit as i64);
+    }
+}
+
+/// Append the WHERE clause of a price query
+fn push_price_filters<'a, DB: sqlx::Database>(builder: &mut QueryBuilder<'a, DB>, query: &'a PriceQuery)
+where
+    String: sqlx::Encode<'a, DB> + sqlx::Type<DB>,
+    i64: sqlx::Encode<'a, DB> + sqlx::Type<DB>,
+{
+    builder.push("SELECT data FROM prices WHERE 1 = 1");
+    if let Some(run_id) = &query.run_id {
+        builder.push(" AND run_id = ").push_bind(run_id.clone());
+    }
+    if let Some(market_id) = &query.market_id {
+        builder.push(" AND market_id = ").push_bind(market_id.clone());
+    }
+    if let Some(from) = query.from {
+        builder.push(" AND timestamp_ms >= ").push_bind(from.timestamp_millis());
+    }
+    if let Some(to) = query.to {
+        builder.push(" AND timestamp_ms < ").push_bind(to.timestamp_millis());
+    }
+    builder.push(" ORDER BY timestamp_ms, id");
+    if let Some(limit) = query.limit {
+        builder.push(" LIMIT ").push_bind(limit as i64);
+    }
+}
+
+fn rows_to_trades<R: Row>(rows: Vec<R>) -> Result<Vec<StoredTrade>, String>
+where
+    for<'r> String: sqlx::Decode<'r, R::Database> + sqlx::Type<R::Database>,
+    for<'r> &'r str: sqlx::ColumnIndex<R>,
+{
+    rows.iter()
+        .map(|row| {
+            let run_id: String = row.try_get("run_id").map_err(|e| e.to_string())?;
+            let data: String = row.try_get("data").map_err(|e| e.to_string())?;
+            Ok(StoredTrade { run_id, trade: from_json(&data)? })
+        })
+        .collect()
+}
+
+/// SQLite database file
+#[derive(Debug, Clone)]
+pub struct SqliteStorage {
+    pool: SqlitePool,
+}
+
+impl SqliteStorage {
+    /// Open (creating if needed) the database at `path`
+    pub async fn open(path: &Path) -> Result<Self, String> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create database directory: {}", e))?;
+        }
+        let options = SqliteConnectOptions::from_str(&format!("sqlite://{}", path.display()))
+            .map_err(|e| format!("Invalid SQLite path: {}", e))?
+            .create_if_missing(true);
+        let pool = SqlitePoolOptions::new()
+            .connect_with(options)
+            .await
+            .map_err(|e| format!("Failed to open SQLite database: {}", e))?;
+        migrate_sqlite(&pool, "storage", STORAGE_MIGRATIONS).await?;
+        Ok(Self { pool })
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn save_trade<'a>(&'a self, run_id: &'a str, trade: &'a TradeExecution) -> BoxFuture<'a, Result<(), String>> {
+        async move {
+            sqlx::query("INSERT OR REPLACE INTO trades (run_id, trade_id, market_id, entry_ms, data) VALUES (?, ?, ?, ?, ?)")
+                .bind(run_id)
+                .bind(&trade.trade_id)
+                .bind(&trade.market_id)
+                .bind(trade.entry_time.timestamp_millis())
+                .bind(to_json(trade)?)
+                .execute(&self.pool)
+                .await
+                .map_err(|e| format!("Failed to save trade: {}", e))?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn save_snapshot<'a>(&'a self, snapshot: &'a RunSnapshot) -> BoxFuture<'a, Result<(), String>> {
+        async move {
+            sqlx::query("INSERT OR REPLACE INTO snapshots (run_id, step, timestamp_ms, data) VALUES (?, ?, ?, ?)")
+                .bind(&snapshot.run_id)
+                .bind(snapshot.step as i64)
+                .bind(snapshot.timestamp.timestamp_millis())
+                .bind(to_json(snapshot)?)
+                .execute(&self.pool)
+                .await
+                .map_err(|e| format!("Failed to save snapshot: {}", e))?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn load_run<'a>(&'a self, run_id: &'a str) -> BoxFuture<'a, Result<StoredRun, String>> {
+        async move {
+            let query = TradeQuery { run_id: Some(run_id.to_string()), ..Default::default() };
+            let trades = self.query(&query).await?.into_iter().map(|t| t.trade).collect();
+
+            let rows = sqlx::query("SELECT data FROM snapshots WHERE run_id = ? ORDER BY step")
+                .bind(run_id)
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| format!("Failed to load snapshots: {}", e))?;
+            let snapshots = rows
+                .iter()
+                .map(|row| from_json(row.get::<&str, _>("data")))
+                .collect::<Result<_, _>>()?;
+
+            Ok(StoredRun { run_id: run_id.to_string(), trades, snapshots })
+        }
+        .boxed()
+    }
+
+    fn query<'a>(&'a self, query: &'a TradeQuery) -> BoxFuture<'a, Result<Vec<StoredTrade>, String>> {
+        async move {
+            let mut builder = QueryBuilder::new("");
+            push_trade_filters(&mut builder, query);
+            let rows = builder
+                .build()
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| format!("Failed to query trades: {}", e))?;
+            rows_to_trades(rows)
+        }
+        .boxed()
+    }
+
+    fn query_steps<'a>(&'a self, query: &'a StepQuery) -> BoxFuture<'a, Result<Vec<RunSnapshot>, String>> {
+        async move {
+            let mut builder = QueryBuilder::new("");
+            push_step_filters(&mut builder, query);
+            let rows = builder
+                .build()
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| format!("Failed to query steps: {}", e))?;
+            rows.iter().map(|row| from_json(row.get::<&str, _>("data"))).collect()
+        }
+        .boxed()
+    }
+
+    fn save_prices<'a>(&'a self, prices: &'a [StoredPrice]) -> BoxFuture<'a, Result<(), String>> {
+        async move {
+            let mut tx = self.pool.begin().await.map_err(|e| format!("Failed to save prices: {}", e))?;
+            for price in prices {
+                sqlx::query("INSERT INTO prices (run_id, market_id, timestamp_ms, data) VALUES (?, ?, ?, ?)")
+                    .bind(&price.run_id)
+                    .bind(&price.market_id)
+                    .bind(price.snapshot.timestamp.timestamp_millis())
+                    .bind(to_json(price)?)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(|e| format!("Failed to save price: {}", e))?;
+            }
+            tx.commit().await.map_err(|e| format!("Failed to save prices: {}", e))
+        }
+        .boxed()
+    }
+
+    fn query_prices<'a>(&'a self, query: &'a PriceQuery) -> BoxFuture<'a, Result<Vec<StoredPrice>, String>> {
+        async move {
+            let mut builder = QueryBuilder::new("");
+            push_price_filters(&mut builder, query);
+            let rows = builder
+                .build()
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| format!("Failed to query prices: {}", e))?;
+            rows.iter().map(|row| from_json(row.get::<&str, _>("data"))).collect()
+        }
+        .boxed()
+    }
+
+    fn save_user<'a>(&'a self, user: &'a UserAccount) -> BoxFuture<'a, Result<(), String>> {
+        async move {
+            sqlx::query("INSERT OR REPLACE INTO users (username, data) VALUES (?, ?)")
+                .bind(&user.username)
+                .bind(to_json(user)?)
+                .execute(&self.pool)
+                .await
+                .map_err(|e| format!("Failed to save user: {}", e))?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn load_user<'a>(&'a self, username: &'a str) -> BoxFuture<'a, Result<Option<UserAccount>, String>> {
+        async move {
+            let row = sqlx::query("SELECT data FROM users WHERE username = ?")
+                .bind(username)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(|e| format!("Failed to load user: {}", e))?;
+            row.map(|row| from_json(row.get::<&str, _>("data"))).transpose()
+        }
+        .boxed()
+    }
+
+    fn list_users(&self) -> BoxFuture<'_, Result<Vec<UserAccount>, String>> {
+        async move {
+            let rows = sqlx::query("SELECT data FROM users ORDER BY username")
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| format!("Failed to list users: {}", e))?;
+            rows.iter().map(|row| from_json(row.get::<&str, _>("data"))).collect()
+        }
+        .boxed()
+    }
+
+    fn delete_user<'a>(&'a self, username: &'a str) -> BoxFuture<'a, Result<bool, String>> {
+        async move {
+            let result = sqlx::query("DELETE FROM users WHERE username = ?")
+                .bind(username)
+                .execute(&self.pool)
+                .await
+                .map_err(|e| format!("Failed to delete user: {}", e))?;
+            Ok(result.rows_affected() > 0)
+        }
+        .boxed()
+    }
+}
+
+const OPPORTUNITIES_TABLE: &str = "CREATE TABLE IF NOT EXISTS opportunities (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    timestamp_ms BIGINT NOT NULL,
+    market_id TEXT NOT NULL,
+    arb_type TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    data TEXT NOT NULL
+)";
+
+/// SQLite table of detected candidates, kept and dropped, for offline analysis
+#[derive(Debug, Clone)]
+pub struct OpportunityStore {
+    pool: SqlitePool,
+}
+
+impl OpportunityStore {
+    /// Open (creating if needed) the database at `path`
+    pub async fn open(path: &Path) -> Result<Self, String> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create database directory: {}", e))?;
+        }
+        let options = SqliteConnectOptions::from_str(&format!("sqlite://{}", path.display()))
+            .map_err(|e| format!("Invalid SQLite path: {}", e))?
+            .create_if_missing(true);
+        let pool = SqlitePoolOptions::new()
+            .connect_with(options)
+            .await
+            .map_err(|e| format!("Failed to open SQLite database: {}", e))?;
+        migrate_sqlite(&pool, "opportunity_history", &[&[OPPORTUNITIES_TABLE]]).await?;
+        Ok(Self { pool })
+    }
+
+    /// Append records in one transaction
+    pub async fn save(&self, records: &[OpportunityRecord]) -> Result<(), String> {
+        let mut tx = self.pool.begin().await.map_err(|e| format!("Failed to save opportunities: {}", e))?;
+        for record in records {
+            sqlx::query("INSERT INTO opportunities (timestamp_ms, market_id, arb_type, reason, data) VALUES (?, ?, ?, ?, ?)")
+                .bind(record.timestamp.timestamp_millis())
+                .bind(&record.market_id)
+                .bind(format!("{:?}", record.arb_type))
+                .bind(record.reason.as_str())
+                .bind(to_json(record)?)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| format!("Failed to save opportunity: {}", e))?;
+        }
+        tx.commit().await.map_err(|e| format!("Failed to save opportunities: {}", e))
+    }
+
+    /// Records matching a query, oldest first; with a limit, the most recent ones
+    pub async fn query(&self, query: &OpportunityQuery) -> Result<Vec<OpportunityRecord>, String> {
+        let mut builder: QueryBuilder<sqlx::Sqlite> = QueryBuilder::new("SELECT data FROM opportunities WHERE 1 = 1");
+        if let Some(market_id) = &query.market_id {
+            builder.push(" AND market_id = ").push_bind(market_id.clone());
+        }
+        if let Some(arb_type) = query.arb_type {
+            builder.push(" AND arb_type = ").push_bind(format!("{:?}", arb_type));
+        }
+        if let Some(reason) = query.reason {
+            builder.push(" AND reason = ").push_bind(reason.as_str());
+        }
+        if let Some(since) = query.since {
+            builder.push(" AND timestamp_ms >= ").push_bind(since.timestamp_millis());
+        }
+        if let Some(until) = query.until {
+            builder.push(" AND timestamp_ms < ").push_bind(until.timestamp_millis());
+        }
+        builder.push(" ORDER BY timestamp_ms DESC, id DESC");
+        if let Some(limit) = query.limit {
+            builder.push(" LIMIT ").push_bind(limit as i64);
+        }
+        let rows = builder
+            .build()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to query opportunities: {}", e))?;
+        let mut records = rows.iter().map(|row| from_json(row.get::<&str, _>("data"))).collect::<Result<Vec<_>, _>>()?;
+        records.reverse();
+        Ok(records)
+    }
+}
+
+/// Schema migrations for the Postgres backend, embedded at compile time
+#[cfg(feature = "postgres")]
+pub static PG_MIGRATOR: sqlx::migrate::Migrator = sqlx::migrate!("./migrations/postgres");
+
+/// Postgres database shared by several users
+#[cfg(feature = "postgres")]
+#[derive(Debug, Clone)]
+pub struct PostgresStorage {
+    pool: PgPool,
+}
+
+#[cfg(feature = "postgres")]
+impl PostgresStorage {
+    /// Connect and bring the schema up to date
+    pub async fn connect(url: &str, config: &PgPoolConfig) -> Result<Self, String> {
+        let max_connections = config.max_connections.max(1);
+        let pool = PgPoolOptions::new()
+            .max_connections(max_connections)
+            .min_connections(config.min_connections.min(max_connections))
+            .acquire_timeout(Duration::from_secs(config.acquire_timeout_secs))
+            .idle_timeout(Duration::from_secs(config.idle_timeout_secs))
+            .connect(url)
+            .await
+            .map_err(|e| format!("Failed to connect to Postgres: {}", e))?;
+
+        // Le migrazioni prendono un advisory lock: più istanze possono avviarsi insieme
+        PG_MIGRATOR
+            .run(&pool)
+            .await
+            .map_err(|e| format!("Failed to migrate Postgres schema: {}", e))?;
+        Ok(Self { pool })
+    }
+
+    pub fn pool(&self) -> &PgPool {
+        &self.pool
+    }
+}
+
+#[cfg(feature = "postgres")]
+impl Storage for PostgresStorage {
+    fn save_trade<'a>(&'a self, run_id: &'a str, trade: &'a TradeExecution) -> BoxFuture<'a, Result<(), String>> {
+        async move {
+            sqlx::query(
+                "INSERT INTO trades (run_id, trade_id, market_id, entry_ms, data) VALUES ($1, $2, $3, $4, $5)
+                 ON CONFLICT (run_id, trade_id) DO UPDATE SET data = EXCLUDED.data",
+            )
+            .bind(run_id)
+            .bind(&trade.trade_id)
+            .bind(&trade.market_id)
+            .bind(trade.entry_time.timestamp_millis())
+            .bind(to_json(trade)?)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to save trade: {}", e))?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn save_snapshot<'a>(&'a self, snapshot: &'a RunSnapshot) -> BoxFuture<'a, Result<(), String>> {
+        async move {
+            sqlx::query(
+                "INSERT INTO snapshots (run_id, step, timestamp_ms, data) VALUES ($1, $2, $3, $4)
+                 ON CONFLICT (run_id, step) DO UPDATE SET data = EXCLUDED.data",
+            )
+            .bind(&snapshot.run_id)
+            .bind(snapshot.step as i64)
+            .bind(snapshot.timestamp.timestamp_millis())
+            .bind(to_json(snapshot)?)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| format!("Failed to save snapshot: {}", e))?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn load_run<'a>(&'a self, run_id: &'a str) -> BoxFuture<'a, Result<StoredRun, String>> {
+        async move {
+            let query = TradeQuery { run_id: Some(run_id.to_string()), ..Default::default() };
+            let trades = self.query(&query).await?.into_iter().map(|t| t.trade).collect();
+
+            let rows = sqlx::query("SELECT data FROM snapshots WHERE run_id = $1 ORDER BY step")
+                .bind(run_id)
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| format!("Failed to load snapshots: {}", e))?;
+            let snapshots = rows
+                .iter()
+                .map(|row| from_json(row.get::<&str, _>("data")))
+                .collect::<Result<_, _>>()?;
+
+            Ok(StoredRun { run_id: run_id.to_string(), trades, snapshots })
+        }
+        .boxed()
+    }
+
+    fn query<'a>(&'a self, query: &'a TradeQuery) -> BoxFuture<'a, Result<Vec<StoredTrade>, String>> {
+        async move {
+            let mut builder = QueryBuilder::new("");
+            push_trade_filters(&mut builder, query);
+            let rows = builder
+                .build()
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| format!("Failed to query trades: {}", e))?;
+            rows_to_trades(rows)
+        }
+        .boxed()
+    }
+
+    fn query_steps<'a>(&'a self, query: &'a StepQuery) -> BoxFuture<'a, Result<Vec<RunSnapshot>, String>> {
+        async move {
+            let mut builder = QueryBuilder::new("");
+            push_step_filters(&mut builder, query);
+            let rows = builder
+                .build()
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| format!("Failed to query steps: {}", e))?;
+            rows.iter().map(|row| from_json(row.get::<&str, _>("data"))).collect()
+        }
+        .boxed()
+    }
+
+    fn save_prices<'a>(&'a self, prices: &'a [StoredPrice]) -> BoxFuture<'a, Result<(), String>> {
+        async move {
+            let mut tx = self.pool.begin().await.map_err(|e| format!("Failed to save prices: {}", e))?;
+            for price in prices {
+                sqlx::query("INSERT INTO prices (run_id, market_id, timestamp_ms, data) VALUES ($1, $2, $3, $4)")
+                    .bind(&price.run_id)
+                    .bind(&price.market_id)
+                    .bind(price.snapshot.timestamp.timestamp_millis())
+                    .bind(to_json(price)?)
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(|e| format!("Failed to save price: {}", e))?;
+            }
+            tx.commit().await.map_err(|e| format!("Failed to save prices: {}", e))
+        }
+        .boxed()
+    }
+
+    fn query_prices<'a>(&'a self, query: &'a PriceQuery) -> BoxFuture<'a, Result<Vec<StoredPrice>, String>> {
+        async move {
+            let mut builder = QueryBuilder::new("");
+            push_price_filters(&mut builder, query);
+            let rows = builder
+                .build()
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| format!("Failed to query prices: {}", e))?;
+            rows.iter().map(|row| from_json(row.get::<&str, _>("data"))).collect()
+        }
+        .boxed()
+    }
+
+    fn save_user<'a>(&'a self, user: &'a UserAccount) -> BoxFuture<'a, Result<(), String>> {
+        async move {
+            sqlx::query("INSERT INTO users (username, data) VALUES ($1, $2)
+                 ON CONFLICT (username) DO UPDATE SET data = EXCLUDED.data")
+                .bind(&user.username)
+                .bind(to_json(user)?)
+                .execute(&self.pool)
+                .await
+                .map_err(|e| format!("Failed to save user: {}", e))?;
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn load_user<'a>(&'a self, username: &'a str) -> BoxFuture<'a, Result<Option<UserAccount>, String>> {
+        async move {
+            let row = sqlx::query("SELECT data FROM users WHERE username = $1")
+                .bind(username)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(|e| format!("Failed to load user: {}", e))?;
+            row.map(|row| from_json(row.get::<&str, _>("data"))).transpose()
+        }
+        .boxed()
+    }
+
+    fn list_users(&self) -> BoxFuture<'_, Result<Vec<UserAccount>, String>> {
+        async move {
+            let rows = sqlx::query("SELECT data FROM users ORDER BY username")
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| format!("Failed to list users: {}", e))?;
+            rows.iter().map(|row| from_json(row.get::<&str, _>("data"))).collect()
+        }
+        .boxed()
+    }
+
+    fn delete_user<'a>(&'a self, username: &'a str) -> BoxFuture<'a, Result<bool, String>> {
+        async move {
+            let result = sqlx::query("DELETE FROM users WHERE username = $1")
+                .bind(username)
+                .execute(&self.pool)
+                .await
+                .map_err(|e| format!("Failed to delete user: {}", e))?;
+            Ok(result.rows_affected() > 0)
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ArbType;
+
+    fn trade(id: &str, market_id: &str, minute: i64) -> TradeExecution {
+        let entry_time = DateTime::from_timestamp(1_700_000_000 + minute * 60, 0).unwrap();
+        TradeExecution {
+            trade_id: id.to_string(),
+            market_id: market_id.to_string(),
+            arb_type: ArbType::YesNoSimple,
+            legs: Vec::new(),
+            total_investment: 100.0,
+            expected_return: 102.0,
+            actual_return: 102.0,
+            profit: 2.0,
+            roi_pct: 2.0,
+            entry_time,
+            exit_time: entry_time,
+            execution_time_ms: 5,
+            slippage_pct: 0.0,
+            gas_cost: 0.0,
+            fees: 0.0,
+        }
+    }
+
+    async fn exercise(storage: SharedStorage) {
+        storage.save_trade("run-a", &trade("t2", "m1", 2)).await.unwrap();
+        storage.save_trade("run-a", &trade("t1", "m2", 1)).await.unwrap();
+        storage.save_trade("run-b", &trade("t3", "m1", 3)).await.unwrap();
+
+        let result = StepResult { step: 1, opportunities: 4, trades: 1, profit: 2.0, capital: 1002.0, win_rate: 1.0, avg_edge: 0.02, expectancy: 2.0 };
+        let snapshot = RunSnapshot::from_step("run-a", &result, DateTime::from_timestamp(1_700_000_000, 0).unwrap(), 12);
+        storage.save_snapshot(&snapshot).await.unwrap();
+        let later = StepResult { step: 2, trades: 0, profit: 0.0, ..result.clone() };
+        storage.save_snapshot(&RunSnapshot::from_step("run-b", &later, DateTime::from_timestamp(1_700_000_060, 0).unwrap(), 8)).await.unwrap();
+
+        let run = storage.load_run("run-a").await.unwrap();
+        assert_eq!(run.trades.iter().map(|t| t.trade_id.as_str()).collect::<Vec<_>>(), vec!["t1", "t2"]);
+        assert_eq!(run.snapshots[0].capital, 1002.0);
+        assert!(storage.load_run("missing").await.unwrap().trades.is_empty());
+
+        let query = TradeQuery { market_id: Some("m1".to_string()), ..Default::default() };
+        let m1 = storage.query(&query).await.unwrap();
+        assert_eq!(m1.iter().map(|t| t.run_id.as_str()).collect::<Vec<_>>(), vec!["run-a", "run-b"]);
+
+        let query = TradeQuery {
+            since: Some(DateTime::from_timestamp(1_700_000_000 + 90, 0).unwrap()),
+            limit: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(storage.query(&query).await.unwrap()[0].trade.trade_id, "t2");
+
+        let steps = storage.query_steps(&StepQuery::default()).await.unwrap();
+        assert_eq!(steps.iter().map(|s| (s.run_id.as_str(), s.latency_ms)).collect::<Vec<_>>(), vec![("run-a", 12), ("run-b", 8)]);
+        let query = StepQuery { from: Some(DateTime::from_timestamp(1_700_000_030, 0).unwrap()), ..Default::default() };
+        assert_eq!(storage.query_steps(&query).await.unwrap()[0].step, 2);
+        let query = StepQuery { run_id: Some("run-a".to_string()), to: Some(DateTime::from_timestamp(1_700_000_000, 0).unwrap()), ..Default::default() };
+        assert!(storage.query_steps(&query).await.unwrap().is_empty());
+
+        let price = |run_id: &str, market_id: &str, second: i64, yes_price: f64| StoredPrice {
+            run_id: run_id.to_string(),
+            market_id: market_id.to_string(),
+            snapshot: PriceSnapshot { timestamp: DateTime::from_timestamp(1_700_000_000 + second, 0).unwrap(), yes_price, no_price: 1.0 - yes_price, volume: 100.0 },
+        };
+        storage.save_prices(&[price("run-a", "m1", 60, 0.40), price("run-a", "m2", 0, 0.55), price("run-b", "m1", 30, 0.45)]).await.unwrap();
+        let query = PriceQuery { market_id: Some("m1".to_string()), ..Default::default() };
+        let m1 = storage.query_prices(&query).await.unwrap();
+        assert_eq!(m1.iter().map(|p| (p.run_id.as_str(), p.snapshot.yes_price)).collect::<Vec<_>>(), vec![("run-b", 0.45), ("run-a", 0.40)]);
+        let query = PriceQuery { run_id: Some("run-a".to_string()), to: Some(DateTime::from_timestamp(1_700_000_060, 0).unwrap()), ..Default::default() };
+        assert_eq!(storage.query_prices(&query).await.unwrap().iter().map(|p| p.market_id.as_str()).collect::<Vec<_>>(), vec!["m2"]);
+    }
+
+    #[tokio::test]
+    async fn test_flat_file_and_sqlite_backends() {
+        let dir = std::env::temp_dir().join(format!("storage_{}", uuid::Uuid::new_v4()));
+
+        exercise(open_storage(&StorageConfig::FlatFile { dir: dir.join("runs") }).await.unwrap()).await;
+        exercise(open_storage(&StorageConfig::Sqlite { path: dir.join("bot.db") }).await.unwrap()).await;
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_schemas_are_versioned_per_database() {
+        let path = std::env::temp_dir().join(format!("versions_{}.db", uuid::Uuid::new_v4()));
+
+        // Database precedente al versioning: tabelle senza versione registrata, dati conservati
+        let legacy = SqlitePool::connect_with(SqliteConnectOptions::from_str(&format!("sqlite://{}", path.display())).unwrap().create_if_missing(true)).await.unwrap();
+        sqlx::query(TRADES_TABLE).execute(&legacy).await.unwrap();
+        sqlx::query("INSERT INTO trades (run_id, trade_id, market_id, entry_ms, data) VALUES (?, ?, ?, ?, ?)")
+            .bind("run-a").bind("t1").bind("m1").bind(0_i64).bind(to_json(&trade("t1", "m1", 0)).unwrap())
+            .execute(&legacy).await.unwrap();
+        legacy.close().await;
+
+        let storage = SqliteStorage::open(&path).await.unwrap();
+        assert_eq!(storage.load_run("run-a").await.unwrap().trades.len(), 1);
+        OpportunityStore::open(&path).await.unwrap();
+        let versions: Vec<(String, i64)> = sqlx::query("SELECT schema, version FROM schema_versions ORDER BY schema")
+            .fetch_all(&storage.pool).await.unwrap()
+            .iter().map(|row| (row.get("schema"), row.get("version"))).collect();
+        assert_eq!(versions, vec![("opportunity_history".to_string(), 1), ("storage".to_string(), STORAGE_MIGRATIONS.len() as i64)]);
+
+        // Migrato da una build successiva: rifiutato invece di essere riscritto
+        sqlx::query("UPDATE schema_versions SET version = 99 WHERE schema = 'storage'").execute(&storage.pool).await.unwrap();
+        assert!(SqliteStorage::open(&path).await.unwrap_err().contains("upgrade the crate"));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_opportunity_store_archives_and_filters_records() {
+        use crate::opportunity_log::FilterReason;
+
+        let path = std::env::temp_dir().join(format!("opportunities_{}.db", uuid::Uuid::new_v4()));
+        let store = OpportunityStore::open(&path).await.unwrap();
+        let record = |market_id: &str, second: i64, reason: FilterReason| OpportunityRecord::candidate(
+            market_id, ArbType::YesNoSimple, DateTime::from_timestamp(1_700_000_000 + second, 0).unwrap(), 0.01, 500.0, 0.02, reason,
+        );
+        store.save(&[
+            record("m1", 0, FilterReason::MinProfit),
+            record("m2", 1, FilterReason::Accepted),
+            record("m1", 2, FilterReason::Liquidity),
+        ]).await.unwrap();
+
+        let m1 = store.query(&OpportunityQuery { market_id: Some("m1".to_string()), ..Default::default() }).await.unwrap();
+        assert_eq!(m1.iter().map(|r| r.reason).collect::<Vec<_>>(), vec![FilterReason::MinProfit, FilterReason::Liquidity]);
+        let accepted = store.query(&OpportunityQuery { reason: Some(FilterReason::Accepted), arb_type: Some(ArbType::YesNoSimple), ..Default::default() }).await.unwrap();
+        assert_eq!(accepted, vec![record("m2", 1, FilterReason::Accepted)]);
+        let latest = store.query(&OpportunityQuery { limit: Some(2), ..Default::default() }).await.unwrap();
+        assert_eq!(latest.iter().map(|r| r.market_id.as_str()).collect::<Vec<_>>(), vec!["m2", "m1"]);
+        std::fs::remove_file(path).ok();
+    }
+
+    /// Runs only against a throwaway database named in POSTGRES_TEST_URL (its tables are emptied)
+    #[tokio::test]
+    #[cfg(feature = "postgres")]
+    async fn test_postgres_backend() {
+        let Ok(url) = std::env::var("POSTGRES_TEST_URL") else { return };
+
+        let storage = PostgresStorage::connect(&url, &PgPoolConfig::default()).await.unwrap();
+        sqlx::query("TRUNCATE trades, snapshots, prices").execute(storage.pool()).await.unwrap();
+        // Rieseguire le migrazioni su uno schema aggiornato non fa nulla
+        PG_MIGRATOR.run(storage.pool()).await.unwrap();
+
+        exercise(Arc::new(storage)).await;
+    }
+
+    #[test]
+    fn test_storage_config_defaults() {
+        let config: StorageConfig = serde_json::from_value(serde_json::json!({
+            "backend": "postgres",
+            "url": "postgres://bot@localhost/dashboard",
+            "pool": { "max_connections": 32 }
+        })).unwrap();
+
+        let history: StepHistoryConfig = serde_json::from_value(serde_json::json!({ "every_n_steps": 10 })).unwrap();
+        assert!(history.enabled);
+        let step = |step, trades| StepResult { step, opportunities: 0, trades, profit: 0.0, capital: 0.0, win_rate: 0.0, avg_edge: 0.0, expectancy: 0.0 };
+        assert!(history.should_persist(&step(20, 0)));
+        assert!(!history.should_persist(&step(21, 0)));
+        assert!(history.should_persist(&step(21, 1)));
+        // Feed della dashboard: nessuno step salvato senza configurazione esplicita
+        let feed = StepHistoryConfig::dashboard_feed();
+        assert!(!feed.is_due(60) && !feed.should_persist(&step(60, 0)));
+
+        let StorageConfig::Postgres { pool, .. } = config else { panic!("expected postgres config") };
+        assert_eq!(pool.max_connections, 32);
+        assert_eq!(pool.acquire_timeout_secs, PgPoolConfig::default().acquire_timeout_secs);
+        #[cfg(feature = "postgres")]
+        assert!(PG_MIGRATOR.iter().count() >= 1);
+
+        // Run id da una richiesta: niente separatori o risalite di directory
+        assert!(validate_run_id(&uuid::Uuid::new_v4().to_string()).is_ok());
+        for invalid in ["", "../users", "a/b", "run id", &"x".repeat(65)] {
+            assert!(validate_run_id(invalid).is_err(), "{}", invalid);
+        }
+    }
+}
@@ -0,0 +1,236 @@
+//! Symbol mapping module
+//!
+//! Implements:
+//! 1. Registry of venue identifiers (condition ids, token ids, tickers) per internal market and outcome
+//! 2. Reverse lookup from any venue identifier to the internal market and outcome
+//! 3. Registration of the Polymarket identifiers carried by the market feed
+//! 4. JSON persistence, so every component resolves the same identities across restarts
+
+use crate::types::{MarketData, TokenType};
+use crate::venues::POLYMARKET_VENUE;
+use crate::versioning::{load_document, to_document, Schema};
+use fxhash::FxHashMap;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Default registry location
+pub const SYMBOL_REGISTRY_PATH: &str = "./data/symbols.json";
+
+/// Kind of a venue identifier
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SymbolKind {
+    ConditionId, // Condition id CTF del mercato (Polymarket, on-chain)
+    TokenId,     // Asset id CLOB di un esito
+    Ticker,      // Ticker di mercato (es. Kalshi)
+}
+
+/// One venue identifier of an internal market, or of one of its outcomes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SymbolMapping {
+    pub market_id: String,
+    #[serde(default)]
+    pub outcome: Option<TokenType>, // None = identificativo del mercato intero
+    pub venue: String,
+    pub kind: SymbolKind,
+    pub symbol: String,
+}
+
+impl SymbolMapping {
+    fn key(&self) -> MappingKey {
+        (self.market_id.clone(), self.outcome, self.venue.clone(), self.kind)
+    }
+}
+
+type MappingKey = (String, Option<TokenType>, String, SymbolKind);
+
+/// Schema of the symbol registry file
+pub const SYMBOLS_SCHEMA: Schema = Schema::new("symbols", &[]);
+
+/// Venue identifiers by internal id and internal ids by venue identifier, persisted as JSON
+#[derive(Debug, Clone, Default)]
+pub struct SymbolRegistry {
+    mappings: FxHashMap<MappingKey, SymbolMapping>,
+    by_symbol: FxHashMap<(String, String), MappingKey>, // (venue, simbolo) -> mapping
+    pub path: Option<PathBuf>,
+}
+
+impl SymbolRegistry {
+    /// Load the registry from file (empty if the file does not exist yet)
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, String> {
+        let path = path.into();
+        let mut registry = Self { path: Some(path.clone()), ..Self::default() };
+
+        let entries: Option<Vec<SymbolMapping>> = load_document(&SYMBOLS_SCHEMA, &path)
+            .map_err(|e| format!("Failed to load symbol registry: {}", e))?;
+        for entry in entries.unwrap_or_default() {
+            registry.insert(entry)?;
+        }
+
+        Ok(registry)
+    }
+
+    /// Persist the registry to its file (no-op for in-memory registries)
+    pub fn save(&self) -> Result<(), String> {
+        let Some(path) = &self.path else { return Ok(()) };
+
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create symbol registry directory: {}", e))?;
+        }
+        let content = to_document(&SYMBOLS_SCHEMA, &self.list())
+            .map_err(|e| format!("Failed to serialize symbol registry: {}", e))?;
+        std::fs::write(path, content)
+            .map_err(|e| format!("Failed to write symbol registry: {}", e))
+    }
+
+    /// Add or replace a mapping without persisting; returns whether anything changed
+    ///
+    /// A venue symbol identifies one market/outcome only: mapping it to another is an error.
+    fn insert(&mut self, mapping: SymbolMapping) -> Result<bool, String> {
+        let key = mapping.key();
+        let symbol_key = (mapping.venue.clone(), mapping.symbol.clone());
+        if let Some(owner) = self.by_symbol.get(&symbol_key).filter(|owner| **owner != key) {
+            return Err(format!("{} symbol {} is already mapped to market {}", mapping.venue, mapping.symbol, owner.0));
+        }
+        if self.mappings.get(&key) == Some(&mapping) {
+            return Ok(false);
+        }
+        if let Some(previous) = self.mappings.insert(key.clone(), mapping) {
+            self.by_symbol.remove(&(previous.venue, previous.symbol));
+        }
+        self.by_symbol.insert(symbol_key, key);
+        Ok(true)
+    }
+
+    /// Map a venue identifier and persist
+    pub fn register(&mut self, mapping: SymbolMapping) -> Result<(), String> {
+        if self.insert(mapping)? {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// Register the condition id and token ids of markets from the Polymarket feed and persist once
+    ///
+    /// Returns how many mappings were added or changed.
+    pub fn register_markets<'a>(&mut self, markets: impl IntoIterator<Item = &'a MarketData>) -> Result<usize, String> {
+        let mut changed = 0;
+        for market in markets {
+            let Some(tokens) = &market.tokens else { continue };
+            let mapping = |outcome: Option<TokenType>, kind: SymbolKind, symbol: &str| SymbolMapping {
+                market_id: market.id.clone(),
+                outcome,
+                venue: POLYMARKET_VENUE.to_string(),
+                kind,
+                symbol: symbol.to_string(),
+            };
+            let mut entries = vec![
+                mapping(Some(TokenType::Yes), SymbolKind::TokenId, &tokens.yes_token_id),
+                mapping(Some(TokenType::No), SymbolKind::TokenId, &tokens.no_token_id),
+            ];
+            if let Some(condition_id) = &tokens.condition_id {
+                entries.push(mapping(None, SymbolKind::ConditionId, condition_id));
+            }
+            for entry in entries {
+                changed += usize::from(self.insert(entry)?);
+            }
+        }
+        if changed > 0 {
+            self.save()?;
+        }
+        Ok(changed)
+    }
+
+    /// Market and outcome behind a venue identifier
+    pub fn resolve(&self, venue: &str, symbol: &str) -> Option<&SymbolMapping> {
+        let key = self.by_symbol.get(&(venue.to_string(), symbol.to_string()))?;
+        self.mappings.get(key)
+    }
+
+    /// Venue identifier of an internal market (`outcome` None) or outcome
+    pub fn symbol(&self, market_id: &str, outcome: Option<TokenType>, venue: &str, kind: SymbolKind) -> Option<&str> {
+        self.mappings
+            .get(&(market_id.to_string(), outcome, venue.to_string(), kind))
+            .map(|m| m.symbol.as_str())
+    }
+
+    /// Every identifier of an internal market, across venues
+    pub fn mappings_for(&self, market_id: &str) -> Vec<SymbolMapping> {
+        self.list().into_iter().filter(|m| m.market_id == market_id).collect()
+    }
+
+    /// Drop every identifier of a market and persist; returns how many were removed
+    pub fn remove_market(&mut self, market_id: &str) -> Result<usize, String> {
+        let before = self.mappings.len();
+        self.mappings.retain(|key, _| key.0 != market_id);
+        self.by_symbol.retain(|_, key| key.0 != market_id);
+        let removed = before - self.mappings.len();
+        if removed > 0 {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    /// Mappings ordered by market, venue, kind and outcome
+    pub fn list(&self) -> Vec<SymbolMapping> {
+        let mut entries: Vec<SymbolMapping> = self.mappings.values().cloned().collect();
+        entries.sort_by(|a, b| {
+            (&a.market_id, &a.venue, a.kind, a.outcome.map(|o| o == TokenType::No))
+                .cmp(&(&b.market_id, &b.venue, b.kind, b.outcome.map(|o| o == TokenType::No)))
+        });
+        entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.mappings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mappings.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::TokenPair;
+
+    #[test]
+    fn test_registry_resolves_and_persists_venue_symbols() {
+        let path = std::env::temp_dir().join(format!("symbols_{}.json", uuid::Uuid::new_v4()));
+        let mut registry = SymbolRegistry::load(&path).unwrap();
+        let market = MarketData {
+            id: "m1".to_string(),
+            tokens: Some(TokenPair { yes_token_id: "111".to_string(), no_token_id: "222".to_string(), condition_id: Some("0xabc".to_string()) }),
+            ..MarketData::default()
+        };
+        assert_eq!(registry.register_markets(std::slice::from_ref(&market)).unwrap(), 3);
+        assert_eq!(registry.register_markets(&[market]).unwrap(), 0);
+        registry.register(SymbolMapping {
+            market_id: "m1".to_string(),
+            outcome: None,
+            venue: "kalshi".to_string(),
+            kind: SymbolKind::Ticker,
+            symbol: "PRES-24-DEM".to_string(),
+        }).unwrap();
+
+        // Un token già mappato non può passare a un altro mercato
+        let conflict = SymbolMapping {
+            market_id: "m2".to_string(),
+            outcome: Some(TokenType::Yes),
+            venue: POLYMARKET_VENUE.to_string(),
+            kind: SymbolKind::TokenId,
+            symbol: "111".to_string(),
+        };
+        assert!(registry.register(conflict).is_err());
+
+        let reloaded = SymbolRegistry::load(&path).unwrap();
+        let no = reloaded.resolve(POLYMARKET_VENUE, "222").unwrap();
+        assert_eq!((no.market_id.as_str(), no.outcome), ("m1", Some(TokenType::No)));
+        assert_eq!(reloaded.symbol("m1", None, "kalshi", SymbolKind::Ticker), Some("PRES-24-DEM"));
+        assert_eq!(reloaded.symbol("m1", None, POLYMARKET_VENUE, SymbolKind::ConditionId), Some("0xabc"));
+        assert_eq!(reloaded.mappings_for("m1").len(), 4);
+        std::fs::remove_file(path).ok();
+    }
+}
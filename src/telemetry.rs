@@ -0,0 +1,289 @@
+//! Telemetry module
+//!
+//! Implements:
+//! 1. Prometheus metrics of the paper ledger, all labeled by strategy, market category and venue
+//! 2. Label normalization, so the same strategy or category never splits across spellings
+//! 3. Grafana dashboard definition generated from the registered metrics, with one template
+//!    variable per label
+
+use crate::paper::{MarketInfo, PaperPosition, SimulatedTrade, TradeSource};
+use crate::venues::POLYMARKET_VENUE;
+use prometheus::core::Collector;
+use prometheus::{CounterVec, Encoder, GaugeVec, IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder};
+use serde::Serialize;
+use serde_json::json;
+
+/// Labels carried by every ledger metric, in this order
+pub const METRIC_LABELS: [&str; 3] = ["strategy", "category", "venue"];
+
+/// Prefix of every exported metric
+pub const METRIC_NAMESPACE: &str = "polymarket";
+
+/// Label value for markets without a Gamma category
+const UNCATEGORIZED: &str = "uncategorized";
+
+/// Kind of a registered metric, as Grafana needs to query it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricKind {
+    Counter,
+    Gauge,
+}
+
+/// Name, help and labels of a registered metric
+#[derive(Debug, Clone, Serialize)]
+pub struct MetricDescriptor {
+    pub name: String,
+    pub help: String,
+    pub kind: MetricKind,
+    pub labels: Vec<String>,
+}
+
+/// Label value: lowercase, words joined by underscores
+pub fn normalize_label(value: &str) -> String {
+    value
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// Strategy label of a paper trade or position
+pub fn strategy_label(source: TradeSource) -> &'static str {
+    match source {
+        TradeSource::Bot => "arbitrage",
+        TradeSource::Manual => "manual",
+    }
+}
+
+/// Category label of a market: its normalized Gamma category, or `uncategorized`
+fn category_label(market_id: &str, markets: &[MarketInfo]) -> String {
+    markets
+        .iter()
+        .find(|m| m.id == market_id)
+        .and_then(|m| m.category.as_deref())
+        .map(normalize_label)
+        .filter(|c| !c.is_empty())
+        .unwrap_or_else(|| UNCATEGORIZED.to_string())
+}
+
+/// Metrics registry of the dashboard: fills counted as they are booked, gauges refreshed on each scrape
+#[derive(Clone)]
+pub struct DashboardMetrics {
+    registry: Registry,
+    descriptors: Vec<MetricDescriptor>,
+    trades: IntCounterVec,
+    notional: CounterVec,
+    realized_pnl: GaugeVec,
+    open_positions: IntGaugeVec,
+    position_cost: GaugeVec,
+    balance: GaugeVec,
+}
+
+impl Default for DashboardMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DashboardMetrics {
+    pub fn new() -> Self {
+        let registry = Registry::new();
+        let mut descriptors = Vec::new();
+        let opts = |name: &str, help: &str| Opts::new(name, help).namespace(METRIC_NAMESPACE);
+
+        let trades = IntCounterVec::new(opts("trades_total", "Filled paper trades"), &METRIC_LABELS).unwrap();
+        let notional = CounterVec::new(opts("traded_notional_usd_total", "Traded notional (USD)"), &METRIC_LABELS).unwrap();
+        let realized_pnl = GaugeVec::new(opts("realized_pnl_usd", "Realized PnL (USD)"), &METRIC_LABELS).unwrap();
+        let open_positions = IntGaugeVec::new(opts("open_positions", "Open positions"), &METRIC_LABELS).unwrap();
+        let position_cost = GaugeVec::new(opts("position_cost_usd", "Cost basis of open positions (USD)"), &METRIC_LABELS).unwrap();
+        let balance = GaugeVec::new(opts("balance_usd", "Cash balance (USD)"), &["venue"]).unwrap();
+
+        let mut register = |collector: Box<dyn Collector>, kind: MetricKind| {
+            for desc in collector.desc() {
+                descriptors.push(MetricDescriptor {
+                    name: desc.fq_name.clone(),
+                    help: desc.help.clone(),
+                    kind,
+                    labels: desc.variable_labels.clone(),
+                });
+            }
+            registry.register(collector).expect("metric names are unique");
+        };
+        register(Box::new(trades.clone()), MetricKind::Counter);
+        register(Box::new(notional.clone()), MetricKind::Counter);
+        register(Box::new(realized_pnl.clone()), MetricKind::Gauge);
+        register(Box::new(open_positions.clone()), MetricKind::Gauge);
+        register(Box::new(position_cost.clone()), MetricKind::Gauge);
+        register(Box::new(balance.clone()), MetricKind::Gauge);
+
+        Self { registry, descriptors, trades, notional, realized_pnl, open_positions, position_cost, balance }
+    }
+
+    /// Metrics currently registered
+    pub fn descriptors(&self) -> &[MetricDescriptor] {
+        &self.descriptors
+    }
+
+    /// Count one booked trade: fill counters and realized PnL grow once per fill, so they survive
+    /// the dashboard trimming its trade history
+    pub fn record_trade(&self, trade: &SimulatedTrade, markets: &[MarketInfo]) {
+        if trade.status != "FILLED" {
+            return;
+        }
+        let category = category_label(&trade.market_id, markets);
+        let labels = [strategy_label(trade.source), category.as_str(), POLYMARKET_VENUE];
+        self.trades.with_label_values(&labels).inc();
+        self.notional.with_label_values(&labels).inc_by(trade.amount.abs());
+        self.realized_pnl.with_label_values(&labels).add(trade.pnl);
+    }
+
+    /// Rebuild the position and balance gauges from the ledger
+    ///
+    /// Series of strategies or categories no longer holding positions disappear.
+    pub fn refresh(&self, positions: &[PaperPosition], markets: &[MarketInfo], balance: f64) {
+        self.open_positions.reset();
+        self.position_cost.reset();
+        for position in positions {
+            let category = category_label(&position.market_id, markets);
+            let labels = [strategy_label(position.source), category.as_str(), POLYMARKET_VENUE];
+            self.open_positions.with_label_values(&labels).inc();
+            self.position_cost.with_label_values(&labels).add(position.cost_basis());
+        }
+        self.balance.with_label_values(&[POLYMARKET_VENUE]).set(balance);
+    }
+
+    /// Prometheus text exposition of the current series
+    pub fn encode(&self) -> Result<String, String> {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .map_err(|e| e.to_string())?;
+        String::from_utf8(buffer).map_err(|e| e.to_string())
+    }
+
+    /// Grafana dashboard with one panel per registered metric
+    ///
+    /// Counters are plotted as 5-minute rates, gauges as values, summed by strategy (or by
+    /// their first label). The datasource is a template variable, so the same JSON works for
+    /// file provisioning and for the import API.
+    pub fn grafana_dashboard(&self) -> serde_json::Value {
+        let mut templating = vec![json!({
+            "name": "datasource",
+            "label": "Prometheus",
+            "type": "datasource",
+            "query": "prometheus",
+        })];
+        let reference = format!("{}_trades_total", METRIC_NAMESPACE);
+        for label in METRIC_LABELS {
+            templating.push(json!({
+                "name": label,
+                "label": label,
+                "type": "query",
+                "datasource": { "type": "prometheus", "uid": "${datasource}" },
+                "query": format!("label_values({}, {})", reference, label),
+                "refresh": 2,
+                "includeAll": true,
+                "multi": true,
+                "current": { "text": "All", "value": "$__all" },
+            }));
+        }
+
+        let panels: Vec<serde_json::Value> = self.descriptors.iter().enumerate().map(|(i, metric)| {
+            let selector = metric
+                .labels
+                .iter()
+                .filter(|l| METRIC_LABELS.contains(&l.as_str()))
+                .map(|l| format!("{}=~\"${}\"", l, l))
+                .collect::<Vec<_>>()
+                .join(",");
+            let series = format!("{}{{{}}}", metric.name, selector);
+            let group = if metric.labels.iter().any(|l| l == "strategy") { "strategy" } else { metric.labels.first().map(String::as_str).unwrap_or("") };
+            let expr = match metric.kind {
+                MetricKind::Counter => format!("sum by ({}) (rate({}[5m]))", group, series),
+                MetricKind::Gauge => format!("sum by ({}) ({})", group, series),
+            };
+            json!({
+                "id": i + 1,
+                "type": "timeseries",
+                "title": metric.help,
+                "description": metric.name,
+                "datasource": { "type": "prometheus", "uid": "${datasource}" },
+                "gridPos": { "h": 8, "w": 12, "x": (i % 2) * 12, "y": (i / 2) * 8 },
+                "targets": [{ "refId": "A", "expr": expr, "legendFormat": format!("{{{{{}}}}}", group) }],
+            })
+        }).collect();
+
+        json!({
+            "uid": "polymarket-arb",
+            "title": "Polymarket Arbitrage Bot",
+            "tags": ["polymarket", "arbitrage"],
+            "timezone": "utc",
+            "schemaVersion": 39,
+            "refresh": "30s",
+            "time": { "from": "now-6h", "to": "now" },
+            "templating": { "list": templating },
+            "panels": panels,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    #[test]
+    fn test_metrics_are_labeled_and_dashboard_covers_them() {
+        let trade = |source: TradeSource, market_id: &str, pnl: f64| SimulatedTrade {
+            id: "t".to_string(),
+            market_id: market_id.to_string(),
+            question: String::new(),
+            action: "BUY_YES".to_string(),
+            price: 0.5,
+            quantity: 100.0,
+            amount: 50.0,
+            timestamp: Utc::now(),
+            status: "FILLED".to_string(),
+            pnl,
+            arbitrage_profit: 0.0,
+            source,
+        };
+        let market = MarketInfo {
+            id: "m1".to_string(),
+            question: String::new(),
+            yes_price: 0.5,
+            no_price: 0.5,
+            yes_liquidity: 0.0,
+            no_liquidity: 0.0,
+            volume_24h: 0.0,
+            timestamp: Utc::now(),
+            event_id: None,
+            category: Some("US Politics".to_string()),
+        };
+        let metrics = DashboardMetrics::new();
+        let markets = [market];
+        for fill in [trade(TradeSource::Bot, "m1", 2.0), trade(TradeSource::Bot, "m1", -0.5), trade(TradeSource::Manual, "m2", 1.0)] {
+            metrics.record_trade(&fill, &markets);
+        }
+        // Il refresh ricostruisce solo posizioni e balance: i contatori dei fill restano
+        metrics.refresh(&[], &markets, 10_000.0);
+        metrics.refresh(&[], &markets, 10_000.0);
+
+        let text = metrics.encode().unwrap();
+        assert!(text.contains(r#"polymarket_trades_total{category="us_politics",strategy="arbitrage",venue="polymarket"} 2"#));
+        assert!(text.contains(r#"polymarket_realized_pnl_usd{category="us_politics",strategy="arbitrage",venue="polymarket"} 1.5"#));
+        assert!(text.contains(r#"polymarket_trades_total{category="uncategorized",strategy="manual",venue="polymarket"} 1"#));
+        assert!(text.contains(r#"polymarket_balance_usd{venue="polymarket"} 10000"#));
+
+        let dashboard = metrics.grafana_dashboard();
+        let panels = dashboard["panels"].as_array().unwrap();
+        assert_eq!(panels.len(), metrics.descriptors().len());
+        assert_eq!(
+            panels[0]["targets"][0]["expr"],
+            r#"sum by (strategy) (rate(polymarket_trades_total{strategy=~"$strategy",category=~"$category",venue=~"$venue"}[5m]))"#
+        );
+        assert_eq!(dashboard["templating"]["list"].as_array().unwrap().len(), 1 + METRIC_LABELS.len());
+    }
+}
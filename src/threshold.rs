@@ -0,0 +1,152 @@
+//! Adaptive profit threshold module
+//!
+//! Implements:
+//! 1. Minimum profit per market category, starting from the configured threshold
+//! 2. Feedback controller: the threshold rises when filled executions capture less of the detected
+//!    edge than targeted and falls when they capture more
+//! 3. User-defined bounds the threshold never leaves
+//! 4. Only the YES/NO and mint-and-sell fills are learned from, the arb types the threshold gates
+
+use crate::types::{ArbType, ArbitrageOpportunity, TradeExecution};
+use fxhash::FxHashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Category key of markets without a Gamma category
+const UNCATEGORIZED: &str = "uncategorized";
+
+/// Bounds and tuning of the threshold controller
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AdaptiveThresholdConfig {
+    pub min: f64,            // Soglia minima di profitto per coppia
+    pub max: f64,            // Soglia massima
+    pub gain: f64,           // Correzione relativa per punto di scostamento dal target
+    pub target_capture: f64, // Quota del margine rilevato che le esecuzioni dovrebbero realizzare
+    pub window: usize,       // Esecuzioni recenti considerate per categoria
+    pub min_samples: usize,  // Esecuzioni necessarie prima di correggere la soglia
+}
+
+impl Default for AdaptiveThresholdConfig {
+    fn default() -> Self {
+        Self { min: 0.002, max: 0.05, gain: 0.5, target_capture: 0.8, window: 50, min_samples: 5 }
+    }
+}
+
+/// Outcome of one filled execution: detected edge and realized edge (fractions of notional)
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ExecutionOutcome {
+    expected: f64,
+    realized: f64,
+}
+
+/// Minimum profit per category, corrected after every execution
+#[derive(Debug, Clone)]
+pub struct ThresholdController {
+    pub config: AdaptiveThresholdConfig,
+    base: f64,
+    thresholds: FxHashMap<String, f64>,
+    outcomes: FxHashMap<String, VecDeque<ExecutionOutcome>>,
+}
+
+impl ThresholdController {
+    /// Controller starting every category at `base`, clamped to the bounds
+    pub fn new(config: AdaptiveThresholdConfig, base: f64) -> Self {
+        let base = base.clamp(config.min, config.max.max(config.min));
+        Self { config, base, thresholds: FxHashMap::default(), outcomes: FxHashMap::default() }
+    }
+
+    fn key(category: Option<&str>) -> String {
+        category.map(|c| c.trim().to_lowercase()).filter(|c| !c.is_empty()).unwrap_or_else(|| UNCATEGORIZED.to_string())
+    }
+
+    /// Current minimum profit of a category
+    pub fn threshold_for(&self, category: Option<&str>) -> f64 {
+        self.thresholds.get(&Self::key(category)).copied().unwrap_or(self.base)
+    }
+
+    /// Thresholds of the categories corrected so far
+    pub fn thresholds(&self) -> &FxHashMap<String, f64> {
+        &self.thresholds
+    }
+
+    /// Share of the detected edge realized by the recent executions of a category
+    pub fn capture(&self, category: Option<&str>) -> Option<f64> {
+        let outcomes = self.outcomes.get(&Self::key(category))?;
+        let expected: f64 = outcomes.iter().map(|o| o.expected).sum();
+        (expected > 0.0).then(|| outcomes.iter().map(|o| o.realized).sum::<f64>() / expected)
+    }
+
+    /// Record a filled execution's detected and realized edge
+    pub fn record(&mut self, category: Option<&str>, expected_edge: f64, realized_edge: f64) {
+        if expected_edge <= 0.0 {
+            return;
+        }
+        let key = Self::key(category);
+        let outcomes = self.outcomes.entry(key.clone()).or_default();
+        if outcomes.len() >= self.config.window.max(1) {
+            outcomes.pop_front();
+        }
+        outcomes.push_back(ExecutionOutcome { expected: expected_edge, realized: realized_edge });
+        if outcomes.len() < self.config.min_samples {
+            return;
+        }
+
+        let Some(capture) = self.capture(Some(&key)) else { return };
+        // Correzione proporzionale, limitata a un dimezzamento/raddoppio per esecuzione
+        let factor = (1.0 + self.config.gain * (self.config.target_capture - capture)).clamp(0.5, 2.0);
+        let threshold = (self.threshold_for(Some(&key)) * factor).clamp(self.config.min, self.config.max.max(self.config.min));
+        self.thresholds.insert(key, threshold);
+    }
+
+    /// Whether the threshold gates the detection of an arb type
+    pub fn governs(arb_type: ArbType) -> bool {
+        matches!(arb_type, ArbType::YesNoSimple | ArbType::YesNoSell)
+    }
+
+    /// Record the fill of an executed opportunity
+    ///
+    /// Fills of arb types detected against a fixed minimum profit (events, relations, graph,
+    /// pairs, maker, cross-venue) are not recorded: they would move a threshold they never face.
+    pub fn record_execution(&mut self, category: Option<&str>, opportunity: &ArbitrageOpportunity, trade: &TradeExecution) {
+        if !Self::governs(opportunity.arb_type) {
+            return;
+        }
+        let realized = if trade.total_investment > 0.0 { trade.profit / trade.total_investment } else { 0.0 };
+        self.record(category, opportunity.roi_pct / 100.0, realized);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_threshold_follows_capture_within_bounds() {
+        let config = AdaptiveThresholdConfig { min: 0.004, max: 0.02, min_samples: 2, ..AdaptiveThresholdConfig::default() };
+        let mut controller = ThresholdController::new(config, 0.005);
+
+        // Crypto: metà del margine persa in slippage → soglia più alta
+        controller.record(Some("Crypto"), 0.02, 0.01);
+        assert_eq!(controller.threshold_for(Some("crypto")), 0.005);
+        controller.record(Some("Crypto"), 0.02, 0.01);
+        assert!((controller.threshold_for(Some("crypto")) - 0.005 * 1.15).abs() < 1e-12);
+
+        // Sport: margine realizzato per intero → soglia più bassa, mai sotto il minimo
+        for _ in 0..20 {
+            controller.record(Some("Sports"), 0.02, 0.02);
+        }
+        assert_eq!(controller.threshold_for(Some("sports")), 0.004);
+
+        // Fill che non catturano nulla del margine: soglia al massimo
+        for _ in 0..40 {
+            controller.record(None, 0.02, 0.0);
+        }
+        assert_eq!(controller.threshold_for(None), 0.02);
+        assert_eq!(controller.threshold_for(Some("Politics")), 0.005);
+
+        // Soglia fissa per eventi, relazioni e coppie: i loro fill non la correggono
+        assert!(ThresholdController::governs(ArbType::YesNoSell));
+        assert!(!ThresholdController::governs(ArbType::YesNoMulti) && !ThresholdController::governs(ArbType::StatisticalArb));
+    }
+}
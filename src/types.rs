@@ -1,7 +1,52 @@
 //! Core types for the arbitrage bot
 
+#[cfg(feature = "native")]
+use crate::adverse_selection::AdverseSelectionConfig;
+#[cfg(feature = "native")]
+use crate::arbitrage::{GraphLimitsConfig, LegSizingConfig, OpportunityDedupConfig, TradingCosts};
+#[cfg(feature = "native")]
+use crate::exchange_accounts::ExchangeAccountConfig;
+#[cfg(feature = "native")]
+use crate::execution::SizingTiersConfig;
+#[cfg(feature = "native")]
+use crate::execution_queue::ExecutionQueueConfig;
+#[cfg(feature = "native")]
+use crate::faults::FaultConfig;
+#[cfg(feature = "native")]
+use crate::impact::ImpactConfig;
+#[cfg(feature = "native")]
+use crate::maker::MakerArbConfig;
+#[cfg(feature = "native")]
+use crate::news::NewsFeedConfig;
+#[cfg(feature = "native")]
+use crate::onboarding::OnboardingConfig;
+#[cfg(feature = "native")]
+use crate::opportunity_log::OpportunityLogConfig;
+#[cfg(feature = "native")]
+use crate::order_journal::OrderJournalConfig;
+#[cfg(feature = "native")]
+use crate::events::EventExportConfig;
+#[cfg(feature = "native")]
+use crate::reconciliation::LiveReconcileConfig;
+#[cfg(feature = "native")]
+use crate::refresh::RefreshScheduleConfig;
+#[cfg(feature = "native")]
+use crate::relations::{MarketRelation, MarketTriangle};
+#[cfg(feature = "native")]
+use crate::risk::StrategyRiskLimits;
+#[cfg(feature = "native")]
+use crate::stat_arb::{PairStatArbConfig, StatArbConfig};
+#[cfg(feature = "native")]
+use crate::threshold::AdaptiveThresholdConfig;
+#[cfg(feature = "native")]
+use crate::storage::{StepHistoryConfig, StorageConfig};
+#[cfg(feature = "native")]
+use crate::webhook::OpportunityWebhookConfig;
+#[cfg(feature = "native")]
+use crate::venues::CrossVenueConfig;
+use crate::maker::MakerEstimate;
 use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::fmt;
 
 /// Token types
@@ -31,10 +76,15 @@ pub enum Direction {
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ArbType {
     YesNoSimple,
+    YesNoSell,   // Set YES+NO coniato con 1 USDC di collaterale e venduto su entrambi i lati
     YesNoMulti,
+    CrossMarket, // YES su un mercato + NO su un duplicato della stessa domanda
     GraphArbitrage,
     StatisticalArb,
     MevExtraction,
+    Conditional, // Paniere su due mercati legati da una relazione logica (implicazione, esclusione) prezzati in modo incoerente
+    YesNoMaker,  // Bid YES e NO postati dentro lo spread, con fill maker invece di attraversare gli ask
+    CrossVenue,  // YES su una venue + NO sul contratto equivalente di un'altra (es. Polymarket e Kalshi)
 }
 
 /// MEV type
@@ -46,6 +96,96 @@ pub enum MevType {
     EquilibriumManipulation,
 }
 
+/// CLOB asset ids of a binary market's outcome tokens
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TokenPair {
+    pub yes_token_id: String,
+    pub no_token_id: String,
+    #[serde(default)]
+    pub condition_id: Option<String>, // Condition id CLOB del mercato (chiave degli endpoint rewards)
+}
+
+impl TokenPair {
+    pub fn token_id(&self, token_type: TokenType) -> &str {
+        match token_type {
+            TokenType::Yes => &self.yes_token_id,
+            TokenType::No => &self.no_token_id,
+        }
+    }
+}
+
+/// CLOB order constraints of a market
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct OrderConstraints {
+    pub tick_size: f64,
+    pub min_size: f64, // Quantità minima in share
+}
+
+impl Default for OrderConstraints {
+    fn default() -> Self {
+        Self { tick_size: 0.01, min_size: 5.0 }
+    }
+}
+
+impl OrderConstraints {
+    /// Quantità accettate dal CLOB con due decimali
+    pub const SIZE_STEP: f64 = 0.01;
+
+    /// Snap a price onto the tick grid without worsening it (buys round down, sells up)
+    pub fn round_price(&self, price: f64, direction: Direction) -> f64 {
+        let ticks = price / self.tick_size;
+        // Tolleranza per prezzi già sulla griglia ma non rappresentabili esattamente
+        let ticks = match direction {
+            Direction::Buy => (ticks + 1e-9).floor(),
+            Direction::Sell => (ticks - 1e-9).ceil(),
+        };
+        let decimals = (-self.tick_size.log10()).ceil().max(0.0) as i32;
+        let scale = 10f64.powi(decimals);
+        let price = (ticks * self.tick_size * scale).round() / scale;
+        price.clamp(self.tick_size, 1.0 - self.tick_size)
+    }
+
+    /// Round a quantity down to the size step; None if the result is below the minimum size
+    pub fn round_size(&self, quantity: f64) -> Option<f64> {
+        let size = ((quantity / Self::SIZE_STEP) + 1e-9).floor() * Self::SIZE_STEP;
+        let size = (size * 100.0).round() / 100.0;
+        (size >= self.min_size).then_some(size)
+    }
+}
+
+/// Deserialize a number that the API may encode as a string
+pub(crate) fn de_f64<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<f64, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum NumOrStr {
+        Num(f64),
+        Str(String),
+    }
+
+    let value = match NumOrStr::deserialize(deserializer)? {
+        NumOrStr::Num(n) => n,
+        NumOrStr::Str(s) => s.parse().map_err(serde::de::Error::custom)?,
+    };
+    // "NaN" e "inf" sono stringhe valide per f64::from_str ma non prezzi o size
+    if !value.is_finite() {
+        return Err(serde::de::Error::custom(format!("non-finite number: {}", value)));
+    }
+    Ok(value)
+}
+
+/// Optional variant of `de_f64`
+pub(crate) fn de_opt_f64<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Option<f64>, D::Error> {
+    #[derive(Deserialize)]
+    struct Wrapper(#[serde(deserialize_with = "de_f64")] f64);
+
+    Ok(Option::<Wrapper>::deserialize(deserializer)?.map(|w| w.0))
+}
+
+/// Deserialize a millisecond timestamp encoded as string or number
+pub(crate) fn de_u64<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<u64, D::Error> {
+    de_f64(deserializer).map(|v| v as u64)
+}
+
 /// Market data
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MarketData {
@@ -57,6 +197,16 @@ pub struct MarketData {
     pub no_liquidity: f64,
     pub timestamp: DateTime<Utc>,
     pub volume_24h: f64,
+    #[serde(default)]
+    pub tokens: Option<TokenPair>, // Token CLOB negoziabili, se noti
+    #[serde(default)]
+    pub spread: Option<f64>, // Spread bid-ask del token YES sul CLOB, se noto
+    #[serde(default)]
+    pub event_id: Option<String>, // Evento Gamma di appartenenza, se noto
+    #[serde(default)]
+    pub category: Option<String>, // Categoria Gamma (Politics, Crypto, Sports...), se nota
+    #[serde(default)]
+    pub end_date: Option<DateTime<Utc>>, // Data di fine (risoluzione attesa), se nota
 }
 
 impl Default for MarketData {
@@ -70,6 +220,11 @@ impl Default for MarketData {
             no_liquidity: 0.0,
             timestamp: Utc::now(),
             volume_24h: 0.0,
+            tokens: None,
+            spread: None,
+            event_id: None,
+            category: None,
+            end_date: None,
         }
     }
 }
@@ -85,6 +240,37 @@ impl MarketData {
     }
 }
 
+/// Multi-outcome event: one binary market per outcome
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventData {
+    pub id: String,
+    pub title: String,
+    pub neg_risk: bool, // Esiti mutuamente esclusivi: esattamente un mercato risolve YES
+    pub markets: Vec<MarketData>,
+    pub timestamp: DateTime<Utc>,
+    #[serde(default)]
+    pub tags: Vec<String>, // Etichette Gamma (es. "Politics", "Sports")
+    #[serde(default)]
+    pub end_date: Option<DateTime<Utc>>, // Data di risoluzione prevista
+}
+
+impl EventData {
+    /// Whether the event reached its end date at `now`
+    pub fn has_ended(&self, now: DateTime<Utc>) -> bool {
+        self.end_date.is_some_and(|end| end <= now)
+    }
+
+    /// Cost of buying YES on every outcome
+    pub fn yes_price_sum(&self) -> f64 {
+        self.markets.iter().map(|m| m.yes_price).sum()
+    }
+
+    /// Cost of buying NO on every outcome
+    pub fn no_price_sum(&self) -> f64 {
+        self.markets.iter().map(|m| m.no_price).sum()
+    }
+}
+
 /// Arbitrage opportunity
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ArbitrageOpportunity {
@@ -101,6 +287,8 @@ pub struct ArbitrageOpportunity {
     pub timestamp: DateTime<Utc>,
     pub legs: Option<Vec<ArbitrageLeg>>,
     pub path: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub maker: Option<MakerEstimate>, // Bid maker: probabilità di fill e ask su cui completare un leg scoperto
 }
 
 /// Arbitrage leg
@@ -111,6 +299,10 @@ pub struct ArbitrageLeg {
     pub direction: Direction,
     pub price: f64,
     pub quantity: f64,
+    #[serde(default)]
+    pub token_id: Option<String>, // Asset CLOB da negoziare, se noto
+    #[serde(default)]
+    pub venue: Option<String>, // Venue del leg; None = Polymarket
 }
 
 /// Trade execution
@@ -259,6 +451,7 @@ pub struct VwapSlice {
 }
 
 /// Bot configuration
+#[cfg(feature = "native")]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BotConfig {
     pub initial_capital: f64,
@@ -275,13 +468,104 @@ pub struct BotConfig {
     pub polymarket_api_key: Option<String>, // Polymarket API Key
     pub polymarket_secret: Option<String>,   // Polymarket API Secret
     pub polymarket_passphrase: Option<String>, // Polymarket API Passphrase
+    #[serde(default)]
+    pub polymarket_private_key: Option<String>, // Chiave del wallet Polygon: deriva le credenziali se mancano
+    #[serde(default)]
+    pub storage: StorageConfig, // Backend di persistenza di trade e snapshot
+    #[serde(default)]
+    pub step_history: StepHistoryConfig, // Quali step salvare come snapshot per l'analisi delle serie
+    #[serde(default)]
+    pub news_feed: Option<NewsFeedConfig>, // Feed di notizie per il rischio di risoluzione, opzionale
+    #[serde(default)]
+    pub market_filter: MarketFilter, // Categorie e tag dell'universo negoziato
+    #[serde(default)]
+    pub stat_arb: Option<StatArbConfig>, // Strategia di mean reversion con uscite trailing, opzionale
+    #[serde(default)]
+    pub live_reconciliation: Option<LiveReconcileConfig>, // Confronto periodico con lo stato dell'exchange, opzionale
+    #[serde(default)]
+    pub accounts: Vec<ExchangeAccountConfig>, // Account aggiuntivi (live o paper) gestiti dallo stesso processo
+    #[serde(default)]
+    pub trading_costs: TradingCosts, // Fee taker, gas e relayer dedotti dal margine delle opportunità
+    #[serde(default)]
+    pub refresh_schedule: RefreshScheduleConfig, // Intervalli dei refresh REST per priorità e limite di arretrato del feed
+    #[serde(default)]
+    pub leg_sizing: LegSizingConfig, // Quota della liquidità di ciascun lato su cui dimensionare i leg
+    #[serde(default)]
+    pub event_export: Option<EventExportConfig>, // Pubblicazione di trade, opportunità ed eventi di rischio su Kafka/NATS, opzionale
+    #[serde(default)]
+    pub opportunity_webhook: Option<OpportunityWebhookConfig>, // Opportunità approvate e dimensionate verso un'esecuzione esterna, opzionale
+    #[serde(default)]
+    pub cross_venue: Option<CrossVenueConfig>, // Coppie su contratti equivalenti di altre venue (es. Kalshi), opzionale
+    #[serde(default)]
+    pub impact: ImpactConfig, // Calibrazione delle curve di impatto dai fill realizzati
+    #[serde(default)]
+    pub opportunity_dedup: OpportunityDedupConfig, // Cooldown delle opportunità ripetute sulle stesse quotazioni
+    #[serde(default)]
+    pub adaptive_threshold: Option<AdaptiveThresholdConfig>, // Soglia di profitto per categoria corretta dalla qualità dei fill, opzionale
+    #[serde(default)]
+    pub pair_stat_arb: Option<PairStatArbConfig>, // Coppie di mercati cointegrati negoziate sullo z-score dello spread, opzionale
+    #[serde(default)]
+    pub onboarding: Option<OnboardingConfig>, // Controlli di idoneità prima che un nuovo mercato diventi negoziabile, opzionale
+    #[serde(default)]
+    pub faults: Option<FaultConfig>, // Outage, gap del feed, latenza e fill parziali simulati, opzionale
+    #[serde(default)]
+    pub relations: Vec<MarketRelation>, // Relazioni logiche tra mercati i cui prezzi devono essere coerenti
+    #[serde(default)]
+    pub triangles: Vec<MarketTriangle>, // Terne di mercati in cui uno è la congiunzione degli altri due
+    #[serde(default)]
+    pub execution_queue: Option<ExecutionQueueConfig>, // Esecuzione concorrente per priorità; senza, una sola esecuzione per step
+    #[serde(default)]
+    pub opportunity_history: Option<OpportunityLogConfig>, // Storico di ogni candidato rilevato, anche scartato, con il motivo
+    #[serde(default)]
+    pub order_journal: Option<OrderJournalConfig>, // Journal delle esecuzioni su disco, per recuperarle dopo un riavvio
+    #[serde(default)]
+    pub graph_limits: Option<GraphLimitsConfig>, // Potatura e limite di dimensione del grafo dei prezzi, opzionale
+    #[serde(default)]
+    pub adverse_selection: Option<AdverseSelectionConfig>, // Sconto del margine per la selezione avversa durante la latenza di fill, opzionale
+    #[serde(default)]
+    pub sizing_tiers: Option<SizingTiersConfig>, // Posizione minima e massima per tier di liquidità, opzionale
+    #[serde(default)]
+    pub maker_arb: Option<MakerArbConfig>, // Arbitraggio YES/NO con bid maker dentro lo spread, opzionale
+    #[serde(default)]
+    pub strategy_risk: Vec<StrategyRiskLimits>, // Limiti di rischio più stretti per singola strategia
+}
+
+/// Restriction of the traded universe to categories and tags (case-insensitive)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MarketFilter {
+    #[serde(default)]
+    pub categories: Vec<String>, // Es. "sports", "crypto", "politics"; vuoto = tutte
+    #[serde(default)]
+    pub exclude: Vec<String>, // Categorie o tag mai negoziati
+}
+
+impl MarketFilter {
+    pub fn is_empty(&self) -> bool {
+        self.categories.is_empty() && self.exclude.is_empty()
+    }
+
+    /// Whether a market with this category and tags belongs to the universe
+    pub fn matches(&self, category: Option<&str>, tags: &[String]) -> bool {
+        let labels: Vec<String> = category
+            .into_iter()
+            .chain(tags.iter().map(String::as_str))
+            .map(|l| l.trim().to_lowercase())
+            .collect();
+        let hit = |wanted: &[String]| wanted.iter().any(|w| labels.contains(&w.trim().to_lowercase()));
+
+        if hit(&self.exclude) {
+            return false;
+        }
+        self.categories.is_empty() || hit(&self.categories)
+    }
 }
 
+#[cfg(feature = "native")]
 impl Default for BotConfig {
     fn default() -> Self {
         Self {
             initial_capital: 1000.0,
-            min_profit_threshold: 0.01,
+            min_profit_threshold: 0.005, // 0.5%: più opportunità, la soglia adattiva la corregge per categoria
             risk_per_trade: 0.02,
             max_position_size: 100.0,
             api_base: "https://api.polymarket.com".to_string(),
@@ -294,6 +578,36 @@ impl Default for BotConfig {
             polymarket_api_key: None,
             polymarket_secret: None,
             polymarket_passphrase: None,
+            polymarket_private_key: None,
+            storage: StorageConfig::default(),
+            step_history: StepHistoryConfig::default(),
+            news_feed: None,
+            market_filter: MarketFilter::default(),
+            stat_arb: None,
+            live_reconciliation: None,
+            accounts: Vec::new(),
+            trading_costs: TradingCosts::default(),
+            refresh_schedule: RefreshScheduleConfig::default(),
+            leg_sizing: LegSizingConfig::default(),
+            event_export: None,
+            opportunity_webhook: None,
+            cross_venue: None,
+            impact: ImpactConfig::default(),
+            opportunity_dedup: OpportunityDedupConfig::default(),
+            adaptive_threshold: None,
+            pair_stat_arb: None,
+            onboarding: None,
+            faults: None,
+            relations: Vec::new(),
+            triangles: Vec::new(),
+            execution_queue: None,
+            opportunity_history: None,
+            order_journal: None,
+            graph_limits: None,
+            adverse_selection: None,
+            sizing_tiers: None,
+            maker_arb: None,
+            strategy_risk: Vec::new(),
         }
     }
 }
@@ -0,0 +1,634 @@
+//! Cross-venue comparison module
+//!
+//! Implements:
+//! 1. Linking of markets on different venues that track the same event
+//! 2. Time series of the YES price spread across venues, for monitoring only
+//! 3. Execution planning of cross-venue pairs: fees, settlement lockup, withdrawal limits
+//!    and currency hedging, with a feasibility verdict and sized legs per venue
+//! 4. `VenueAdapter` trait for quote sources of other venues (Kalshi in the `kalshi` module)
+//! 5. Cross-venue detection on a mapping of equivalent contracts: YES on one venue and NO on
+//!    the other bought below the payout after both venues' fees, one leg per venue; the bot
+//!    publishes these pairs but never sends them through the Polymarket executor
+//!
+//! The service records a spread as soon as a second venue starts publishing quotes for a
+//! linked event; venues without an adapter can push their quotes over the API.
+
+use crate::detector::{Detector, MarketView};
+use crate::kalshi::KalshiConfig;
+use crate::symbols::{SymbolKind, SymbolRegistry};
+use crate::types::{ArbType, ArbitrageLeg, ArbitrageOpportunity, Direction, MarketData, TokenType};
+use chrono::{DateTime, Duration, Utc};
+use futures_util::future::BoxFuture;
+use fxhash::FxHashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+/// Name of the Polymarket venue in quotes and links
+pub const POLYMARKET_VENUE: &str = "polymarket";
+
+/// Quotes older than this are not compared
+pub const VENUE_QUOTE_MAX_AGE_SECS: i64 = 30;
+
+/// Spread samples kept per event
+pub const VENUE_SPREAD_HISTORY: usize = 1_000;
+
+/// YES price of a market on one venue
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VenueQuote {
+    pub venue: String,
+    pub market_id: String,
+    pub yes_price: f64,
+    pub timestamp: DateTime<Utc>,
+    #[serde(default)]
+    pub no_price: Option<f64>, // Ask NO, se la venue lo pubblica; altrimenti 1 - yes_price
+    #[serde(default)]
+    pub liquidity: Option<f64>, // Liquidità esposta sul mercato, in valuta della venue, se nota
+}
+
+impl VenueQuote {
+    /// Price of buying the NO side
+    pub fn no_ask(&self) -> f64 {
+        self.no_price.unwrap_or(1.0 - self.yes_price)
+    }
+}
+
+/// Spread between the richest and cheapest venue at a point in time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VenueSpreadSample {
+    pub timestamp: DateTime<Utc>,
+    pub high_venue: String,
+    pub low_venue: String,
+    pub spread: f64, // Prezzo YES più alto meno il più basso
+}
+
+/// Spread statistics of one linked event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VenueSpreadSummary {
+    pub event_key: String,
+    pub venues: Vec<String>,
+    pub samples: usize,
+    pub latest: Option<VenueSpreadSample>,
+    pub mean_spread: f64,
+    pub max_spread: f64,
+}
+
+/// Tracks the same-event price spread across venues
+#[derive(Debug, Clone, Default)]
+pub struct VenueComparison {
+    links: FxHashMap<(String, String), String>, // (venue, market_id) -> evento
+    quotes: FxHashMap<String, FxHashMap<String, VenueQuote>>, // evento -> venue -> ultima quotazione
+    history: FxHashMap<String, VecDeque<VenueSpreadSample>>,
+}
+
+impl VenueComparison {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declare that a venue's market tracks `event_key`
+    pub fn link(&mut self, venue: &str, market_id: &str, event_key: &str) {
+        self.links.insert((venue.to_string(), market_id.to_string()), event_key.to_string());
+    }
+
+    /// Record a quote; returns the new spread sample when at least two venues have fresh quotes
+    pub fn observe(&mut self, quote: VenueQuote) -> Option<VenueSpreadSample> {
+        let event_key = self.links.get(&(quote.venue.clone(), quote.market_id.clone()))?.clone();
+        let now = quote.timestamp;
+        let quotes = self.quotes.entry(event_key.clone()).or_default();
+        quotes.insert(quote.venue.clone(), quote);
+
+        let fresh: Vec<&VenueQuote> = quotes
+            .values()
+            .filter(|q| now - q.timestamp <= Duration::seconds(VENUE_QUOTE_MAX_AGE_SECS))
+            .collect();
+        if fresh.len() < 2 {
+            return None;
+        }
+        let high = fresh.iter().max_by(|a, b| a.yes_price.total_cmp(&b.yes_price))?;
+        let low = fresh.iter().min_by(|a, b| a.yes_price.total_cmp(&b.yes_price))?;
+        let sample = VenueSpreadSample {
+            timestamp: now,
+            high_venue: high.venue.clone(),
+            low_venue: low.venue.clone(),
+            spread: high.yes_price - low.yes_price,
+        };
+
+        let history = self.history.entry(event_key).or_default();
+        history.push_back(sample.clone());
+        if history.len() > VENUE_SPREAD_HISTORY {
+            history.pop_front();
+        }
+        Some(sample)
+    }
+
+    /// Spread history of an event, oldest first
+    pub fn history(&self, event_key: &str) -> Vec<VenueSpreadSample> {
+        self.history.get(event_key).map(|h| h.iter().cloned().collect()).unwrap_or_default()
+    }
+
+    /// Statistics of every linked event that has quotes
+    pub fn summary(&self) -> Vec<VenueSpreadSummary> {
+        let mut summaries: Vec<VenueSpreadSummary> = self.quotes
+            .iter()
+            .map(|(event_key, quotes)| {
+                let history = self.history.get(event_key);
+                let spreads: Vec<f64> = history.iter().flat_map(|h| h.iter().map(|s| s.spread)).collect();
+                let mut venues: Vec<String> = quotes.keys().cloned().collect();
+                venues.sort();
+                VenueSpreadSummary {
+                    event_key: event_key.clone(),
+                    venues,
+                    samples: spreads.len(),
+                    latest: history.and_then(|h| h.back().cloned()),
+                    mean_spread: if spreads.is_empty() { 0.0 } else { spreads.iter().sum::<f64>() / spreads.len() as f64 },
+                    max_spread: spreads.iter().cloned().fold(0.0, f64::max),
+                }
+            })
+            .collect();
+        summaries.sort_by(|a, b| a.event_key.cmp(&b.event_key));
+        summaries
+    }
+}
+
+/// Trading conditions of a venue
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VenueProfile {
+    pub venue: String,
+    pub currency: String,        // Valuta di regolamento (es. "USDC", "USD")
+    pub fee_bps: f64,            // Commissione taker sul nozionale
+    pub settlement_hours: f64,   // Tempo tra risoluzione e fondi prelevabili
+    pub withdrawal_limit: f64,   // Massimo prelevabile per ciclo, in valuta della venue
+    pub available_balance: f64,  // Saldo utilizzabile, in valuta della venue
+    pub min_order_size: f64,     // Share minime per ordine
+}
+
+/// Cross-venue pair: YES on one venue and NO on the other pay exactly one unit together
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrossVenueOpportunity {
+    pub event_key: String,
+    pub yes_venue: String,
+    pub yes_price: f64,
+    pub no_venue: String,
+    pub no_price: f64,
+}
+
+/// Planner assumptions shared by every venue
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrossVenueCosts {
+    pub base_currency: String,
+    pub fx_rates: FxHashMap<String, f64>, // Valore di un'unità di valuta nella valuta base
+    pub fx_hedge_bps: f64,                // Costo di copertura sul nozionale in valuta estera
+    pub annual_hurdle_rate: f64,          // Costo opportunità del capitale bloccato
+    pub max_capital: f64,                 // In valuta base
+}
+
+/// Sized leg on one venue
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannedVenueLeg {
+    pub venue: String,
+    pub token_type: TokenType,
+    pub price: f64,
+    pub quantity: f64,
+    pub cost: f64, // Prezzo più commissioni, in valuta della venue
+}
+
+/// Feasibility verdict and sized legs of a cross-venue pair
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrossVenuePlan {
+    pub feasible: bool,
+    pub reasons: Vec<String>, // Motivi di infattibilità
+    pub pairs: f64,
+    pub legs: Vec<PlannedVenueLeg>,
+    pub cost_per_pair: f64,      // In valuta base, commissioni e copertura incluse
+    pub payout_per_pair: f64,    // Caso peggiore tra le due venue, in valuta base
+    pub lockup_hours: f64,
+    pub net_profit: f64,         // In valuta base, al netto del costo del capitale
+}
+
+/// Size a cross-venue pair within balances, withdrawal limits and capital, and judge it
+pub fn plan_cross_venue(
+    opportunity: &CrossVenueOpportunity,
+    yes_venue: &VenueProfile,
+    no_venue: &VenueProfile,
+    costs: &CrossVenueCosts,
+) -> CrossVenuePlan {
+    let mut reasons = Vec::new();
+    let fx = |venue: &VenueProfile| -> Option<f64> {
+        if venue.currency == costs.base_currency {
+            Some(1.0)
+        } else {
+            costs.fx_rates.get(&venue.currency).copied()
+        }
+    };
+    let hedge = |venue: &VenueProfile| if venue.currency == costs.base_currency { 0.0 } else { costs.fx_hedge_bps / 10_000.0 };
+
+    let (Some(yes_fx), Some(no_fx)) = (fx(yes_venue), fx(no_venue)) else {
+        return CrossVenuePlan {
+            feasible: false,
+            reasons: vec!["missing FX rate for a venue currency".to_string()],
+            pairs: 0.0,
+            legs: Vec::new(),
+            cost_per_pair: 0.0,
+            payout_per_pair: 0.0,
+            lockup_hours: 0.0,
+            net_profit: 0.0,
+        };
+    };
+
+    // Costo per coppia in valuta della venue, poi in valuta base con la copertura del cambio
+    let yes_cost = opportunity.yes_price * (1.0 + yes_venue.fee_bps / 10_000.0);
+    let no_cost = opportunity.no_price * (1.0 + no_venue.fee_bps / 10_000.0);
+    let cost_per_pair = yes_cost * yes_fx * (1.0 + hedge(yes_venue)) + no_cost * no_fx * (1.0 + hedge(no_venue));
+    // Paga una sola delle due venue, e non si sa quale
+    let payout_per_pair = (yes_fx * (1.0 - hedge(yes_venue))).min(no_fx * (1.0 - hedge(no_venue)));
+
+    // Capitale bloccato fino al regolamento della venue più lenta
+    let lockup_hours = yes_venue.settlement_hours.max(no_venue.settlement_hours);
+    let lockup_cost = cost_per_pair * costs.annual_hurdle_rate * lockup_hours / (365.0 * 24.0);
+    let profit_per_pair = payout_per_pair - cost_per_pair - lockup_cost;
+
+    // Ogni venue può dover pagare tutte le coppie: il limite di prelievo le vincola
+    let limits = [
+        (costs.max_capital / cost_per_pair, "capital"),
+        (yes_venue.available_balance / yes_cost, "YES venue balance"),
+        (no_venue.available_balance / no_cost, "NO venue balance"),
+        (yes_venue.withdrawal_limit, "YES venue withdrawal limit"),
+        (no_venue.withdrawal_limit, "NO venue withdrawal limit"),
+    ];
+    let (pairs, binding) = limits
+        .iter()
+        .fold((f64::INFINITY, ""), |(best, name), &(limit, n)| if limit < best { (limit, n) } else { (best, name) });
+    let pairs = if pairs.is_finite() { (pairs * 100.0).floor() / 100.0 } else { 0.0 };
+
+    if profit_per_pair <= 0.0 {
+        reasons.push(format!("net edge {:.4} per pair after fees, hedging and lockup", profit_per_pair));
+    }
+    let min_size = yes_venue.min_order_size.max(no_venue.min_order_size);
+    if pairs < min_size {
+        reasons.push(format!("{} allows {:.2} pairs, below the minimum order size {}", binding, pairs, min_size));
+    }
+
+    CrossVenuePlan {
+        feasible: reasons.is_empty(),
+        reasons,
+        pairs,
+        legs: vec![
+            PlannedVenueLeg { venue: yes_venue.venue.clone(), token_type: TokenType::Yes, price: opportunity.yes_price, quantity: pairs, cost: pairs * yes_cost },
+            PlannedVenueLeg { venue: no_venue.venue.clone(), token_type: TokenType::No, price: opportunity.no_price, quantity: pairs, cost: pairs * no_cost },
+        ],
+        cost_per_pair,
+        payout_per_pair,
+        lockup_hours,
+        net_profit: pairs * profit_per_pair,
+    }
+}
+
+/// Quote source of a venue other than Polymarket
+pub trait VenueAdapter: Send + Sync {
+    /// Venue name used in quotes, contract mappings and legs
+    fn venue(&self) -> &str;
+
+    /// Current quotes of the given venue markets, stamped `now`; markets not open for trading are left out
+    fn fetch_quotes<'a>(&'a self, market_ids: &'a [String], now: DateTime<Utc>) -> BoxFuture<'a, Result<Vec<VenueQuote>, String>>;
+}
+
+/// Latest quote of each venue market, shared between the adapters and the cross-venue detector
+#[derive(Debug, Clone, Default)]
+pub struct VenueQuoteBook {
+    quotes: Arc<Mutex<FxHashMap<(String, String), VenueQuote>>>, // (venue, mercato) -> quotazione
+}
+
+impl VenueQuoteBook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store a quote unless a newer one of the same market is already known
+    pub fn update(&self, quote: VenueQuote) {
+        let mut quotes = self.quotes.lock().unwrap();
+        let key = (quote.venue.clone(), quote.market_id.clone());
+        if quotes.get(&key).is_none_or(|known| known.timestamp <= quote.timestamp) {
+            quotes.insert(key, quote);
+        }
+    }
+
+    pub fn get(&self, venue: &str, market_id: &str) -> Option<VenueQuote> {
+        self.quotes.lock().unwrap().get(&(venue.to_string(), market_id.to_string())).cloned()
+    }
+}
+
+/// Polymarket market and the equivalent contract on another venue
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EquivalentContract {
+    pub market_id: String, // Mercato Polymarket interno
+    pub venue: String,
+    pub venue_market_id: String, // Ticker o id del contratto sulla venue
+    #[serde(default)]
+    pub inverted: bool, // Il YES della venue paga quando il mercato Polymarket risolve NO
+}
+
+impl EquivalentContract {
+    /// Tickers of `venue` in the symbol registry; a ticker mapped to the NO outcome is inverted
+    pub fn from_symbols(symbols: &SymbolRegistry, venue: &str) -> Vec<Self> {
+        symbols.list()
+            .into_iter()
+            .filter(|m| m.venue == venue && m.kind == SymbolKind::Ticker)
+            .map(|m| Self {
+                inverted: m.outcome == Some(TokenType::No),
+                market_id: m.market_id,
+                venue: m.venue,
+                venue_market_id: m.symbol,
+            })
+            .collect()
+    }
+}
+
+/// Taker fees of a venue
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VenueFees {
+    pub fee_bps: f64,       // Sul nozionale
+    pub variance_rate: f64, // Per contratto: rate × p × (1 − p), come la tabella taker di Kalshi (0.07)
+}
+
+impl VenueFees {
+    /// Fee paid per contract bought at `price`
+    pub fn per_contract(&self, price: f64) -> f64 {
+        price * self.fee_bps / 10_000.0 + self.variance_rate * price * (1.0 - price)
+    }
+}
+
+fn default_cross_venue_min_profit() -> f64 {
+    0.01
+}
+
+fn default_max_quote_age_secs() -> i64 {
+    VENUE_QUOTE_MAX_AGE_SECS
+}
+
+/// Cross-venue detection settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrossVenueConfig {
+    pub contracts: Vec<EquivalentContract>,
+    #[serde(default)]
+    pub fees: FxHashMap<String, VenueFees>, // Per venue, "polymarket" incluso; una venue assente non ha commissioni
+    #[serde(default = "default_cross_venue_min_profit")]
+    pub min_profit: f64, // Margine minimo per contratto, al netto delle commissioni di entrambe le venue
+    #[serde(default = "default_max_quote_age_secs")]
+    pub max_quote_age_secs: i64,
+    #[serde(default)]
+    pub kalshi: Option<KalshiConfig>, // Adapter Kalshi; senza, le quotazioni vanno inserite nel VenueQuoteBook del bot
+}
+
+impl CrossVenueConfig {
+    pub fn new(contracts: Vec<EquivalentContract>) -> Self {
+        Self {
+            contracts,
+            fees: FxHashMap::default(),
+            min_profit: default_cross_venue_min_profit(),
+            max_quote_age_secs: default_max_quote_age_secs(),
+            kalshi: None,
+        }
+    }
+
+    fn fee(&self, venue: &str, price: f64) -> f64 {
+        self.fees.get(venue).map_or(0.0, |f| f.per_contract(price))
+    }
+}
+
+/// Detects Polymarket markets whose equivalent contract on another venue prices the opposite
+/// outcome low enough that buying both pays more than they cost, fees included
+pub struct CrossVenueDetector {
+    pub config: CrossVenueConfig,
+    pub quotes: VenueQuoteBook,
+}
+
+impl CrossVenueDetector {
+    pub fn new(config: CrossVenueConfig, quotes: VenueQuoteBook) -> Self {
+        Self { config, quotes }
+    }
+
+    /// Best of the two pairs (YES on Polymarket with NO on the venue, or the reverse) clearing `min_profit`
+    fn detect(&self, contract: &EquivalentContract, market: &MarketData, now: DateTime<Utc>) -> Option<ArbitrageOpportunity> {
+        let quote = self.quotes.get(&contract.venue, &contract.venue_market_id)
+            .filter(|q| now - q.timestamp <= Duration::seconds(self.config.max_quote_age_secs))?;
+        // Esiti della venue nei termini del mercato Polymarket
+        let (venue_yes, venue_no) = if contract.inverted {
+            ((quote.no_ask(), TokenType::No), (quote.yes_price, TokenType::Yes))
+        } else {
+            ((quote.yes_price, TokenType::Yes), (quote.no_ask(), TokenType::No))
+        };
+        let pairs = [
+            (TokenType::Yes, market.yes_price, market.yes_liquidity, venue_no),
+            (TokenType::No, market.no_price, market.no_liquidity, venue_yes),
+        ];
+
+        let (token_type, price, liquidity, (venue_price, venue_token), profit, cost) = pairs
+            .into_iter()
+            .filter(|&(_, price, _, (venue_price, _))| price > 0.0 && venue_price > 0.0)
+            .map(|(token_type, price, liquidity, venue)| {
+                let cost = price + venue.0
+                    + self.config.fee(POLYMARKET_VENUE, price)
+                    + self.config.fee(&contract.venue, venue.0);
+                (token_type, price, liquidity, venue, 1.0 - cost, cost)
+            })
+            .max_by(|a, b| a.4.total_cmp(&b.4))?;
+        if profit < self.config.min_profit {
+            return None;
+        }
+
+        let roi = profit / cost;
+        let (yes_price, no_price) = match token_type {
+            TokenType::Yes => (price, venue_price),
+            TokenType::No => (venue_price, price),
+        };
+        let leg = |market_id: &str, token_type: TokenType, price: f64, token_id: Option<String>, venue: Option<String>| ArbitrageLeg {
+            market_id: market_id.to_string(),
+            token_type,
+            direction: Direction::Buy,
+            price,
+            quantity: 0.0,
+            token_id,
+            venue,
+        };
+        Some(ArbitrageOpportunity {
+            market_id: market.id.clone(),
+            question: market.question.clone(),
+            arb_type: ArbType::CrossVenue,
+            profit,
+            roi_pct: roi * 100.0,
+            confidence: (roi / 0.05).min(1.0),
+            yes_price,
+            no_price,
+            sum_price: price + venue_price,
+            liquidity: quote.liquidity.map_or(liquidity, |l| l.min(liquidity)),
+            timestamp: now,
+            legs: Some(vec![
+                leg(&market.id, token_type, price, market.tokens.as_ref().map(|t| t.token_id(token_type).to_string()), None),
+                leg(&contract.venue_market_id, venue_token, venue_price, None, Some(contract.venue.clone())),
+            ]),
+            path: None,
+            maker: None,
+        })
+    }
+}
+
+impl Detector for CrossVenueDetector {
+    fn name(&self) -> &str {
+        "cross_venue"
+    }
+
+    fn scan(&self, view: &MarketView<'_>) -> Vec<ArbitrageOpportunity> {
+        self.config.contracts.iter()
+            .filter_map(|contract| {
+                let market = view.markets.iter().find(|m| m.id == contract.market_id)?;
+                self.detect(contract, market, view.now)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quote(venue: &str, market_id: &str, yes_price: f64, timestamp: DateTime<Utc>) -> VenueQuote {
+        VenueQuote { venue: venue.to_string(), market_id: market_id.to_string(), yes_price, timestamp, no_price: None, liquidity: None }
+    }
+
+    #[test]
+    fn test_spread_needs_two_fresh_venues() {
+        let start = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let mut comparison = VenueComparison::new();
+        comparison.link(POLYMARKET_VENUE, "0xabc", "fed-dec-cut");
+        comparison.link("other", "FED-DEC", "fed-dec-cut");
+
+        assert!(comparison.observe(quote(POLYMARKET_VENUE, "unlinked", 0.5, start)).is_none());
+        assert!(comparison.observe(quote(POLYMARKET_VENUE, "0xabc", 0.62, start)).is_none());
+        let sample = comparison.observe(quote("other", "FED-DEC", 0.58, start + Duration::seconds(5))).unwrap();
+        assert_eq!((sample.high_venue.as_str(), sample.low_venue.as_str()), (POLYMARKET_VENUE, "other"));
+        assert!((sample.spread - 0.04).abs() < 1e-9);
+
+        // La quotazione Polymarket è ormai vecchia: nessun confronto
+        assert!(comparison.observe(quote("other", "FED-DEC", 0.60, start + Duration::seconds(60))).is_none());
+
+        let summary = comparison.summary();
+        assert_eq!(summary[0].venues, vec!["other", POLYMARKET_VENUE]);
+        assert_eq!(summary[0].samples, 1);
+        assert_eq!(comparison.history("fed-dec-cut").len(), 1);
+    }
+
+    #[test]
+    fn test_cross_venue_plan_sizing_and_verdict() {
+        let profile = |venue: &str, currency: &str, fee_bps: f64, withdrawal_limit: f64| VenueProfile {
+            venue: venue.to_string(),
+            currency: currency.to_string(),
+            fee_bps,
+            settlement_hours: 48.0,
+            withdrawal_limit,
+            available_balance: 10_000.0,
+            min_order_size: 5.0,
+        };
+        let polymarket = profile(POLYMARKET_VENUE, "USDC", 0.0, 50_000.0);
+        let other = profile("other", "USD", 100.0, 300.0);
+        let costs = CrossVenueCosts {
+            base_currency: "USD".to_string(),
+            fx_rates: [("USDC".to_string(), 0.999)].into_iter().collect(),
+            fx_hedge_bps: 5.0,
+            annual_hurdle_rate: 0.05,
+            max_capital: 1_000.0,
+        };
+        let opportunity = CrossVenueOpportunity {
+            event_key: "fed-dec-cut".to_string(),
+            yes_venue: POLYMARKET_VENUE.to_string(),
+            yes_price: 0.55,
+            no_venue: "other".to_string(),
+            no_price: 0.40,
+        };
+
+        let plan = plan_cross_venue(&opportunity, &polymarket, &other, &costs);
+        assert!(plan.feasible, "{:?}", plan.reasons);
+        assert_eq!(plan.pairs, 300.0); // Vincolato dal limite di prelievo
+        assert!(plan.net_profit > 0.0 && plan.payout_per_pair < 1.0);
+        assert_eq!(plan.legs[1].token_type, TokenType::No);
+
+        // Commissioni che annullano il margine
+        let expensive = profile("other", "USD", 2_000.0, 300.0);
+        let plan = plan_cross_venue(&opportunity, &polymarket, &expensive, &costs);
+        assert!(!plan.feasible);
+        assert!(plan.reasons[0].starts_with("net edge"));
+    }
+
+    #[test]
+    fn test_cross_venue_pairs_clear_both_venues_fees() {
+        let now = DateTime::from_timestamp(1_790_000_000, 0).unwrap();
+        let market = MarketData {
+            id: "fed-cut".to_string(),
+            yes_price: 0.55,
+            no_price: 0.46,
+            yes_liquidity: 5_000.0,
+            no_liquidity: 5_000.0,
+            ..MarketData::default()
+        };
+        let contract = |inverted: bool| EquivalentContract {
+            market_id: "fed-cut".to_string(),
+            venue: "kalshi".to_string(),
+            venue_market_id: "FED-25DEC-T4.00".to_string(),
+            inverted,
+        };
+        let quotes = VenueQuoteBook::new();
+        quotes.update(VenueQuote {
+            no_price: Some(0.62),
+            liquidity: Some(800.0),
+            ..quote("kalshi", "FED-25DEC-T4.00", 0.40, now)
+        });
+        // Quotazione più vecchia arrivata in ritardo: ignorata
+        quotes.update(quote("kalshi", "FED-25DEC-T4.00", 0.70, now - Duration::seconds(5)));
+
+        let mut config = CrossVenueConfig::new(vec![contract(false)]);
+        config.fees.insert("kalshi".to_string(), VenueFees { fee_bps: 0.0, variance_rate: 0.07 });
+        let mut detector = CrossVenueDetector::new(config, quotes.clone());
+        let scan = |detector: &CrossVenueDetector, at: DateTime<Utc>| {
+            let (watchlist, books, history, rewards) = (crate::market::Watchlist::default(), crate::orderbook::OrderBookStore::default(), FxHashMap::default(), crate::rewards::RewardBook::new());
+            let markets = std::slice::from_ref(&market);
+            detector.scan(&MarketView {
+                markets, events: &[], watchlist: &watchlist, order_books: &books, price_history: &history,
+                relations: &[], triangles: &[], rewards: &rewards, now: at,
+            })
+        };
+
+        // NO su Polymarket a 0.46 + YES su Kalshi a 0.40: 0.14 lordi, 0.0168 di fee Kalshi
+        let found = scan(&detector, now);
+        assert_eq!(found.len(), 1);
+        let opportunity = &found[0];
+        assert_eq!(opportunity.arb_type, ArbType::CrossVenue);
+        assert!((opportunity.profit - (0.14 - 0.07 * 0.40 * 0.60)).abs() < 1e-9);
+        assert_eq!(opportunity.liquidity, 800.0);
+        let legs = opportunity.legs.as_ref().unwrap();
+        assert_eq!((legs[0].venue.as_deref(), legs[0].token_type), (None, TokenType::No));
+        assert_eq!((legs[1].venue.as_deref(), legs[1].market_id.as_str(), legs[1].token_type), (Some("kalshi"), "FED-25DEC-T4.00", TokenType::Yes));
+
+        // Contratto invertito: il YES Kalshi paga sul NO Polymarket, la coppia è YES su entrambe le venue
+        detector.config.contracts = vec![contract(true)];
+        let found = scan(&detector, now);
+        assert!((found[0].profit - (1.0 - 0.95 - 0.07 * 0.40 * 0.60)).abs() < 1e-9);
+        let legs = found[0].legs.as_ref().unwrap();
+        assert_eq!((legs[0].token_type, legs[1].token_type), (TokenType::Yes, TokenType::Yes));
+
+        // Quotazione scaduta
+        detector.config.contracts = vec![contract(false)];
+        assert!(scan(&detector, now + Duration::seconds(VENUE_QUOTE_MAX_AGE_SECS + 1)).is_empty());
+
+        // Mapping dal registro simboli: ticker sull'esito NO = contratto invertito
+        let mut symbols = SymbolRegistry::default();
+        symbols.register(crate::symbols::SymbolMapping {
+            market_id: "fed-cut".to_string(),
+            outcome: Some(TokenType::No),
+            venue: "kalshi".to_string(),
+            kind: SymbolKind::Ticker,
+            symbol: "FED-25DEC-T4.00".to_string(),
+        }).unwrap();
+        assert_eq!(EquivalentContract::from_symbols(&symbols, "kalshi"), vec![contract(true)]);
+    }
+}
@@ -0,0 +1,267 @@
+//! Persisted state versioning module
+//!
+//! Implements:
+//! 1. Schema identifier and version on every persisted artifact: an envelope around whole-file
+//!    JSON documents, header lines in JSON-lines files
+//! 2. Migrations applied on load, one version at a time, so state saved by an older release keeps
+//!    loading; files written before versioning are version 1
+//! 3. Refusal, with an explicit error, of state written under another schema or by a newer release,
+//!    instead of silently discarding or overwriting it
+//! 4. Backup of a document before its first save in a newer version
+//!
+//! SQL backends keep their own versions: `schema_versions` in SQLite, sqlx migrations in Postgres.
+
+use fxhash::FxHashSet;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Upgrade of persisted data from one version to the next
+pub type Migration = fn(Value) -> Result<Value, String>;
+
+/// Identifier and migrations of one kind of persisted state
+#[derive(Debug, Clone, Copy)]
+pub struct Schema {
+    pub id: &'static str,
+    pub migrations: &'static [Migration], // migrations[i]: dalla versione i + 1 alla i + 2
+}
+
+impl Schema {
+    pub const fn new(id: &'static str, migrations: &'static [Migration]) -> Self {
+        Self { id, migrations }
+    }
+
+    /// Version written by this build: 1 for the first persisted layout, plus one per migration
+    pub const fn version(&self) -> u32 {
+        self.migrations.len() as u32 + 1
+    }
+
+    pub fn header(&self) -> SchemaHeader {
+        SchemaHeader { schema: self.id.to_string(), schema_version: self.version() }
+    }
+
+    /// Whether this build can read data saved at `version`
+    pub fn check_version(&self, version: u32) -> Result<(), String> {
+        if version == 0 || version > self.version() {
+            return Err(format!(
+                "{} schema version {} is not supported by this build (1 to {}): upgrade the crate instead of overwriting it",
+                self.id, version, self.version()
+            ));
+        }
+        Ok(())
+    }
+
+    /// Bring `data` saved at `version` up to the current version
+    pub fn migrate(&self, version: u32, mut data: Value) -> Result<Value, String> {
+        self.check_version(version)?;
+        for (from, migration) in self.migrations.iter().enumerate().skip(version as usize - 1) {
+            data = migration(data).map_err(|e| format!("{} migration from version {} failed: {}", self.id, from + 1, e))?;
+        }
+        Ok(data)
+    }
+
+    /// Version of a header, refusing headers of another schema
+    fn version_of(&self, header: &SchemaHeader) -> Result<u32, String> {
+        if header.schema != self.id {
+            return Err(format!("Expected {} data, found {}", self.id, header.schema));
+        }
+        self.check_version(header.schema_version)?;
+        Ok(header.schema_version)
+    }
+}
+
+/// Schema identifier and version, at the top of a document or on its own line in JSON-lines files
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchemaHeader {
+    pub schema: String,
+    pub schema_version: u32,
+}
+
+impl SchemaHeader {
+    /// Header fields of a JSON object, if it has them
+    fn parse(value: &Value) -> Option<Self> {
+        let object = value.as_object()?;
+        Some(Self {
+            schema: object.get("schema")?.as_str()?.to_string(),
+            schema_version: u32::try_from(object.get("schema_version")?.as_u64()?).ok()?,
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct Envelope<'a, T> {
+    #[serde(flatten)]
+    header: SchemaHeader,
+    data: &'a T,
+}
+
+/// Serialize `data` as a versioned document
+pub fn to_document<T: Serialize>(schema: &Schema, data: &T) -> Result<String, String> {
+    serde_json::to_string_pretty(&Envelope { header: schema.header(), data }).map_err(|e| e.to_string())
+}
+
+/// Version and migrated data of a document; documents without an envelope predate versioning
+fn decode_document(schema: &Schema, content: &str) -> Result<(u32, Value), String> {
+    let mut value: Value = serde_json::from_str(content).map_err(|e| e.to_string())?;
+    let (version, data) = match SchemaHeader::parse(&value) {
+        Some(header) => {
+            let data = value.as_object_mut().and_then(|o| o.remove("data"))
+                .ok_or_else(|| format!("{} document without data", schema.id))?;
+            (schema.version_of(&header)?, data)
+        }
+        None => (1, value),
+    };
+    Ok((version, schema.migrate(version, data)?))
+}
+
+/// Parse a document saved by this or an older version
+pub fn from_document<T: DeserializeOwned>(schema: &Schema, content: &str) -> Result<T, String> {
+    let (_, data) = decode_document(schema, content)?;
+    serde_json::from_value(data).map_err(|e| e.to_string())
+}
+
+/// Backup kept of a document saved at an older `version`
+pub fn backup_path(path: &Path, version: u32) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".v{}.bak", version));
+    path.with_file_name(name)
+}
+
+/// Read a document from `path` (None if the file does not exist yet)
+///
+/// A document of an older version is copied to `backup_path` first, so the next save, which
+/// writes the current version, never loses the only copy an older release can read.
+pub fn load_document<T: DeserializeOwned>(schema: &Schema, path: &Path) -> Result<Option<T>, String> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let (version, data) = decode_document(schema, &content)?;
+    if version < schema.version() {
+        let backup = backup_path(path, version);
+        if !backup.exists() {
+            std::fs::write(&backup, &content).map_err(|e| format!("Failed to back up {}: {}", path.display(), e))?;
+        }
+    }
+    serde_json::from_value(data).map(Some).map_err(|e| e.to_string())
+}
+
+/// Records of a JSON-lines file (empty if it does not exist yet), migrated to the current version
+///
+/// A header line sets the version of the records after it; records before any header predate
+/// versioning. Records that fail to parse or migrate come back as errors, for the caller to skip
+/// (a line truncated by a crash) or report; a header of another schema or a newer version fails
+/// the whole read.
+pub fn read_lines<T: DeserializeOwned>(schema: &Schema, path: &Path) -> Result<Vec<Result<T, String>>, String> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let file = std::fs::File::open(path).map_err(|e| e.to_string())?;
+    let mut version = 1;
+    let mut records = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(|e| e.to_string())?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let value = match serde_json::from_str::<Value>(&line) {
+            Ok(value) => value,
+            Err(e) => {
+                records.push(Err(e.to_string()));
+                continue;
+            }
+        };
+        match SchemaHeader::parse(&value) {
+            Some(header) => version = schema.version_of(&header)?,
+            None => records.push(
+                schema.migrate(version, value).and_then(|v| serde_json::from_value(v).map_err(|e| e.to_string()))
+            ),
+        }
+    }
+    Ok(records)
+}
+
+/// Appends to JSON-lines files, writing the schema header before the first records of each
+/// process, so a file extended across upgrades stays readable line by line
+#[derive(Debug, Clone, Default)]
+pub struct LineWriter {
+    headed: Arc<Mutex<FxHashSet<PathBuf>>>, // File già intestati da questo processo
+}
+
+impl LineWriter {
+    /// Append `lines`, creating the file if needed; returns the file for callers that sync it
+    pub fn append(&self, schema: &Schema, path: &Path, lines: &[String]) -> Result<std::fs::File, String> {
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| e.to_string())?;
+        let mut headed = self.headed.lock().unwrap();
+        let empty = file.metadata().map_err(|e| e.to_string())?.len() == 0;
+        if empty || !headed.contains(path) {
+            let header = serde_json::to_string(&schema.header()).map_err(|e| e.to_string())?;
+            writeln!(file, "{}", header).map_err(|e| e.to_string())?;
+            headed.insert(path.to_path_buf());
+        }
+        for line in lines {
+            writeln!(file, "{}", line).map_err(|e| e.to_string())?;
+        }
+        Ok(file)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Versione 2: `price` in centesimi diventa `price` in dollari
+    fn cents_to_dollars(mut data: Value) -> Result<Value, String> {
+        let cents = data["price_cents"].as_f64().ok_or("missing price_cents")?;
+        let object = data.as_object_mut().ok_or("not an object")?;
+        object.remove("price_cents");
+        object.insert("price".to_string(), serde_json::json!(cents / 100.0));
+        Ok(data)
+    }
+
+    const QUOTES: Schema = Schema::new("quotes", &[cents_to_dollars]);
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Quote {
+        price: f64,
+    }
+
+    #[test]
+    fn test_old_state_migrates_and_newer_state_is_refused() {
+        let dir = std::env::temp_dir().join(format!("versioning_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        // Documento senza envelope, salvato prima del versioning: migrato e conservato in backup
+        let path = dir.join("quote.json");
+        std::fs::write(&path, r#"{"price_cents": 45}"#).unwrap();
+        assert_eq!(load_document::<Quote>(&QUOTES, &path).unwrap(), Some(Quote { price: 0.45 }));
+        assert!(backup_path(&path, 1).exists());
+
+        let saved = to_document(&QUOTES, &Quote { price: 0.5 }).unwrap();
+        assert_eq!(from_document::<Quote>(&QUOTES, &saved).unwrap(), Quote { price: 0.5 });
+        let newer = saved.replace("\"schema_version\": 2", "\"schema_version\": 3");
+        assert!(from_document::<Quote>(&QUOTES, &newer).unwrap_err().contains("upgrade the crate"));
+        let other = saved.replace("\"quotes\"", "\"watchlist\"");
+        assert!(from_document::<Quote>(&QUOTES, &other).is_err());
+
+        // JSON lines estese da due versioni: ogni record letto nella propria versione
+        let lines = dir.join("quotes.jsonl");
+        std::fs::write(&lines, "{\"price_cents\": 40}\n{\"price_cents\": 4\n").unwrap();
+        let writer = LineWriter::default();
+        writer.append(&QUOTES, &lines, &[r#"{"price": 0.6}"#.to_string()]).unwrap();
+        writer.append(&QUOTES, &lines, &[r#"{"price": 0.7}"#.to_string()]).unwrap();
+        let records = read_lines::<Quote>(&QUOTES, &lines).unwrap();
+        let prices: Vec<Option<f64>> = records.iter().map(|r| r.as_ref().ok().map(|q| q.price)).collect();
+        assert_eq!(prices, vec![Some(0.4), None, Some(0.6), Some(0.7)]);
+        assert_eq!(std::fs::read_to_string(&lines).unwrap().matches("schema_version").count(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,167 @@
+//! Polygon wallet module (feature "onchain")
+//!
+//! Implements:
+//! 1. Private key loading and (EIP-55 checksummed) address derivation
+//! 2. EIP-712 `ClobAuth` signatures for CLOB L1 authentication (create/derive API key)
+
+use k256::ecdsa::SigningKey;
+use sha3::{Digest, Keccak256};
+use std::fmt;
+
+/// Environment variable holding the wallet private key (hex, with or without 0x)
+pub const PRIVATE_KEY_ENV: &str = "POLYMARKET_PRIVATE_KEY";
+
+/// Message signed by the wallet to prove control over the address
+pub const CLOB_AUTH_MESSAGE: &str = "This message attests that I control the given wallet";
+
+fn keccak256(data: &[u8]) -> [u8; 32] {
+    Keccak256::digest(data).into()
+}
+
+/// Unsigned integer as a 32-byte ABI word
+fn abi_u256(value: u64) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[24..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+/// EOA key pair used for L1 authentication
+#[derive(Clone)]
+pub struct Wallet {
+    key: SigningKey,
+    address: String,
+}
+
+// La chiave privata non deve mai finire nei log
+impl fmt::Debug for Wallet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Wallet").field("address", &self.address).finish_non_exhaustive()
+    }
+}
+
+impl Wallet {
+    /// Load a wallet from a hex private key
+    pub fn from_private_key(private_key: &str) -> Result<Self, String> {
+        let trimmed = private_key.trim();
+        let bytes = hex::decode(trimmed.strip_prefix("0x").unwrap_or(trimmed))
+            .map_err(|_| "Private key is not valid hex".to_string())?;
+        if bytes.len() != 32 {
+            return Err(format!("Private key must be 32 bytes, got {}", bytes.len()));
+        }
+        let key = SigningKey::from_slice(&bytes).map_err(|_| "Private key is not a valid secp256k1 scalar".to_string())?;
+
+        // Indirizzo = ultimi 20 byte del keccak della chiave pubblica non compressa (senza prefisso 0x04)
+        let public = key.verifying_key().to_encoded_point(false);
+        let hash = keccak256(&public.as_bytes()[1..]);
+        let address = checksum_address(&hash[12..]);
+
+        Ok(Self { key, address })
+    }
+
+    /// Wallet from `POLYMARKET_PRIVATE_KEY`, if set
+    pub fn from_env() -> Option<Result<Self, String>> {
+        std::env::var(PRIVATE_KEY_ENV)
+            .ok()
+            .filter(|v| !v.trim().is_empty())
+            .map(|key| Self::from_private_key(&key))
+    }
+
+    /// EIP-55 checksummed address
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Sign the `ClobAuth` typed data; returns the 65-byte signature as 0x-prefixed hex (r || s || v)
+    pub fn sign_clob_auth(&self, chain_id: u64, timestamp: i64, nonce: u64) -> Result<String, String> {
+        let digest = clob_auth_digest(&self.address, chain_id, timestamp, nonce)?;
+        let (signature, recovery_id) = self.key
+            .sign_prehash_recoverable(&digest)
+            .map_err(|e| format!("Signing failed: {}", e))?;
+
+        let mut bytes = signature.to_bytes().to_vec();
+        bytes.push(27 + recovery_id.to_byte());
+        Ok(format!("0x{}", hex::encode(bytes)))
+    }
+}
+
+/// EIP-55 mixed-case encoding of a 20-byte address
+fn checksum_address(address: &[u8]) -> String {
+    let lower = hex::encode(address);
+    let hash = keccak256(lower.as_bytes());
+    let checksummed: String = lower
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let nibble = (hash[i / 2] >> if i % 2 == 0 { 4 } else { 0 }) & 0x0f;
+            if nibble >= 8 { c.to_ascii_uppercase() } else { c }
+        })
+        .collect();
+    format!("0x{}", checksummed)
+}
+
+/// EIP-712 digest of `ClobAuth(address address,string timestamp,uint256 nonce,string message)`
+pub fn clob_auth_digest(address: &str, chain_id: u64, timestamp: i64, nonce: u64) -> Result<[u8; 32], String> {
+    let address_bytes = hex::decode(address.trim_start_matches("0x"))
+        .ok()
+        .filter(|b| b.len() == 20)
+        .ok_or_else(|| format!("Invalid address {}", address))?;
+
+    // Dominio senza verifyingContract: solo nome, versione e chain
+    let mut domain = Vec::with_capacity(4 * 32);
+    domain.extend(keccak256(b"EIP712Domain(string name,string version,uint256 chainId)"));
+    domain.extend(keccak256(b"ClobAuthDomain"));
+    domain.extend(keccak256(b"1"));
+    domain.extend(abi_u256(chain_id));
+    let domain_separator = keccak256(&domain);
+
+    let mut message = Vec::with_capacity(5 * 32);
+    message.extend(keccak256(b"ClobAuth(address address,string timestamp,uint256 nonce,string message)"));
+    message.extend([0u8; 12]);
+    message.extend(&address_bytes);
+    message.extend(keccak256(timestamp.to_string().as_bytes()));
+    message.extend(abi_u256(nonce));
+    message.extend(keccak256(CLOB_AUTH_MESSAGE.as_bytes()));
+    let struct_hash = keccak256(&message);
+
+    let mut payload = Vec::with_capacity(66);
+    payload.extend([0x19, 0x01]);
+    payload.extend(domain_separator);
+    payload.extend(struct_hash);
+    Ok(keccak256(&payload))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+
+    // Primo account di sviluppo di Hardhat/Anvil
+    const TEST_KEY: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+    #[test]
+    fn test_wallet_address_and_clob_auth_signature() {
+        let wallet = Wallet::from_private_key(TEST_KEY).unwrap();
+        assert_eq!(wallet.address(), "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266");
+        assert!(!format!("{:?}", wallet).contains("ac0974"));
+
+        let signature = wallet.sign_clob_auth(80002, 10_000_000, 23).unwrap();
+        assert_eq!(
+            signature,
+            "0xf62319a987514da40e57e2f4d7529f7bac38f0355bd88bb5adbb3768d80de6c1682518e0af677d5260366425f4361e7b70c25ae232aff0ab2331e2b164a1aedc1b"
+        );
+
+        // La firma recupera l'indirizzo del wallet
+        let bytes = hex::decode(&signature[2..]).unwrap();
+        let digest = clob_auth_digest(wallet.address(), 80002, 10_000_000, 23).unwrap();
+        let recovered = VerifyingKey::recover_from_prehash(
+            &digest,
+            &Signature::from_slice(&bytes[..64]).unwrap(),
+            RecoveryId::from_byte(bytes[64] - 27).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(&recovered, wallet.key.verifying_key());
+
+        assert!(Wallet::from_private_key("0x1234").is_err());
+        assert!(Wallet::from_private_key("not hex").is_err());
+    }
+}
@@ -0,0 +1,142 @@
+//! WebAssembly bindings module
+//!
+//! Implements:
+//! 1. JSON-in/JSON-out exports of the analytics core for the dashboard frontend
+//! 2. What-if replay of a recorded window with the same backtester as `/api/whatif`
+//! 3. YES/NO, negRisk and duplicate-market detection net of trading costs
+//! 4. Optimizer ranking, Kelly position sizing and risk metrics of a PnL series
+
+use crate::arbitrage::{ArbitrageDetector, TradingCosts};
+use crate::backtest::{what_if, BacktestConfig, WhatIfRequest};
+use crate::market::PriceSnapshot;
+use crate::optimization::StatisticalArbOptimizer;
+use crate::orderbook::OrderBookStore;
+use crate::risk::{PositionSizer, RiskManager};
+use crate::types::{ArbitrageOpportunity, EventData, MarketData};
+use fxhash::FxHashMap;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+/// Episodes listed in the drawdown section of a risk report
+const REPORTED_DRAWDOWNS: usize = 5;
+
+fn parse<T: DeserializeOwned>(json: &str, what: &str) -> Result<T, String> {
+    serde_json::from_str(json).map_err(|e| format!("Invalid {}: {}", what, e))
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String, String> {
+    serde_json::to_string(value).map_err(|e| format!("Failed to serialize result: {}", e))
+}
+
+/// Replay a recorded window (`{market_id: [PriceSnapshot]}`) under the baseline and a what-if request
+#[wasm_bindgen(js_name = whatIf)]
+pub fn what_if_report(window_json: &str, request_json: &str, initial_capital: f64) -> Result<String, String> {
+    let window: FxHashMap<String, Vec<PriceSnapshot>> = parse(window_json, "window")?;
+    let request: WhatIfRequest = parse(request_json, "what-if request")?;
+    if request.trade_fraction.is_some_and(|f| !(0.0..=1.0).contains(&f)) {
+        return Err("trade_fraction must be between 0 and 1".to_string());
+    }
+
+    let baseline = BacktestConfig { initial_capital, ..BacktestConfig::default() };
+    let scenario = request.apply(&baseline);
+    to_json(&what_if(&window, &[], baseline, scenario))
+}
+
+/// Opportunities in markets, duplicate pairs and negRisk events, net of trading costs (top-of-book sizing)
+#[wasm_bindgen(js_name = detectOpportunities)]
+pub fn detect_opportunities(markets_json: &str, events_json: &str, costs_json: &str, min_profit: f64, min_liquidity: f64) -> Result<String, String> {
+    let markets: Vec<MarketData> = parse(markets_json, "markets")?;
+    let events: Vec<EventData> = parse(events_json, "events")?;
+    let costs: TradingCosts = parse(costs_json, "trading costs")?;
+
+    let detector = ArbitrageDetector::new(min_profit, min_liquidity).with_costs(costs);
+
+    let mut opportunities = detector.scan_markets(&markets, &OrderBookStore::new());
+    opportunities.extend(detector.scan_events(&events));
+    opportunities.extend(detector.scan_duplicate_markets(&markets));
+    to_json(&opportunities)
+}
+
+/// Opportunities kept by the statistical optimizer, best first
+#[wasm_bindgen(js_name = rankOpportunities)]
+pub fn rank_opportunities(opportunities_json: &str) -> Result<String, String> {
+    let opportunities: Vec<ArbitrageOpportunity> = parse(opportunities_json, "opportunities")?;
+    to_json(&StatisticalArbOptimizer::new().select_pairs(&opportunities))
+}
+
+/// Position size from the modified Kelly criterion
+#[wasm_bindgen(js_name = kellyPosition)]
+#[allow(clippy::too_many_arguments)]
+pub fn kelly_position(
+    capital: f64,
+    win_rate: f64,
+    avg_win: f64,
+    avg_loss: f64,
+    confidence: f64,
+    kelly_fraction: f64,
+    max_position_pct: f64,
+    min_position: f64,
+) -> f64 {
+    PositionSizer::new(kelly_fraction, max_position_pct, min_position).calculate_position(capital, win_rate, avg_win, avg_loss, confidence)
+}
+
+/// VaR, Sharpe and drawdown episodes of a sequence of trade PnLs
+#[wasm_bindgen(js_name = riskReport)]
+pub fn risk_report(pnl_json: &str, initial_capital: f64) -> Result<String, String> {
+    let pnl: Vec<f64> = parse(pnl_json, "PnL series")?;
+
+    let risk = RiskManager::replay(&pnl, initial_capital);
+
+    to_json(&serde_json::json!({
+        "var_95": risk.calculate_var_95(),
+        "sharpe_ratio": risk.calculate_sharpe_ratio(),
+        "current_drawdown": risk.metrics.current_drawdown,
+        "final_capital": initial_capital + pnl.iter().sum::<f64>(),
+        "drawdowns": risk.drawdowns.report(REPORTED_DRAWDOWNS),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wasm_exports_round_trip_json() {
+        let market = MarketData {
+            id: "m1".to_string(),
+            question: "Test?".to_string(),
+            yes_price: 0.45,
+            no_price: 0.50,
+            yes_liquidity: 5_000.0,
+            no_liquidity: 5_000.0,
+            ..MarketData::default()
+        };
+        let markets = serde_json::to_string(&vec![market]).unwrap();
+        let found: Vec<ArbitrageOpportunity> =
+            serde_json::from_str(&detect_opportunities(&markets, "[]", "{}", 0.01, 0.0).unwrap()).unwrap();
+        assert_eq!(found.len(), 1);
+        assert!(found[0].profit > 0.0);
+        assert!(detect_opportunities("not json", "[]", "{}", 0.01, 0.0).unwrap_err().starts_with("Invalid markets"));
+
+        let t0 = chrono::DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        let window: FxHashMap<String, Vec<PriceSnapshot>> = [(
+            "m1".to_string(),
+            (0..5)
+                .map(|i| PriceSnapshot { timestamp: t0 + chrono::Duration::minutes(i), yes_price: 0.45, no_price: 0.50, volume: 0.0 })
+                .collect(),
+        )]
+        .into_iter()
+        .collect();
+        let report: serde_json::Value =
+            serde_json::from_str(&what_if_report(&serde_json::to_string(&window).unwrap(), r#"{"min_profit":0.2}"#, 1_000.0).unwrap()).unwrap();
+        assert!(report["baseline"]["trades"].as_u64().unwrap() > 0);
+        assert_eq!(report["scenario"]["trades"], 0);
+        assert!(what_if_report("{}", r#"{"trade_fraction":2.0}"#, 1_000.0).is_err());
+
+        let risk: serde_json::Value = serde_json::from_str(&risk_report("[10.0, -30.0, 5.0]", 1_000.0).unwrap()).unwrap();
+        assert_eq!(risk["final_capital"], 985.0);
+        assert!(risk["current_drawdown"].as_f64().unwrap() > 0.0);
+        assert!(kelly_position(1_000.0, 0.6, 1.0, 1.0, 1.0, 0.25, 0.05, 10.0) >= 10.0);
+    }
+}
@@ -0,0 +1,400 @@
+//! Opportunity webhook module
+//!
+//! Implements:
+//! 1. Signals of approved opportunities with their sized legs, as the executor would send them
+//! 2. HMAC-SHA256 signed POSTs to an external execution stack (`X-Signal-Signature: sha256=<hex>`)
+//! 3. Signal-only mode: approved opportunities are published instead of executed internally
+//! 4. Bounded channel filled by the trading step and drained by a spawned delivery task, so a slow
+//!    receiver never blocks detection: when the channel is full, new signals are dropped and counted
+//! 5. Bounded retries: a signal still undelivered after `max_attempts` tries or `max_age_secs` in
+//!    the queue is given up and counted as expired, so a failing receiver never gets stale prices
+
+use crate::execution::ExecutionPlan;
+use crate::signals::{sign_payload, SIGNATURE_HEADER};
+use crate::types::ArbitrageOpportunity;
+use chrono::{DateTime, Utc};
+use reqwest::Client as HttpClient;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+/// Environment variable holding the signing secret when the config has none
+pub const OPPORTUNITY_WEBHOOK_SECRET_ENV: &str = "OPPORTUNITY_WEBHOOK_SECRET";
+
+/// Longest wait between two delivery attempts of the same signal
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(30);
+
+fn default_max_queue() -> usize {
+    1_000
+}
+
+fn default_timeout_ms() -> u64 {
+    2000
+}
+
+fn default_max_attempts() -> u32 {
+    5
+}
+
+fn default_max_age_secs() -> u64 {
+    60 // TTL di dedup predefinito: oltre, la stessa opportunità viene riportata di nuovo
+}
+
+/// Outbound webhook settings
+#[derive(Clone, Serialize, Deserialize)]
+pub struct OpportunityWebhookConfig {
+    pub url: String,
+    #[serde(default)]
+    pub secret: Option<String>, // Firma HMAC del body; altrimenti da OPPORTUNITY_WEBHOOK_SECRET, se impostata
+    #[serde(default)]
+    pub execute_internally: bool, // false: il bot pubblica soltanto, l'esecuzione spetta al sistema esterno
+    #[serde(default = "default_max_queue")]
+    pub max_queue: usize, // Segnali in attesa di consegna; oltre, i nuovi sono scartati
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32, // Tentativi di consegna per segnale prima di abbandonarlo
+    #[serde(default = "default_max_age_secs")]
+    pub max_age_secs: u64, // Età massima in coda: un segnale più vecchio ha prezzi superati
+}
+
+impl std::fmt::Debug for OpportunityWebhookConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OpportunityWebhookConfig")
+            .field("url", &self.url)
+            .field("secret", &self.secret.as_ref().map(|_| "[REDACTED]"))
+            .field("execute_internally", &self.execute_internally)
+            .field("max_queue", &self.max_queue)
+            .field("timeout_ms", &self.timeout_ms)
+            .field("max_attempts", &self.max_attempts)
+            .field("max_age_secs", &self.max_age_secs)
+            .finish()
+    }
+}
+
+impl OpportunityWebhookConfig {
+    /// Signal-only webhook to `url`
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            secret: None,
+            execute_internally: false,
+            max_queue: default_max_queue(),
+            timeout_ms: default_timeout_ms(),
+            max_attempts: default_max_attempts(),
+            max_age_secs: default_max_age_secs(),
+        }
+    }
+}
+
+/// Approved opportunity with the sizing the bot would have traded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpportunitySignal {
+    pub signal_id: String,
+    pub opportunity: ArbitrageOpportunity,
+    pub plan: ExecutionPlan,
+    pub capital: f64,         // Capitale disponibile al momento del sizing
+    pub size_multiplier: f64, // Riduzione per flag di rischio di risoluzione, in (0, 1]
+    pub executed_internally: bool,
+    pub approved_at: DateTime<Utc>,
+}
+
+/// Signal waiting for delivery, with the time it was queued
+struct QueuedSignal {
+    signal: OpportunitySignal,
+    queued_at: Instant,
+}
+
+/// Endpoint, signing key, retry limits and counters shared with the delivery task
+#[derive(Clone)]
+struct Delivery {
+    url: String,
+    secret: Option<String>,
+    http_client: HttpClient,
+    max_attempts: u32,
+    max_age: Duration,
+    sent: Arc<AtomicU64>,
+    rejected: Arc<AtomicU64>,
+    expired: Arc<AtomicU64>,
+}
+
+impl Delivery {
+    fn encode(&self, signal: &OpportunitySignal) -> Result<(Vec<u8>, Option<String>), String> {
+        let body = serde_json::to_vec(signal).map_err(|e| e.to_string())?;
+        let signature = self.secret.as_deref().map(|secret| sign_payload(secret, &body));
+        Ok((body, signature))
+    }
+
+    /// POST one signal; Ok(false) when the receiver refused it for good (4xx other than 429)
+    async fn post(&self, signal: &OpportunitySignal) -> Result<bool, String> {
+        let (body, signature) = self.encode(signal)?;
+        let mut request = self.http_client
+            .post(&self.url)
+            .header("Content-Type", "application/json")
+            .body(body);
+        if let Some(signature) = signature {
+            request = request.header(SIGNATURE_HEADER, signature);
+        }
+        let response = request.send().await.map_err(|e| format!("Opportunity webhook unreachable: {}", e))?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(true);
+        }
+        let text = response.text().await.unwrap_or_default();
+        let error = format!("Opportunity webhook rejected {}: {} {}", signal.signal_id, status, text);
+        if status.is_client_error() && status != reqwest::StatusCode::TOO_MANY_REQUESTS {
+            eprintln!("{}", error);
+            return Ok(false);
+        }
+        Err(error)
+    }
+
+    /// Deliver the signals in order, retrying each with backoff until accepted, refused or expired
+    async fn run(self, mut rx: mpsc::Receiver<QueuedSignal>) {
+        while let Some(QueuedSignal { signal, queued_at }) = rx.recv().await {
+            let mut backoff = Duration::from_millis(250);
+            let mut attempts = 0;
+            loop {
+                if queued_at.elapsed() > self.max_age {
+                    eprintln!("Opportunity webhook gave up {}: older than {:?}", signal.signal_id, self.max_age);
+                    self.expired.fetch_add(1, Ordering::Relaxed);
+                    break;
+                }
+                attempts += 1;
+                match self.post(&signal).await {
+                    Ok(true) => {
+                        self.sent.fetch_add(1, Ordering::Relaxed);
+                        break;
+                    }
+                    Ok(false) => {
+                        self.rejected.fetch_add(1, Ordering::Relaxed);
+                        break;
+                    }
+                    Err(e) if attempts >= self.max_attempts.max(1) => {
+                        eprintln!("{} (gave up after {} attempts)", e, attempts);
+                        self.expired.fetch_add(1, Ordering::Relaxed);
+                        break;
+                    }
+                    Err(e) => {
+                        eprintln!("{} (retry in {:?})", e, backoff);
+                        tokio::time::sleep(backoff).await;
+                        backoff = (backoff * 2).min(MAX_RETRY_BACKOFF);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Outbound webhook: signals are queued by the trading step and POSTed by a background task
+pub struct OpportunityWebhook {
+    pub config: OpportunityWebhookConfig,
+    delivery: Delivery,
+    tx: mpsc::Sender<QueuedSignal>,
+    rx: Option<mpsc::Receiver<QueuedSignal>>, // Fino all'avvio del task di consegna
+    pub dropped: u64, // Scartati per coda piena
+}
+
+impl OpportunityWebhook {
+    pub fn new(config: OpportunityWebhookConfig) -> Result<Self, String> {
+        if !config.url.starts_with("http://") && !config.url.starts_with("https://") {
+            return Err(format!("Unsupported opportunity webhook URL (expected http(s)://): {}", config.url));
+        }
+        let secret = config.secret.clone()
+            .or_else(|| std::env::var(OPPORTUNITY_WEBHOOK_SECRET_ENV).ok())
+            .filter(|s| !s.is_empty());
+        let delivery = Delivery {
+            url: config.url.clone(),
+            secret,
+            http_client: HttpClient::builder()
+                .timeout(Duration::from_millis(config.timeout_ms))
+                .build()
+                .map_err(|e| e.to_string())?,
+            max_attempts: config.max_attempts,
+            max_age: Duration::from_secs(config.max_age_secs),
+            sent: Arc::new(AtomicU64::new(0)),
+            rejected: Arc::new(AtomicU64::new(0)),
+            expired: Arc::new(AtomicU64::new(0)),
+        };
+        let (tx, rx) = mpsc::channel(config.max_queue.max(1));
+        Ok(Self { config, delivery, tx, rx: Some(rx), dropped: 0 })
+    }
+
+    /// Whether the bot still executes the opportunities it publishes
+    pub fn executes_internally(&self) -> bool {
+        self.config.execute_internally
+    }
+
+    /// Queue a signal for delivery without waiting; dropped (and counted) when the queue is full
+    ///
+    /// The delivery task starts with the first signal published inside a Tokio runtime.
+    pub fn publish(&mut self, signal: OpportunitySignal) {
+        if self.rx.is_some() {
+            if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+                let rx = self.rx.take().expect("receiver checked above");
+                runtime.spawn(self.delivery.clone().run(rx));
+            }
+        }
+        if self.tx.try_send(QueuedSignal { signal, queued_at: Instant::now() }).is_err() {
+            self.dropped += 1;
+        }
+    }
+
+    /// Signals queued and not yet taken by the delivery task
+    pub fn pending(&self) -> usize {
+        self.tx.max_capacity() - self.tx.capacity()
+    }
+
+    /// Signals accepted by the receiver
+    pub fn sent(&self) -> u64 {
+        self.delivery.sent.load(Ordering::Relaxed)
+    }
+
+    /// Signals refused by the receiver and not retried
+    pub fn rejected(&self) -> u64 {
+        self.delivery.rejected.load(Ordering::Relaxed)
+    }
+
+    /// Signals given up after `max_attempts` failed tries or `max_age_secs` in the queue
+    pub fn expired(&self) -> u64 {
+        self.delivery.expired.load(Ordering::Relaxed)
+    }
+
+    /// Body and signature header of a signal
+    pub fn encode(&self, signal: &OpportunitySignal) -> Result<(Vec<u8>, Option<String>), String> {
+        self.delivery.encode(signal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signals::verify_signature;
+    use crate::types::{ArbType, ArbitrageLeg, Direction, TokenType};
+
+    fn signal(id: &str) -> OpportunitySignal {
+        let now = DateTime::from_timestamp(1_790_000_000, 0).unwrap();
+        let leg = |token_type: TokenType, price: f64| ArbitrageLeg {
+            market_id: "m1".to_string(),
+            token_type,
+            direction: Direction::Buy,
+            price,
+            quantity: 100.0,
+            token_id: None,
+            venue: None,
+        };
+        OpportunitySignal {
+            signal_id: id.to_string(),
+            opportunity: ArbitrageOpportunity {
+                market_id: "m1".to_string(),
+                question: String::new(),
+                arb_type: ArbType::YesNoSimple,
+                profit: 0.03,
+                roi_pct: 3.09,
+                confidence: 0.9,
+                yes_price: 0.48,
+                no_price: 0.49,
+                sum_price: 0.97,
+                liquidity: 5000.0,
+                timestamp: now,
+                legs: None,
+                path: None,
+                maker: None,
+            },
+            plan: ExecutionPlan {
+                position: 97.0,
+                legs: vec![leg(TokenType::Yes, 0.48), leg(TokenType::No, 0.49)],
+                total_investment: 97.0,
+                expected_return: 100.0,
+            },
+            capital: 1000.0,
+            size_multiplier: 1.0,
+            executed_internally: false,
+            approved_at: now,
+        }
+    }
+
+    #[test]
+    fn test_signals_are_signed_and_queue_is_bounded() {
+        assert!(OpportunityWebhook::new(OpportunityWebhookConfig::new("nats://localhost:4222")).is_err());
+        let config = OpportunityWebhookConfig {
+            secret: Some("topsecret".to_string()),
+            max_queue: 2,
+            ..OpportunityWebhookConfig::new("http://localhost:9000/signals")
+        };
+        let mut webhook = OpportunityWebhook::new(config).unwrap();
+        assert!(!webhook.executes_internally());
+        assert!(!format!("{:?}", webhook.config).contains("topsecret"));
+        // Fuori da un runtime nessun task di consegna: la coda si riempie e scarta i nuovi
+        for id in ["s1", "s2", "s3"] {
+            webhook.publish(signal(id));
+        }
+        assert_eq!(webhook.pending(), 2);
+        assert_eq!(webhook.dropped, 1);
+
+        // Il ricevente verifica la firma con lo stesso secret del webhook in ingresso
+        let (body, signature) = webhook.encode(&signal("s1")).unwrap();
+        assert!(verify_signature("topsecret", &body, &signature.unwrap()));
+        let decoded: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(decoded["plan"]["legs"][1]["price"], 0.49);
+        assert_eq!(decoded["opportunity"]["arb_type"], "YesNoSimple");
+    }
+
+    #[tokio::test]
+    async fn test_failing_signal_expires_without_blocking_the_next() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Ricevente di prova: 500 per s1, 200 per gli altri, una connessione per richiesta
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/signals", listener.local_addr().unwrap());
+        let (delivered_tx, mut delivered) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0u8; 8192];
+                let body = loop {
+                    let n = socket.read(&mut buf).await.unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                    let text = String::from_utf8_lossy(&request).to_string();
+                    let Some((head, body)) = text.split_once("\r\n\r\n") else { continue };
+                    let length: usize = head.lines()
+                        .find_map(|l| l.to_ascii_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse().unwrap()))
+                        .unwrap();
+                    if body.len() >= length {
+                        break body.to_string();
+                    }
+                };
+                let signal: OpportunitySignal = serde_json::from_str(&body).unwrap();
+                let status = if signal.signal_id == "s1" { "500 Internal Server Error" } else { "200 OK" };
+                let response = format!("HTTP/1.1 {}\r\ncontent-length: 0\r\nconnection: close\r\n\r\n", status);
+                socket.write_all(response.as_bytes()).await.unwrap();
+                delivered_tx.send((signal.signal_id, status)).unwrap();
+            }
+        });
+
+        let mut webhook = OpportunityWebhook::new(OpportunityWebhookConfig { max_attempts: 2, ..OpportunityWebhookConfig::new(&url) }).unwrap();
+        webhook.publish(signal("s1"));
+        webhook.publish(signal("s2"));
+
+        let received: Vec<(String, &str)> = tokio::time::timeout(Duration::from_secs(5), async {
+            let mut received = Vec::new();
+            while let Some(delivery) = delivered.recv().await {
+                let done = delivery.0 == "s2";
+                received.push(delivery);
+                if done {
+                    return received;
+                }
+            }
+            received
+        }).await.unwrap();
+        // Due tentativi per s1, poi abbandonato: s2 consegnato subito dopo
+        let ids: Vec<&str> = received.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, vec!["s1", "s1", "s2"]);
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!((webhook.sent(), webhook.expired(), webhook.rejected()), (1, 1, 0));
+    }
+}